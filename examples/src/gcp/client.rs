@@ -4,17 +4,19 @@ pub mod api {
 
 use api::{publisher_client::PublisherClient, ListTopicsRequest};
 use tonic::{
-    metadata::MetadataValue,
+    oauth2::{ServiceAccount, ServiceAccountKey, TokenCredentials},
     transport::{Certificate, Channel, ClientTlsConfig},
     Request,
 };
 
 const ENDPOINT: &str = "https://pubsub.googleapis.com";
+const OAUTH2_ENDPOINT: &str = "https://oauth2.googleapis.com";
+const SCOPE: &str = "https://www.googleapis.com/auth/pubsub";
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let token = std::env::var("GCP_AUTH_TOKEN").map_err(|_| {
-        "Pass a valid 0Auth bearer token via `GCP_AUTH_TOKEN` environment variable.".to_string()
+    let key_file = std::env::var("GOOGLE_APPLICATION_CREDENTIALS").map_err(|_| {
+        "Pass the path of a service account key file via `GOOGLE_APPLICATION_CREDENTIALS` environment variable.".to_string()
     })?;
 
     let project = std::env::args()
@@ -22,8 +24,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .next()
         .ok_or("Expected a project name as the first argument.".to_string())?;
 
-    let bearer_token = format!("Bearer {}", token);
-    let header_value = MetadataValue::from_str(&bearer_token)?;
+    let key = ServiceAccountKey::from_json(&tokio::fs::read(key_file).await?)?;
 
     let certs = tokio::fs::read("examples/data/gcp/roots.pem").await?;
 
@@ -36,11 +37,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .connect()
         .await?;
 
-    let mut service = PublisherClient::with_interceptor(channel, move |mut req: Request<()>| {
-        req.metadata_mut()
-            .insert("authorization", header_value.clone());
-        Ok(req)
-    });
+    // The access tokens are requested from the OAuth2 endpoint of Google.
+    let tls_config = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(certs.as_slice()))
+        .domain_name("oauth2.googleapis.com");
+
+    let oauth2 = Channel::from_static(OAUTH2_ENDPOINT)
+        .tls_config(tls_config)
+        .connect()
+        .await?;
+
+    let credentials = TokenCredentials::new(ServiceAccount::new(oauth2, key).scopes(vec![SCOPE]));
+    let mut service = PublisherClient::new(channel).call_credentials(credentials);
 
     let response = service
         .list_topics(Request::new(ListTopicsRequest {
@@ -0,0 +1,223 @@
+use compression::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Message,
+};
+use std::{
+    io::{self, Write},
+    net::{SocketAddr, TcpListener},
+};
+use tonic::{
+    codec::{CompressionEncoding, Compressor},
+    transport::Server,
+    Code, Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Message>) -> Result<Response<Message>, Status> {
+        let compression = request.metadata().get("x-compress").cloned();
+
+        let mut response = Response::new(request.into_inner());
+        match compression.as_ref().map(|v| v.to_str().unwrap()) {
+            Some("off") => response.set_compression(None),
+            Some("gzip") => response.set_compression(CompressionEncoding::Gzip),
+            _ => {}
+        }
+
+        Ok(response)
+    }
+}
+
+fn spawn_server(server: TestServer<Svc>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(server)
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+fn message() -> Message {
+    Message {
+        data: vec![42; 64 * 1024],
+    }
+}
+
+async fn assert_round_trip(encoding: CompressionEncoding) {
+    let server = TestServer::new(Svc)
+        .accept_compressed(encoding)
+        .send_compressed(encoding);
+    let addr = spawn_server(server);
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+        .send_compressed(encoding)
+        .accept_compressed(encoding);
+
+    let res = client.echo(message()).await.unwrap();
+    assert_eq!(res.into_inner(), message());
+}
+
+#[tokio::test]
+async fn round_trips_gzip_messages() {
+    assert_round_trip(CompressionEncoding::Gzip).await;
+}
+
+#[tokio::test]
+async fn round_trips_zstd_messages() {
+    assert_round_trip(CompressionEncoding::Zstd).await;
+}
+
+#[tokio::test]
+async fn round_trips_deflate_messages() {
+    assert_round_trip(CompressionEncoding::Deflate).await;
+}
+
+#[tokio::test]
+async fn negotiates_response_encoding() {
+    let server = TestServer::new(Svc)
+        .accept_compressed(CompressionEncoding::Zstd)
+        .send_compressed(CompressionEncoding::Gzip)
+        .send_compressed(CompressionEncoding::Zstd);
+    let addr = spawn_server(server);
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+        .send_compressed(CompressionEncoding::Zstd)
+        .accept_compressed(CompressionEncoding::Zstd);
+
+    let res = client.echo(message()).await.unwrap();
+    assert_eq!(res.into_inner(), message());
+}
+
+#[tokio::test]
+async fn rejects_unsupported_encoding() {
+    let addr = spawn_server(TestServer::new(Svc));
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+        .send_compressed(CompressionEncoding::Gzip);
+
+    let err = client.echo(message()).await.unwrap_err();
+    assert_eq!(err.code(), Code::Unimplemented);
+}
+
+#[tokio::test]
+async fn uncompressed_responses_when_not_accepted() {
+    let server = TestServer::new(Svc).send_compressed(CompressionEncoding::Gzip);
+    let addr = spawn_server(server);
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let res = client.echo(message()).await.unwrap();
+    assert_eq!(res.into_inner(), message());
+}
+
+#[tokio::test]
+async fn request_override_enables_compression() {
+    let server = TestServer::new(Svc).accept_compressed(CompressionEncoding::Gzip);
+    let addr = spawn_server(server);
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let mut request = Request::new(message());
+    request.set_compression(CompressionEncoding::Gzip);
+    client.echo(request).await.unwrap();
+
+    // Without accepting gzip, the server rejects the compressed request.
+    let addr = spawn_server(TestServer::new(Svc));
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let mut request = Request::new(message());
+    request.set_compression(CompressionEncoding::Gzip);
+    let err = client.echo(request).await.unwrap_err();
+    assert_eq!(err.code(), Code::Unimplemented);
+}
+
+#[tokio::test]
+async fn request_override_disables_compression() {
+    let addr = spawn_server(TestServer::new(Svc));
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+        .send_compressed(CompressionEncoding::Gzip);
+
+    let mut request = Request::new(message());
+    request.set_compression(None);
+    client.echo(request).await.unwrap();
+}
+
+#[tokio::test]
+async fn response_overrides() {
+    let addr = spawn_server(TestServer::new(Svc).send_compressed(CompressionEncoding::Gzip));
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+        .accept_compressed(CompressionEncoding::Gzip);
+
+    let res = client.echo(message()).await.unwrap();
+    assert_eq!(res.metadata().get("grpc-encoding").unwrap(), "gzip");
+
+    let mut request = Request::new(message());
+    request
+        .metadata_mut()
+        .insert("x-compress", "off".parse().unwrap());
+    let res = client.echo(request).await.unwrap();
+    assert!(res.metadata().get("grpc-encoding").is_none());
+    assert_eq!(res.into_inner(), message());
+
+    let addr = spawn_server(TestServer::new(Svc));
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+        .accept_compressed(CompressionEncoding::Gzip);
+
+    let mut request = Request::new(message());
+    request
+        .metadata_mut()
+        .insert("x-compress", "gzip".parse().unwrap());
+    let res = client.echo(request).await.unwrap();
+    assert_eq!(res.metadata().get("grpc-encoding").unwrap(), "gzip");
+    assert_eq!(res.into_inner(), message());
+}
+
+struct Reversed;
+
+impl Compressor for Reversed {
+    fn name(&self) -> &'static str {
+        "x-reversed"
+    }
+
+    fn compress(&self, src: &[u8], dst: &mut dyn Write) -> io::Result<()> {
+        dst.write_all(&src.iter().rev().copied().collect::<Vec<_>>())
+    }
+
+    fn decompress(&self, src: &[u8], dst: &mut dyn Write) -> io::Result<()> {
+        self.compress(src, dst)
+    }
+}
+
+#[tokio::test]
+async fn round_trips_custom_messages() {
+    assert_round_trip(CompressionEncoding::register(Reversed).unwrap()).await;
+}
@@ -0,0 +1,116 @@
+fn main() {
+    tonic_build::compile_protos("proto/test.proto").unwrap();
+    tonic_build::compile_protos("proto/stream.proto").unwrap();
+    tonic_build::compile_protos("proto/channels.proto").unwrap();
+    tonic_build::compile_protos("proto/optional.proto").unwrap();
+    tonic_build::configure()
+        .client_trait(true)
+        .compile(&["proto/governance.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .lazy_decode("/routing.Router/Route")
+        .file_descriptor_set_path(
+            std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("routing.bin"),
+        )
+        .compile(&["proto/routing.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .rename("renames.Registry", "Directory")
+        .rename("renames.Registry.New", "create")
+        .rename(".renames.Registry.Connect", "link")
+        .compile(&["proto/renames.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .method_error("errors.Orders.Get", ".errors.NotFound", "NotFound")
+        .method_error(
+            "errors.Orders.Get",
+            "errors.OutOfStock",
+            "FailedPrecondition",
+        )
+        .method_error(
+            "errors.Orders.Get",
+            ".google.rpc.BadRequest",
+            "InvalidArgument",
+        )
+        .compile(&["proto/errors.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .boxed(".representation.Document.metadata")
+        .boxed(".representation.Document.body.text")
+        .btree_map(".representation.Document.labels")
+        .retain_enum_prefix(true)
+        .compile(&["proto/representation.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .compile(&["proto/transcoding.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .client_field_methods(true)
+        .compile(&["proto/fields.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .include_file("layout.rs")
+        .compile(&["proto/layout/shapes.proto"], &["proto"])
+        .unwrap();
+
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("units.bin"))
+        .compile(&["proto/vendored/units.proto"], &["proto/vendored"])
+        .unwrap();
+
+    tonic_build::configure()
+        .import_descriptor_set(out_dir.join("units.bin"))
+        .compile(&["proto/vendoring.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .include_file("gated_packages.rs")
+        .feature_gates("gated-features.toml")
+        .compile(&["proto/gated.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .async_fn_in_trait(true)
+        .compile(&["proto/native.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .extern_type(".conversion.Uuid", "crate::Id", "crate::IdConversion")
+        .well_known_types(tonic_build::WellKnownTypes::Chrono)
+        .compile(&["proto/conversion.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .build_client_for(["selective.Consumed"])
+        .build_server_for(["selective.Served"])
+        .client_attribute("selective.Consumed", "#[derive(Debug)]")
+        .client_trait(true)
+        .server_attribute("selective.Served.Call", "#[must_use]")
+        .compile(&["proto/selective.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .prost_version(tonic_build::ProstVersion::Crate)
+        .compile(&["proto/versioned.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .build_dispatch(true)
+        .compile(&["proto/dispatch.proto"], &["proto"])
+        .unwrap();
+
+    tonic_build::configure()
+        .comment_style(tonic_build::CommentStyle::Plain)
+        .detached_comments(true)
+        .comment_locations(true)
+        .compile(&["proto/comments.proto"], &["proto"])
+        .unwrap();
+}
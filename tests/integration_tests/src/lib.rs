@@ -0,0 +1,119 @@
+pub mod pb {
+    tonic::include_proto!("test");
+}
+
+pub mod stream {
+    tonic::include_proto!("stream");
+}
+
+pub mod fields {
+    tonic::include_proto!("fields");
+}
+
+pub mod governance {
+    tonic::include_proto!("governance");
+}
+
+pub mod optional {
+    tonic::include_proto!("optional");
+}
+
+pub mod layout {
+    tonic::include_file!("layout.rs");
+}
+
+pub mod dispatch {
+    tonic::include_proto!("dispatch");
+}
+
+pub mod errors {
+    tonic::include_proto!("errors");
+}
+
+pub mod gated {
+    tonic::include_file!("gated_packages.rs");
+}
+
+pub mod native {
+    tonic::include_proto!("native");
+}
+
+pub mod renames {
+    tonic::include_proto!("renames");
+}
+
+pub mod representation {
+    tonic::include_proto!("representation");
+}
+
+pub mod routing {
+    tonic::include_proto!("routing");
+}
+
+pub mod selective {
+    tonic::include_proto!("selective");
+}
+
+pub mod channels {
+    tonic::include_proto!("channels");
+}
+
+pub mod comments {
+    tonic::include_proto!("comments");
+}
+
+pub mod conversion {
+    tonic::include_proto!("conversion");
+}
+
+pub mod units {
+    tonic::include_proto!("units");
+}
+
+pub mod versioned {
+    tonic::include_proto!("versioned");
+}
+
+pub mod vendoring {
+    tonic::include_proto!("vendoring");
+}
+
+pub mod transcoding {
+    tonic::include_proto!("transcoding");
+}
+
+/// An id, standing in for the `conversion.Uuid` messages of the generated
+/// clients and servers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Id(pub u128);
+
+/// Converts `conversion.Uuid` messages to and from [`Id`]s.
+pub enum IdConversion {}
+
+impl tonic::codec::Conversion for IdConversion {
+    type Proto = conversion::Uuid;
+    type Rust = Id;
+
+    fn from_proto(proto: conversion::Uuid) -> Result<Id, tonic::Status> {
+        let hex = proto.value.replace('-', "");
+        if hex.len() != 32 {
+            return Err(tonic::Status::invalid_argument("invalid UUID"));
+        }
+        u128::from_str_radix(&hex, 16)
+            .map(Id)
+            .map_err(|_| tonic::Status::invalid_argument("invalid UUID"))
+    }
+
+    fn into_proto(rust: Id) -> conversion::Uuid {
+        let hex = format!("{:032x}", rust.0);
+        let value = format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        );
+        conversion::Uuid { value }
+    }
+}
@@ -0,0 +1,86 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    any::Any,
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+use tonic::{
+    server::{AuditLayer, MessageAudit},
+    transport::Server,
+    Request, Response, Status,
+};
+use tower::layer::Layer;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+/// Logs the payloads, redacting the requests before the handler sees them.
+#[derive(Default)]
+struct Redact(Mutex<Vec<String>>);
+
+impl MessageAudit for Redact {
+    fn request(&self, method: &str, message: &mut dyn Any) {
+        let payload = message.downcast_mut::<Payload>().unwrap();
+        let entry = format!(
+            "{} request {}",
+            method,
+            String::from_utf8_lossy(&payload.data)
+        );
+        self.0.lock().unwrap().push(entry);
+        payload.data = b"[redacted]".to_vec();
+    }
+
+    fn response(&self, method: &str, message: &mut dyn Any) {
+        let payload = message.downcast_mut::<Payload>().unwrap();
+        let entry = format!(
+            "{} response {}",
+            method,
+            String::from_utf8_lossy(&payload.data)
+        );
+        self.0.lock().unwrap().push(entry);
+    }
+}
+
+#[tokio::test]
+async fn passes_the_messages_of_the_calls_to_the_audit() {
+    let audit = Arc::new(Redact::default());
+    let layer = AuditLayer::new(audit.clone()).method("/test.Test/Echo");
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(layer.layer(TestServer::new(Svc)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let response = client
+        .echo(Payload {
+            data: b"ssn=078-05-1120".to_vec(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(response.into_inner().data, b"[redacted]");
+    assert_eq!(
+        *audit.0.lock().unwrap(),
+        vec![
+            "/test.Test/Echo request ssn=078-05-1120",
+            "/test.Test/Echo response [redacted]",
+        ]
+    );
+}
@@ -0,0 +1,95 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{future::Future, net::TcpListener, pin::Pin};
+use tonic::{
+    transport::{
+        server::{Authorizer, AuthzLayer, AuthzRequest, Decision, Policy, Rule},
+        Channel, Server,
+    },
+    Code, Request, Response, Status,
+};
+use tower::layer::Layer;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+async fn serve(authz: AuthzLayer) -> TestClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(authz.layer(TestServer::new(Svc)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+}
+
+fn echo(tenant: &str) -> Request<Payload> {
+    let mut request = Request::new(Payload { data: vec![1] });
+    request
+        .metadata_mut()
+        .insert("x-tenant", tenant.parse().unwrap());
+    request
+}
+
+#[tokio::test]
+async fn evaluates_the_rules_of_the_policy() {
+    let policy = Policy::new()
+        .allow("/test.Test/*", Rule::any().metadata("x-tenant", "acme"))
+        .allow("/test.Test/*", Rule::any().metadata("x-tenant", "globex"))
+        .deny("*", Rule::any().metadata("x-tenant", "globex"));
+    let mut client = serve(AuthzLayer::new(policy)).await;
+
+    client.echo(echo("acme")).await.unwrap();
+
+    let status = client.echo(echo("globex")).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    assert_eq!(status.message(), "denied by policy");
+
+    let status = client.echo(echo("initech")).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    assert_eq!(status.message(), "not allowed by policy");
+}
+
+/// Stands in for an external engine, only allowing tenants by name.
+struct Engine;
+
+impl Authorizer for Engine {
+    fn authorize<'a>(
+        &'a self,
+        request: &'a AuthzRequest,
+    ) -> Pin<Box<dyn Future<Output = Decision> + Send + 'a>> {
+        Box::pin(async move {
+            let tenant = request.metadata().get("x-tenant");
+            match tenant.and_then(|tenant| tenant.to_str().ok()) {
+                Some(tenant) if tenant.starts_with('a') => Decision::Allow,
+                Some(tenant) => Decision::Deny(format!("{} is not allowed", tenant)),
+                None => Decision::Deny("missing tenant".to_string()),
+            }
+        })
+    }
+}
+
+#[tokio::test]
+async fn asks_the_authorizer() {
+    let mut client = serve(AuthzLayer::new(Engine)).await;
+
+    client.echo(echo("acme")).await.unwrap();
+
+    let status = client.echo(echo("globex")).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    assert_eq!(status.message(), "globex is not allowed");
+}
@@ -0,0 +1,110 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{net::TcpListener, time::Duration};
+use tonic::{
+    transport::{
+        server::Binding, Certificate, Channel, ClientTlsConfig, Identity, Server, ServerTlsConfig,
+    },
+    Request, Response, Status,
+};
+
+const CA: &[u8] = include_bytes!("../../../examples/data/tls/ca.pem");
+const CERT: &[u8] = include_bytes!("../../../examples/data/tls/server.pem");
+const KEY: &[u8] = include_bytes!("../../../examples/data/tls/server.key");
+
+/// Sleeps for as many milliseconds as the payload has bytes.
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let delay = request.get_ref().data.len() as u64;
+        tokio::time::delay_for(Duration::from_millis(delay)).await;
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+fn sleep_for(millis: usize) -> Payload {
+    Payload {
+        data: vec![0; millis],
+    }
+}
+
+#[tokio::test]
+async fn serves_each_binding_with_its_settings() {
+    let public = TcpListener::bind("127.0.0.1:0").unwrap();
+    let local = TcpListener::bind("127.0.0.1:0").unwrap();
+    let public_addr = public.local_addr().unwrap();
+    let local_addr = local.local_addr().unwrap();
+
+    let bindings = vec![
+        Binding::from_listener(public)
+            .tls_config(ServerTlsConfig::new().identity(Identity::from_pem(CERT, KEY)))
+            .timeout(Duration::from_millis(50)),
+        Binding::from_listener(local),
+    ];
+    tokio::spawn(async move {
+        Server::builder()
+            .timeout(Duration::from_secs(5))
+            .add_service(TestServer::new(Svc))
+            .serve_bindings(bindings)
+            .await
+            .unwrap();
+    });
+
+    let tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(CA))
+        .domain_name("example.com");
+    let channel = Channel::from_shared(format!("https://{}", public_addr))
+        .unwrap()
+        .tls_config(tls)
+        .connect()
+        .await
+        .unwrap();
+    let mut public = TestClient::new(channel);
+
+    let channel = Channel::from_shared(format!("http://{}", local_addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut local = TestClient::new(channel);
+
+    // The public binding uses its own timeout.
+    public.echo(sleep_for(0)).await.unwrap();
+    public.echo(sleep_for(200)).await.unwrap_err();
+
+    // The local binding is plaintext and uses the server wide timeout.
+    local.echo(sleep_for(0)).await.unwrap();
+    local.echo(sleep_for(200)).await.unwrap();
+}
+
+#[tokio::test]
+async fn rejects_plaintext_on_tls_bindings() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let binding = Binding::from_listener(listener)
+        .tls_config(ServerTlsConfig::new().identity(Identity::from_pem(CERT, KEY)));
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc))
+            .serve_bindings(vec![binding])
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await;
+    if let Ok(channel) = channel {
+        TestClient::new(channel)
+            .echo(sleep_for(0))
+            .await
+            .unwrap_err();
+    }
+}
@@ -0,0 +1,44 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::net::TcpListener;
+use tonic::{codec::BufferPool, transport::Server, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+#[tokio::test]
+async fn calls_reuse_pooled_buffers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc).buffer_pool(BufferPool::new(16)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let pool = BufferPool::new(16);
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+        .buffer_pool(pool.clone());
+
+    for len in 0..10 {
+        let res = client.echo(Payload { data: vec![1; len] }).await.unwrap();
+        assert_eq!(res.into_inner().data, vec![1; len]);
+
+        // The response decoding buffer goes back once the call is done.
+        assert!(!pool.is_empty());
+    }
+}
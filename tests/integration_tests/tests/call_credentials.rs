@@ -0,0 +1,120 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    future::Future,
+    net::TcpListener,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use tonic::{
+    metadata::MetadataMap, transport::Server, CallCredentials, Code, Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    // Echoes the credentials of the call.
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let credentials = ["authorization", "x-tenant"]
+            .iter()
+            .filter_map(|key| request.metadata().get(*key))
+            .map(|value| value.to_str().unwrap())
+            .collect::<Vec<_>>()
+            .join(";");
+        Ok(Response::new(Payload {
+            data: credentials.into_bytes(),
+        }))
+    }
+}
+
+/// Issues a new token for each call.
+#[derive(Default)]
+struct Tokens(AtomicUsize);
+
+impl CallCredentials for Tokens {
+    fn metadata<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<MetadataMap, Status>> + Send + 'a>> {
+        Box::pin(async move {
+            assert_eq!(path, "/test.Test/Echo");
+            let token = self.0.fetch_add(1, Ordering::SeqCst);
+            let mut metadata = MetadataMap::new();
+            metadata.insert(
+                "authorization",
+                format!("Bearer {}", token).parse().unwrap(),
+            );
+            Ok(metadata)
+        })
+    }
+}
+
+struct Tenant(Result<&'static str, Code>);
+
+impl CallCredentials for Tenant {
+    fn metadata<'a>(
+        &'a self,
+        _: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<MetadataMap, Status>> + Send + 'a>> {
+        Box::pin(async move {
+            let tenant = self.0.map_err(|code| Status::new(code, "no credentials"))?;
+            let mut metadata = MetadataMap::new();
+            metadata.insert("x-tenant", tenant.parse().unwrap());
+            Ok(metadata)
+        })
+    }
+}
+
+async fn echo(
+    client: &mut TestClient<tonic::transport::Channel>,
+    request: Request<Payload>,
+) -> Result<String, Status> {
+    let res = client.echo(request).await?;
+    Ok(String::from_utf8(res.into_inner().data).unwrap())
+}
+
+#[tokio::test]
+async fn sends_the_metadata_of_the_credentials() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+        .call_credentials(Tokens::default());
+
+    // Produced again for each call, replacing the metadata of the request.
+    let mut request = Request::new(Payload::default());
+    request
+        .metadata_mut()
+        .insert("authorization", "Bearer stale".parse().unwrap());
+    assert_eq!(echo(&mut client, request).await.unwrap(), "Bearer 0");
+    assert_eq!(
+        echo(&mut client, Request::new(Payload::default()))
+            .await
+            .unwrap(),
+        "Bearer 1"
+    );
+
+    // The credentials of a call come on top of the ones of the client.
+    let mut request = Request::new(Payload::default());
+    request.set_call_credentials(Tenant(Ok("acme")));
+    assert_eq!(echo(&mut client, request).await.unwrap(), "Bearer 2;acme");
+
+    let mut request = Request::new(Payload::default());
+    request.set_call_credentials(Tenant(Err(Code::Unauthenticated)));
+    let status = echo(&mut client, request).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+}
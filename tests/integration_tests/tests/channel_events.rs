@@ -0,0 +1,113 @@
+use futures_util::StreamExt;
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{net::TcpListener, time::Duration};
+use tonic::{
+    transport::{
+        channel::{
+            ChannelEventKind, ChannelEvents,
+            ConnectivityState::{self, *},
+        },
+        Endpoint, Server,
+    },
+    Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+/// The kinds of the next events, up to the change to the state `to`.
+async fn until(events: &mut ChannelEvents, to: ConnectivityState) -> Vec<ChannelEventKind> {
+    let mut kinds = Vec::new();
+    loop {
+        let event = tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await
+            .expect("timed out waiting for the events")
+            .unwrap();
+        let kind = event.kind().clone();
+        kinds.push(kind.clone());
+        if let ChannelEventKind::StateChanged { to: state, .. } = kind {
+            if state == to {
+                return kinds;
+            }
+        }
+    }
+}
+
+fn changed(from: ConnectivityState, to: ConnectivityState) -> ChannelEventKind {
+    ChannelEventKind::StateChanged { from, to }
+}
+
+#[tokio::test]
+async fn reports_the_connections_of_subchannels() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+    let serve = tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener_shutdown(listener, async {
+                let _ = rx.await;
+            })
+            .await
+            .unwrap();
+    });
+
+    let endpoint = Endpoint::from_shared(format!("http://{}", addr)).unwrap();
+    let mut events = endpoint.events();
+    let channel = endpoint.connect().await.unwrap();
+    let mut channel_events = channel.events();
+    let mut client = TestClient::new(channel);
+    client.echo(Payload { data: vec![1] }).await.unwrap();
+
+    assert_eq!(
+        until(&mut events, Ready).await,
+        vec![
+            ChannelEventKind::ConnectAttempt,
+            changed(Idle, Connecting),
+            ChannelEventKind::Connected,
+            changed(Connecting, Ready),
+        ]
+    );
+
+    // The server closes the connection as it shuts down.
+    tx.send(()).unwrap();
+    serve.await.unwrap();
+    assert_eq!(
+        until(&mut events, Idle).await,
+        vec![
+            ChannelEventKind::Disconnected { error: None },
+            changed(Ready, Idle),
+        ]
+    );
+
+    client.echo(Payload { data: vec![1] }).await.unwrap_err();
+    let kinds = until(&mut events, TransientFailure).await;
+    assert_eq!(kinds.len(), 4, "{:?}", kinds);
+    assert_eq!(
+        kinds[..2],
+        [ChannelEventKind::ConnectAttempt, changed(Idle, Connecting)]
+    );
+    match &kinds[2] {
+        ChannelEventKind::ConnectFailed { error } => assert!(!error.is_empty()),
+        kind => panic!("unexpected {:?}", kind),
+    }
+    assert_eq!(kinds[3], changed(Connecting, TransientFailure));
+
+    // The channel subscribers see the events after they subscribed.
+    let event = channel_events.next().await.unwrap();
+    assert_eq!(
+        event.kind(),
+        &ChannelEventKind::Disconnected { error: None }
+    );
+    assert_eq!(event.target().port_u16(), Some(addr.port()));
+}
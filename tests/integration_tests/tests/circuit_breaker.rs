@@ -0,0 +1,79 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tonic::{
+    client::{CircuitBreaker, CircuitBreakerLayer},
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::layer::Layer;
+
+/// Fails the calls until it is told to recover, counting them.
+struct Svc {
+    calls: Arc<AtomicUsize>,
+}
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        if self.calls.fetch_add(1, Ordering::SeqCst) < 2 {
+            return Err(Status::unavailable("overloaded"));
+        }
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+async fn echo(client: &mut TestClient<CircuitBreaker<Channel>>) -> Result<(), Status> {
+    client.echo(Payload::default()).await.map(drop)
+}
+
+#[tokio::test]
+async fn fails_fast_while_the_circuit_is_open() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let svc = Svc {
+        calls: calls.clone(),
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let breaker = CircuitBreakerLayer::new()
+        .consecutive_failures(2)
+        .open_duration(Duration::from_millis(200));
+    let mut client = TestClient::new(breaker.layer(channel));
+
+    assert_eq!(echo(&mut client).await.unwrap_err().message(), "overloaded");
+    assert_eq!(echo(&mut client).await.unwrap_err().message(), "overloaded");
+
+    let status = echo(&mut client).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(status.message(), "circuit breaker open");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // The server is probed once the circuit is half open.
+    tokio::time::delay_for(Duration::from_millis(250)).await;
+    echo(&mut client).await.unwrap();
+    echo(&mut client).await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
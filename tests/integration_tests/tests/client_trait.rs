@@ -0,0 +1,73 @@
+use futures_util::stream;
+use integration_tests::selective::{
+    consumed_client::{ConsumedApi, ConsumedClient},
+    served_server::{Served, ServedServer},
+    Empty,
+};
+use std::net::TcpListener;
+use tonic::{
+    codec::Streaming, codegen::BoxMessageStream, transport::Server, Code, Request, Response, Status,
+};
+
+/// Application code only knowing about the trait.
+async fn collect(api: &mut impl ConsumedApi) -> Result<(), Status> {
+    api.call(Request::new(Empty {})).await?;
+
+    let messages = BoxMessageStream::new(stream::iter(vec![Empty {}]));
+    api.collect(Request::new(messages)).await?;
+    Ok(())
+}
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Served for Svc {
+    async fn call(&self, _: Request<Empty>) -> Result<Response<Empty>, Status> {
+        Ok(Response::new(Empty {}))
+    }
+}
+
+#[derive(Default)]
+struct Fake {
+    calls: usize,
+}
+
+#[tonic::async_trait]
+impl ConsumedApi for Fake {
+    async fn call(&mut self, _: Request<Empty>) -> Result<Response<Empty>, Status> {
+        self.calls += 1;
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn collect(
+        &mut self,
+        _: Request<BoxMessageStream<Empty>>,
+    ) -> Result<Response<Streaming<Empty>>, Status> {
+        Err(Status::unavailable("fake"))
+    }
+}
+
+#[tokio::test]
+async fn clients_can_be_replaced() {
+    let mut fake = Fake::default();
+    let status = collect(&mut fake).await.unwrap_err();
+    assert_eq!(fake.calls, 1);
+    assert_eq!(status.code(), Code::Unavailable);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(ServedServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = ConsumedClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+    let status = collect(&mut client).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+}
@@ -0,0 +1,24 @@
+use integration_tests::comments::catalog_client::CatalogClient;
+
+const GENERATED: &str = include_str!(concat!(env!("OUT_DIR"), "/comments.rs"));
+
+#[test]
+fn renders_the_comments_of_services_and_methods() {
+    let _ = CatalogClient::<tonic::transport::Channel>::connect::<&'static str>;
+
+    for line in &[
+        " Catalog",
+        " Detached from the service below.",
+        " Lists the items of a Catalog, see the docs (https://example.com).",
+        " Declared as `comments.Catalog` in `comments.proto`.",
+        " Returns the next Item.",
+        " Declared as `comments.Catalog.Next` in `comments.proto`.",
+    ] {
+        assert!(
+            GENERATED.contains(&format!("///{}\n", line))
+                || GENERATED.contains(&format!("#[doc = {:?}]", line)),
+            "missing doc line: {:?}",
+            line
+        );
+    }
+}
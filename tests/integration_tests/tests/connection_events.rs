@@ -0,0 +1,126 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::io::AsyncWriteExt;
+use tonic::{
+    transport::{server::ConnectionEvent, Channel, Identity, Server, ServerTlsConfig},
+    Request, Response, Status,
+};
+
+const CERT: &[u8] = include_bytes!("../../../examples/data/tls/server.pem");
+const KEY: &[u8] = include_bytes!("../../../examples/data/tls/server.key");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+/// Records the events of connections, as `kind peer`.
+#[derive(Clone, Default)]
+struct Events(Arc<Mutex<Vec<String>>>);
+
+impl Events {
+    fn record(&self, event: &ConnectionEvent<'_>) {
+        let event = match event {
+            ConnectionEvent::Accepted { remote_addr } => format!("accepted {:?}", remote_addr),
+            ConnectionEvent::HandshakeFailed { remote_addr, .. } => {
+                format!("handshake failed {:?}", remote_addr)
+            }
+            ConnectionEvent::Established { remote_addr, tls } => {
+                format!("established tls={} {:?}", tls, remote_addr)
+            }
+            ConnectionEvent::Closed {
+                remote_addr, stats, ..
+            } => {
+                assert!(stats.bytes_read() > 0);
+                assert!(stats.bytes_written() > 0);
+                format!("closed {:?}", remote_addr)
+            }
+        };
+        self.0.lock().unwrap().push(event);
+    }
+
+    /// Wait for `count` events to be recorded.
+    async fn wait_for(&self, count: usize) -> Vec<String> {
+        for _ in 0..100 {
+            let events = self.0.lock().unwrap().clone();
+            if events.len() >= count {
+                return events;
+            }
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+        }
+        panic!("missing events: {:?}", self.0.lock().unwrap());
+    }
+}
+
+fn serve(server: Server, events: &Events) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let events = events.clone();
+    tokio::spawn(async move {
+        server
+            .on_connection_event(move |event| events.record(event))
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn reports_the_lifecycle_of_connections_in_order() {
+    let events = Events::default();
+    let addr = serve(Server::builder(), &events);
+
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(channel);
+    client.echo(Payload::default()).await.unwrap();
+    drop(client);
+
+    let events = events.wait_for(3).await;
+    let peer = events[0].trim_start_matches("accepted ");
+    assert_eq!(
+        events,
+        vec![
+            format!("accepted {}", peer),
+            format!("established tls=false {}", peer),
+            format!("closed {}", peer),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reports_failed_handshakes_instead_of_established_connections() {
+    let events = Events::default();
+    let tls = ServerTlsConfig::new().identity(Identity::from_pem(CERT, KEY));
+    let addr = serve(Server::builder().tls_config(tls), &events);
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"not a client hello").await.unwrap();
+
+    let events = events.wait_for(2).await;
+    let peer = events[0].trim_start_matches("accepted ");
+    assert_eq!(
+        events,
+        vec![
+            format!("accepted {}", peer),
+            format!("handshake failed {}", peer),
+        ]
+    );
+}
@@ -0,0 +1,126 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use futures_util::{stream, StreamExt};
+use integration_tests::{
+    conversion::{
+        accounts_client::AccountsClient,
+        accounts_server::{Accounts, AccountsServer},
+        Empty, Uuid,
+    },
+    Id,
+};
+use std::{net::TcpListener, pin::Pin};
+use tonic::{
+    codec::ProstCodec,
+    codegen::http::uri::PathAndQuery,
+    transport::{Channel, Server},
+    Code, Request, Response, Status, Streaming,
+};
+
+const ID: Id = Id(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+
+fn created_at() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2020, 1, 8, 12, 0, 0).unwrap() + Duration::nanoseconds(500)
+}
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Accounts for Svc {
+    async fn create(&self, _: Request<Empty>) -> Result<Response<Id>, Status> {
+        Ok(Response::new(ID))
+    }
+
+    async fn created_at(&self, request: Request<Id>) -> Result<Response<DateTime<Utc>>, Status> {
+        if request.into_inner() != ID {
+            return Err(Status::not_found("unknown account"));
+        }
+        Ok(Response::new(created_at()))
+    }
+
+    type AgesStream =
+        Pin<Box<dyn futures_core::Stream<Item = Result<Duration, Status>> + Send + Sync>>;
+
+    async fn ages(
+        &self,
+        request: Request<Streaming<Id>>,
+    ) -> Result<Response<Self::AgesStream>, Status> {
+        let ages = request
+            .into_inner()
+            .map(|id| id.map(|id| Duration::seconds(id.0 as i64)));
+        Ok(Response::new(Box::pin(ages)))
+    }
+}
+
+async fn serve() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(AccountsServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    format!("http://{}", addr)
+}
+
+#[test]
+fn converts_ids() {
+    let uuid = Uuid {
+        value: "01234567-89ab-cdef-0123-456789abcdef".to_string(),
+    };
+    assert_eq!(
+        <integration_tests::IdConversion as tonic::codec::Conversion>::into_proto(ID),
+        uuid
+    );
+}
+
+#[tokio::test]
+async fn maps_requests_and_responses_to_other_types() {
+    let mut client = AccountsClient::connect(serve().await).await.unwrap();
+
+    let id = client.create(Empty {}).await.unwrap().into_inner();
+    assert_eq!(id, ID);
+
+    let at = client.created_at(id).await.unwrap().into_inner();
+    assert_eq!(at, created_at());
+
+    let status = client.created_at(Id(1)).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    let ages = client
+        .ages(stream::iter(vec![Id(1), Id(90)]))
+        .await
+        .unwrap()
+        .into_inner()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(ages, vec![Duration::seconds(1), Duration::seconds(90)]);
+}
+
+#[tokio::test]
+async fn rejects_messages_failing_the_conversion() {
+    let channel = Channel::from_shared(serve().await)
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+
+    // Send the message itself, skipping the conversion of the client.
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready().await.unwrap();
+    let status = grpc
+        .unary(
+            Request::new(Uuid {
+                value: "not a uuid".to_string(),
+            }),
+            PathAndQuery::from_static("/conversion.Accounts/CreatedAt"),
+            ProstCodec::<_, prost_types::Timestamp>::default(),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
@@ -0,0 +1,90 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tonic::{
+    codec::{MakeCodec, ProstCodec},
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+/// Counts the codecs it makes, for the messages of any method.
+#[derive(Debug, Clone, Default)]
+struct Counting(Arc<AtomicUsize>);
+
+impl<T, U> MakeCodec<T, U> for Counting
+where
+    T: prost::Message + Send + Sync + 'static,
+    U: prost::Message + Default + Send + Sync + 'static,
+{
+    type Codec = ProstCodec<T, U>;
+
+    fn make_codec(&self) -> Self::Codec {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        ProstCodec::default()
+    }
+}
+
+fn serve(codec: Counting) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::with_codec(Svc, codec))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    addr
+}
+
+async fn connect(addr: SocketAddr) -> Channel {
+    Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn makes_the_codec_of_each_call() {
+    let server_codec = Counting::default();
+    let addr = serve(server_codec.clone());
+
+    let client_codec = Counting::default();
+    let mut client = TestClient::with_codec(connect(addr).await, client_codec.clone());
+    for _ in 0..3 {
+        let response = client.echo(Payload { data: vec![1, 2] }).await.unwrap();
+        assert_eq!(response.into_inner().data, vec![1, 2]);
+    }
+
+    assert_eq!(client_codec.0.load(Ordering::SeqCst), 3);
+    assert_eq!(server_codec.0.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn takes_a_codec_of_the_service_messages() {
+    let addr = serve(Counting::default());
+
+    let codec = ProstCodec::<Payload, Payload>::default();
+    let mut client = TestClient::with_codec(connect(addr).await, codec);
+    let response = client.echo(Payload { data: vec![3] }).await.unwrap();
+    assert_eq!(response.into_inner().data, vec![3]);
+}
@@ -0,0 +1,99 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tonic::{
+    body::BoxBody,
+    codegen::{http, Service},
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::buffer::Buffer;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+#[tokio::test]
+async fn clients_wrap_any_service() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(Buffer::new(channel, 16));
+
+    let payload = Payload { data: vec![1, 2] };
+    let res = client.echo(payload.clone()).await.unwrap();
+    assert_eq!(res.into_inner(), payload);
+}
+
+/// Records the URI of the requests, replying with an `Unimplemented` status.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<http::Uri>>>);
+
+impl Service<http::Request<BoxBody>> for Recorder {
+    type Response = http::Response<BoxBody>;
+    type Error = Status;
+    type Future = futures_util::future::Ready<Result<Self::Response, Status>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Status>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<BoxBody>) -> Self::Future {
+        self.0.lock().unwrap().push(request.uri().clone());
+
+        let response = http::Response::builder()
+            .header("grpc-status", "12")
+            .body(BoxBody::empty())
+            .unwrap();
+        futures_util::future::ok(response)
+    }
+}
+
+#[tokio::test]
+async fn sends_requests_to_the_origin() {
+    let recorder = Recorder::default();
+    let origin = "https://example.com:8443".parse().unwrap();
+    let mut client = TestClient::with_origin(recorder.clone(), origin);
+
+    let status = client.echo(Payload::default()).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+
+    let uris = recorder.0.lock().unwrap();
+    assert_eq!(
+        uris.as_slice(),
+        &["https://example.com:8443/test.Test/Echo"
+            .parse::<http::Uri>()
+            .unwrap()]
+    );
+}
+
+#[test]
+#[should_panic(expected = "origin without a scheme or an authority")]
+fn rejects_origins_without_authority() {
+    TestClient::with_origin(Recorder::default(), "/path".parse().unwrap());
+}
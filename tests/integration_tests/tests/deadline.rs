@@ -0,0 +1,84 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{net::TcpListener, time::Duration};
+use tonic::{
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+
+/// Replies with the time left until its deadline, in milliseconds, or
+/// forwards the request to `backend`.
+struct Svc {
+    backend: Option<TestClient<Channel>>,
+}
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        if let Some(backend) = &self.backend {
+            return backend.clone().echo(request.into_inner()).await;
+        }
+
+        let remaining = request
+            .time_remaining()
+            .ok_or_else(|| Status::failed_precondition("no deadline"))?;
+        Ok(Response::new(Payload {
+            data: (remaining.as_millis() as u64).to_be_bytes().to_vec(),
+        }))
+    }
+}
+
+async fn serve(svc: Svc) -> TestClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+}
+
+fn remaining(payload: Payload) -> Duration {
+    let mut millis = [0; 8];
+    millis.copy_from_slice(&payload.data);
+    Duration::from_millis(u64::from_be_bytes(millis))
+}
+
+#[tokio::test]
+async fn server_reads_grpc_timeout() {
+    let mut client = serve(Svc { backend: None }).await;
+
+    let status = client.echo(Payload::default()).await.unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+
+    let mut request = Request::new(Payload::default());
+    request.set_timeout(Duration::from_secs(10));
+    let left = remaining(client.echo(request).await.unwrap().into_inner());
+    assert!(left <= Duration::from_secs(10));
+    assert!(left > Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn nested_calls_inherit_deadline() {
+    let backend = serve(Svc { backend: None }).await;
+    let mut client = serve(Svc {
+        backend: Some(backend),
+    })
+    .await;
+
+    let mut request = Request::new(Payload::default());
+    request.set_timeout(Duration::from_secs(10));
+    let left = remaining(client.echo(request).await.unwrap().into_inner());
+    assert!(left <= Duration::from_secs(10));
+    assert!(left > Duration::from_secs(5));
+}
@@ -0,0 +1,116 @@
+use futures_util::{stream, StreamExt};
+use integration_tests::{
+    pb::{
+        test_client::TestClient,
+        test_server::{Test, TestServer},
+        Payload,
+    },
+    stream::{
+        events_client::EventsClient,
+        events_server::{Events, EventsServer},
+        Event,
+    },
+};
+use std::{net::TcpListener, pin::Pin, time::Duration};
+use tonic::{transport::Server, Code, Request, Response, Status};
+
+/// Answers after 200ms.
+struct Slow;
+
+#[tonic::async_trait]
+impl Test for Slow {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        tokio::time::delay_for(Duration::from_millis(200)).await;
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+/// Sends back the request event, then stalls.
+struct Stalled;
+
+#[tonic::async_trait]
+impl Events for Stalled {
+    type SubscribeStream =
+        Pin<Box<dyn futures_core::Stream<Item = Result<Event, Status>> + Send + Sync>>;
+
+    async fn subscribe(
+        &self,
+        request: Request<Event>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let events = stream::once(async move { Ok(request.into_inner()) }).chain(stream::pending());
+        Ok(Response::new(Box::pin(events) as Self::SubscribeStream))
+    }
+}
+
+async fn serve(enforce_deadlines: bool) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Slow).enforce_deadlines(enforce_deadlines))
+            .add_service(EventsServer::new(Stalled).enforce_deadlines(enforce_deadlines))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    format!("http://{}", addr)
+}
+
+fn with_timeout<T>(message: T, timeout: Duration) -> Request<T> {
+    let mut request = Request::new(message);
+    request.set_timeout(timeout);
+    request
+}
+
+#[tokio::test]
+async fn unary_deadline_exceeded() {
+    let mut client = TestClient::connect(serve(true).await).await.unwrap();
+
+    let request = with_timeout(Payload::default(), Duration::from_millis(50));
+    let status = client.echo(request).await.unwrap_err();
+    assert_eq!(status.code(), Code::DeadlineExceeded);
+
+    let request = with_timeout(Payload::default(), Duration::from_secs(5));
+    client.echo(request).await.unwrap();
+}
+
+#[tokio::test]
+async fn unary_deadline_not_enforced() {
+    let mut client = TestClient::connect(serve(false).await).await.unwrap();
+
+    let request = with_timeout(Payload::default(), Duration::from_millis(50));
+    client.echo(request).await.unwrap();
+}
+
+#[tokio::test]
+async fn streaming_deadline_exceeded() {
+    let mut client = EventsClient::connect(serve(true).await).await.unwrap();
+
+    let event = Event {
+        name: "hello".to_string(),
+    };
+    let mut events = client
+        .subscribe(with_timeout(event, Duration::from_millis(50)))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(events.message().await.unwrap().unwrap().name, "hello");
+    let status = events.message().await.unwrap_err();
+    assert_eq!(status.code(), Code::DeadlineExceeded);
+}
+
+#[tokio::test]
+async fn deadline_past_the_timer_range() {
+    let mut client = TestClient::connect(serve(true).await).await.unwrap();
+
+    // Sent as `99999999H`, over 11,000 years, past what the timer can wait
+    // for.
+    let timeout = Duration::from_secs(99_999_999 * 60 * 60);
+    client
+        .echo(with_timeout(Payload::default(), timeout))
+        .await
+        .unwrap();
+}
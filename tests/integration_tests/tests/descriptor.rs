@@ -0,0 +1,45 @@
+use integration_tests::{selective::consumed_descriptor, stream::events_descriptor};
+use tonic::descriptor::{IdempotencyLevel, MethodDescriptor, MethodKind};
+
+#[test]
+fn describes_services_and_methods() {
+    assert_eq!(consumed_descriptor::SERVICE.name, "selective.Consumed");
+    assert_eq!(
+        consumed_descriptor::SERVICE.methods,
+        &[consumed_descriptor::CALL, consumed_descriptor::COLLECT]
+    );
+
+    assert_eq!(
+        consumed_descriptor::CALL,
+        MethodDescriptor {
+            name: "Call",
+            service: "selective.Consumed",
+            path: "/selective.Consumed/Call",
+            kind: MethodKind::Unary,
+            idempotency_level: IdempotencyLevel::Idempotent,
+            options: b"\x90\x02\x02",
+            http_rules: &[],
+        }
+    );
+    assert_eq!(consumed_descriptor::COLLECT.kind, MethodKind::Streaming);
+    assert_eq!(
+        consumed_descriptor::COLLECT.idempotency_level,
+        IdempotencyLevel::Unknown
+    );
+    assert_eq!(
+        events_descriptor::SUBSCRIBE.kind,
+        MethodKind::ServerStreaming
+    );
+}
+
+#[test]
+fn finds_methods_by_path() {
+    let method = consumed_descriptor::SERVICE
+        .method("/selective.Consumed/Collect")
+        .unwrap();
+    assert_eq!(method.name, "Collect");
+
+    assert!(consumed_descriptor::SERVICE
+        .method("/selective.Served/Call")
+        .is_none());
+}
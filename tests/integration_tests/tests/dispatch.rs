@@ -0,0 +1,60 @@
+use futures_util::stream;
+use integration_tests::dispatch::{
+    countdown_server::{self, Countdown, CountdownServer},
+    Count,
+};
+use prost::Message;
+use tonic::{Code, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Countdown for Svc {
+    type StartStream = stream::Iter<std::vec::IntoIter<Result<Count, Status>>>;
+
+    async fn start(&self, request: Request<Count>) -> Result<Response<Self::StartStream>, Status> {
+        let start = request.into_inner().value;
+        let counts = (0..start)
+            .rev()
+            .map(|value| Ok(Count { value }))
+            .collect::<Vec<_>>();
+        Ok(Response::new(stream::iter(counts)))
+    }
+}
+
+fn frame(count: Count) -> Vec<u8> {
+    let mut message = Vec::new();
+    count.encode(&mut message).unwrap();
+
+    let mut frame = vec![0];
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend(message);
+    frame
+}
+
+#[tokio::test]
+async fn dispatches_framed_requests() {
+    let server = CountdownServer::new(Svc);
+
+    let body = frame(Count { value: 3 });
+    let (body, status) =
+        countdown_server::dispatch(&server, "/dispatch.Countdown/Start", body.into()).await;
+
+    assert_eq!(status.code(), Code::Ok);
+    let expected = (0..3)
+        .rev()
+        .flat_map(|value| frame(Count { value }))
+        .collect::<Vec<_>>();
+    assert_eq!(&body[..], &expected[..]);
+}
+
+#[tokio::test]
+async fn fails_unknown_methods() {
+    let server = CountdownServer::new(Svc);
+
+    let (body, status) =
+        countdown_server::dispatch(&server, "/dispatch.Countdown/Stop", Vec::new().into()).await;
+
+    assert!(body.is_empty());
+    assert_eq!(status.code(), Code::Unimplemented);
+}
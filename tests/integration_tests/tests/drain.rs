@@ -0,0 +1,164 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    fmt,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::oneshot;
+use tonic::{
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+/// Records when the server at `addr` drained, sent `GOAWAY` and stopped.
+#[derive(Clone)]
+struct Log {
+    addr: String,
+    entries: Arc<Mutex<Vec<(Instant, String)>>>,
+}
+
+impl Log {
+    fn new(addr: SocketAddr) -> Self {
+        Log {
+            addr: addr.to_string(),
+            entries: Arc::default(),
+        }
+    }
+
+    fn push(&self, entry: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .push((Instant::now(), entry.to_string()));
+    }
+
+    fn entries(&self) -> Vec<String> {
+        let log = self.entries.lock().unwrap();
+        log.iter().map(|(_, entry)| entry.clone()).collect()
+    }
+
+    fn at(&self, entry: &str) -> Instant {
+        let log = self.entries.lock().unwrap();
+        log.iter().find(|(_, e)| e == entry).unwrap().0
+    }
+}
+
+#[derive(Default)]
+struct Fields {
+    peer: String,
+    direction: String,
+    message: String,
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            "direction" => self.direction = format!("{:?}", value),
+            _ => {}
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "peer" => self.peer = value.to_string(),
+            "direction" => self.direction = value.to_string(),
+            _ => {}
+        }
+    }
+}
+
+impl Subscriber for Log {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target().ends_with("frames")
+    }
+
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        // The client traces the frames of its connection to the server, whose
+        // first GOAWAY is the one that matters.
+        let goaway = fields.peer != self.addr
+            && fields.direction == "sent"
+            && fields.message.starts_with("GOAWAY");
+        if goaway && !self.entries().iter().any(|e| e == "goaway") {
+            self.push("goaway");
+        }
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[tokio::test]
+async fn drains_and_waits_the_lameduck_period_before_goaway() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let log = Log::new(addr);
+    let _guard = tracing::subscriber::set_default(log.clone());
+    let lameduck = Duration::from_millis(200);
+    let (tx, rx) = oneshot::channel::<()>();
+    let server = {
+        let log = log.clone();
+        let on_drain = log.clone();
+        tokio::spawn(async move {
+            Server::builder()
+                .on_drain(move || on_drain.push("drain"))
+                .lameduck(lameduck)
+                .add_service(TestServer::new(Svc))
+                .serve_with_listener_shutdown(listener, async {
+                    let _ = rx.await;
+                })
+                .await
+                .unwrap();
+            log.push("stopped");
+        })
+    };
+
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(channel);
+    client.echo(Payload::default()).await.unwrap();
+
+    tx.send(()).unwrap();
+    tokio::time::delay_for(lameduck / 4).await;
+    assert_eq!(log.entries(), vec!["drain"]);
+
+    // Requests are still served during the lameduck period.
+    client.echo(Payload::default()).await.unwrap();
+
+    // The connection stays open until the server sends GOAWAY.
+    server.await.unwrap();
+    assert_eq!(log.entries(), vec!["drain", "goaway", "stopped"]);
+    assert!(log.at("goaway") - log.at("drain") >= lameduck);
+}
@@ -0,0 +1,94 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::{SocketAddr, TcpListener},
+    time::{Duration, Instant},
+};
+use tonic::{
+    fault::{Fault, FaultLayer},
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::layer::Layer;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+fn serve(faults: FaultLayer) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(faults.layer(TestServer::new(Svc)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    addr
+}
+
+async fn connect(addr: SocketAddr) -> Channel {
+    Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+fn echo(fault: Option<&str>) -> Request<Payload> {
+    let mut request = Request::new(Payload { data: vec![1] });
+    if let Some(fault) = fault {
+        request
+            .metadata_mut()
+            .insert("x-fault", fault.parse().unwrap());
+    }
+    request
+}
+
+#[tokio::test]
+async fn fails_the_selected_calls_on_servers() {
+    let faults = FaultLayer::new().fault(
+        Fault::new()
+            .abort(Code::Unavailable, "injected fault")
+            .method("/test.Test/Echo")
+            .metadata("x-fault", "unavailable"),
+    );
+    let mut client = TestClient::new(connect(serve(faults)).await);
+
+    client.echo(echo(None)).await.unwrap();
+    client.echo(echo(Some("slow"))).await.unwrap();
+
+    let status = client.echo(echo(Some("unavailable"))).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(status.message(), "injected fault");
+}
+
+#[tokio::test]
+async fn delays_the_selected_calls_of_clients() {
+    let channel = connect(serve(FaultLayer::new())).await;
+    let faults = FaultLayer::new()
+        .fault(Fault::new().abort(Code::Internal, "never").sample(0.0))
+        .fault(
+            Fault::new()
+                .delay(Duration::from_millis(200))
+                .metadata("x-fault", "slow"),
+        );
+    let mut client = TestClient::new(faults.layer(channel));
+
+    let started_at = Instant::now();
+    client.echo(echo(None)).await.unwrap();
+    assert!(started_at.elapsed() < Duration::from_millis(200));
+
+    let started_at = Instant::now();
+    client.echo(echo(Some("slow"))).await.unwrap();
+    assert!(started_at.elapsed() >= Duration::from_millis(200));
+}
@@ -0,0 +1,53 @@
+// The services are compiled in with their features, `gated.Enabled` by
+// default, so these tests follow the features the crate is built with.
+
+#[cfg(feature = "gated.Enabled")]
+#[test]
+fn compiles_the_enabled_services() {
+    use integration_tests::gated::gated::{enabled_client::EnabledClient, enabled_descriptor};
+
+    assert_eq!(enabled_descriptor::SERVICE.name, "gated.Enabled");
+    let _ = EnabledClient::<tonic::transport::Channel>::connect::<&'static str>;
+}
+
+#[cfg(feature = "gated.Disabled")]
+#[test]
+fn compiles_the_disabled_services_once_enabled() {
+    use integration_tests::gated::gated::{disabled_client::DisabledClient, disabled_descriptor};
+
+    assert_eq!(disabled_descriptor::SERVICE.name, "gated.Disabled");
+    let _ = DisabledClient::<tonic::transport::Channel>::connect::<&'static str>;
+}
+
+#[test]
+fn registers_the_enabled_services() {
+    use integration_tests::gated::REGISTRY;
+
+    let services = REGISTRY
+        .services()
+        .map(|service| service.name)
+        .collect::<Vec<_>>();
+
+    // The disabled services get no client, server or descriptor.
+    let mut expected = Vec::new();
+    if cfg!(feature = "gated.Enabled") {
+        expected.push("gated.Enabled");
+    }
+    if cfg!(feature = "gated.Disabled") {
+        expected.push("gated.Disabled");
+    }
+    assert_eq!(services, expected);
+}
+
+#[test]
+fn writes_the_features_of_the_manifest() {
+    let features = include_str!(concat!(env!("OUT_DIR"), "/gated-features.toml"));
+    assert_eq!(
+        features,
+        "# The features gating the packages and services generated by tonic-build.\n\
+         [features]\n\
+         \"gated\" = []\n\
+         \"gated.Disabled\" = [\"gated\"]\n\
+         \"gated.Enabled\" = [\"gated\"]\n"
+    );
+}
@@ -0,0 +1,101 @@
+use futures_util::{stream, StreamExt};
+use integration_tests::fields::{
+    users_client::UsersClient,
+    users_server::{Users, UsersServer},
+    GetUser, ListUsers, Role, User,
+};
+use std::{net::TcpListener, pin::Pin};
+use tonic::{transport::Server, Request, Response, Status};
+
+fn user(id: u64, name: &str, role: Role) -> User {
+    User {
+        id,
+        name: name.to_string(),
+        role: role as i32,
+        address: None,
+    }
+}
+
+fn users() -> Vec<User> {
+    vec![
+        user(1, "alice", Role::Admin),
+        user(2, "albert", Role::Member),
+        user(3, "bob", Role::Admin),
+    ]
+}
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Users for Svc {
+    async fn get(&self, request: Request<GetUser>) -> Result<Response<User>, Status> {
+        let id = request.into_inner().id;
+        users()
+            .into_iter()
+            .find(|user| user.id == id)
+            .map(Response::new)
+            .ok_or_else(|| Status::not_found(id.to_string()))
+    }
+
+    type ListStream = Pin<Box<dyn futures_core::Stream<Item = Result<User, Status>> + Send + Sync>>;
+
+    async fn list(
+        &self,
+        request: Request<ListUsers>,
+    ) -> Result<Response<Self::ListStream>, Status> {
+        let ListUsers {
+            prefix,
+            limit,
+            role,
+        } = request.into_inner();
+        let users = users()
+            .into_iter()
+            .filter(move |user| user.name.starts_with(&prefix) && user.role == role)
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .map(Ok);
+        Ok(Response::new(Box::pin(stream::iter(users))))
+    }
+
+    async fn update(&self, request: Request<User>) -> Result<Response<User>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+#[tokio::test]
+async fn methods_take_the_fields_of_their_request() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(UsersServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = UsersClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let res = client.get_with(3).await.unwrap();
+    assert_eq!(res.into_inner().name, "bob");
+
+    let names = |res: Response<tonic::Streaming<User>>| {
+        res.into_inner()
+            .map(|user| user.unwrap().name)
+            .collect::<Vec<_>>()
+    };
+
+    let res = client
+        .list_with("al", None, Role::Admin as i32)
+        .await
+        .unwrap();
+    assert_eq!(names(res).await, vec!["alice"]);
+
+    let res = client
+        .list_with(String::new(), Some(1), Role::Admin as i32)
+        .await
+        .unwrap();
+    assert_eq!(names(res).await, vec!["alice"]);
+}
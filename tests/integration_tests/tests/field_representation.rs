@@ -0,0 +1,52 @@
+use integration_tests::representation::{
+    archive_client::ArchiveClient,
+    archive_server::{Archive, ArchiveServer},
+    document::Body,
+    Document, Format, Metadata, Text,
+};
+use std::{collections::BTreeMap, net::TcpListener};
+use tonic::{transport::Server, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Archive for Svc {
+    async fn store(&self, request: Request<Document>) -> Result<Response<Document>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+#[tokio::test]
+async fn sends_configured_field_representations() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(ArchiveServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = ArchiveClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let mut labels = BTreeMap::new();
+    labels.insert("lang".to_string(), "en".to_string());
+    let document = Document {
+        metadata: Some(Box::new(Metadata {
+            title: "Notes".to_string(),
+            authors: vec!["ann".to_string()],
+        })),
+        labels,
+        body: Some(Body::Text(Box::new(Text {
+            content: "hello".to_string(),
+        }))),
+        format: Format::FormatPlain as i32,
+    };
+
+    let res = client.store(document.clone()).await.unwrap();
+    assert_eq!(res.into_inner(), document);
+}
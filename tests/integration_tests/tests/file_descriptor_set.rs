@@ -0,0 +1,27 @@
+use integration_tests::{pb, routing};
+use prost::Message;
+use prost_types::FileDescriptorSet;
+
+fn decode(buf: &[u8]) -> FileDescriptorSet {
+    FileDescriptorSet::decode(buf).unwrap()
+}
+
+#[test]
+fn packages_embed_their_descriptors() {
+    let set = decode(pb::FILE_DESCRIPTOR_SET);
+    assert_eq!(set.file.len(), 1);
+
+    let file = &set.file[0];
+    assert_eq!(file.name(), "test.proto");
+    assert_eq!(file.package(), "test");
+    assert_eq!(file.service[0].name(), "Test");
+    assert_eq!(file.service[0].method[0].name(), "Echo");
+}
+
+#[test]
+fn descriptors_are_written_to_the_given_path() {
+    let written = include_bytes!(concat!(env!("OUT_DIR"), "/routing.bin"));
+
+    assert_eq!(decode(written), decode(routing::FILE_DESCRIPTOR_SET));
+    assert_eq!(decode(written).file[0].name(), "routing.proto");
+}
@@ -0,0 +1,149 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    fmt,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+};
+use tonic::{
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+/// Records the frames traced, as `direction message`.
+#[derive(Clone, Default)]
+struct Frames(Arc<Mutex<Vec<String>>>);
+
+#[derive(Default)]
+struct Fields {
+    direction: String,
+    message: String,
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            "direction" => self.direction = format!("{:?}", value),
+            _ => {}
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "direction" {
+            self.direction = value.to_string();
+        }
+    }
+}
+
+impl Subscriber for Frames {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target().ends_with("frames")
+    }
+
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let frame = format!("{} {}", fields.direction, fields.message);
+        self.0.lock().unwrap().push(frame);
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+impl Frames {
+    fn sent_by_server(&self, frame: &str) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|f| f.starts_with("sent ") && f.contains(frame))
+    }
+}
+
+fn serve(mut server: Server) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        server
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    addr
+}
+
+async fn client(addr: SocketAddr) -> TestClient<Channel> {
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    TestClient::new(channel)
+}
+
+#[tokio::test]
+async fn advertises_the_configured_windows_and_frame_size() {
+    let frames = Frames::default();
+    let _guard = tracing::subscriber::set_default(frames.clone());
+
+    let addr = serve(
+        Server::builder()
+            .initial_stream_window_size(1 << 20)
+            .initial_connection_window_size(4 << 20)
+            .max_frame_size(1 << 16)
+            .tcp_recv_buffer_size(1 << 20),
+    );
+    let mut client = client(addr).await;
+    let data = vec![1; 1 << 20];
+    let response = client.echo(Payload { data: data.clone() }).await.unwrap();
+    assert_eq!(response.into_inner().data, data);
+
+    assert!(frames.sent_by_server("INITIAL_WINDOW_SIZE=1048576"));
+    assert!(frames.sent_by_server("MAX_FRAME_SIZE=65536"));
+    // The connection window is raised past its default of 65,535 right away.
+    assert!(frames.sent_by_server("WINDOW_UPDATE increment=4128769"));
+}
+
+#[tokio::test]
+async fn probes_the_bandwidth_with_an_adaptive_window() {
+    let frames = Frames::default();
+    let _guard = tracing::subscriber::set_default(frames.clone());
+
+    let addr = serve(Server::builder().http2_adaptive_window(true));
+    let mut client = client(addr).await;
+    let data = vec![1; 1 << 20];
+    let response = client.echo(Payload { data: data.clone() }).await.unwrap();
+    assert_eq!(response.into_inner().data, data);
+
+    // The client does not ping, so the pings come from the server measuring
+    // the bandwidth-delay product.
+    assert!(frames.sent_by_server("PING"));
+}
@@ -0,0 +1,49 @@
+use integration_tests::governance::{legacy_descriptor, FILE_DESCRIPTOR_SET};
+use prost::Message;
+use prost_types::{MethodOptions, ServiceOptions};
+
+/// The custom options of `governance.proto`.
+#[derive(Clone, PartialEq, Message)]
+struct Governance {
+    #[prost(string, tag = "50000")]
+    owner_or_team: String,
+}
+
+const GENERATED: &str = include_str!(concat!(env!("OUT_DIR"), "/governance.rs"));
+
+#[test]
+fn descriptors_keep_custom_options() {
+    let service = legacy_descriptor::SERVICE.options;
+    assert!(ServiceOptions::decode(service).unwrap().deprecated());
+    assert_eq!(
+        Governance::decode(service).unwrap().owner_or_team,
+        "storage"
+    );
+
+    let fetch = legacy_descriptor::FETCH.options;
+    assert!(MethodOptions::decode(fetch).unwrap().deprecated());
+    assert_eq!(Governance::decode(fetch).unwrap().owner_or_team, "alice");
+
+    assert!(legacy_descriptor::STORE.options.is_empty());
+
+    assert!(FILE_DESCRIPTOR_SET
+        .windows(fetch.len())
+        .any(|window| window == fetch));
+}
+
+#[test]
+fn deprecations_reach_generated_code() {
+    for deprecated in &[
+        "#[deprecated]\n    pub legacy_id:",
+        "#[deprecated]\n    pub struct LegacyClient<T, C = DefaultCodec>",
+        "#[deprecated]\n        pub async fn fetch(",
+        "#[deprecated]\n    #[async_trait]\n    pub trait Legacy:",
+        "#[deprecated]\n        async fn fetch(",
+    ] {
+        assert!(GENERATED.contains(deprecated), "{:?} not found", deprecated);
+    }
+    assert!(!GENERATED.contains("#[deprecated]\n        pub async fn store("));
+
+    assert!(GENERATED.contains("**Deprecated** in the `.proto` file.\"]\npub struct Archive"));
+    assert!(GENERATED.contains("**Deprecated** in the `.proto` file.\"]\n    Retired = 2,"));
+}
@@ -0,0 +1,110 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    fmt,
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+use tonic::{
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+/// Records the frames traced, as `direction message`.
+#[derive(Clone, Default)]
+struct Frames(Arc<Mutex<Vec<String>>>);
+
+#[derive(Default)]
+struct Fields {
+    direction: String,
+    message: String,
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            "direction" => self.direction = format!("{:?}", value),
+            _ => {}
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "direction" {
+            self.direction = value.to_string();
+        }
+    }
+}
+
+impl Subscriber for Frames {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target().ends_with("frames")
+    }
+
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let frame = format!("{} {}", fields.direction, fields.message);
+        self.0.lock().unwrap().push(frame);
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[tokio::test]
+async fn traces_the_control_frames_of_connections() {
+    let frames = Frames::default();
+    let _guard = tracing::subscriber::set_default(frames.clone());
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(channel);
+    client.echo(Payload { data: vec![1] }).await.unwrap();
+
+    let frames = frames.0.lock().unwrap();
+    let traced = |frame: &str| frames.iter().any(|f| f == frame);
+    // Both ends run here, so the frames sent by one are received by the other.
+    let settings = "SETTINGS ENABLE_PUSH=0 INITIAL_WINDOW_SIZE=2097152 MAX_FRAME_SIZE=16384";
+    assert!(traced(&format!("sent {}", settings)), "{:#?}", frames);
+    assert!(traced(&format!("received {}", settings)), "{:#?}", frames);
+    assert!(traced("sent SETTINGS ack"), "{:#?}", frames);
+    assert!(traced("received SETTINGS ack"), "{:#?}", frames);
+}
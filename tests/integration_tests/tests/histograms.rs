@@ -0,0 +1,69 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::net::TcpListener;
+use tonic::{
+    histograms::Histograms,
+    metrics::{MetricsLayer, Side},
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+use tower::layer::Layer;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+#[tokio::test]
+async fn keeps_the_histograms_of_methods() {
+    let histograms = Histograms::new();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let metrics = MetricsLayer::server(histograms.clone());
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(metrics.layer(TestServer::new(Svc)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let metrics = MetricsLayer::client(histograms.clone());
+    let mut client = TestClient::new(metrics.layer(channel));
+    for len in 1..=3 {
+        client.echo(Payload { data: vec![0; len] }).await.unwrap();
+    }
+
+    let snapshot = histograms.snapshot();
+    let sides = snapshot.iter().map(|m| m.side()).collect::<Vec<_>>();
+    assert_eq!(sides, vec![Side::Client, Side::Server]);
+    for method in &snapshot {
+        assert_eq!(method.service(), "test.Test");
+        assert_eq!(method.method(), "Echo");
+        assert_eq!(method.latency().count(), 3);
+        assert!(method.latency().percentile(0.99) <= method.latency().max());
+        // Each message is prefixed by 5 bytes, and its field by 2.
+        for sizes in &[method.request_size(), method.response_size()] {
+            assert_eq!(sizes.count(), 3);
+            assert_eq!(sizes.sum(), 8 + 9 + 10);
+            assert_eq!(sizes.percentile(0.5), 9);
+            assert_eq!(sizes.max(), 10);
+        }
+    }
+
+    histograms.reset();
+    assert!(histograms.snapshot().is_empty());
+}
@@ -0,0 +1,76 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tonic::{
+    transport::{server::IdempotencyLayer, Channel, Server},
+    Request, Response, Status,
+};
+use tower::layer::Layer;
+
+/// Answers the calls with their number.
+struct Svc {
+    calls: Arc<AtomicUsize>,
+}
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, _: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(Response::new(Payload {
+            data: call.to_string().into_bytes(),
+        }))
+    }
+}
+
+async fn echo(client: &mut TestClient<Channel>, key: Option<&str>) -> (String, bool) {
+    let mut request = Request::new(Payload::default());
+    if let Some(key) = key {
+        request
+            .metadata_mut()
+            .insert("idempotency-key", key.parse().unwrap());
+    }
+    let response = client.echo(request).await.unwrap();
+    let replayed = response.metadata().get("idempotency-replayed").is_some();
+    let data = String::from_utf8(response.into_inner().data).unwrap();
+    (data, replayed)
+}
+
+#[tokio::test]
+async fn answers_retries_with_the_first_response() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let svc = Svc {
+        calls: calls.clone(),
+    };
+    let idempotency = IdempotencyLayer::new(Duration::from_secs(60));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(idempotency.layer(TestServer::new(svc)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    assert_eq!(echo(&mut client, Some("a")).await, ("1".to_string(), false));
+    assert_eq!(echo(&mut client, Some("a")).await, ("1".to_string(), true));
+    assert_eq!(echo(&mut client, Some("b")).await, ("2".to_string(), false));
+    // The calls without a key are not deduplicated.
+    assert_eq!(echo(&mut client, None).await, ("3".to_string(), false));
+    assert_eq!(echo(&mut client, None).await, ("4".to_string(), false));
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
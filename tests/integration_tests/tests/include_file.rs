@@ -0,0 +1,38 @@
+use integration_tests::layout::layout::{
+    colors::Color,
+    shapes::{painter_client::PainterClient, Square},
+};
+
+#[test]
+fn nests_the_modules_of_the_packages() {
+    // `Square` refers to `Color` through `super::super::colors`.
+    let square = Square {
+        side: 2,
+        color: Some(Color {
+            name: "red".to_string(),
+        }),
+    };
+    assert_eq!(square.color.unwrap().name, "red");
+
+    // The clients are generated in the module of their package.
+    let _ = PainterClient::<tonic::transport::Channel>::connect::<&'static str>;
+}
+
+#[test]
+fn registers_the_packages_with_services() {
+    use integration_tests::layout::{layout::shapes::painter_descriptor, REGISTRY};
+
+    // `layout.colors` declares no service.
+    let packages = REGISTRY
+        .packages
+        .iter()
+        .map(|package| package.name)
+        .collect::<Vec<_>>();
+    assert_eq!(packages, vec!["layout.shapes"]);
+
+    assert_eq!(
+        REGISTRY.service("layout.shapes.Painter"),
+        Some(&painter_descriptor::SERVICE)
+    );
+    assert!(REGISTRY.file_descriptor_sets().all(|set| !set.is_empty()));
+}
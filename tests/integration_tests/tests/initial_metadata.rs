@@ -0,0 +1,78 @@
+use futures_util::stream;
+use integration_tests::stream::{
+    events_client::EventsClient,
+    events_server::{Events, EventsServer},
+    Event,
+};
+use std::{net::TcpListener, pin::Pin, sync::Mutex, time::Duration};
+use tokio::sync::oneshot;
+use tonic::{transport::Server, Request, Response, Status};
+
+struct Svc {
+    // Held back until the client read the initial metadata.
+    release: Mutex<Option<oneshot::Receiver<()>>>,
+}
+
+#[tonic::async_trait]
+impl Events for Svc {
+    type SubscribeStream =
+        Pin<Box<dyn futures_core::Stream<Item = Result<Event, Status>> + Send + Sync>>;
+
+    async fn subscribe(
+        &self,
+        request: Request<Event>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let release = self.release.lock().unwrap().take().unwrap();
+        let event = request.into_inner();
+
+        let events = stream::once(async move {
+            release.await.unwrap();
+            Ok(event)
+        });
+
+        let mut response = Response::new(Box::pin(events) as Self::SubscribeStream);
+        response
+            .metadata_mut()
+            .insert("session-token", "abc".parse().unwrap());
+        Ok(response)
+    }
+}
+
+#[tokio::test]
+async fn initial_metadata_before_first_message() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (release, released) = oneshot::channel();
+    let svc = Svc {
+        release: Mutex::new(Some(released)),
+    };
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(EventsServer::new(svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = EventsClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let request = Event {
+        name: "hello".to_string(),
+    };
+    let mut events = tokio::time::timeout(Duration::from_secs(5), client.subscribe(request))
+        .await
+        .expect("headers should arrive before the first message")
+        .unwrap()
+        .into_inner();
+
+    let metadata = events.initial_metadata().unwrap();
+    assert_eq!(metadata.get("session-token").unwrap(), "abc");
+
+    release.send(()).unwrap();
+    assert_eq!(events.message().await.unwrap().unwrap().name, "hello");
+    assert!(events.message().await.unwrap().is_none());
+}
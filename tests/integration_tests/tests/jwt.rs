@@ -0,0 +1,194 @@
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body,
+};
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use ring::{
+    rand::SystemRandom,
+    signature::{KeyPair, RsaKeyPair, RSA_PKCS1_SHA256},
+};
+use serde_json::{json, Value};
+use std::{
+    convert::Infallible,
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tonic::{
+    jwt::{Claims, JwtAuthLayer, KeySet, Validator},
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::layer::Layer;
+
+const KEY: &[u8] = include_bytes!("../data/service-account.json");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    // Echoes the subject of the token of the call.
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let claims = request.extensions().get::<Claims>().unwrap();
+        Ok(Response::new(Payload {
+            data: claims.subject().unwrap().as_bytes().to_vec(),
+        }))
+    }
+}
+
+fn key_pair() -> RsaKeyPair {
+    let pem = serde_json::from_slice::<Value>(KEY).unwrap()["private_key"]
+        .as_str()
+        .unwrap()
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect::<String>();
+    RsaKeyPair::from_pkcs8(&base64::decode(&pem).unwrap()).unwrap()
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+fn sign(key_pair: &RsaKeyPair, claims: Value) -> String {
+    let header = json!({"alg": "RS256", "typ": "JWT", "kid": "test-key"});
+    let message = format!(
+        "{}.{}",
+        encode(header.to_string().as_bytes()),
+        encode(claims.to_string().as_bytes())
+    );
+    let mut signature = vec![0; key_pair.public_modulus_len()];
+    key_pair
+        .sign(
+            &RSA_PKCS1_SHA256,
+            &SystemRandom::new(),
+            message.as_bytes(),
+            &mut signature,
+        )
+        .unwrap();
+    format!("{}.{}", message, encode(&signature))
+}
+
+/// Serves the key set of `key_pair`, counting the times it is fetched.
+async fn serve_jwks(key_pair: &RsaKeyPair, fetches: Arc<AtomicUsize>) -> Channel {
+    let public_key = key_pair.public_key();
+    let jwks = json!({"keys": [{
+        "kty": "RSA",
+        "kid": "test-key",
+        "use": "sig",
+        "n": encode(public_key.modulus().big_endian_without_leading_zero()),
+        "e": encode(public_key.exponent().big_endian_without_leading_zero()),
+    }]})
+    .to_string();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let make_service = make_service_fn(move |_| {
+            let jwks = jwks.clone();
+            let fetches = fetches.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: hyper::Request<Body>| {
+                    assert_eq!(request.uri().path(), "/jwks.json");
+                    fetches.fetch_add(1, Ordering::SeqCst);
+                    let jwks = jwks.clone();
+                    async move { Ok::<_, Infallible>(hyper::Response::new(Body::from(jwks))) }
+                }))
+            }
+        });
+        hyper::Server::from_tcp(listener)
+            .unwrap()
+            .http2_only(true)
+            .serve(make_service)
+            .await
+            .unwrap();
+    });
+
+    Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+async fn echo(client: &mut TestClient<Channel>, token: Option<&str>) -> Result<String, Status> {
+    let mut request = Request::new(Payload::default());
+    if let Some(token) = token {
+        request.metadata_mut().insert(
+            "authorization",
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+    }
+    let res = client.echo(request).await?;
+    Ok(String::from_utf8(res.into_inner().data).unwrap())
+}
+
+#[tokio::test]
+async fn validates_the_tokens_of_the_calls() {
+    let key_pair = key_pair();
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let issuer = serve_jwks(&key_pair, fetches.clone()).await;
+    let keys = KeySet::remote(issuer, "http://auth.example.com/jwks.json".parse().unwrap());
+    let auth = JwtAuthLayer::new(
+        Validator::new(keys)
+            .issuer("https://auth.example.com")
+            .audience("tests"),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(auth.layer(TestServer::new(Svc)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let claims = json!({
+        "iss": "https://auth.example.com",
+        "aud": "tests",
+        "sub": "alice",
+        "exp": now + 300,
+    });
+
+    let token = sign(&key_pair, claims.clone());
+    assert_eq!(echo(&mut client, Some(&token)).await.unwrap(), "alice");
+    assert_eq!(echo(&mut client, Some(&token)).await.unwrap(), "alice");
+    // The key set is cached.
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+    let status = echo(&mut client, None).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+    assert_eq!(status.message(), "missing bearer token");
+
+    let mut expired = claims.clone();
+    expired["exp"] = json!(now - 120);
+    let status = echo(&mut client, Some(&sign(&key_pair, expired)))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+    assert_eq!(status.message(), "invalid token: expired");
+
+    let mut other_audience = claims;
+    other_audience["aud"] = json!("billing");
+    let status = echo(&mut client, Some(&sign(&key_pair, other_audience)))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+    assert_eq!(status.message(), "invalid token: unexpected audience");
+}
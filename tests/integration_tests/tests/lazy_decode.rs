@@ -0,0 +1,62 @@
+use integration_tests::routing::{
+    router_client::RouterClient,
+    router_server::{Router, RouterServer},
+    Destination, Envelope,
+};
+use std::net::TcpListener;
+use tonic::{
+    codec::{Lazy, ProstDecoder},
+    transport::Server,
+    Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Router for Svc {
+    async fn route(&self, request: Request<Lazy<Envelope>>) -> Result<Response<Envelope>, Status> {
+        let envelope = request.into_inner();
+
+        let destination: Destination = envelope.decode_with(ProstDecoder::default())?;
+        if destination.destination != "here" {
+            return Err(Status::not_found(destination.destination));
+        }
+
+        Ok(Response::new(envelope.decode()?))
+    }
+}
+
+#[tokio::test]
+async fn routes_on_partially_decoded_requests() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(RouterServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = RouterClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let envelope = Envelope {
+        destination: "here".to_string(),
+        payload: vec![1; 1024],
+    };
+    let res = client.route(envelope.clone()).await.unwrap();
+    assert_eq!(res.into_inner(), envelope);
+
+    let status = client
+        .route(Envelope {
+            destination: "elsewhere".to_string(),
+            payload: vec![1; 1024],
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+    assert_eq!(status.message(), "elsewhere");
+}
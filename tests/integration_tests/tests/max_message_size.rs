@@ -0,0 +1,94 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::net::TcpListener;
+use tonic::{
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+async fn client() -> TestClient<Channel> {
+    client_of(TestServer::new(Svc)).await
+}
+
+async fn client_of(server: TestServer<Svc>) -> TestClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(server)
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+}
+
+fn payload(len: usize) -> Payload {
+    Payload { data: vec![0; len] }
+}
+
+#[tokio::test]
+async fn decoding_limit_defaults_to_4mib() {
+    let mut client = client().await;
+
+    client.echo(payload(1024 * 1024)).await.unwrap();
+
+    let status = client.echo(payload(5 * 1024 * 1024)).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+}
+
+#[tokio::test]
+async fn response_larger_than_decoding_limit() {
+    let mut client = client().await.max_decoding_message_size(100);
+
+    client.echo(payload(10)).await.unwrap();
+
+    let status = client.echo(payload(200)).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+}
+
+#[tokio::test]
+async fn request_larger_than_encoding_limit() {
+    let mut client = client().await.max_encoding_message_size(100);
+
+    client.echo(payload(10)).await.unwrap();
+
+    let status = client.echo(payload(200)).await.unwrap_err();
+    assert_eq!(status.code(), Code::OutOfRange);
+}
+
+#[tokio::test]
+async fn request_larger_than_server_decoding_limit() {
+    let mut client = client_of(TestServer::new(Svc).max_decoding_message_size(100)).await;
+
+    client.echo(payload(10)).await.unwrap();
+
+    let status = client.echo(payload(200)).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+}
+
+#[tokio::test]
+async fn response_larger_than_server_encoding_limit() {
+    let mut client = client_of(TestServer::new(Svc).max_encoding_message_size(100)).await;
+
+    client.echo(payload(10)).await.unwrap();
+
+    let status = client.echo(payload(200)).await.unwrap_err();
+    assert_eq!(status.code(), Code::OutOfRange);
+}
@@ -0,0 +1,71 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::net::TcpListener;
+use tonic::{
+    metadata::MetadataValue,
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+
+/// Replies with a `x-padding` response header as long as the `x-padding`
+/// request header.
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let padding = request.metadata().get("x-padding").cloned();
+        let mut response = Response::new(request.into_inner());
+        if let Some(padding) = padding {
+            response.metadata_mut().insert("x-padding", padding);
+        }
+        Ok(response)
+    }
+}
+
+async fn client(max_metadata_size: usize) -> TestClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc).max_metadata_size(max_metadata_size))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+}
+
+fn padded(len: usize) -> Request<Payload> {
+    let mut request = Request::new(Payload::default());
+    let padding = MetadataValue::from_str(&"x".repeat(len)).unwrap();
+    request.metadata_mut().insert("x-padding", padding);
+    request
+}
+
+#[tokio::test]
+async fn request_metadata_larger_than_limit() {
+    let mut client = client(1024).await;
+
+    client.echo(padded(100)).await.unwrap();
+
+    let status = client.echo(padded(2048)).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+}
+
+#[tokio::test]
+async fn response_metadata_larger_than_limit() {
+    let mut client = client(16 * 1024).await.max_metadata_size(1024);
+
+    client.echo(padded(100)).await.unwrap();
+
+    let status = client.echo(padded(2048)).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+}
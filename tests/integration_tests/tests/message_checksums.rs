@@ -0,0 +1,50 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::net::TcpListener;
+use tonic::{transport::Server, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        // The client sends the checksum in the request trailers.
+        if request.metadata().get("message-crc32c").is_none() {
+            return Err(Status::invalid_argument("missing checksum"));
+        }
+
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+#[tokio::test]
+async fn checksums_are_sent_and_validated() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc).message_checksums(true))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+        .message_checksums(true);
+
+    let res = client
+        .echo(Payload {
+            data: vec![1; 1024],
+        })
+        .await
+        .unwrap();
+
+    assert!(res.metadata().get("message-crc32c").is_some());
+    assert_eq!(res.into_inner().data, vec![1; 1024]);
+}
@@ -0,0 +1,90 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_descriptor,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+use tonic::{
+    body::BoxBody,
+    codegen::{http, Context, Poll, Service},
+    descriptor::MethodDescriptor,
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+
+/// Replies with the method the client tagged the request with.
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let method = request.metadata().get("x-method").unwrap();
+        Ok(Response::new(Payload {
+            data: method.as_bytes().to_vec(),
+        }))
+    }
+}
+
+fn tag(mut request: Request<()>) -> Result<Request<()>, Status> {
+    let method = request.method_descriptor().unwrap();
+    request
+        .metadata_mut()
+        .insert("x-method", method.name.parse().unwrap());
+    Ok(request)
+}
+
+/// Records the methods of the requests sent through it.
+#[derive(Clone)]
+struct Recorded {
+    inner: Channel,
+    methods: Arc<Mutex<Vec<&'static MethodDescriptor>>>,
+}
+
+impl Service<http::Request<BoxBody>> for Recorded {
+    type Response = <Channel as Service<http::Request<BoxBody>>>::Response;
+    type Error = <Channel as Service<http::Request<BoxBody>>>::Error;
+    type Future = <Channel as Service<http::Request<BoxBody>>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<BoxBody>) -> Self::Future {
+        let method = request.extensions().get::<&'static MethodDescriptor>();
+        self.methods.lock().unwrap().push(*method.unwrap());
+        self.inner.call(request)
+    }
+}
+
+#[tokio::test]
+async fn exposes_the_method_called_to_interceptors_and_layers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+
+    let methods = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Recorded {
+        inner: channel,
+        methods: methods.clone(),
+    };
+    let mut client = TestClient::with_interceptor(recorded, tag);
+
+    let response = client.echo(Payload { data: vec![] }).await.unwrap();
+    assert_eq!(response.into_inner().data, b"Echo");
+    assert_eq!(*methods.lock().unwrap(), vec![&test_descriptor::ECHO]);
+}
@@ -0,0 +1,91 @@
+use integration_tests::errors::{
+    orders_client::OrdersClient,
+    orders_errors::GetError,
+    orders_server::{Orders, OrdersServer},
+    GetOrder, NotFound, Order, OutOfStock,
+};
+use std::net::TcpListener;
+use tonic::{
+    error_details::{BadRequest, FieldViolation},
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Orders for Svc {
+    async fn get(&self, request: Request<GetOrder>) -> Result<Response<Order>, Status> {
+        let id = request.into_inner().id;
+        let error = match id.as_str() {
+            "" => GetError::BadRequest(BadRequest {
+                field_violations: vec![FieldViolation {
+                    field: "id".to_string(),
+                    description: "missing".to_string(),
+                }],
+            }),
+            "sold-out" => GetError::OutOfStock(OutOfStock { available: 0 }),
+            "broken" => GetError::Status(Status::internal("broken")),
+            _ => GetError::NotFound(NotFound { id }),
+        };
+        Err(error.into())
+    }
+
+    async fn cancel(&self, _: Request<GetOrder>) -> Result<Response<Order>, Status> {
+        Err(Status::unimplemented("cancel"))
+    }
+}
+
+async fn get(client: &mut OrdersClient<Channel>, id: &str) -> GetError {
+    let request = GetOrder { id: id.to_string() };
+    GetError::from(client.get(request).await.unwrap_err())
+}
+
+#[tokio::test]
+async fn converts_statuses_into_typed_errors() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(OrdersServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = OrdersClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+    match get(&mut client, "42").await {
+        GetError::NotFound(not_found) => assert_eq!(not_found.id, "42"),
+        error => panic!("unexpected error: {:?}", error),
+    }
+    match get(&mut client, "sold-out").await {
+        GetError::OutOfStock(out_of_stock) => assert_eq!(out_of_stock.available, 0),
+        error => panic!("unexpected error: {:?}", error),
+    }
+    match get(&mut client, "").await {
+        GetError::BadRequest(bad_request) => {
+            assert_eq!(bad_request.field_violations[0].field, "id")
+        }
+        error => panic!("unexpected error: {:?}", error),
+    }
+    match get(&mut client, "broken").await {
+        GetError::Status(status) => assert_eq!(status.code(), Code::Internal),
+        error => panic!("unexpected error: {:?}", error),
+    }
+}
+
+#[test]
+fn sends_typed_errors_with_their_code() {
+    let status = Status::from(GetError::NotFound(NotFound {
+        id: "42".to_string(),
+    }));
+    assert_eq!(status.code(), Code::NotFound);
+    assert_eq!(status.message(), "errors.NotFound");
+
+    let error = GetError::OutOfStock(OutOfStock { available: 3 });
+    assert_eq!(error.to_string(), "errors.OutOfStock");
+    assert_eq!(Status::from(error).code(), Code::FailedPrecondition);
+}
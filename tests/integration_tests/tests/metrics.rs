@@ -0,0 +1,126 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tonic::{
+    metrics::{CallInfo, MetricsLayer, MetricsRecorder, Side},
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::layer::Layer;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let payload = request.into_inner();
+        if payload.data.is_empty() {
+            return Err(Status::invalid_argument("empty payload"));
+        }
+        Ok(Response::new(payload))
+    }
+}
+
+#[derive(Default)]
+struct Events(Mutex<Vec<String>>);
+
+impl Events {
+    fn push(&self, call: &CallInfo, event: String) {
+        let side = match call.side() {
+            Side::Client => "client",
+            Side::Server => "server",
+        };
+        let event = format!("{} {}/{} {}", side, call.service(), call.method(), event);
+        self.0.lock().unwrap().push(event);
+    }
+
+    fn take(&self, side: &str) -> Vec<String> {
+        let mut events = self.0.lock().unwrap();
+        let (taken, rest) = events.drain(..).partition(|e| e.starts_with(side));
+        *events = rest;
+        taken
+    }
+}
+
+impl MetricsRecorder for Events {
+    fn call_started(&self, call: &CallInfo) {
+        self.push(call, "started".to_string());
+    }
+
+    fn call_completed(&self, call: &CallInfo, code: Code, _: Duration) {
+        self.push(call, format!("completed {:?}", code));
+    }
+
+    fn message_sent(&self, call: &CallInfo, size: usize) {
+        self.push(call, format!("sent {}", size));
+    }
+
+    fn message_received(&self, call: &CallInfo, size: usize) {
+        self.push(call, format!("received {}", size));
+    }
+}
+
+#[tokio::test]
+async fn records_the_calls_of_clients_and_servers() {
+    let events = Arc::new(Events::default());
+    let server_metrics = MetricsLayer::server(events.clone());
+    let client_metrics = MetricsLayer::client(events.clone());
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(server_metrics.layer(TestServer::new(Svc)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(client_metrics.layer(channel));
+
+    let payload = Payload {
+        data: b"hello".to_vec(),
+    };
+    client.echo(payload).await.unwrap();
+    // A message of 5 bytes is sent as 12, with its prefix and tag.
+    assert_eq!(
+        events.take("client"),
+        vec![
+            "client test.Test/Echo started",
+            "client test.Test/Echo sent 12",
+            "client test.Test/Echo received 12",
+            "client test.Test/Echo completed Ok",
+        ]
+    );
+    assert_eq!(
+        events.take("server"),
+        vec![
+            "server test.Test/Echo started",
+            "server test.Test/Echo received 12",
+            "server test.Test/Echo sent 12",
+            "server test.Test/Echo completed Ok",
+        ]
+    );
+
+    let status = client.echo(Payload::default()).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(
+        events.take("client"),
+        vec![
+            "client test.Test/Echo started",
+            "client test.Test/Echo sent 5",
+            "client test.Test/Echo completed InvalidArgument",
+        ]
+    );
+}
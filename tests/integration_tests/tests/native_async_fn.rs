@@ -0,0 +1,69 @@
+use futures_util::{stream, StreamExt, TryStreamExt};
+use integration_tests::native::{
+    counter_client::CounterClient,
+    counter_server::{Counter, CounterServer},
+    Number,
+};
+use std::{net::TcpListener, pin::Pin};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+struct Svc;
+
+// Implemented with plain `async fn`s, without `#[tonic::async_trait]`.
+impl Counter for Svc {
+    async fn add(&self, request: Request<Number>) -> Result<Response<Number>, Status> {
+        let value = request.into_inner().value + 1;
+        Ok(Response::new(Number { value }))
+    }
+
+    type CountStream =
+        Pin<Box<dyn futures_core::Stream<Item = Result<Number, Status>> + Send + Sync>>;
+
+    async fn count(&self, request: Request<Number>) -> Result<Response<Self::CountStream>, Status> {
+        let numbers = (0..request.into_inner().value).map(|value| Ok(Number { value }));
+        Ok(Response::new(Box::pin(stream::iter(numbers))))
+    }
+
+    async fn sum(&self, request: Request<Streaming<Number>>) -> Result<Response<Number>, Status> {
+        let value = request
+            .into_inner()
+            .try_fold(0, |sum, number| async move { Ok(sum + number.value) })
+            .await?;
+        Ok(Response::new(Number { value }))
+    }
+}
+
+#[tokio::test]
+async fn serves_native_async_fn_traits() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(CounterServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = CounterClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let res = client.add(Number { value: 1 }).await.unwrap();
+    assert_eq!(res.into_inner().value, 2);
+
+    let counted = client
+        .count(Number { value: 3 })
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|number| number.unwrap().value)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(counted, vec![0, 1, 2]);
+
+    let numbers = (1..=4).map(|value| Number { value });
+    let res = client.sum(stream::iter(numbers)).await.unwrap();
+    assert_eq!(res.into_inner().value, 10);
+}
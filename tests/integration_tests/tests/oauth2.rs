@@ -0,0 +1,212 @@
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body,
+};
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use ring::signature::{self, KeyPair, RsaKeyPair};
+use serde_json::Value;
+use std::{
+    convert::Infallible,
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+use tonic::{
+    oauth2::{ClientCredentials, ServiceAccount, ServiceAccountKey, TokenCredentials},
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+
+const KEY: &[u8] = include_bytes!("../data/service-account.json");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    // Echoes the authorization of the call.
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let authorization = request.metadata().get("authorization").unwrap();
+        Ok(Response::new(Payload {
+            data: authorization.as_bytes().to_vec(),
+        }))
+    }
+}
+
+/// Records the forms posted to the token endpoint, issuing `token-{n}` for
+/// the `n`th one, unless the client secret is wrong.
+#[derive(Clone, Default)]
+struct TokenEndpoint(Arc<Mutex<Vec<String>>>);
+
+impl TokenEndpoint {
+    async fn issue(
+        self,
+        request: hyper::Request<Body>,
+    ) -> Result<hyper::Response<Body>, Infallible> {
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.uri().path(), "/token");
+        assert_eq!(
+            request.headers()["content-type"],
+            "application/x-www-form-urlencoded"
+        );
+
+        let form = hyper::body::to_bytes(request.into_body()).await.unwrap();
+        let form = String::from_utf8(form.to_vec()).unwrap();
+        let mut forms = self.0.lock().unwrap();
+        let response = if form.contains("client_secret=wrong") {
+            hyper::Response::builder()
+                .status(401)
+                .body(Body::from(r#"{"error":"invalid_client"}"#))
+        } else {
+            hyper::Response::builder().body(Body::from(format!(
+                r#"{{"access_token":"token-{}","expires_in":3599,"token_type":"Bearer"}}"#,
+                forms.len()
+            )))
+        };
+        forms.push(form);
+        Ok(response.unwrap())
+    }
+}
+
+/// The channels to a gRPC and a token server, the latter recording the
+/// token requests.
+async fn servers() -> (Channel, Channel, TokenEndpoint) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let endpoint = TokenEndpoint::default();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let token_addr = listener.local_addr().unwrap();
+    let issuer = endpoint.clone();
+    tokio::spawn(async move {
+        let make_service = make_service_fn(move |_| {
+            let issuer = issuer.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |request| issuer.clone().issue(request))) }
+        });
+        hyper::Server::from_tcp(listener)
+            .unwrap()
+            .http2_only(true)
+            .serve(make_service)
+            .await
+            .unwrap();
+    });
+
+    let connect = |addr| async move {
+        Channel::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap()
+    };
+    (connect(addr).await, connect(token_addr).await, endpoint)
+}
+
+async fn echo(client: &mut TestClient<Channel>) -> Result<String, Status> {
+    let res = client.echo(Payload::default()).await?;
+    Ok(String::from_utf8(res.into_inner().data).unwrap())
+}
+
+fn decode(part: &str) -> Value {
+    let json = base64::decode_config(part, base64::URL_SAFE_NO_PAD).unwrap();
+    serde_json::from_slice(&json).unwrap()
+}
+
+#[tokio::test]
+async fn exchanges_signed_assertions_of_service_accounts() {
+    let (channel, oauth2, endpoint) = servers().await;
+    let key = ServiceAccountKey::from_json(KEY).unwrap();
+    assert_eq!(
+        key.client_email(),
+        "tests@tonic-tests.iam.gserviceaccount.com"
+    );
+
+    let credentials = TokenCredentials::new(
+        ServiceAccount::new(oauth2, key)
+            .scopes(vec!["https://www.googleapis.com/auth/pubsub", "openid"])
+            .subject("user@example.com"),
+    );
+    let mut client = TestClient::new(channel).call_credentials(credentials);
+
+    // The token is cached until it is about to expire.
+    assert_eq!(echo(&mut client).await.unwrap(), "Bearer token-0");
+    assert_eq!(echo(&mut client).await.unwrap(), "Bearer token-0");
+
+    let forms = endpoint.0.lock().unwrap();
+    assert_eq!(forms.len(), 1);
+    let assertion = forms[0]
+        .strip_prefix("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer&assertion=")
+        .unwrap();
+    let parts = assertion.split('.').collect::<Vec<_>>();
+
+    let header = decode(parts[0]);
+    assert_eq!(header["alg"], "RS256");
+    assert_eq!(header["kid"], "test-key");
+    let claims = decode(parts[1]);
+    assert_eq!(claims["iss"], "tests@tonic-tests.iam.gserviceaccount.com");
+    assert_eq!(claims["sub"], "user@example.com");
+    assert_eq!(claims["aud"], "https://oauth2.googleapis.com/token");
+    assert_eq!(
+        claims["scope"],
+        "https://www.googleapis.com/auth/pubsub openid"
+    );
+    assert_eq!(
+        claims["exp"].as_u64().unwrap() - claims["iat"].as_u64().unwrap(),
+        3600
+    );
+
+    let pem = serde_json::from_slice::<Value>(KEY).unwrap()["private_key"]
+        .as_str()
+        .unwrap()
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect::<String>();
+    let key_pair = RsaKeyPair::from_pkcs8(&base64::decode(&pem).unwrap()).unwrap();
+    let public_key = signature::UnparsedPublicKey::new(
+        &signature::RSA_PKCS1_2048_8192_SHA256,
+        key_pair.public_key().as_ref(),
+    );
+    let signature = base64::decode_config(parts[2], base64::URL_SAFE_NO_PAD).unwrap();
+    let message = format!("{}.{}", parts[0], parts[1]);
+    public_key.verify(message.as_bytes(), &signature).unwrap();
+}
+
+#[tokio::test]
+async fn requests_tokens_with_client_credentials() {
+    let (channel, oauth2, endpoint) = servers().await;
+    let token_uri = "http://auth.example.com/token".parse().unwrap();
+
+    let credentials = TokenCredentials::new(
+        ClientCredentials::new(oauth2.clone(), token_uri, "tonic", "s3cr&t").scopes(vec!["read"]),
+    );
+    let mut client = TestClient::new(channel.clone()).call_credentials(credentials);
+    assert_eq!(echo(&mut client).await.unwrap(), "Bearer token-0");
+
+    // Rejected credentials fail the calls.
+    let token_uri = "http://auth.example.com/token".parse().unwrap();
+    let credentials =
+        TokenCredentials::new(ClientCredentials::new(oauth2, token_uri, "tonic", "wrong"));
+    let mut client = TestClient::new(channel).call_credentials(credentials);
+    let status = echo(&mut client).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+    assert_eq!(
+        status.message(),
+        "access token request failed: invalid_client"
+    );
+
+    assert_eq!(
+        *endpoint.0.lock().unwrap(),
+        vec![
+            "grant_type=client_credentials&client_id=tonic&client_secret=s3cr%26t&scope=read",
+            "grant_type=client_credentials&client_id=tonic&client_secret=wrong",
+        ]
+    );
+}
@@ -0,0 +1,38 @@
+use integration_tests::optional::{settings::Nested, Level, Settings};
+use prost::Message;
+
+fn encode(settings: &Settings) -> Vec<u8> {
+    let mut buf = Vec::new();
+    settings.encode(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn unset_fields_are_not_encoded() {
+    let settings = Settings::default();
+    assert_eq!(settings.volume, None);
+    assert_eq!(settings.name, None);
+    assert_eq!(settings.key, None);
+    assert_eq!(settings.level, None);
+    assert_eq!(settings.nested, None);
+
+    assert!(encode(&settings).is_empty());
+}
+
+#[test]
+fn default_values_keep_their_presence() {
+    let settings = Settings {
+        volume: Some(0),
+        name: Some(String::new()),
+        key: Some(Vec::new()),
+        level: Some(Level::Low as i32),
+        nested: Some(Nested {
+            enabled: Some(false),
+        }),
+        plain: 0,
+    };
+
+    let buf = encode(&settings);
+    assert!(!buf.is_empty());
+    assert_eq!(Settings::decode(&buf[..]).unwrap(), settings);
+}
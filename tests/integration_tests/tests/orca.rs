@@ -0,0 +1,112 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tonic::{
+    orca::{
+        OrcaClient, OrcaLayer, OrcaLoadReport, OrcaLoadReportRequest, OrcaRecorder, OrcaService,
+    },
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+use tower::layer::Layer;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let recorder = OrcaRecorder::from_request(&request).unwrap();
+        recorder.set_request_cost("bytes", request.get_ref().data.len() as f64);
+        if request.get_ref().data.is_empty() {
+            recorder.set_cpu_utilization(0.9);
+        }
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+async fn serve(server_metrics: OrcaRecorder) -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let orca = OrcaLayer::server().server_metrics(server_metrics.clone());
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(orca.layer(TestServer::new(Svc)))
+            .add_service(
+                OrcaService::new(server_metrics).min_report_interval(Duration::from_millis(10)),
+            )
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn sends_the_load_reports_of_calls_in_their_trailers() {
+    let server_metrics = OrcaRecorder::new();
+    server_metrics.set_cpu_utilization(0.5);
+    server_metrics.set_utilization("disk", 0.25);
+    let channel = serve(server_metrics).await;
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let listener = received.clone();
+    let orca = OrcaLayer::client(move |report: &OrcaLoadReport| {
+        listener.lock().unwrap().push(report.clone());
+    });
+    let mut client = TestClient::new(orca.layer(channel));
+
+    let response = client.echo(Payload { data: vec![1, 2] }).await.unwrap();
+    let report = OrcaLoadReport::from_metadata(response.trailers()).unwrap();
+    assert_eq!(report.cpu_utilization, 0.5);
+    assert_eq!(report.utilization["disk"], 0.25);
+    assert_eq!(report.request_cost["bytes"], 2.0);
+
+    // The metrics of calls take precedence over the ones of their server.
+    let response = client.echo(Payload { data: vec![] }).await.unwrap();
+    let report = OrcaLoadReport::from_metadata(response.trailers()).unwrap();
+    assert_eq!(report.cpu_utilization, 0.9);
+    assert_eq!(report.request_cost["bytes"], 0.0);
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[1], report);
+}
+
+#[tokio::test]
+async fn streams_the_load_reports_of_servers() {
+    let server_metrics = OrcaRecorder::new();
+    server_metrics.set_cpu_utilization(0.5);
+    server_metrics.set_request_cost("queries", 3.0);
+    server_metrics.set_request_cost("bytes", 8.0);
+    let channel = serve(server_metrics.clone()).await;
+
+    let mut client = OrcaClient::new(channel);
+    let mut reports = client
+        .stream_core_metrics(OrcaLoadReportRequest {
+            report_interval: Some(Duration::from_millis(1).into()),
+            request_cost_names: vec!["queries".to_string()],
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    let report = reports.message().await.unwrap().unwrap();
+    assert_eq!(report.cpu_utilization, 0.5);
+    assert_eq!(report.request_cost.len(), 1);
+    assert_eq!(report.request_cost["queries"], 3.0);
+
+    server_metrics.set_cpu_utilization(0.75);
+    let report = reports.message().await.unwrap().unwrap();
+    assert_eq!(report.cpu_utilization, 0.75);
+}
@@ -0,0 +1,119 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+use tonic::{
+    metrics::MetricsLayer,
+    otel::{Instrument, KeyValue, Meter, OtelRecorder, Value},
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+use tower::layer::Layer;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let payload = request.into_inner();
+        if payload.data.is_empty() {
+            return Err(Status::invalid_argument("empty payload"));
+        }
+        Ok(Response::new(payload))
+    }
+}
+
+/// The values recorded, with their attributes as `key=value`.
+#[derive(Default)]
+struct Recorded(Mutex<Vec<(Instrument, f64, Vec<String>)>>);
+
+impl Recorded {
+    fn take(&self, instrument: Instrument) -> Vec<(f64, Vec<String>)> {
+        let mut recorded = self.0.lock().unwrap();
+        let (taken, rest) = recorded.drain(..).partition(|(i, _, _)| *i == instrument);
+        *recorded = rest;
+        taken.into_iter().map(|(_, v, a)| (v, a)).collect()
+    }
+}
+
+impl Meter for Recorded {
+    fn record(&self, instrument: Instrument, value: f64, attributes: &[KeyValue<'_>]) {
+        let attributes = attributes
+            .iter()
+            .map(|attribute| match attribute.value {
+                Value::String(value) => format!("{}={}", attribute.key, value),
+                Value::I64(value) => format!("{}={}", attribute.key, value),
+            })
+            .collect();
+        self.0.lock().unwrap().push((instrument, value, attributes));
+    }
+}
+
+fn attributes(code: Option<i64>) -> Vec<String> {
+    let mut attributes = vec![
+        "rpc.system=grpc".to_string(),
+        "rpc.service=test.Test".to_string(),
+        "rpc.method=Echo".to_string(),
+    ];
+    if let Some(code) = code {
+        attributes.push(format!("rpc.grpc.status_code={}", code));
+    }
+    attributes
+}
+
+#[tokio::test]
+async fn records_the_semantic_convention_metrics() {
+    let recorded = Arc::new(Recorded::default());
+    let server_metrics = MetricsLayer::server(OtelRecorder::new(recorded.clone()));
+    let client_metrics = MetricsLayer::client(OtelRecorder::new(recorded.clone()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(server_metrics.layer(TestServer::new(Svc)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(client_metrics.layer(channel));
+
+    client
+        .echo(Payload {
+            data: vec![1, 2, 3],
+        })
+        .await
+        .unwrap();
+    client.echo(Payload { data: vec![] }).await.unwrap_err();
+
+    for instrument in &[Instrument::ClientRequestSize, Instrument::ServerRequestSize] {
+        let sizes = recorded.take(*instrument);
+        assert_eq!(
+            sizes,
+            vec![(10.0, attributes(None)), (5.0, attributes(None))]
+        );
+    }
+    for instrument in &[
+        Instrument::ClientResponseSize,
+        Instrument::ServerResponseSize,
+    ] {
+        assert_eq!(recorded.take(*instrument), vec![(10.0, attributes(None))]);
+    }
+    for instrument in &[Instrument::ClientDuration, Instrument::ServerDuration] {
+        let durations = recorded.take(*instrument);
+        let codes = durations.iter().map(|(_, a)| a.clone()).collect::<Vec<_>>();
+        assert_eq!(codes, vec![attributes(Some(0)), attributes(Some(3))]);
+        assert!(durations.iter().all(|(millis, _)| *millis >= 0.0));
+    }
+    assert!(recorded.0.lock().unwrap().is_empty());
+}
@@ -0,0 +1,46 @@
+use integration_tests::versioned::{
+    counter_client::CounterClient,
+    counter_server::{Counter, CounterServer},
+    Amount, ProstCodec,
+};
+use std::net::TcpListener;
+use tonic::{codec::Codec, transport::Server, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Counter for Svc {
+    async fn add(&self, request: Request<Amount>) -> Result<Response<Amount>, Status> {
+        let value = request.into_inner().value + 1;
+        Ok(Response::new(Amount { value }))
+    }
+
+    async fn reset(&self, request: Request<()>) -> Result<Response<()>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+#[tokio::test]
+async fn encodes_with_the_prost_of_the_crate() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(CounterServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = CounterClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let res = client.add(Amount { value: 41 }).await.unwrap();
+    assert_eq!(res.into_inner().value, 42);
+    client.reset(()).await.unwrap();
+
+    // The package gets a codec of its own.
+    let _ = ProstCodec::<Amount, Amount>::default().encoder();
+}
@@ -0,0 +1,61 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{net::TcpListener, time::Duration};
+use tonic::{
+    transport::{
+        server::{Quota, RateLimitKey, RateLimitLayer},
+        Channel, Server,
+    },
+    Code, Request, Response, Status,
+};
+use tower::layer::Layer;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+async fn echo(client: &mut TestClient<Channel>, api_key: &str) -> Result<(), Status> {
+    let mut request = Request::new(Payload::default());
+    request
+        .metadata_mut()
+        .insert("x-api-key", api_key.parse().unwrap());
+    client.echo(request).await.map(drop)
+}
+
+#[tokio::test]
+async fn rejects_the_calls_over_the_quota_of_their_key() {
+    let limit = RateLimitLayer::new(Quota::per_second(100))
+        .method("/test.Test/Echo", Quota::per_minute(2))
+        .key(RateLimitKey::Metadata("x-api-key".to_string()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(limit.layer(TestServer::new(Svc)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    echo(&mut client, "a").await.unwrap();
+    echo(&mut client, "a").await.unwrap();
+    let status = echo(&mut client, "a").await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    let delay = status.retry_delay().unwrap();
+    assert!(delay > Duration::from_secs(29) && delay <= Duration::from_secs(30));
+
+    // Each key has its own quota.
+    echo(&mut client, "b").await.unwrap();
+}
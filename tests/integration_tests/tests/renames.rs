@@ -0,0 +1,60 @@
+use integration_tests::renames::{
+    directory_client::DirectoryClient,
+    directory_descriptor,
+    directory_server::{Directory, DirectoryServer},
+    Entry,
+};
+use std::net::TcpListener;
+use tonic::{transport::Server, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Directory for Svc {
+    async fn create(&self, request: Request<Entry>) -> Result<Response<Entry>, Status> {
+        let name = format!("created {}", request.into_inner().name);
+        Ok(Response::new(Entry { name }))
+    }
+
+    async fn link(&self, request: Request<Entry>) -> Result<Response<Entry>, Status> {
+        let name = format!("linked {}", request.into_inner().name);
+        Ok(Response::new(Entry { name }))
+    }
+
+    // Escaped as a raw identifier.
+    async fn r#type(&self, request: Request<Entry>) -> Result<Response<Entry>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+#[tokio::test]
+async fn renames_services_and_methods() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(DirectoryServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = DirectoryClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let entry = || Entry {
+        name: "a".to_string(),
+    };
+    let res = client.create(entry()).await.unwrap();
+    assert_eq!(res.into_inner().name, "created a");
+    let res = client.link(entry()).await.unwrap();
+    assert_eq!(res.into_inner().name, "linked a");
+    let res = client.r#type(entry()).await.unwrap();
+    assert_eq!(res.into_inner().name, "a");
+
+    // The gRPC paths keep the proto names.
+    assert_eq!(directory_descriptor::SERVICE.name, "renames.Registry");
+    assert_eq!(directory_descriptor::NEW.path, "/renames.Registry/New");
+}
@@ -0,0 +1,90 @@
+use futures_util::{future, SinkExt, StreamExt};
+use integration_tests::channels::{
+    accumulator_client::AccumulatorClient,
+    accumulator_server::{Accumulator, AccumulatorServer},
+    Number,
+};
+use std::{net::TcpListener, pin::Pin};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Accumulator for Svc {
+    async fn sum(&self, request: Request<Number>) -> Result<Response<Number>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+
+    type RunningSumStream =
+        Pin<Box<dyn futures_core::Stream<Item = Result<Number, Status>> + Send + Sync>>;
+
+    async fn running_sum(
+        &self,
+        request: Request<Streaming<Number>>,
+    ) -> Result<Response<Self::RunningSumStream>, Status> {
+        let sums = request.into_inner().scan(0, |sum, number| {
+            future::ready(Some(number.map(|number| {
+                *sum += number.value;
+                Number { value: *sum }
+            })))
+        });
+        Ok(Response::new(Box::pin(sums)))
+    }
+
+    async fn total(&self, request: Request<Streaming<Number>>) -> Result<Response<Number>, Status> {
+        let mut numbers = request.into_inner();
+        let mut value = 0;
+        while let Some(number) = numbers.next().await {
+            value += number?.value;
+        }
+        Ok(Response::new(Number { value }))
+    }
+}
+
+async fn client() -> AccumulatorClient<tonic::transport::Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(AccumulatorServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    AccumulatorClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn sends_client_streaming_requests_to_a_sink() {
+    let mut client = client().await;
+
+    let (mut sink, response) = client.total_channel();
+    let send = async move {
+        for value in 1..=3 {
+            sink.send(Number { value }).await.unwrap();
+        }
+    };
+    let (_, response) = future::join(send, response).await;
+    assert_eq!(response.unwrap().into_inner().value, 6);
+}
+
+#[tokio::test]
+async fn pairs_a_sink_with_the_streamed_responses() {
+    let mut client = client().await;
+
+    let (mut sink, response) = client.running_sum_channel();
+    let (_, response) = future::join(sink.send(Number { value: 1 }), response).await;
+    let mut sums = response.unwrap().into_inner();
+    assert_eq!(sums.message().await.unwrap().unwrap().value, 1);
+
+    // Through the `Sink` impl.
+    SinkExt::send(&mut sink, Number { value: 2 }).await.unwrap();
+    assert_eq!(sums.message().await.unwrap().unwrap().value, 3);
+
+    drop(sink);
+    assert!(sums.message().await.unwrap().is_none());
+}
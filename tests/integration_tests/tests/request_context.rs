@@ -0,0 +1,120 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+use tonic::{
+    body::BoxBody,
+    codegen::{http, BoxFuture, Context, HyperBody, Poll, Service},
+    transport::{NamedService, Server},
+    Request, RequestContext, Response, Status,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+struct RequestId(String);
+
+#[derive(Clone, Debug, PartialEq)]
+struct Principal(String);
+
+/// Replies with the request id and principal found in the context.
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let context = request.context().unwrap();
+        let RequestId(id) = context.get().unwrap();
+        let Principal(principal) = context.get().unwrap();
+
+        Ok(Response::new(Payload {
+            data: format!("{} {}", id, principal).into_bytes(),
+        }))
+    }
+}
+
+fn authenticate(request: Request<()>) -> Result<Request<()>, Status> {
+    request
+        .context()
+        .unwrap()
+        .insert(Principal("alice".to_string()));
+    Ok(request)
+}
+
+/// Middleware tagging requests with an id, and recording the principal
+/// found in the context of their response.
+#[derive(Clone)]
+struct Tagged<S> {
+    inner: S,
+    principals: Arc<Mutex<Vec<Principal>>>,
+}
+
+impl<S> Service<http::Request<HyperBody>> for Tagged<S>
+where
+    S: Service<http::Request<HyperBody>, Response = http::Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<HyperBody>) -> Self::Future {
+        let context = RequestContext::new();
+        context.insert(RequestId("42".to_string()));
+        request.extensions_mut().insert(context);
+
+        let principals = self.principals.clone();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            let context = response.extensions().get::<RequestContext>().unwrap();
+            principals
+                .lock()
+                .unwrap()
+                .extend(context.get::<Principal>());
+            Ok(response)
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for Tagged<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+#[tokio::test]
+async fn context_reaches_handler_and_response() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let principals = Arc::new(Mutex::new(Vec::new()));
+    let svc = Tagged {
+        inner: TestServer::with_interceptor(Svc, authenticate),
+        principals: principals.clone(),
+    };
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let response = client.echo(Payload::default()).await.unwrap();
+    assert_eq!(response.into_inner().data, b"42 alice");
+    assert_eq!(
+        *principals.lock().unwrap(),
+        vec![Principal("alice".to_string())]
+    );
+}
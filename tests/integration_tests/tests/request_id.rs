@@ -0,0 +1,103 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::net::{SocketAddr, TcpListener};
+use tonic::{
+    request_id::{self, RequestId, RequestIdLayer},
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+use tower::layer::Layer;
+
+/// Echoes the request ID of the calls, or forwards them to a backend.
+struct Svc {
+    backend: Option<TestClient<Channel>>,
+}
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let id = request.extensions().get::<RequestId>().unwrap().clone();
+        assert_eq!(request_id::current(), Some(id.clone()));
+        assert_eq!(
+            RequestId::from_metadata(request.metadata()),
+            Some(id.clone())
+        );
+
+        match &self.backend {
+            Some(backend) => backend.clone().echo(Payload::default()).await,
+            None => Ok(Response::new(Payload {
+                data: id.as_str().as_bytes().to_vec(),
+            })),
+        }
+    }
+}
+
+fn serve(svc: Svc, layer: RequestIdLayer) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(layer.layer(TestServer::new(svc)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    addr
+}
+
+async fn client(addr: SocketAddr) -> TestClient<Channel> {
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    TestClient::with_interceptor(channel, request_id::propagate())
+}
+
+fn request(id: &str) -> Request<Payload> {
+    let mut request = Request::new(Payload::default());
+    request
+        .metadata_mut()
+        .insert("x-request-id", id.parse().unwrap());
+    request
+}
+
+#[tokio::test]
+async fn propagates_and_echoes_the_request_id() {
+    let backend = serve(Svc { backend: None }, RequestIdLayer::new());
+    let frontend = serve(
+        Svc {
+            backend: Some(client(backend).await),
+        },
+        RequestIdLayer::new(),
+    );
+    let mut client = client(frontend).await;
+
+    let response = client.echo(request("order-42")).await.unwrap();
+    let echoed = RequestId::from_metadata(response.metadata()).unwrap();
+    assert_eq!(echoed.as_str(), "order-42");
+    assert_eq!(response.into_inner().data, b"order-42");
+
+    // Calls without an ID are given one.
+    let response = client.echo(Payload::default()).await.unwrap();
+    let echoed = RequestId::from_metadata(response.metadata()).unwrap();
+    assert_eq!(echoed.as_str().len(), 32);
+    assert_eq!(response.into_inner().data, echoed.as_str().as_bytes());
+}
+
+#[tokio::test]
+async fn replaces_the_request_ids_of_untrusted_clients() {
+    let addr = serve(
+        Svc { backend: None },
+        RequestIdLayer::new().trust_incoming(false),
+    );
+    let mut client = client(addr).await;
+
+    let response = client.echo(request("order-42")).await.unwrap();
+    let echoed = RequestId::from_metadata(response.metadata()).unwrap();
+    assert_ne!(echoed.as_str(), "order-42");
+    assert_eq!(response.into_inner().data, echoed.as_str().as_bytes());
+}
@@ -0,0 +1,76 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::net::TcpListener;
+use tonic::{
+    metadata::MetadataMap, server::RequestTransformLayer, transport::Server, Code, Request,
+    Response, Status,
+};
+use tower::layer::Layer;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+/// Fills empty payloads with the tenant of the call.
+fn fill_tenant(payload: &mut Payload, metadata: &MetadataMap) -> Result<(), Status> {
+    if payload.data.is_empty() {
+        let tenant = metadata
+            .get("x-tenant")
+            .ok_or_else(|| Status::invalid_argument("missing tenant"))?;
+        payload.data = tenant.as_bytes().to_vec();
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn transforms_the_request_messages() {
+    let transform = RequestTransformLayer::new()
+        .method("/test.Test/Echo", fill_tenant)
+        .method(
+            "/test.Test/Echo",
+            |payload: &mut Payload, _: &MetadataMap| {
+                payload.data.make_ascii_uppercase();
+                Ok(())
+            },
+        );
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(transform.layer(TestServer::new(Svc)))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let response = client
+        .echo(Payload {
+            data: b"hi".to_vec(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(response.into_inner().data, b"HI");
+
+    let mut request = Request::new(Payload { data: Vec::new() });
+    request
+        .metadata_mut()
+        .insert("x-tenant", "acme".parse().unwrap());
+    let response = client.echo(request).await.unwrap();
+    assert_eq!(response.into_inner().data, b"ACME");
+
+    let status = client.echo(Payload { data: Vec::new() }).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(status.message(), "missing tenant");
+}
@@ -0,0 +1,113 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tonic::{
+    transport::{server::ResponseCacheLayer, Channel, Server},
+    Request, Response, Status,
+};
+use tower::layer::Layer;
+
+/// Replies with the payload followed by the number of calls it handled.
+#[derive(Default)]
+struct Svc(Arc<AtomicUsize>);
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let calls = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut data = request.into_inner().data;
+        data.push(calls as u8);
+        Ok(Response::new(Payload { data }))
+    }
+}
+
+async fn call(client: &mut TestClient<Channel>, data: u8, cache_control: Option<&str>) -> Vec<u8> {
+    let mut request = Request::new(Payload { data: vec![data] });
+    if let Some(cache_control) = cache_control {
+        request
+            .metadata_mut()
+            .insert("cache-control", cache_control.parse().unwrap());
+    }
+    client.echo(request).await.unwrap().into_inner().data
+}
+
+async fn client(cache: ResponseCacheLayer) -> TestClient<Channel> {
+    let cache = cache.method("/test.Test/Echo");
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(cache.layer(TestServer::new(Svc::default())))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn answers_the_same_requests_with_the_cached_responses() {
+    let mut client = client(ResponseCacheLayer::new(Duration::from_secs(60))).await;
+
+    assert_eq!(call(&mut client, 7, None).await, [7, 1]);
+    assert_eq!(call(&mut client, 7, None).await, [7, 1]);
+    assert_eq!(call(&mut client, 8, None).await, [8, 2]);
+
+    // Skipping the cached response replaces it.
+    assert_eq!(call(&mut client, 7, Some("no-cache")).await, [7, 3]);
+    assert_eq!(call(&mut client, 7, None).await, [7, 3]);
+
+    // Skipping the cache leaves it as it is.
+    assert_eq!(call(&mut client, 8, Some("no-store")).await, [8, 4]);
+    assert_eq!(call(&mut client, 8, Some("max-age=60")).await, [8, 2]);
+}
+
+#[tokio::test]
+async fn shares_the_responses_between_the_same_values_of_the_varied_metadata() {
+    let cache = ResponseCacheLayer::new(Duration::from_secs(60)).vary("x-tenant-id");
+    let mut client = client(cache).await;
+
+    let mut call_as = |tenant: Option<&'static str>| {
+        let mut request = Request::new(Payload { data: vec![7] });
+        if let Some(tenant) = tenant {
+            request
+                .metadata_mut()
+                .insert("x-tenant-id", tenant.parse().unwrap());
+        }
+        let mut client = client.clone();
+        async move { client.echo(request).await.unwrap().into_inner().data }
+    };
+
+    assert_eq!(call_as(Some("a")).await, [7, 1]);
+    assert_eq!(call_as(Some("b")).await, [7, 2]);
+    assert_eq!(call_as(None).await, [7, 3]);
+    assert_eq!(call_as(Some("a")).await, [7, 1]);
+    assert_eq!(call_as(None).await, [7, 3]);
+}
+
+#[tokio::test]
+async fn passes_on_the_requests_too_large_to_be_cached() {
+    let mut client = client(ResponseCacheLayer::new(Duration::from_secs(60)).max_bytes(64)).await;
+
+    let large = vec![7; 100];
+    for calls in 1..=2 {
+        let request = Request::new(Payload {
+            data: large.clone(),
+        });
+        let data = client.echo(request).await.unwrap().into_inner().data;
+        assert_eq!(data[..100], large[..]);
+        assert_eq!(data[100], calls);
+    }
+}
@@ -0,0 +1,101 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tonic::{
+    transport::{channel::RouteLayer, Channel, Server},
+    Request, Response, Status,
+};
+use tower::layer::Layer;
+
+/// Replies with its name, recording the payloads received.
+#[derive(Clone)]
+struct Backend {
+    name: &'static str,
+    received: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+#[tonic::async_trait]
+impl Test for Backend {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        self.received
+            .lock()
+            .unwrap()
+            .push(request.into_inner().data);
+        Ok(Response::new(Payload {
+            data: self.name.as_bytes().to_vec(),
+        }))
+    }
+}
+
+async fn serve(name: &'static str) -> (Backend, Channel) {
+    let backend = Backend {
+        name,
+        received: Arc::default(),
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let svc = TestServer::new(backend.clone());
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    (backend, channel)
+}
+
+fn echo(canary: bool) -> Request<Payload> {
+    let mut request = Request::new(Payload { data: vec![1, 2] });
+    if canary {
+        request
+            .metadata_mut()
+            .insert("x-canary", "true".parse().unwrap());
+    }
+    request
+}
+
+#[tokio::test]
+async fn routes_the_selected_calls_to_the_alternate_channel() {
+    let (_, stable) = serve("stable").await;
+    let (_, canary) = serve("canary").await;
+    let layer = RouteLayer::route(canary).metadata("x-canary", "true");
+    let mut client = TestClient::new(layer.layer(stable));
+
+    let response = client.echo(echo(false)).await.unwrap();
+    assert_eq!(response.into_inner().data, b"stable");
+    let response = client.echo(echo(true)).await.unwrap();
+    assert_eq!(response.into_inner().data, b"canary");
+}
+
+#[tokio::test]
+async fn mirrors_the_selected_calls_to_the_alternate_channel() {
+    let (stable, stable_channel) = serve("stable").await;
+    let (shadow, shadow_channel) = serve("shadow").await;
+    let layer = RouteLayer::mirror(shadow_channel).sample(1.0);
+    let mut client = TestClient::new(layer.layer(stable_channel));
+
+    let response = client.echo(echo(false)).await.unwrap();
+    assert_eq!(response.into_inner().data, b"stable");
+    assert_eq!(*stable.received.lock().unwrap(), vec![vec![1, 2]]);
+
+    for _ in 0..100 {
+        if !shadow.received.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+    }
+    assert_eq!(*shadow.received.lock().unwrap(), vec![vec![1, 2]]);
+}
@@ -0,0 +1,42 @@
+use integration_tests::selective::{
+    consumed_client::ConsumedClient,
+    served_server::{Served, ServedServer},
+    Empty,
+};
+use std::net::TcpListener;
+use tonic::{transport::Server, Code, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Served for Svc {
+    async fn call(&self, _: Request<Empty>) -> Result<Response<Empty>, Status> {
+        Ok(Response::new(Empty {}))
+    }
+}
+
+#[tokio::test]
+async fn generates_only_the_selected_clients_and_servers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(ServedServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    // Only the client of `Consumed` and the server of `Served` exist, so
+    // this server doesn't know the called service.
+    let mut client = ConsumedClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    // Derived through `client_attribute`.
+    assert!(format!("{:?}", client).starts_with("ConsumedClient"));
+
+    let status = client.call(Empty {}).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+}
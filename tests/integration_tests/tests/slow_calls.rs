@@ -0,0 +1,85 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::oneshot;
+use tonic::{
+    transport::{server::SlowCallLayer, Server},
+    Request, Response, Status,
+};
+use tower::layer::Layer;
+
+/// Hangs on the calls with an empty payload until released.
+struct Svc(Mutex<Option<oneshot::Receiver<()>>>);
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        if request.get_ref().data.is_empty() {
+            let release = self.0.lock().unwrap().take().unwrap();
+            release.await.unwrap();
+        }
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+#[tokio::test]
+async fn reports_the_calls_in_progress_after_their_threshold() {
+    let (release, released) = oneshot::channel();
+    let release = Mutex::new(Some(release));
+    let reported = Arc::new(Mutex::new(Vec::new()));
+
+    let calls = reported.clone();
+    let layer = SlowCallLayer::new(Duration::from_millis(50)).on_slow_call(move |call| {
+        let id = call
+            .metadata()
+            .get("x-request-id")
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let slow = call.elapsed() >= Duration::from_millis(50) && call.remote_addr().is_some();
+        let report = (call.method().to_string(), id.to_string(), slow);
+        calls.lock().unwrap().push(report);
+        // The handler only returns once its call is reported.
+        if let Some(release) = release.lock().unwrap().take() {
+            release.send(()).unwrap();
+        }
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(layer.layer(TestServer::new(Svc(Mutex::new(Some(released))))))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let mut request = Request::new(Payload { data: vec![1] });
+    request
+        .metadata_mut()
+        .insert("x-request-id", "fast".parse().unwrap());
+    client.echo(request).await.unwrap();
+
+    let mut request = Request::new(Payload { data: vec![] });
+    request
+        .metadata_mut()
+        .insert("x-request-id", "hung".parse().unwrap());
+    client.echo(request).await.unwrap();
+
+    assert_eq!(
+        *reported.lock().unwrap(),
+        vec![("/test.Test/Echo".to_string(), "hung".to_string(), true)]
+    );
+}
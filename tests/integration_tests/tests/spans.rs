@@ -0,0 +1,168 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tonic::{
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let request = request.into_inner();
+        if request.data.is_empty() {
+            return Err(Status::not_found("empty"));
+        }
+        Ok(Response::new(request))
+    }
+}
+
+type Fields = HashMap<String, String>;
+
+struct Visitor<'a>(&'a mut Fields);
+
+impl Visit for Visitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+/// Records the fields of the spans and of the events in them.
+#[derive(Clone, Default)]
+struct Recorder {
+    next: Arc<AtomicU64>,
+    spans: Arc<Mutex<HashMap<u64, Fields>>>,
+    events: Arc<Mutex<Vec<(u64, Fields)>>>,
+}
+
+impl Recorder {
+    fn spans(&self, kind: &str) -> Vec<(u64, Fields)> {
+        let spans = self.spans.lock().unwrap();
+        let mut spans = spans
+            .iter()
+            .filter(|(_, fields)| fields.get("otel.kind").map(String::as_str) == Some(kind))
+            .map(|(id, fields)| (*id, fields.clone()))
+            .collect::<Vec<_>>();
+        spans.sort_by_key(|(id, _)| *id);
+        spans
+    }
+
+    fn events(&self, span: u64) -> Vec<Fields> {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .filter(|(parent, _)| *parent == span)
+            .map(|(_, fields)| fields.clone())
+            .collect()
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &span::Attributes<'_>) -> span::Id {
+        let id = self.next.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut fields = Fields::new();
+        attributes.record(&mut Visitor(&mut fields));
+        self.spans.lock().unwrap().insert(id, fields);
+        span::Id::from_u64(id)
+    }
+
+    fn record(&self, span: &span::Id, values: &span::Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        values.record(&mut Visitor(spans.get_mut(&span.into_u64()).unwrap()));
+    }
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        if let Some(parent) = event.parent() {
+            let mut fields = Fields::new();
+            event.record(&mut Visitor(&mut fields));
+            self.events
+                .lock()
+                .unwrap()
+                .push((parent.into_u64(), fields));
+        }
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[tokio::test]
+async fn records_the_calls_in_spans() {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(channel);
+
+    client.echo(Payload { data: vec![1] }).await.unwrap();
+    let status = client.echo(Payload { data: vec![] }).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    let clients = recorder.spans("client");
+    let servers = recorder.spans("server");
+    assert_eq!(clients.len(), 2);
+    assert_eq!(servers.len(), 2);
+
+    for (_, fields) in clients.iter().chain(&servers) {
+        assert_eq!(fields["rpc.system"], "grpc");
+        assert_eq!(fields["rpc.service"], "test.Test");
+        assert_eq!(fields["rpc.method"], "Echo");
+    }
+    assert_eq!(clients[0].1["rpc.grpc.status_code"], "0");
+    assert_eq!(clients[1].1["rpc.grpc.status_code"], "5");
+    assert_eq!(servers[0].1["rpc.grpc.status_code"], "0");
+    assert_eq!(servers[1].1["rpc.grpc.status_code"], "5");
+    assert_eq!(servers[0].1["net.peer.ip"], "127.0.0.1");
+
+    let sent = &recorder.events(clients[0].0)[0];
+    assert_eq!(sent["message.type"], "SENT");
+    assert_eq!(sent["message.id"], "1");
+    assert_eq!(sent["message.compressed_size"], "8");
+    let received = &recorder.events(servers[0].0)[0];
+    assert_eq!(received["message.type"], "RECEIVED");
+    assert_eq!(received["message.compressed_size"], "8");
+    assert_eq!(recorder.events(clients[0].0).len(), 2);
+    assert_eq!(recorder.events(servers[0].0).len(), 2);
+}
@@ -0,0 +1,175 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+use tonic::{
+    stats::{ConnInfo, ConnStats, RpcInfo, RpcStats, Side, StatsHandler},
+    transport::{Endpoint, Server},
+    Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let payload = request.into_inner();
+        if payload.data.is_empty() {
+            return Err(Status::invalid_argument("empty payload"));
+        }
+        Ok(Response::new(payload))
+    }
+}
+
+/// Records the stats of one side, along with the identifier of their call.
+#[derive(Default)]
+struct Recorder {
+    rpcs: Mutex<Vec<(u64, String)>>,
+    conns: Mutex<Vec<String>>,
+}
+
+impl StatsHandler for Recorder {
+    fn handle_rpc(&self, rpc: &RpcInfo, stats: &RpcStats<'_>) {
+        assert_eq!(rpc.method(), "/test.Test/Echo");
+        let stats = match stats {
+            RpcStats::Begin { .. } => "begin".to_string(),
+            RpcStats::InHeader { .. } => "in header".to_string(),
+            RpcStats::OutHeader { .. } => "out header".to_string(),
+            RpcStats::InPayload { size, .. } => format!("in payload {}", size),
+            RpcStats::OutPayload { size, .. } => format!("out payload {}", size),
+            RpcStats::InTrailer { .. } => "in trailer".to_string(),
+            RpcStats::OutTrailer { .. } => "out trailer".to_string(),
+            RpcStats::End { code, .. } => format!("end {:?}", code),
+        };
+        self.rpcs.lock().unwrap().push((rpc.id(), stats));
+    }
+
+    fn handle_conn(&self, conn: &ConnInfo, stats: &ConnStats) {
+        let side = match conn.side() {
+            Side::Client => "client",
+            Side::Server => "server",
+        };
+        let stats = match stats {
+            ConnStats::Begin { .. } => "begin",
+            ConnStats::End { .. } => "end",
+        };
+        self.conns
+            .lock()
+            .unwrap()
+            .push(format!("{} {}", side, stats));
+    }
+}
+
+impl Recorder {
+    /// The stats of the calls, in the order they began.
+    fn calls(&self) -> Vec<Vec<String>> {
+        let rpcs = self.rpcs.lock().unwrap();
+        let mut ids = rpcs.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        ids.dedup();
+        ids.iter()
+            .map(|id| {
+                rpcs.iter()
+                    .filter(|(rpc, _)| rpc == id)
+                    .map(|(_, stats)| stats.clone())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[tokio::test]
+async fn handles_the_stats_of_calls_and_connections() {
+    let server = Arc::new(Recorder::default());
+    let client = Arc::new(Recorder::default());
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+    let server_stats = server.clone();
+    let serve = tokio::spawn(async move {
+        Server::builder()
+            .stats_handler(server_stats)
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener_shutdown(listener, async {
+                let _ = rx.await;
+            })
+            .await
+            .unwrap();
+    });
+
+    let channel = Endpoint::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .stats_handler(client.clone())
+        .connect()
+        .await
+        .unwrap();
+    let mut test = TestClient::new(channel);
+
+    test.echo(Payload {
+        data: vec![1, 2, 3],
+    })
+    .await
+    .unwrap();
+    let status = test.echo(Payload { data: vec![] }).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    assert_eq!(
+        client.calls(),
+        vec![
+            vec![
+                "begin",
+                "out header",
+                "out payload 10",
+                "in header",
+                "in payload 10",
+                "in trailer",
+                "end Ok",
+            ],
+            vec![
+                "begin",
+                "out header",
+                "out payload 5",
+                "in header",
+                "end InvalidArgument"
+            ],
+        ]
+    );
+    assert_eq!(
+        server.calls(),
+        vec![
+            vec![
+                "begin",
+                "in header",
+                "in payload 10",
+                "out header",
+                "out payload 10",
+                "out trailer",
+                "end Ok",
+            ],
+            vec![
+                "begin",
+                "in header",
+                "in payload 5",
+                "out header",
+                "end InvalidArgument"
+            ],
+        ]
+    );
+
+    drop(test);
+    tx.send(()).unwrap();
+    serve.await.unwrap();
+    assert_eq!(
+        *client.conns.lock().unwrap(),
+        vec!["client begin", "client end"]
+    );
+    assert_eq!(
+        *server.conns.lock().unwrap(),
+        vec!["server begin", "server end"]
+    );
+}
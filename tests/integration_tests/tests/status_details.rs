@@ -0,0 +1,58 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::net::TcpListener;
+use tonic::{
+    error_details::{ErrorDetail, ErrorInfo},
+    transport::Server,
+    Code, Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, _: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Err(Status::with_error_details(
+            Code::FailedPrecondition,
+            "quota exceeded",
+            vec![ErrorDetail::ErrorInfo(ErrorInfo {
+                reason: "QUOTA".to_string(),
+                domain: "example.com".to_string(),
+                ..ErrorInfo::default()
+            })],
+        ))
+    }
+}
+
+#[tokio::test]
+async fn trailers_only_response_details() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let status = client.echo(Payload { data: vec![] }).await.unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    assert!(!status.details().is_empty());
+
+    match &status.error_details().unwrap()[..] {
+        [ErrorDetail::ErrorInfo(info)] => {
+            assert_eq!(info.reason, "QUOTA");
+            assert_eq!(info.domain, "example.com");
+        }
+        details => panic!("unexpected details: {:?}", details),
+    }
+}
@@ -0,0 +1,31 @@
+//! Runs in its own test binary, as it sets the environment variables read
+//! by `systemd_listeners`.
+
+#![cfg(unix)]
+
+use std::{env, io, process};
+use tonic::transport::server::systemd_listeners;
+
+fn activate(pid: u32, fds: &str) {
+    env::set_var("LISTEN_PID", pid.to_string());
+    env::set_var("LISTEN_FDS", fds);
+}
+
+#[test]
+fn takes_only_valid_activations_of_this_process() {
+    activate(process::id() + 1, "1");
+    assert!(systemd_listeners().unwrap().is_empty());
+    assert!(env::var("LISTEN_FDS").is_ok());
+
+    for fds in &["-1", "x", "4294967295"] {
+        activate(process::id(), fds);
+        let err = systemd_listeners().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", fds);
+        assert_eq!(env::var("LISTEN_FDS").unwrap(), *fds);
+    }
+
+    activate(process::id(), "0");
+    assert!(systemd_listeners().unwrap().is_empty());
+    assert!(env::var("LISTEN_PID").is_err());
+    assert!(env::var("LISTEN_FDS").is_err());
+}
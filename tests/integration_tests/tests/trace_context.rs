@@ -0,0 +1,73 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::net::{SocketAddr, TcpListener};
+use tonic::{
+    trace_context::{self, TraceContext},
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+
+/// Echoes the `traceparent` of the calls, or forwards them to a backend.
+struct Svc {
+    backend: Option<TestClient<Channel>>,
+}
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        let context = request.extensions().get::<TraceContext>().unwrap().clone();
+        assert_eq!(trace_context::current(), Some(context.clone()));
+
+        match &self.backend {
+            Some(backend) => backend.clone().echo(Payload::default()).await,
+            None => Ok(Response::new(Payload {
+                data: context.to_traceparent().into_bytes(),
+            })),
+        }
+    }
+}
+
+fn serve(svc: Svc) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::with_interceptor(svc, trace_context::extract()))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+    addr
+}
+
+async fn client(addr: SocketAddr) -> TestClient<Channel> {
+    let channel = Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    TestClient::with_interceptor(channel, trace_context::inject())
+}
+
+#[tokio::test]
+async fn propagates_the_trace_context_across_calls() {
+    let backend = serve(Svc { backend: None });
+    let frontend = serve(Svc {
+        backend: Some(client(backend).await),
+    });
+    let mut client = client(frontend).await;
+
+    let root = TraceContext::new_root(true);
+    let response = trace_context::scope(Some(root.clone()), client.echo(Payload::default()))
+        .await
+        .unwrap();
+    let traceparent = String::from_utf8(response.into_inner().data).unwrap();
+    let backend_context = TraceContext::from_traceparent(&traceparent, None).unwrap();
+
+    assert_eq!(backend_context.trace_id(), root.trace_id());
+    assert_ne!(backend_context.span_id(), root.span_id());
+    assert!(backend_context.is_sampled());
+}
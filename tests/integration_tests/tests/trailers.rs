@@ -0,0 +1,43 @@
+use integration_tests::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Payload,
+};
+use std::net::TcpListener;
+use tonic::{transport::Server, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn echo(&self, request: Request<Payload>) -> Result<Response<Payload>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+#[tokio::test]
+async fn unary_response_trailers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc).message_checksums(true))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+        .message_checksums(true);
+
+    let response = client.echo(Payload { data: vec![1] }).await.unwrap();
+
+    let trailers = response.trailers();
+    assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+    assert!(trailers.contains_key("message-crc32c"));
+    assert!(!trailers.contains_key("content-type"));
+    assert!(response.metadata().contains_key("content-type"));
+}
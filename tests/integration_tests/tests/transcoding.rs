@@ -0,0 +1,68 @@
+use integration_tests::transcoding::library_descriptor;
+use tonic::descriptor::HttpRule;
+
+#[test]
+fn describes_http_rules() {
+    assert_eq!(
+        library_descriptor::GET_SHELF.http_rules,
+        &[
+            HttpRule {
+                method: "GET",
+                path: "/v1/{name=shelves/*}",
+                body: "",
+                response_body: "",
+            },
+            HttpRule {
+                method: "GET",
+                path: "/v1/shelves/default",
+                body: "",
+                response_body: "",
+            },
+        ]
+    );
+    assert_eq!(
+        library_descriptor::MOVE_BOOK.http_rules,
+        &[HttpRule {
+            method: "MOVE",
+            path: "/v1/{name=shelves/*/books/*}:move",
+            body: "*",
+            response_body: "name",
+        }]
+    );
+    assert!(library_descriptor::PING.http_rules.is_empty());
+}
+
+#[test]
+fn routes_http_requests_to_methods() {
+    let service = library_descriptor::SERVICE;
+
+    let route = service.route("GET", "/v1/shelves/fiction").unwrap();
+    assert_eq!(route.method, &library_descriptor::GET_SHELF);
+    assert_eq!(
+        route.bindings,
+        vec![("name", "shelves/fiction".to_string())]
+    );
+
+    let route = service.route("GET", "/v1/shelves/default").unwrap();
+    assert_eq!(route.method, &library_descriptor::GET_SHELF);
+
+    let route = service.route("POST", "/v1/shelves/fiction/books").unwrap();
+    assert_eq!(route.method, &library_descriptor::CREATE_BOOK);
+    assert_eq!(route.rule.body, "book");
+    assert_eq!(
+        route.bindings,
+        vec![("parent", "shelves/fiction".to_string())]
+    );
+
+    let route = service
+        .route("MOVE", "/v1/shelves/fiction/books/dune:move")
+        .unwrap();
+    assert_eq!(route.method, &library_descriptor::MOVE_BOOK);
+    assert_eq!(
+        route.bindings,
+        vec![("name", "shelves/fiction/books/dune".to_string())]
+    );
+
+    assert!(service.route("DELETE", "/v1/shelves/fiction").is_none());
+    assert!(service.route("GET", "/transcoding.Library/Ping").is_none());
+}
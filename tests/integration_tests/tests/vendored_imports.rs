@@ -0,0 +1,53 @@
+use integration_tests::{
+    units::{Quantity, Unit},
+    vendoring::{
+        pantry_client::PantryClient,
+        pantry_server::{Pantry, PantryServer},
+    },
+};
+use std::net::TcpListener;
+use tonic::{transport::Server, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Pantry for Svc {
+    async fn stock(&self, request: Request<Quantity>) -> Result<Response<Quantity>, Status> {
+        let quantity = request.into_inner();
+        Ok(Response::new(Quantity {
+            amount: quantity.amount * 2,
+            ..quantity
+        }))
+    }
+}
+
+#[tokio::test]
+async fn imports_types_from_descriptor_sets() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(PantryServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = PantryClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let quantity = Quantity {
+        amount: 250,
+        unit: Unit::Gram as i32,
+    };
+    let res = client.stock(quantity).await.unwrap();
+    assert_eq!(
+        res.into_inner(),
+        Quantity {
+            amount: 500,
+            unit: Unit::Gram as i32,
+        }
+    );
+}
@@ -0,0 +1,7 @@
+fn main() {
+    tonic_build::configure()
+        .derive_serde(true)
+        .codec_path("tonic::codec::JsonCodec")
+        .compile(&["proto/test.proto"], &["proto"])
+        .unwrap();
+}
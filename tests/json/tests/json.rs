@@ -0,0 +1,124 @@
+use json::pb::{
+    person::Contact,
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Greeting, Mood, Person,
+};
+use prost_types::{Duration, Timestamp};
+use serde_json::json;
+use std::net::TcpListener;
+use tonic::{transport::Server, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn greet(&self, request: Request<Person>) -> Result<Response<Greeting>, Status> {
+        let content_type = request.metadata().get("content-type").unwrap();
+        assert_eq!(content_type, "application/grpc+json");
+
+        let person = request.into_inner();
+        Ok(Response::new(Greeting {
+            message: format!("Hello {}, {} years old", person.name, person.age),
+        }))
+    }
+}
+
+#[tokio::test]
+async fn round_trips_json_messages() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(TestServer::new(Svc))
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    let mut client = TestClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap();
+
+    let res = client
+        .greet(Person {
+            name: "Ferris".to_string(),
+            age: 5,
+            ..Person::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        res.metadata().get("content-type").unwrap(),
+        "application/grpc+json"
+    );
+    assert_eq!(res.into_inner().message, "Hello Ferris, 5 years old");
+}
+
+#[test]
+fn follows_the_proto3_json_mapping() {
+    let person = Person {
+        name: "Ferris".to_string(),
+        age: 5,
+        avatar: vec![0xfb, 0xff],
+        born_at: Some(Timestamp {
+            seconds: 1_431_648_000,
+            nanos: 500_000_000,
+        }),
+        nap_lengths: vec![Duration {
+            seconds: 90,
+            nanos: 0,
+        }],
+        files: vec![("key".to_string(), b"rust".to_vec())]
+            .into_iter()
+            .collect(),
+        signature: Some(b"ok".to_vec()),
+        mood: Mood::Happy as i32,
+        contact: Some(Contact::PublicKey(vec![1, 2, 3])),
+        fingerprint: Some(Vec::new()),
+    };
+
+    let value = json!({
+        "name": "Ferris",
+        "age": 5,
+        "avatar": "+/8=",
+        "bornAt": "2015-05-15T00:00:00.500Z",
+        "napLengths": ["90s"],
+        "files": { "key": "cnVzdA==" },
+        "signature": "b2s=",
+        "mood": 1,
+        "publicKey": "AQID",
+        "fingerprint": "",
+    });
+
+    assert_eq!(serde_json::to_value(&person).unwrap(), value);
+    assert_eq!(serde_json::from_value::<Person>(value).unwrap(), person);
+
+    assert_eq!(
+        serde_json::to_value(Mood::Happy).unwrap(),
+        json!("MOOD_HAPPY")
+    );
+}
+
+#[test]
+fn accepts_original_names_and_missing_fields() {
+    let person: Person = serde_json::from_value(json!({
+        "born_at": "2015-05-15T02:00:00+02:00",
+        "email": "ferris@rust-lang.org",
+    }))
+    .unwrap();
+
+    assert_eq!(
+        person,
+        Person {
+            born_at: Some(Timestamp {
+                seconds: 1_431_648_000,
+                nanos: 0,
+            }),
+            contact: Some(Contact::Email("ferris@rust-lang.org".to_string())),
+            ..Person::default()
+        }
+    );
+}
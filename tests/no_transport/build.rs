@@ -0,0 +1,7 @@
+fn main() {
+    tonic_build::configure()
+        .build_transport(false)
+        .build_dispatch(true)
+        .compile(&["proto/echo.proto"], &["proto"])
+        .unwrap();
+}
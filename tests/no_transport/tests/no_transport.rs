@@ -0,0 +1,55 @@
+use no_transport::pb::{
+    echo_client::EchoClient,
+    echo_descriptor,
+    echo_server::{self, Echo, EchoServer},
+    Message,
+};
+use tonic::{Code, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Echo for Svc {
+    async fn say(&self, request: Request<Message>) -> Result<Response<Message>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+}
+
+#[tokio::test]
+async fn calls_over_a_custom_transport() {
+    // The server itself is the transport of the client, without hyper.
+    let transport = EchoServer::new(Svc);
+    let mut client = EchoClient::with_origin(transport, "http://in-process".parse().unwrap());
+
+    let message = Message {
+        text: "hello".to_string(),
+    };
+    let res = client.say(message.clone()).await.unwrap();
+    assert_eq!(res.into_inner(), message);
+}
+
+#[tokio::test]
+async fn dispatches_framed_requests() {
+    let server = EchoServer::new(Svc);
+
+    let mut message = Vec::new();
+    prost::Message::encode(
+        &Message {
+            text: "hello".to_string(),
+        },
+        &mut message,
+    )
+    .unwrap();
+    let mut body = vec![0];
+    body.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    body.extend(message);
+
+    let (res, status) = echo_server::dispatch(&server, "/echo.Echo/Say", body.clone().into()).await;
+    assert_eq!(status.code(), Code::Ok);
+    assert_eq!(&res[..], &body[..]);
+}
+
+#[test]
+fn describes_methods() {
+    assert_eq!(echo_descriptor::SAY.path, "/echo.Echo/Say");
+}
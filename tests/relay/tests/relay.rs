@@ -0,0 +1,129 @@
+use bytes::Bytes;
+use relay::pb::{
+    test_client::TestClient,
+    test_server::{Test, TestServer},
+    Greeting, Person,
+};
+use std::net::TcpListener;
+use std::task::{Context, Poll};
+use tonic::{
+    body::BoxBody,
+    client,
+    codec::BytesCodec,
+    codegen::{http, BoxFuture, Never, Service},
+    server::{self, UnaryService},
+    transport::{Body, Channel, NamedService, Server},
+    Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Test for Svc {
+    async fn greet(&self, request: Request<Person>) -> Result<Response<Greeting>, Status> {
+        Ok(Response::new(Greeting {
+            message: format!("Hello {}", request.into_inner().name),
+        }))
+    }
+}
+
+/// Relays every unary call of `test.Test` to `channel` without decoding it.
+#[derive(Clone)]
+struct Relay {
+    channel: Channel,
+}
+
+impl NamedService for Relay {
+    const NAME: &'static str = "test.Test";
+}
+
+impl Service<http::Request<Body>> for Relay {
+    type Response = http::Response<BoxBody>;
+    type Error = Never;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        let forward = Forward {
+            client: client::Grpc::new(self.channel.clone()),
+            path: req.uri().path_and_query().cloned().unwrap(),
+        };
+
+        Box::pin(async move {
+            let mut grpc = server::Grpc::new(BytesCodec::default());
+            Ok(grpc.unary(forward, req).await)
+        })
+    }
+}
+
+struct Forward {
+    client: client::Grpc<Channel>,
+    path: http::uri::PathAndQuery,
+}
+
+impl UnaryService<Bytes> for Forward {
+    type Response = Bytes;
+    type Future = BoxFuture<Response<Bytes>, Status>;
+
+    fn call(&mut self, request: Request<Bytes>) -> Self::Future {
+        let mut client = self.client.clone();
+        let path = self.path.clone();
+
+        Box::pin(async move {
+            client
+                .ready()
+                .await
+                .map_err(|e| Status::unavailable(e.to_string()))?;
+            client.unary(request, path, BytesCodec::default()).await
+        })
+    }
+}
+
+fn serve<S>(svc: S) -> String
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>>
+        + NamedService
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<Box<dyn std::error::Error + Send + Sync>> + Send,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_listener(listener)
+            .await
+            .unwrap();
+    });
+
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn relays_raw_messages() {
+    let backend = serve(TestServer::new(Svc));
+    let channel = Channel::from_shared(backend)
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let relay = serve(Relay { channel });
+
+    let mut client = TestClient::connect(relay).await.unwrap();
+
+    let res = client
+        .greet(Person {
+            name: "Ferris".to_string(),
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(res.into_inner().message, "Hello Ferris");
+}
@@ -0,0 +1,147 @@
+//! Message fields generated as `Box<T>`.
+//!
+//! prost-build only boxes the fields of recursive messages, so the other
+//! fields to box are marked with an attribute, and their types rewritten in
+//! the generated code.
+
+use prost_build::Config;
+use std::{fs, io, path::Path};
+
+/// Marks the fields to box, removed along with the rewrite.
+const MARKER: &str = "#[tonic_build(boxed)]";
+
+/// Mark the fields at `paths`, like `.pkg.Message.field` or
+/// `.pkg.Message.oneof.field` for the fields of a oneof.
+pub(crate) fn configure(config: &mut Config, paths: &[String]) {
+    for path in paths {
+        config.field_attribute(path, MARKER);
+    }
+}
+
+/// Box the marked message fields of the code generated in `out_dir`.
+pub(crate) fn box_fields(out_dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(out_dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+
+        let code = fs::read_to_string(&path)?;
+        if let Some(code) = box_marked_fields(&code) {
+            fs::write(&path, code)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Box the type of the message fields and oneof variants following a marker,
+/// removing the markers, returning the new code if there were any.
+fn box_marked_fields(code: &str) -> Option<String> {
+    let mut lines = code.lines().map(str::to_string).collect::<Vec<_>>();
+    let mut changed = false;
+
+    for i in 0..lines.len() {
+        if lines[i].trim() != MARKER {
+            continue;
+        }
+        changed = true;
+
+        // Scalars, repeated fields and maps aren't boxed.
+        let is_message = lines[..i]
+            .iter()
+            .rev()
+            .find(|l| l.trim().starts_with("#[prost("))
+            .is_some_and(|l| {
+                l.contains("message") && !l.contains("repeated") && !l.contains("map")
+            });
+        let item = match lines[i..].iter_mut().find(|l| !l.trim().starts_with('#')) {
+            Some(item) if is_message => item,
+            _ => continue,
+        };
+
+        if let Some(colon) = item.trim().strip_prefix("pub ").and(item.find(": ")) {
+            let ty = item[colon + 2..].trim_end().trim_end_matches(',');
+            if let Some(inner) = ty
+                .strip_prefix("::std::option::Option<")
+                .and_then(|ty| ty.strip_suffix('>'))
+                .filter(|inner| !inner.starts_with("::std::boxed::Box<"))
+            {
+                *item = format!(
+                    "{}::std::option::Option<::std::boxed::Box<{}>>,",
+                    &item[..colon + 2],
+                    inner
+                );
+            }
+        } else if let Some(open) = item.find('(') {
+            let ty = item[open + 1..].trim_end().trim_end_matches(',');
+            if let Some(inner) = ty
+                .strip_suffix(')')
+                .filter(|inner| !inner.starts_with("Box<") && !inner.starts_with("::std::boxed"))
+            {
+                *item = format!("{}(::std::boxed::Box<{}>),", &item[..open], inner);
+            }
+        }
+    }
+
+    if changed {
+        lines.retain(|line| line.trim() != MARKER);
+        let mut code = lines.join("\n");
+        code.push('\n');
+        Some(code)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_marked_message_fields() {
+        let code = "pub struct Outer {\n    \
+                    #[prost(message, optional, tag=\"1\")]\n    \
+                    #[tonic_build(boxed)]\n    \
+                    pub inner: ::std::option::Option<Inner>,\n    \
+                    #[prost(message, repeated, tag=\"2\")]\n    \
+                    #[tonic_build(boxed)]\n    \
+                    pub items: ::std::vec::Vec<Inner>,\n    \
+                    #[prost(message, optional, tag=\"3\")]\n    \
+                    pub other: ::std::option::Option<Inner>,\n\
+                    }\n\
+                    pub enum Choice {\n    \
+                    #[prost(message, tag=\"4\")]\n    \
+                    #[tonic_build(boxed)]\n    \
+                    #[serde(default)]\n    \
+                    Inner(super::Inner),\n    \
+                    #[prost(uint64, tag=\"5\")]\n    \
+                    #[tonic_build(boxed)]\n    \
+                    Id(u64),\n\
+                    }\n";
+
+        assert_eq!(
+            box_marked_fields(code).unwrap(),
+            "pub struct Outer {\n    \
+             #[prost(message, optional, tag=\"1\")]\n    \
+             pub inner: ::std::option::Option<::std::boxed::Box<Inner>>,\n    \
+             #[prost(message, repeated, tag=\"2\")]\n    \
+             pub items: ::std::vec::Vec<Inner>,\n    \
+             #[prost(message, optional, tag=\"3\")]\n    \
+             pub other: ::std::option::Option<Inner>,\n\
+             }\n\
+             pub enum Choice {\n    \
+             #[prost(message, tag=\"4\")]\n    \
+             #[serde(default)]\n    \
+             Inner(::std::boxed::Box<super::Inner>),\n    \
+             #[prost(uint64, tag=\"5\")]\n    \
+             Id(u64),\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn keeps_code_without_markers() {
+        assert_eq!(box_marked_fields("pub struct Empty {}\n"), None);
+    }
+}
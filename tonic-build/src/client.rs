@@ -1,27 +1,53 @@
-use crate::{generate_doc_comments, naive_snake_case};
+use crate::codec::{Codec, MethodCodecs};
+use crate::{descriptor, fields::RequestFields};
+use crate::{
+    generate_deprecated, generate_doc_comment, generate_doc_comments, naive_snake_case,
+    service_path, Attributes,
+};
 use proc_macro2::TokenStream;
 use prost_build::{Method, Service};
 use quote::{format_ident, quote};
 
-pub(crate) fn generate(service: &Service, proto: &str) -> TokenStream {
+pub(crate) fn generate(
+    service: &Service,
+    proto: &str,
+    codec: &Codec,
+    attributes: &Attributes,
+    request_fields: &RequestFields,
+    client_trait: ClientTrait,
+    transport: bool,
+) -> TokenStream {
     let service_ident = quote::format_ident!("{}Client", service.name);
     let client_mod = quote::format_ident!("{}_client", naive_snake_case(&service.name));
-    let methods = generate_methods(service, proto);
+    let methods = generate_methods(service, proto, codec, attributes, request_fields);
+    let generated_trait = generate_trait(service, proto, codec, &service_ident, client_trait);
+    let default_codec = MethodCodecs::client(codec, proto, service).generate_default();
 
-    let connect = generate_connect(&service_ident);
+    let connect = if transport {
+        generate_connect(&service_ident)
+    } else {
+        TokenStream::new()
+    };
     let service_doc = generate_doc_comments(&service.comments.leading);
+    let service_attributes = attributes.get(&service_path(service));
+    let service_deprecated = generate_deprecated(service.options.deprecated());
 
     quote! {
         /// Generated client implementations.
         pub mod #client_mod {
-            #![allow(unused_variables, dead_code, missing_docs)]
+            #![allow(unused_variables, dead_code, missing_docs, deprecated)]
             use tonic::codegen::*;
 
             #service_doc
-            pub struct #service_ident<T> {
+            #service_deprecated
+            #service_attributes
+            pub struct #service_ident<T, C = DefaultCodec> {
                 inner: tonic::client::Grpc<T>,
+                codec: C,
             }
 
+            #default_codec
+
             #connect
 
             impl<T> #service_ident<T>
@@ -31,29 +57,207 @@ pub(crate) fn generate(service: &Service, proto: &str) -> TokenStream {
                   <T::ResponseBody as HttpBody>::Error: Into<StdError> + Send, {
                 pub fn new(inner: T) -> Self {
                     let inner = tonic::client::Grpc::new(inner);
-                    Self { inner }
+                    Self { inner, codec: DefaultCodec }
                 }
 
                 pub fn with_interceptor(inner: T, interceptor: impl Into<tonic::Interceptor>) -> Self {
                     let inner = tonic::client::Grpc::with_interceptor(inner, interceptor);
-                    Self { inner }
+                    Self { inner, codec: DefaultCodec }
+                }
+
+                /// Send the requests to `origin`, for services passing them to a transport of
+                /// their own instead of a `Channel`.
+                pub fn with_origin(inner: T, origin: http::Uri) -> Self {
+                    let inner = tonic::client::Grpc::with_origin(inner, origin);
+                    Self { inner, codec: DefaultCodec }
+                }
+            }
+
+            impl<T, C> #service_ident<T, C>
+            where T: tonic::client::GrpcService<tonic::body::BoxBody>,
+                  T::ResponseBody: Body + HttpBody + Send + 'static,
+                  T::Error: Into<StdError>,
+                  <T::ResponseBody as HttpBody>::Error: Into<StdError> + Send, {
+                /// Encode and decode the messages of the calls with the codecs made by `codec`,
+                /// instead of the ones chosen when generating this client.
+                pub fn with_codec(inner: T, codec: C) -> Self {
+                    let inner = tonic::client::Grpc::new(inner);
+                    Self { inner, codec }
+                }
+
+                /// Authenticate every call with the metadata produced by `credentials` right
+                /// before it is sent.
+                pub fn call_credentials(mut self, credentials: impl tonic::CallCredentials) -> Self {
+                    self.inner = self.inner.call_credentials(credentials);
+                    self
+                }
+
+                /// Compress requests with the provided encoding.
+                ///
+                /// The server must accept that encoding, otherwise it replies with an
+                /// `Unimplemented` status.
+                pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+                    self.inner = self.inner.send_compressed(encoding);
+                    self
+                }
+
+                /// Accept responses compressed with the provided encoding.
+                pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+                    self.inner = self.inner.accept_compressed(encoding);
+                    self
+                }
+
+                /// Set the level requests are compressed at.
+                pub fn compression_level(mut self, level: CompressionLevel) -> Self {
+                    self.inner = self.inner.compression_level(level);
+                    self
+                }
+
+                /// Send request messages smaller than `threshold` bytes uncompressed.
+                pub fn compression_threshold(mut self, threshold: usize) -> Self {
+                    self.inner = self.inner.compression_threshold(threshold);
+                    self
+                }
+
+                /// Limit the size of the response messages decoded to `limit` bytes.
+                ///
+                /// Defaults to 4MiB.
+                pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+                    self.inner = self.inner.max_decoding_message_size(limit);
+                    self
+                }
+
+                /// Limit the size of the request messages encoded to `limit` bytes.
+                ///
+                /// Defaults to no limit.
+                pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+                    self.inner = self.inner.max_encoding_message_size(limit);
+                    self
+                }
+
+                /// Take the buffers messages are encoded into and decoded from from `pool`.
+                pub fn buffer_pool(mut self, pool: BufferPool) -> Self {
+                    self.inner = self.inner.buffer_pool(pool);
+                    self
+                }
+
+                /// Yield to the runtime after handing out `limit` buffered response messages in a row.
+                pub fn yield_after_messages(mut self, limit: usize) -> Self {
+                    self.inner = self.inner.yield_after_messages(limit);
+                    self
+                }
+
+                /// Send a checksum of the request messages, and check the one of the response messages.
+                pub fn message_checksums(mut self, enable: bool) -> Self {
+                    self.inner = self.inner.message_checksums(enable);
+                    self
+                }
+
+                /// Limit the size of the response metadata to `limit` bytes, counted like HTTP/2
+                /// header lists.
+                pub fn max_metadata_size(mut self, limit: usize) -> Self {
+                    self.inner = self.inner.max_metadata_size(limit);
+                    self
                 }
 
                 #methods
             }
 
-            impl<T: Clone> Clone for #service_ident<T> {
+            impl<T: Clone, C: Clone> Clone for #service_ident<T, C> {
                 fn clone(&self) -> Self {
                     Self {
                         inner: self.inner.clone(),
+                        codec: self.codec.clone(),
                     }
                 }
             }
+
+            #generated_trait
+        }
+    }
+}
+
+/// Whether a trait with the methods of a client is generated, and mocked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ClientTrait {
+    Disabled,
+    Enabled,
+    Mocked,
+}
+
+fn generate_trait(
+    service: &Service,
+    proto: &str,
+    codec: &Codec,
+    service_ident: &syn::Ident,
+    client_trait: ClientTrait,
+) -> TokenStream {
+    let automock = match client_trait {
+        ClientTrait::Disabled => return TokenStream::new(),
+        ClientTrait::Enabled => TokenStream::new(),
+        ClientTrait::Mocked => quote!(#[mockall::automock]),
+    };
+
+    let trait_ident = format_ident!("{}Api", service.name);
+    let trait_deprecated = generate_deprecated(service.options.deprecated());
+    let trait_doc = generate_doc_comment(&format!(
+        "Generated trait containing the gRPC methods of {}, so it can be replaced in tests.",
+        service_ident
+    ));
+
+    let mut methods = TokenStream::new();
+    let mut impls = TokenStream::new();
+
+    for method in &service.methods {
+        let ident = format_ident!("{}", method.name);
+        let method_doc = generate_doc_comments(&method.comments.leading);
+        let method_deprecated = generate_deprecated(method.options.deprecated());
+
+        let (request, response) = codec.types(proto, method);
+        let request = if method.client_streaming {
+            quote!(tonic::Request<BoxMessageStream<#request>>)
+        } else {
+            quote!(tonic::Request<#request>)
+        };
+        let response = if method.server_streaming {
+            quote!(tonic::Response<tonic::codec::Streaming<#response>>)
+        } else {
+            quote!(tonic::Response<#response>)
+        };
+
+        methods.extend(quote! {
+            #method_doc
+            #method_deprecated
+            async fn #ident(&mut self, request: #request) -> Result<#response, tonic::Status>;
+        });
+        impls.extend(quote! {
+            async fn #ident(&mut self, request: #request) -> Result<#response, tonic::Status> {
+                #service_ident::#ident(self, request).await
+            }
+        });
+    }
+
+    quote! {
+        #trait_doc
+        #trait_deprecated
+        #automock
+        #[async_trait]
+        pub trait #trait_ident: Send {
+            #methods
+        }
+
+        #[async_trait]
+        impl<T> #trait_ident for #service_ident<T>
+        where T: tonic::client::GrpcService<tonic::body::BoxBody> + Send,
+              T::Future: Send,
+              T::ResponseBody: Body + HttpBody + Send + 'static,
+              T::Error: Into<StdError>,
+              <T::ResponseBody as HttpBody>::Error: Into<StdError> + Send, {
+            #impls
         }
     }
 }
 
-#[cfg(feature = "transport")]
 fn generate_connect(service_ident: &syn::Ident) -> TokenStream {
     quote! {
         impl #service_ident<tonic::transport::Channel> {
@@ -70,13 +274,15 @@ fn generate_connect(service_ident: &syn::Ident) -> TokenStream {
     }
 }
 
-#[cfg(not(feature = "transport"))]
-fn generate_connect(_service_ident: &syn::Ident) -> TokenStream {
-    TokenStream::new()
-}
-
-fn generate_methods(service: &Service, proto: &str) -> TokenStream {
+fn generate_methods(
+    service: &Service,
+    proto: &str,
+    codec: &Codec,
+    attributes: &Attributes,
+    request_fields: &RequestFields,
+) -> TokenStream {
     let mut stream = TokenStream::new();
+    let service_path = service_path(service);
 
     for method in &service.methods {
         let path = format!(
@@ -85,95 +291,255 @@ fn generate_methods(service: &Service, proto: &str) -> TokenStream {
         );
 
         stream.extend(generate_doc_comments(&method.comments.leading));
+        stream.extend(generate_deprecated(method.options.deprecated()));
+        stream.extend(attributes.get(&format!("{}.{}", service_path, method.proto_name)));
 
+        let with_fields = generate_with_fields(method, proto, codec, request_fields);
+        let descriptor = descriptor::method_descriptor(proto, service, method);
         let method = match (method.client_streaming, method.server_streaming) {
-            (false, false) => generate_unary(method, &proto, path),
-            (false, true) => generate_server_streaming(method, &proto, path),
-            (true, false) => generate_client_streaming(method, &proto, path),
-            (true, true) => generate_streaming(method, &proto, path),
+            (false, false) => generate_unary(method, &proto, path, descriptor, codec),
+            (false, true) => generate_server_streaming(method, &proto, path, descriptor, codec),
+            (true, false) => generate_client_streaming(method, &proto, path, descriptor, codec),
+            (true, true) => generate_streaming(method, &proto, path, descriptor, codec),
         };
 
         stream.extend(method);
+        stream.extend(with_fields);
     }
 
     stream
 }
 
-fn generate_unary(method: &Method, proto: &str, path: String) -> TokenStream {
+/// Generate the `{method}_with` twin of `method`, taking the fields of its
+/// request, if they are simple enough.
+fn generate_with_fields(
+    method: &Method,
+    proto: &str,
+    codec: &Codec,
+    request_fields: &RequestFields,
+) -> TokenStream {
+    let fields = match request_fields.get(&method.input_proto_type) {
+        Some(fields) if !method.client_streaming && !codec.is_mapped(&method.input_proto_type) => {
+            fields
+        }
+        _ => return TokenStream::new(),
+    };
+
     let ident = format_ident!("{}", method.name);
-    let (request, response) = crate::replace_wellknown(proto, &method);
+    let with_ident = format_ident!("{}_with", method.name);
+    let (request, codec_response) = codec.types(proto, method);
+    let response = if method.server_streaming {
+        quote!(tonic::codec::Streaming<#codec_response>)
+    } else {
+        codec_response.clone()
+    };
+    let doc = generate_doc_comment(&format!(
+        " Calls [`{}`](Self::{}) with a request made of the given fields.",
+        method.name, method.name
+    ));
+    let deprecated = generate_deprecated(method.options.deprecated());
+
+    let args = fields.iter().map(|field| {
+        let (ident, ty) = (&field.ident, &field.ty);
+        quote!(#ident: #ty)
+    });
+    let values = fields.iter().map(|field| {
+        let ident = &field.ident;
+        if field.into {
+            quote!(#ident: #ident.into())
+        } else {
+            quote!(#ident)
+        }
+    });
+
+    quote! {
+        #doc
+        #deprecated
+        pub async fn #with_ident(
+            &mut self,
+            #(#args),*
+        ) -> Result<tonic::Response<#response>, tonic::Status>
+        where
+            C: tonic::codec::MakeCodec<#request, #codec_response>,
+        {
+            self.#ident(#request { #(#values),* }).await
+        }
+    }
+}
+
+fn generate_unary(
+    method: &Method,
+    proto: &str,
+    path: String,
+    descriptor: TokenStream,
+    codec: &Codec,
+) -> TokenStream {
+    let ident = format_ident!("{}", method.name);
+    let (request, response) = codec.types(proto, method);
 
     quote! {
         pub async fn #ident(
             &mut self,
             request: impl tonic::IntoRequest<#request>,
-        ) -> Result<tonic::Response<#response>, tonic::Status> {
+        ) -> Result<tonic::Response<#response>, tonic::Status>
+        where
+            C: tonic::codec::MakeCodec<#request, #response>,
+        {
             self.inner.ready().await.map_err(|e| {
                         tonic::Status::new(tonic::Code::Unknown, format!("Service was not ready: {}", e.into()))
             })?;
-           let codec = tonic::codec::ProstCodec::default();
+           let codec = tonic::codec::MakeCodec::<#request, #response>::make_codec(&self.codec);
            let path = http::uri::PathAndQuery::from_static(#path);
-           self.inner.unary(request.into_request(), path, codec).await
+           let mut request = request.into_request();
+           request.extensions_mut().insert(&#descriptor);
+           self.inner.unary(request, path, codec).await
         }
     }
 }
 
-fn generate_server_streaming(method: &Method, proto: &str, path: String) -> TokenStream {
+fn generate_server_streaming(
+    method: &Method,
+    proto: &str,
+    path: String,
+    descriptor: TokenStream,
+    codec: &Codec,
+) -> TokenStream {
     let ident = format_ident!("{}", method.name);
 
-    let (request, response) = crate::replace_wellknown(proto, &method);
+    let (request, response) = codec.types(proto, method);
 
     quote! {
         pub async fn #ident(
             &mut self,
             request: impl tonic::IntoRequest<#request>,
-        ) -> Result<tonic::Response<tonic::codec::Streaming<#response>>, tonic::Status> {
+        ) -> Result<tonic::Response<tonic::codec::Streaming<#response>>, tonic::Status>
+        where
+            C: tonic::codec::MakeCodec<#request, #response>,
+        {
             self.inner.ready().await.map_err(|e| {
                         tonic::Status::new(tonic::Code::Unknown, format!("Service was not ready: {}", e.into()))
             })?;
-           let codec = tonic::codec::ProstCodec::default();
+           let codec = tonic::codec::MakeCodec::<#request, #response>::make_codec(&self.codec);
            let path = http::uri::PathAndQuery::from_static(#path);
-           self.inner.server_streaming(request.into_request(), path, codec).await
+           let mut request = request.into_request();
+           request.extensions_mut().insert(&#descriptor);
+           self.inner.server_streaming(request, path, codec).await
         }
     }
 }
 
-fn generate_client_streaming(method: &Method, proto: &str, path: String) -> TokenStream {
+fn generate_client_streaming(
+    method: &Method,
+    proto: &str,
+    path: String,
+    descriptor: TokenStream,
+    codec: &Codec,
+) -> TokenStream {
     let ident = format_ident!("{}", method.name);
 
-    let (request, response) = crate::replace_wellknown(proto, &method);
+    let (request, response) = codec.types(proto, method);
+    let channel = generate_channel(method, &request, &response, &response);
 
     quote! {
         pub async fn #ident(
             &mut self,
             request: impl tonic::IntoStreamingRequest<Message = #request>
-        ) -> Result<tonic::Response<#response>, tonic::Status> {
+        ) -> Result<tonic::Response<#response>, tonic::Status>
+        where
+            C: tonic::codec::MakeCodec<#request, #response>,
+        {
             self.inner.ready().await.map_err(|e| {
                         tonic::Status::new(tonic::Code::Unknown, format!("Service was not ready: {}", e.into()))
             })?;
-            let codec = tonic::codec::ProstCodec::default();
+            let codec = tonic::codec::MakeCodec::<#request, #response>::make_codec(&self.codec);
             let path = http::uri::PathAndQuery::from_static(#path);
-            self.inner.client_streaming(request.into_streaming_request(), path, codec).await
+            let mut request = request.into_streaming_request();
+           request.extensions_mut().insert(&#descriptor);
+           self.inner.client_streaming(request, path, codec).await
         }
+
+        #channel
     }
 }
 
-fn generate_streaming(method: &Method, proto: &str, path: String) -> TokenStream {
+fn generate_streaming(
+    method: &Method,
+    proto: &str,
+    path: String,
+    descriptor: TokenStream,
+    codec: &Codec,
+) -> TokenStream {
     let ident = format_ident!("{}", method.name);
 
-    let (request, response) = crate::replace_wellknown(proto, &method);
+    let (request, response) = codec.types(proto, method);
+    let channel = generate_channel(
+        method,
+        &request,
+        &response,
+        &quote!(tonic::codec::Streaming<#response>),
+    );
 
     quote! {
         pub async fn #ident(
             &mut self,
             request: impl tonic::IntoStreamingRequest<Message = #request>
-        ) -> Result<tonic::Response<tonic::codec::Streaming<#response>>, tonic::Status> {
+        ) -> Result<tonic::Response<tonic::codec::Streaming<#response>>, tonic::Status>
+        where
+            C: tonic::codec::MakeCodec<#request, #response>,
+        {
             self.inner.ready().await.map_err(|e| {
                         tonic::Status::new(tonic::Code::Unknown, format!("Service was not ready: {}", e.into()))
             })?;
-           let codec = tonic::codec::ProstCodec::default();
+           let codec = tonic::codec::MakeCodec::<#request, #response>::make_codec(&self.codec);
            let path = http::uri::PathAndQuery::from_static(#path);
-           self.inner.streaming(request.into_streaming_request(), path, codec).await
+           let mut request = request.into_streaming_request();
+           request.extensions_mut().insert(&#descriptor);
+           self.inner.streaming(request, path, codec).await
+        }
+
+        #channel
+    }
+}
+
+/// Generate the `{method}_channel` helper of a client or bidirectional
+/// streaming method, starting the call with the requests sent to a sink.
+///
+/// `decode` is the message decoded from the responses, and `response` the
+/// body of the response.
+fn generate_channel(
+    method: &Method,
+    request: &TokenStream,
+    decode: &TokenStream,
+    response: &TokenStream,
+) -> TokenStream {
+    let ident = format_ident!("{}", method.name);
+    let channel_ident = format_ident!("{}_channel", method.name.trim_start_matches("r#"));
+    let resolves = if method.server_streaming {
+        "to poll alongside sending the requests"
+    } else {
+        "resolving once the sink is closed or dropped"
+    };
+    let doc = generate_doc_comments(&[
+        format!(
+            " Start a call to `{}` with the requests sent to the returned sink,",
+            method.name
+        ),
+        format!(" along with the future of its response, {}.", resolves),
+    ]);
+
+    quote! {
+        #doc
+        pub fn #channel_ident(
+            &mut self,
+        ) -> (
+            tonic::client::RequestSink<#request>,
+            impl Future<Output = Result<tonic::Response<#response>, tonic::Status>> + '_,
+        )
+        where
+            C: tonic::codec::MakeCodec<#request, #decode>,
+        {
+            let (sink, requests) = tonic::client::request_channel();
+            (sink, self.#ident(requests))
         }
     }
 }
@@ -0,0 +1,378 @@
+//! The codec of the generated clients and servers, and the Rust types
+//! standing in for the messages of their methods.
+
+use proc_macro2::TokenStream;
+use prost_build::{Method, Service};
+use quote::{quote, ToTokens};
+
+/// The Rust types the well-known `google.protobuf` types map to, in the
+/// requests and responses of the generated clients and servers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WellKnownTypes {
+    /// The messages of `prost-types`, the default.
+    Prost,
+    /// `chrono::DateTime<chrono::Utc>` for `Timestamp` and
+    /// `chrono::Duration` for `Duration`, converted by the codecs of the
+    /// `chrono` feature of `tonic`.
+    Chrono,
+}
+
+impl WellKnownTypes {
+    fn extern_types(self) -> Vec<ExternType> {
+        match self {
+            WellKnownTypes::Prost => Vec::new(),
+            WellKnownTypes::Chrono => vec![
+                ExternType::new(
+                    ".google.protobuf.Timestamp",
+                    "::chrono::DateTime<::chrono::Utc>",
+                    "tonic::codec::ChronoTimestamp",
+                ),
+                ExternType::new(
+                    ".google.protobuf.Duration",
+                    "::chrono::Duration",
+                    "tonic::codec::ChronoDuration",
+                ),
+            ],
+        }
+    }
+}
+
+/// The `prost` crate the generated clients and servers encode and decode
+/// messages with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProstVersion {
+    /// The version `tonic` depends on, through `tonic::codec::ProstCodec`,
+    /// the default.
+    Tonic,
+    /// The `prost` dependency of the crate including the generated code,
+    /// whatever its major version, through a `ProstCodec` generated in the
+    /// module of each package declaring services.
+    Crate,
+}
+
+impl ProstVersion {
+    /// The path of the codec, from the client and server modules.
+    pub(crate) fn codec_path(self) -> &'static str {
+        match self {
+            ProstVersion::Tonic => "tonic::codec::ProstCodec",
+            ProstVersion::Crate => "super::ProstCodec",
+        }
+    }
+
+    /// Generate the `ProstCodec` of a package, if generated.
+    ///
+    /// Messages are encoded to and decoded from a `Vec<u8>` and a `&[u8]`,
+    /// which implement the `BufMut` and `Buf` of every `bytes` version the
+    /// `prost` versions build on, unlike the buffers of `tonic`.
+    pub(crate) fn generate_codec(self) -> TokenStream {
+        if self == ProstVersion::Tonic {
+            return TokenStream::new();
+        }
+
+        quote! {
+            /// Encodes and decodes the messages of the generated clients and
+            /// servers with the `prost` dependency of this crate.
+            pub struct ProstCodec<T, U>(::std::marker::PhantomData<fn() -> (T, U)>);
+
+            impl<T, U> Default for ProstCodec<T, U> {
+                fn default() -> Self {
+                    ProstCodec(::std::marker::PhantomData)
+                }
+            }
+
+            impl<T, U> tonic::codec::Codec for ProstCodec<T, U>
+            where
+                T: ::prost::Message + Send + 'static,
+                U: ::prost::Message + Default + Send + 'static,
+            {
+                type Encode = T;
+                type Decode = U;
+
+                type Encoder = ProstCodec<T, U>;
+                type Decoder = ProstCodec<T, U>;
+
+                fn encoder(&mut self) -> Self::Encoder {
+                    ProstCodec::default()
+                }
+
+                fn decoder(&mut self) -> Self::Decoder {
+                    ProstCodec::default()
+                }
+            }
+
+            impl<T: ::prost::Message, U> tonic::codec::Encoder for ProstCodec<T, U> {
+                type Item = T;
+                type Error = tonic::Status;
+
+                fn encode(
+                    &mut self,
+                    item: T,
+                    buf: &mut tonic::codec::EncodeBuf<'_>,
+                ) -> Result<(), tonic::Status> {
+                    let mut bytes = Vec::with_capacity(item.encoded_len());
+                    ::prost::Message::encode(&item, &mut bytes)
+                        .expect("Message only errors if not enough space");
+                    tonic::codegen::BufMut::put_slice(buf, &bytes);
+                    Ok(())
+                }
+
+                fn encoded_len(&self, item: &T) -> Option<usize> {
+                    Some(item.encoded_len())
+                }
+            }
+
+            impl<T, U: ::prost::Message + Default> tonic::codec::Decoder for ProstCodec<T, U> {
+                type Item = U;
+                type Error = tonic::Status;
+
+                fn decode(
+                    &mut self,
+                    buf: &mut tonic::codec::DecodeBuf<'_>,
+                ) -> Result<Option<U>, tonic::Status> {
+                    let bytes = tonic::codegen::Buf::to_bytes(buf);
+                    ::prost::Message::decode(&bytes[..])
+                        .map(Some)
+                        .map_err(|error| tonic::Status::internal(error.to_string()))
+                }
+            }
+        }
+    }
+}
+
+/// A message mapped to a Rust type through a `tonic::codec::Conversion`.
+#[derive(Debug, Clone)]
+pub(crate) struct ExternType {
+    /// The fully qualified proto name of the message, like `.pkg.Message`.
+    proto_path: String,
+    rust_type: String,
+    conversion: String,
+}
+
+impl ExternType {
+    pub(crate) fn new(proto_path: &str, rust_type: &str, conversion: &str) -> Self {
+        let proto_path = if proto_path.starts_with('.') {
+            proto_path.to_string()
+        } else {
+            format!(".{}", proto_path)
+        };
+
+        ExternType {
+            proto_path,
+            rust_type: rust_type.to_string(),
+            conversion: conversion.to_string(),
+        }
+    }
+
+    fn rust_type(&self) -> TokenStream {
+        syn::parse_str::<syn::Type>(&self.rust_type)
+            .unwrap_or_else(|_| panic!("invalid extern type: {}", self.rust_type))
+            .into_token_stream()
+    }
+
+    fn conversion(&self) -> TokenStream {
+        syn::parse_str::<syn::Path>(&self.conversion)
+            .unwrap_or_else(|_| panic!("invalid conversion path: {}", self.conversion))
+            .into_token_stream()
+    }
+}
+
+/// The codec used by the generated code, converting the messages mapped to
+/// other Rust types.
+pub(crate) struct Codec {
+    path: TokenStream,
+    extern_types: Vec<ExternType>,
+}
+
+impl Codec {
+    pub(crate) fn new(
+        path: &str,
+        extern_types: &[ExternType],
+        well_known_types: WellKnownTypes,
+    ) -> Self {
+        let path = syn::parse_str::<syn::Path>(path)
+            .expect("invalid codec path")
+            .into_token_stream();
+
+        // The types mapped explicitly come first, so they take precedence.
+        let mut all = extern_types.to_vec();
+        all.extend(well_known_types.extern_types());
+
+        Codec {
+            path,
+            extern_types: all,
+        }
+    }
+
+    /// The types of the requests and responses of `method`, as seen by the
+    /// generated code.
+    pub(crate) fn types(&self, proto_path: &str, method: &Method) -> (TokenStream, TokenStream) {
+        let (request, response) = crate::replace_wellknown(proto_path, method);
+        let request = self
+            .extern_type(&method.input_proto_type)
+            .map_or(request, ExternType::rust_type);
+        let response = self
+            .extern_type(&method.output_proto_type)
+            .map_or(response, ExternType::rust_type);
+
+        (request, response)
+    }
+
+    /// The type of the codec of a client method.
+    pub(crate) fn client(&self, proto_path: &str, method: &Method) -> TokenStream {
+        let (request, response) = crate::replace_wellknown(proto_path, method);
+        self.ty(
+            (&method.input_proto_type, request),
+            (&method.output_proto_type, response),
+        )
+    }
+
+    /// The type of the codec of a server method.
+    pub(crate) fn server(&self, proto_path: &str, method: &Method) -> TokenStream {
+        let (request, response) = crate::replace_wellknown(proto_path, method);
+        self.ty(
+            (&method.output_proto_type, response),
+            (&method.input_proto_type, request),
+        )
+    }
+
+    /// The codec encoding `encode` and decoding `decode`, given as their
+    /// proto name and generated message.
+    fn ty(&self, encode: (&str, TokenStream), decode: (&str, TokenStream)) -> TokenStream {
+        let path = &self.path;
+        let (encode_type, encode) = encode;
+        let (decode_type, decode) = decode;
+
+        let encode_conversion = self.extern_type(encode_type).map(ExternType::conversion);
+        let decode_conversion = self.extern_type(decode_type).map(ExternType::conversion);
+        if encode_conversion.is_none() && decode_conversion.is_none() {
+            return quote!(#path<#encode, #decode>);
+        }
+
+        let encode_conversion =
+            encode_conversion.unwrap_or_else(|| quote!(tonic::codec::Identity<#encode>));
+        let decode_conversion =
+            decode_conversion.unwrap_or_else(|| quote!(tonic::codec::Identity<#decode>));
+
+        quote! {
+            tonic::codec::ConvertCodec<#path<#encode, #decode>, #encode_conversion, #decode_conversion>
+        }
+    }
+
+    /// Whether the message named `proto_type` is mapped to another Rust type.
+    pub(crate) fn is_mapped(&self, proto_type: &str) -> bool {
+        self.extern_type(proto_type).is_some()
+    }
+
+    fn extern_type(&self, proto_type: &str) -> Option<&ExternType> {
+        self.extern_types
+            .iter()
+            .find(|extern_type| extern_type.proto_path == proto_type)
+    }
+}
+
+/// The codecs of the methods of a generated client or server, one for each
+/// pair of message types they encode and decode.
+pub(crate) struct MethodCodecs {
+    codecs: Vec<(TokenStream, TokenStream, TokenStream)>,
+}
+
+impl MethodCodecs {
+    /// The codecs of the methods of a client of `service`.
+    pub(crate) fn client(codec: &Codec, proto_path: &str, service: &Service) -> Self {
+        let mut codecs = MethodCodecs { codecs: Vec::new() };
+        for method in &service.methods {
+            let (request, response) = codec.types(proto_path, method);
+            codecs.push(request, response, codec.client(proto_path, method));
+        }
+        codecs
+    }
+
+    /// The codecs of the methods of a server of `service`.
+    pub(crate) fn server(codec: &Codec, proto_path: &str, service: &Service) -> Self {
+        let mut codecs = MethodCodecs { codecs: Vec::new() };
+        for method in &service.methods {
+            let (request, response) = codec.types(proto_path, method);
+            codecs.push(response, request, codec.server(proto_path, method));
+        }
+        codecs
+    }
+
+    fn push(&mut self, encode: TokenStream, decode: TokenStream, codec: TokenStream) {
+        let key = (encode.to_string(), decode.to_string());
+        let known = self
+            .codecs
+            .iter()
+            .any(|(e, d, _)| (e.to_string(), d.to_string()) == key);
+        if !known {
+            self.codecs.push((encode, decode, codec));
+        }
+    }
+
+    /// The bounds of a `C` making the codecs of every method.
+    pub(crate) fn bounds(&self) -> TokenStream {
+        let bounds = self
+            .codecs
+            .iter()
+            .map(|(encode, decode, _)| quote!(tonic::codec::MakeCodec<#encode, #decode>));
+        quote!(#(#bounds)+*)
+    }
+
+    /// Generate the `DefaultCodec`, making the codecs chosen when generating
+    /// the code.
+    pub(crate) fn generate_default(&self) -> TokenStream {
+        let impls = self.codecs.iter().map(|(encode, decode, codec)| {
+            quote! {
+                impl tonic::codec::MakeCodec<#encode, #decode> for DefaultCodec {
+                    type Codec = #codec;
+
+                    fn make_codec(&self) -> Self::Codec {
+                        Default::default()
+                    }
+                }
+            }
+        });
+
+        quote! {
+            /// Makes the codecs chosen when generating this code.
+            #[derive(Debug, Clone, Copy, Default)]
+            pub struct DefaultCodec;
+
+            #(#impls)*
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_types_take_precedence() {
+        let explicit = [ExternType::new(
+            "google.protobuf.Timestamp",
+            "my_time::Instant",
+            "my_time::InstantConversion",
+        )];
+        let codec = Codec::new(
+            "tonic::codec::ProstCodec",
+            &explicit,
+            WellKnownTypes::Chrono,
+        );
+
+        let timestamp = codec.extern_type(".google.protobuf.Timestamp").unwrap();
+        assert_eq!(timestamp.rust_type, "my_time::Instant");
+        let duration = codec.extern_type(".google.protobuf.Duration").unwrap();
+        assert_eq!(duration.rust_type, "::chrono::Duration");
+        assert!(codec.extern_type(".google.protobuf.Empty").is_none());
+    }
+
+    #[test]
+    fn generates_a_codec_for_the_prost_of_the_crate() {
+        assert!(ProstVersion::Tonic.generate_codec().is_empty());
+
+        let codec = ProstVersion::Crate.generate_codec().to_string();
+        assert!(codec.contains("pub struct ProstCodec"));
+        assert!(codec.contains(":: prost :: Message :: decode"));
+        assert_eq!(ProstVersion::Crate.codec_path(), "super::ProstCodec");
+    }
+}
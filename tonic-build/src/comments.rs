@@ -0,0 +1,240 @@
+//! The doc comments of the clients and servers, rendered from the comments
+//! of the services and methods in the `.proto` files.
+
+use prost_build::{Comments, Service};
+use prost_types::FileDescriptorProto;
+use std::collections::HashMap;
+
+/// How the comments of the `.proto` files are rendered in the doc comments
+/// of the generated clients and servers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentStyle {
+    /// Kept as they are, rendered as Markdown by rustdoc, the default.
+    Markdown,
+    /// Stripped of their Markdown syntax: headings, emphasis, code spans and
+    /// links, the latter keeping their text and URL.
+    Plain,
+    /// Kept as they are in a `text` code block, for comments laid out as
+    /// plain text, whose brackets and indentation rustdoc would interpret.
+    Verbatim,
+}
+
+/// The rendering of the comments, along with the files the services are
+/// declared in.
+#[derive(Debug)]
+pub(crate) struct DocComments {
+    style: Option<CommentStyle>,
+    detached: bool,
+    /// The files declaring the services, by fully qualified name, for their
+    /// location, if appended.
+    files: Option<HashMap<String, String>>,
+}
+
+impl DocComments {
+    pub(crate) fn new(
+        style: CommentStyle,
+        detached: bool,
+        locations: bool,
+        files: &[FileDescriptorProto],
+    ) -> Self {
+        let files = if locations {
+            let mut declared = HashMap::new();
+            for file in files {
+                for service in &file.service {
+                    let name = match file.package() {
+                        "" => service.name().to_string(),
+                        package => format!("{}.{}", package, service.name()),
+                    };
+                    declared.insert(name, file.name().to_string());
+                }
+            }
+            Some(declared)
+        } else {
+            None
+        };
+
+        DocComments {
+            style: Some(style).filter(|style| *style != CommentStyle::Markdown),
+            detached,
+            files,
+        }
+    }
+
+    /// Render the leading comments of `service` and its methods, the ones
+    /// the generated items are documented with.
+    pub(crate) fn render(&self, service: &mut Service) {
+        let service_path = crate::service_path(service);
+        let file = self
+            .files
+            .as_ref()
+            .and_then(|files| files.get(&service_path));
+
+        for method in &mut service.methods {
+            let path = format!("{}.{}", service_path, method.proto_name);
+            method.comments.leading = self.lines(&method.comments, &path, file);
+        }
+        service.comments.leading = self.lines(&service.comments, &service_path, file);
+    }
+
+    fn lines(&self, comments: &Comments, path: &str, file: Option<&String>) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.detached {
+            for block in &comments.leading_detached {
+                lines.extend(block.iter().cloned());
+                lines.push(String::new());
+            }
+        }
+        lines.extend(comments.leading.iter().cloned());
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+
+        match self.style {
+            Some(CommentStyle::Plain) => {
+                lines = lines.iter().map(|line| strip_markdown(line)).collect();
+            }
+            Some(CommentStyle::Verbatim) if !lines.is_empty() => {
+                lines.insert(0, " ```text".to_string());
+                lines.push(" ```".to_string());
+            }
+            _ => {}
+        }
+
+        if let Some(file) = file {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(format!(" Declared as `{}` in `{}`.", path, file));
+        }
+        lines
+    }
+}
+
+/// `line` without its Markdown syntax.
+fn strip_markdown(line: &str) -> String {
+    let content = line.trim_start();
+    let indent = &line[..line.len() - content.len()];
+    let content = match content.trim_start_matches('#') {
+        heading if heading.len() < content.len() && heading.starts_with(' ') => {
+            heading.trim_start()
+        }
+        _ => content,
+    };
+
+    let mut stripped = String::from(indent);
+    let mut chars = content.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '`' => {}
+            // Emphasis, but not the `*` or `_` within words.
+            '*' | '_' => {
+                let before = content[..i].chars().next_back();
+                let after = content[i + c.len_utf8()..].chars().next();
+                let within_word = before.is_some_and(char::is_alphanumeric)
+                    && after.is_some_and(char::is_alphanumeric);
+                if within_word {
+                    stripped.push(c);
+                }
+            }
+            // `[text](url)` as `text (url)`, `[text]` as `text`.
+            '[' => match link(&content[i..]) {
+                Some((text, url, len)) => {
+                    stripped.push_str(text);
+                    if let Some(url) = url {
+                        stripped.push_str(&format!(" ({})", url));
+                    }
+                    while chars.peek().is_some_and(|(j, _)| *j < i + len) {
+                        chars.next();
+                    }
+                }
+                None => stripped.push(c),
+            },
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+/// The text, the URL and the length of the link `[text](url)` or reference
+/// `[text]` starting `s`.
+fn link(s: &str) -> Option<(&str, Option<&str>, usize)> {
+    let close = s.find(']')?;
+    let text = &s[1..close];
+    let rest = &s[close + 1..];
+    if rest.starts_with('(') {
+        if let Some(end) = rest.find(')') {
+            return Some((text, Some(&rest[1..end]), close + end + 2));
+        }
+    }
+    Some((text, None, close + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comments(detached: &[&[&str]], leading: &[&str]) -> Comments {
+        let lines = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        Comments {
+            leading_detached: detached.iter().map(|block| lines(block)).collect(),
+            leading: lines(leading),
+            trailing: Vec::new(),
+        }
+    }
+
+    fn docs(style: CommentStyle, detached: bool, file: Option<&str>) -> DocComments {
+        DocComments {
+            style: Some(style).filter(|style| *style != CommentStyle::Markdown),
+            detached,
+            files: file.map(|file| {
+                let mut files = HashMap::new();
+                files.insert("pkg.Service".to_string(), file.to_string());
+                files
+            }),
+        }
+    }
+
+    #[test]
+    fn strips_markdown() {
+        assert_eq!(
+            strip_markdown(" ## Returns the **current** `user_id`"),
+            " Returns the current user_id"
+        );
+        assert_eq!(
+            strip_markdown(" See [the guide](https://example.com) and [Other]."),
+            " See the guide (https://example.com) and Other."
+        );
+        assert_eq!(strip_markdown("   * a_b *c*"), "    a_b c");
+    }
+
+    #[test]
+    fn includes_detached_comments_and_locations() {
+        let docs = docs(CommentStyle::Verbatim, true, Some("pkg/service.proto"));
+        let comments = comments(&[&[" Section."]], &[" Gets [a] thing.", ""]);
+        let file = docs.files.as_ref().unwrap().get("pkg.Service");
+
+        assert_eq!(
+            docs.lines(&comments, "pkg.Service.Get", file),
+            vec![
+                " ```text",
+                " Section.",
+                "",
+                " Gets [a] thing.",
+                " ```",
+                "",
+                " Declared as `pkg.Service.Get` in `pkg/service.proto`.",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_comments_by_default() {
+        let docs = docs(CommentStyle::Markdown, false, None);
+        let comments = comments(&[&[" Section."]], &[" Gets *a* thing."]);
+
+        assert_eq!(
+            docs.lines(&comments, "pkg.Service.Get", None),
+            vec![" Gets *a* thing."]
+        );
+    }
+}
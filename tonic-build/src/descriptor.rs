@@ -0,0 +1,106 @@
+use crate::{http, naive_snake_case, options::RawOptions, server::method_path, service_path};
+use proc_macro2::{Ident, Literal, TokenStream};
+use prost_build::{Method, Service};
+use prost_types::method_options::IdempotencyLevel;
+use quote::{format_ident, quote};
+
+/// Generate the `{service}_descriptor` module, holding constants describing
+/// the service and its methods.
+pub(crate) fn generate(service: &Service, options: &RawOptions) -> TokenStream {
+    let descriptor_mod = format_ident!("{}_descriptor", naive_snake_case(&service.name));
+    let service_name = service_path(service);
+
+    let mut methods = TokenStream::new();
+    let mut method_idents = Vec::new();
+    for method in &service.methods {
+        let ident = method_ident(method);
+        let name = &method.proto_name;
+        let path = method_path(service, method);
+        let kind = match (method.client_streaming, method.server_streaming) {
+            (false, false) => quote!(Unary),
+            (true, false) => quote!(ClientStreaming),
+            (false, true) => quote!(ServerStreaming),
+            (true, true) => quote!(Streaming),
+        };
+        let level = match method.options.idempotency_level() {
+            IdempotencyLevel::NoSideEffects => quote!(NoSideEffects),
+            IdempotencyLevel::Idempotent => quote!(Idempotent),
+            IdempotencyLevel::IdempotencyUnknown => quote!(Unknown),
+        };
+        let http_rules = http::generate_rules(options.method(&path));
+        let method_options = Literal::byte_string(options.method(&path));
+        let doc = format!(" Describes the `{}` method.", name);
+
+        methods.extend(quote! {
+            #[doc = #doc]
+            pub const #ident: MethodDescriptor = MethodDescriptor {
+                name: #name,
+                service: #service_name,
+                path: #path,
+                kind: MethodKind::#kind,
+                idempotency_level: IdempotencyLevel::#level,
+                options: #method_options,
+                http_rules: #http_rules,
+            };
+        });
+        method_idents.push(ident);
+    }
+
+    let service_doc = format!(" Describes the `{}` service.", service_name);
+    let service_options = Literal::byte_string(options.service(&service_name));
+
+    quote! {
+        /// Generated descriptors of a service and its methods.
+        pub mod #descriptor_mod {
+            use tonic::descriptor::*;
+
+            #[doc = #service_doc]
+            pub const SERVICE: ServiceDescriptor = ServiceDescriptor {
+                name: #service_name,
+                methods: &[#(#method_idents),*],
+                options: #service_options,
+            };
+
+            #methods
+        }
+    }
+}
+
+/// The path of the descriptor of `service`, from the module of its package.
+pub(crate) fn service_descriptor(service: &Service) -> TokenStream {
+    let descriptor_mod = format_ident!("{}_descriptor", naive_snake_case(&service.name));
+    quote!(#descriptor_mod::SERVICE)
+}
+
+/// The path of the descriptor of `method` of `service`, from the module at
+/// `proto_path`.
+pub(crate) fn method_descriptor(
+    proto_path: &str,
+    service: &Service,
+    method: &Method,
+) -> TokenStream {
+    let proto_path = syn::parse_str::<syn::Path>(proto_path).unwrap();
+    let descriptor_mod = format_ident!("{}_descriptor", naive_snake_case(&service.name));
+    let ident = method_ident(method);
+    quote!(#proto_path::#descriptor_mod::#ident)
+}
+
+fn method_ident(method: &Method) -> Ident {
+    format_ident!("{}", naive_snake_case(&method.proto_name).to_uppercase())
+}
+
+/// Generate the `PACKAGE` constant describing `package` and its `services`,
+/// along with the `FILE_DESCRIPTOR_SET` embedded after the code generation.
+pub(crate) fn generate_package(package: &str, services: &TokenStream) -> TokenStream {
+    let doc = format!(" Describes the `{}` package and its services.", package);
+
+    quote! {
+        #[doc = #doc]
+        pub const PACKAGE: tonic::descriptor::PackageDescriptor =
+            tonic::descriptor::PackageDescriptor {
+                name: #package,
+                services: &[#services],
+                file_descriptor_set: FILE_DESCRIPTOR_SET,
+            };
+    }
+}
@@ -0,0 +1,114 @@
+//! Encoded `FileDescriptorSet`s, for server reflection.
+
+use crate::options::RawFileDescriptorSet;
+use proc_macro2::Literal;
+use prost::Message;
+use prost_types::FileDescriptorProto;
+use std::{collections::HashSet, fs, io, path::Path};
+
+/// Append a `FILE_DESCRIPTOR_SET` constant to the code generated in
+/// `out_dir` for each package of `files`, holding the descriptors of its
+/// files and of their dependencies.
+///
+/// `raw_files` is the set of `files` as encoded by `protoc`, whose custom
+/// options are kept.
+pub(crate) fn embed(
+    out_dir: &Path,
+    files: &[FileDescriptorProto],
+    raw_files: &[u8],
+    extern_path: &[(String, String)],
+) -> io::Result<()> {
+    let raw_files = RawFileDescriptorSet::decode(raw_files)?.file;
+
+    let mut packages = Vec::new();
+    for file in files {
+        if !packages.contains(&file.package()) && !crate::is_extern(file.package(), extern_path) {
+            packages.push(file.package());
+        }
+    }
+
+    for package in packages {
+        let path = out_dir.join(format!("{}.rs", package));
+        let mut code = fs::read_to_string(&path)?;
+        let set = RawFileDescriptorSet {
+            file: package_files(package, files)
+                .map(|i| raw_files[i].clone())
+                .collect(),
+        };
+        let mut buf = Vec::with_capacity(set.encoded_len());
+        set.encode(&mut buf)
+            .expect("Vec<u8> provides the needed capacity");
+        let set = Literal::byte_string(&buf);
+
+        code.push_str(
+            "/// The encoded `FileDescriptorSet` of this package and its dependencies,\n\
+             /// for server reflection.\n",
+        );
+        code.push_str(&format!(
+            "pub const FILE_DESCRIPTOR_SET: &[u8] = {};\n",
+            set
+        ));
+        fs::write(&path, code)?;
+    }
+
+    Ok(())
+}
+
+/// The indices of the files of `package` along with their transitive
+/// dependencies, in the order of `files`, where dependencies come first.
+fn package_files<'a>(
+    package: &str,
+    files: &'a [FileDescriptorProto],
+) -> impl Iterator<Item = usize> + 'a {
+    let mut needed = HashSet::new();
+    for file in files.iter().rev() {
+        if file.package() == package || needed.contains(file.name()) {
+            needed.insert(file.name());
+            needed.extend(file.dependency.iter().map(String::as_str));
+        }
+    }
+
+    files
+        .iter()
+        .enumerate()
+        .filter(move |(_, file)| needed.contains(file.name()))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, package: &str, dependencies: &[&str]) -> FileDescriptorProto {
+        FileDescriptorProto {
+            name: Some(name.to_string()),
+            package: Some(package.to_string()),
+            dependency: dependencies.iter().map(|d| d.to_string()).collect(),
+            ..FileDescriptorProto::default()
+        }
+    }
+
+    #[test]
+    fn includes_transitive_dependencies() {
+        let files = vec![
+            file("google/protobuf/empty.proto", "google.protobuf", &[]),
+            file("unused.proto", "other", &[]),
+            file("common.proto", "common", &["google/protobuf/empty.proto"]),
+            file("a.proto", "pkg", &["common.proto"]),
+            file("b.proto", "pkg", &[]),
+        ];
+
+        let names = package_files("pkg", &files)
+            .map(|i| files[i].name())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "google/protobuf/empty.proto",
+                "common.proto",
+                "a.proto",
+                "b.proto"
+            ]
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! The typed errors of the methods, declared with
+//! [`Builder::method_error`](crate::Builder::method_error).
+
+use crate::{naive_snake_case, rust_ident, service_path};
+use proc_macro2::{Ident, TokenStream};
+use prost_build::Service;
+use prost_types::FileDescriptorProto;
+use quote::{format_ident, quote};
+
+/// The standard `google.rpc` error details, decoded by `tonic` into the
+/// variants of `tonic::error_details::ErrorDetail` of the same name.
+const STANDARD_DETAILS: &[&str] = &[
+    "ErrorInfo",
+    "RetryInfo",
+    "DebugInfo",
+    "QuotaFailure",
+    "PreconditionFailure",
+    "BadRequest",
+    "RequestInfo",
+    "ResourceInfo",
+    "Help",
+    "LocalizedMessage",
+];
+
+/// An error detail message a method fails with.
+#[derive(Debug, Clone)]
+pub(crate) struct MethodError {
+    /// The fully qualified name of the method, like `pkg.Service.Method`.
+    pub(crate) method: String,
+    /// The fully qualified name of the message, like `.pkg.NotFound`.
+    pub(crate) detail: String,
+    /// The `tonic::Code` the error is sent with, like `NotFound`.
+    pub(crate) code: String,
+}
+
+/// The declared errors, with the packages their messages are resolved in.
+#[derive(Debug, Default)]
+pub(crate) struct MethodErrors {
+    errors: Vec<MethodError>,
+    packages: Vec<String>,
+    extern_path: Vec<(String, String)>,
+}
+
+impl MethodErrors {
+    pub(crate) fn new(
+        errors: Vec<MethodError>,
+        files: &[FileDescriptorProto],
+        extern_path: &[(String, String)],
+    ) -> Self {
+        let mut extern_path = extern_path.to_vec();
+        extern_path.push((".google.protobuf".to_string(), "::prost_types".to_string()));
+
+        MethodErrors {
+            errors,
+            packages: files.iter().map(|f| f.package().to_string()).collect(),
+            extern_path,
+        }
+    }
+
+    /// Generate the `{service}_errors` module, holding an enum of the errors
+    /// of each method declaring some.
+    pub(crate) fn generate(&self, service: &Service) -> TokenStream {
+        let service_path = service_path(service);
+
+        let mut enums = TokenStream::new();
+        for method in &service.methods {
+            let method_path = format!("{}.{}", service_path, method.proto_name);
+            let errors = self
+                .errors
+                .iter()
+                .filter(|error| error.method == method_path)
+                .collect::<Vec<_>>();
+            if errors.is_empty() {
+                continue;
+            }
+
+            let name = format_ident!("{}Error", upper_camel(&method.name));
+            enums.extend(self.generate_enum(&service.package, &name, &method.proto_name, &errors));
+        }
+
+        if enums.is_empty() {
+            return enums;
+        }
+
+        let errors_mod = format_ident!("{}_errors", naive_snake_case(&service.name));
+        quote! {
+            /// Generated typed errors of the methods of a service.
+            pub mod #errors_mod {
+                #![allow(deprecated)]
+                use tonic::{error_details::ErrorDetail, Code, Status};
+
+                #enums
+            }
+        }
+    }
+
+    fn generate_enum(
+        &self,
+        package: &str,
+        name: &Ident,
+        method: &str,
+        errors: &[&MethodError],
+    ) -> TokenStream {
+        let mut variants = TokenStream::new();
+        let mut decode = TokenStream::new();
+        let mut encode = TokenStream::new();
+        let mut display = TokenStream::new();
+
+        for error in errors {
+            let proto_name = error.detail.trim_start_matches('.');
+            let type_name = proto_name.rsplit('.').next().unwrap();
+            let variant = format_ident!("{}", type_name);
+            let code = format_ident!("{}", error.code);
+            let doc = format!(
+                " The `{}` error detail, sent with the `{}` code.",
+                proto_name, error.code
+            );
+
+            match proto_name.strip_prefix("google.rpc.") {
+                Some(standard) if STANDARD_DETAILS.contains(&standard) => {
+                    let standard = format_ident!("{}", standard);
+                    variants.extend(quote! {
+                        #[doc = #doc]
+                        #variant(tonic::error_details::#standard),
+                    });
+                    decode.extend(quote! {
+                        ErrorDetail::#standard(detail) => return #name::#variant(detail),
+                    });
+                    encode.extend(quote! {
+                        #name::#variant(detail) => Status::with_error_details(
+                            Code::#code,
+                            #proto_name,
+                            vec![ErrorDetail::#standard(detail)],
+                        ),
+                    });
+                }
+                _ => {
+                    let ty = self.rust_type(package, &error.detail);
+                    let type_url = format!("type.googleapis.com/{}", proto_name);
+                    variants.extend(quote! {
+                        #[doc = #doc]
+                        #variant(#ty),
+                    });
+                    decode.extend(quote! {
+                        ErrorDetail::Other { type_url, value } if type_url == #type_url => {
+                            if let Ok(detail) = <#ty as ::prost::Message>::decode(&value[..]) {
+                                return #name::#variant(detail);
+                            }
+                        }
+                    });
+                    encode.extend(quote! {
+                        #name::#variant(detail) => {
+                            let mut value = Vec::new();
+                            ::prost::Message::encode(&detail, &mut value)
+                                .expect("Message only errors if not enough space");
+                            Status::with_error_details(
+                                Code::#code,
+                                #proto_name,
+                                vec![ErrorDetail::Other {
+                                    type_url: #type_url.to_string(),
+                                    value,
+                                }],
+                            )
+                        }
+                    });
+                }
+            }
+            display.extend(quote! {
+                #name::#variant(_) => f.write_str(#proto_name),
+            });
+        }
+
+        let doc = format!(" The errors of the `{}` method.", method);
+        quote! {
+            #[doc = #doc]
+            ///
+            /// Converted from the `Status` of a failed call, and into the
+            /// `Status` a handler fails with.
+            #[derive(Debug, Clone)]
+            pub enum #name {
+                #variants
+                /// A status without any of the error details above.
+                Status(Status),
+            }
+
+            impl From<Status> for #name {
+                fn from(status: Status) -> Self {
+                    for detail in status.error_details().unwrap_or_default() {
+                        match detail {
+                            #decode
+                            _ => {}
+                        }
+                    }
+                    #name::Status(status)
+                }
+            }
+
+            impl From<#name> for Status {
+                fn from(error: #name) -> Self {
+                    match error {
+                        #encode
+                        #name::Status(status) => status,
+                    }
+                }
+            }
+
+            impl std::fmt::Display for #name {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    match self {
+                        #display
+                        #name::Status(status) => status.fmt(f),
+                    }
+                }
+            }
+
+            impl std::error::Error for #name {}
+        }
+    }
+
+    /// The Rust type of the message `detail`, like `.pkg.Outer.Inner`, from
+    /// the errors module generated in the module of `package`.
+    fn rust_type(&self, package: &str, detail: &str) -> TokenStream {
+        let path = match self.extern_type(detail) {
+            Some(path) => path,
+            None => {
+                let detail_package = self
+                    .packages
+                    .iter()
+                    .filter(|p| p.is_empty() || detail.starts_with(&format!(".{}.", p)))
+                    .max_by_key(|p| p.len())
+                    .unwrap_or_else(|| panic!("unknown error detail message: {}", detail));
+                let name = match detail_package.as_str() {
+                    "" => &detail[1..],
+                    p => &detail[p.len() + 2..],
+                };
+
+                let from = package
+                    .split('.')
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>();
+                let to = detail_package
+                    .split('.')
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>();
+                let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+                // One more `super` for the errors module.
+                let mut path = vec!["super".to_string(); from.len() - common + 1];
+                path.extend(to[common..].iter().map(|s| naive_snake_case(s)));
+                path.push(type_path(name));
+                path.join("::")
+            }
+        };
+
+        let segments = path
+            .split("::")
+            .filter(|s| !s.is_empty())
+            .map(|segment| match segment {
+                "super" | "crate" | "self" => format_ident!("{}", segment),
+                _ => rust_ident(segment),
+            });
+        if path.starts_with("::") {
+            quote!(#(::#segments)*)
+        } else {
+            quote!(#(#segments)::*)
+        }
+    }
+
+    fn extern_type(&self, detail: &str) -> Option<String> {
+        self.extern_path
+            .iter()
+            .filter(|(proto, _)| detail.starts_with(&format!("{}.", proto)))
+            .max_by_key(|(proto, _)| proto.len())
+            .map(|(proto, rust)| format!("{}::{}", rust, type_path(&detail[proto.len() + 1..])))
+    }
+}
+
+/// The path of the nested message `name`, like `Outer.Inner`, from the
+/// module of its package: `outer::Inner`.
+fn type_path(name: &str) -> String {
+    let mut segments = name.split('.').collect::<Vec<_>>();
+    let last = segments.pop().unwrap();
+    segments
+        .iter()
+        .map(|s| naive_snake_case(s))
+        .chain(Some(last.to_string()))
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// `get_user` as `GetUser`.
+fn upper_camel(name: &str) -> String {
+    name.trim_start_matches("r#")
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(packages: &[&str], extern_path: &[(&str, &str)]) -> MethodErrors {
+        MethodErrors {
+            errors: Vec::new(),
+            packages: packages.iter().map(|p| p.to_string()).collect(),
+            extern_path: extern_path
+                .iter()
+                .map(|(p, r)| (p.to_string(), r.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn resolves_detail_types() {
+        let errors = errors(
+            &["shop.orders", "shop.errors", "shop"],
+            &[(".acme", "::acme")],
+        );
+        let path = |package, detail| errors.rust_type(package, detail).to_string();
+
+        assert_eq!(
+            path("shop.orders", ".shop.orders.NotFound"),
+            "super :: NotFound"
+        );
+        assert_eq!(
+            path("shop.orders", ".shop.errors.Conflict.Reason"),
+            "super :: super :: errors :: conflict :: Reason"
+        );
+        assert_eq!(
+            path("shop.orders", ".shop.Fault"),
+            "super :: super :: Fault"
+        );
+        assert_eq!(
+            path("shop.orders", ".acme.type.Denied"),
+            ":: acme :: r#type :: Denied"
+        );
+    }
+
+    #[test]
+    fn names_enums_after_methods() {
+        assert_eq!(upper_camel("get_user"), "GetUser");
+        assert_eq!(upper_camel("r#type"), "Type");
+    }
+}
@@ -0,0 +1,143 @@
+//! The Cargo features gating the generated packages and services.
+
+use proc_macro2::TokenStream;
+use prost_types::FileDescriptorProto;
+use quote::quote;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs, io,
+    path::Path,
+};
+
+/// The attribute gating the code generated for the package or the service
+/// with the fully qualified proto name `name`, behind a feature of the same
+/// name.
+pub(crate) fn gate(name: &str) -> TokenStream {
+    quote!(#[cfg(feature = #name)])
+}
+
+/// Write to `path` the `[features]` section of a manifest declaring a feature
+/// per package and per service of `files`, each enabling the packages it
+/// depends on.
+pub(crate) fn write(
+    path: &Path,
+    files: &[FileDescriptorProto],
+    extern_path: &[(String, String)],
+) -> io::Result<()> {
+    fs::write(path, manifest(files, extern_path))
+}
+
+fn manifest(files: &[FileDescriptorProto], extern_path: &[(String, String)]) -> String {
+    let packages = files
+        .iter()
+        .map(|file| (file.name(), file.package()))
+        .collect::<HashMap<_, _>>();
+
+    // The packages each feature enables.
+    let mut features = BTreeMap::<String, BTreeSet<&str>>::new();
+    for file in files {
+        let package = file.package();
+        if crate::is_extern(package, extern_path) {
+            continue;
+        }
+
+        for service in &file.service {
+            let dependencies = features
+                .entry(feature(package, service.name()))
+                .or_default();
+            if !package.is_empty() {
+                dependencies.insert(package);
+            }
+        }
+        if package.is_empty() {
+            continue;
+        }
+
+        let dependencies = features.entry(package.to_string()).or_default();
+        for dependency in &file.dependency {
+            match packages.get(dependency.as_str()) {
+                Some(&other)
+                    if other != package
+                        && !other.is_empty()
+                        && !crate::is_extern(other, extern_path) =>
+                {
+                    dependencies.insert(other);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut manifest = String::from(
+        "# The features gating the packages and services generated by tonic-build.\n[features]\n",
+    );
+    for (feature, dependencies) in features {
+        let dependencies = dependencies
+            .iter()
+            .map(|dependency| format!("{:?}", dependency))
+            .collect::<Vec<_>>();
+        manifest.push_str(&format!("{:?} = [{}]\n", feature, dependencies.join(", ")));
+    }
+    manifest
+}
+
+/// The feature of the service `name` of `package`.
+fn feature(package: &str, name: &str) -> String {
+    match package {
+        "" => name.to_string(),
+        package => format!("{}.{}", package, name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_types::ServiceDescriptorProto;
+
+    fn file(
+        name: &str,
+        package: &str,
+        dependency: &[&str],
+        services: &[&str],
+    ) -> FileDescriptorProto {
+        FileDescriptorProto {
+            name: Some(name.to_string()),
+            package: Some(package.to_string()),
+            dependency: dependency.iter().map(|d| d.to_string()).collect(),
+            service: services
+                .iter()
+                .map(|service| ServiceDescriptorProto {
+                    name: Some(service.to_string()),
+                    ..ServiceDescriptorProto::default()
+                })
+                .collect(),
+            ..FileDescriptorProto::default()
+        }
+    }
+
+    #[test]
+    fn declares_packages_and_services() {
+        let files = [
+            file("google/protobuf/empty.proto", "google.protobuf", &[], &[]),
+            file("types.proto", "shop.types", &[], &[]),
+            file(
+                "orders.proto",
+                "shop.orders",
+                &["types.proto", "google/protobuf/empty.proto"],
+                &["Orders", "Admin"],
+            ),
+            file("extra.proto", "shop.orders", &["orders.proto"], &[]),
+            file("health.proto", "", &[], &["Health"]),
+        ];
+        assert_eq!(
+            manifest(&files, &[]),
+            "# The features gating the packages and services generated by tonic-build.\n\
+             [features]\n\
+             \"Health\" = []\n\
+             \"shop.orders\" = [\"shop.types\"]\n\
+             \"shop.orders.Admin\" = [\"shop.orders\"]\n\
+             \"shop.orders.Orders\" = [\"shop.orders\"]\n\
+             \"shop.types\" = []\n"
+        );
+    }
+}
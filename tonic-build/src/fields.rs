@@ -0,0 +1,183 @@
+//! The fields of the request messages taken as arguments by the generated
+//! `{method}_with` client methods.
+
+use crate::{optional::Proto3Optional, rust_ident};
+use proc_macro2::{Ident, TokenStream};
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, FileDescriptorProto,
+};
+use quote::quote;
+use std::collections::HashMap;
+
+/// Messages with more fields are better built by hand.
+const MAX_FIELDS: usize = 4;
+
+/// The fields of the request messages simple enough to be passed as
+/// arguments, by fully qualified message name, like `.pkg.Message`.
+#[derive(Debug, Default)]
+pub(crate) struct RequestFields(HashMap<String, Vec<Field>>);
+
+/// A field of a request message, as a method argument.
+#[derive(Debug)]
+pub(crate) struct Field {
+    pub(crate) ident: Ident,
+    /// The type of the argument.
+    pub(crate) ty: TokenStream,
+    /// Whether the argument is converted into the type of the field, for
+    /// strings and bytes.
+    pub(crate) into: bool,
+}
+
+impl RequestFields {
+    /// Collect the messages of the proto3 `files` whose fields are all
+    /// scalars, strings, bytes or enums, outside of oneofs.
+    pub(crate) fn collect(
+        files: &[FileDescriptorProto],
+        extern_path: &[(String, String)],
+        optional: &Proto3Optional,
+    ) -> Self {
+        let mut fields = RequestFields::default();
+
+        for file in files {
+            if file.syntax() != "proto3" || crate::is_extern(file.package(), extern_path) {
+                continue;
+            }
+            let package = match file.package() {
+                "" => String::new(),
+                package => format!(".{}", package),
+            };
+            for message in &file.message_type {
+                fields.insert(&package, message, optional);
+            }
+        }
+
+        fields
+    }
+
+    fn insert(&mut self, scope: &str, message: &DescriptorProto, optional: &Proto3Optional) {
+        let path = format!("{}.{}", scope, message.name());
+
+        let fields = message
+            .field
+            .iter()
+            .map(|field| {
+                if field.label() == Label::Repeated || field.oneof_index.is_some() {
+                    return None;
+                }
+                let (ty, into) = match field.r#type() {
+                    Type::Double => (quote!(f64), false),
+                    Type::Float => (quote!(f32), false),
+                    Type::Int64 | Type::Sint64 | Type::Sfixed64 => (quote!(i64), false),
+                    Type::Uint64 | Type::Fixed64 => (quote!(u64), false),
+                    Type::Int32 | Type::Sint32 | Type::Sfixed32 | Type::Enum => {
+                        (quote!(i32), false)
+                    }
+                    Type::Uint32 | Type::Fixed32 => (quote!(u32), false),
+                    Type::Bool => (quote!(bool), false),
+                    Type::String => (quote!(String), true),
+                    Type::Bytes => (quote!(Vec<u8>), true),
+                    Type::Message | Type::Group => return None,
+                };
+
+                let field_path = format!("{}.{}", path, field.name());
+                let ident = rust_ident(field.name());
+                Some(if optional.contains(&field_path) {
+                    Field {
+                        ident,
+                        ty: quote!(Option<#ty>),
+                        into: false,
+                    }
+                } else if into {
+                    Field {
+                        ident,
+                        ty: quote!(impl Into<#ty>),
+                        into: true,
+                    }
+                } else {
+                    Field {
+                        ident,
+                        ty,
+                        into: false,
+                    }
+                })
+            })
+            .collect::<Option<Vec<_>>>();
+
+        if let Some(fields) = fields.filter(|fields| fields.len() <= MAX_FIELDS) {
+            self.0.insert(path.clone(), fields);
+        }
+
+        for nested in &message.nested_type {
+            self.insert(&path, nested, optional);
+        }
+    }
+
+    /// The fields of the message named `message`, if they can be passed as
+    /// arguments.
+    pub(crate) fn get(&self, message: &str) -> Option<&[Field]> {
+        self.0.get(message).map(Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_types::{FieldDescriptorProto, OneofDescriptorProto};
+
+    fn field(name: &str, r#type: Type) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            label: Some(Label::Optional as i32),
+            r#type: Some(r#type as i32),
+            ..FieldDescriptorProto::default()
+        }
+    }
+
+    fn message(name: &str, field: Vec<FieldDescriptorProto>) -> DescriptorProto {
+        DescriptorProto {
+            name: Some(name.to_string()),
+            field,
+            ..DescriptorProto::default()
+        }
+    }
+
+    #[test]
+    fn collects_simple_messages() {
+        let mut in_oneof = message("InOneof", vec![field("id", Type::Uint64)]);
+        in_oneof.field[0].oneof_index = Some(0);
+        in_oneof.oneof_decl.push(OneofDescriptorProto::default());
+
+        let mut simple = message(
+            "Simple",
+            vec![field("id", Type::Uint64), field("type", Type::String)],
+        );
+        simple.nested_type.push(message("Empty", vec![]));
+
+        let file = FileDescriptorProto {
+            package: Some("pkg".to_string()),
+            syntax: Some("proto3".to_string()),
+            message_type: vec![
+                simple,
+                message("Nested", vec![field("inner", Type::Message)]),
+                message("Large", vec![field("a", Type::Bool); MAX_FIELDS + 1]),
+                in_oneof,
+            ],
+            ..FileDescriptorProto::default()
+        };
+
+        let optional = Proto3Optional::rewrite::<&str>(&[], &[]).unwrap();
+        let fields = RequestFields::collect(&[file], &[], &optional);
+
+        let simple = fields.get(".pkg.Simple").unwrap();
+        assert_eq!(simple[0].ident.to_string(), "id");
+        assert_eq!(simple[0].ty.to_string(), "u64");
+        assert_eq!(simple[1].ident.to_string(), "r#type");
+        assert!(simple[1].into);
+
+        assert!(fields.get(".pkg.Simple.Empty").unwrap().is_empty());
+        assert!(fields.get(".pkg.Nested").is_none());
+        assert!(fields.get(".pkg.Large").is_none());
+        assert!(fields.get(".pkg.InOneof").is_none());
+    }
+}
@@ -0,0 +1,153 @@
+//! The REST mappings of the methods, declared with the `google.api.http`
+//! option of `google/api/annotations.proto`.
+
+use proc_macro2::TokenStream;
+use prost::{Message, Oneof};
+use quote::quote;
+
+/// The `google.protobuf.MethodOptions`, reduced to the `google.api.http`
+/// extension.
+#[derive(Clone, PartialEq, Message)]
+struct HttpOptions {
+    #[prost(message, optional, tag = "72295728")]
+    http: Option<HttpRule>,
+}
+
+/// A `google.api.HttpRule`.
+#[derive(Clone, PartialEq, Message)]
+struct HttpRule {
+    #[prost(oneof = "Pattern", tags = "2, 3, 4, 5, 6, 8")]
+    pattern: Option<Pattern>,
+    #[prost(string, tag = "7")]
+    body: String,
+    #[prost(string, tag = "12")]
+    response_body: String,
+    #[prost(message, repeated, tag = "11")]
+    additional_bindings: Vec<HttpRule>,
+}
+
+#[derive(Clone, PartialEq, Oneof)]
+enum Pattern {
+    #[prost(string, tag = "2")]
+    Get(String),
+    #[prost(string, tag = "3")]
+    Put(String),
+    #[prost(string, tag = "4")]
+    Post(String),
+    #[prost(string, tag = "5")]
+    Delete(String),
+    #[prost(string, tag = "6")]
+    Patch(String),
+    #[prost(message, tag = "8")]
+    Custom(CustomHttpPattern),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct CustomHttpPattern {
+    #[prost(string, tag = "1")]
+    kind: String,
+    #[prost(string, tag = "2")]
+    path: String,
+}
+
+/// Generate the `tonic::descriptor::HttpRule`s declared in the encoded
+/// method `options`, the additional bindings following their rule.
+///
+/// Options declaring another extension with the same number are ignored.
+pub(crate) fn generate_rules(options: &[u8]) -> TokenStream {
+    let mut rules = Vec::new();
+    if let Ok(HttpOptions { http: Some(rule) }) = HttpOptions::decode(options) {
+        flatten(rule, &mut rules);
+    }
+
+    let rules = rules.iter().map(|(method, path, body, response_body)| {
+        quote! {
+            HttpRule {
+                method: #method,
+                path: #path,
+                body: #body,
+                response_body: #response_body,
+            }
+        }
+    });
+    quote!(&[#(#rules),*])
+}
+
+fn flatten(rule: HttpRule, rules: &mut Vec<(String, String, String, String)>) {
+    let (method, path) = match rule.pattern {
+        Some(Pattern::Get(path)) => ("GET".to_string(), path),
+        Some(Pattern::Put(path)) => ("PUT".to_string(), path),
+        Some(Pattern::Post(path)) => ("POST".to_string(), path),
+        Some(Pattern::Delete(path)) => ("DELETE".to_string(), path),
+        Some(Pattern::Patch(path)) => ("PATCH".to_string(), path),
+        Some(Pattern::Custom(custom)) => (custom.kind, custom.path),
+        None => return,
+    };
+    rules.push((method, path, rule.body, rule.response_body));
+
+    for binding in rule.additional_bindings {
+        flatten(binding, rules);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(rule: HttpRule) -> String {
+        let mut options = Vec::new();
+        HttpOptions { http: Some(rule) }
+            .encode(&mut options)
+            .unwrap();
+        generate_rules(&options).to_string()
+    }
+
+    #[test]
+    fn flattens_additional_bindings() {
+        let rule = HttpRule {
+            pattern: Some(Pattern::Get("/v1/{name=shelves/*}".to_string())),
+            body: String::new(),
+            response_body: "shelf".to_string(),
+            additional_bindings: vec![HttpRule {
+                pattern: Some(Pattern::Custom(CustomHttpPattern {
+                    kind: "HEAD".to_string(),
+                    path: "/v1/shelves".to_string(),
+                })),
+                body: String::new(),
+                response_body: String::new(),
+                additional_bindings: vec![],
+            }],
+        };
+
+        assert_eq!(
+            rules(rule),
+            quote! {
+                &[
+                    HttpRule {
+                        method: "GET",
+                        path: "/v1/{name=shelves/*}",
+                        body: "",
+                        response_body: "shelf",
+                    },
+                    HttpRule {
+                        method: "HEAD",
+                        path: "/v1/shelves",
+                        body: "",
+                        response_body: "",
+                    }
+                ]
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn ignores_methods_without_rules() {
+        assert_eq!(generate_rules(&[]).to_string(), quote!(&[]).to_string());
+        // `deprecated = true`
+        assert_eq!(
+            generate_rules(b"\x88\x02\x01").to_string(),
+            quote!(&[]).to_string()
+        );
+    }
+}
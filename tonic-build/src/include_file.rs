@@ -0,0 +1,222 @@
+//! The index module including the code generated for each package.
+
+use crate::{features, naive_snake_case, rust_ident};
+use proc_macro2::TokenStream;
+use prost_types::FileDescriptorProto;
+use quote::quote;
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+/// Write to `path` the module tree of the packages of `files`, each package
+/// `a.b` becoming a module `a::b` including the `a.b.rs` file generated for
+/// it in the same directory, behind a feature named after the package if
+/// `gated`, followed by the `REGISTRY` of the packages declaring services.
+pub(crate) fn write(
+    path: &Path,
+    files: &[FileDescriptorProto],
+    extern_path: &[(String, String)],
+    gated: bool,
+) -> io::Result<()> {
+    let mut root = Module {
+        gated,
+        ..Module::default()
+    };
+    for file in files {
+        if !crate::is_extern(file.package(), extern_path) {
+            root.insert(file.package());
+            if !file.service.is_empty() {
+                root.insert_services(file.package());
+            }
+        }
+    }
+
+    let registry = root.registry();
+    fs::write(path, quote!(#root #registry).to_string())
+}
+
+#[derive(Debug, Default)]
+struct Module {
+    /// The package of this module, if it is one.
+    package: Option<String>,
+    /// Whether the packages are included behind a feature named after them.
+    gated: bool,
+    /// Whether the package declares services, and so a `PACKAGE` constant.
+    services: bool,
+    children: BTreeMap<String, Module>,
+}
+
+impl Module {
+    fn insert(&mut self, package: &str) {
+        let gated = self.gated;
+        let mut module = self;
+        for segment in package.split('.').filter(|s| !s.is_empty()) {
+            module = module
+                .children
+                .entry(segment.to_string())
+                .or_insert(Module {
+                    gated,
+                    ..Module::default()
+                });
+        }
+        module.package = Some(package.to_string());
+    }
+
+    fn insert_services(&mut self, package: &str) {
+        let mut module = self;
+        for segment in package.split('.').filter(|s| !s.is_empty()) {
+            module = module.children.get_mut(segment).unwrap();
+        }
+        module.services = true;
+    }
+
+    /// The `REGISTRY` constant listing the `PACKAGE` constants of the
+    /// packages declaring services.
+    fn registry(&self) -> TokenStream {
+        let mut packages = TokenStream::new();
+        self.collect_packages(&mut Vec::new(), &mut packages);
+
+        quote! {
+            /// The packages declaring services, with their descriptors.
+            pub const REGISTRY: tonic::descriptor::Registry = tonic::descriptor::Registry {
+                packages: &[#packages],
+            };
+        }
+    }
+
+    fn collect_packages(&self, path: &mut Vec<TokenStream>, packages: &mut TokenStream) {
+        if let (Some(package), true) = (&self.package, self.services) {
+            if self.gated && !package.is_empty() {
+                packages.extend(features::gate(package));
+            }
+            packages.extend(quote!(#(#path::)* PACKAGE,));
+        }
+
+        for (segment, child) in &self.children {
+            let ident = rust_ident(&naive_snake_case(segment));
+            path.push(quote!(#ident));
+            child.collect_packages(path, packages);
+            path.pop();
+        }
+    }
+}
+
+impl quote::ToTokens for Module {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        if let Some(package) = &self.package {
+            if self.gated && !package.is_empty() {
+                tokens.extend(features::gate(package));
+            }
+            let file = format!("{}.rs", package);
+            tokens.extend(quote!(include!(#file);));
+        }
+
+        for (segment, child) in &self.children {
+            let ident = rust_ident(&naive_snake_case(segment));
+
+            tokens.extend(quote! {
+                pub mod #ident {
+                    #child
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(packages: &[&str], gated: bool) -> String {
+        let mut root = Module {
+            gated,
+            ..Module::default()
+        };
+        for package in packages {
+            root.insert(package);
+        }
+        quote!(#root).to_string()
+    }
+
+    #[test]
+    fn nests_packages() {
+        assert_eq!(
+            module(&["a.b", "a", "a.c", "d"], false),
+            quote! {
+                pub mod a {
+                    include!("a.rs");
+                    pub mod b {
+                        include!("a.b.rs");
+                    }
+                    pub mod c {
+                        include!("a.c.rs");
+                    }
+                }
+                pub mod d {
+                    include!("d.rs");
+                }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn escapes_keywords() {
+        assert_eq!(
+            module(&["google.type"], false),
+            quote! {
+                pub mod google {
+                    pub mod r#type {
+                        include!("google.type.rs");
+                    }
+                }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn registers_packages_with_services() {
+        let mut root = Module {
+            gated: true,
+            ..Module::default()
+        };
+        for package in &["a", "a.b", "c"] {
+            root.insert(package);
+        }
+        root.insert_services("a.b");
+        root.insert_services("c");
+
+        assert_eq!(
+            root.registry().to_string(),
+            quote! {
+                /// The packages declaring services, with their descriptors.
+                pub const REGISTRY: tonic::descriptor::Registry = tonic::descriptor::Registry {
+                    packages: &[
+                        #[cfg(feature = "a.b")]
+                        a::b::PACKAGE,
+                        #[cfg(feature = "c")]
+                        c::PACKAGE,
+                    ],
+                };
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn gates_packages() {
+        assert_eq!(
+            module(&["a.b", "a"], true),
+            quote! {
+                pub mod a {
+                    #[cfg(feature = "a")]
+                    include!("a.rs");
+                    pub mod b {
+                        #[cfg(feature = "a.b")]
+                        include!("a.b.rs");
+                    }
+                }
+            }
+            .to_string()
+        );
+    }
+}
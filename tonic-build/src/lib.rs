@@ -7,6 +7,66 @@
 //! this makes the code readable and the error messages nice. This requires that `rustfmt`
 //! is installed. This is enabled by default.
 //!
+//! # `protoc`
+//!
+//! The `.proto` files are compiled by the `protoc` bundled with
+//! `prost-build`, or by the one found in the `PATH` on platforms it has no
+//! bundled binary for. Hermetic builds, and platforms without either, point
+//! the `PROTOC` and `PROTOC_INCLUDE` environment variables at the `protoc`
+//! binary and include directory to use, which also pins the version used.
+//! `prost-build` reads them when it is itself built, so changing them
+//! takes a `cargo clean -p prost-build`.
+//!
+//! There is no mode parsing the files without `protoc`: `prost-build` runs
+//! `protoc` itself and only generates code from its output, so a pure-Rust
+//! parser in `tonic-build` would have nothing to hand its descriptors to.
+//! The imported files can come from vendored descriptor sets though, like
+//! the ones `buf build` writes for the modules of a registry, with
+//! [`Builder::import_descriptor_set`].
+//!
+//! # Proto3 optional fields
+//!
+//! Fields declared `optional` in proto3 files are generated as `Option<T>`,
+//! and only encoded when set, even though the bundled `protoc` doesn't know
+//! about them yet.
+//!
+//! # Deprecation and options
+//!
+//! Fields, services and methods declared with the `deprecated` option are
+//! marked `#[deprecated]`, along with the clients and servers of deprecated
+//! services. Deprecated messages, enums, enum values and oneof fields only
+//! get a note in their docs, as the impls derived by prost would warn.
+//!
+//! The options of services and methods, custom options included, are kept
+//! in the generated `tonic::descriptor` constants.
+//!
+//! # Comments
+//!
+//! The comments of the services and methods document the generated clients
+//! and servers, kept as Markdown, stripped of it or kept verbatim with
+//! [`Builder::comment_style`]. The blocks of comments detached from them and
+//! their location in the `.proto` files can be added with
+//! [`Builder::detached_comments`] and [`Builder::comment_locations`]. The
+//! messages and enums are documented by `prost`, with their comments as
+//! they are.
+//!
+//! # Field representations
+//!
+//! Message fields can be boxed with [`Builder::boxed`], and map fields
+//! generated as `BTreeMap`s with [`Builder::btree_map`], without a separate
+//! `prost-build` configuration. The `bytes` and enum fields are always
+//! generated as `Vec<u8>` and `i32`, the only types `prost` 0.6 encodes them
+//! from.
+//!
+//! # External types
+//!
+//! The requests and responses of the generated clients and servers can use
+//! other Rust types than the generated messages, like `uuid::Uuid` for a
+//! `Uuid` message, with [`Builder::extern_type`] and a
+//! `tonic::codec::Conversion` the codec applies to each message. The
+//! `google.protobuf.Timestamp` and `Duration` types map to `chrono` types
+//! with [`Builder::well_known_types`].
+//!
 //! # Required dependencies
 //!
 //! ```toml
@@ -18,6 +78,10 @@
 //! tonic-build = <tonic-version>
 //! ```
 //!
+//! The `prost` dependency has to be the version `tonic` depends on, unless
+//! the generated code uses the one of the crate with
+//! [`Builder::prost_version`].
+//!
 //! # Examples
 //! Simple
 //!
@@ -60,25 +124,80 @@ use proc_macro2::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenS
 use prost_build::{Config, Method};
 use quote::{ToTokens, TokenStreamExt};
 
-#[cfg(feature = "rustfmt")]
-use std::process::Command;
+use prost::Message;
+use prost_types::{FileDescriptorProto, FileDescriptorSet};
 use std::{
-    io,
+    collections::HashMap,
+    fs, io,
     path::{Path, PathBuf},
+    process::Command,
 };
 
+mod boxed;
 mod client;
+mod codec;
+mod comments;
+mod descriptor;
+mod descriptor_set;
+mod errors;
+mod features;
+mod fields;
+mod http;
+mod include_file;
+mod optional;
+mod options;
+mod serde;
 mod server;
+mod vendored;
+
+use client::ClientTrait;
+use codec::{Codec, ExternType};
+use comments::DocComments;
+use errors::{MethodError, MethodErrors};
+use fields::RequestFields;
+use optional::Proto3Optional;
+use options::RawOptions;
+use vendored::VendoredImports;
+
+pub use codec::{ProstVersion, WellKnownTypes};
+pub use comments::CommentStyle;
 
 /// Service generator builder.
 #[derive(Debug, Clone)]
 pub struct Builder {
     build_client: bool,
     build_server: bool,
+    build_transport: bool,
+    build_dispatch: bool,
+    client_services: Option<Vec<String>>,
+    server_services: Option<Vec<String>>,
     extern_path: Vec<(String, String)>,
     field_attributes: Vec<(String, String)>,
     type_attributes: Vec<(String, String)>,
+    boxed: Vec<String>,
+    btree_map: Vec<String>,
+    retain_enum_prefix: bool,
     out_dir: Option<PathBuf>,
+    codec_path: Option<String>,
+    prost_version: ProstVersion,
+    extern_types: Vec<ExternType>,
+    well_known_types: WellKnownTypes,
+    lazy_decode: Vec<String>,
+    client_attributes: Attributes,
+    server_attributes: Attributes,
+    renames: Vec<(String, String)>,
+    method_errors: Vec<MethodError>,
+    client_trait: ClientTrait,
+    client_field_methods: bool,
+    async_fn_in_trait: bool,
+    derive_serde: bool,
+    comment_style: CommentStyle,
+    detached_comments: bool,
+    comment_locations: bool,
+    file_descriptor_set_path: Option<PathBuf>,
+    include_file: Option<PathBuf>,
+    feature_gates: Option<PathBuf>,
+    descriptor_set_imports: Vec<PathBuf>,
     #[cfg(feature = "rustfmt")]
     format: bool,
 }
@@ -96,6 +215,130 @@ impl Builder {
         self
     }
 
+    /// Enable or disable the code relying on the `transport` feature of
+    /// `tonic`: the `connect` constructor of the clients and the
+    /// `NamedService` impl of the servers.
+    ///
+    /// Without it, crates depending on `tonic` without the `transport`
+    /// feature, like the ones built for wasm or embedded targets, still use
+    /// the generated messages, descriptors, servers and clients, the latter
+    /// over a transport of their own passed to `new` or `with_origin`.
+    ///
+    /// Defaults to whether the `transport` feature of `tonic-build` is
+    /// enabled.
+    pub fn build_transport(mut self, enable: bool) -> Self {
+        self.build_transport = enable;
+        self
+    }
+
+    /// Enable or disable the generation of a `dispatch` function next to each
+    /// server, calling the server with the path and the gRPC framed body of
+    /// a request, and returning the framed response messages and the status
+    /// of the call.
+    ///
+    /// The servers can then be mounted on HTTP servers other than the one of
+    /// `tonic`, or run on custom executors, without the HTTP types of either:
+    ///
+    /// ```rust,ignore
+    /// let (body, status) = greeter_server::dispatch(&server, path, body).await;
+    /// ```
+    ///
+    /// Disabled by default.
+    pub fn build_dispatch(mut self, enable: bool) -> Self {
+        self.build_dispatch = enable;
+        self
+    }
+
+    /// Generate clients only for `services`, instead of all of them.
+    ///
+    /// Services are named by their fully qualified proto name, like
+    /// `helloworld.Greeter`. Can be called several times to add services.
+    pub fn build_client_for<I, S>(mut self, services: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.client_services
+            .get_or_insert_with(Vec::new)
+            .extend(services.into_iter().map(service_name));
+        self
+    }
+
+    /// Generate servers only for `services`, instead of all of them.
+    ///
+    /// Services are named by their fully qualified proto name, like
+    /// `helloworld.Greeter`. Can be called several times to add services.
+    pub fn build_server_for<I, S>(mut self, services: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.server_services
+            .get_or_insert_with(Vec::new)
+            .extend(services.into_iter().map(service_name));
+        self
+    }
+
+    /// Enable or disable the generation of a trait with the methods of each
+    /// client.
+    ///
+    /// The trait of the `Greeter` service, `greeter_client::GreeterApi`, is
+    /// implemented by `GreeterClient`, so application code can depend on the
+    /// trait and tests can replace the client without a live channel. Its
+    /// methods take a `tonic::Request`, holding a
+    /// `tonic::codegen::BoxMessageStream` for client streaming methods.
+    pub fn client_trait(mut self, enable: bool) -> Self {
+        self.client_trait = if enable {
+            ClientTrait::Enabled
+        } else {
+            ClientTrait::Disabled
+        };
+        self
+    }
+
+    /// Enable or disable the generation of a client trait, see
+    /// [`client_trait`], annotated with `mockall::automock`.
+    ///
+    /// The crate including the generated code then needs the `mockall`
+    /// dependency, and gets a `MockGreeterApi` type for the `Greeter`
+    /// service.
+    ///
+    /// [`client_trait`]: #method.client_trait
+    pub fn mock_client_trait(mut self, enable: bool) -> Self {
+        self.client_trait = if enable {
+            ClientTrait::Mocked
+        } else {
+            ClientTrait::Disabled
+        };
+        self
+    }
+
+    /// Enable or disable the generation of client methods taking the fields
+    /// of their request directly.
+    ///
+    /// Unary and server streaming methods whose request has at most four
+    /// fields, all scalars, strings, bytes or enums outside of oneofs, get a
+    /// `{method}_with` twin, so `client.get_user(GetUserRequest { id })`
+    /// can be written `client.get_user_with(id)`. Strings and bytes are
+    /// taken as `impl Into<String>` and `impl Into<Vec<u8>>`, enums as their
+    /// `i32` value, and proto3 optional fields as `Option<T>`.
+    pub fn client_field_methods(mut self, enable: bool) -> Self {
+        self.client_field_methods = enable;
+        self
+    }
+
+    /// Generate the server traits with native `async fn` methods instead of
+    /// `#[async_trait]` ones, saving the allocation of a boxed future per
+    /// call and keeping the stack traces of the services readable.
+    ///
+    /// The methods are declared as returning `impl Future + Send`, which
+    /// requires Rust 1.75. Services implement them with plain `async fn`,
+    /// without `#[tonic::async_trait]`, and their futures must be `Send`.
+    pub fn async_fn_in_trait(mut self, enable: bool) -> Self {
+        self.async_fn_in_trait = enable;
+        self
+    }
+
     /// Enable the output to be formated by rustfmt.
     #[cfg(feature = "rustfmt")]
     pub fn format(mut self, run: bool) -> Self {
@@ -140,6 +383,352 @@ impl Builder {
         self
     }
 
+    /// Generate the message field at `path`, like `.pkg.Message.field`, or
+    /// `.pkg.Message.oneof.field` for a field of a oneof, as a `Box<T>`.
+    ///
+    /// Boxing the large fields of a message keeps it, and the futures
+    /// holding it, small. The fields of recursive messages are always boxed,
+    /// and fields that aren't messages never are.
+    pub fn boxed(mut self, path: impl AsRef<str>) -> Self {
+        self.boxed.push(path.as_ref().to_string());
+        self
+    }
+
+    /// Generate the map fields matching `path` as `BTreeMap`s instead of
+    /// `HashMap`s, `.` matching all of them.
+    ///
+    /// Passed directly to `prost_build::Config.btree_map`.
+    pub fn btree_map(mut self, path: impl AsRef<str>) -> Self {
+        self.btree_map.push(path.as_ref().to_string());
+        self
+    }
+
+    /// Keep the prefix of the enum values named after their enum, like
+    /// `Color::ColorRed` instead of `Color::Red`.
+    ///
+    /// Passed directly to `prost_build::Config.retain_enum_prefix`.
+    pub fn retain_enum_prefix(mut self, enable: bool) -> Self {
+        self.retain_enum_prefix = enable;
+        self
+    }
+
+    /// Add an attribute to the generated client of a service, or to one of
+    /// its methods.
+    ///
+    /// `path` is the fully qualified proto name of the service, like
+    /// `helloworld.Greeter`, to add it to the client struct, or of a method,
+    /// like `helloworld.Greeter.SayHello`, to add it to that client method.
+    pub fn client_attribute<P: AsRef<str>, A: AsRef<str>>(mut self, path: P, attribute: A) -> Self {
+        self.client_attributes.push(path, attribute);
+        self
+    }
+
+    /// Add an attribute to the generated server trait of a service, or to
+    /// one of its methods.
+    ///
+    /// `path` is the fully qualified proto name of the service, like
+    /// `helloworld.Greeter`, to add it to the trait, or of a method, like
+    /// `helloworld.Greeter.SayHello`, to add it to that trait method.
+    pub fn server_attribute<P: AsRef<str>, A: AsRef<str>>(mut self, path: P, attribute: A) -> Self {
+        self.server_attributes.push(path, attribute);
+        self
+    }
+
+    /// Rename the Rust items generated for a service, or for one of its
+    /// methods.
+    ///
+    /// `path` is the fully qualified proto name of the service, like
+    /// `helloworld.Greeter`, whose clients, servers, traits and modules are
+    /// then named after `name`, like `{name}Client` and `{name}_server`, or
+    /// of a method, like `helloworld.Greeter.SayHello`, whose client and
+    /// server trait methods are then named `name`. The gRPC paths keep the
+    /// proto names.
+    ///
+    /// Methods named like Rust keywords are escaped as raw identifiers
+    /// already, this resolves the other collisions, like a `New` method
+    /// clashing with the `new` constructor of the client, or two services of
+    /// the same name in packages generated into the same module.
+    pub fn rename<P: AsRef<str>, N: AsRef<str>>(mut self, path: P, name: N) -> Self {
+        self.renames
+            .push((service_name(path), name.as_ref().to_string()));
+        self
+    }
+
+    /// Declare that the method `method`, like `shop.Orders.Get`, fails with
+    /// the error detail message `detail`, like `.shop.NotFound`, sent with
+    /// the `tonic::Code` named `code`, like `NotFound`.
+    ///
+    /// The methods declaring errors get an enum of them in the generated
+    /// `{service}_errors` module, named after the method, like `GetError`,
+    /// with a variant per detail message and a `Status` variant for the
+    /// other statuses. It converts from the `Status` of a failed call, by
+    /// decoding its error details, and into the `Status` a handler fails
+    /// with:
+    ///
+    /// ```rust,ignore
+    /// match client.get(request).await.map_err(GetError::from) {
+    ///     Ok(response) => { /* ... */ }
+    ///     Err(GetError::NotFound(not_found)) => { /* ... */ }
+    ///     Err(GetError::Status(status)) => { /* ... */ }
+    /// }
+    /// ```
+    ///
+    /// The standard `google.rpc` details, like `.google.rpc.BadRequest`, map
+    /// to the types of `tonic::error_details`, without compiling their
+    /// `.proto` files.
+    pub fn method_error(
+        mut self,
+        method: impl AsRef<str>,
+        detail: impl AsRef<str>,
+        code: impl AsRef<str>,
+    ) -> Self {
+        let detail = detail.as_ref();
+        self.method_errors.push(MethodError {
+            method: service_name(method),
+            detail: if detail.starts_with('.') {
+                detail.to_string()
+            } else {
+                format!(".{}", detail)
+            },
+            code: code.as_ref().to_string(),
+        });
+        self
+    }
+
+    /// Set the codec used by the generated clients and servers to encode and
+    /// decode messages.
+    ///
+    /// `path` must name a type that is generic over the encoded and decoded
+    /// message types, like `my_crate::MyCodec`, and implements
+    /// `tonic::codec::Codec` and `Default` for every message of the services.
+    ///
+    /// This codec can also be replaced at runtime, for a single client or
+    /// server, with their generated `with_codec` constructors taking a
+    /// `tonic::codec::MakeCodec`.
+    ///
+    /// Defaults to the `ProstCodec` of the [`prost_version`].
+    ///
+    /// [`prost_version`]: #method.prost_version
+    pub fn codec_path(mut self, path: impl AsRef<str>) -> Self {
+        self.codec_path = Some(path.as_ref().to_string());
+        self
+    }
+
+    /// Set the `prost` crate the generated clients and servers encode and
+    /// decode messages with.
+    ///
+    /// With [`ProstVersion::Crate`], the messages go through the `prost`
+    /// dependency of the crate including the generated code, the one their
+    /// derives already use, instead of the version `tonic` depends on. The
+    /// generated code then builds against any major version of `prost` and
+    /// `prost-types`, so crates migrating between versions don't need to
+    /// match the one of `tonic`. Each message is copied once through a
+    /// buffer of its own, and the [`WellKnownTypes::Chrono`] conversions,
+    /// built on the `prost-types` of `tonic`, aren't available.
+    ///
+    /// Defaults to [`ProstVersion::Tonic`].
+    pub fn prost_version(mut self, version: ProstVersion) -> Self {
+        self.prost_version = version;
+        self
+    }
+
+    /// Map a message to another Rust type in the requests and responses of
+    /// the generated clients and servers.
+    ///
+    /// `proto_path` is the fully qualified proto name of the message, like
+    /// `.common.Uuid`, and `rust_type` the type standing in for it, like
+    /// `uuid::Uuid`. `conversion` names a type implementing
+    /// `tonic::codec::Conversion` between the message generated by prost and
+    /// `rust_type`, which the codec of the methods using the message applies
+    /// to each message it encodes or decodes. Decoded messages failing the
+    /// conversion fail the call with the returned status.
+    ///
+    /// Only the requests and responses of the methods are mapped, the fields
+    /// of the messages keep their generated type. Takes precedence over
+    /// [`well_known_types`], so the `google.protobuf` types can be mapped to
+    /// other crates, like `time`, with conversions of their own.
+    ///
+    /// [`well_known_types`]: #method.well_known_types
+    pub fn extern_type(
+        mut self,
+        proto_path: impl AsRef<str>,
+        rust_type: impl AsRef<str>,
+        conversion: impl AsRef<str>,
+    ) -> Self {
+        self.extern_types.push(ExternType::new(
+            proto_path.as_ref(),
+            rust_type.as_ref(),
+            conversion.as_ref(),
+        ));
+        self
+    }
+
+    /// Set the Rust types the `google.protobuf.Timestamp` and
+    /// `google.protobuf.Duration` requests and responses map to.
+    ///
+    /// With [`WellKnownTypes::Chrono`], the crate including the generated
+    /// code needs the `chrono` dependency and the `chrono` feature of
+    /// `tonic`. Defaults to [`WellKnownTypes::Prost`].
+    pub fn well_known_types(mut self, types: WellKnownTypes) -> Self {
+        self.well_known_types = types;
+        self
+    }
+
+    /// Defer decoding the requests of a server method.
+    ///
+    /// `path` is the gRPC path of the method, like `/helloworld.Greeter/SayHello`.
+    /// Its requests are handed to the generated server trait as
+    /// `tonic::codec::Lazy` values holding the raw message, so the service
+    /// can inspect a few fields with a partial decode before, or instead of,
+    /// decoding the whole message.
+    pub fn lazy_decode(mut self, path: impl AsRef<str>) -> Self {
+        self.lazy_decode.push(path.as_ref().to_string());
+        self
+    }
+
+    /// Set how the comments of the services and methods in the `.proto`
+    /// files are rendered in the doc comments of the generated clients and
+    /// servers.
+    ///
+    /// Defaults to [`CommentStyle::Markdown`], keeping them as they are.
+    pub fn comment_style(mut self, style: CommentStyle) -> Self {
+        self.comment_style = style;
+        self
+    }
+
+    /// Enable or disable the leading detached comments of the services and
+    /// methods in their doc comments: the blocks of comments separated from
+    /// them by a blank line, like section headers of large schemas.
+    ///
+    /// Disabled by default.
+    pub fn detached_comments(mut self, enable: bool) -> Self {
+        self.detached_comments = enable;
+        self
+    }
+
+    /// Enable or disable a last line in the doc comments of the services and
+    /// methods naming their fully qualified proto name and the `.proto` file
+    /// declaring them, like ``Declared as `helloworld.Greeter.SayHello` in
+    /// `helloworld.proto`.``
+    ///
+    /// Disabled by default.
+    pub fn comment_locations(mut self, enable: bool) -> Self {
+        self.comment_locations = enable;
+        self
+    }
+
+    /// Derive serde's `Serialize` and `Deserialize` on the generated messages
+    /// and enums, following the [proto3 JSON mapping].
+    ///
+    /// Fields are named after their JSON name, like `fooBar`, and accept their
+    /// original name too. `bytes`, `google.protobuf.Timestamp` and
+    /// `google.protobuf.Duration` fields are written as strings with the
+    /// helpers of `tonic::codegen::serde`, oneofs are flattened into their
+    /// message, and missing fields get their default value. Enums are named
+    /// like in the `.proto` files, while enum fields, generated as `i32`, are
+    /// written as numbers.
+    ///
+    /// The crate including the generated code needs `serde` with its `derive`
+    /// feature, and `tonic` with its `json` feature. Compilation fails on
+    /// fields of the other `google.protobuf` messages, like `Any`, which
+    /// don't implement serde's traits.
+    ///
+    /// [proto3 JSON mapping]: https://developers.google.com/protocol-buffers/docs/proto3#json
+    pub fn derive_serde(mut self, enable: bool) -> Self {
+        self.derive_serde = enable;
+        self
+    }
+
+    /// Write the encoded `FileDescriptorSet` of the compiled files, along
+    /// with their imports, to `path`.
+    ///
+    /// The generated code of each package holds the descriptors of its files
+    /// and their dependencies in a `FILE_DESCRIPTOR_SET` constant already,
+    /// this is for tools needing a file.
+    pub fn file_descriptor_set_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.file_descriptor_set_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Generate a file named `path` in the output directory, holding a tree
+    /// of modules that include the code generated for each package.
+    ///
+    /// The package `a.b` becomes the module `a::b`, which is the layout the
+    /// references between packages expect. The whole tree can then be
+    /// included at once, in any module:
+    ///
+    /// ```rust,ignore
+    /// pub mod protos {
+    ///     tonic::include_file!("protos.rs");
+    /// }
+    ///
+    /// use protos::a::b::Message;
+    /// ```
+    ///
+    /// The code of each package is still generated to a file of its own.
+    ///
+    /// The tree is followed by a `REGISTRY` constant, a
+    /// `tonic::descriptor::Registry` listing the `PACKAGE` constant of each
+    /// package declaring services, with their descriptors and file
+    /// descriptor sets, to register every compiled service with reflection
+    /// or health checking at once:
+    ///
+    /// ```rust,ignore
+    /// for service in protos::REGISTRY.services() {
+    ///     health_reporter.set_serving(service.name).await;
+    /// }
+    /// ```
+    pub fn include_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.include_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Gate the clients, servers and descriptors of each service behind a
+    /// Cargo feature named after the service, like `shop.orders.Orders`, and
+    /// the packages included by the [`include_file`] behind a feature named
+    /// after the package, like `shop.orders`.
+    ///
+    /// The `[features]` section of a manifest declaring them is written to
+    /// `path` in the output directory, each service enabling its package and
+    /// each package the packages it imports, for the crate compiling the
+    /// files to copy:
+    ///
+    /// ```toml
+    /// [features]
+    /// "shop.orders" = ["shop.types"]
+    /// "shop.orders.Orders" = ["shop.orders"]
+    /// "shop.types" = []
+    /// ```
+    ///
+    /// Crates depending on it can then enable only the services they use.
+    ///
+    /// [`include_file`]: Builder::include_file
+    pub fn feature_gates(mut self, path: impl AsRef<Path>) -> Self {
+        self.feature_gates = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Resolve the imports missing from the include directories from the
+    /// encoded `FileDescriptorSet` at `path`, instead of requiring all the
+    /// imported `.proto` files on disk.
+    ///
+    /// The sets written by `protoc --include_imports -o` or `buf build -o`
+    /// fit, the latter resolving the dependencies of a `buf.lock` from the
+    /// Buf Schema Registry:
+    ///
+    /// ```sh
+    /// buf build buf.build/acme/shop -o proto/shop.binpb
+    /// ```
+    ///
+    /// The files of the set are written back as `.proto` files, keeping the
+    /// types, services and standard options they declare, but neither their
+    /// comments nor their custom options. Proto2 groups aren't supported.
+    pub fn import_descriptor_set(mut self, path: impl AsRef<Path>) -> Self {
+        self.descriptor_set_imports
+            .push(path.as_ref().to_path_buf());
+        self
+    }
+
     /// Compile the .proto files and execute code generation.
     pub fn compile<P: AsRef<Path>>(self, protos: &[P], includes: &[P]) -> io::Result<()> {
         let mut config = Config::new();
@@ -162,9 +751,69 @@ impl Builder {
         for (path, attr) in self.type_attributes.iter() {
             config.type_attribute(path, attr);
         }
-        config.service_generator(Box::new(ServiceGenerator::new(self)));
+        boxed::configure(&mut config, &self.boxed);
+        config.btree_map(&self.btree_map);
+        if self.retain_enum_prefix {
+            config.retain_enum_prefix();
+        }
 
-        config.compile_protos(protos, includes)?;
+        let vendored = VendoredImports::write(&self.descriptor_set_imports, includes)?;
+        let protos = protos
+            .iter()
+            .map(|proto| proto.as_ref().to_path_buf())
+            .collect::<Vec<_>>();
+        let includes = includes
+            .iter()
+            .map(|include| include.as_ref().to_path_buf())
+            .chain(vendored.dir().map(Path::to_path_buf))
+            .collect::<Vec<_>>();
+
+        let mut optional = Proto3Optional::rewrite(&protos, &includes)?;
+        let (files, raw_files) = file_descriptors(optional.protos(), optional.includes())?;
+        optional.configure(&mut config, &files);
+        options::configure_deprecated(&mut config, &files);
+        if self.derive_serde {
+            serde::configure(&mut config, &files, &self.extern_path, &optional)?;
+        }
+        if let Some(path) = &self.file_descriptor_set_path {
+            fs::write(path, &raw_files)?;
+        }
+        let extern_path = self.extern_path.clone();
+        let include_file = self.include_file.clone();
+        let feature_gates = self.feature_gates.clone();
+        let options = RawOptions::parse(&raw_files)?;
+        let request_fields = if self.client_field_methods {
+            RequestFields::collect(&files, &self.extern_path, &optional)
+        } else {
+            RequestFields::default()
+        };
+        let method_errors =
+            MethodErrors::new(self.method_errors.clone(), &files, &self.extern_path);
+        let doc_comments = DocComments::new(
+            self.comment_style,
+            self.detached_comments,
+            self.comment_locations,
+            &files,
+        );
+        config.service_generator(Box::new(ServiceGenerator::new(
+            self,
+            options,
+            request_fields,
+            method_errors,
+            doc_comments,
+        )));
+
+        config.compile_protos(optional.protos(), optional.includes())?;
+        optional.wrap_fields(&out_dir)?;
+        boxed::box_fields(&out_dir)?;
+        descriptor_set::embed(&out_dir, &files, &raw_files, &extern_path)?;
+        if let Some(path) = include_file {
+            let gated = feature_gates.is_some();
+            include_file::write(&out_dir.join(path), &files, &extern_path, gated)?;
+        }
+        if let Some(path) = feature_gates {
+            features::write(&out_dir.join(path), &files, &extern_path)?;
+        }
 
         #[cfg(feature = "rustfmt")]
         {
@@ -177,6 +826,62 @@ impl Builder {
     }
 }
 
+/// Run `protoc` to get the descriptors of `protos`, like prost-build does,
+/// both decoded and as encoded by `protoc`.
+fn file_descriptors<P: AsRef<Path>>(
+    protos: &[P],
+    includes: &[P],
+) -> io::Result<(Vec<FileDescriptorProto>, Vec<u8>)> {
+    let descriptor_set =
+        std::env::temp_dir().join(format!("tonic-build-{}.desc", std::process::id()));
+
+    let mut cmd = Command::new(prost_build::protoc());
+    cmd.arg("--include_imports").arg("-o").arg(&descriptor_set);
+    for include in includes {
+        cmd.arg("-I").arg(include.as_ref());
+    }
+    cmd.arg("-I").arg(prost_build::protoc_include());
+    for proto in protos {
+        cmd.arg(proto.as_ref());
+    }
+
+    let output = cmd.output().map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!(
+                "failed to run protoc at {}: {}, set the PROTOC environment variable to the \
+                 protoc binary to use",
+                prost_build::protoc().display(),
+                e
+            ),
+        )
+    })?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "protoc failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    let buf = fs::read(&descriptor_set);
+    let _ = fs::remove_file(&descriptor_set);
+    let buf = buf?;
+    let descriptor_set = FileDescriptorSet::decode(&*buf)?;
+
+    Ok((descriptor_set.file, buf))
+}
+
+/// Whether the types of `package` are provided by another crate, and not
+/// generated.
+fn is_extern(package: &str, extern_path: &[(String, String)]) -> bool {
+    let package = format!(".{}", package);
+
+    package == ".google.protobuf"
+        || extern_path.iter().any(|(proto_path, _)| {
+            package == *proto_path || package.starts_with(&format!("{}.", proto_path))
+        })
+}
+
 /// Configure tonic-build code generation.
 ///
 /// Use [`compile_protos`] instead if you don't need to tweak anything.
@@ -184,10 +889,37 @@ pub fn configure() -> Builder {
     Builder {
         build_client: true,
         build_server: true,
+        build_transport: cfg!(feature = "transport"),
+        build_dispatch: false,
+        client_services: None,
+        server_services: None,
         out_dir: None,
         extern_path: Vec::new(),
         field_attributes: Vec::new(),
         type_attributes: Vec::new(),
+        boxed: Vec::new(),
+        btree_map: Vec::new(),
+        retain_enum_prefix: false,
+        codec_path: None,
+        prost_version: ProstVersion::Tonic,
+        extern_types: Vec::new(),
+        well_known_types: WellKnownTypes::Prost,
+        lazy_decode: Vec::new(),
+        client_attributes: Attributes::default(),
+        server_attributes: Attributes::default(),
+        renames: Vec::new(),
+        method_errors: Vec::new(),
+        client_trait: ClientTrait::Disabled,
+        client_field_methods: false,
+        async_fn_in_trait: false,
+        derive_serde: false,
+        comment_style: CommentStyle::Markdown,
+        detached_comments: false,
+        comment_locations: false,
+        file_descriptor_set_path: None,
+        include_file: None,
+        feature_gates: None,
+        descriptor_set_imports: Vec::new(),
         #[cfg(feature = "rustfmt")]
         format: true,
     }
@@ -216,6 +948,9 @@ fn fmt(out_dir: &str) {
 
     for entry in dir {
         let file = entry.unwrap().file_name().into_string().unwrap();
+        if !file.ends_with(".rs") {
+            continue;
+        }
         let out = Command::new("rustfmt")
             .arg("--emit")
             .arg("files")
@@ -232,36 +967,145 @@ fn fmt(out_dir: &str) {
 
 struct ServiceGenerator {
     builder: Builder,
+    options: RawOptions,
+    request_fields: RequestFields,
+    method_errors: MethodErrors,
+    doc_comments: DocComments,
+    descriptors: TokenStream,
+    /// The descriptors of the services of each package, the services of the
+    /// imported files being generated before their package is finalized.
+    package_services: HashMap<String, TokenStream>,
+    errors: TokenStream,
     clients: TokenStream,
     servers: TokenStream,
 }
 
 impl ServiceGenerator {
-    fn new(builder: Builder) -> Self {
+    fn new(
+        builder: Builder,
+        options: RawOptions,
+        request_fields: RequestFields,
+        method_errors: MethodErrors,
+        doc_comments: DocComments,
+    ) -> Self {
         ServiceGenerator {
             builder,
+            options,
+            request_fields,
+            method_errors,
+            doc_comments,
+            descriptors: TokenStream::default(),
+            package_services: HashMap::new(),
+            errors: TokenStream::default(),
             clients: TokenStream::default(),
             servers: TokenStream::default(),
         }
     }
+
+    /// Apply the renames of the builder to `service` and its methods, the
+    /// generated items being named after their `name`.
+    fn rename(&self, service: &mut prost_build::Service) {
+        let renamed = |path: &str| {
+            self.builder
+                .renames
+                .iter()
+                .rev()
+                .find(|(p, _)| p == path)
+                .map(|(_, name)| name.clone())
+        };
+        let service_path = service_path(service);
+
+        for method in &mut service.methods {
+            if let Some(name) = renamed(&format!("{}.{}", service_path, method.proto_name)) {
+                method.name = name;
+            }
+        }
+        if let Some(name) = renamed(&service_path) {
+            service.name = name;
+        }
+    }
 }
 
 impl prost_build::ServiceGenerator for ServiceGenerator {
-    fn generate(&mut self, service: prost_build::Service, _buf: &mut String) {
+    fn generate(&mut self, mut service: prost_build::Service, _buf: &mut String) {
         let path = "super";
+        self.rename(&mut service);
+        self.doc_comments.render(&mut service);
+        let codec_path = self
+            .builder
+            .codec_path
+            .as_deref()
+            .unwrap_or_else(|| self.builder.prost_version.codec_path());
+        let codec = Codec::new(
+            codec_path,
+            &self.builder.extern_types,
+            self.builder.well_known_types,
+        );
+
+        let name = service_path(&service);
+        let gate = match self.builder.feature_gates {
+            Some(_) => features::gate(&name),
+            None => TokenStream::new(),
+        };
+
+        self.descriptors.extend(gate.clone());
+        self.descriptors
+            .extend(descriptor::generate(&service, &self.options));
+        let service_descriptor = descriptor::service_descriptor(&service);
+        self.package_services
+            .entry(service.package.clone())
+            .or_default()
+            .extend(quote::quote!(#gate #service_descriptor,));
+
+        let errors = self.method_errors.generate(&service);
+        if !errors.is_empty() {
+            self.errors.extend(gate.clone());
+            self.errors.extend(errors);
+        }
 
-        if self.builder.build_server {
-            let server = server::generate(&service, path);
+        if self.builder.build_server && is_selected(&self.builder.server_services, &name) {
+            let server = server::generate(
+                &service,
+                path,
+                &codec,
+                &self.builder.lazy_decode,
+                &self.builder.server_attributes,
+                self.builder.async_fn_in_trait,
+                self.builder.build_transport,
+                self.builder.build_dispatch,
+            );
+            self.servers.extend(gate.clone());
             self.servers.extend(server);
         }
 
-        if self.builder.build_client {
-            let client = client::generate(&service, path);
+        if self.builder.build_client && is_selected(&self.builder.client_services, &name) {
+            let client = client::generate(
+                &service,
+                path,
+                &codec,
+                &self.builder.client_attributes,
+                &self.request_fields,
+                self.builder.client_trait,
+                self.builder.build_transport,
+            );
+            self.clients.extend(gate);
             self.clients.extend(client);
         }
     }
 
     fn finalize(&mut self, buf: &mut String) {
+        if !self.descriptors.is_empty() {
+            buf.push_str(&self.descriptors.to_string());
+
+            self.descriptors = TokenStream::default();
+        }
+
+        if !self.errors.is_empty() {
+            buf.push_str(&self.errors.to_string());
+
+            self.errors = TokenStream::default();
+        }
+
         if self.builder.build_client && !self.clients.is_empty() {
             let clients = &self.clients;
 
@@ -288,6 +1132,17 @@ impl prost_build::ServiceGenerator for ServiceGenerator {
             self.servers = TokenStream::default();
         }
     }
+
+    fn finalize_package(&mut self, package: &str, buf: &mut String) {
+        if self.builder.codec_path.is_none() {
+            buf.push_str(&self.builder.prost_version.generate_codec().to_string());
+        }
+
+        let services = self.package_services.remove(package).unwrap_or_default();
+        if !crate::is_extern(package, &self.builder.extern_path) {
+            buf.push_str(&descriptor::generate_package(package, &services).to_string());
+        }
+    }
 }
 
 // Generate a singular line of a doc comment
@@ -306,6 +1161,15 @@ fn generate_doc_comment(comment: &str) -> TokenStream {
     stream
 }
 
+// Generate `#[deprecated]` for items declared with the `deprecated` option
+fn generate_deprecated(deprecated: bool) -> TokenStream {
+    if deprecated {
+        quote::quote!(#[deprecated])
+    } else {
+        TokenStream::new()
+    }
+}
+
 // Generate a larger doc comment composed of many lines of doc comments
 fn generate_doc_comments<T: AsRef<str>>(comments: &[T]) -> TokenStream {
     let mut stream = TokenStream::new();
@@ -337,6 +1201,57 @@ fn replace_wellknown(proto_path: &str, method: &Method) -> (TokenStream, TokenSt
     (request, response)
 }
 
+/// Attributes added to the generated items named by a proto path.
+#[derive(Debug, Clone, Default)]
+struct Attributes(Vec<(String, String)>);
+
+impl Attributes {
+    fn push(&mut self, path: impl AsRef<str>, attribute: impl AsRef<str>) {
+        self.0
+            .push((service_name(path), attribute.as_ref().to_string()));
+    }
+
+    /// The attributes of the item named `path`.
+    fn get(&self, path: &str) -> TokenStream {
+        let mut stream = TokenStream::new();
+
+        for (_, attribute) in self.0.iter().filter(|(p, _)| p == path) {
+            let attribute = attribute
+                .parse::<TokenStream>()
+                .unwrap_or_else(|_| panic!("invalid attribute: {}", attribute));
+            stream.extend(attribute);
+        }
+
+        stream
+    }
+}
+
+/// The fully qualified proto name of `service`.
+fn service_path(service: &prost_build::Service) -> String {
+    if service.package.is_empty() {
+        service.proto_name.clone()
+    } else {
+        format!("{}.{}", service.package, service.proto_name)
+    }
+}
+
+/// Strip the leading dot of fully qualified proto names.
+fn service_name(name: impl AsRef<str>) -> String {
+    let name = name.as_ref();
+    name.strip_prefix('.').unwrap_or(name).to_string()
+}
+
+fn is_selected(services: &Option<Vec<String>>, name: &str) -> bool {
+    services
+        .as_ref()
+        .is_none_or(|services| services.iter().any(|service| service == name))
+}
+
+/// The identifier named `name`, escaped if it is a keyword.
+fn rust_ident(name: &str) -> Ident {
+    syn::parse_str::<Ident>(name).unwrap_or_else(|_| quote::format_ident!("r#{}", name))
+}
+
 fn naive_snake_case(name: &str) -> String {
     let mut s = String::new();
     let mut it = name.chars().peekable();
@@ -353,6 +1268,86 @@ fn naive_snake_case(name: &str) -> String {
     s
 }
 
+#[test]
+fn test_selected_services() {
+    let services = configure()
+        .build_client_for(["pkg.Foo", ".pkg.Bar"])
+        .client_services;
+    assert!(is_selected(&services, "pkg.Foo"));
+    assert!(is_selected(&services, "pkg.Bar"));
+    assert!(!is_selected(&services, "pkg.Baz"));
+    assert!(!is_selected(&services, "other.Foo"));
+
+    assert!(is_selected(&None, "pkg.Baz"));
+    assert!(!is_selected(&Some(Vec::new()), "pkg.Baz"));
+}
+
+#[test]
+fn test_attributes() {
+    let mut attributes = Attributes::default();
+    attributes.push(".pkg.Foo", "#[deprecated]");
+    attributes.push("pkg.Foo.Bar", "#[cfg(unix)]");
+    attributes.push("pkg.Foo", "#[must_use]");
+
+    assert_eq!(
+        attributes.get("pkg.Foo").to_string(),
+        quote::quote!(#[deprecated] #[must_use]).to_string()
+    );
+    assert_eq!(
+        attributes.get("pkg.Foo.Bar").to_string(),
+        quote::quote!(#[cfg(unix)]).to_string()
+    );
+    assert!(attributes.get("pkg.Baz").is_empty());
+}
+
+#[test]
+fn test_package_services() {
+    use prost_build::ServiceGenerator as _;
+
+    let service = |package: &str, name: &str| prost_build::Service {
+        name: name.to_string(),
+        proto_name: name.to_string(),
+        package: package.to_string(),
+        comments: prost_build::Comments {
+            leading_detached: Vec::new(),
+            leading: Vec::new(),
+            trailing: Vec::new(),
+        },
+        methods: Vec::new(),
+        options: Default::default(),
+    };
+    let mut generator = ServiceGenerator::new(
+        configure(),
+        RawOptions::default(),
+        RequestFields::default(),
+        MethodErrors::default(),
+        DocComments::new(CommentStyle::Markdown, false, false, &[]),
+    );
+
+    // prost-build generates the services of the imported files along with
+    // the compiled ones, before finalizing their packages.
+    let mut buf = String::new();
+    generator.generate(service("imported", "Imported"), &mut buf);
+    generator.generate(service("importing", "Importing"), &mut buf);
+
+    let mut importing = String::new();
+    generator.finalize_package("importing", &mut importing);
+    let mut imported = String::new();
+    generator.finalize_package("imported", &mut imported);
+
+    let package = |package: &str, descriptor: TokenStream| {
+        descriptor::generate_package(package, &quote::quote!(#descriptor,)).to_string()
+    };
+    assert!(importing.ends_with(&package(
+        "importing",
+        quote::quote!(importing_descriptor::SERVICE)
+    )));
+    assert!(imported.ends_with(&package(
+        "imported",
+        quote::quote!(imported_descriptor::SERVICE)
+    )));
+}
+
 #[test]
 fn test_snake_case() {
     for case in &[
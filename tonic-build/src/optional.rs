@@ -0,0 +1,433 @@
+//! Support for proto3 `optional` fields.
+//!
+//! Neither the `protoc` bundled with prost-build nor prost-build itself know
+//! about them, so the label is removed from copies of the `.proto` files
+//! before compiling them, and the fields are generated like proto2 optional
+//! fields, as `Option<T>` only encoded when set.
+
+use prost_build::Config;
+use prost_types::{field_descriptor_proto::Type, DescriptorProto, FileDescriptorProto};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Marks the fields to generate as `Option<T>`, and makes prost encode them
+/// as optional fields.
+const MARKER: &str = "#[prost(optional)]";
+
+/// The `.proto` files to compile, with their proto3 `optional` labels removed.
+pub(crate) struct Proto3Optional {
+    protos: Vec<PathBuf>,
+    includes: Vec<PathBuf>,
+    /// Fully qualified names of the optional fields, like `.pkg.Message.field`.
+    fields: HashSet<String>,
+    dir: Option<PathBuf>,
+}
+
+impl Proto3Optional {
+    /// Copy the files imported from `protos` declaring proto3 optional
+    /// fields to a temporary directory, without their `optional` labels.
+    pub(crate) fn rewrite<P: AsRef<Path>>(protos: &[P], includes: &[P]) -> io::Result<Self> {
+        static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+        let dir = std::env::temp_dir().join(format!(
+            "tonic-build-optional-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        let includes = includes
+            .iter()
+            .map(|include| include.as_ref().to_path_buf())
+            .collect::<Vec<_>>();
+        let mut rewritten = Proto3Optional {
+            protos: protos.iter().map(|p| p.as_ref().to_path_buf()).collect(),
+            includes: Vec::new(),
+            fields: HashSet::new(),
+            dir: None,
+        };
+
+        let mut queue = rewritten.protos.clone();
+        let mut seen = HashSet::new();
+        while let Some(path) = queue.pop() {
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+
+            let source = fs::read_to_string(&path)?;
+            let (imports, stripped) = parse(&source);
+            for import in imports {
+                let found = includes
+                    .iter()
+                    .map(|i| i.join(&import))
+                    .find(|p| p.is_file());
+                queue.extend(found);
+            }
+
+            let (source, fields) = match stripped {
+                Some(stripped) => stripped,
+                None => continue,
+            };
+            let (i, relative) = match includes
+                .iter()
+                .enumerate()
+                .find_map(|(i, include)| Some((i, path.strip_prefix(include).ok()?)))
+            {
+                Some(found) => found,
+                None => continue,
+            };
+
+            let copy = dir.join(i.to_string()).join(relative);
+            fs::create_dir_all(copy.parent().unwrap())?;
+            fs::write(&copy, source)?;
+            rewritten.dir = Some(dir.clone());
+            rewritten.fields.extend(fields);
+
+            // Compile the copy instead of the original file.
+            for proto in &mut rewritten.protos {
+                if *proto == path {
+                    *proto = copy.clone();
+                }
+            }
+        }
+
+        // The copies shadow the original files.
+        for (i, include) in includes.into_iter().enumerate() {
+            let copies = dir.join(i.to_string());
+            if copies.exists() {
+                rewritten.includes.push(copies);
+            }
+            rewritten.includes.push(include);
+        }
+
+        Ok(rewritten)
+    }
+
+    pub(crate) fn protos(&self) -> &[PathBuf] {
+        &self.protos
+    }
+
+    pub(crate) fn includes(&self) -> &[PathBuf] {
+        &self.includes
+    }
+
+    /// Whether `.pkg.Message.field` is a proto3 optional field, generated as
+    /// `Option<T>`.
+    pub(crate) fn contains(&self, field: &str) -> bool {
+        self.fields.contains(field)
+    }
+
+    /// Mark the optional fields of `files` that aren't messages, those being
+    /// generated as `Option<T>` already.
+    pub(crate) fn configure(&mut self, config: &mut Config, files: &[FileDescriptorProto]) {
+        let mut scalars = HashSet::new();
+
+        for file in files {
+            let package = match file.package() {
+                "" => String::new(),
+                package => format!(".{}", package),
+            };
+            for message in &file.message_type {
+                self.find_scalars(&package, message, &mut scalars);
+            }
+        }
+
+        for field in &scalars {
+            config.field_attribute(field, MARKER);
+        }
+        self.fields = scalars;
+    }
+
+    fn find_scalars(&self, scope: &str, message: &DescriptorProto, scalars: &mut HashSet<String>) {
+        let path = format!("{}.{}", scope, message.name());
+
+        for field in &message.field {
+            let field_path = format!("{}.{}", path, field.name());
+            if field.r#type() != Type::Message && self.fields.contains(&field_path) {
+                scalars.insert(field_path);
+            }
+        }
+        for nested in &message.nested_type {
+            self.find_scalars(&path, nested, scalars);
+        }
+    }
+
+    /// Turn the types of the marked fields generated in `out_dir` into
+    /// `Option<T>`.
+    pub(crate) fn wrap_fields(&self, out_dir: &Path) -> io::Result<()> {
+        for entry in fs::read_dir(out_dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "rs") {
+                continue;
+            }
+
+            let code = fs::read_to_string(&path)?;
+            if let Some(code) = wrap_marked_fields(&code) {
+                fs::write(&path, code)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Proto3Optional {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// Wrap the type of the fields following a marker in `Option`, returning the
+/// new code if there were any.
+fn wrap_marked_fields(code: &str) -> Option<String> {
+    let mut lines = code.lines().map(str::to_string).collect::<Vec<_>>();
+    let mut changed = false;
+
+    for i in 0..lines.len() {
+        if lines[i].trim() != MARKER {
+            continue;
+        }
+
+        let field = match lines[i..].iter_mut().find(|l| l.trim().starts_with("pub ")) {
+            Some(field) => field,
+            None => continue,
+        };
+        let colon = match field.find(": ") {
+            Some(colon) => colon + 2,
+            None => continue,
+        };
+        let ty = field[colon..].trim_end().trim_end_matches(',');
+        if ty.starts_with("::std::option::Option<") {
+            continue;
+        }
+
+        *field = format!("{}::std::option::Option<{}>,", &field[..colon], ty);
+        changed = true;
+    }
+
+    if changed {
+        let mut code = lines.join("\n");
+        code.push('\n');
+        Some(code)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Symbol(char),
+    Literal(&'a str),
+}
+
+/// Split `source` into tokens along with their offsets, skipping comments.
+fn tokenize(source: &str) -> Vec<(usize, Token<'_>)> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        match bytes[i] {
+            b if b.is_ascii_whitespace() => i += 1,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !bytes[i..].starts_with(b"*/") {
+                    i += 1;
+                }
+                i += 2;
+            }
+            quote @ b'"' | quote @ b'\'' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+                let end = (i - 1).max(start + 1).min(source.len());
+                tokens.push((start, Token::Literal(&source[start + 1..end])));
+            }
+            b if b.is_ascii_alphanumeric() || b == b'_' => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                tokens.push((start, Token::Ident(&source[start..i])));
+            }
+            b => {
+                i += 1;
+                tokens.push((start, Token::Symbol(b as char)));
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Parse a `.proto` file, returning its imports and, if it is a proto3 file
+/// with optional fields, its source without their `optional` labels along
+/// with their fully qualified names.
+#[allow(clippy::type_complexity)]
+fn parse(source: &str) -> (Vec<String>, Option<(String, Vec<String>)>) {
+    let tokens = tokenize(source);
+    let token = |i: usize| tokens.get(i).map(|(_, token)| *token);
+
+    let mut proto3 = false;
+    let mut package = String::new();
+    // `Some(name)` for messages, `None` for the other blocks.
+    let mut scopes: Vec<Option<&str>> = Vec::new();
+    let mut statement_start = true;
+    let mut imports = Vec::new();
+    let mut labels = Vec::new();
+    let mut fields = Vec::new();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i].1 {
+            Token::Ident("syntax") if statement_start && scopes.is_empty() => {
+                proto3 = tokens[i..]
+                    .iter()
+                    .take_while(|(_, token)| *token != Token::Symbol(';'))
+                    .any(|(_, token)| *token == Token::Literal("proto3"));
+            }
+            Token::Ident("import") if statement_start && scopes.is_empty() => {
+                let import = tokens[i + 1..]
+                    .iter()
+                    .take_while(|(_, token)| *token != Token::Symbol(';'))
+                    .find_map(|(_, token)| match token {
+                        Token::Literal(import) => Some(import.to_string()),
+                        _ => None,
+                    });
+                imports.extend(import);
+            }
+            Token::Ident("package") if statement_start && scopes.is_empty() => {
+                package = tokens[i + 1..]
+                    .iter()
+                    .take_while(|(_, token)| *token != Token::Symbol(';'))
+                    .map(|(_, token)| match token {
+                        Token::Ident(ident) => *ident,
+                        _ => ".",
+                    })
+                    .collect();
+            }
+            Token::Ident("optional")
+                if statement_start && matches!(scopes.last(), Some(Some(_))) =>
+            {
+                // `optional Type name =`, as opposed to a field of a type named
+                // `optional`.
+                let mut j = i + 1;
+                while token(j) == Some(Token::Symbol('.'))
+                    || matches!(token(j), Some(Token::Ident(_)))
+                {
+                    j += 1;
+                }
+                if j >= i + 3 && token(j) == Some(Token::Symbol('=')) {
+                    if let Some(Token::Ident(name)) = token(j - 1) {
+                        let scope = scopes.iter().flatten().copied().collect::<Vec<_>>();
+                        let message = match package.as_str() {
+                            "" => scope.join("."),
+                            package => format!("{}.{}", package, scope.join(".")),
+                        };
+                        fields.push(format!(".{}.{}", message, name));
+                        labels.push(tokens[i].0);
+                    }
+                }
+            }
+            Token::Symbol('{') => {
+                let message = match (
+                    i.checked_sub(2).and_then(token),
+                    i.checked_sub(1).and_then(token),
+                ) {
+                    (Some(Token::Ident("message")), Some(Token::Ident(name))) => Some(name),
+                    _ => None,
+                };
+                scopes.push(message);
+            }
+            Token::Symbol('}') => {
+                scopes.pop();
+            }
+            _ => {}
+        }
+
+        statement_start = matches!(tokens[i].1, Token::Symbol(';' | '{' | '}'));
+        i += 1;
+    }
+
+    if !proto3 || labels.is_empty() {
+        return (imports, None);
+    }
+
+    let mut stripped = source.to_string();
+    for offset in labels {
+        stripped.replace_range(offset..offset + "optional".len(), "        ");
+    }
+
+    (imports, Some((stripped, fields)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_proto3_optional_labels() {
+        let source = r#"
+            syntax = "proto3";
+            package pkg.sub;
+
+            import "google/protobuf/empty.proto";
+            import public "other.proto";
+
+            message Outer {
+              optional string name = 1; // optional comment
+              optional .pkg.sub.Outer.Inner inner = 2;
+              optional optional = 3;
+              repeated string tags = 4;
+
+              message Inner {
+                /* optional */ optional int32 count = 1;
+              }
+            }
+        "#;
+
+        let (imports, stripped) = parse(source);
+        assert_eq!(imports, vec!["google/protobuf/empty.proto", "other.proto"]);
+
+        let (stripped, fields) = stripped.unwrap();
+        assert_eq!(
+            fields,
+            vec![
+                ".pkg.sub.Outer.name",
+                ".pkg.sub.Outer.inner",
+                ".pkg.sub.Outer.Inner.count"
+            ]
+        );
+        assert!(stripped.contains("string name = 1; // optional comment"));
+        assert!(stripped.contains("optional optional = 3;"));
+        assert!(stripped.contains("/* optional */          int32 count = 1;"));
+
+        let proto2 = source.replace("proto3", "proto2");
+        assert!(parse(&proto2).1.is_none());
+    }
+
+    #[test]
+    fn wraps_marked_fields() {
+        let code = "pub struct Outer {\n    #[prost(string, tag=\"1\")]\n    #[prost(optional)]\n    #[serde(default)]\n    pub name: std::string::String,\n    #[prost(int32, tag=\"2\")]\n    pub count: i32,\n}\n";
+
+        let wrapped = wrap_marked_fields(code).unwrap();
+        assert!(wrapped.contains("    pub name: ::std::option::Option<std::string::String>,\n"));
+        assert!(wrapped.contains("    pub count: i32,\n"));
+        assert_eq!(wrap_marked_fields(&wrapped), None);
+    }
+}
@@ -0,0 +1,220 @@
+//! Options of the `.proto` files carried into the generated code.
+
+use prost::Message;
+use prost_build::Config;
+use prost_types::{DescriptorProto, EnumDescriptorProto, FileDescriptorProto};
+use std::{collections::HashMap, io};
+
+/// Add `#[deprecated]` to the fields declared with the `deprecated` option,
+/// and a note to the docs of the deprecated messages, enums, enum values and
+/// oneof fields.
+///
+/// The impls derived by prost would trigger the deprecation warnings of the
+/// latter, so they aren't marked `#[deprecated]`.
+pub(crate) fn configure_deprecated(config: &mut Config, files: &[FileDescriptorProto]) {
+    for file in files {
+        let package = match file.package() {
+            "" => String::new(),
+            package => format!(".{}", package),
+        };
+        let deprecated = file.options.as_ref().is_some_and(|o| o.deprecated());
+
+        for message in &file.message_type {
+            deprecate_message(config, &package, message, deprecated);
+        }
+        for enumeration in &file.enum_type {
+            deprecate_enum(config, &package, enumeration, deprecated);
+        }
+    }
+}
+
+const DEPRECATED_DOC: &str = "#[doc = \"\"] #[doc = \" **Deprecated** in the `.proto` file.\"]";
+
+fn deprecate_message(config: &mut Config, scope: &str, message: &DescriptorProto, parent: bool) {
+    let path = format!("{}.{}", scope, message.name());
+    let deprecated = parent || message.options.as_ref().is_some_and(|o| o.deprecated());
+
+    if deprecated {
+        config.type_attribute(&path, DEPRECATED_DOC);
+    }
+    for field in &message.field {
+        if deprecated || !field.options.as_ref().is_some_and(|o| o.deprecated()) {
+            continue;
+        }
+        match field.oneof_index {
+            Some(index) => {
+                let oneof = message.oneof_decl[index as usize].name();
+                let field_path = format!("{}.{}.{}", path, oneof, field.name());
+                config.field_attribute(field_path, DEPRECATED_DOC);
+            }
+            None => {
+                let field_path = format!("{}.{}", path, field.name());
+                config.field_attribute(field_path, "#[deprecated]");
+            }
+        }
+    }
+
+    for nested in &message.nested_type {
+        deprecate_message(config, &path, nested, deprecated);
+    }
+    for enumeration in &message.enum_type {
+        deprecate_enum(config, &path, enumeration, deprecated);
+    }
+}
+
+fn deprecate_enum(
+    config: &mut Config,
+    scope: &str,
+    enumeration: &EnumDescriptorProto,
+    parent: bool,
+) {
+    let path = format!("{}.{}", scope, enumeration.name());
+
+    if parent || enumeration.options.as_ref().is_some_and(|o| o.deprecated()) {
+        config.type_attribute(&path, DEPRECATED_DOC);
+        return;
+    }
+    for value in &enumeration.value {
+        if value.options.as_ref().is_some_and(|o| o.deprecated()) {
+            config.field_attribute(format!("{}.{}", path, value.name()), DEPRECATED_DOC);
+        }
+    }
+}
+
+/// The encoded options of the services and methods, as written by `protoc`.
+///
+/// Unlike the options decoded by prost, they keep the custom options.
+#[derive(Debug, Default)]
+pub(crate) struct RawOptions {
+    /// By fully qualified service name, like `pkg.Service`.
+    services: HashMap<String, Vec<u8>>,
+    /// By method path, like `/pkg.Service/Method`.
+    methods: HashMap<String, Vec<u8>>,
+}
+
+impl RawOptions {
+    /// Collect the options of the encoded `FileDescriptorSet` `buf`.
+    pub(crate) fn parse(buf: &[u8]) -> io::Result<Self> {
+        let mut options = RawOptions::default();
+
+        for file in RawFileDescriptorSet::decode(buf)?.file {
+            let file = RawFileDescriptor::decode(&*file)?;
+
+            for service in file.service {
+                let service = RawServiceDescriptor::decode(&*service)?;
+                let name = match file.package.as_str() {
+                    "" => service.name.clone(),
+                    package => format!("{}.{}", package, service.name),
+                };
+
+                for method in service.method {
+                    let method = RawMethodDescriptor::decode(&*method)?;
+                    if let Some(buf) = method.options {
+                        let path = format!("/{}.{}/{}", file.package, service.name, method.name);
+                        options.methods.insert(path, buf);
+                    }
+                }
+                if let Some(buf) = service.options {
+                    options.services.insert(name, buf);
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    pub(crate) fn service(&self, name: &str) -> &[u8] {
+        self.services.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn method(&self, path: &str) -> &[u8] {
+        self.methods.get(path).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// A `FileDescriptorSet` with its files left encoded.
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct RawFileDescriptorSet {
+    #[prost(bytes, repeated, tag = "1")]
+    pub(crate) file: Vec<Vec<u8>>,
+}
+
+/// The parts of a `FileDescriptorProto` needed to find the service options.
+#[derive(Clone, PartialEq, Message)]
+struct RawFileDescriptor {
+    #[prost(string, tag = "2")]
+    package: String,
+    #[prost(bytes, repeated, tag = "6")]
+    service: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RawServiceDescriptor {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(bytes, repeated, tag = "2")]
+    method: Vec<Vec<u8>>,
+    #[prost(bytes, optional, tag = "3")]
+    options: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RawMethodDescriptor {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(bytes, optional, tag = "4")]
+    options: Option<Vec<u8>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_types::{MethodDescriptorProto, MethodOptions, ServiceDescriptorProto};
+
+    fn encode(message: impl Message) -> Vec<u8> {
+        let mut buf = Vec::new();
+        message.encode(&mut buf).unwrap();
+        buf
+    }
+
+    /// Append a short length delimited field to `buf`.
+    fn append(mut buf: Vec<u8>, key: u8, value: &[u8]) -> Vec<u8> {
+        buf.push(key);
+        buf.push(value.len() as u8);
+        buf.extend_from_slice(value);
+        buf
+    }
+
+    #[test]
+    fn keeps_custom_options() {
+        let mut options = encode(MethodOptions {
+            deprecated: Some(true),
+            ..MethodOptions::default()
+        });
+        // A custom string option numbered 50000, set to "x".
+        options.extend_from_slice(&[0x82, 0xb5, 0x18, 0x01, b'x']);
+
+        let method = encode(MethodDescriptorProto {
+            name: Some("Method".to_string()),
+            ..MethodDescriptorProto::default()
+        });
+        let service = encode(ServiceDescriptorProto {
+            name: Some("Service".to_string()),
+            ..ServiceDescriptorProto::default()
+        });
+        let file = encode(FileDescriptorProto {
+            package: Some("pkg".to_string()),
+            ..FileDescriptorProto::default()
+        });
+
+        let method = append(method, 4 << 3 | 2, &options);
+        let service = append(service, 2 << 3 | 2, &method);
+        let file = append(file, 6 << 3 | 2, &service);
+        let set = append(Vec::new(), 1 << 3 | 2, &file);
+
+        let parsed = RawOptions::parse(&set).unwrap();
+        assert_eq!(parsed.method("/pkg.Service/Method"), &options[..]);
+        assert!(parsed.method("/pkg.Service/Other").is_empty());
+        assert!(parsed.service("pkg.Service").is_empty());
+    }
+}
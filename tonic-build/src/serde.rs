@@ -0,0 +1,209 @@
+use crate::optional::Proto3Optional;
+use prost_build::Config;
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+};
+use std::io::{self, Error};
+
+/// Wrappers of `google.protobuf` generated as plain Rust types by prost, and
+/// thus already handled by serde.
+const WRAPPERS: &[&str] = &[
+    "BoolValue",
+    "DoubleValue",
+    "Empty",
+    "FloatValue",
+    "Int32Value",
+    "Int64Value",
+    "StringValue",
+    "UInt32Value",
+    "UInt64Value",
+];
+
+/// Add the attributes deriving serde, following the proto3 JSON mapping, on
+/// the types generated from `files`.
+///
+/// Fields are named after their JSON name, their original name being accepted
+/// too.
+pub(crate) fn configure(
+    config: &mut Config,
+    files: &[FileDescriptorProto],
+    extern_path: &[(String, String)],
+    optional: &Proto3Optional,
+) -> io::Result<()> {
+    config.type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]");
+
+    for file in files {
+        let package = match file.package() {
+            "" => String::new(),
+            package => format!(".{}", package),
+        };
+        if crate::is_extern(file.package(), extern_path) {
+            continue;
+        }
+
+        let proto2 = file.syntax() != "proto3";
+        for message in &file.message_type {
+            configure_message(config, &package, message, proto2, optional)?;
+        }
+        for enumeration in &file.enum_type {
+            configure_enum(config, &package, enumeration);
+        }
+    }
+
+    Ok(())
+}
+
+fn configure_message(
+    config: &mut Config,
+    scope: &str,
+    message: &DescriptorProto,
+    proto2: bool,
+    optional: &Proto3Optional,
+) -> io::Result<()> {
+    let path = format!("{}.{}", scope, message.name());
+
+    // Oneofs are flattened into their message, the matcher has no leading
+    // dot so it doesn't also match their variants.
+    for oneof in &message.oneof_decl {
+        config.field_attribute(
+            format!("{}.{}", &path[1..], oneof.name()),
+            "#[serde(flatten)]",
+        );
+    }
+
+    for field in &message.field {
+        let json_name = match field.json_name() {
+            "" => field.name(),
+            json_name => json_name,
+        };
+        let mut attributes = Vec::new();
+
+        let (field_path, shape) = match field.oneof_index {
+            Some(index) => {
+                // Variants are named in camel case, their name is always set.
+                attributes.push(format!("rename = {:?}", json_name));
+                if json_name != field.name() {
+                    attributes.push(format!("alias = {:?}", field.name()));
+                }
+
+                let oneof = message.oneof_decl[index as usize].name();
+                let field_path = format!("{}.{}.{}", path, oneof, field.name());
+                (field_path, Shape::Plain)
+            }
+            None => {
+                if json_name != field.name() {
+                    attributes.push(format!("rename = {:?}", json_name));
+                    attributes.push(format!("alias = {:?}", field.name()));
+                }
+                attributes.push("default".to_string());
+                let field_path = format!("{}.{}", path, field.name());
+                let shape = match (field.label(), field.r#type()) {
+                    (Label::Repeated, _) => match map_entry(message, field) {
+                        Some(entry) => Shape::Map(&entry.field[1]),
+                        None => Shape::Vec,
+                    },
+                    (Label::Optional, Type::Message) => Shape::Option,
+                    (Label::Optional, _) if proto2 || optional.contains(&field_path) => {
+                        Shape::Option
+                    }
+                    _ => Shape::Plain,
+                };
+                (field_path, shape)
+            }
+        };
+
+        if let Some(with) = with(&path, field, shape)? {
+            attributes.push(with);
+        }
+        let attributes = attributes.join(", ");
+        config.field_attribute(field_path, format!("#[serde({})]", attributes));
+    }
+
+    for nested in &message.nested_type {
+        if !is_map_entry(nested) {
+            configure_message(config, &path, nested, proto2, optional)?;
+        }
+    }
+    for enumeration in &message.enum_type {
+        configure_enum(config, &path, enumeration);
+    }
+
+    Ok(())
+}
+
+/// Enums are named like in the `.proto` files.
+///
+/// Enum fields are generated as `i32` and thus written as numbers, which
+/// keeps the values unknown to the reader intact.
+fn configure_enum(config: &mut Config, scope: &str, enumeration: &EnumDescriptorProto) {
+    let path = format!("{}.{}", scope, enumeration.name());
+
+    for value in &enumeration.value {
+        config.field_attribute(
+            format!("{}.{}", path, value.name()),
+            format!("#[serde(rename = {:?})]", value.name()),
+        );
+    }
+}
+
+/// How a field is wrapped in the generated Rust type.
+enum Shape<'a> {
+    Plain,
+    Option,
+    Vec,
+    Map(&'a FieldDescriptorProto),
+}
+
+/// The `with` attribute for fields that need one of the helpers of
+/// `tonic::codegen::serde`.
+fn with(path: &str, field: &FieldDescriptorProto, shape: Shape<'_>) -> io::Result<Option<String>> {
+    let (value, suffix) = match shape {
+        Shape::Plain => (field, ""),
+        Shape::Option => (field, "::option"),
+        Shape::Vec => (field, "::vec"),
+        Shape::Map(value) => (value, "::map"),
+    };
+
+    let helper = match (value.r#type(), value.type_name()) {
+        (Type::Bytes, _) | (Type::Message, ".google.protobuf.BytesValue") => "bytes",
+        (Type::Message, ".google.protobuf.Timestamp") => "timestamp",
+        (Type::Message, ".google.protobuf.Duration") => "duration",
+        (Type::Message, name) if name.starts_with(".google.protobuf.") => {
+            if WRAPPERS.contains(&&name[".google.protobuf.".len()..]) {
+                return Ok(None);
+            }
+
+            return Err(Error::other(format!(
+                "derive_serde doesn't support `{}` fields, used by `{}.{}`",
+                &name[1..],
+                &path[1..],
+                field.name()
+            )));
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(format!(
+        "with = \"tonic::codegen::serde::{}{}\"",
+        helper, suffix
+    )))
+}
+
+fn map_entry<'a>(
+    message: &'a DescriptorProto,
+    field: &FieldDescriptorProto,
+) -> Option<&'a DescriptorProto> {
+    let name = field.type_name().rsplit('.').next()?;
+    message
+        .nested_type
+        .iter()
+        .find(|nested| nested.name() == name && is_map_entry(nested))
+}
+
+fn is_map_entry(message: &DescriptorProto) -> bool {
+    message
+        .options
+        .as_ref()
+        .is_some_and(|options| options.map_entry())
+}
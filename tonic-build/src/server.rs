@@ -1,26 +1,81 @@
-use crate::{generate_doc_comment, generate_doc_comments, naive_snake_case};
+use crate::codec::{Codec, MethodCodecs};
+use crate::{
+    generate_deprecated, generate_doc_comment, generate_doc_comments, naive_snake_case,
+    service_path, Attributes,
+};
 use proc_macro2::{Span, TokenStream};
 use prost_build::{Method, Service};
 use quote::quote;
 use syn::{Ident, Lit, LitStr};
 
-pub(crate) fn generate(service: &Service, proto_path: &str) -> TokenStream {
-    let methods = generate_methods(&service, proto_path);
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn generate(
+    service: &Service,
+    proto_path: &str,
+    codec: &Codec,
+    lazy_decode: &[String],
+    attributes: &Attributes,
+    async_fn_in_trait: bool,
+    transport: bool,
+    dispatch: bool,
+) -> TokenStream {
+    let methods = generate_methods(&service, proto_path, codec, lazy_decode);
 
     let server_service = quote::format_ident!("{}Server", service.name);
     let server_trait = quote::format_ident!("{}", service.name);
     let server_mod = quote::format_ident!("{}_server", naive_snake_case(&service.name));
-    let generated_trait = generate_trait(service, proto_path, server_trait.clone());
+    let generated_trait = generate_trait(
+        service,
+        proto_path,
+        codec,
+        server_trait.clone(),
+        lazy_decode,
+        attributes,
+        async_fn_in_trait,
+    );
     let service_doc = generate_doc_comments(&service.comments.leading);
+    let codecs = MethodCodecs::server(codec, proto_path, service);
+    let default_codec = codecs.generate_default();
+    let codec_bounds = codecs.bounds();
 
     // Transport based implementations
     let path = format!("{}.{}", service.package, service.proto_name);
-    let transport = generate_transport(&server_service, &server_trait, &path);
+    let named_service = if transport {
+        generate_transport(&server_service, &server_trait, &path)
+    } else {
+        TokenStream::new()
+    };
+    // Without the transport, the servers take the requests of any body.
+    let service_impl = if !transport {
+        quote! {
+            impl<T: #server_trait, C, B> Service<http::Request<B>> for #server_service<T, C>
+            where
+                C: #codec_bounds,
+                B: HttpBody + Send + Sync + 'static,
+                B::Error: Into<StdError> + Send + 'static,
+        }
+    } else {
+        quote! {
+            impl<T: #server_trait, C> Service<http::Request<HyperBody>> for #server_service<T, C>
+            where
+                C: #codec_bounds,
+        }
+    };
+    let request_body = if transport {
+        quote!(HyperBody)
+    } else {
+        quote!(B)
+    };
+    let dispatch = if dispatch {
+        generate_dispatch(&server_service, &server_trait, transport)
+    } else {
+        TokenStream::new()
+    };
 
     quote! {
         /// Generated server implementations.
         pub mod #server_mod {
-            #![allow(unused_variables, dead_code, missing_docs)]
+            #![allow(unused_variables, dead_code, missing_docs, deprecated)]
             use tonic::codegen::*;
 
             #generated_trait
@@ -28,27 +83,154 @@ pub(crate) fn generate(service: &Service, proto_path: &str) -> TokenStream {
             #service_doc
             #[derive(Debug)]
             #[doc(hidden)]
-            pub struct #server_service<T: #server_trait> {
+            pub struct #server_service<T: #server_trait, C = DefaultCodec> {
                 inner: _Inner<T>,
+                codec: C,
+                accept_compression_encodings: EnabledCompressionEncodings,
+                send_compression_encodings: EnabledCompressionEncodings,
+                compression_level: CompressionLevel,
+                compression_threshold: usize,
+                buffer_pool: Option<BufferPool>,
+                yield_after_messages: Option<usize>,
+                message_checksums: bool,
+                enforce_deadlines: bool,
+                max_metadata_size: Option<usize>,
+                max_decoding_message_size: Option<usize>,
+                max_encoding_message_size: Option<usize>,
             }
 
             struct _Inner<T>(Arc<T>, Option<tonic::Interceptor>);
 
+            #default_codec
+
             impl<T: #server_trait> #server_service<T> {
                 pub fn new(inner: T) -> Self {
-                    let inner = Arc::new(inner);
-                    let inner = _Inner(inner, None);
-                    Self { inner }
+                    Self::with_codec(inner, DefaultCodec)
                 }
 
                 pub fn with_interceptor(inner: T, interceptor: impl Into<tonic::Interceptor>) -> Self {
                     let inner = Arc::new(inner);
                     let inner = _Inner(inner, Some(interceptor.into()));
-                    Self { inner }
+                    Self {
+                        inner,
+                        codec: DefaultCodec,
+                        accept_compression_encodings: Default::default(),
+                        send_compression_encodings: Default::default(),
+                        compression_level: Default::default(),
+                        compression_threshold: 0,
+                        buffer_pool: None,
+                        yield_after_messages: None,
+                        message_checksums: false,
+                        enforce_deadlines: true,
+                        max_metadata_size: None,
+                        max_decoding_message_size: None,
+                        max_encoding_message_size: None,
+                    }
                 }
             }
 
-            impl<T: #server_trait> Service<http::Request<HyperBody>> for #server_service<T> {
+            impl<T: #server_trait, C> #server_service<T, C> {
+                /// Encode and decode the messages of the calls with the codecs made by `codec`,
+                /// instead of the ones chosen when generating this server.
+                pub fn with_codec(inner: T, codec: C) -> Self {
+                    let inner = Arc::new(inner);
+                    let inner = _Inner(inner, None);
+                    Self {
+                        inner,
+                        codec,
+                        accept_compression_encodings: Default::default(),
+                        send_compression_encodings: Default::default(),
+                        compression_level: Default::default(),
+                        compression_threshold: 0,
+                        buffer_pool: None,
+                        yield_after_messages: None,
+                        message_checksums: false,
+                        enforce_deadlines: true,
+                        max_metadata_size: None,
+                        max_decoding_message_size: None,
+                        max_encoding_message_size: None,
+                    }
+                }
+
+                /// Accept requests compressed with the provided encoding.
+                pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+                    self.accept_compression_encodings.enable(encoding);
+                    self
+                }
+
+                /// Compress responses with the provided encoding, if the client accepts it.
+                pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+                    self.send_compression_encodings.enable(encoding);
+                    self
+                }
+
+                /// Set the level responses are compressed at.
+                pub fn compression_level(mut self, level: CompressionLevel) -> Self {
+                    self.compression_level = level;
+                    self
+                }
+
+                /// Send response messages smaller than `threshold` bytes uncompressed.
+                pub fn compression_threshold(mut self, threshold: usize) -> Self {
+                    self.compression_threshold = threshold;
+                    self
+                }
+
+                /// Take the buffers messages are encoded into and decoded from from `pool`.
+                pub fn buffer_pool(mut self, pool: BufferPool) -> Self {
+                    self.buffer_pool = Some(pool);
+                    self
+                }
+
+                /// Yield to the runtime after handing `limit` buffered request messages in a row to
+                /// the service.
+                pub fn yield_after_messages(mut self, limit: usize) -> Self {
+                    self.yield_after_messages = Some(limit);
+                    self
+                }
+
+                /// Send a checksum of the response messages, and check the one of the request messages.
+                pub fn message_checksums(mut self, enable: bool) -> Self {
+                    self.message_checksums = enable;
+                    self
+                }
+
+                /// Fail calls with `DeadlineExceeded` once the `grpc-timeout` sent by the client
+                /// elapsed, cancelling their handler.
+                ///
+                /// Enabled by default.
+                pub fn enforce_deadlines(mut self, enable: bool) -> Self {
+                    self.enforce_deadlines = enable;
+                    self
+                }
+
+                /// Limit the size of the request metadata to `limit` bytes, counted like HTTP/2
+                /// header lists.
+                ///
+                /// Larger metadata fails the call with a `ResourceExhausted` status.
+                pub fn max_metadata_size(mut self, limit: usize) -> Self {
+                    self.max_metadata_size = Some(limit);
+                    self
+                }
+
+                /// Limit the size of the request messages decoded to `limit` bytes.
+                ///
+                /// Larger messages fail the call with a `ResourceExhausted` status. Defaults to 4MiB.
+                pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+                    self.max_decoding_message_size = Some(limit);
+                    self
+                }
+
+                /// Limit the size of the response messages encoded to `limit` bytes.
+                ///
+                /// Larger messages fail the call with an `OutOfRange` status. Defaults to no limit.
+                pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+                    self.max_encoding_message_size = Some(limit);
+                    self
+                }
+            }
+
+            #service_impl {
                 type Response = http::Response<tonic::body::BoxBody>;
                 type Error = Never;
                 type Future = BoxFuture<Self::Response, Self::Error>;
@@ -57,7 +239,7 @@ pub(crate) fn generate(service: &Service, proto_path: &str) -> TokenStream {
                     Poll::Ready(Ok(()))
                 }
 
-                fn call(&mut self, req: http::Request<HyperBody>) -> Self::Future {
+                fn call(&mut self, req: http::Request<#request_body>) -> Self::Future {
                     let inner = self.inner.clone();
 
                     match req.uri().path() {
@@ -74,10 +256,24 @@ pub(crate) fn generate(service: &Service, proto_path: &str) -> TokenStream {
                 }
             }
 
-            impl<T: #server_trait> Clone for #server_service<T> {
+            impl<T: #server_trait, C: Clone> Clone for #server_service<T, C> {
                 fn clone(&self) -> Self {
                     let inner = self.inner.clone();
-                    Self { inner }
+                    Self {
+                        inner,
+                        codec: self.codec.clone(),
+                        accept_compression_encodings: self.accept_compression_encodings,
+                        send_compression_encodings: self.send_compression_encodings,
+                        compression_level: self.compression_level,
+                        compression_threshold: self.compression_threshold,
+                        buffer_pool: self.buffer_pool.clone(),
+                        yield_after_messages: self.yield_after_messages,
+                        message_checksums: self.message_checksums,
+                        enforce_deadlines: self.enforce_deadlines,
+                        max_metadata_size: self.max_metadata_size,
+                        max_decoding_message_size: self.max_decoding_message_size,
+                        max_encoding_message_size: self.max_encoding_message_size,
+                    }
                 }
             }
 
@@ -93,93 +289,144 @@ pub(crate) fn generate(service: &Service, proto_path: &str) -> TokenStream {
                 }
             }
 
-            #transport
+            #named_service
+
+            #dispatch
+        }
+    }
+}
+
+fn generate_dispatch(server_service: &Ident, server_trait: &Ident, transport: bool) -> TokenStream {
+    let request_body = if transport {
+        quote!(HyperBody)
+    } else {
+        quote!(tonic::server::FullBody)
+    };
+
+    quote! {
+        /// Call `server` with a request to `path` carrying the gRPC framed `body`, without any
+        /// transport, returning the framed response messages and the status of the call.
+        ///
+        /// See `tonic::server::dispatch`.
+        pub async fn dispatch<T: #server_trait>(
+            server: &#server_service<T>,
+            path: &str,
+            body: Bytes,
+        ) -> (Bytes, tonic::Status) {
+            tonic::server::dispatch::<_, #request_body>(server.clone(), path, body).await
         }
     }
 }
 
-fn generate_trait(service: &Service, proto_path: &str, server_trait: Ident) -> TokenStream {
-    let methods = generate_trait_methods(service, proto_path);
+fn generate_trait(
+    service: &Service,
+    proto_path: &str,
+    codec: &Codec,
+    server_trait: Ident,
+    lazy_decode: &[String],
+    attributes: &Attributes,
+    async_fn_in_trait: bool,
+) -> TokenStream {
+    let methods = generate_trait_methods(
+        service,
+        proto_path,
+        codec,
+        lazy_decode,
+        attributes,
+        async_fn_in_trait,
+    );
+    let mut trait_attributes = generate_deprecated(service.options.deprecated());
+    trait_attributes.extend(attributes.get(&service_path(service)));
     let trait_doc = generate_doc_comment(&format!(
         "Generated trait containing gRPC methods that should be implemented for use with {}Server.",
         service.name
     ));
 
+    let async_trait = if async_fn_in_trait {
+        TokenStream::new()
+    } else {
+        quote!(#[async_trait])
+    };
+
     quote! {
         #trait_doc
-        #[async_trait]
+        #trait_attributes
+        #async_trait
         pub trait #server_trait : Send + Sync + 'static {
             #methods
         }
     }
 }
 
-fn generate_trait_methods(service: &Service, proto_path: &str) -> TokenStream {
+fn generate_trait_methods(
+    service: &Service,
+    proto_path: &str,
+    codec: &Codec,
+    lazy_decode: &[String],
+    attributes: &Attributes,
+    async_fn_in_trait: bool,
+) -> TokenStream {
     let mut stream = TokenStream::new();
+    let service_path = service_path(service);
 
     for method in &service.methods {
         let name = quote::format_ident!("{}", method.name);
 
-        let (req_message, res_message) = crate::replace_wellknown(proto_path, &method);
+        let lazy = lazy_decode.contains(&method_path(service, method));
+        let (req_message, res_message) = message_types(codec, proto_path, &method, lazy);
 
         let method_doc = generate_doc_comments(&method.comments.leading);
+        let mut method_attributes = generate_deprecated(method.options.deprecated());
+        method_attributes
+            .extend(attributes.get(&format!("{}.{}", service_path, method.proto_name)));
+
+        let request = if method.client_streaming {
+            quote!(tonic::Request<tonic::Streaming<#req_message>>)
+        } else {
+            quote!(tonic::Request<#req_message>)
+        };
 
-        let method = match (method.client_streaming, method.server_streaming) {
-            (false, false) => {
-                quote! {
-                    #method_doc
-                    async fn #name(&self, request: tonic::Request<#req_message>)
-                        -> Result<tonic::Response<#res_message>, tonic::Status>;
-                }
-            }
-            (true, false) => {
-                quote! {
-                    #method_doc
-                    async fn #name(&self, request: tonic::Request<tonic::Streaming<#req_message>>)
-                        -> Result<tonic::Response<#res_message>, tonic::Status>;
-                }
-            }
-            (false, true) => {
-                let stream = quote::format_ident!("{}Stream", method.proto_name);
-                let stream_doc = generate_doc_comment(&format!(
-                    "Server streaming response type for the {} method.",
-                    method.proto_name
-                ));
-
-                quote! {
-                    #stream_doc
-                    type #stream: Stream<Item = Result<#res_message, tonic::Status>> + Send + Sync + 'static;
-
-                    #method_doc
-                    async fn #name(&self, request: tonic::Request<#req_message>)
-                        -> Result<tonic::Response<Self::#stream>, tonic::Status>;
-                }
+        let response = if method.server_streaming {
+            let stream_ident = quote::format_ident!("{}Stream", method.proto_name);
+            let stream_doc = generate_doc_comment(&format!(
+                "Server streaming response type for the {} method.",
+                method.proto_name
+            ));
+
+            stream.extend(quote! {
+                #stream_doc
+                type #stream_ident: Stream<Item = Result<#res_message, tonic::Status>> + Send + Sync + 'static;
+            });
+            quote!(tonic::Response<Self::#stream_ident>)
+        } else {
+            quote!(tonic::Response<#res_message>)
+        };
+
+        // Native `async fn` futures aren't known to be `Send`, so the method
+        // returns an `impl Future` that is, which `async fn` implementations
+        // satisfy.
+        let signature = if async_fn_in_trait {
+            quote! {
+                fn #name(&self, request: #request)
+                    -> impl Future<Output = Result<#response, tonic::Status>> + Send;
             }
-            (true, true) => {
-                let stream = quote::format_ident!("{}Stream", method.proto_name);
-                let stream_doc = generate_doc_comment(&format!(
-                    "Server streaming response type for the {} method.",
-                    method.proto_name
-                ));
-
-                quote! {
-                    #stream_doc
-                    type #stream: Stream<Item = Result<#res_message, tonic::Status>> + Send + Sync + 'static;
-
-                    #method_doc
-                    async fn #name(&self, request: tonic::Request<tonic::Streaming<#req_message>>)
-                        -> Result<tonic::Response<Self::#stream>, tonic::Status>;
-                }
+        } else {
+            quote! {
+                async fn #name(&self, request: #request)
+                    -> Result<#response, tonic::Status>;
             }
         };
 
-        stream.extend(method);
+        stream.extend(quote! {
+            #method_doc
+            #method_attributes
+            #signature
+        });
     }
 
     stream
 }
 
-#[cfg(feature = "transport")]
 fn generate_transport(
     server_service: &syn::Ident,
     server_trait: &syn::Ident,
@@ -188,44 +435,50 @@ fn generate_transport(
     let service_name = syn::LitStr::new(service_name, proc_macro2::Span::call_site());
 
     quote! {
-        impl<T: #server_trait> tonic::transport::NamedService for #server_service<T> {
+        impl<T: #server_trait, C> tonic::transport::NamedService for #server_service<T, C> {
             const NAME: &'static str = #service_name;
         }
     }
 }
 
-#[cfg(not(feature = "transport"))]
-fn generate_transport(
-    _server_service: &syn::Ident,
-    _server_trait: &syn::Ident,
-    _service_name: &str,
+fn generate_methods(
+    service: &Service,
+    proto_path: &str,
+    codec: &Codec,
+    lazy_decode: &[String],
 ) -> TokenStream {
-    TokenStream::new()
-}
-
-fn generate_methods(service: &Service, proto_path: &str) -> TokenStream {
     let mut stream = TokenStream::new();
 
     for method in &service.methods {
-        let path = format!(
-            "/{}.{}/{}",
-            service.package, service.proto_name, method.proto_name
-        );
+        let path = method_path(service, method);
+        let lazy = lazy_decode.contains(&path);
         let method_path = Lit::Str(LitStr::new(&path, Span::call_site()));
         let ident = quote::format_ident!("{}", method.name);
         let server_trait = quote::format_ident!("{}", service.name);
 
         let method_stream = match (method.client_streaming, method.server_streaming) {
-            (false, false) => generate_unary(method, ident, proto_path, server_trait),
+            (false, false) => generate_unary(method, ident, proto_path, server_trait, codec, lazy),
+
+            (false, true) => generate_server_streaming(
+                method,
+                ident.clone(),
+                proto_path,
+                server_trait,
+                codec,
+                lazy,
+            ),
+            (true, false) => generate_client_streaming(
+                method,
+                ident.clone(),
+                proto_path,
+                server_trait,
+                codec,
+                lazy,
+            ),
 
-            (false, true) => {
-                generate_server_streaming(method, ident.clone(), proto_path, server_trait)
-            }
-            (true, false) => {
-                generate_client_streaming(method, ident.clone(), proto_path, server_trait)
+            (true, true) => {
+                generate_streaming(method, ident.clone(), proto_path, server_trait, codec, lazy)
             }
-
-            (true, true) => generate_streaming(method, ident.clone(), proto_path, server_trait),
         };
 
         let method = quote! {
@@ -244,10 +497,13 @@ fn generate_unary(
     method_ident: Ident,
     proto_path: &str,
     server_trait: Ident,
+    codec: &Codec,
+    lazy: bool,
 ) -> TokenStream {
     let service_ident = quote::format_ident!("{}Svc", method.proto_name);
 
-    let (request, response) = crate::replace_wellknown(proto_path, &method);
+    let (request, response) = message_types(codec, proto_path, &method, lazy);
+    let codec = server_codec(codec, proto_path, method, lazy);
 
     quote! {
         struct #service_ident<T: #server_trait >(pub Arc<T>);
@@ -266,17 +522,37 @@ fn generate_unary(
         }
 
         let inner = self.inner.clone();
+        let accept_compression_encodings = self.accept_compression_encodings;
+        let send_compression_encodings = self.send_compression_encodings;
+        let compression_level = self.compression_level;
+        let compression_threshold = self.compression_threshold;
+        let buffer_pool = self.buffer_pool.clone();
+        let yield_after_messages = self.yield_after_messages;
+        let message_checksums = self.message_checksums;
+        let enforce_deadlines = self.enforce_deadlines;
+        let max_metadata_size = self.max_metadata_size;
+        let max_decoding_message_size = self.max_decoding_message_size;
+        let max_encoding_message_size = self.max_encoding_message_size;
+        let codec = #codec;
         let fut = async move {
             let interceptor = inner.1.clone();
             let inner = inner.0;
             let method = #service_ident(inner);
-            let codec = tonic::codec::ProstCodec::default();
 
             let mut grpc = if let Some(interceptor) = interceptor {
                 tonic::server::Grpc::with_interceptor(codec, interceptor)
             } else {
                 tonic::server::Grpc::new(codec)
-            };
+            }
+            .apply_compression_config(accept_compression_encodings, send_compression_encodings)
+            .compression_level(compression_level)
+            .compression_threshold(compression_threshold)
+            .apply_buffer_pool(buffer_pool)
+            .apply_yield_after_messages(yield_after_messages)
+            .apply_message_checksums(message_checksums)
+            .apply_deadline_enforcement(enforce_deadlines)
+            .apply_max_metadata_size(max_metadata_size)
+            .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
 
             let res = grpc.unary(method, req).await;
             Ok(res)
@@ -291,10 +567,13 @@ fn generate_server_streaming(
     method_ident: Ident,
     proto_path: &str,
     server_trait: Ident,
+    codec: &Codec,
+    lazy: bool,
 ) -> TokenStream {
     let service_ident = quote::format_ident!("{}Svc", method.proto_name);
 
-    let (request, response) = crate::replace_wellknown(proto_path, &method);
+    let (request, response) = message_types(codec, proto_path, &method, lazy);
+    let codec = server_codec(codec, proto_path, method, lazy);
 
     let response_stream = quote::format_ident!("{}Stream", method.proto_name);
 
@@ -317,17 +596,37 @@ fn generate_server_streaming(
         }
 
         let inner = self.inner.clone();
+        let accept_compression_encodings = self.accept_compression_encodings;
+        let send_compression_encodings = self.send_compression_encodings;
+        let compression_level = self.compression_level;
+        let compression_threshold = self.compression_threshold;
+        let buffer_pool = self.buffer_pool.clone();
+        let yield_after_messages = self.yield_after_messages;
+        let message_checksums = self.message_checksums;
+        let enforce_deadlines = self.enforce_deadlines;
+        let max_metadata_size = self.max_metadata_size;
+        let max_decoding_message_size = self.max_decoding_message_size;
+        let max_encoding_message_size = self.max_encoding_message_size;
+        let codec = #codec;
         let fut = async move {
             let interceptor = inner.1;
             let inner = inner.0;
             let method = #service_ident(inner);
-            let codec = tonic::codec::ProstCodec::default();
 
             let mut grpc = if let Some(interceptor) = interceptor {
                 tonic::server::Grpc::with_interceptor(codec, interceptor)
             } else {
                 tonic::server::Grpc::new(codec)
-            };
+            }
+            .apply_compression_config(accept_compression_encodings, send_compression_encodings)
+            .compression_level(compression_level)
+            .compression_threshold(compression_threshold)
+            .apply_buffer_pool(buffer_pool)
+            .apply_yield_after_messages(yield_after_messages)
+            .apply_message_checksums(message_checksums)
+            .apply_deadline_enforcement(enforce_deadlines)
+            .apply_max_metadata_size(max_metadata_size)
+            .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
 
             let res = grpc.server_streaming(method, req).await;
             Ok(res)
@@ -342,10 +641,13 @@ fn generate_client_streaming(
     method_ident: Ident,
     proto_path: &str,
     server_trait: Ident,
+    codec: &Codec,
+    lazy: bool,
 ) -> TokenStream {
     let service_ident = quote::format_ident!("{}Svc", method.proto_name);
 
-    let (request, response) = crate::replace_wellknown(proto_path, &method);
+    let (request, response) = message_types(codec, proto_path, &method, lazy);
+    let codec = server_codec(codec, proto_path, method, lazy);
 
     quote! {
         struct #service_ident<T: #server_trait >(pub Arc<T>);
@@ -366,17 +668,37 @@ fn generate_client_streaming(
         }
 
         let inner = self.inner.clone();
+        let accept_compression_encodings = self.accept_compression_encodings;
+        let send_compression_encodings = self.send_compression_encodings;
+        let compression_level = self.compression_level;
+        let compression_threshold = self.compression_threshold;
+        let buffer_pool = self.buffer_pool.clone();
+        let yield_after_messages = self.yield_after_messages;
+        let message_checksums = self.message_checksums;
+        let enforce_deadlines = self.enforce_deadlines;
+        let max_metadata_size = self.max_metadata_size;
+        let max_decoding_message_size = self.max_decoding_message_size;
+        let max_encoding_message_size = self.max_encoding_message_size;
+        let codec = #codec;
         let fut = async move {
             let interceptor = inner.1;
             let inner = inner.0;
             let method = #service_ident(inner);
-            let codec = tonic::codec::ProstCodec::default();
 
             let mut grpc = if let Some(interceptor) = interceptor {
                 tonic::server::Grpc::with_interceptor(codec, interceptor)
             } else {
                 tonic::server::Grpc::new(codec)
-            };
+            }
+            .apply_compression_config(accept_compression_encodings, send_compression_encodings)
+            .compression_level(compression_level)
+            .compression_threshold(compression_threshold)
+            .apply_buffer_pool(buffer_pool)
+            .apply_yield_after_messages(yield_after_messages)
+            .apply_message_checksums(message_checksums)
+            .apply_deadline_enforcement(enforce_deadlines)
+            .apply_max_metadata_size(max_metadata_size)
+            .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
 
             let res = grpc.client_streaming(method, req).await;
             Ok(res)
@@ -391,10 +713,13 @@ fn generate_streaming(
     method_ident: Ident,
     proto_path: &str,
     server_trait: Ident,
+    codec: &Codec,
+    lazy: bool,
 ) -> TokenStream {
     let service_ident = quote::format_ident!("{}Svc", method.proto_name);
 
-    let (request, response) = crate::replace_wellknown(proto_path, &method);
+    let (request, response) = message_types(codec, proto_path, &method, lazy);
+    let codec = server_codec(codec, proto_path, method, lazy);
 
     let response_stream = quote::format_ident!("{}Stream", method.proto_name);
 
@@ -417,17 +742,37 @@ fn generate_streaming(
         }
 
         let inner = self.inner.clone();
+        let accept_compression_encodings = self.accept_compression_encodings;
+        let send_compression_encodings = self.send_compression_encodings;
+        let compression_level = self.compression_level;
+        let compression_threshold = self.compression_threshold;
+        let buffer_pool = self.buffer_pool.clone();
+        let yield_after_messages = self.yield_after_messages;
+        let message_checksums = self.message_checksums;
+        let enforce_deadlines = self.enforce_deadlines;
+        let max_metadata_size = self.max_metadata_size;
+        let max_decoding_message_size = self.max_decoding_message_size;
+        let max_encoding_message_size = self.max_encoding_message_size;
+        let codec = #codec;
         let fut = async move {
             let interceptor = inner.1;
             let inner = inner.0;
             let method = #service_ident(inner);
-            let codec = tonic::codec::ProstCodec::default();
 
             let mut grpc = if let Some(interceptor) = interceptor {
                 tonic::server::Grpc::with_interceptor(codec, interceptor)
             } else {
                 tonic::server::Grpc::new(codec)
-            };
+            }
+            .apply_compression_config(accept_compression_encodings, send_compression_encodings)
+            .compression_level(compression_level)
+            .compression_threshold(compression_threshold)
+            .apply_buffer_pool(buffer_pool)
+            .apply_yield_after_messages(yield_after_messages)
+            .apply_message_checksums(message_checksums)
+            .apply_deadline_enforcement(enforce_deadlines)
+            .apply_max_metadata_size(max_metadata_size)
+            .apply_max_message_size_config(max_decoding_message_size, max_encoding_message_size);
 
             let res = grpc.streaming(method, req).await;
             Ok(res)
@@ -436,3 +781,36 @@ fn generate_streaming(
         Box::pin(fut)
     }
 }
+
+pub(crate) fn method_path(service: &Service, method: &Method) -> String {
+    format!(
+        "/{}.{}/{}",
+        service.package, service.proto_name, method.proto_name
+    )
+}
+
+// Methods opted into lazy decoding receive their requests as
+// `tonic::codec::Lazy` values, decoded by the configured codec on demand.
+fn message_types(
+    codec: &Codec,
+    proto_path: &str,
+    method: &Method,
+    lazy: bool,
+) -> (TokenStream, TokenStream) {
+    let (request, response) = codec.types(proto_path, method);
+    if lazy {
+        (quote!(tonic::codec::Lazy<#request>), response)
+    } else {
+        (request, response)
+    }
+}
+
+fn server_codec(codec: &Codec, proto_path: &str, method: &Method, lazy: bool) -> TokenStream {
+    let (request, response) = codec.types(proto_path, method);
+    let codec = quote!(tonic::codec::MakeCodec::<#response, #request>::make_codec(&self.codec));
+    if lazy {
+        quote!(tonic::codec::LazyCodec::new(#codec))
+    } else {
+        codec
+    }
+}
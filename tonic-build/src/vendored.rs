@@ -0,0 +1,505 @@
+//! Imports resolved from vendored `FileDescriptorSet`s, like the ones
+//! written by `buf build -o` for the modules of a registry.
+//!
+//! prost-build only compiles `.proto` files, so the files of the sets missing
+//! from the include directories are written back as `.proto` files, to a
+//! temporary directory added to the includes.
+
+use prost::Message;
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+    FileDescriptorSet, ServiceDescriptorProto,
+};
+use std::{
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The maximum field number, the end of `extensions 100 to max;`.
+const MAX_FIELD_NUMBER: i32 = 536_870_911;
+
+/// The files of vendored descriptor sets written back as `.proto` files,
+/// removed when dropped.
+pub(crate) struct VendoredImports {
+    dir: Option<PathBuf>,
+}
+
+impl VendoredImports {
+    /// Write the files of the encoded `FileDescriptorSet`s at `sets` that
+    /// aren't found in `includes` as `.proto` files.
+    pub(crate) fn write<P: AsRef<Path>>(sets: &[PathBuf], includes: &[P]) -> io::Result<Self> {
+        static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+        let dir = std::env::temp_dir().join(format!(
+            "tonic-build-vendored-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut vendored = VendoredImports { dir: None };
+
+        for set in sets {
+            let buf = fs::read(set).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("failed to read the descriptor set {}: {}", set.display(), e),
+                )
+            })?;
+
+            for file in FileDescriptorSet::decode(&*buf)?.file {
+                let on_disk = includes
+                    .iter()
+                    .map(AsRef::as_ref)
+                    .chain(Some(prost_build::protoc_include()))
+                    .any(|include| include.join(file.name()).is_file());
+                if on_disk {
+                    continue;
+                }
+
+                let path = dir.join(file.name());
+                fs::create_dir_all(path.parent().unwrap())?;
+                fs::write(&path, source(&file)?)?;
+                vendored.dir = Some(dir.clone());
+            }
+        }
+
+        Ok(vendored)
+    }
+
+    /// The directory to add to the includes, if any file was written.
+    pub(crate) fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+}
+
+impl Drop for VendoredImports {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// The `.proto` source of `file`, declaring the same types, services and
+/// standard options, without the comments and custom options.
+fn source(file: &FileDescriptorProto) -> io::Result<String> {
+    let mut printer = Printer {
+        out: String::new(),
+        depth: 0,
+        proto3: file.syntax() == "proto3",
+    };
+    printer.file(file)?;
+    Ok(printer.out)
+}
+
+struct Printer {
+    out: String,
+    depth: usize,
+    proto3: bool,
+}
+
+impl Printer {
+    fn line(&mut self, line: impl AsRef<str>) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(line.as_ref());
+        self.out.push('\n');
+    }
+
+    fn file(&mut self, file: &FileDescriptorProto) -> io::Result<()> {
+        let syntax = if self.proto3 { "proto3" } else { "proto2" };
+        self.line(format!("syntax = \"{}\";", syntax));
+        if !file.package().is_empty() {
+            self.line(format!("package {};", file.package()));
+        }
+        for (i, dependency) in file.dependency.iter().enumerate() {
+            if file.public_dependency.contains(&(i as i32)) {
+                self.line(format!("import public \"{}\";", dependency));
+            } else {
+                self.line(format!("import \"{}\";", dependency));
+            }
+        }
+        if file.options.as_ref().is_some_and(|o| o.deprecated()) {
+            self.line("option deprecated = true;");
+        }
+
+        for message in &file.message_type {
+            self.message(message)?;
+        }
+        for enumeration in &file.enum_type {
+            self.enumeration(enumeration);
+        }
+        self.extensions(&file.extension)?;
+        for service in &file.service {
+            self.service(service);
+        }
+        Ok(())
+    }
+
+    fn message(&mut self, message: &DescriptorProto) -> io::Result<()> {
+        self.line(format!("message {} {{", message.name()));
+        self.depth += 1;
+
+        if message.options.as_ref().is_some_and(|o| o.deprecated()) {
+            self.line("option deprecated = true;");
+        }
+
+        let mut oneofs_done = Vec::new();
+        for field in &message.field {
+            match field.oneof_index {
+                Some(index) if self.is_proto3_optional(message, field) => {
+                    oneofs_done.push(index);
+                    let decl = self.field_decl(message, field, Some("optional"))?;
+                    self.line(decl);
+                }
+                Some(index) => {
+                    if oneofs_done.contains(&index) {
+                        continue;
+                    }
+                    oneofs_done.push(index);
+                    self.line(format!(
+                        "oneof {} {{",
+                        message.oneof_decl[index as usize].name()
+                    ));
+                    self.depth += 1;
+                    for member in &message.field {
+                        if member.oneof_index == Some(index) {
+                            let decl = self.field_decl(message, member, Some(""))?;
+                            self.line(decl);
+                        }
+                    }
+                    self.depth -= 1;
+                    self.line("}");
+                }
+                None => {
+                    let decl = self.field_decl(message, field, None)?;
+                    self.line(decl);
+                }
+            }
+        }
+
+        for range in &message.extension_range {
+            let end = match range.end() - 1 {
+                MAX_FIELD_NUMBER => "max".to_string(),
+                end => end.to_string(),
+            };
+            self.line(format!("extensions {} to {};", range.start(), end));
+        }
+        for nested in &message.nested_type {
+            if !nested.options.as_ref().is_some_and(|o| o.map_entry()) {
+                self.message(nested)?;
+            }
+        }
+        for enumeration in &message.enum_type {
+            self.enumeration(enumeration);
+        }
+        self.extensions(&message.extension)?;
+
+        self.depth -= 1;
+        self.line("}");
+        Ok(())
+    }
+
+    /// Whether `field` is a proto3 `optional` field, in a synthetic oneof
+    /// named after it.
+    fn is_proto3_optional(&self, message: &DescriptorProto, field: &FieldDescriptorProto) -> bool {
+        let index = match field.oneof_index {
+            Some(index) if self.proto3 => index,
+            _ => return false,
+        };
+        message.oneof_decl[index as usize].name() == format!("_{}", field.name())
+            && message
+                .field
+                .iter()
+                .filter(|f| f.oneof_index == Some(index))
+                .count()
+                == 1
+    }
+
+    /// The declaration of `field`, with `label` or the one of the
+    /// descriptor.
+    fn field_decl(
+        &self,
+        message: &DescriptorProto,
+        field: &FieldDescriptorProto,
+        label: Option<&str>,
+    ) -> io::Result<String> {
+        let map_entry = message.nested_type.iter().find(|nested| {
+            nested.options.as_ref().is_some_and(|o| o.map_entry())
+                && field.type_name().ends_with(&format!(".{}", nested.name()))
+        });
+
+        let (label, ty) = match map_entry {
+            Some(entry) if field.label() == Label::Repeated => {
+                let key = field_type(&entry.field[0])?;
+                let value = field_type(&entry.field[1])?;
+                (String::new(), format!("map<{}, {}>", key, value))
+            }
+            _ => {
+                let label = match label {
+                    Some(label) => label,
+                    None => match field.label() {
+                        Label::Repeated => "repeated",
+                        Label::Required => "required",
+                        Label::Optional if self.proto3 => "",
+                        Label::Optional => "optional",
+                    },
+                };
+                (label.to_string(), field_type(field)?)
+            }
+        };
+
+        let mut decl = String::new();
+        if !label.is_empty() {
+            write!(decl, "{} ", label).unwrap();
+        }
+        write!(decl, "{} {} = {}", ty, field.name(), field.number()).unwrap();
+
+        let mut options = Vec::new();
+        if let Some(default) = &field.default_value {
+            let default = match field.r#type() {
+                Type::String => {
+                    format!("\"{}\"", default.replace('\\', "\\\\").replace('"', "\\\""))
+                }
+                // Already escaped by `protoc`.
+                Type::Bytes => format!("\"{}\"", default),
+                _ => default.clone(),
+            };
+            options.push(format!("default = {}", default));
+        }
+        if let Some(field_options) = &field.options {
+            if let Some(packed) = field_options.packed {
+                options.push(format!("packed = {}", packed));
+            }
+            if field_options.deprecated() {
+                options.push("deprecated = true".to_string());
+            }
+        }
+        if !options.is_empty() {
+            write!(decl, " [{}]", options.join(", ")).unwrap();
+        }
+        decl.push(';');
+        Ok(decl)
+    }
+
+    fn enumeration(&mut self, enumeration: &EnumDescriptorProto) {
+        self.line(format!("enum {} {{", enumeration.name()));
+        self.depth += 1;
+
+        if let Some(options) = &enumeration.options {
+            if options.allow_alias() {
+                self.line("option allow_alias = true;");
+            }
+            if options.deprecated() {
+                self.line("option deprecated = true;");
+            }
+        }
+        for value in &enumeration.value {
+            let deprecated = if value.options.as_ref().is_some_and(|o| o.deprecated()) {
+                " [deprecated = true]"
+            } else {
+                ""
+            };
+            self.line(format!(
+                "{} = {}{};",
+                value.name(),
+                value.number(),
+                deprecated
+            ));
+        }
+
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    /// The `extend` blocks of the `extensions`, grouped by extended message.
+    fn extensions(&mut self, extensions: &[FieldDescriptorProto]) -> io::Result<()> {
+        let mut extendees = Vec::<&str>::new();
+        for extension in extensions {
+            if !extendees.contains(&extension.extendee()) {
+                extendees.push(extension.extendee());
+            }
+        }
+
+        for extendee in extendees {
+            self.line(format!("extend {} {{", extendee));
+            self.depth += 1;
+            for extension in extensions.iter().filter(|e| e.extendee() == extendee) {
+                let decl = self.field_decl(&DescriptorProto::default(), extension, None)?;
+                self.line(decl);
+            }
+            self.depth -= 1;
+            self.line("}");
+        }
+        Ok(())
+    }
+
+    fn service(&mut self, service: &ServiceDescriptorProto) {
+        self.line(format!("service {} {{", service.name()));
+        self.depth += 1;
+
+        if service.options.as_ref().is_some_and(|o| o.deprecated()) {
+            self.line("option deprecated = true;");
+        }
+        for method in &service.method {
+            let stream = |streaming| if streaming { "stream " } else { "" };
+            let signature = format!(
+                "rpc {}({}{}) returns ({}{})",
+                method.name(),
+                stream(method.client_streaming()),
+                method.input_type(),
+                stream(method.server_streaming()),
+                method.output_type(),
+            );
+
+            let mut options = Vec::new();
+            if let Some(method_options) = &method.options {
+                if method_options.deprecated() {
+                    options.push("option deprecated = true;".to_string());
+                }
+                if let Some(level) = method_options.idempotency_level {
+                    let level = match level {
+                        1 => "NO_SIDE_EFFECTS",
+                        2 => "IDEMPOTENT",
+                        _ => "IDEMPOTENCY_UNKNOWN",
+                    };
+                    options.push(format!("option idempotency_level = {};", level));
+                }
+            }
+
+            if options.is_empty() {
+                self.line(format!("{};", signature));
+            } else {
+                self.line(format!("{} {{", signature));
+                self.depth += 1;
+                for option in options {
+                    self.line(option);
+                }
+                self.depth -= 1;
+                self.line("}");
+            }
+        }
+
+        self.depth -= 1;
+        self.line("}");
+    }
+}
+
+/// The type of `field`, as declared in a `.proto` file.
+fn field_type(field: &FieldDescriptorProto) -> io::Result<String> {
+    let ty = match field.r#type() {
+        Type::Double => "double",
+        Type::Float => "float",
+        Type::Int64 => "int64",
+        Type::Uint64 => "uint64",
+        Type::Int32 => "int32",
+        Type::Fixed64 => "fixed64",
+        Type::Fixed32 => "fixed32",
+        Type::Bool => "bool",
+        Type::String => "string",
+        Type::Bytes => "bytes",
+        Type::Uint32 => "uint32",
+        Type::Sfixed32 => "sfixed32",
+        Type::Sfixed64 => "sfixed64",
+        Type::Sint32 => "sint32",
+        Type::Sint64 => "sint64",
+        Type::Message | Type::Enum => return Ok(field.type_name().to_string()),
+        Type::Group => {
+            return Err(io::Error::other(format!(
+                "the group `{}` of a vendored descriptor set can't be imported",
+                field.name()
+            )))
+        }
+    };
+    Ok(ty.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    /// The descriptor of the `.proto` file `name` holding `source`, as
+    /// compiled by `protoc` in `dir`.
+    fn compile(dir: &Path, name: &str, source: &str) -> FileDescriptorProto {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(name), source).unwrap();
+
+        let out = dir.join("out.desc");
+        let status = Command::new(prost_build::protoc())
+            .arg("-I")
+            .arg(dir)
+            .arg("-I")
+            .arg(prost_build::protoc_include())
+            .arg("-o")
+            .arg(&out)
+            .arg(dir.join(name))
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let set = FileDescriptorSet::decode(&*fs::read(out).unwrap()).unwrap();
+        set.file.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn prints_equivalent_sources() {
+        let original = r#"
+            syntax = "proto2";
+            package shop.v1;
+            import "google/protobuf/descriptor.proto";
+
+            message Order {
+              option deprecated = true;
+              required uint64 id = 1;
+              optional string note = 2 [default = "say \"hi\""];
+              optional bytes raw = 8 [default = "\001\n"];
+              repeated int32 counts = 3 [packed = true];
+              map<string, Item> items = 4;
+              oneof payment {
+                string card = 5;
+                bytes token = 6 [deprecated = true];
+              }
+              optional Status status = 7 [default = OPEN];
+              enum Status {
+                option allow_alias = true;
+                OPEN = 0;
+                CLOSED = 1;
+                DONE = 1;
+              }
+              extensions 100 to max;
+            }
+
+            message Item {
+              optional double price = 1;
+            }
+
+            extend Order {
+              optional bool gift = 100;
+            }
+
+            extend google.protobuf.MethodOptions {
+              optional string owner = 50000;
+            }
+
+            service Orders {
+              rpc Get(Order) returns (Order) {
+                option idempotency_level = NO_SIDE_EFFECTS;
+              }
+              rpc Watch(stream Order) returns (stream Item);
+            }
+        "#;
+
+        let dir =
+            std::env::temp_dir().join(format!("tonic-build-vendored-test-{}", std::process::id()));
+        let file = compile(&dir.join("original"), "shop.proto", original);
+        let printed = source(&file).unwrap();
+        let reprinted = compile(&dir.join("printed"), "shop.proto", &printed);
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(file, reprinted, "{}", printed);
+    }
+}
@@ -23,7 +23,7 @@ macro_rules! bench {
             b.iter(|| {
                 rt.block_on(async {
                     let decoder = MockDecoder::new($message_size);
-                    let mut stream = Streaming::new_request(decoder, body.clone());
+                    let mut stream = Streaming::new_request(decoder, body.clone(), None);
 
                     let mut count = 0;
                     while let Some(msg) = stream.message().await.unwrap() {
@@ -178,7 +178,7 @@ where
             Pin::new_unchecked(&mut me.0).poll_trailers(cx)
         };
 
-        let v = futures_util::ready!(v).map_err(|e| Status::from_error(&*e.into()));
+        let v = futures_util::ready!(v).map_err(|e| Status::from_error(e.into()));
         Poll::Ready(v)
     }
 }
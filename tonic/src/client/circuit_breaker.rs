@@ -0,0 +1,629 @@
+use crate::{Code, Status};
+use futures_util::ready;
+use http::HeaderMap;
+use http_body::Body as HttpBody;
+use pin_project::pin_project;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+const DEFAULT_CONSECUTIVE_FAILURES: u32 = 5;
+const DEFAULT_OPEN_DURATION: Duration = Duration::from_secs(30);
+const DEFAULT_FAILURE_CODES: &[Code] = &[
+    Code::Unknown,
+    Code::DeadlineExceeded,
+    Code::Internal,
+    Code::Unavailable,
+    Code::DataLoss,
+];
+
+/// Wraps a channel, or any other service calls are sent to, with a
+/// [`CircuitBreaker`], so the calls fail fast while the server is failing.
+///
+/// The circuit is closed, letting calls through, until it trips: after
+/// `consecutive_failures` failed calls in a row, 5 by default, or when the
+/// [`failure_rate`] is exceeded. It then opens, failing the calls right away
+/// with `UNAVAILABLE`, for the [`open_duration`], after which it is half
+/// open: a few calls are let through to probe the server, closing the
+/// circuit if they all succeed, opening it again otherwise.
+///
+/// The calls fail when they can't be sent, or with one of the
+/// [`failure_codes`], by default `UNKNOWN`, `DEADLINE_EXCEEDED`,
+/// `INTERNAL`, `UNAVAILABLE` and `DATA_LOSS`, telling the server apart from
+/// the errors of the calls themselves.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use tonic::client::CircuitBreakerLayer;
+///
+/// let breaker = CircuitBreakerLayer::new()
+///     .consecutive_failures(3)
+///     .failure_rate(0.5, 20, Duration::from_secs(10))
+///     .open_duration(Duration::from_secs(5))
+///     .per_method(true);
+///
+/// // let client = GreeterClient::new(tower::layer::Layer::layer(&breaker, channel));
+/// ```
+///
+/// [`CircuitBreaker`]: struct.CircuitBreaker.html
+/// [`failure_rate`]: #method.failure_rate
+/// [`open_duration`]: #method.open_duration
+/// [`failure_codes`]: #method.failure_codes
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerLayer {
+    policy: Policy,
+}
+
+#[derive(Debug, Clone)]
+struct Policy {
+    consecutive_failures: Option<u32>,
+    failure_rate: Option<FailureRate>,
+    open_duration: Duration,
+    probes: u32,
+    failure_codes: Vec<Code>,
+    per_method: bool,
+    methods: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FailureRate {
+    rate: f64,
+    min_calls: usize,
+    window: Duration,
+}
+
+impl CircuitBreakerLayer {
+    /// A circuit tripped by 5 consecutive failures, open for 30 seconds,
+    /// shared by all the methods.
+    pub fn new() -> Self {
+        CircuitBreakerLayer {
+            policy: Policy {
+                consecutive_failures: Some(DEFAULT_CONSECUTIVE_FAILURES),
+                failure_rate: None,
+                open_duration: DEFAULT_OPEN_DURATION,
+                probes: 1,
+                failure_codes: DEFAULT_FAILURE_CODES.to_vec(),
+                per_method: false,
+                methods: Vec::new(),
+            },
+        }
+    }
+
+    /// Trip the circuit after `failures` failed calls in a row, or never on
+    /// consecutive failures if `None`.
+    pub fn consecutive_failures(mut self, failures: impl Into<Option<u32>>) -> Self {
+        self.policy.consecutive_failures = failures.into().map(|failures| failures.max(1));
+        self
+    }
+
+    /// Trip the circuit when more than `rate`, between 0 and 1, of the calls
+    /// of the last `window` failed, once there were at least `min_calls`.
+    pub fn failure_rate(mut self, rate: f64, min_calls: usize, window: Duration) -> Self {
+        self.policy.failure_rate = Some(FailureRate {
+            rate,
+            min_calls: min_calls.max(1),
+            window,
+        });
+        self
+    }
+
+    /// Keep the circuit open for `duration` before probing the server, by
+    /// default 30 seconds.
+    pub fn open_duration(mut self, duration: Duration) -> Self {
+        self.policy.open_duration = duration;
+        self
+    }
+
+    /// Let `probes` calls through while half open, closing the circuit once
+    /// they all succeed, by default 1.
+    pub fn half_open_probes(mut self, probes: u32) -> Self {
+        self.policy.probes = probes.max(1);
+        self
+    }
+
+    /// The codes of the calls counted as failures.
+    pub fn failure_codes(mut self, codes: impl IntoIterator<Item = Code>) -> Self {
+        self.policy.failure_codes = codes.into_iter().collect();
+        self
+    }
+
+    /// Give each method its own circuit, instead of one shared by all, so a
+    /// failing method doesn't fail the others fast.
+    pub fn per_method(mut self, enabled: bool) -> Self {
+        self.policy.per_method = enabled;
+        self
+    }
+
+    /// Only break the calls of the method at `path`, like
+    /// `/helloworld.Greeter/SayHello`, and of the other methods added, the
+    /// calls of the others going through as they are.
+    pub fn method(mut self, path: impl Into<String>) -> Self {
+        self.policy.methods.push(path.into());
+        self
+    }
+}
+
+impl Default for CircuitBreakerLayer {
+    fn default() -> Self {
+        CircuitBreakerLayer::new()
+    }
+}
+
+impl<S> Layer<S> for CircuitBreakerLayer {
+    type Service = CircuitBreaker<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CircuitBreaker {
+            inner,
+            breaker: Arc::new(Breaker {
+                policy: self.policy.clone(),
+                circuits: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+}
+
+/// A service failing the calls fast with `UNAVAILABLE` while its circuit is
+/// open, created by a [`CircuitBreakerLayer`].
+///
+/// Its clones share the same circuits.
+///
+/// [`CircuitBreakerLayer`]: struct.CircuitBreakerLayer.html
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker<S> {
+    inner: S,
+    breaker: Arc<Breaker>,
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for CircuitBreaker<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Error: Into<crate::Error>,
+{
+    type Response = http::Response<CircuitBreakerBody<ResBody>>;
+    type Error = crate::Error;
+    type Future = CircuitBreakerFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let path = request.uri().path();
+        if !self.breaker.policy.methods.is_empty()
+            && !self.breaker.policy.methods.iter().any(|m| m == path)
+        {
+            return CircuitBreakerFuture {
+                inner: Some(self.inner.call(request)),
+                permit: None,
+                rejected: None,
+            };
+        }
+
+        let key = if self.breaker.policy.per_method {
+            path.to_string()
+        } else {
+            String::new()
+        };
+        match self.breaker.acquire(key, Instant::now()) {
+            Ok(permit) => CircuitBreakerFuture {
+                inner: Some(self.inner.call(request)),
+                permit: Some(permit),
+                rejected: None,
+            },
+            Err(retry_after) => CircuitBreakerFuture {
+                inner: None,
+                permit: None,
+                rejected: Some(open(retry_after)),
+            },
+        }
+    }
+}
+
+/// The status of the calls failed fast.
+fn open(retry_after: Duration) -> Status {
+    let message = "circuit breaker open";
+    #[cfg(feature = "prost")]
+    return Status::with_retry_delay(Code::Unavailable, message, retry_after);
+    #[cfg(not(feature = "prost"))]
+    {
+        let _ = retry_after;
+        Status::new(Code::Unavailable, message)
+    }
+}
+
+/// The response future of a [`CircuitBreaker`].
+///
+/// [`CircuitBreaker`]: struct.CircuitBreaker.html
+#[pin_project]
+pub struct CircuitBreakerFuture<F> {
+    #[pin]
+    inner: Option<F>,
+    permit: Option<Permit>,
+    rejected: Option<Status>,
+}
+
+impl<F, B, E> Future for CircuitBreakerFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+    E: Into<crate::Error>,
+{
+    type Output = Result<http::Response<CircuitBreakerBody<B>>, crate::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Some(status) = this.rejected.take() {
+            return Poll::Ready(Err(Box::new(status)));
+        }
+
+        let inner = this
+            .inner
+            .as_pin_mut()
+            .expect("polled after the circuit breaker rejected the call");
+        let response = match ready!(inner.poll(cx)) {
+            Ok(response) => response,
+            Err(err) => {
+                if let Some(permit) = this.permit.take() {
+                    permit.record(None);
+                }
+                return Poll::Ready(Err(err.into()));
+            }
+        };
+
+        // The status of trailers-only responses is known right away, the
+        // one of the others once their trailers are received.
+        let mut permit = this.permit.take();
+        if let Some(status) = Status::from_header_map(response.headers()) {
+            if let Some(permit) = permit.take() {
+                permit.record(Some(status.code()));
+            }
+        }
+        Poll::Ready(Ok(
+            response.map(|inner| CircuitBreakerBody { inner, permit })
+        ))
+    }
+}
+
+impl<F> fmt::Debug for CircuitBreakerFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreakerFuture").finish()
+    }
+}
+
+/// The response body of a [`CircuitBreaker`], recording the status of the
+/// call from its trailers.
+///
+/// [`CircuitBreaker`]: struct.CircuitBreaker.html
+#[pin_project]
+pub struct CircuitBreakerBody<B> {
+    #[pin]
+    inner: B,
+    permit: Option<Permit>,
+}
+
+impl<B> HttpBody for CircuitBreakerBody<B>
+where
+    B: HttpBody,
+    B::Error: Into<crate::Error>,
+{
+    type Data = B::Data;
+    type Error = crate::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        match ready!(this.inner.poll_data(cx)) {
+            Some(Err(err)) => {
+                if let Some(permit) = this.permit.take() {
+                    permit.record(None);
+                }
+                Poll::Ready(Some(Err(err.into())))
+            }
+            data => Poll::Ready(data.map(|data| data.map_err(Into::into))),
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx));
+        if let Some(permit) = this.permit.take() {
+            let code = match &trailers {
+                Ok(trailers) => Some(
+                    trailers
+                        .as_ref()
+                        .and_then(Status::from_header_map)
+                        .map_or(Code::Unknown, |status| status.code()),
+                ),
+                Err(_) => None,
+            };
+            permit.record(code);
+        }
+        Poll::Ready(trailers.map_err(Into::into))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+}
+
+impl<B> fmt::Debug for CircuitBreakerBody<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreakerBody").finish()
+    }
+}
+
+/// The policy of the circuits, along with their states.
+#[derive(Debug)]
+struct Breaker {
+    policy: Policy,
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    state: State,
+    consecutive_failures: u32,
+    /// The time the recent calls completed at, and whether they failed.
+    outcomes: VecDeque<(Instant, bool)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum State {
+    #[default]
+    Closed,
+    Open {
+        until: Instant,
+    },
+    HalfOpen {
+        probing: u32,
+        succeeded: u32,
+    },
+}
+
+/// Lets a call through a circuit, recording its outcome.
+struct Permit {
+    breaker: Arc<Breaker>,
+    key: String,
+    probe: bool,
+    recorded: bool,
+}
+
+impl Breaker {
+    /// Let a call through the circuit `key`, or the time until it is half
+    /// open.
+    fn acquire(self: &Arc<Self>, key: String, now: Instant) -> Result<Permit, Duration> {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(key.clone()).or_default();
+
+        if let State::Open { until } = circuit.state {
+            if until > now {
+                return Err(until - now);
+            }
+            circuit.state = State::HalfOpen {
+                probing: 0,
+                succeeded: 0,
+            };
+        }
+        let probe = match &mut circuit.state {
+            State::HalfOpen { probing, succeeded } => {
+                if *probing + *succeeded >= self.policy.probes {
+                    return Err(Duration::from_secs(0));
+                }
+                *probing += 1;
+                true
+            }
+            _ => false,
+        };
+
+        Ok(Permit {
+            breaker: self.clone(),
+            key,
+            probe,
+            recorded: false,
+        })
+    }
+
+    /// Record the outcome of a call through the circuit `key`: its code, or
+    /// `None` if it couldn't be sent or received.
+    fn record(&self, key: &str, probe: bool, code: Option<Code>, now: Instant) {
+        let failed = code.is_none_or(|code| self.policy.failure_codes.contains(&code));
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = match circuits.get_mut(key) {
+            Some(circuit) => circuit,
+            None => return,
+        };
+
+        match &mut circuit.state {
+            // The calls sent before the circuit opened.
+            State::Open { .. } => {}
+            State::HalfOpen { .. } if !probe => {}
+            State::HalfOpen { probing, succeeded } => {
+                *probing -= 1;
+                if failed {
+                    circuit.state = State::Open {
+                        until: now + self.policy.open_duration,
+                    };
+                } else {
+                    *succeeded += 1;
+                    if *succeeded >= self.policy.probes {
+                        *circuit = Circuit::default();
+                    }
+                }
+            }
+            State::Closed => {
+                if failed {
+                    circuit.consecutive_failures += 1;
+                } else {
+                    circuit.consecutive_failures = 0;
+                }
+                if self.trips(circuit, failed, now) {
+                    *circuit = Circuit {
+                        state: State::Open {
+                            until: now + self.policy.open_duration,
+                        },
+                        ..Circuit::default()
+                    };
+                }
+            }
+        }
+    }
+
+    /// Whether the closed `circuit` trips, after a call that `failed`.
+    fn trips(&self, circuit: &mut Circuit, failed: bool, now: Instant) -> bool {
+        if let Some(max) = self.policy.consecutive_failures {
+            if circuit.consecutive_failures >= max {
+                return true;
+            }
+        }
+
+        let rate = match self.policy.failure_rate {
+            Some(rate) => rate,
+            None => return false,
+        };
+        circuit.outcomes.push_back((now, failed));
+        while let Some((at, _)) = circuit.outcomes.front() {
+            if now.duration_since(*at) <= rate.window {
+                break;
+            }
+            circuit.outcomes.pop_front();
+        }
+        let calls = circuit.outcomes.len();
+        let failures = circuit
+            .outcomes
+            .iter()
+            .filter(|(_, failed)| *failed)
+            .count();
+        calls >= rate.min_calls && failures as f64 > rate.rate * calls as f64
+    }
+
+    /// Release the probe of a call whose outcome is unknown, like a call
+    /// cancelled before receiving its status.
+    fn release(&self, key: &str) {
+        let mut circuits = self.circuits.lock().unwrap();
+        if let Some(Circuit {
+            state: State::HalfOpen { probing, .. },
+            ..
+        }) = circuits.get_mut(key)
+        {
+            *probing -= 1;
+        }
+    }
+}
+
+impl Permit {
+    fn record(mut self, code: Option<Code>) {
+        self.recorded = true;
+        self.breaker
+            .record(&self.key, self.probe, code, Instant::now());
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if !self.recorded && self.probe {
+            self.breaker.release(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(layer: CircuitBreakerLayer) -> Arc<Breaker> {
+        Arc::new(Breaker {
+            policy: layer.policy,
+            circuits: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn call(breaker: &Arc<Breaker>, code: Option<Code>, now: Instant) -> Result<(), Duration> {
+        let mut permit = breaker.acquire(String::new(), now)?;
+        permit.recorded = true;
+        breaker.record("", permit.probe, code, now);
+        Ok(())
+    }
+
+    #[test]
+    fn trips_on_consecutive_failures() {
+        let breaker = breaker(
+            CircuitBreakerLayer::new()
+                .consecutive_failures(2)
+                .open_duration(Duration::from_secs(10)),
+        );
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        call(&breaker, Some(Code::Unavailable), at(0)).unwrap();
+        // Successes and the errors of the calls reset the count.
+        call(&breaker, Some(Code::NotFound), at(0)).unwrap();
+        call(&breaker, None, at(0)).unwrap();
+        call(&breaker, Some(Code::Internal), at(1)).unwrap();
+        assert_eq!(
+            call(&breaker, Some(Code::Ok), at(5)),
+            Err(Duration::from_secs(6))
+        );
+
+        // A failed probe opens the circuit again, a successful one closes it.
+        call(&breaker, None, at(11)).unwrap();
+        assert!(call(&breaker, Some(Code::Ok), at(12)).is_err());
+        call(&breaker, Some(Code::Ok), at(21)).unwrap();
+        call(&breaker, Some(Code::Unavailable), at(21)).unwrap();
+        call(&breaker, Some(Code::Ok), at(21)).unwrap();
+    }
+
+    #[test]
+    fn trips_on_failure_rate() {
+        let breaker = breaker(
+            CircuitBreakerLayer::new()
+                .consecutive_failures(None)
+                .failure_rate(0.5, 4, Duration::from_secs(10)),
+        );
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        call(&breaker, None, at(0)).unwrap();
+        call(&breaker, None, at(0)).unwrap();
+        call(&breaker, None, at(0)).unwrap();
+        // Older calls are out of the window.
+        call(&breaker, Some(Code::Ok), at(20)).unwrap();
+        call(&breaker, None, at(20)).unwrap();
+        call(&breaker, Some(Code::Ok), at(21)).unwrap();
+        call(&breaker, None, at(21)).unwrap();
+        call(&breaker, None, at(22)).unwrap();
+        assert!(call(&breaker, Some(Code::Ok), at(22)).is_err());
+    }
+
+    #[test]
+    fn limits_the_probes_of_half_open_circuits() {
+        let breaker = breaker(
+            CircuitBreakerLayer::new()
+                .consecutive_failures(1)
+                .open_duration(Duration::from_secs(1)),
+        );
+        let now = Instant::now();
+        call(&breaker, None, now).unwrap();
+
+        let later = now + Duration::from_secs(2);
+        let probe = breaker.acquire(String::new(), later).unwrap();
+        assert!(breaker.acquire(String::new(), later).is_err());
+        // A cancelled probe lets another one through.
+        drop(probe);
+        let probe = breaker.acquire(String::new(), later).unwrap();
+        probe.record(Some(Code::Ok));
+        assert!(!breaker.acquire(String::new(), later).unwrap().probe);
+    }
+}
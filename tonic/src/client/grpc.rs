@@ -1,18 +1,33 @@
 use crate::{
     body::{Body, BoxBody},
     client::GrpcService,
-    codec::{encode_client, Codec, Streaming},
+    codec::{
+        encode_client, BufferPool, Codec, CompressionEncoding, CompressionLevel,
+        CompressionOverride, CompressionSettings, DecodeSettings, EnabledCompressionEncodings,
+        EncodeError, Streaming, ACCEPT_ENCODING_HEADER, DEFAULT_MAX_DECODING_MESSAGE_SIZE,
+        ENCODING_HEADER,
+    },
+    credentials::{CallCredentials, SharedCredentials},
+    deadline,
     interceptor::Interceptor,
-    Code, Request, Response, Status,
+    metadata::{self, MetadataMap},
+    spans::{self, Direction, TracedBody},
+    stats::{RpcTracker, StatsBody},
+    CancellationCause, Code, Request, Response, Status,
 };
 use futures_core::Stream;
 use futures_util::{future, stream, TryStreamExt};
 use http::{
     header::{HeaderValue, CONTENT_TYPE, TE},
-    uri::{Parts, PathAndQuery, Uri},
+    uri::{Authority, Parts, PathAndQuery, Scheme, Uri},
 };
 use http_body::Body as HttpBody;
-use std::fmt;
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{Instrument, Span};
 
 /// A gRPC client dispatcher.
 ///
@@ -29,7 +44,18 @@ use std::fmt;
 /// [gRPC protocol definition]: https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md#requests
 pub struct Grpc<T> {
     inner: T,
+    origin: Option<(Scheme, Authority)>,
     interceptor: Option<Interceptor>,
+    call_credentials: Option<SharedCredentials>,
+    send_compression_encoding: Option<CompressionEncoding>,
+    accept_compression_encodings: EnabledCompressionEncodings,
+    compression_settings: CompressionSettings,
+    max_decoding_message_size: Option<usize>,
+    max_encoding_message_size: Option<usize>,
+    buffer_pool: Option<BufferPool>,
+    yield_after_messages: Option<usize>,
+    message_checksums: bool,
+    max_metadata_size: Option<usize>,
 }
 
 impl<T> Grpc<T> {
@@ -37,7 +63,18 @@ impl<T> Grpc<T> {
     pub fn new(inner: T) -> Self {
         Self {
             inner,
+            origin: None,
             interceptor: None,
+            call_credentials: None,
+            send_compression_encoding: None,
+            accept_compression_encodings: EnabledCompressionEncodings::default(),
+            compression_settings: CompressionSettings::default(),
+            max_decoding_message_size: Some(DEFAULT_MAX_DECODING_MESSAGE_SIZE),
+            max_encoding_message_size: None,
+            buffer_pool: None,
+            yield_after_messages: None,
+            message_checksums: false,
+            max_metadata_size: None,
         }
     }
 
@@ -45,11 +82,140 @@ impl<T> Grpc<T> {
     /// the provided interceptor on each request.
     pub fn with_interceptor(inner: T, interceptor: impl Into<Interceptor>) -> Self {
         Self {
-            inner,
             interceptor: Some(interceptor.into()),
+            ..Self::new(inner)
+        }
+    }
+
+    /// Creates a new gRPC client with the provided [`GrpcService`], sending
+    /// its requests to `origin`.
+    ///
+    /// The requests sent by other clients only hold the path of the called
+    /// method, the transport of [`Channel`] adding the origin of its
+    /// endpoint, so this is for services passing requests to a transport of
+    /// their own, like a `hyper::Client`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `origin` lacks a scheme or an authority.
+    ///
+    /// [`Channel`]: ../transport/struct.Channel.html
+    pub fn with_origin(inner: T, origin: Uri) -> Self {
+        let origin = match (origin.scheme(), origin.authority()) {
+            (Some(scheme), Some(authority)) => (scheme.clone(), authority.clone()),
+            _ => panic!("origin without a scheme or an authority: {}", origin),
+        };
+
+        Self {
+            origin: Some(origin),
+            ..Self::new(inner)
+        }
+    }
+
+    /// Authenticate every call with the metadata produced by `credentials`
+    /// right before it is sent.
+    pub fn call_credentials(self, credentials: impl CallCredentials) -> Self {
+        Self {
+            call_credentials: Some(SharedCredentials::new(credentials)),
+            ..self
         }
     }
 
+    /// Compress requests with the provided encoding.
+    ///
+    /// Requires the server to accept that encoding, otherwise it will reply
+    /// with an `Unimplemented` status.
+    pub fn send_compressed(self, encoding: CompressionEncoding) -> Self {
+        Self {
+            send_compression_encoding: Some(encoding),
+            ..self
+        }
+    }
+
+    /// Accept responses compressed with the provided encoding.
+    ///
+    /// The encodings are advertised to the server with the
+    /// `grpc-accept-encoding` header, and the server picks whether to use one
+    /// of them.
+    pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.accept_compression_encodings.enable(encoding);
+        self
+    }
+
+    /// Set the level requests are compressed at.
+    pub fn compression_level(mut self, level: CompressionLevel) -> Self {
+        self.compression_settings.level = level;
+        self
+    }
+
+    /// Send request messages smaller than `threshold` bytes uncompressed,
+    /// since compressing them costs CPU and can grow them.
+    pub fn compression_threshold(mut self, threshold: usize) -> Self {
+        self.compression_settings.threshold = threshold;
+        self
+    }
+
+    /// Limit the size of the response messages this client decodes to
+    /// `limit` bytes.
+    ///
+    /// Larger messages fail the call with a `ResourceExhausted` status.
+    /// Defaults to 4MiB.
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.max_decoding_message_size = Some(limit);
+        self
+    }
+
+    /// Limit the size of the request messages this client encodes to `limit`
+    /// bytes.
+    ///
+    /// Larger messages fail the call with an `OutOfRange` status. Defaults
+    /// to no limit.
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.max_encoding_message_size = Some(limit);
+        self
+    }
+
+    /// Take the buffers messages are encoded into and decoded from from
+    /// `pool` instead of allocating them for every call.
+    pub fn buffer_pool(mut self, pool: BufferPool) -> Self {
+        self.buffer_pool = Some(pool);
+        self
+    }
+
+    /// Yield to the runtime after handing out `limit` already buffered
+    /// response messages in a row, so one busy stream does not starve the
+    /// other tasks.
+    ///
+    /// By default a stream keeps handing out buffered messages for as long
+    /// as it is polled.
+    pub fn yield_after_messages(mut self, limit: usize) -> Self {
+        self.yield_after_messages = Some(limit);
+        self
+    }
+
+    /// Send a CRC32C checksum of the request messages in the request
+    /// trailers, and check the one the server sends in the response trailers,
+    /// if any.
+    ///
+    /// A mismatch, caused for example by a misbehaving intermediary, fails
+    /// the call with a `DataLoss` status.
+    pub fn message_checksums(mut self, enable: bool) -> Self {
+        self.message_checksums = enable;
+        self
+    }
+
+    /// Limit the size of the response metadata, headers and trailers alike,
+    /// to `limit` bytes.
+    ///
+    /// The size of an entry is counted as the length of its key and value
+    /// plus 32 bytes, like HTTP/2 does. Larger metadata fails the call with a
+    /// `ResourceExhausted` status. Defaults to no limit besides the one of
+    /// the transport.
+    pub fn max_metadata_size(mut self, limit: usize) -> Self {
+        self.max_metadata_size = Some(limit);
+        self
+    }
+
     /// Check if the inner [`GrpcService`] is able to accept a  new request.
     ///
     /// This will call [`GrpcService::poll_ready`] until it returns ready or
@@ -97,7 +263,8 @@ impl<T> Grpc<T> {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
-        let (mut parts, body) = self.streaming(request, path, codec).await?.into_parts();
+        let (mut metadata, extensions, body) =
+            self.streaming(request, path, codec).await?.into_parts();
 
         futures_util::pin_mut!(body);
 
@@ -106,11 +273,10 @@ impl<T> Grpc<T> {
             .await?
             .ok_or_else(|| Status::new(Code::Internal, "Missing response message."))?;
 
-        if let Some(trailers) = body.trailers().await? {
-            parts.merge(trailers);
-        }
+        let trailers = body.trailers().await?.unwrap_or_default();
+        metadata.merge(trailers.clone());
 
-        Ok(Response::from_parts(parts, message))
+        Ok(Response::from_parts(metadata, extensions, message).with_trailers(trailers))
     }
 
     /// Send a server side streaming gRPC request.
@@ -134,10 +300,41 @@ impl<T> Grpc<T> {
 
     /// Send a bi-directional streaming gRPC request.
     pub async fn streaming<S, M1, M2, C>(
+        &mut self,
+        request: Request<S>,
+        path: PathAndQuery,
+        codec: C,
+    ) -> Result<Response<Streaming<M2>>, Status>
+    where
+        T: GrpcService<BoxBody>,
+        T::ResponseBody: Body + HttpBody + Send + 'static,
+        <T::ResponseBody as HttpBody>::Error: Into<crate::Error>,
+        S: Stream<Item = M1> + Send + Sync + 'static,
+        C: Codec<Encode = M1, Decode = M2>,
+        M1: Send + Sync + 'static,
+        M2: Send + Sync + 'static,
+    {
+        let authority = self
+            .origin
+            .as_ref()
+            .map(|(_, authority)| authority.as_str());
+        let span = spans::client(path.path(), authority);
+        let response = self
+            .traced_streaming(request, path, codec, span.clone())
+            .instrument(span.clone())
+            .await;
+        if let Err(status) = &response {
+            spans::record_code(&span, status);
+        }
+        response
+    }
+
+    async fn traced_streaming<S, M1, M2, C>(
         &mut self,
         request: Request<S>,
         path: PathAndQuery,
         mut codec: C,
+        span: Span,
     ) -> Result<Response<Streaming<M2>>, Status>
     where
         T: GrpcService<BoxBody>,
@@ -148,20 +345,70 @@ impl<T> Grpc<T> {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
-        let request = if let Some(interceptor) = &self.interceptor {
+        let mut request = if let Some(interceptor) = &self.interceptor {
             interceptor.call(request)?
         } else {
             request
         };
 
+        let call_credentials = request.take_call_credentials();
+        for credentials in self.call_credentials.iter().chain(&call_credentials) {
+            let metadata = credentials.0.metadata(path.path()).await?;
+            request.metadata_mut().merge(metadata);
+        }
+
+        // Calls made while handling another one inherit its deadline.
+        let deadline = match (request.deadline(), deadline::current()) {
+            (Some(own), Some(current)) => Some(own.min(current)),
+            (own, current) => own.or(current),
+        };
+        let timeout = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) if timeout > Duration::from_secs(0) => Some(timeout),
+                _ => {
+                    return Err(Status::deadline_exceeded("Deadline already exceeded.")
+                        .with_cancellation_cause(CancellationCause::Deadline))
+                }
+            },
+            None => None,
+        };
+
+        let send_encoding = match request.take_compression_override() {
+            Some(CompressionOverride::Disable) => None,
+            Some(CompressionOverride::Encoding(encoding)) => Some(encoding),
+            None => self.send_compression_encoding,
+        };
+        let compression = send_encoding.map(|encoding| (encoding, self.compression_settings));
+
         let mut parts = Parts::default();
         parts.path_and_query = Some(path);
+        if let Some((scheme, authority)) = &self.origin {
+            parts.scheme = Some(scheme.clone());
+            parts.authority = Some(authority.clone());
+        }
 
-        let uri = Uri::from_parts(parts).expect("path_and_query only is valid Uri");
+        let uri = Uri::from_parts(parts).expect("path and origin make a valid Uri");
 
+        let encode_error = EncodeError::default();
         let request = request
-            .map(|s| encode_client(codec.encoder(), s))
+            .map(|s| {
+                encode_client(
+                    codec.encoder(),
+                    s,
+                    compression,
+                    self.max_encoding_message_size,
+                    self.buffer_pool.clone(),
+                    self.message_checksums,
+                    encode_error.clone(),
+                )
+            })
             .map(BoxBody::new);
+        let request = if span.is_disabled() {
+            request
+        } else {
+            let span = span.clone();
+            request.map(|body| BoxBody::new(TracedBody::new(body, span, Direction::Sent)))
+        };
 
         let mut request = request.into_http(uri);
 
@@ -173,16 +420,42 @@ impl<T> Grpc<T> {
         // Set the content type
         request
             .headers_mut()
-            .insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+            .insert(CONTENT_TYPE, HeaderValue::from_static(codec.content_type()));
 
-        let response = self
-            .inner
-            .call(request)
-            .await
-            .map_err(|err| Status::from_error(&*(err.into())))?;
+        if let Some(timeout) = timeout {
+            request.headers_mut().insert(
+                deadline::GRPC_TIMEOUT_HEADER,
+                deadline::encode_timeout(timeout),
+            );
+        }
+
+        if let Some(encoding) = send_encoding {
+            request
+                .headers_mut()
+                .insert(ENCODING_HEADER, encoding.into_header_value());
+        }
+
+        if let Some(value) = self
+            .accept_compression_encodings
+            .into_accept_encoding_header_value()
+        {
+            request.headers_mut().insert(ACCEPT_ENCODING_HEADER, value);
+        }
+
+        let mut response = self.inner.call(request).await.map_err(|err| {
+            encode_error
+                .take()
+                .unwrap_or_else(|| Status::from_error(err.into()))
+        })?;
+
+        // The tracker of the call, when its channel reports its stats.
+        let tracker = response.extensions_mut().remove::<Arc<RpcTracker>>();
+
+        metadata::check_size(response.headers(), self.max_metadata_size)?;
 
         let status_code = response.status();
         let trailers_only_status = Status::from_header_map(response.headers());
+        spans::record_status(&span, response.headers());
 
         // We do not need to check for trailers if the `grpc-status` header is present
         // with a valid code.
@@ -196,12 +469,37 @@ impl<T> Grpc<T> {
             true
         };
 
+        // Unlike servers, clients answer responses compressed with an
+        // unsupported encoding with `Internal`, as the gRPC spec mandates.
+        let encoding = CompressionEncoding::from_encoding_header(
+            response.headers(),
+            self.accept_compression_encodings,
+        )
+        .map_err(|status| Status::new(Code::Internal, status.message()))?;
+
+        let initial_metadata = MetadataMap::from_headers(response.headers().clone());
+
         let response = response.map(|body| {
-            if expect_additional_trailers {
-                Streaming::new_response(codec.decoder(), body, status_code)
+            let body = StatsBody::new(body, tracker, true);
+            let body = TracedBody::new(body, span, Direction::Received);
+            let streaming = if expect_additional_trailers {
+                Streaming::new_response(
+                    codec.decoder(),
+                    body,
+                    status_code,
+                    encoding,
+                    DecodeSettings {
+                        max_message_size: self.max_decoding_message_size,
+                        yield_after_messages: self.yield_after_messages,
+                        buffer_pool: self.buffer_pool.clone(),
+                        checksum: self.message_checksums,
+                        max_metadata_size: self.max_metadata_size,
+                    },
+                )
             } else {
                 Streaming::new_empty(codec.decoder(), body)
-            }
+            };
+            streaming.with_initial_metadata(initial_metadata)
         });
 
         Ok(Response::from_http(response))
@@ -212,7 +510,18 @@ impl<T: Clone> Clone for Grpc<T> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            origin: self.origin.clone(),
             interceptor: self.interceptor.clone(),
+            call_credentials: self.call_credentials.clone(),
+            send_compression_encoding: self.send_compression_encoding,
+            accept_compression_encodings: self.accept_compression_encodings,
+            compression_settings: self.compression_settings,
+            max_decoding_message_size: self.max_decoding_message_size,
+            max_encoding_message_size: self.max_encoding_message_size,
+            buffer_pool: self.buffer_pool.clone(),
+            yield_after_messages: self.yield_after_messages,
+            message_checksums: self.message_checksums,
+            max_metadata_size: self.max_metadata_size,
         }
     }
 }
@@ -7,8 +7,14 @@
 //! This client is generally used by some code generation tool to provide stubs
 //! for the gRPC service. Thusly, they are a bit cumbersome to use by hand.
 
+mod circuit_breaker;
 mod grpc;
 mod service;
+mod sink;
 
+pub use self::circuit_breaker::{
+    CircuitBreaker, CircuitBreakerBody, CircuitBreakerFuture, CircuitBreakerLayer,
+};
 pub use self::grpc::Grpc;
 pub use self::service::GrpcService;
+pub use self::sink::{request_channel, RequestSink, RequestStream};
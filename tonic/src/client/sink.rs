@@ -0,0 +1,110 @@
+use crate::Status;
+use futures_channel::mpsc;
+use futures_core::Stream;
+use futures_sink::Sink;
+use futures_util::future::poll_fn;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// The number of requests a [`RequestSink`] buffers before waiting for the
+/// call to send them.
+const BUFFER: usize = 16;
+
+/// Create a [`RequestSink`] paired with the [`RequestStream`] of the
+/// requests sent to it, for the client streaming calls of the generated
+/// `{method}_channel` helpers.
+pub fn request_channel<T>() -> (RequestSink<T>, RequestStream<T>) {
+    let (sender, receiver) = mpsc::channel(BUFFER);
+    (RequestSink(sender), RequestStream(receiver))
+}
+
+/// The sending half of the requests of a streaming call.
+///
+/// The requests end once the sink is closed or dropped.
+#[derive(Debug)]
+pub struct RequestSink<T>(mpsc::Sender<T>);
+
+impl<T> RequestSink<T> {
+    /// Send `request`, waiting for room in the buffer.
+    ///
+    /// Fails with `Cancelled` once the call ended.
+    pub async fn send(&mut self, request: T) -> Result<(), Status> {
+        poll_fn(|cx| self.0.poll_ready(cx))
+            .await
+            .map_err(|_| call_ended())?;
+        self.0.start_send(request).map_err(|_| call_ended())
+    }
+
+    /// End the requests of the call.
+    pub fn close(&mut self) {
+        self.0.close_channel();
+    }
+}
+
+impl<T> Sink<T> for RequestSink<T> {
+    type Error = Status;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Status>> {
+        self.get_mut().0.poll_ready(cx).map_err(|_| call_ended())
+    }
+
+    fn start_send(self: Pin<&mut Self>, request: T) -> Result<(), Status> {
+        self.get_mut()
+            .0
+            .start_send(request)
+            .map_err(|_| call_ended())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Status>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Status>> {
+        self.get_mut().close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn call_ended() -> Status {
+    Status::cancelled("the call ended")
+}
+
+/// The requests sent to a [`RequestSink`].
+#[derive(Debug)]
+pub struct RequestStream<T>(mpsc::Receiver<T>);
+
+impl<T> Stream for RequestStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Pin::new(&mut self.get_mut().0).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn streams_the_sent_requests() {
+        let (mut sink, stream) = request_channel();
+        sink.send(1).await.unwrap();
+        sink.send(2).await.unwrap();
+        sink.close();
+
+        assert_eq!(stream.collect::<Vec<_>>().await, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn fails_once_the_call_ended() {
+        let (mut sink, stream) = request_channel();
+        drop(stream);
+
+        let status = sink.send(1).await.unwrap_err();
+        assert_eq!(status.code(), Code::Cancelled);
+    }
+}
@@ -1,13 +1,26 @@
-use bytes::{Buf, BufMut, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::mem::MaybeUninit;
 
 /// A specialized buffer to decode gRPC messages from.
+///
+/// [`Buf::to_bytes`] hands out the message as a slice of the received
+/// buffer instead of copying it, so decoders can keep `Bytes` backed fields
+/// zero-copy.
 #[derive(Debug)]
 pub struct DecodeBuf<'a> {
-    buf: &'a mut BytesMut,
+    buf: Source<'a>,
     len: usize,
 }
 
+/// The buffer a [`DecodeBuf`] reads from.
+#[derive(Debug)]
+enum Source<'a> {
+    /// The buffer data is received into.
+    Received(&'a mut BytesMut),
+    /// A message that was already split off the received data.
+    Frozen(&'a mut Bytes),
+}
+
 /// A specialized buffer to encode gRPC messages into.
 #[derive(Debug)]
 pub struct EncodeBuf<'a> {
@@ -16,7 +29,17 @@ pub struct EncodeBuf<'a> {
 
 impl<'a> DecodeBuf<'a> {
     pub(crate) fn new(buf: &'a mut BytesMut, len: usize) -> Self {
-        DecodeBuf { buf, len }
+        DecodeBuf {
+            buf: Source::Received(buf),
+            len,
+        }
+    }
+
+    pub(crate) fn from_bytes(buf: &'a mut Bytes, len: usize) -> Self {
+        DecodeBuf {
+            buf: Source::Frozen(buf),
+            len,
+        }
     }
 }
 
@@ -28,7 +51,10 @@ impl Buf for DecodeBuf<'_> {
 
     #[inline]
     fn bytes(&self) -> &[u8] {
-        let ret = self.buf.bytes();
+        let ret = match &self.buf {
+            Source::Received(buf) => buf.bytes(),
+            Source::Frozen(buf) => buf.bytes(),
+        };
 
         if ret.len() > self.len {
             &ret[..self.len]
@@ -40,9 +66,21 @@ impl Buf for DecodeBuf<'_> {
     #[inline]
     fn advance(&mut self, cnt: usize) {
         assert!(cnt <= self.len);
-        self.buf.advance(cnt);
+        match &mut self.buf {
+            Source::Received(buf) => buf.advance(cnt),
+            Source::Frozen(buf) => buf.advance(cnt),
+        }
         self.len -= cnt;
     }
+
+    // Splits the message off the underlying buffer instead of copying it.
+    fn to_bytes(&mut self) -> Bytes {
+        let len = std::mem::replace(&mut self.len, 0);
+        match &mut self.buf {
+            Source::Received(buf) => buf.split_to(len).freeze(),
+            Source::Frozen(buf) => buf.split_to(len),
+        }
+    }
 }
 
 impl<'a> EncodeBuf<'a> {
@@ -104,6 +142,31 @@ mod tests {
 
         assert_eq!(buf.to_bytes().len(), 5);
         assert!(!buf.has_remaining());
+        assert_eq!(payload.len(), 30);
+    }
+
+    #[test]
+    fn decode_buf_to_bytes_does_not_copy() {
+        let mut payload = BytesMut::with_capacity(100);
+        payload.put(&b"hello world"[..]);
+        let ptr = payload.as_ptr();
+
+        let bytes = DecodeBuf::new(&mut payload, 5).to_bytes();
+        assert_eq!(&bytes[..], b"hello");
+        assert_eq!(bytes.as_ptr(), ptr);
+        assert_eq!(&payload[..], b" world");
+    }
+
+    #[test]
+    fn decode_buf_from_bytes() {
+        let mut payload = Bytes::from_static(b"hello world");
+        let mut buf = DecodeBuf::from_bytes(&mut payload, 5);
+
+        assert_eq!(buf.bytes(), b"hello");
+        buf.advance(1);
+        assert_eq!(buf.to_bytes(), Bytes::from_static(b"ello"));
+        assert!(!buf.has_remaining());
+        assert_eq!(&payload[..], b" world");
     }
 
     #[test]
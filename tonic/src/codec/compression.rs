@@ -0,0 +1,778 @@
+use crate::Status;
+use bytes::{buf::BufMutExt, Buf, BytesMut};
+use http::{HeaderMap, HeaderValue};
+use std::{
+    fmt, io,
+    sync::{Arc, RwLock},
+};
+
+pub(crate) const ENCODING_HEADER: &str = "grpc-encoding";
+pub(crate) const ACCEPT_ENCODING_HEADER: &str = "grpc-accept-encoding";
+
+/// The maximum number of encodings that can be registered with
+/// [`CompressionEncoding::register`].
+const MAX_CUSTOM_ENCODINGS: usize = 64;
+
+/// Compressors registered with [`CompressionEncoding::register`], indexed
+/// by [`CustomEncoding`].
+static CUSTOM_ENCODINGS: RwLock<Vec<Arc<dyn Compressor>>> = RwLock::new(Vec::new());
+
+/// A compression algorithm that can be registered as a custom
+/// [`CompressionEncoding`].
+///
+/// # Example
+///
+/// ```
+/// use std::io::{self, Write};
+/// use tonic::codec::{CompressionEncoding, Compressor};
+///
+/// /// Sends messages as is, under another name.
+/// struct Passthrough;
+///
+/// impl Compressor for Passthrough {
+///     fn name(&self) -> &'static str {
+///         "x-passthrough"
+///     }
+///
+///     fn compress(&self, src: &[u8], dst: &mut dyn Write) -> io::Result<()> {
+///         dst.write_all(src)
+///     }
+///
+///     fn decompress(&self, src: &[u8], dst: &mut dyn Write) -> io::Result<()> {
+///         dst.write_all(src)
+///     }
+/// }
+///
+/// let encoding = CompressionEncoding::register(Passthrough).unwrap();
+/// assert_eq!(encoding.to_string(), "x-passthrough");
+/// ```
+pub trait Compressor: Send + Sync + 'static {
+    /// The name of the encoding, as sent in the `grpc-encoding` and
+    /// `grpc-accept-encoding` headers.
+    fn name(&self) -> &'static str;
+
+    /// Compress the message in `src` into `dst`.
+    fn compress(&self, src: &[u8], dst: &mut dyn io::Write) -> io::Result<()>;
+
+    /// Decompress the message in `src` into `dst`.
+    fn decompress(&self, src: &[u8], dst: &mut dyn io::Write) -> io::Result<()>;
+
+    /// Compress the message in `src` into `dst` at the configured `level`.
+    ///
+    /// The default implementation ignores `level` and calls [`compress`].
+    ///
+    /// [`compress`]: #tymethod.compress
+    fn compress_with_level(
+        &self,
+        src: &[u8],
+        dst: &mut dyn io::Write,
+        level: CompressionLevel,
+    ) -> io::Result<()> {
+        let _ = level;
+        self.compress(src, dst)
+    }
+}
+
+/// The compression encodings supported by tonic.
+///
+/// Each built-in encoding is gated behind a cargo feature of the same name.
+/// Other algorithms can be plugged in with [`CompressionEncoding::register`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionEncoding {
+    /// The `gzip` encoding.
+    #[cfg(feature = "gzip")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "gzip")))]
+    Gzip,
+    /// The `zstd` encoding.
+    #[cfg(feature = "zstd")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zstd")))]
+    Zstd,
+    /// The `deflate` encoding, using the zlib format.
+    #[cfg(feature = "deflate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "deflate")))]
+    Deflate,
+    /// An encoding registered with [`CompressionEncoding::register`].
+    Custom(CustomEncoding),
+}
+
+/// A per-call override of the compression configured on a client or server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CompressionOverride {
+    /// Send the messages of the call uncompressed.
+    Disable,
+    /// Compress the messages of the call with this encoding.
+    Encoding(CompressionEncoding),
+}
+
+impl From<Option<CompressionEncoding>> for CompressionOverride {
+    fn from(encoding: Option<CompressionEncoding>) -> Self {
+        encoding.map_or(CompressionOverride::Disable, CompressionOverride::Encoding)
+    }
+}
+
+/// The level to compress messages at, trading speed for size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompressionLevel {
+    /// The fastest level of the encoding.
+    Fastest,
+    /// The default level of the encoding.
+    #[default]
+    Default,
+    /// The level with the best compression ratio.
+    Best,
+    /// A level specific to the encoding, clamped to the range it supports:
+    /// 0 to 9 for `gzip` and `deflate`, 1 to 22 for `zstd`.
+    Precise(u32),
+}
+
+impl CompressionLevel {
+    #[cfg(any(feature = "gzip", feature = "deflate"))]
+    fn into_flate2(self) -> flate2::Compression {
+        match self {
+            CompressionLevel::Fastest => flate2::Compression::fast(),
+            CompressionLevel::Default => flate2::Compression::default(),
+            CompressionLevel::Best => flate2::Compression::best(),
+            CompressionLevel::Precise(level) => flate2::Compression::new(level.min(9)),
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    fn into_zstd(self) -> i32 {
+        let range = zstd_lib::compression_level_range();
+        match self {
+            CompressionLevel::Fastest => 1,
+            CompressionLevel::Default => zstd_lib::DEFAULT_COMPRESSION_LEVEL,
+            CompressionLevel::Best => *range.end(),
+            CompressionLevel::Precise(level) => {
+                (level.min(i32::MAX as u32) as i32).clamp(1, *range.end())
+            }
+        }
+    }
+}
+
+/// How the messages of a call are compressed once an encoding is picked.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct CompressionSettings {
+    pub(crate) level: CompressionLevel,
+    /// Messages smaller than this many bytes are sent uncompressed.
+    pub(crate) threshold: usize,
+}
+
+/// The error of a [`Compressor`] that couldn't be registered with
+/// [`CompressionEncoding::register`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterError(RegisterErrorKind);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RegisterErrorKind {
+    InvalidName(&'static str),
+    Full,
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            RegisterErrorKind::InvalidName(name) => {
+                write!(f, "invalid compression encoding name `{}`", name)
+            }
+            RegisterErrorKind::Full => write!(
+                f,
+                "at most {} custom compression encodings can be registered",
+                MAX_CUSTOM_ENCODINGS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegisterError {}
+
+/// A handle to a [`Compressor`] registered with
+/// [`CompressionEncoding::register`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CustomEncoding {
+    index: usize,
+}
+
+impl CustomEncoding {
+    fn compressor(self) -> Arc<dyn Compressor> {
+        CUSTOM_ENCODINGS.read().unwrap()[self.index].clone()
+    }
+
+    /// The name of the encoding.
+    pub fn name(self) -> &'static str {
+        self.compressor().name()
+    }
+}
+
+/// The set of compression encodings enabled for one direction of a client
+/// or server.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EnabledCompressionEncodings {
+    #[cfg(feature = "gzip")]
+    gzip: bool,
+    #[cfg(feature = "zstd")]
+    zstd: bool,
+    #[cfg(feature = "deflate")]
+    deflate: bool,
+    /// A bit for each [`CustomEncoding`], by index.
+    custom: u64,
+}
+
+impl EnabledCompressionEncodings {
+    /// Enable `encoding`.
+    pub fn enable(&mut self, encoding: CompressionEncoding) {
+        match encoding {
+            #[cfg(feature = "gzip")]
+            CompressionEncoding::Gzip => self.gzip = true,
+            #[cfg(feature = "zstd")]
+            CompressionEncoding::Zstd => self.zstd = true,
+            #[cfg(feature = "deflate")]
+            CompressionEncoding::Deflate => self.deflate = true,
+            CompressionEncoding::Custom(custom) => self.custom |= 1 << custom.index,
+        }
+    }
+
+    /// Check if `encoding` is enabled.
+    pub fn is_enabled(&self, encoding: CompressionEncoding) -> bool {
+        match encoding {
+            #[cfg(feature = "gzip")]
+            CompressionEncoding::Gzip => self.gzip,
+            #[cfg(feature = "zstd")]
+            CompressionEncoding::Zstd => self.zstd,
+            #[cfg(feature = "deflate")]
+            CompressionEncoding::Deflate => self.deflate,
+            CompressionEncoding::Custom(custom) => self.custom & (1 << custom.index) != 0,
+        }
+    }
+
+    fn iter(self) -> impl Iterator<Item = CompressionEncoding> {
+        let custom = (0..MAX_CUSTOM_ENCODINGS)
+            .filter(move |index| self.custom & (1 << index) != 0)
+            .map(|index| CompressionEncoding::Custom(CustomEncoding { index }));
+
+        CompressionEncoding::BUILT_IN
+            .iter()
+            .copied()
+            .filter(move |encoding| self.is_enabled(*encoding))
+            .chain(custom)
+    }
+
+    /// The value of the `grpc-accept-encoding` header advertising these
+    /// encodings, or `None` if none of them is enabled.
+    pub(crate) fn into_accept_encoding_header_value(self) -> Option<HeaderValue> {
+        let value = self
+            .iter()
+            .map(CompressionEncoding::as_str)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        if value.is_empty() {
+            None
+        } else {
+            Some(HeaderValue::from_str(&value).expect("encoding names are valid header values"))
+        }
+    }
+}
+
+impl CompressionEncoding {
+    const BUILT_IN: &'static [CompressionEncoding] = &[
+        #[cfg(feature = "gzip")]
+        CompressionEncoding::Gzip,
+        #[cfg(feature = "zstd")]
+        CompressionEncoding::Zstd,
+        #[cfg(feature = "deflate")]
+        CompressionEncoding::Deflate,
+    ];
+
+    /// Pick the first encoding listed in the `grpc-accept-encoding` header
+    /// value that is also `enabled`.
+    pub(crate) fn from_accept_encoding_header(
+        value: Option<&HeaderValue>,
+        enabled: EnabledCompressionEncodings,
+    ) -> Option<Self> {
+        let value = value?.to_str().ok()?;
+
+        value
+            .split(',')
+            .map(str::trim)
+            .filter_map(Self::from_name)
+            .find(|encoding| enabled.is_enabled(*encoding))
+    }
+
+    /// Read the encoding of the messages from the `grpc-encoding` header of
+    /// `map`.
+    ///
+    /// Returns `Ok(None)` if the messages are not compressed, and an
+    /// `Unimplemented` status if they are compressed with an encoding that
+    /// is not `enabled`.
+    pub(crate) fn from_encoding_header(
+        map: &HeaderMap,
+        enabled: EnabledCompressionEncodings,
+    ) -> Result<Option<Self>, Status> {
+        let value = match map.get(ENCODING_HEADER) {
+            Some(value) => value.to_str().unwrap_or_default(),
+            None => return Ok(None),
+        };
+
+        if value == "identity" {
+            return Ok(None);
+        }
+
+        match Self::from_name(value) {
+            Some(encoding) if enabled.is_enabled(encoding) => Ok(Some(encoding)),
+            _ => Err(Status::unimplemented(format!(
+                "Content is compressed with `{}` which isn't supported",
+                value
+            ))),
+        }
+    }
+
+    /// Register a custom compression algorithm, and return the encoding to
+    /// pass to the `send_compressed` and `accept_compressed` methods of
+    /// clients and servers.
+    ///
+    /// Registered encodings are advertised in `grpc-accept-encoding` and
+    /// negotiated like the built-in ones. Registering a name again returns
+    /// the encoding already registered under it, which keeps its first
+    /// compressor, so libraries can register the encodings they use without
+    /// coordinating.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the name of `compressor` is not a valid header value or is
+    /// `identity`, or if 64 custom encodings are already registered.
+    pub fn register(compressor: impl Compressor) -> Result<Self, RegisterError> {
+        let name = compressor.name();
+        if name == "identity" || HeaderValue::from_str(name).is_err() || name.contains(',') {
+            return Err(RegisterError(RegisterErrorKind::InvalidName(name)));
+        }
+
+        let built_in = Self::BUILT_IN
+            .iter()
+            .copied()
+            .find(|encoding| encoding.as_str() == name);
+        if let Some(encoding) = built_in {
+            return Ok(encoding);
+        }
+
+        let mut custom = CUSTOM_ENCODINGS.write().unwrap();
+        let index = match custom.iter().position(|c| c.name() == name) {
+            Some(index) => index,
+            None if custom.len() >= MAX_CUSTOM_ENCODINGS => {
+                return Err(RegisterError(RegisterErrorKind::Full));
+            }
+            None => {
+                custom.push(Arc::new(compressor));
+                custom.len() - 1
+            }
+        };
+        Ok(CompressionEncoding::Custom(CustomEncoding { index }))
+    }
+
+    fn from_name(value: &str) -> Option<Self> {
+        match value {
+            #[cfg(feature = "gzip")]
+            "gzip" => Some(CompressionEncoding::Gzip),
+            #[cfg(feature = "zstd")]
+            "zstd" => Some(CompressionEncoding::Zstd),
+            #[cfg(feature = "deflate")]
+            "deflate" => Some(CompressionEncoding::Deflate),
+            _ => CUSTOM_ENCODINGS
+                .read()
+                .unwrap()
+                .iter()
+                .position(|compressor| compressor.name() == value)
+                .map(|index| CompressionEncoding::Custom(CustomEncoding { index })),
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            #[cfg(feature = "gzip")]
+            CompressionEncoding::Gzip => "gzip",
+            #[cfg(feature = "zstd")]
+            CompressionEncoding::Zstd => "zstd",
+            #[cfg(feature = "deflate")]
+            CompressionEncoding::Deflate => "deflate",
+            CompressionEncoding::Custom(custom) => custom.name(),
+        }
+    }
+
+    pub(crate) fn into_header_value(self) -> HeaderValue {
+        HeaderValue::from_static(self.as_str())
+    }
+}
+
+impl fmt::Display for CompressionEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Compress the first `len` bytes of `src` into `dst`, and advance `src`
+/// past them.
+pub(crate) fn compress(
+    encoding: CompressionEncoding,
+    level: CompressionLevel,
+    src: &mut BytesMut,
+    dst: &mut BytesMut,
+    len: usize,
+) -> io::Result<()> {
+    match encoding {
+        #[cfg(feature = "gzip")]
+        CompressionEncoding::Gzip => {
+            let mut encoder = flate2::read::GzEncoder::new(&src[..len], level.into_flate2());
+            io::copy(&mut encoder, &mut dst.writer())?;
+            src.advance(len);
+            Ok(())
+        }
+        #[cfg(feature = "zstd")]
+        CompressionEncoding::Zstd => {
+            let mut encoder = zstd_lib::stream::read::Encoder::new(&src[..len], level.into_zstd())?;
+            io::copy(&mut encoder, &mut dst.writer())?;
+            src.advance(len);
+            Ok(())
+        }
+        #[cfg(feature = "deflate")]
+        CompressionEncoding::Deflate => {
+            let mut encoder = flate2::read::ZlibEncoder::new(&src[..len], level.into_flate2());
+            io::copy(&mut encoder, &mut dst.writer())?;
+            src.advance(len);
+            Ok(())
+        }
+        CompressionEncoding::Custom(custom) => {
+            custom
+                .compressor()
+                .compress_with_level(&src[..len], &mut dst.writer(), level)?;
+            src.advance(len);
+            Ok(())
+        }
+    }
+}
+
+/// Decompress the first `len` bytes of `src` into `dst`, and advance `src`
+/// past them.
+///
+/// With a `limit`, decompression stops one byte past it, which is enough for
+/// the caller to tell the message is too large without inflating all of it.
+pub(crate) fn decompress(
+    encoding: CompressionEncoding,
+    src: &mut BytesMut,
+    dst: &mut BytesMut,
+    len: usize,
+    limit: Option<usize>,
+) -> io::Result<()> {
+    let max = limit.map_or(u64::MAX, |limit| limit as u64 + 1);
+    match encoding {
+        #[cfg(feature = "gzip")]
+        CompressionEncoding::Gzip => {
+            let decoder = flate2::read::GzDecoder::new(&src[..len]);
+            io::copy(&mut io::Read::take(decoder, max), &mut dst.writer())?;
+            src.advance(len);
+            Ok(())
+        }
+        #[cfg(feature = "zstd")]
+        CompressionEncoding::Zstd => {
+            let decoder = zstd_lib::stream::read::Decoder::new(&src[..len])?;
+            io::copy(&mut io::Read::take(decoder, max), &mut dst.writer())?;
+            src.advance(len);
+            Ok(())
+        }
+        #[cfg(feature = "deflate")]
+        CompressionEncoding::Deflate => {
+            let decoder = flate2::read::ZlibDecoder::new(&src[..len]);
+            io::copy(&mut io::Read::take(decoder, max), &mut dst.writer())?;
+            src.advance(len);
+            Ok(())
+        }
+        CompressionEncoding::Custom(custom) => {
+            let mut writer = LimitWriter {
+                inner: dst.writer(),
+                remaining: max,
+            };
+            match custom.compressor().decompress(&src[..len], &mut writer) {
+                // The compressor gave up because the writer is full.
+                Err(_) if writer.remaining == 0 => {}
+                res => res?,
+            }
+            src.advance(len);
+            Ok(())
+        }
+    }
+}
+
+/// A writer accepting no more bytes once `remaining` bytes were written to
+/// it, failing `write_all` with `WriteZero`.
+struct LimitWriter<W> {
+    inner: W,
+    remaining: u64,
+}
+
+impl<W: io::Write> io::Write for LimitWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf
+            .len()
+            .min(self.remaining.min(usize::MAX as u64) as usize);
+        let written = self.inner.write(&buf[..len])?;
+        self.remaining -= written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Run-length encodes messages as `(count, byte)` pairs.
+    struct RunLength(&'static str);
+
+    impl Compressor for RunLength {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn compress(&self, src: &[u8], dst: &mut dyn Write) -> io::Result<()> {
+            let mut runs = src.iter().peekable();
+            while let Some(&byte) = runs.next() {
+                let mut count = 1u8;
+                while count < u8::MAX && runs.peek() == Some(&&byte) {
+                    runs.next();
+                    count += 1;
+                }
+                dst.write_all(&[count, byte])?;
+            }
+            Ok(())
+        }
+
+        fn decompress(&self, src: &[u8], dst: &mut dyn Write) -> io::Result<()> {
+            for run in src.chunks(2) {
+                dst.write_all(&vec![run[1]; run[0] as usize])?;
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    fn enabled() -> EnabledCompressionEncodings {
+        let mut enabled = EnabledCompressionEncodings::default();
+        enabled.enable(CompressionEncoding::Gzip);
+        enabled
+    }
+
+    fn assert_round_trip(encoding: CompressionEncoding) {
+        assert_round_trip_at(encoding, CompressionLevel::Default);
+    }
+
+    fn assert_round_trip_at(encoding: CompressionEncoding, level: CompressionLevel) {
+        let msg = vec![7u8; 4096];
+        let mut src = BytesMut::from(&msg[..]);
+        let mut compressed = BytesMut::new();
+        compress(encoding, level, &mut src, &mut compressed, msg.len()).unwrap();
+        assert!(src.is_empty());
+        assert!(compressed.len() < msg.len());
+
+        let len = compressed.len();
+        let mut decompressed = BytesMut::new();
+        decompress(encoding, &mut compressed, &mut decompressed, len, None).unwrap();
+        assert_eq!(&decompressed[..], &msg[..]);
+    }
+
+    fn assert_decompression_stops_past(encoding: CompressionEncoding, limit: usize) {
+        let msg = vec![0u8; 1024 * 1024];
+        let mut src = BytesMut::from(&msg[..]);
+        let mut compressed = BytesMut::new();
+        compress(
+            encoding,
+            CompressionLevel::Default,
+            &mut src,
+            &mut compressed,
+            msg.len(),
+        )
+        .unwrap();
+
+        let len = compressed.len();
+        let mut decompressed = BytesMut::new();
+        decompress(
+            encoding,
+            &mut compressed,
+            &mut decompressed,
+            len,
+            Some(limit),
+        )
+        .unwrap();
+        assert!(compressed.is_empty());
+        assert_eq!(decompressed.len(), limit + 1);
+    }
+
+    #[test]
+    fn stops_decompressing_past_the_limit() {
+        let encoding = CompressionEncoding::register(RunLength("x-rle-limit")).unwrap();
+        assert_decompression_stops_past(encoding, 1000);
+        #[cfg(feature = "gzip")]
+        assert_decompression_stops_past(CompressionEncoding::Gzip, 1000);
+        #[cfg(feature = "zstd")]
+        assert_decompression_stops_past(CompressionEncoding::Zstd, 1000);
+        #[cfg(feature = "deflate")]
+        assert_decompression_stops_past(CompressionEncoding::Deflate, 1000);
+    }
+
+    #[test]
+    fn custom_round_trip() {
+        assert_round_trip(CompressionEncoding::register(RunLength("x-rle-round-trip")).unwrap());
+    }
+
+    #[test]
+    fn negotiates_custom_encodings() {
+        let encoding = CompressionEncoding::register(RunLength("x-rle-negotiate")).unwrap();
+        let mut enabled = EnabledCompressionEncodings::default();
+        enabled.enable(encoding);
+
+        let mut map = HeaderMap::new();
+        map.insert(
+            ACCEPT_ENCODING_HEADER,
+            HeaderValue::from_static("snappy, x-rle-negotiate"),
+        );
+        assert_eq!(
+            CompressionEncoding::from_accept_encoding_header(
+                map.get(ACCEPT_ENCODING_HEADER),
+                enabled
+            ),
+            Some(encoding)
+        );
+
+        map.insert(ENCODING_HEADER, encoding.into_header_value());
+        assert_eq!(
+            CompressionEncoding::from_encoding_header(&map, enabled).unwrap(),
+            Some(encoding)
+        );
+        assert_eq!(
+            enabled.into_accept_encoding_header_value().unwrap(),
+            "x-rle-negotiate"
+        );
+    }
+
+    #[test]
+    fn registers_names_once() {
+        let encoding = CompressionEncoding::register(RunLength("x-rle-duplicate")).unwrap();
+        assert_eq!(
+            CompressionEncoding::register(RunLength("x-rle-duplicate")),
+            Ok(encoding)
+        );
+        #[cfg(feature = "gzip")]
+        assert_eq!(
+            CompressionEncoding::register(RunLength("gzip")),
+            Ok(CompressionEncoding::Gzip)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        for name in &["identity", "x-rle, gzip", "x-rle\n"] {
+            let err = CompressionEncoding::register(RunLength(*name)).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("invalid compression encoding name `{}`", name)
+            );
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_round_trip() {
+        assert_round_trip(CompressionEncoding::Gzip);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_levels_round_trip() {
+        for level in &[
+            CompressionLevel::Fastest,
+            CompressionLevel::Best,
+            CompressionLevel::Precise(1),
+            CompressionLevel::Precise(100),
+        ] {
+            assert_round_trip_at(CompressionEncoding::Gzip, *level);
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_round_trip() {
+        assert_round_trip(CompressionEncoding::Zstd);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_levels_round_trip() {
+        for level in &[
+            CompressionLevel::Fastest,
+            CompressionLevel::Best,
+            CompressionLevel::Precise(0),
+        ] {
+            assert_round_trip_at(CompressionEncoding::Zstd, *level);
+        }
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn deflate_round_trip() {
+        assert_round_trip(CompressionEncoding::Deflate);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn parses_encoding_header() {
+        let mut map = HeaderMap::new();
+        assert_eq!(
+            CompressionEncoding::from_encoding_header(&map, enabled()).unwrap(),
+            None
+        );
+
+        map.insert(ENCODING_HEADER, HeaderValue::from_static("gzip"));
+        assert_eq!(
+            CompressionEncoding::from_encoding_header(&map, enabled()).unwrap(),
+            Some(CompressionEncoding::Gzip)
+        );
+
+        let err = CompressionEncoding::from_encoding_header(&map, Default::default()).unwrap_err();
+        assert_eq!(err.code(), crate::Code::Unimplemented);
+
+        map.insert(ENCODING_HEADER, HeaderValue::from_static("snappy"));
+        let err = CompressionEncoding::from_encoding_header(&map, enabled()).unwrap_err();
+        assert_eq!(err.code(), crate::Code::Unimplemented);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn negotiates_accept_encoding() {
+        let mut map = HeaderMap::new();
+        map.insert(
+            ACCEPT_ENCODING_HEADER,
+            HeaderValue::from_static("snappy, gzip"),
+        );
+
+        assert_eq!(
+            CompressionEncoding::from_accept_encoding_header(
+                map.get(ACCEPT_ENCODING_HEADER),
+                enabled()
+            ),
+            Some(CompressionEncoding::Gzip)
+        );
+        assert_eq!(
+            CompressionEncoding::from_accept_encoding_header(
+                map.get(ACCEPT_ENCODING_HEADER),
+                Default::default()
+            ),
+            None
+        );
+        assert_eq!(
+            enabled().into_accept_encoding_header_value().unwrap(),
+            "gzip"
+        );
+    }
+}
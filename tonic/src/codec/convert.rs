@@ -0,0 +1,345 @@
+use super::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use crate::Status;
+use std::{fmt, marker::PhantomData};
+
+/// Converts the messages of a codec to and from the Rust type standing in
+/// for them in the generated clients and servers.
+///
+/// Conversions are named by `tonic_build::Builder::extern_type`, and are
+/// implemented by a type of their own, so any message can be mapped to any
+/// Rust type, including ones from other crates:
+///
+/// ```ignore
+/// pub enum UuidConversion {}
+///
+/// impl tonic::codec::Conversion for UuidConversion {
+///     type Proto = proto::Uuid;
+///     type Rust = uuid::Uuid;
+///
+///     fn from_proto(proto: proto::Uuid) -> Result<uuid::Uuid, Status> {
+///         uuid::Uuid::parse_str(&proto.value).map_err(|e| Status::invalid_argument(e.to_string()))
+///     }
+///
+///     fn into_proto(rust: uuid::Uuid) -> proto::Uuid {
+///         proto::Uuid { value: rust.to_string() }
+///     }
+/// }
+/// ```
+pub trait Conversion {
+    /// The message encoded and decoded by the codec.
+    type Proto;
+    /// The type standing in for the message.
+    type Rust;
+
+    /// Convert a decoded message, failing the call if it isn't valid.
+    fn from_proto(proto: Self::Proto) -> Result<Self::Rust, Status>;
+
+    /// Convert a value to the message to encode.
+    fn into_proto(rust: Self::Rust) -> Self::Proto;
+}
+
+/// A [`Conversion`] leaving messages untouched, for the side of a method
+/// whose message isn't mapped.
+pub struct Identity<T>(PhantomData<fn(T) -> T>);
+
+impl<T> Conversion for Identity<T> {
+    type Proto = T;
+    type Rust = T;
+
+    fn from_proto(proto: T) -> Result<T, Status> {
+        Ok(proto)
+    }
+
+    fn into_proto(rust: T) -> T {
+        rust
+    }
+}
+
+impl<T> fmt::Debug for Identity<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Identity").finish()
+    }
+}
+
+/// A [`Codec`] that wraps another codec, converting the messages it encodes
+/// with `E` and the ones it decodes with `D`.
+pub struct ConvertCodec<C, E, D> {
+    inner: C,
+    _pd: PhantomData<fn(E, D)>,
+}
+
+impl<C: Default, E, D> Default for ConvertCodec<C, E, D> {
+    fn default() -> Self {
+        Self {
+            inner: C::default(),
+            _pd: PhantomData,
+        }
+    }
+}
+
+impl<C, E, D> Codec for ConvertCodec<C, E, D>
+where
+    C: Codec,
+    E: Conversion<Proto = C::Encode> + 'static,
+    E::Rust: Send + 'static,
+    D: Conversion<Proto = C::Decode> + 'static,
+    D::Rust: Send + 'static,
+{
+    type Encode = E::Rust;
+    type Decode = D::Rust;
+
+    type Encoder = ConvertEncoder<C::Encoder, E>;
+    type Decoder = ConvertDecoder<C::Decoder, D>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        ConvertEncoder {
+            inner: self.inner.encoder(),
+            _pd: PhantomData,
+        }
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        ConvertDecoder {
+            inner: self.inner.decoder(),
+            _pd: PhantomData,
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        self.inner.content_type()
+    }
+}
+
+impl<C: fmt::Debug, E, D> fmt::Debug for ConvertCodec<C, E, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConvertCodec")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// An [`Encoder`] converting values with `E` before encoding them with the
+/// encoder `T`.
+pub struct ConvertEncoder<T, E> {
+    inner: T,
+    _pd: PhantomData<fn(E)>,
+}
+
+impl<T, E> Encoder for ConvertEncoder<T, E>
+where
+    T: Encoder<Error = Status>,
+    E: Conversion<Proto = T::Item>,
+{
+    type Item = E::Rust;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        self.inner.encode(E::into_proto(item), dst)
+    }
+}
+
+impl<T: fmt::Debug, E> fmt::Debug for ConvertEncoder<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConvertEncoder")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// A [`Decoder`] converting the messages decoded by the decoder `T` with
+/// `D`.
+pub struct ConvertDecoder<T, D> {
+    inner: T,
+    _pd: PhantomData<fn(D)>,
+}
+
+impl<T, D> Decoder for ConvertDecoder<T, D>
+where
+    T: Decoder<Error = Status>,
+    D: Conversion<Proto = T::Item>,
+{
+    type Item = D::Rust;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode(src)?.map(D::from_proto).transpose()
+    }
+}
+
+impl<T: fmt::Debug, D> fmt::Debug for ConvertDecoder<T, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConvertDecoder")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// Conversions of the well-known types to `chrono` types.
+#[cfg(feature = "chrono")]
+mod chrono {
+    use super::Conversion;
+    use crate::Status;
+    use chrono_lib::{DateTime, Duration, TimeZone, Utc};
+    use prost_types::{Duration as ProtoDuration, Timestamp};
+    use std::convert::TryFrom;
+
+    /// Converts `google.protobuf.Timestamp` to and from `chrono::DateTime<Utc>`.
+    #[derive(Debug)]
+    pub enum ChronoTimestamp {}
+
+    impl Conversion for ChronoTimestamp {
+        type Proto = Timestamp;
+        type Rust = DateTime<Utc>;
+
+        fn from_proto(proto: Timestamp) -> Result<DateTime<Utc>, Status> {
+            u32::try_from(proto.nanos)
+                .ok()
+                .and_then(|nanos| Utc.timestamp_opt(proto.seconds, nanos).single())
+                .ok_or_else(|| Status::invalid_argument("Timestamp out of range."))
+        }
+
+        fn into_proto(rust: DateTime<Utc>) -> Timestamp {
+            Timestamp {
+                seconds: rust.timestamp(),
+                nanos: rust.timestamp_subsec_nanos() as i32,
+            }
+        }
+    }
+
+    /// Converts `google.protobuf.Duration` to and from `chrono::Duration`.
+    #[derive(Debug)]
+    pub enum ChronoDuration {}
+
+    /// The range of `google.protobuf.Duration`, about 10,000 years.
+    const MAX_SECONDS: i64 = 315_576_000_000;
+
+    impl Conversion for ChronoDuration {
+        type Proto = ProtoDuration;
+        type Rust = Duration;
+
+        fn from_proto(proto: ProtoDuration) -> Result<Duration, Status> {
+            let valid = proto.seconds.abs() <= MAX_SECONDS
+                && proto.nanos.abs() < 1_000_000_000
+                && (proto.seconds == 0
+                    || proto.nanos == 0
+                    || (proto.seconds < 0) == (proto.nanos < 0));
+            if !valid {
+                return Err(Status::invalid_argument("Duration out of range."));
+            }
+
+            Ok(Duration::seconds(proto.seconds) + Duration::nanoseconds(proto.nanos.into()))
+        }
+
+        fn into_proto(rust: Duration) -> ProtoDuration {
+            let seconds = rust.num_seconds();
+            let nanos = (rust - Duration::seconds(seconds))
+                .num_nanoseconds()
+                .unwrap_or(0);
+
+            ProtoDuration {
+                seconds,
+                nanos: nanos as i32,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn timestamps() {
+            let proto = Timestamp {
+                seconds: 1_431_648_000,
+                nanos: 500,
+            };
+            let rust = ChronoTimestamp::from_proto(proto.clone()).unwrap();
+            let expected = Utc.with_ymd_and_hms(2015, 5, 15, 0, 0, 0).unwrap();
+            assert_eq!(rust, expected + Duration::nanoseconds(500));
+            assert_eq!(ChronoTimestamp::into_proto(rust), proto);
+
+            let before_epoch = Timestamp {
+                seconds: -1,
+                nanos: 999_999_999,
+            };
+            let rust = ChronoTimestamp::from_proto(before_epoch.clone()).unwrap();
+            assert_eq!(ChronoTimestamp::into_proto(rust), before_epoch);
+
+            let invalid = Timestamp {
+                seconds: 0,
+                nanos: -1,
+            };
+            assert!(ChronoTimestamp::from_proto(invalid).is_err());
+        }
+
+        #[test]
+        fn durations() {
+            for &(seconds, nanos) in &[(90, 500), (-90, -500), (0, -1), (MAX_SECONDS, 0)] {
+                let proto = ProtoDuration { seconds, nanos };
+                let rust = ChronoDuration::from_proto(proto.clone()).unwrap();
+                assert_eq!(ChronoDuration::into_proto(rust), proto);
+            }
+            assert_eq!(
+                ChronoDuration::from_proto(ProtoDuration {
+                    seconds: 1,
+                    nanos: 500_000_000
+                })
+                .unwrap(),
+                Duration::milliseconds(1_500)
+            );
+
+            for &(seconds, nanos) in &[(1, -1), (MAX_SECONDS + 1, 0), (0, 1_000_000_000)] {
+                assert!(ChronoDuration::from_proto(ProtoDuration { seconds, nanos }).is_err());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+pub use self::chrono::{ChronoDuration, ChronoTimestamp};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{BytesCodec, DecodeBuf};
+    use bytes::{Bytes, BytesMut};
+
+    /// Stands in for UTF-8 messages with `String`s.
+    enum Utf8 {}
+
+    impl Conversion for Utf8 {
+        type Proto = Bytes;
+        type Rust = String;
+
+        fn from_proto(proto: Bytes) -> Result<String, Status> {
+            String::from_utf8(proto.to_vec()).map_err(|e| Status::invalid_argument(e.to_string()))
+        }
+
+        fn into_proto(rust: String) -> Bytes {
+            rust.into()
+        }
+    }
+
+    fn decode(bytes: &'static [u8]) -> Result<Option<String>, Status> {
+        let mut codec = ConvertCodec::<BytesCodec, Utf8, Utf8>::default();
+        let mut buf = BytesMut::from(bytes);
+        codec
+            .decoder()
+            .decode(&mut DecodeBuf::new(&mut buf, bytes.len()))
+    }
+
+    #[test]
+    fn converts_messages() {
+        let mut codec = ConvertCodec::<BytesCodec, Utf8, Identity<Bytes>>::default();
+        let mut buf = BytesMut::new();
+        codec
+            .encoder()
+            .encode("hello".to_string(), &mut EncodeBuf::new(&mut buf))
+            .unwrap();
+        assert_eq!(&buf[..], b"hello");
+
+        assert_eq!(decode(b"hello").unwrap().unwrap(), "hello");
+        let status = decode(b"\xff").unwrap_err();
+        assert_eq!(status.code(), crate::Code::InvalidArgument);
+    }
+}
@@ -1,4 +1,9 @@
-use super::{DecodeBuf, Decoder};
+use super::{
+    checksum::Checksum,
+    compression::decompress,
+    pool::{BufferPool, PooledBuf},
+    CompressionEncoding, DecodeBuf, Decoder,
+};
 use crate::{body::BoxBody, metadata::MetadataMap, Code, Status};
 use bytes::{Buf, BufMut, BytesMut};
 use futures_core::Stream;
@@ -18,13 +23,26 @@ const BUFFER_SIZE: usize = 8 * 1024;
 ///
 /// This will wrap some inner [`Body`] and [`Decoder`] and provide an interface
 /// to fetch the message stream and trailing metadata
+///
+/// Messages are only decoded as they are polled, and more data is only read
+/// from the body once the buffered data holds no complete message. A slow
+/// consumer therefore stops reading from the body, which lets the HTTP/2
+/// flow-control window fill up and pushes back on the peer.
 pub struct Streaming<T> {
     decoder: Box<dyn Decoder<Item = T, Error = Status> + Send + Sync + 'static>,
     body: BoxBody,
     state: State,
     direction: Direction,
-    buf: BytesMut,
+    buf: PooledBuf,
     trailers: Option<MetadataMap>,
+    initial_metadata: Option<MetadataMap>,
+    decompress_buf: BytesMut,
+    encoding: Option<CompressionEncoding>,
+    max_message_size: Option<usize>,
+    yield_after_messages: Option<usize>,
+    messages_in_a_row: usize,
+    checksum: Option<Checksum>,
+    max_metadata_size: Option<usize>,
 }
 
 impl<T> Unpin for Streaming<T> {}
@@ -42,14 +60,36 @@ enum Direction {
     EmptyResponse,
 }
 
+/// Settings applied to the messages decoded by a [`Streaming`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DecodeSettings {
+    pub(crate) max_message_size: Option<usize>,
+    pub(crate) yield_after_messages: Option<usize>,
+    pub(crate) buffer_pool: Option<BufferPool>,
+    pub(crate) checksum: bool,
+    pub(crate) max_metadata_size: Option<usize>,
+}
+
 impl<T> Streaming<T> {
-    pub(crate) fn new_response<B, D>(decoder: D, body: B, status_code: StatusCode) -> Self
+    pub(crate) fn new_response<B, D>(
+        decoder: D,
+        body: B,
+        status_code: StatusCode,
+        encoding: Option<CompressionEncoding>,
+        settings: DecodeSettings,
+    ) -> Self
     where
         B: Body + Send + Sync + 'static,
         B::Error: Into<crate::Error>,
         D: Decoder<Item = T, Error = Status> + Send + Sync + 'static,
     {
-        Self::new(decoder, body, Direction::Response(status_code))
+        Self::new(
+            decoder,
+            body,
+            Direction::Response(status_code),
+            encoding,
+            settings,
+        )
     }
 
     pub(crate) fn new_empty<B, D>(decoder: D, body: B) -> Self
@@ -58,20 +98,53 @@ impl<T> Streaming<T> {
         B::Error: Into<crate::Error>,
         D: Decoder<Item = T, Error = Status> + Send + Sync + 'static,
     {
-        Self::new(decoder, body, Direction::EmptyResponse)
+        Self::new(
+            decoder,
+            body,
+            Direction::EmptyResponse,
+            None,
+            DecodeSettings::default(),
+        )
+    }
+
+    pub(crate) fn with_initial_metadata(self, initial_metadata: MetadataMap) -> Self {
+        Self {
+            initial_metadata: Some(initial_metadata),
+            ..self
+        }
     }
 
     #[doc(hidden)]
-    pub fn new_request<B, D>(decoder: D, body: B) -> Self
+    pub fn new_request<B, D>(decoder: D, body: B, encoding: Option<CompressionEncoding>) -> Self
+    where
+        B: Body + Send + Sync + 'static,
+        B::Error: Into<crate::Error>,
+        D: Decoder<Item = T, Error = Status> + Send + Sync + 'static,
+    {
+        Self::new_request_with(decoder, body, encoding, DecodeSettings::default())
+    }
+
+    pub(crate) fn new_request_with<B, D>(
+        decoder: D,
+        body: B,
+        encoding: Option<CompressionEncoding>,
+        settings: DecodeSettings,
+    ) -> Self
     where
         B: Body + Send + Sync + 'static,
         B::Error: Into<crate::Error>,
         D: Decoder<Item = T, Error = Status> + Send + Sync + 'static,
     {
-        Self::new(decoder, body, Direction::Request)
+        Self::new(decoder, body, Direction::Request, encoding, settings)
     }
 
-    fn new<B, D>(decoder: D, body: B, direction: Direction) -> Self
+    fn new<B, D>(
+        decoder: D,
+        body: B,
+        direction: Direction,
+        encoding: Option<CompressionEncoding>,
+        settings: DecodeSettings,
+    ) -> Self
     where
         B: Body + Send + Sync + 'static,
         B::Error: Into<crate::Error>,
@@ -82,8 +155,20 @@ impl<T> Streaming<T> {
             body: BoxBody::map_from(body),
             state: State::ReadHeader,
             direction,
-            buf: BytesMut::with_capacity(BUFFER_SIZE),
+            buf: PooledBuf::new(settings.buffer_pool.as_ref(), BUFFER_SIZE),
             trailers: None,
+            initial_metadata: None,
+            decompress_buf: BytesMut::new(),
+            encoding,
+            max_message_size: settings.max_message_size,
+            yield_after_messages: settings.yield_after_messages,
+            messages_in_a_row: 0,
+            checksum: if settings.checksum {
+                Some(Checksum::default())
+            } else {
+                None
+            },
+            max_metadata_size: settings.max_metadata_size,
         }
     }
 }
@@ -146,11 +231,43 @@ impl<T> Streaming<T> {
         // them manually.
         let map = future::poll_fn(|cx| Pin::new(&mut self.body).poll_trailers(cx))
             .await
-            .map_err(|e| Status::from_error(&e))?;
+            .map_err(|e| Status::from_error(e.into()))?;
+
+        if let Some(map) = &map {
+            crate::metadata::check_size(map, self.max_metadata_size)?;
+        }
 
         Ok(map.map(MetadataMap::from_headers))
     }
 
+    /// Get the initial metadata sent by the server.
+    ///
+    /// Only set on the response streams of clients. Calls resolve as soon as
+    /// the response headers arrive, so the initial metadata can be read
+    /// before any message is received, and stays available after the
+    /// stream is taken out of its `Response`.
+    ///
+    /// ```rust
+    /// # use tonic::{Streaming, Status};
+    /// # async fn initial_metadata_ex<T>(mut response: Streaming<T>) -> Result<(), Status> {
+    /// if let Some(metadata) = response.initial_metadata() {
+    ///     println!("{:?}", metadata.get("session-token"));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn initial_metadata(&self) -> Option<&MetadataMap> {
+        self.initial_metadata.as_ref()
+    }
+
+    /// The `grpc-encoding` the peer compresses messages with, if any.
+    ///
+    /// Messages are only decompressed when their compressed flag is set, so
+    /// a peer may still send some of them uncompressed.
+    pub fn encoding(&self) -> Option<CompressionEncoding> {
+        self.encoding
+    }
+
     fn decode_chunk(&mut self) -> Result<Option<T>, Status> {
         if let State::ReadHeader = self.state {
             if self.buf.remaining() < 5 {
@@ -160,11 +277,14 @@ impl<T> Streaming<T> {
             let is_compressed = match self.buf.get_u8() {
                 0 => false,
                 1 => {
-                    trace!("message compressed, compression not supported yet");
-                    return Err(Status::new(
-                        Code::Unimplemented,
-                        "Message compressed, compression not supported yet.".to_string(),
-                    ));
+                    if self.encoding.is_none() {
+                        trace!("message compressed, but no grpc-encoding was specified");
+                        return Err(Status::new(
+                            Code::Internal,
+                            "Protocol error: received message with compressed-flag but no grpc-encoding was specified.".to_string(),
+                        ));
+                    }
+                    true
                 }
                 f => {
                     trace!("unexpected compression flag");
@@ -175,6 +295,7 @@ impl<T> Streaming<T> {
                 }
             };
             let len = self.buf.get_u32() as usize;
+            self.check_message_size(len)?;
 
             self.state = State::ReadBody {
                 compression: is_compressed,
@@ -182,19 +303,47 @@ impl<T> Streaming<T> {
             }
         }
 
-        if let State::ReadBody { len, .. } = &self.state {
+        if let State::ReadBody { compression, len } = self.state {
             // if we haven't read enough of the message then return and keep
             // reading
-            if self.buf.remaining() < *len || self.buf.len() < *len {
+            if self.buf.remaining() < len || self.buf.len() < len {
                 return Ok(None);
             }
 
-            return match self
-                .decoder
-                .decode(&mut DecodeBuf::new(&mut self.buf, *len))
-            {
+            let checksum = self.checksum.map(|mut checksum| {
+                checksum.update(&self.buf[..len]);
+                checksum
+            });
+
+            let decoded = match self.encoding.filter(|_| compression) {
+                Some(encoding) => {
+                    self.decompress_buf.clear();
+                    if let Err(err) = decompress(
+                        encoding,
+                        &mut self.buf,
+                        &mut self.decompress_buf,
+                        len,
+                        self.max_message_size,
+                    ) {
+                        trace!("error decompressing message");
+                        return Err(Status::new(
+                            Code::Internal,
+                            format!("Error decompressing: {}", err),
+                        ));
+                    }
+
+                    let len = self.decompress_buf.len();
+                    self.check_message_size(len)?;
+                    self.decoder
+                        .decode(&mut DecodeBuf::new(&mut self.decompress_buf, len))
+                }
+                None => self.decoder.decode(&mut DecodeBuf::new(&mut self.buf, len)),
+            };
+
+            return match decoded {
                 Ok(Some(msg)) => {
                     self.state = State::ReadHeader;
+                    self.checksum = checksum;
                     Ok(Some(msg))
                 }
                 Ok(None) => Ok(None),
@@ -204,27 +353,61 @@ impl<T> Streaming<T> {
 
         Ok(None)
     }
+
+    fn check_message_size(&self, len: usize) -> Result<(), Status> {
+        match self.max_message_size {
+            Some(limit) if len > limit => {
+                trace!("message larger than the decoding limit");
+                Err(Status::new(
+                    Code::ResourceExhausted,
+                    format!("Received message larger than max ({} vs. {})", len, limit),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<T> Stream for Streaming<T> {
     type Item = Result<T, Status>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // Many messages may already be buffered, let other tasks run in
+        // between if the consumer asked for it.
+        if let Some(limit) = self.yield_after_messages {
+            if self.messages_in_a_row >= limit {
+                self.messages_in_a_row = 0;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        }
+
         loop {
             // FIXME: implement the ability to poll trailers when we _know_ that
             // the consumer of this stream will only poll for the first message.
             // This means we skip the poll_trailers step.
             match self.decode_chunk()? {
-                Some(item) => return Poll::Ready(Some(Ok(item))),
+                Some(item) => {
+                    self.messages_in_a_row += 1;
+                    return Poll::Ready(Some(Ok(item)));
+                }
                 None => (),
             }
 
-            let chunk = match ready!(Pin::new(&mut self.body).poll_data(cx)) {
+            let chunk = match Pin::new(&mut self.body).poll_data(cx) {
+                Poll::Ready(chunk) => chunk,
+                Poll::Pending => {
+                    self.messages_in_a_row = 0;
+                    return Poll::Pending;
+                }
+            };
+
+            let chunk = match chunk {
                 Some(Ok(d)) => Some(d),
                 Some(Err(e)) => {
                     let err: crate::Error = e.into();
                     debug!("decoder inner stream error: {:?}", err);
-                    let status = Status::from_error(&*err);
+                    let status = Status::from_error(err);
                     Err(status)?;
                     break;
                 }
@@ -257,19 +440,40 @@ impl<T> Stream for Streaming<T> {
             }
         }
 
-        if let Direction::Response(status) = self.direction {
+        let expects_trailers = match self.direction {
+            Direction::Response(_) => true,
+            Direction::Request => self.checksum.is_some(),
+            Direction::EmptyResponse => false,
+        };
+
+        if expects_trailers {
             match ready!(Pin::new(&mut self.body).poll_trailers(cx)) {
                 Ok(trailer) => {
-                    if let Err(e) = crate::status::infer_grpc_status(trailer.as_ref(), status) {
-                        return Some(Err(e)).into();
-                    } else {
-                        self.trailers = trailer.map(MetadataMap::from_headers);
+                    if let Some(trailer) = &trailer {
+                        if let Err(e) = crate::metadata::check_size(trailer, self.max_metadata_size)
+                        {
+                            return Some(Err(e)).into();
+                        }
+                    }
+
+                    if let Direction::Response(status) = self.direction {
+                        if let Err(e) = crate::status::infer_grpc_status(trailer.as_ref(), status) {
+                            return Some(Err(e)).into();
+                        }
                     }
+
+                    if let Some(checksum) = self.checksum {
+                        if let Err(e) = checksum.validate(trailer.as_ref()) {
+                            return Some(Err(e)).into();
+                        }
+                    }
+
+                    self.trailers = trailer.map(MetadataMap::from_headers);
                 }
                 Err(e) => {
                     let err: crate::Error = e.into();
                     debug!("decoder inner trailers error: {:?}", err);
-                    let status = Status::from_error(&*err);
+                    let status = Status::from_error(err);
                     return Some(Err(status)).into();
                 }
             }
@@ -1,4 +1,9 @@
-use super::{EncodeBuf, Encoder};
+use super::{
+    checksum::{Checksum, CHECKSUM_HEADER},
+    compression::compress,
+    pool::{BufferPool, PooledBuf},
+    CompressionEncoding, CompressionSettings, EncodeBuf, Encoder,
+};
 use crate::{Code, Status};
 use bytes::{BufMut, Bytes, BytesMut};
 use futures_core::{Stream, TryStream};
@@ -8,6 +13,7 @@ use http_body::Body;
 use pin_project::pin_project;
 use std::{
     pin::Pin,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 
@@ -16,57 +22,76 @@ const BUFFER_SIZE: usize = 8 * 1024;
 pub(crate) fn encode_server<T, U>(
     encoder: T,
     source: U,
+    compression: Option<(CompressionEncoding, CompressionSettings)>,
+    max_message_size: Option<usize>,
+    pool: Option<BufferPool>,
+    checksum: bool,
 ) -> EncodeBody<impl Stream<Item = Result<Bytes, Status>>>
 where
     T: Encoder<Error = Status> + Send + Sync + 'static,
     T::Item: Send + Sync,
     U: Stream<Item = Result<T::Item, Status>> + Send + Sync + 'static,
 {
-    let stream = encode(encoder, source).into_stream();
-    EncodeBody::new_server(stream)
+    let stream = encode(encoder, source, compression, max_message_size, pool).into_stream();
+    EncodeBody::new_server(stream, checksum)
 }
 
 pub(crate) fn encode_client<T, U>(
     encoder: T,
     source: U,
+    compression: Option<(CompressionEncoding, CompressionSettings)>,
+    max_message_size: Option<usize>,
+    pool: Option<BufferPool>,
+    checksum: bool,
+    error: EncodeError,
 ) -> EncodeBody<impl Stream<Item = Result<Bytes, Status>>>
 where
     T: Encoder<Error = Status> + Send + Sync + 'static,
     T::Item: Send + Sync,
     U: Stream<Item = T::Item> + Send + Sync + 'static,
 {
-    let stream = encode(encoder, source.map(|x| Ok(x))).into_stream();
-    EncodeBody::new_client(stream)
+    let stream = encode(
+        encoder,
+        source.map(|x| Ok(x)),
+        compression,
+        max_message_size,
+        pool,
+    )
+    .into_stream();
+    EncodeBody::new_client(stream, checksum, error)
 }
 
-fn encode<T, U>(mut encoder: T, source: U) -> impl TryStream<Ok = Bytes, Error = Status>
+fn encode<T, U>(
+    mut encoder: T,
+    source: U,
+    compression: Option<(CompressionEncoding, CompressionSettings)>,
+    max_message_size: Option<usize>,
+    pool: Option<BufferPool>,
+) -> impl TryStream<Ok = Bytes, Error = Status>
 where
     T: Encoder<Error = Status>,
     U: Stream<Item = Result<T::Item, Status>>,
 {
     async_stream::stream! {
-        let mut buf = BytesMut::with_capacity(BUFFER_SIZE);
+        let mut buf = PooledBuf::new(pool.as_ref(), BUFFER_SIZE);
+        let mut uncompressed_buf = if compression.is_some() {
+            BytesMut::with_capacity(BUFFER_SIZE)
+        } else {
+            BytesMut::new()
+        };
         futures_util::pin_mut!(source);
 
         loop {
             match source.next().await {
                 Some(Ok(item)) => {
-                    buf.reserve(5);
-                    unsafe {
-                        buf.advance_mut(5);
-                    }
-                    encoder.encode(item, &mut EncodeBuf::new(&mut buf)).map_err(drop).unwrap();
-
-                    // now that we know length, we can write the header
-                    let len = buf.len() - 5;
-                    assert!(len <= std::u32::MAX as usize);
-                    {
-                        let mut buf = &mut buf[..5];
-                        buf.put_u8(0); // byte must be 0, reserve doesn't auto-zero
-                        buf.put_u32(len as u32);
-                    }
-
-                    yield Ok(buf.split_to(len + 5).freeze());
+                    yield encode_item(
+                        &mut encoder,
+                        &mut buf,
+                        &mut uncompressed_buf,
+                        compression,
+                        max_message_size,
+                        item,
+                    );
                 },
                 Some(Err(status)) => yield Err(status),
                 None => break,
@@ -75,9 +100,124 @@ where
     }
 }
 
+fn encode_item<T>(
+    encoder: &mut T,
+    buf: &mut BytesMut,
+    uncompressed_buf: &mut BytesMut,
+    compression: Option<(CompressionEncoding, CompressionSettings)>,
+    max_message_size: Option<usize>,
+    item: T::Item,
+) -> Result<Bytes, Status>
+where
+    T: Encoder<Error = Status>,
+{
+    let encoded_len = encoder.encoded_len(&item);
+    if let Some(len) = encoded_len {
+        // Reject oversized messages before spending time encoding them.
+        check_message_size(len, max_message_size)?;
+        assert!(len <= u32::MAX as usize);
+    }
+
+    buf.reserve(5 + encoded_len.unwrap_or(0));
+    match encoded_len.filter(|_| compression.is_none()) {
+        // The length is known upfront, so the header can be written right away.
+        Some(len) => {
+            buf.put_u8(0);
+            buf.put_u32(len as u32);
+        }
+        None => unsafe {
+            buf.advance_mut(5);
+        },
+    }
+
+    let compressed = if let Some((encoding, settings)) = compression {
+        uncompressed_buf.clear();
+        uncompressed_buf.reserve(encoded_len.unwrap_or(0));
+        encoder
+            .encode(item, &mut EncodeBuf::new(uncompressed_buf))
+            .map_err(drop)
+            .unwrap();
+
+        let uncompressed_len = uncompressed_buf.len();
+        if let Err(status) = check_message_size(uncompressed_len, max_message_size) {
+            buf.clear();
+            return Err(status);
+        }
+
+        if uncompressed_len < settings.threshold {
+            buf.extend_from_slice(&uncompressed_buf[..]);
+            false
+        } else {
+            if let Err(err) = compress(
+                encoding,
+                settings.level,
+                uncompressed_buf,
+                buf,
+                uncompressed_len,
+            ) {
+                buf.clear();
+                return Err(Status::internal(format!("Error compressing: {}", err)));
+            }
+            true
+        }
+    } else {
+        encoder
+            .encode(item, &mut EncodeBuf::new(buf))
+            .map_err(drop)
+            .unwrap();
+
+        if let Err(status) = check_message_size(buf.len() - 5, max_message_size) {
+            buf.clear();
+            return Err(status);
+        }
+
+        false
+    };
+
+    // now that we know length, we can write the header, unless it was
+    // already written from an accurate length hint
+    let len = buf.len() - 5;
+    assert!(len <= u32::MAX as usize);
+    if compression.is_some() || encoded_len != Some(len) {
+        let mut buf = &mut buf[..5];
+        // byte must be set explicitly, reserve doesn't auto-zero
+        buf.put_u8(compressed as u8);
+        buf.put_u32(len as u32);
+    }
+
+    Ok(buf.split_to(len + 5).freeze())
+}
+
+fn check_message_size(len: usize, max_message_size: Option<usize>) -> Result<(), Status> {
+    match max_message_size {
+        Some(limit) if len > limit => Err(Status::new(
+            Code::OutOfRange,
+            format!("Sending message larger than max ({} vs. {})", len, limit),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The error that made a client request body abort.
+///
+/// The transport only reports the stream reset caused by the aborted body,
+/// so the client looks the original status up here instead.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EncodeError(Arc<Mutex<Option<Status>>>);
+
+impl EncodeError {
+    pub(crate) fn take(&self) -> Option<Status> {
+        self.0.lock().unwrap().take()
+    }
+
+    fn set(&self, status: &Status) {
+        *self.0.lock().unwrap() = Some(status.clone());
+    }
+}
+
 #[derive(Debug)]
 enum Role {
-    Client,
+    Client(EncodeError),
     Server,
 }
 
@@ -88,25 +228,36 @@ pub(crate) struct EncodeBody<S> {
     inner: S,
     error: Option<Status>,
     role: Role,
+    checksum: Option<Checksum>,
 }
 
 impl<S> EncodeBody<S>
 where
     S: Stream<Item = Result<Bytes, Status>> + Send + Sync + 'static,
 {
-    pub(crate) fn new_client(inner: S) -> Self {
+    pub(crate) fn new_client(inner: S, checksum: bool, error: EncodeError) -> Self {
         Self {
             inner,
             error: None,
-            role: Role::Client,
+            role: Role::Client(error),
+            checksum: if checksum {
+                Some(Checksum::default())
+            } else {
+                None
+            },
         }
     }
 
-    pub(crate) fn new_server(inner: S) -> Self {
+    pub(crate) fn new_server(inner: S, checksum: bool) -> Self {
         Self {
             inner,
             error: None,
             role: Role::Server,
+            checksum: if checksum {
+                Some(Checksum::default())
+            } else {
+                None
+            },
         }
     }
 }
@@ -128,9 +279,18 @@ where
     ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
         let mut self_proj = self.project();
         match ready!(self_proj.inner.try_poll_next_unpin(cx)) {
-            Some(Ok(d)) => Some(Ok(d)).into(),
+            Some(Ok(d)) => {
+                if let Some(checksum) = self_proj.checksum {
+                    // Each item is a single frame, skip its header.
+                    checksum.update(&d[5..]);
+                }
+                Some(Ok(d)).into()
+            }
             Some(Err(status)) => match self_proj.role {
-                Role::Client => Some(Err(status)).into(),
+                Role::Client(error) => {
+                    error.set(&status);
+                    Some(Err(status)).into()
+                }
                 Role::Server => {
                     *self_proj.error = Some(status);
                     None.into()
@@ -144,18 +304,25 @@ where
         self: Pin<&mut Self>,
         _cx: &mut Context<'_>,
     ) -> Poll<Result<Option<HeaderMap>, Status>> {
-        match self.role {
-            Role::Client => Poll::Ready(Ok(None)),
+        let self_proj = self.project();
+        let mut trailers = match self_proj.role {
+            Role::Client(_) if self_proj.checksum.is_none() => return Poll::Ready(Ok(None)),
+            Role::Client(_) => HeaderMap::new(),
             Role::Server => {
-                let self_proj = self.project();
                 let status = if let Some(status) = self_proj.error.take() {
                     status
                 } else {
                     Status::new(Code::Ok, "")
                 };
 
-                Poll::Ready(Ok(Some(status.to_header_map()?)))
+                status.to_header_map()?
             }
+        };
+
+        if let Some(checksum) = self_proj.checksum {
+            trailers.insert(CHECKSUM_HEADER, checksum.to_header_value());
         }
+
+        Poll::Ready(Ok(Some(trailers)))
     }
 }
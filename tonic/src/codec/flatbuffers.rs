@@ -0,0 +1,149 @@
+use super::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use crate::Status;
+use bytes::{Buf, BufMut, Bytes};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A finished FlatBuffers message whose root table is a `T`.
+///
+/// FlatBuffers messages are read in place, so this only holds on to the
+/// serialized bytes. Build one from the data of a finished
+/// `flatbuffers::FlatBufferBuilder` and access the root table of a received
+/// message with `flatbuffers::get_root::<T>(message.as_bytes())`.
+pub struct FlatBuffer<T> {
+    buf: Bytes,
+    _pd: PhantomData<fn() -> T>,
+}
+
+impl<T> FlatBuffer<T> {
+    /// Create a message from the serialized bytes of a finished buffer.
+    pub fn new(buf: impl Into<Bytes>) -> Self {
+        Self {
+            buf: buf.into(),
+            _pd: PhantomData,
+        }
+    }
+
+    /// Get the serialized bytes of this message.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..]
+    }
+
+    /// Consumes `self`, returning the serialized bytes of this message.
+    pub fn into_bytes(self) -> Bytes {
+        self.buf
+    }
+}
+
+impl<T> Clone for FlatBuffer<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.buf.clone())
+    }
+}
+
+impl<T> fmt::Debug for FlatBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FlatBuffer").field(&self.buf).finish()
+    }
+}
+
+/// A [`Codec`] that implements `application/grpc+flatbuffers`.
+///
+/// It exchanges [`FlatBuffer`] messages and is wire compatible with the gRPC
+/// services `flatc` generates for other languages. Received messages are
+/// never copied nor parsed, their tables are read in place.
+#[derive(Debug, Clone)]
+pub struct FlatBuffersCodec<T, U> {
+    _pd: PhantomData<(T, U)>,
+}
+
+impl<T, U> Default for FlatBuffersCodec<T, U> {
+    fn default() -> Self {
+        Self { _pd: PhantomData }
+    }
+}
+
+impl<T, U> Codec for FlatBuffersCodec<T, U>
+where
+    T: 'static,
+    U: 'static,
+{
+    type Encode = FlatBuffer<T>;
+    type Decode = FlatBuffer<U>;
+
+    type Encoder = FlatBuffersEncoder<T>;
+    type Decoder = FlatBuffersDecoder<U>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        FlatBuffersEncoder(PhantomData)
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        FlatBuffersDecoder(PhantomData)
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/grpc+flatbuffers"
+    }
+}
+
+/// A [`Encoder`] that knows how to encode a [`FlatBuffer<T>`].
+#[derive(Debug, Clone, Default)]
+pub struct FlatBuffersEncoder<T>(PhantomData<fn() -> T>);
+
+impl<T> Encoder for FlatBuffersEncoder<T> {
+    type Item = FlatBuffer<T>;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, buf: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        buf.put(item.buf);
+        Ok(())
+    }
+
+    fn encoded_len(&self, item: &Self::Item) -> Option<usize> {
+        Some(item.buf.len())
+    }
+}
+
+/// A [`Decoder`] that knows how to decode a [`FlatBuffer<U>`].
+#[derive(Debug, Clone, Default)]
+pub struct FlatBuffersDecoder<U>(PhantomData<fn() -> U>);
+
+impl<U> Decoder for FlatBuffersDecoder<U> {
+    type Item = FlatBuffer<U>;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(Some(FlatBuffer::new(buf.to_bytes())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+
+    struct Monster;
+
+    #[test]
+    fn round_trip() {
+        let mut codec = FlatBuffersCodec::<Monster, Monster>::default();
+        let payload = &b"\x0c\x00\x00\x00\x08\x00\x0c\x00"[..];
+
+        let mut buf = BytesMut::new();
+        codec
+            .encoder()
+            .encode(FlatBuffer::new(payload), &mut EncodeBuf::new(&mut buf))
+            .unwrap();
+        assert_eq!(&buf[..], payload);
+
+        let len = buf.len();
+        let decoded = codec
+            .decoder()
+            .decode(&mut DecodeBuf::new(&mut buf, len))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.as_bytes(), payload);
+        assert!(buf.is_empty());
+    }
+}
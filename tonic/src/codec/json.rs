@@ -0,0 +1,131 @@
+use super::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use crate::{Code, Status};
+use bytes::{buf::BufMutExt, Buf};
+use serde::{de::DeserializeOwned, Serialize};
+use std::marker::PhantomData;
+
+/// A [`Codec`] that implements `application/grpc+json` via the serde library.
+///
+/// Messages are framed like any other gRPC message, but their payload is the
+/// JSON representation of `T` and `U`.
+#[derive(Debug, Clone)]
+pub struct JsonCodec<T, U> {
+    _pd: PhantomData<(T, U)>,
+}
+
+impl<T, U> Default for JsonCodec<T, U> {
+    fn default() -> Self {
+        Self { _pd: PhantomData }
+    }
+}
+
+impl<T, U> Codec for JsonCodec<T, U>
+where
+    T: Serialize + Send + Sync + 'static,
+    U: DeserializeOwned + Send + Sync + 'static,
+{
+    type Encode = T;
+    type Decode = U;
+
+    type Encoder = JsonEncoder<T>;
+    type Decoder = JsonDecoder<U>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        JsonEncoder(PhantomData)
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        JsonDecoder(PhantomData)
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/grpc+json"
+    }
+}
+
+/// A [`Encoder`] that knows how to encode `T` as JSON.
+#[derive(Debug, Clone, Default)]
+pub struct JsonEncoder<T>(PhantomData<T>);
+
+impl<T: Serialize> Encoder for JsonEncoder<T> {
+    type Item = T;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, buf: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        serde_json::to_writer(buf.writer(), &item)
+            .map_err(|error| Status::new(Code::Internal, error.to_string()))
+    }
+}
+
+/// A [`Decoder`] that knows how to decode `U` from JSON.
+#[derive(Debug, Clone, Default)]
+pub struct JsonDecoder<U>(PhantomData<U>);
+
+impl<U: DeserializeOwned> Decoder for JsonDecoder<U> {
+    type Item = U;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        // The whole message is contiguous, so it can be parsed in place.
+        let len = buf.remaining();
+        let item = serde_json::from_slice(buf.bytes())
+            .map_err(|error| Status::new(Code::Internal, error.to_string()))?;
+        buf.advance(len);
+
+        Ok(Some(item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        name: String,
+        values: Vec<u32>,
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut codec = JsonCodec::<Message, Message>::default();
+        let msg = Message {
+            name: "tonic".to_string(),
+            values: vec![1, 2, 3],
+        };
+
+        let mut buf = BytesMut::new();
+        codec
+            .encoder()
+            .encode(msg, &mut EncodeBuf::new(&mut buf))
+            .unwrap();
+        assert_eq!(&buf[..], &br#"{"name":"tonic","values":[1,2,3]}"#[..]);
+
+        let len = buf.len();
+        let decoded = codec
+            .decoder()
+            .decode(&mut DecodeBuf::new(&mut buf, len))
+            .unwrap();
+        assert_eq!(
+            decoded,
+            Some(Message {
+                name: "tonic".to_string(),
+                values: vec![1, 2, 3],
+            })
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn invalid_json_is_internal() {
+        let mut buf = BytesMut::from(&b"{\"name\":"[..]);
+        let len = buf.len();
+        let status = JsonCodec::<Message, Message>::default()
+            .decoder()
+            .decode(&mut DecodeBuf::new(&mut buf, len))
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Internal);
+    }
+}
@@ -0,0 +1,163 @@
+use super::{Codec, DecodeBuf, Decoder};
+use crate::Status;
+use bytes::{Buf, Bytes};
+use std::fmt;
+
+/// A received message whose decoding is deferred until it is asked for.
+///
+/// It holds the raw bytes of the message, so routers can inspect a single
+/// field with a small partial decode through [`Lazy::decode_with`], and only
+/// pay for the full [`Lazy::decode`] when they need the whole message.
+pub struct Lazy<T> {
+    bytes: Bytes,
+    decoder: Box<dyn Decoder<Item = T, Error = Status> + Send + Sync + 'static>,
+}
+
+impl<T> Lazy<T> {
+    /// Get the raw bytes of the message.
+    pub fn bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+
+    /// Consumes `self`, returning the raw bytes of the message.
+    pub fn into_bytes(self) -> Bytes {
+        self.bytes
+    }
+
+    /// Decode the whole message with the decoder of the method's codec.
+    pub fn decode(mut self) -> Result<T, Status> {
+        let len = self.bytes.len();
+        self.decoder
+            .decode(&mut DecodeBuf::from_bytes(&mut self.bytes, len))?
+            .ok_or_else(|| Status::internal("Missing message."))
+    }
+
+    /// Decode the message with another `decoder`, typically one for a
+    /// message type declaring only the few fields of interest.
+    ///
+    /// The raw bytes are left untouched, so the message can still be fully
+    /// decoded or forwarded afterwards.
+    pub fn decode_with<D>(&self, mut decoder: D) -> Result<D::Item, Status>
+    where
+        D: Decoder<Error = Status>,
+    {
+        let mut bytes = self.bytes.clone();
+        let len = bytes.len();
+        decoder
+            .decode(&mut DecodeBuf::from_bytes(&mut bytes, len))?
+            .ok_or_else(|| Status::internal("Missing message."))
+    }
+}
+
+impl<T> fmt::Debug for Lazy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lazy")
+            .field("len", &self.bytes.len())
+            .finish()
+    }
+}
+
+/// A [`Codec`] that wraps another codec and defers decoding messages.
+///
+/// Messages are encoded by the inner codec, while received messages are
+/// handed out as [`Lazy`] values holding their raw bytes.
+#[derive(Debug, Clone, Default)]
+pub struct LazyCodec<C> {
+    inner: C,
+}
+
+impl<C> LazyCodec<C> {
+    /// Create a codec deferring the decoding of the messages of `inner`.
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+}
+
+impl<C> Codec for LazyCodec<C>
+where
+    C: Codec + Send + Sync + 'static,
+{
+    type Encode = C::Encode;
+    type Decode = Lazy<C::Decode>;
+
+    type Encoder = C::Encoder;
+    type Decoder = LazyDecoder<C>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        self.inner.encoder()
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        LazyDecoder {
+            codec: C::default(),
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        self.inner.content_type()
+    }
+}
+
+/// A [`Decoder`] that splits off the raw bytes of messages and defers their
+/// decoding to the decoder of the codec `C`.
+#[derive(Debug, Clone, Default)]
+pub struct LazyDecoder<C> {
+    codec: C,
+}
+
+impl<C> Decoder for LazyDecoder<C>
+where
+    C: Codec,
+{
+    type Item = Lazy<C::Decode>;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(Some(Lazy {
+            bytes: buf.to_bytes(),
+            decoder: Box::new(self.codec.decoder()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{BytesCodec, EncodeBuf, Encoder};
+    use bytes::{BufMut, BytesMut};
+
+    #[derive(Debug, Default)]
+    struct FirstByte;
+
+    impl Decoder for FirstByte {
+        type Item = u8;
+        type Error = Status;
+
+        fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+            Ok(Some(buf.get_u8()))
+        }
+    }
+
+    #[test]
+    fn defers_decoding() {
+        let mut codec = LazyCodec::<BytesCodec>::default();
+
+        let mut buf = BytesMut::new();
+        codec
+            .encoder()
+            .encode(Bytes::from_static(b"hello"), &mut EncodeBuf::new(&mut buf))
+            .unwrap();
+        buf.put(&b"world"[..]);
+
+        let lazy = codec
+            .decoder()
+            .decode(&mut DecodeBuf::new(&mut buf, 5))
+            .unwrap()
+            .unwrap();
+        assert_eq!(&buf[..], b"world");
+
+        assert_eq!(lazy.decode_with(FirstByte).unwrap(), b'h');
+        assert_eq!(lazy.bytes(), &Bytes::from_static(b"hello"));
+        assert_eq!(lazy.decode().unwrap(), Bytes::from_static(b"hello"));
+    }
+}
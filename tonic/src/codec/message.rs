@@ -0,0 +1,164 @@
+use super::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use crate::Status;
+use std::marker::PhantomData;
+
+/// A message that knows how to encode itself to, and decode itself from, its
+/// wire format.
+///
+/// This trait decouples [`MessageCodec`] from any particular message crate.
+/// Implement it for messages generated by `rust-protobuf`, `quick-protobuf`
+/// or any other generator to use them with tonic:
+///
+/// ```ignore
+/// impl tonic::codec::Message for HelloRequest {
+///     fn encode(&self, buf: &mut EncodeBuf<'_>) -> Result<(), Status> {
+///         self.write_to_writer(&mut buf.writer())
+///             .map_err(|e| Status::internal(e.to_string()))
+///     }
+///
+///     fn decode(buf: &mut DecodeBuf<'_>) -> Result<Self, Status> {
+///         protobuf::parse_from_reader(&mut buf.reader())
+///             .map_err(|e| Status::internal(e.to_string()))
+///     }
+/// }
+/// ```
+pub trait Message: Sized {
+    /// Encodes the message into the provided buffer.
+    fn encode(&self, buf: &mut EncodeBuf<'_>) -> Result<(), Status>;
+
+    /// The exact number of bytes the message encodes to, if it is cheap to
+    /// compute. Defaults to `None`.
+    fn encoded_len(&self) -> Option<usize> {
+        None
+    }
+
+    /// Decodes a message from the provided buffer.
+    ///
+    /// The buffer contains exactly the bytes of one message, which should all
+    /// be consumed.
+    fn decode(buf: &mut DecodeBuf<'_>) -> Result<Self, Status>;
+}
+
+/// A [`Codec`] for messages implementing the [`Message`] trait.
+///
+/// Use it with `tonic_build::Builder::codec_path("tonic::codec::MessageCodec")`
+/// to generate clients and servers for messages that do not come from prost.
+#[derive(Debug, Clone)]
+pub struct MessageCodec<T, U> {
+    _pd: PhantomData<(T, U)>,
+}
+
+impl<T, U> Default for MessageCodec<T, U> {
+    fn default() -> Self {
+        Self { _pd: PhantomData }
+    }
+}
+
+impl<T, U> Codec for MessageCodec<T, U>
+where
+    T: Message + Send + Sync + 'static,
+    U: Message + Send + Sync + 'static,
+{
+    type Encode = T;
+    type Decode = U;
+
+    type Encoder = MessageEncoder<T>;
+    type Decoder = MessageDecoder<U>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        MessageEncoder(PhantomData)
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        MessageDecoder(PhantomData)
+    }
+}
+
+/// A [`Encoder`] that knows how to encode any [`Message`] `T`.
+#[derive(Debug, Clone, Default)]
+pub struct MessageEncoder<T>(PhantomData<T>);
+
+impl<T: Message> Encoder for MessageEncoder<T> {
+    type Item = T;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, buf: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode(buf)
+    }
+
+    fn encoded_len(&self, item: &Self::Item) -> Option<usize> {
+        item.encoded_len()
+    }
+}
+
+/// A [`Decoder`] that knows how to decode any [`Message`] `U`.
+#[derive(Debug, Clone, Default)]
+pub struct MessageDecoder<U>(PhantomData<U>);
+
+impl<U: Message> Decoder for MessageDecoder<U> {
+    type Item = U;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        U::decode(buf).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+    use bytes::{Buf, BufMut, BytesMut};
+
+    /// A message with a hand written wire format, standing in for one
+    /// generated by a crate other than prost.
+    #[derive(Debug, PartialEq)]
+    struct Pair(u32, u32);
+
+    impl Message for Pair {
+        fn encode(&self, buf: &mut EncodeBuf<'_>) -> Result<(), Status> {
+            buf.put_u32(self.0);
+            buf.put_u32(self.1);
+            Ok(())
+        }
+
+        fn decode(buf: &mut DecodeBuf<'_>) -> Result<Self, Status> {
+            if buf.remaining() != 8 {
+                return Err(Status::internal("invalid pair length"));
+            }
+
+            Ok(Pair(buf.get_u32(), buf.get_u32()))
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut codec = MessageCodec::<Pair, Pair>::default();
+
+        let mut buf = BytesMut::new();
+        codec
+            .encoder()
+            .encode(Pair(1, 2), &mut EncodeBuf::new(&mut buf))
+            .unwrap();
+        assert_eq!(&buf[..], &[0, 0, 0, 1, 0, 0, 0, 2][..]);
+
+        let len = buf.len();
+        let decoded = codec
+            .decoder()
+            .decode(&mut DecodeBuf::new(&mut buf, len))
+            .unwrap();
+        assert_eq!(decoded, Some(Pair(1, 2)));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn decode_error_is_surfaced() {
+        let mut buf = BytesMut::from(&[0, 0, 0, 1][..]);
+        let len = buf.len();
+        let status = MessageCodec::<Pair, Pair>::default()
+            .decoder()
+            .decode(&mut DecodeBuf::new(&mut buf, len))
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Internal);
+    }
+}
@@ -1,27 +1,71 @@
 //! Generic encoding and decoding.
 //!
 //! This module contains the generic `Codec`, `Encoder` and `Decoder` traits
-//! and a protobuf codec based on prost.
+//! and a protobuf codec based on prost. A JSON codec based on serde is
+//! available with the `json` feature, a FlatBuffers codec with the
+//! `flatbuffers` feature, and messages from other crates can be
+//! plugged in through the [`Message`] trait and [`MessageCodec`]. The
+//! [`BytesCodec`] passes payloads through untouched, and [`LazyCodec`]
+//! defers decoding received messages. [`ConvertCodec`] converts messages to
+//! and from other Rust types through a [`Conversion`], like the conversions of
+//! the well-known types to `chrono` types available with the `chrono`
+//! feature.
 
 mod buffer;
+mod checksum;
+mod compression;
+mod convert;
 mod decode;
 mod encode;
+#[cfg(feature = "flatbuffers")]
+mod flatbuffers;
+#[cfg(feature = "json")]
+mod json;
+mod lazy;
+mod message;
+mod pool;
 #[cfg(feature = "prost")]
 mod prost;
+mod raw;
 
 #[cfg(test)]
 mod tests;
 
 use std::io;
 
+pub use self::compression::{
+    CompressionEncoding, CompressionLevel, Compressor, CustomEncoding, EnabledCompressionEncodings,
+    RegisterError,
+};
+pub(crate) use self::compression::{
+    CompressionOverride, CompressionSettings, ACCEPT_ENCODING_HEADER, ENCODING_HEADER,
+};
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+pub use self::convert::{ChronoDuration, ChronoTimestamp};
+pub use self::convert::{Conversion, ConvertCodec, ConvertDecoder, ConvertEncoder, Identity};
+pub(crate) use self::decode::DecodeSettings;
 pub use self::decode::Streaming;
-pub(crate) use self::encode::{encode_client, encode_server};
+pub(crate) use self::encode::{encode_client, encode_server, EncodeError};
+#[cfg(feature = "flatbuffers")]
+#[cfg_attr(docsrs, doc(cfg(feature = "flatbuffers")))]
+pub use self::flatbuffers::{FlatBuffer, FlatBuffersCodec, FlatBuffersDecoder, FlatBuffersEncoder};
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub use self::json::{JsonCodec, JsonDecoder, JsonEncoder};
+pub use self::lazy::{Lazy, LazyCodec, LazyDecoder};
+pub use self::message::{Message, MessageCodec, MessageDecoder, MessageEncoder};
+pub use self::pool::BufferPool;
 #[cfg(feature = "prost")]
 #[cfg_attr(docsrs, doc(cfg(feature = "prost")))]
-pub use self::prost::ProstCodec;
+pub use self::prost::{ProstCodec, ProstDecoder, ProstEncoder};
+pub use self::raw::{BytesCodec, BytesDecoder, BytesEncoder};
 use crate::Status;
 pub use buffer::{DecodeBuf, EncodeBuf};
 
+/// The default maximum size of the messages clients and servers decode.
+pub(crate) const DEFAULT_MAX_DECODING_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
 /// Trait that knows how to encode and decode gRPC messages.
 pub trait Codec: Default {
     /// The encodable message.
@@ -38,6 +82,43 @@ pub trait Codec: Default {
     fn encoder(&mut self) -> Self::Encoder;
     /// Fetch the decoder.
     fn decoder(&mut self) -> Self::Decoder;
+
+    /// The `content-type` sent with the requests and responses using this
+    /// codec.
+    ///
+    /// Defaults to `application/grpc`.
+    fn content_type(&self) -> &'static str {
+        "application/grpc"
+    }
+}
+
+/// Creates the codec of each call of the clients and servers built with
+/// their generated `with_codec` constructors.
+///
+/// Clients call `make_codec` with the request and response types of a
+/// method, servers with its response and request types. Implementing it for
+/// every message type, usually generically, replaces at runtime the codec
+/// chosen when generating the code.
+///
+/// Every `Codec` creates clones of itself, so a codec of the message types
+/// of a service can be passed as is.
+pub trait MakeCodec<T, U> {
+    /// The codec created.
+    type Codec: Codec<Encode = T, Decode = U> + Send + Sync + 'static;
+
+    /// Create the codec of a call.
+    fn make_codec(&self) -> Self::Codec;
+}
+
+impl<C> MakeCodec<C::Encode, C::Decode> for C
+where
+    C: Codec + Clone + Send + Sync + 'static,
+{
+    type Codec = C;
+
+    fn make_codec(&self) -> Self::Codec {
+        self.clone()
+    }
 }
 
 /// Encodes gRPC message types
@@ -52,6 +133,16 @@ pub trait Encoder {
 
     /// Encodes a message into the provided buffer.
     fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error>;
+
+    /// The exact number of bytes `item` encodes to, if it is cheap to
+    /// compute.
+    ///
+    /// The buffer is then reserved and the frame header written upfront,
+    /// instead of growing the buffer while encoding. Defaults to `None`.
+    fn encoded_len(&self, item: &Self::Item) -> Option<usize> {
+        let _ = item;
+        None
+    }
 }
 
 /// Decodes gRPC message types
@@ -50,6 +50,10 @@ impl<T: Message> Encoder for ProstEncoder<T> {
 
         Ok(())
     }
+
+    fn encoded_len(&self, item: &Self::Item) -> Option<usize> {
+        Some(item.encoded_len())
+    }
 }
 
 /// A [`Decoder`] that knows how to decode `U`.
@@ -0,0 +1,97 @@
+use super::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use crate::Status;
+use bytes::{Buf, BufMut, Bytes};
+
+/// A [`Codec`] that passes message payloads through as raw [`Bytes`].
+///
+/// Messages are neither decoded nor encoded, only the gRPC framing is
+/// handled. This makes it possible to build gRPC aware proxies, recorders
+/// or fuzzers without knowing the message types, by pairing it with
+/// [`client::Grpc`] and [`server::Grpc`].
+///
+/// [`client::Grpc`]: crate::client::Grpc
+/// [`server::Grpc`]: crate::server::Grpc
+#[derive(Debug, Clone, Default)]
+pub struct BytesCodec {
+    _priv: (),
+}
+
+impl Codec for BytesCodec {
+    type Encode = Bytes;
+    type Decode = Bytes;
+
+    type Encoder = BytesEncoder;
+    type Decoder = BytesDecoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        BytesEncoder { _priv: () }
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        BytesDecoder { _priv: () }
+    }
+}
+
+/// A [`Encoder`] that writes raw [`Bytes`] as the message payload.
+#[derive(Debug, Clone, Default)]
+pub struct BytesEncoder {
+    _priv: (),
+}
+
+impl Encoder for BytesEncoder {
+    type Item = Bytes;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, buf: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        buf.put(item);
+        Ok(())
+    }
+
+    fn encoded_len(&self, item: &Self::Item) -> Option<usize> {
+        Some(item.len())
+    }
+}
+
+/// A [`Decoder`] that yields the message payload as raw [`Bytes`].
+#[derive(Debug, Clone, Default)]
+pub struct BytesDecoder {
+    _priv: (),
+}
+
+impl Decoder for BytesDecoder {
+    type Item = Bytes;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(Some(buf.to_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+
+    #[test]
+    fn round_trip() {
+        let mut codec = BytesCodec::default();
+
+        let mut buf = BytesMut::new();
+        codec
+            .encoder()
+            .encode(
+                Bytes::from_static(b"\x08\x96\x01"),
+                &mut EncodeBuf::new(&mut buf),
+            )
+            .unwrap();
+        assert_eq!(&buf[..], b"\x08\x96\x01");
+
+        let len = buf.len();
+        let decoded = codec
+            .decoder()
+            .decode(&mut DecodeBuf::new(&mut buf, len))
+            .unwrap();
+        assert_eq!(decoded, Some(Bytes::from_static(b"\x08\x96\x01")));
+        assert!(buf.is_empty());
+    }
+}
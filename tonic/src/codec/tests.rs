@@ -1,9 +1,13 @@
-use super::{encode_server, Decoder, Encoder, Streaming};
+use super::{
+    encode_client, encode_server, CompressionEncoding, CompressionSettings, Compressor,
+    DecodeSettings, Decoder, EncodeError, Encoder, Streaming,
+};
 use crate::codec::buffer::DecodeBuf;
 use crate::codec::EncodeBuf;
 use crate::Status;
 use bytes::{Buf, BufMut, BytesMut};
 use http_body::Body;
+use std::io::{self, Write};
 
 const LEN: usize = 10000;
 
@@ -23,7 +27,7 @@ async fn decode() {
 
     let body = body::MockBody::new(&buf[..], 10005, 0);
 
-    let mut stream = Streaming::new_request(decoder, body);
+    let mut stream = Streaming::new_request(decoder, body, None);
 
     let mut i = 0usize;
     while let Some(output_msg) = stream.message().await.unwrap() {
@@ -33,6 +37,133 @@ async fn decode() {
     assert_eq!(i, 1);
 }
 
+#[test]
+fn decode_yields_after_messages() {
+    use futures_core::Stream;
+    use std::{
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    let mut buf = BytesMut::new();
+    for _ in 0..5 {
+        buf.put_u8(0);
+        buf.put_u32(LEN as u32);
+        buf.put(&[0u8; LEN][..]);
+    }
+
+    let body = body::MockBody::new(&buf[..], buf.len(), 0);
+    let settings = DecodeSettings {
+        yield_after_messages: Some(2),
+        ..DecodeSettings::default()
+    };
+    let mut stream = Streaming::new_request_with(MockDecoder, body, None, settings);
+
+    let mut cx = Context::from_waker(futures_util::task::noop_waker_ref());
+    let mut polls = Vec::new();
+    loop {
+        match Pin::new(&mut stream).poll_next(&mut cx) {
+            Poll::Ready(Some(msg)) => {
+                assert_eq!(msg.unwrap().len(), LEN);
+                polls.push('m');
+            }
+            Poll::Ready(None) => break,
+            Poll::Pending => polls.push('p'),
+        }
+    }
+
+    assert_eq!(polls.into_iter().collect::<String>(), "mmpmmpm");
+}
+
+#[tokio::test]
+async fn decode_compressed_flag() {
+    let mut buf = BytesMut::new();
+    buf.put_u8(1);
+    buf.put_u32(LEN as u32);
+    buf.put(&[0u8; LEN][..]);
+
+    // Compressed messages require a `grpc-encoding`.
+    let body = body::MockBody::new(&buf[..], buf.len(), 0);
+    let mut stream = Streaming::new_request(MockDecoder, body, None);
+    assert_eq!(stream.encoding(), None);
+    let status = stream.message().await.unwrap_err();
+    assert_eq!(status.code(), crate::Code::Internal);
+
+    let encoding = CompressionEncoding::register(Passthrough("x-passthrough-flag")).unwrap();
+    let body = body::MockBody::new(&buf[..], buf.len(), 0);
+    let mut stream = Streaming::new_request(MockDecoder, body, Some(encoding));
+    assert_eq!(stream.encoding(), Some(encoding));
+    assert_eq!(stream.message().await.unwrap().unwrap().len(), LEN);
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn decode_rejects_messages_decompressed_past_the_limit() {
+    use super::{compression::compress, CompressionLevel};
+
+    let msg = vec![0u8; LEN];
+    let mut src = BytesMut::from(&msg[..]);
+    let mut compressed = BytesMut::new();
+    let encoding = CompressionEncoding::Gzip;
+    compress(
+        encoding,
+        CompressionLevel::Default,
+        &mut src,
+        &mut compressed,
+        LEN,
+    )
+    .unwrap();
+
+    let mut buf = BytesMut::new();
+    buf.put_u8(1);
+    buf.put_u32(compressed.len() as u32);
+    buf.put(&compressed[..]);
+
+    let settings = DecodeSettings {
+        max_message_size: Some(LEN - 1),
+        ..DecodeSettings::default()
+    };
+    let body = body::MockBody::new(&buf[..], buf.len(), 0);
+    let mut stream = Streaming::new_request_with(MockDecoder, body, Some(encoding), settings);
+    let status = stream.message().await.unwrap_err();
+    assert_eq!(status.code(), crate::Code::ResourceExhausted);
+
+    let settings = DecodeSettings {
+        max_message_size: Some(LEN),
+        ..DecodeSettings::default()
+    };
+    let body = body::MockBody::new(&buf[..], buf.len(), 0);
+    let mut stream = Streaming::new_request_with(MockDecoder, body, Some(encoding), settings);
+    assert_eq!(stream.message().await.unwrap().unwrap().len(), LEN);
+}
+
+#[tokio::test]
+async fn decode_status_details_from_trailers() {
+    let mut buf = BytesMut::new();
+    buf.put_u8(0);
+    buf.put_u32(LEN as u32);
+    buf.put(&[0u8; LEN][..]);
+
+    let details = bytes::Bytes::from_static(b"details");
+    let trailers = Status::with_details(crate::Code::Aborted, "aborted", details.clone())
+        .to_header_map()
+        .unwrap();
+
+    let body = body::MockBody::new(&buf[..], buf.len(), 0).trailers(trailers);
+    let mut stream = Streaming::new_response(
+        MockDecoder,
+        body,
+        http::StatusCode::OK,
+        None,
+        DecodeSettings::default(),
+    );
+    assert!(stream.message().await.unwrap().is_some());
+
+    let status = stream.message().await.unwrap_err();
+    assert_eq!(status.code(), crate::Code::Aborted);
+    assert_eq!(status.details(), &details[..]);
+}
+
 #[tokio::test]
 async fn encode() {
     let encoder = MockEncoder::default();
@@ -42,7 +173,7 @@ async fn encode() {
     let messages = std::iter::repeat(Ok::<_, Status>(msg)).take(10000);
     let source = futures_util::stream::iter(messages);
 
-    let body = encode_server(encoder, source);
+    let body = encode_server(encoder, source, None, None, None, false);
 
     futures_util::pin_mut!(body);
 
@@ -51,6 +182,131 @@ async fn encode() {
     }
 }
 
+#[tokio::test]
+async fn encode_below_compression_threshold() {
+    let encoding = CompressionEncoding::register(Passthrough("x-passthrough-threshold")).unwrap();
+    let settings = CompressionSettings {
+        threshold: 100,
+        ..Default::default()
+    };
+
+    let messages = vec![Ok(vec![1u8; 10]), Ok(vec![2u8; 1000])];
+    let source = futures_util::stream::iter(messages);
+
+    let body = encode_server(
+        MockEncoder,
+        source,
+        Some((encoding, settings)),
+        None,
+        None,
+        false,
+    );
+    futures_util::pin_mut!(body);
+
+    let small = body.data().await.unwrap().unwrap();
+    assert_eq!(small[0], 0);
+    assert_eq!(small.len(), 5 + 10);
+
+    let large = body.data().await.unwrap().unwrap();
+    assert_eq!(large[0], 1);
+    assert_eq!(large.len(), 5 + 1000);
+}
+
+#[tokio::test]
+async fn message_checksums() {
+    let messages = vec![Ok(vec![1u8; LEN]), Ok(vec![2u8; LEN])];
+    let body = encode_server(
+        MockEncoder,
+        futures_util::stream::iter(messages),
+        None,
+        None,
+        None,
+        true,
+    );
+    futures_util::pin_mut!(body);
+
+    let mut data = BytesMut::new();
+    while let Some(frame) = body.data().await {
+        data.put(frame.unwrap());
+    }
+    let trailers = body.trailers().await.unwrap().unwrap();
+
+    let settings = DecodeSettings {
+        checksum: true,
+        ..DecodeSettings::default()
+    };
+
+    let body = body::MockBody::new(&data[..], data.len(), 0).trailers(trailers.clone());
+    let mut stream = Streaming::new_request_with(MockDecoder, body, None, settings.clone());
+    assert!(stream.message().await.unwrap().is_some());
+    assert!(stream.message().await.unwrap().is_some());
+    assert!(stream.message().await.unwrap().is_none());
+
+    // Flip a bit of the second message.
+    data[5 + LEN + 5] ^= 1;
+
+    let body = body::MockBody::new(&data[..], data.len(), 0).trailers(trailers);
+    let mut stream = Streaming::new_request_with(MockDecoder, body, None, settings);
+    assert!(stream.message().await.unwrap().is_some());
+    assert!(stream.message().await.unwrap().is_some());
+    let status = stream.message().await.unwrap_err();
+    assert_eq!(status.code(), crate::Code::DataLoss);
+}
+
+#[tokio::test]
+async fn encode_with_length_hint() {
+    // Accurate and inaccurate hints both produce well-formed frames.
+    for &off_by in &[0, 3] {
+        let messages = vec![Ok(vec![1u8; 10])];
+        let body = encode_server(
+            HintedEncoder(off_by),
+            futures_util::stream::iter(messages),
+            None,
+            None,
+            None,
+            false,
+        );
+        futures_util::pin_mut!(body);
+
+        let frame = body.data().await.unwrap().unwrap();
+        assert_eq!(&frame[..5], &[0, 0, 0, 0, 10]);
+        assert_eq!(&frame[5..], &[1u8; 10][..]);
+    }
+}
+
+#[tokio::test]
+async fn encode_rejects_hinted_length_over_max() {
+    let body = encode_client(
+        HintedEncoder(0),
+        futures_util::stream::iter(vec![vec![1u8; 10]]),
+        None,
+        Some(5),
+        None,
+        false,
+        EncodeError::default(),
+    );
+    futures_util::pin_mut!(body);
+
+    let status = body.data().await.unwrap().unwrap_err();
+    assert_eq!(status.code(), crate::Code::OutOfRange);
+}
+
+struct Passthrough(&'static str);
+
+impl Compressor for Passthrough {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn compress(&self, src: &[u8], dst: &mut dyn Write) -> io::Result<()> {
+        dst.write_all(src)
+    }
+
+    fn decompress(&self, src: &[u8], dst: &mut dyn Write) -> io::Result<()> {
+        dst.write_all(src)
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 struct MockEncoder;
 
@@ -64,6 +320,24 @@ impl Encoder for MockEncoder {
     }
 }
 
+/// Encodes like `MockEncoder`, with a length hint off by `self.0` bytes.
+#[derive(Debug, Clone, Default)]
+struct HintedEncoder(usize);
+
+impl Encoder for HintedEncoder {
+    type Item = Vec<u8>;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, buf: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        buf.put(&item[..]);
+        Ok(())
+    }
+
+    fn encoded_len(&self, item: &Self::Item) -> Option<usize> {
+        Some(item.len() + self.0)
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 struct MockDecoder;
 
@@ -96,6 +370,8 @@ mod body {
 
         // the number of times we've sent
         count: usize,
+
+        trailers: Option<http::HeaderMap>,
     }
 
     impl MockBody {
@@ -104,6 +380,14 @@ mod body {
                 data: Bytes::copy_from_slice(&b[..]),
                 partial_len,
                 count,
+                trailers: None,
+            }
+        }
+
+        pub(super) fn trailers(self, trailers: http::HeaderMap) -> Self {
+            MockBody {
+                trailers: Some(trailers),
+                ..self
             }
         }
     }
@@ -140,11 +424,11 @@ mod body {
         }
 
         fn poll_trailers(
-            self: Pin<&mut Self>,
+            mut self: Pin<&mut Self>,
             cx: &mut Context<'_>,
         ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
             drop(cx);
-            Poll::Ready(Ok(None))
+            Poll::Ready(Ok(self.trailers.take()))
         }
     }
 }
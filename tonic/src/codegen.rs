@@ -1,6 +1,7 @@
 //! Codegen exports used by `tonic-build`.
 
 pub use async_trait::async_trait;
+pub use bytes::{Buf, BufMut, Bytes};
 pub use futures_core::Stream;
 pub use futures_util::future::{ok, poll_fn, Ready};
 
@@ -12,6 +13,9 @@ pub use std::task::{Context, Poll};
 pub use tower_service::Service;
 pub type StdError = Box<dyn std::error::Error + Send + Sync + 'static>;
 pub use crate::body::Body;
+pub use crate::codec::{
+    BufferPool, CompressionEncoding, CompressionLevel, EnabledCompressionEncodings,
+};
 
 #[cfg(feature = "transport")]
 pub use hyper::Body as HyperBody;
@@ -24,6 +28,44 @@ pub mod http {
     pub use http::*;
 }
 
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub mod serde;
+
+/// A boxed stream of messages, sent by the client streaming methods of the
+/// generated client traits.
+pub struct BoxMessageStream<T>(
+    self::Pin<Box<dyn futures_core::Stream<Item = T> + Send + Sync + 'static>>,
+);
+
+impl<T> BoxMessageStream<T> {
+    /// Box `stream`.
+    pub fn new<S>(stream: S) -> Self
+    where
+        S: futures_core::Stream<Item = T> + Send + Sync + 'static,
+    {
+        BoxMessageStream(Box::pin(stream))
+    }
+}
+
+impl<T> futures_core::Stream for BoxMessageStream<T> {
+    type Item = T;
+
+    fn poll_next(self: self::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().0.as_mut().poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T> std::fmt::Debug for BoxMessageStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxMessageStream").finish()
+    }
+}
+
 #[derive(Debug)]
 pub enum Never {}
 
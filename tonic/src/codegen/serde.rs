@@ -0,0 +1,514 @@
+//! Serde helpers for the messages generated with `derive_serde` enabled in
+//! `tonic-build`.
+//!
+//! Each module is meant to be used with `#[serde(with = "...")]` on fields
+//! whose Rust type doesn't follow the [proto3 JSON mapping] on its own. The
+//! `option`, `vec` and `map` submodules handle the optional, repeated and
+//! map fields of the same type.
+//!
+//! [proto3 JSON mapping]: https://developers.google.com/protocol-buffers/docs/proto3#json
+
+use ::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+macro_rules! nested {
+    ($ty:ty) => {
+        struct Ser<'a>(&'a $ty);
+
+        impl Serialize for Ser<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serialize(self.0, serializer)
+            }
+        }
+
+        struct De($ty);
+
+        impl<'de> Deserialize<'de> for De {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserialize(deserializer).map(De)
+            }
+        }
+
+        /// Helpers for optional fields.
+        pub mod option {
+            use super::{De, Ser};
+            use ::serde::{Deserialize, Deserializer, Serializer};
+
+            #[allow(missing_docs)]
+            pub fn serialize<S: Serializer>(
+                value: &Option<$ty>,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                match value {
+                    Some(value) => serializer.serialize_some(&Ser(value)),
+                    None => serializer.serialize_none(),
+                }
+            }
+
+            #[allow(missing_docs)]
+            pub fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Option<$ty>, D::Error> {
+                let value = Option::<De>::deserialize(deserializer)?;
+                Ok(value.map(|De(value)| value))
+            }
+        }
+
+        /// Helpers for repeated fields.
+        pub mod vec {
+            use super::{De, Ser};
+            use ::serde::{Deserialize, Deserializer, Serializer};
+
+            #[allow(missing_docs)]
+            pub fn serialize<S: Serializer>(
+                values: &[$ty],
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(values.iter().map(Ser))
+            }
+
+            #[allow(missing_docs)]
+            pub fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Vec<$ty>, D::Error> {
+                let values = Vec::<De>::deserialize(deserializer)?;
+                Ok(values.into_iter().map(|De(value)| value).collect())
+            }
+        }
+
+        /// Helpers for map fields.
+        pub mod map {
+            use super::{De, Ser};
+            use ::serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+            use std::{collections::HashMap, hash::Hash};
+
+            #[allow(missing_docs)]
+            pub fn serialize<K, S>(
+                values: &HashMap<K, $ty>,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error>
+            where
+                K: Serialize,
+                S: Serializer,
+            {
+                serializer.collect_map(values.iter().map(|(key, value)| (key, Ser(value))))
+            }
+
+            #[allow(missing_docs)]
+            pub fn deserialize<'de, K, D>(deserializer: D) -> Result<HashMap<K, $ty>, D::Error>
+            where
+                K: DeserializeOwned + Eq + Hash,
+                D: Deserializer<'de>,
+            {
+                let values = HashMap::<K, De>::deserialize(deserializer)?;
+                Ok(values
+                    .into_iter()
+                    .map(|(key, De(value))| (key, value))
+                    .collect())
+            }
+        }
+    };
+}
+
+/// `bytes` fields, as base64 strings.
+///
+/// Both the standard and URL-safe alphabets are accepted, padded or not.
+pub mod bytes {
+    use super::*;
+
+    nested!(Vec<u8>);
+
+    #[allow(missing_docs)]
+    pub fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::encode(value))
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        let config = if value.contains(&['-', '_'][..]) {
+            base64::URL_SAFE_NO_PAD
+        } else {
+            base64::STANDARD_NO_PAD
+        };
+
+        base64::decode_config(value.trim_end_matches('='), config)
+            .map_err(|e| de::Error::custom(format_args!("invalid base64 bytes: {}", e)))
+    }
+}
+
+/// `google.protobuf.Timestamp` fields, as RFC 3339 strings like
+/// `"1972-01-01T10:00:20.021Z"`.
+///
+/// Timestamps are written in UTC, timestamps with any offset are accepted.
+pub mod timestamp {
+    use super::*;
+    use prost_types::Timestamp;
+
+    nested!(prost_types::Timestamp);
+
+    // 0001-01-01T00:00:00Z and 9999-12-31T23:59:59Z.
+    const MIN_SECONDS: i64 = -62_135_596_800;
+    const MAX_SECONDS: i64 = 253_402_300_799;
+
+    #[allow(missing_docs)]
+    pub fn serialize<S: Serializer>(value: &Timestamp, serializer: S) -> Result<S::Ok, S::Error> {
+        let formatted = format(value).map_err(::serde::ser::Error::custom)?;
+        serializer.serialize_str(&formatted)
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Timestamp, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse(&value).ok_or_else(|| {
+            de::Error::custom(format_args!("invalid RFC 3339 timestamp: {:?}", value))
+        })
+    }
+
+    fn format(value: &Timestamp) -> Result<String, String> {
+        let seconds = value.seconds;
+        if !(MIN_SECONDS..=MAX_SECONDS).contains(&seconds)
+            || !(0..1_000_000_000).contains(&value.nanos)
+        {
+            return Err(format!("timestamp out of range: {:?}", value));
+        }
+
+        let days = seconds.div_euclid(86_400);
+        let time = seconds.rem_euclid(86_400);
+        let (year, month, day) = civil_from_days(days);
+
+        Ok(format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z",
+            year,
+            month,
+            day,
+            time / 3600,
+            time / 60 % 60,
+            time % 60,
+            super::format_nanos(value.nanos),
+        ))
+    }
+
+    fn parse(value: &str) -> Option<Timestamp> {
+        let bytes = value.as_bytes();
+        if bytes.len() < 20
+            || bytes[4] != b'-'
+            || bytes[7] != b'-'
+            || !(bytes[10] == b'T' || bytes[10] == b't')
+            || bytes[13] != b':'
+            || bytes[16] != b':'
+        {
+            return None;
+        }
+
+        let number = |range: std::ops::Range<usize>| -> Option<i64> {
+            let digits = value.get(range)?;
+            if digits.bytes().all(|b| b.is_ascii_digit()) {
+                digits.parse().ok()
+            } else {
+                None
+            }
+        };
+
+        let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+        let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+        if !(1..=12).contains(&month)
+            || day < 1
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return None;
+        }
+
+        let (nanos, rest) = super::parse_nanos(&value[19..])?;
+        let offset = if rest == "Z" || rest == "z" {
+            0
+        } else {
+            let rest = rest.as_bytes();
+            if rest.len() != 6 || rest[3] != b':' {
+                return None;
+            }
+            let hours = number(value.len() - 5..value.len() - 3)?;
+            let minutes = number(value.len() - 2..value.len())?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            match rest[0] {
+                b'+' => hours * 3600 + minutes * 60,
+                b'-' => -(hours * 3600 + minutes * 60),
+                _ => return None,
+            }
+        };
+
+        let seconds =
+            days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second
+                - offset;
+        if !(MIN_SECONDS..=MAX_SECONDS).contains(&seconds) {
+            return None;
+        }
+
+        Some(Timestamp { seconds, nanos })
+    }
+
+    fn is_leap_year(year: i64) -> bool {
+        year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+    }
+
+    fn days_in_month(year: i64, month: i64) -> i64 {
+        match month {
+            2 if is_leap_year(year) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    // Conversions between days since the Unix epoch and proleptic Gregorian
+    // dates, from http://howardhinnant.github.io/date_algorithms.html.
+    fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    fn civil_from_days(days: i64) -> (i64, i64, i64) {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        (year, month, day)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn formats_and_parses() {
+            for &(seconds, nanos, formatted) in &[
+                (0, 0, "1970-01-01T00:00:00Z"),
+                (63_108_020, 21_000_000, "1972-01-01T10:00:20.021Z"),
+                (951_825_600, 1_000, "2000-02-29T12:00:00.000001Z"),
+                (-1, 999_999_999, "1969-12-31T23:59:59.999999999Z"),
+                (MIN_SECONDS, 0, "0001-01-01T00:00:00Z"),
+                (MAX_SECONDS, 0, "9999-12-31T23:59:59Z"),
+            ] {
+                let timestamp = Timestamp { seconds, nanos };
+                assert_eq!(format(&timestamp).unwrap(), formatted);
+                assert_eq!(parse(formatted), Some(timestamp));
+            }
+        }
+
+        #[test]
+        fn parses_offsets() {
+            let expected = Some(Timestamp {
+                seconds: 63_108_020,
+                nanos: 0,
+            });
+            assert_eq!(parse("1972-01-01T11:30:20+01:30"), expected);
+            assert_eq!(parse("1972-01-01T09:00:20-01:00"), expected);
+        }
+
+        #[test]
+        fn rejects_invalid_timestamps() {
+            for invalid in &[
+                "",
+                "1972-01-01",
+                "1972-01-01T10:00:20",
+                "1972-02-30T10:00:20Z",
+                "1971-02-29T10:00:20Z",
+                "1972-01-01T24:00:00Z",
+                "1972-01-01T10:00:20.Z",
+                "1972-01-01T10:00:20.0000000001Z",
+                "1972-01-01T10:00:20+0100",
+                "0001-01-01T00:00:00+00:01",
+            ] {
+                assert_eq!(parse(invalid), None, "{:?}", invalid);
+            }
+
+            assert!(format(&Timestamp {
+                seconds: MAX_SECONDS + 1,
+                nanos: 0
+            })
+            .is_err());
+        }
+    }
+}
+
+/// `google.protobuf.Duration` fields, as strings of seconds like `"1.5s"`.
+pub mod duration {
+    use super::*;
+    use prost_types::Duration;
+
+    nested!(prost_types::Duration);
+
+    // About 10,000 years.
+    const MAX_SECONDS: i64 = 315_576_000_000;
+
+    #[allow(missing_docs)]
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        let formatted = format(value).map_err(::serde::ser::Error::custom)?;
+        serializer.serialize_str(&formatted)
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse(&value)
+            .ok_or_else(|| de::Error::custom(format_args!("invalid duration: {:?}", value)))
+    }
+
+    fn format(value: &Duration) -> Result<String, String> {
+        let (seconds, nanos) = (value.seconds, value.nanos);
+        if seconds.abs() > MAX_SECONDS
+            || nanos.abs() >= 1_000_000_000
+            || (seconds > 0 && nanos < 0)
+            || (seconds < 0 && nanos > 0)
+        {
+            return Err(format!("duration out of range: {:?}", value));
+        }
+
+        let sign = if seconds < 0 || nanos < 0 { "-" } else { "" };
+        Ok(format!(
+            "{}{}{}s",
+            sign,
+            seconds.abs(),
+            super::format_nanos(nanos.abs())
+        ))
+    }
+
+    fn parse(value: &str) -> Option<Duration> {
+        let value = value.strip_suffix('s')?;
+        let (negative, value) = match value.strip_prefix('-') {
+            Some(value) => (true, value),
+            None => (false, value),
+        };
+
+        let end = value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len());
+        if end == 0 {
+            return None;
+        }
+        let seconds: i64 = value[..end].parse().ok()?;
+        let (nanos, rest) = super::parse_nanos(&value[end..])?;
+        if !rest.is_empty() || seconds > MAX_SECONDS {
+            return None;
+        }
+
+        if negative {
+            Some(Duration {
+                seconds: -seconds,
+                nanos: -nanos,
+            })
+        } else {
+            Some(Duration { seconds, nanos })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn formats_and_parses() {
+            for &(seconds, nanos, formatted) in &[
+                (0, 0, "0s"),
+                (1, 500_000_000, "1.500s"),
+                (3, 1, "3.000000001s"),
+                (-1, -500_000_000, "-1.500s"),
+                (0, -1_000, "-0.000001s"),
+                (MAX_SECONDS, 0, "315576000000s"),
+            ] {
+                let duration = Duration { seconds, nanos };
+                assert_eq!(format(&duration).unwrap(), formatted);
+                assert_eq!(parse(formatted), Some(duration));
+            }
+
+            assert_eq!(
+                parse("1.5s"),
+                Some(Duration {
+                    seconds: 1,
+                    nanos: 500_000_000
+                })
+            );
+        }
+
+        #[test]
+        fn rejects_invalid_durations() {
+            for invalid in &["", "s", "1", "-s", "1.s", "+1s", "1.5ms", "315576000001s"] {
+                assert_eq!(parse(invalid), None, "{:?}", invalid);
+            }
+
+            assert!(format(&Duration {
+                seconds: 1,
+                nanos: -1
+            })
+            .is_err());
+        }
+    }
+}
+
+/// Format `nanos` as the 0, 3, 6 or 9 digits fractional part of a second.
+fn format_nanos(nanos: i32) -> String {
+    if nanos == 0 {
+        String::new()
+    } else if nanos % 1_000_000 == 0 {
+        format!(".{:03}", nanos / 1_000_000)
+    } else if nanos % 1_000 == 0 {
+        format!(".{:06}", nanos / 1_000)
+    } else {
+        format!(".{:09}", nanos)
+    }
+}
+
+/// Parse the optional fractional part of a second at the start of `value`,
+/// returning it as nanoseconds along with the rest of `value`.
+fn parse_nanos(value: &str) -> Option<(i32, &str)> {
+    let digits = match value.strip_prefix('.') {
+        Some(digits) => digits,
+        None => return Some((0, value)),
+    };
+
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    if end == 0 || end > 9 {
+        return None;
+    }
+
+    let nanos = digits[..end].parse::<i32>().ok()? * 10_i32.pow(9 - end as u32);
+    Some((nanos, &digits[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn bytes_accept_every_base64_flavor() {
+        let decode = |value: &str| {
+            super::bytes::deserialize(serde_json::Value::String(value.to_string())).unwrap()
+        };
+
+        assert_eq!(
+            super::bytes::serialize(&[0xfb, 0xff], serde_json::value::Serializer).unwrap(),
+            "+/8="
+        );
+        for value in &["+/8=", "+/8", "-_8=", "-_8"] {
+            assert_eq!(decode(value), vec![0xfb, 0xff]);
+        }
+    }
+}
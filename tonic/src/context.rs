@@ -0,0 +1,83 @@
+use http::Extensions;
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// Values shared by every stage handling a request, on the way in and out.
+///
+/// Servers make sure a `RequestContext` is present in the extensions of each
+/// request they handle, see [`Request::context`], and attach that same
+/// context to the extensions of the HTTP response. Values inserted by
+/// `tower` middleware, interceptors or service handlers, like a request id
+/// or an authenticated principal, are thus visible to the later stages of
+/// the request and to the middleware on the response path.
+///
+/// Middleware running before the server can insert its own context in the
+/// request extensions to keep a handle on it, it is reused instead of a new
+/// one.
+///
+/// ```rust
+/// # use tonic::{Request, RequestContext, Status};
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Principal(String);
+///
+/// fn authenticate(request: Request<()>) -> Result<Request<()>, Status> {
+///     if let Some(context) = request.context() {
+///         context.insert(Principal("alice".to_string()));
+///     }
+///     Ok(request)
+/// }
+///
+/// let context = RequestContext::new();
+/// let mut request = Request::new(());
+/// request.extensions_mut().insert(context.clone());
+///
+/// authenticate(request).unwrap();
+/// assert_eq!(context.get::<Principal>(), Some(Principal("alice".to_string())));
+/// ```
+///
+/// [`Request::context`]: struct.Request.html#method.context
+#[derive(Clone, Default)]
+pub struct RequestContext {
+    values: Arc<Mutex<Extensions>>,
+}
+
+impl RequestContext {
+    /// Create an empty `RequestContext`.
+    pub fn new() -> Self {
+        RequestContext::default()
+    }
+
+    /// Insert a value, returning the previous value of the same type.
+    pub fn insert<T: Send + Sync + 'static>(&self, value: T) -> Option<T> {
+        self.values.lock().unwrap().insert(value)
+    }
+
+    /// Get a copy of the value of type `T`, if any.
+    pub fn get<T: Clone + Send + Sync + 'static>(&self) -> Option<T> {
+        self.values.lock().unwrap().get::<T>().cloned()
+    }
+
+    /// Remove the value of type `T`, returning it.
+    pub fn remove<T: Send + Sync + 'static>(&self) -> Option<T> {
+        self.values.lock().unwrap().remove::<T>()
+    }
+
+    /// Get the context of `extensions`, inserting a new one if missing.
+    pub(crate) fn get_or_insert(extensions: &mut Extensions) -> RequestContext {
+        if let Some(context) = extensions.get::<RequestContext>() {
+            return context.clone();
+        }
+
+        let context = RequestContext::new();
+        extensions.insert(context.clone());
+        context
+    }
+}
+
+impl fmt::Debug for RequestContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestContext").finish()
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{metadata::MetadataMap, Status};
+use std::{fmt, future::Future, pin::Pin, sync::Arc};
+
+/// Produces the metadata authenticating each call, like the `authorization`
+/// header carrying an OAuth2 token, right before it is sent.
+///
+/// Unlike an [`Interceptor`], the metadata is produced asynchronously, so
+/// the credentials can fetch or refresh a token when needed. Credentials are
+/// attached to a client, for all its calls, or to a single call with
+/// [`Request::set_call_credentials`], on top of the ones of the client. They
+/// are separate from the TLS configuration of the connection, and sent over
+/// any transport, so should only be used over secure ones.
+///
+/// ```rust
+/// use std::{future::Future, pin::Pin};
+/// use tonic::{metadata::MetadataMap, CallCredentials, Status};
+///
+/// struct Token(String);
+///
+/// impl CallCredentials for Token {
+///     fn metadata<'a>(
+///         &'a self,
+///         _path: &'a str,
+///     ) -> Pin<Box<dyn Future<Output = Result<MetadataMap, Status>> + Send + 'a>> {
+///         Box::pin(async move {
+///             let mut metadata = MetadataMap::new();
+///             let value = format!("Bearer {}", self.0)
+///                 .parse()
+///                 .map_err(|_| Status::unauthenticated("invalid token"))?;
+///             metadata.insert("authorization", value);
+///             Ok(metadata)
+///         })
+///     }
+/// }
+/// ```
+///
+/// [`Interceptor`]: struct.Interceptor.html
+/// [`Request::set_call_credentials`]: struct.Request.html#method.set_call_credentials
+pub trait CallCredentials: Send + Sync + 'static {
+    /// The metadata to send with a call to the method at `path`, like
+    /// `/helloworld.Greeter/SayHello`, replacing the entries of the request
+    /// with the same keys.
+    ///
+    /// An error fails the call with the returned status, before it is sent.
+    fn metadata<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<MetadataMap, Status>> + Send + 'a>>;
+}
+
+impl<C: CallCredentials + ?Sized> CallCredentials for Arc<C> {
+    fn metadata<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<MetadataMap, Status>> + Send + 'a>> {
+        (**self).metadata(path)
+    }
+}
+
+/// The credentials of a client or a call.
+#[derive(Clone)]
+pub(crate) struct SharedCredentials(pub(crate) Arc<dyn CallCredentials>);
+
+impl SharedCredentials {
+    pub(crate) fn new(credentials: impl CallCredentials) -> Self {
+        SharedCredentials(Arc::new(credentials))
+    }
+}
+
+impl fmt::Debug for SharedCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallCredentials").finish()
+    }
+}
@@ -0,0 +1,307 @@
+//! Propagation of call deadlines.
+//!
+//! Servers read the deadline of incoming calls from their `grpc-timeout`
+//! header, see [`Request::deadline`]. While a handler runs, that deadline is
+//! the current one, and the client calls made from it send the time left
+//! until it as their own `grpc-timeout`, unless they set a shorter one with
+//! [`Request::set_timeout`].
+//!
+//! The current deadline is tied to the future of the handler, so it is lost
+//! by the work moved to spawned tasks or polled by response streams. Such
+//! futures can be wrapped with [`scope`] to keep propagating it:
+//!
+//! ```rust
+//! # async fn call_backend() {}
+//! # fn spawn<F: std::future::Future>(_: F) {}
+//! let deadline = tonic::deadline::current();
+//! spawn(tonic::deadline::scope(deadline, async {
+//!     // Client calls made here inherit `deadline`.
+//!     call_backend().await;
+//! }));
+//! ```
+//!
+//! [`Request::deadline`]: ../struct.Request.html#method.deadline
+//! [`Request::set_timeout`]: ../struct.Request.html#method.set_timeout
+//! [`scope`]: fn.scope.html
+
+use crate::{CancellationCause, Status};
+use futures_core::Stream;
+use http::HeaderValue;
+use pin_project::pin_project;
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+pub(crate) const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+thread_local! {
+    static CURRENT: Cell<Option<Instant>> = Cell::default();
+}
+
+/// The deadline of a request, stored in its extensions.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline(pub(crate) Instant);
+
+/// Get the deadline of the call being handled, if any.
+pub fn current() -> Option<Instant> {
+    CURRENT.with(Cell::get)
+}
+
+/// Run `future` with `deadline` as the current deadline.
+pub fn scope<F>(deadline: Option<Instant>, future: F) -> Scope<F>
+where
+    F: Future,
+{
+    Scope {
+        deadline,
+        inner: future,
+    }
+}
+
+/// A future running with a current deadline, created by [`scope`].
+///
+/// [`scope`]: fn.scope.html
+#[pin_project]
+#[derive(Debug)]
+pub struct Scope<F> {
+    deadline: Option<Instant>,
+    #[pin]
+    inner: F,
+}
+
+impl<F: Future> Future for Scope<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _current = SetCurrent::new(*this.deadline);
+        this.inner.poll(cx)
+    }
+}
+
+/// Restores the previous current deadline on drop, even when unwinding.
+struct SetCurrent(Option<Instant>);
+
+impl SetCurrent {
+    fn new(deadline: Option<Instant>) -> Self {
+        SetCurrent(CURRENT.with(|current| current.replace(deadline)))
+    }
+}
+
+impl Drop for SetCurrent {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.0));
+    }
+}
+
+/// A future or stream failing with `DeadlineExceeded` once a deadline
+/// passed.
+///
+/// Enforcing deadlines needs the timer of the `transport` feature's
+/// runtime, without which this only forwards to `inner`.
+#[pin_project]
+pub(crate) struct Enforced<T> {
+    #[pin]
+    inner: T,
+    expiry: Option<Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>>>,
+    expired: bool,
+}
+
+impl<T> Enforced<T> {
+    pub(crate) fn new(inner: T, deadline: Option<Instant>) -> Self {
+        Enforced {
+            inner,
+            expiry: deadline.and_then(expiry),
+            expired: false,
+        }
+    }
+}
+
+/// The furthest deadline enforced, well within the range of the timer,
+/// which panics past about two years. Further deadlines, allowed by the
+/// `grpc-timeout` header, are as good as none.
+#[cfg(feature = "transport")]
+const MAX_ENFORCED: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+#[cfg(feature = "transport")]
+fn expiry(deadline: Instant) -> Option<Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>>> {
+    if deadline.saturating_duration_since(Instant::now()) > MAX_ENFORCED {
+        return None;
+    }
+    Some(Box::pin(tokio::time::delay_until(deadline.into())))
+}
+
+#[cfg(not(feature = "transport"))]
+fn expiry(_: Instant) -> Option<Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>>> {
+    None
+}
+
+fn poll_expiry(
+    expiry: &mut Option<Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>>>,
+    cx: &mut Context<'_>,
+) -> bool {
+    match expiry {
+        Some(expiry) => expiry.as_mut().poll(cx).is_ready(),
+        None => false,
+    }
+}
+
+fn deadline_exceeded() -> Status {
+    Status::deadline_exceeded("Deadline exceeded.")
+        .with_cancellation_cause(CancellationCause::Deadline)
+}
+
+impl<F, R> Future for Enforced<F>
+where
+    F: Future<Output = Result<R, Status>>,
+{
+    type Output = Result<R, Status>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(output) = this.inner.poll(cx) {
+            return Poll::Ready(output);
+        }
+
+        if poll_expiry(this.expiry, cx) {
+            Poll::Ready(Err(deadline_exceeded()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<S, R> Stream for Enforced<S>
+where
+    S: Stream<Item = Result<R, Status>>,
+{
+    type Item = Result<R, Status>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if *this.expired {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(item) = this.inner.poll_next(cx) {
+            return Poll::Ready(item);
+        }
+
+        if poll_expiry(this.expiry, cx) {
+            *this.expired = true;
+            Poll::Ready(Some(Err(deadline_exceeded())))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Enforced<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Enforced")
+            .field("expired", &self.expired)
+            .finish()
+    }
+}
+
+/// Parse a `grpc-timeout` header value, made of at most 8 digits followed
+/// by a unit.
+pub(crate) fn parse_timeout(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?;
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+
+    let (digits, unit) = value.split_at(value.len() - 1);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let amount = digits.parse::<u64>().ok()?;
+
+    let timeout = match unit {
+        "H" => Duration::from_secs(amount * 60 * 60),
+        "M" => Duration::from_secs(amount * 60),
+        "S" => Duration::from_secs(amount),
+        "m" => Duration::from_millis(amount),
+        "u" => Duration::from_micros(amount),
+        "n" => Duration::from_nanos(amount),
+        _ => return None,
+    };
+
+    Some(timeout)
+}
+
+/// Encode `timeout` as a `grpc-timeout` header value, in the finest unit
+/// fitting in 8 digits.
+pub(crate) fn encode_timeout(timeout: Duration) -> HeaderValue {
+    const MAX: u128 = 99_999_999;
+
+    let nanos = timeout.as_nanos();
+    let (amount, unit) = if nanos <= MAX {
+        (nanos, 'n')
+    } else if nanos / 1_000 <= MAX {
+        (nanos / 1_000, 'u')
+    } else if nanos / 1_000_000 <= MAX {
+        (nanos / 1_000_000, 'm')
+    } else if nanos / 1_000_000_000 <= MAX {
+        (nanos / 1_000_000_000, 'S')
+    } else if nanos / 60_000_000_000 <= MAX {
+        (nanos / 60_000_000_000, 'M')
+    } else {
+        ((nanos / 3_600_000_000_000).min(MAX), 'H')
+    };
+
+    HeaderValue::from_str(&format!("{}{}", amount, unit)).expect("valid grpc-timeout")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout_round_trip() {
+        for &timeout in &[
+            Duration::from_nanos(1),
+            Duration::from_millis(1500),
+            Duration::from_secs(1000),
+            Duration::from_secs(60 * 60 * 24 * 365),
+        ] {
+            let value = encode_timeout(timeout);
+            assert!(value.len() <= 9);
+
+            let parsed = parse_timeout(&value).unwrap();
+            assert!(parsed <= timeout);
+            // Only truncated to the unit fitting 8 digits.
+            assert!(timeout - parsed <= timeout / 1_000);
+        }
+    }
+
+    #[test]
+    fn parse_invalid_timeouts() {
+        for value in &["", "S", "10", "10x", "-10S", "+10S", "123456789S"] {
+            assert_eq!(parse_timeout(&HeaderValue::from_static(value)), None);
+        }
+        assert_eq!(
+            parse_timeout(&HeaderValue::from_static("2M")),
+            Some(Duration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn scope_sets_current_deadline() {
+        use futures_util::future::FutureExt;
+
+        let deadline = Instant::now();
+        assert_eq!(current(), None);
+
+        let found = scope(Some(deadline), async { current() })
+            .now_or_never()
+            .unwrap();
+        assert_eq!(found, Some(deadline));
+        assert_eq!(current(), None);
+    }
+}
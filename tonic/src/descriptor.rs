@@ -0,0 +1,532 @@
+//! Descriptors of the generated services and methods.
+//!
+//! `tonic-build` generates a `{service}_descriptor` module next to the
+//! client and server of each service, with a `SERVICE` constant describing
+//! the service and a constant per method, named after the method in
+//! uppercase snake case. Interceptors, routers and metrics layers can then
+//! match requests on them instead of parsing their URI:
+//!
+//! ```rust,ignore
+//! use hello_world::greeter_descriptor;
+//!
+//! match greeter_descriptor::SERVICE.method(request.uri().path()) {
+//!     Some(method) if method.kind.is_streaming() => { /* ... */ }
+//!     _ => { /* ... */ }
+//! }
+//! ```
+//!
+//! Generated clients also set the descriptor of the method called on each
+//! request, as a `&'static MethodDescriptor` extension, so client
+//! interceptors and layers can key their policies on it:
+//!
+//! ```rust,ignore
+//! let interceptor = Interceptor::new(|request: Request<()>| {
+//!     let method = request.method_descriptor().unwrap();
+//!     if method.idempotency_level == IdempotencyLevel::NoSideEffects {
+//!         /* ... */
+//!     }
+//!     Ok(request)
+//! });
+//! ```
+//!
+//! The options of the services and methods are kept encoded, custom options
+//! included, and can be decoded into a message declaring the custom options
+//! as fields with the same numbers:
+//!
+//! ```rust,ignore
+//! // extend google.protobuf.MethodOptions { string owner = 50000; }
+//! #[derive(Clone, PartialEq, prost::Message)]
+//! struct Ownership {
+//!     #[prost(string, tag = "50000")]
+//!     owner: String,
+//! }
+//!
+//! let ownership = Ownership::decode(greeter_descriptor::SAY_HELLO.options)?;
+//! ```
+//!
+//! The REST mappings declared with the `google.api.http` option are kept as
+//! [`HttpRule`]s, so a transcoding layer can route HTTP/JSON requests to the
+//! methods they map to:
+//!
+//! ```rust,ignore
+//! // rpc GetShelf(GetShelfRequest) returns (Shelf) {
+//! //   option (google.api.http) = { get: "/v1/{name=shelves/*}" };
+//! // }
+//! let route = library_descriptor::SERVICE.route("GET", "/v1/shelves/1").unwrap();
+//! assert_eq!(route.method.name, "GetShelf");
+//! assert_eq!(route.bindings, vec![("name", "shelves/1".to_string())]);
+//! ```
+//!
+//! Each package declaring services gets a `PACKAGE` constant, and the
+//! include file of `tonic-build` a `REGISTRY` constant listing them all, so a
+//! server can set up reflection and health checking for every compiled
+//! service at once:
+//!
+//! ```rust,ignore
+//! for service in protos::REGISTRY.services() {
+//!     health_reporter.set_serving(service.name).await;
+//! }
+//! for set in protos::REGISTRY.file_descriptor_sets() {
+//!     reflection = reflection.register_encoded_file_descriptor_set(set);
+//! }
+//! ```
+
+use std::ops::Range;
+
+/// Describes a gRPC service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServiceDescriptor {
+    /// The fully qualified name of the service, like `helloworld.Greeter`.
+    pub name: &'static str,
+    /// The methods of the service.
+    pub methods: &'static [MethodDescriptor],
+    /// The encoded `google.protobuf.ServiceOptions` of the service,
+    /// including its custom options.
+    pub options: &'static [u8],
+}
+
+impl ServiceDescriptor {
+    /// The method called by requests to `path`, like `/helloworld.Greeter/SayHello`.
+    pub fn method(&self, path: &str) -> Option<&'static MethodDescriptor> {
+        self.methods.iter().find(|method| method.path == path)
+    }
+
+    /// The method an HTTP request with the `method` and the `path` maps to,
+    /// through the first matching rule declared with the `google.api.http`
+    /// option.
+    pub fn route(&self, method: &str, path: &str) -> Option<HttpRoute> {
+        self.methods.iter().find_map(|grpc_method| {
+            grpc_method.http_rules.iter().find_map(|rule| {
+                rule.matches(method, path).map(|bindings| HttpRoute {
+                    method: grpc_method,
+                    rule,
+                    bindings,
+                })
+            })
+        })
+    }
+}
+
+/// Describes a package declaring services, generated as the `PACKAGE`
+/// constant of its module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackageDescriptor {
+    /// The name of the package, like `helloworld`.
+    pub name: &'static str,
+    /// The services of the package.
+    pub services: &'static [ServiceDescriptor],
+    /// The encoded `FileDescriptorSet` of the package and its dependencies.
+    pub file_descriptor_set: &'static [u8],
+}
+
+/// The packages compiled together, generated as the `REGISTRY` constant of
+/// the include file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registry {
+    /// The packages declaring services.
+    pub packages: &'static [PackageDescriptor],
+}
+
+impl Registry {
+    /// The services of all the packages.
+    pub fn services(&self) -> impl Iterator<Item = &'static ServiceDescriptor> {
+        self.packages
+            .iter()
+            .flat_map(|package| package.services.iter())
+    }
+
+    /// The service with the fully qualified `name`, like
+    /// `helloworld.Greeter`.
+    pub fn service(&self, name: &str) -> Option<&'static ServiceDescriptor> {
+        self.services().find(|service| service.name == name)
+    }
+
+    /// The method called by requests to `path`, like
+    /// `/helloworld.Greeter/SayHello`, among all the services.
+    pub fn method(&self, path: &str) -> Option<&'static MethodDescriptor> {
+        self.services().find_map(|service| service.method(path))
+    }
+
+    /// The encoded `FileDescriptorSet`s of the packages, for a reflection
+    /// service to load.
+    pub fn file_descriptor_sets(&self) -> impl Iterator<Item = &'static [u8]> {
+        self.packages
+            .iter()
+            .map(|package| package.file_descriptor_set)
+    }
+}
+
+/// Describes a method of a gRPC service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MethodDescriptor {
+    /// The name of the method, like `SayHello`.
+    pub name: &'static str,
+    /// The fully qualified name of its service, like `helloworld.Greeter`.
+    pub service: &'static str,
+    /// The path requests to the method are sent to, like
+    /// `/helloworld.Greeter/SayHello`.
+    pub path: &'static str,
+    /// Whether the method streams its requests, its responses, or both.
+    pub kind: MethodKind,
+    /// The idempotency level of the method, as declared with the
+    /// `idempotency_level` option.
+    pub idempotency_level: IdempotencyLevel,
+    /// The encoded `google.protobuf.MethodOptions` of the method, including
+    /// its custom options.
+    pub options: &'static [u8],
+    /// The REST mappings of the method, declared with the `google.api.http`
+    /// option, its additional bindings included.
+    pub http_rules: &'static [HttpRule],
+}
+
+/// A REST mapping of a method, declared with the `google.api.http` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpRule {
+    /// The HTTP method, like `GET`, or `*` for any method.
+    pub method: &'static str,
+    /// The path template, like `/v1/{name=shelves/*}/books`.
+    pub path: &'static str,
+    /// The field of the request set from the body of the HTTP request, `*`
+    /// for the whole request, or empty when the body is ignored.
+    pub body: &'static str,
+    /// The field of the response sent as the body of the HTTP response, or
+    /// empty for the whole response.
+    pub response_body: &'static str,
+}
+
+impl HttpRule {
+    /// Match an HTTP request on the rule, returning the values of the
+    /// variables of its path template by field path, like
+    /// `("shelf.name", "shelves/1")`.
+    ///
+    /// The values are left percent-encoded, as they appear in the path.
+    pub fn matches(&self, method: &str, path: &str) -> Option<Vec<(&'static str, String)>> {
+        if self.method != "*" && self.method != method {
+            return None;
+        }
+        Template::parse(self.path)?.matches(path)
+    }
+}
+
+/// A method matched by an HTTP request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRoute {
+    /// The method the request maps to.
+    pub method: &'static MethodDescriptor,
+    /// The rule the request matched.
+    pub rule: &'static HttpRule,
+    /// The values of the variables of the path template, by field path.
+    pub bindings: Vec<(&'static str, String)>,
+}
+
+/// A parsed path template, like `/v1/{name=shelves/*}/books:search`.
+#[derive(Debug)]
+struct Template {
+    segments: Vec<Segment>,
+    /// The field paths of the variables, with the segments they span.
+    variables: Vec<(&'static str, Range<usize>)>,
+    verb: Option<&'static str>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(&'static str),
+    /// `*`, matching a single segment.
+    Single,
+    /// `**`, matching any number of segments.
+    Multi,
+}
+
+impl Template {
+    fn parse(template: &'static str) -> Option<Self> {
+        let mut rest = template.strip_prefix('/')?;
+
+        let verb = match rest.rfind(':') {
+            Some(index) if !rest[index..].contains(['/', '}']) => {
+                let verb = &rest[index + 1..];
+                rest = &rest[..index];
+                Some(verb)
+            }
+            _ => None,
+        };
+
+        let mut segments = Vec::new();
+        let mut variables = Vec::new();
+        while !rest.is_empty() {
+            if let Some(variable) = rest.strip_prefix('{') {
+                let end = variable.find('}')?;
+                let (field, pattern) = match variable[..end].split_once('=') {
+                    Some((field, pattern)) => (field, pattern),
+                    None => (&variable[..end], "*"),
+                };
+                let start = segments.len();
+                segments.extend(pattern.split('/').map(Segment::new));
+                variables.push((field, start..segments.len()));
+                rest = &variable[end + 1..];
+            } else {
+                let end = rest.find('/').unwrap_or(rest.len());
+                segments.push(Segment::new(&rest[..end]));
+                rest = &rest[end..];
+            }
+
+            match rest.strip_prefix('/') {
+                Some(next) if !next.is_empty() => rest = next,
+                None if rest.is_empty() => {}
+                _ => return None,
+            }
+        }
+
+        Some(Template {
+            segments,
+            variables,
+            verb,
+        })
+    }
+
+    fn matches(&self, path: &str) -> Option<Vec<(&'static str, String)>> {
+        let mut path = path.strip_prefix('/')?;
+        if let Some(verb) = self.verb {
+            path = path.strip_suffix(verb)?.strip_suffix(':')?;
+        }
+        let parts = match path {
+            "" => Vec::new(),
+            path => path.split('/').collect(),
+        };
+
+        // The range of parts matched by each segment.
+        let mut matched = Vec::with_capacity(self.segments.len());
+        let mut next = 0;
+        for (index, segment) in self.segments.iter().enumerate() {
+            let end = match segment {
+                Segment::Literal(literal) => {
+                    if parts.get(next) != Some(literal) {
+                        return None;
+                    }
+                    next + 1
+                }
+                Segment::Single => {
+                    if next >= parts.len() {
+                        return None;
+                    }
+                    next + 1
+                }
+                Segment::Multi => {
+                    let after = self.segments.len() - index - 1;
+                    parts.len().checked_sub(after).filter(|end| *end >= next)?
+                }
+            };
+            matched.push(next..end);
+            next = end;
+        }
+        if next != parts.len() {
+            return None;
+        }
+
+        let bindings = self
+            .variables
+            .iter()
+            .map(|(field, segments)| {
+                let start = matched[segments.start].start;
+                let end = matched[segments.end - 1].end;
+                (*field, parts[start..end].join("/"))
+            })
+            .collect();
+        Some(bindings)
+    }
+}
+
+impl Segment {
+    fn new(segment: &'static str) -> Self {
+        match segment {
+            "*" => Segment::Single,
+            "**" => Segment::Multi,
+            literal => Segment::Literal(literal),
+        }
+    }
+}
+
+/// Whether a method streams its requests, its responses, or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MethodKind {
+    /// One request, one response.
+    Unary,
+    /// A stream of requests, one response.
+    ClientStreaming,
+    /// One request, a stream of responses.
+    ServerStreaming,
+    /// Streams of requests and responses.
+    Streaming,
+}
+
+impl MethodKind {
+    /// Whether the requests are streamed.
+    pub fn is_client_streaming(self) -> bool {
+        matches!(self, MethodKind::ClientStreaming | MethodKind::Streaming)
+    }
+
+    /// Whether the responses are streamed.
+    pub fn is_server_streaming(self) -> bool {
+        matches!(self, MethodKind::ServerStreaming | MethodKind::Streaming)
+    }
+
+    /// Whether the requests, the responses, or both are streamed.
+    pub fn is_streaming(self) -> bool {
+        self != MethodKind::Unary
+    }
+}
+
+/// The idempotency level of a method, telling whether it can be retried or
+/// cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdempotencyLevel {
+    /// The method may have side effects, the default.
+    Unknown,
+    /// The method has no side effects.
+    NoSideEffects,
+    /// Calling the method several times has the effect of calling it once.
+    Idempotent,
+}
+
+impl IdempotencyLevel {
+    /// Whether calling the method again after it failed is safe.
+    pub fn is_idempotent(self) -> bool {
+        self != IdempotencyLevel::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAY_HELLO: MethodDescriptor = MethodDescriptor {
+        name: "SayHello",
+        service: "helloworld.Greeter",
+        path: "/helloworld.Greeter/SayHello",
+        kind: MethodKind::Unary,
+        idempotency_level: IdempotencyLevel::NoSideEffects,
+        options: &[],
+        http_rules: &[
+            HttpRule {
+                method: "GET",
+                path: "/v1/{name=greeters/*}/hello",
+                body: "",
+                response_body: "",
+            },
+            HttpRule {
+                method: "POST",
+                path: "/v1/hello",
+                body: "*",
+                response_body: "message",
+            },
+        ],
+    };
+
+    const GREETER: ServiceDescriptor = ServiceDescriptor {
+        name: "helloworld.Greeter",
+        methods: &[SAY_HELLO],
+        options: &[],
+    };
+
+    #[test]
+    fn finds_methods_by_path() {
+        assert_eq!(
+            GREETER.method("/helloworld.Greeter/SayHello"),
+            Some(&SAY_HELLO)
+        );
+        assert_eq!(GREETER.method("/helloworld.Greeter/SayBye"), None);
+        assert_eq!(GREETER.method("/helloworld.Other/SayHello"), None);
+    }
+
+    #[test]
+    fn finds_services_of_the_registry() {
+        const REGISTRY: Registry = Registry {
+            packages: &[PackageDescriptor {
+                name: "helloworld",
+                services: &[GREETER],
+                file_descriptor_set: b"set",
+            }],
+        };
+
+        assert_eq!(REGISTRY.services().collect::<Vec<_>>(), vec![&GREETER]);
+        assert_eq!(REGISTRY.service("helloworld.Greeter"), Some(&GREETER));
+        assert_eq!(REGISTRY.service("helloworld.Other"), None);
+        assert_eq!(
+            REGISTRY.method("/helloworld.Greeter/SayHello"),
+            Some(&SAY_HELLO)
+        );
+        assert_eq!(
+            REGISTRY.file_descriptor_sets().collect::<Vec<_>>(),
+            vec![&b"set"[..]]
+        );
+    }
+
+    #[test]
+    fn routes_http_requests() {
+        let route = GREETER.route("GET", "/v1/greeters/1/hello").unwrap();
+        assert_eq!(route.method, &SAY_HELLO);
+        assert_eq!(route.rule, &SAY_HELLO.http_rules[0]);
+        assert_eq!(route.bindings, vec![("name", "greeters/1".to_string())]);
+
+        let route = GREETER.route("POST", "/v1/hello").unwrap();
+        assert_eq!(route.rule.body, "*");
+        assert!(route.bindings.is_empty());
+
+        assert_eq!(GREETER.route("POST", "/v1/greeters/1/hello"), None);
+        assert_eq!(GREETER.route("GET", "/v1/greeters/1/2/hello"), None);
+    }
+
+    fn bindings(template: &'static str, path: &str) -> Option<Vec<(&'static str, String)>> {
+        let rule = HttpRule {
+            method: "*",
+            path: template,
+            body: "",
+            response_body: "",
+        };
+        rule.matches("PATCH", path)
+    }
+
+    #[test]
+    fn matches_path_templates() {
+        assert_eq!(
+            bindings("/v1/{shelf}/books/{book.id}", "/v1/s/books/b"),
+            Some(vec![
+                ("shelf", "s".to_string()),
+                ("book.id", "b".to_string())
+            ])
+        );
+        assert_eq!(
+            bindings("/v1/{name=files/**}", "/v1/files/a/b/c"),
+            Some(vec![("name", "files/a/b/c".to_string())])
+        );
+        assert_eq!(
+            bindings("/v1/{name=files/**}", "/v1/files"),
+            Some(vec![("name", "files".to_string())])
+        );
+        assert_eq!(
+            bindings("/v1/*/{name=**}/end", "/v1/x/a/b/end"),
+            Some(vec![("name", "a/b".to_string())])
+        );
+        assert_eq!(
+            bindings("/v1/{name=operations/*}:cancel", "/v1/operations/7:cancel"),
+            Some(vec![("name", "operations/7".to_string())])
+        );
+        assert_eq!(
+            bindings("/v1/{name=operations/*}:cancel", "/v1/operations/7"),
+            None
+        );
+        assert_eq!(bindings("/v1/{name}", "/v1/a/b"), None);
+        assert_eq!(bindings("/v1/books", "/v1/books/1"), None);
+        assert_eq!(bindings("/v1/books", "/v1"), None);
+        assert_eq!(bindings("v1/books", "/v1/books"), None);
+    }
+
+    #[test]
+    fn method_kinds() {
+        assert!(!MethodKind::Unary.is_streaming());
+        assert!(MethodKind::ClientStreaming.is_client_streaming());
+        assert!(!MethodKind::ClientStreaming.is_server_streaming());
+        assert!(MethodKind::ServerStreaming.is_server_streaming());
+        assert!(MethodKind::Streaming.is_client_streaming());
+        assert!(MethodKind::Streaming.is_server_streaming());
+    }
+}
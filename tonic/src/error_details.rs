@@ -0,0 +1,483 @@
+//! Rich error details, following the [`google.rpc` error model].
+//!
+//! Servers attach [`ErrorDetail`]s to a [`Status`] with
+//! [`Status::with_error_details`]. They are sent as a serialized
+//! `google.rpc.Status` in the `grpc-status-details-bin` trailer, which
+//! clients read back with [`Status::error_details`]:
+//!
+//! ```rust
+//! use tonic::{error_details::{BadRequest, ErrorDetail, FieldViolation}, Code, Status};
+//!
+//! let status = Status::with_error_details(
+//!     Code::InvalidArgument,
+//!     "name is invalid",
+//!     vec![ErrorDetail::BadRequest(BadRequest {
+//!         field_violations: vec![FieldViolation {
+//!             field: "name".to_string(),
+//!             description: "name must not be empty".to_string(),
+//!         }],
+//!     })],
+//! );
+//!
+//! match &status.error_details().unwrap()[..] {
+//!     [ErrorDetail::BadRequest(bad_request)] => {
+//!         assert_eq!(bad_request.field_violations[0].field, "name");
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! ```
+//!
+//! The messages mirror the ones of `google/rpc/error_details.proto`, so they
+//! interoperate with the other gRPC implementations.
+//!
+//! [`google.rpc` error model]: https://cloud.google.com/apis/design/errors#error_model
+
+use crate::{Code, Status};
+use bytes::Bytes;
+use prost::{DecodeError, Message};
+use std::{collections::HashMap, time};
+
+const TYPE_URL_PREFIX: &str = "type.googleapis.com/";
+
+/// The `google.rpc.Status` message carried by `grpc-status-details-bin`.
+#[derive(Clone, PartialEq, Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<Any>,
+}
+
+/// The `google.protobuf.Any` message.
+#[derive(Clone, PartialEq, Message)]
+struct Any {
+    #[prost(string, tag = "1")]
+    type_url: String,
+    #[prost(bytes, tag = "2")]
+    value: Vec<u8>,
+}
+
+/// A standard error detail message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorDetail {
+    /// The reason of the error.
+    ErrorInfo(ErrorInfo),
+    /// When the client may retry a failed request.
+    RetryInfo(RetryInfo),
+    /// Debugging information.
+    DebugInfo(DebugInfo),
+    /// How a quota check failed.
+    QuotaFailure(QuotaFailure),
+    /// Which preconditions failed.
+    PreconditionFailure(PreconditionFailure),
+    /// Which fields of the request are invalid.
+    BadRequest(BadRequest),
+    /// Metadata about the request, to attach to bug reports.
+    RequestInfo(RequestInfo),
+    /// The resource being accessed.
+    ResourceInfo(ResourceInfo),
+    /// Links to documentation.
+    Help(Help),
+    /// An error message the client can show to users.
+    LocalizedMessage(LocalizedMessage),
+    /// A detail message of any other type.
+    Other {
+        /// The type URL of the message, like
+        /// `type.googleapis.com/my.package.MyDetail`.
+        type_url: String,
+        /// The encoded message.
+        value: Vec<u8>,
+    },
+}
+
+impl ErrorDetail {
+    fn to_any(&self) -> Any {
+        fn any(name: &str, message: &impl Message) -> Any {
+            Any {
+                type_url: format!("{}google.rpc.{}", TYPE_URL_PREFIX, name),
+                value: encode(message),
+            }
+        }
+
+        match self {
+            ErrorDetail::ErrorInfo(m) => any("ErrorInfo", m),
+            ErrorDetail::RetryInfo(m) => any("RetryInfo", m),
+            ErrorDetail::DebugInfo(m) => any("DebugInfo", m),
+            ErrorDetail::QuotaFailure(m) => any("QuotaFailure", m),
+            ErrorDetail::PreconditionFailure(m) => any("PreconditionFailure", m),
+            ErrorDetail::BadRequest(m) => any("BadRequest", m),
+            ErrorDetail::RequestInfo(m) => any("RequestInfo", m),
+            ErrorDetail::ResourceInfo(m) => any("ResourceInfo", m),
+            ErrorDetail::Help(m) => any("Help", m),
+            ErrorDetail::LocalizedMessage(m) => any("LocalizedMessage", m),
+            ErrorDetail::Other { type_url, value } => Any {
+                type_url: type_url.clone(),
+                value: value.clone(),
+            },
+        }
+    }
+
+    fn from_any(any: Any) -> Result<Self, DecodeError> {
+        let name = any
+            .type_url
+            .strip_prefix(TYPE_URL_PREFIX)
+            .and_then(|name| name.strip_prefix("google.rpc."));
+        let value = &any.value[..];
+
+        let detail = match name {
+            Some("ErrorInfo") => ErrorDetail::ErrorInfo(Message::decode(value)?),
+            Some("RetryInfo") => ErrorDetail::RetryInfo(Message::decode(value)?),
+            Some("DebugInfo") => ErrorDetail::DebugInfo(Message::decode(value)?),
+            Some("QuotaFailure") => ErrorDetail::QuotaFailure(Message::decode(value)?),
+            Some("PreconditionFailure") => {
+                ErrorDetail::PreconditionFailure(Message::decode(value)?)
+            }
+            Some("BadRequest") => ErrorDetail::BadRequest(Message::decode(value)?),
+            Some("RequestInfo") => ErrorDetail::RequestInfo(Message::decode(value)?),
+            Some("ResourceInfo") => ErrorDetail::ResourceInfo(Message::decode(value)?),
+            Some("Help") => ErrorDetail::Help(Message::decode(value)?),
+            Some("LocalizedMessage") => ErrorDetail::LocalizedMessage(Message::decode(value)?),
+            _ => ErrorDetail::Other {
+                type_url: any.type_url,
+                value: any.value,
+            },
+        };
+
+        Ok(detail)
+    }
+}
+
+impl Status {
+    /// Create a new `Status` with the associated code, message and rich
+    /// error details.
+    pub fn with_error_details(
+        code: Code,
+        message: impl Into<String>,
+        details: Vec<ErrorDetail>,
+    ) -> Status {
+        let message = message.into();
+        let status = RpcStatus {
+            code: code as i32,
+            message: message.clone(),
+            details: details.iter().map(ErrorDetail::to_any).collect(),
+        };
+
+        Status::with_details(code, message, Bytes::from(encode(&status)))
+    }
+
+    /// Get the rich error details of this `Status`.
+    ///
+    /// Returns an empty list if the status has no details, and an error if
+    /// its details are not a valid `google.rpc.Status`.
+    pub fn error_details(&self) -> Result<Vec<ErrorDetail>, DecodeError> {
+        RpcStatus::decode(self.details())?
+            .details
+            .into_iter()
+            .map(ErrorDetail::from_any)
+            .collect()
+    }
+
+    /// Create a new `Status` with the associated code and message, pushing
+    /// back clients by asking them to wait for `delay` before retrying.
+    ///
+    /// The delay is sent as a [`RetryInfo`] error detail.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use tonic::{Code, Status};
+    ///
+    /// let status = Status::with_retry_delay(Code::ResourceExhausted, "slow down", Duration::from_secs(2));
+    /// assert!(status.is_retryable());
+    /// assert_eq!(status.retry_delay(), Some(Duration::from_secs(2)));
+    /// ```
+    ///
+    /// [`RetryInfo`]: struct.RetryInfo.html
+    pub fn with_retry_delay(
+        code: Code,
+        message: impl Into<String>,
+        delay: time::Duration,
+    ) -> Status {
+        let retry_info = RetryInfo {
+            retry_delay: Some(delay.into()),
+        };
+        Status::with_error_details(code, message, vec![ErrorDetail::RetryInfo(retry_info)])
+    }
+
+    /// Get how long the server asked to wait before retrying, from the
+    /// [`RetryInfo`] error detail of this `Status`, if any.
+    ///
+    /// [`RetryInfo`]: struct.RetryInfo.html
+    pub fn retry_delay(&self) -> Option<time::Duration> {
+        self.error_details()
+            .ok()?
+            .into_iter()
+            .find_map(|detail| match detail {
+                ErrorDetail::RetryInfo(RetryInfo {
+                    retry_delay: Some(delay),
+                }) => Some(delay.into()),
+                _ => None,
+            })
+    }
+}
+
+fn encode(message: &impl Message) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(message.encoded_len());
+    message
+        .encode(&mut buf)
+        .expect("Message only errors if not enough space");
+    buf
+}
+
+/// Describes the cause of the error with structured details.
+#[derive(Clone, PartialEq, Message)]
+pub struct ErrorInfo {
+    /// The reason of the error, as a constant in `UPPER_SNAKE_CASE`.
+    #[prost(string, tag = "1")]
+    pub reason: String,
+    /// The logical grouping to which the reason belongs, typically the
+    /// service name.
+    #[prost(string, tag = "2")]
+    pub domain: String,
+    /// Additional structured details about the error.
+    #[prost(map = "string, string", tag = "3")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Describes when the client may retry a failed request.
+#[derive(Clone, PartialEq, Message)]
+pub struct RetryInfo {
+    /// How long the client should wait before retrying.
+    #[prost(message, optional, tag = "1")]
+    pub retry_delay: Option<Duration>,
+}
+
+/// A `google.protobuf.Duration`.
+#[derive(Clone, PartialEq, Message)]
+pub struct Duration {
+    /// Whole seconds of the duration.
+    #[prost(int64, tag = "1")]
+    pub seconds: i64,
+    /// Nanosecond fraction of the duration.
+    #[prost(int32, tag = "2")]
+    pub nanos: i32,
+}
+
+impl From<time::Duration> for Duration {
+    fn from(duration: time::Duration) -> Self {
+        Duration {
+            seconds: duration.as_secs() as i64,
+            nanos: duration.subsec_nanos() as i32,
+        }
+    }
+}
+
+impl From<Duration> for time::Duration {
+    /// Negative durations are clamped to zero.
+    fn from(duration: Duration) -> Self {
+        if duration.seconds < 0 || duration.nanos < 0 {
+            return time::Duration::from_secs(0);
+        }
+        time::Duration::new(duration.seconds as u64, duration.nanos as u32)
+    }
+}
+
+/// Describes additional debugging info.
+#[derive(Clone, PartialEq, Message)]
+pub struct DebugInfo {
+    /// The stack trace entries indicating where the error occurred.
+    #[prost(string, repeated, tag = "1")]
+    pub stack_entries: Vec<String>,
+    /// Additional debugging information provided by the server.
+    #[prost(string, tag = "2")]
+    pub detail: String,
+}
+
+/// Describes how a quota check failed.
+#[derive(Clone, PartialEq, Message)]
+pub struct QuotaFailure {
+    /// The quota violations.
+    #[prost(message, repeated, tag = "1")]
+    pub violations: Vec<QuotaViolation>,
+}
+
+/// A single quota violation.
+#[derive(Clone, PartialEq, Message)]
+pub struct QuotaViolation {
+    /// The subject on which the quota check failed, like `project:<id>`.
+    #[prost(string, tag = "1")]
+    pub subject: String,
+    /// How the quota check failed.
+    #[prost(string, tag = "2")]
+    pub description: String,
+}
+
+/// Describes which preconditions failed.
+#[derive(Clone, PartialEq, Message)]
+pub struct PreconditionFailure {
+    /// The precondition violations.
+    #[prost(message, repeated, tag = "1")]
+    pub violations: Vec<PreconditionViolation>,
+}
+
+/// A single precondition violation.
+#[derive(Clone, PartialEq, Message)]
+pub struct PreconditionViolation {
+    /// The type of the precondition, like `TOS`.
+    #[prost(string, tag = "1")]
+    pub r#type: String,
+    /// The subject the precondition failed for.
+    #[prost(string, tag = "2")]
+    pub subject: String,
+    /// How the precondition failed.
+    #[prost(string, tag = "3")]
+    pub description: String,
+}
+
+/// Describes violations in a client request.
+#[derive(Clone, PartialEq, Message)]
+pub struct BadRequest {
+    /// The field violations.
+    #[prost(message, repeated, tag = "1")]
+    pub field_violations: Vec<FieldViolation>,
+}
+
+/// A single invalid field of a request.
+#[derive(Clone, PartialEq, Message)]
+pub struct FieldViolation {
+    /// The path to the field, like `user.name`.
+    #[prost(string, tag = "1")]
+    pub field: String,
+    /// Why the field is invalid.
+    #[prost(string, tag = "2")]
+    pub description: String,
+}
+
+/// Metadata about the request, for bug reports.
+#[derive(Clone, PartialEq, Message)]
+pub struct RequestInfo {
+    /// An opaque string identifying the request in the server logs.
+    #[prost(string, tag = "1")]
+    pub request_id: String,
+    /// Any data used to serve the request.
+    #[prost(string, tag = "2")]
+    pub serving_data: String,
+}
+
+/// Describes the resource being accessed.
+#[derive(Clone, PartialEq, Message)]
+pub struct ResourceInfo {
+    /// The type of the resource.
+    #[prost(string, tag = "1")]
+    pub resource_type: String,
+    /// The name of the resource.
+    #[prost(string, tag = "2")]
+    pub resource_name: String,
+    /// The owner of the resource.
+    #[prost(string, tag = "3")]
+    pub owner: String,
+    /// What error was encountered accessing the resource.
+    #[prost(string, tag = "4")]
+    pub description: String,
+}
+
+/// Links to documentation for the error.
+#[derive(Clone, PartialEq, Message)]
+pub struct Help {
+    /// The links.
+    #[prost(message, repeated, tag = "1")]
+    pub links: Vec<Link>,
+}
+
+/// A link to documentation.
+#[derive(Clone, PartialEq, Message)]
+pub struct Link {
+    /// What the link offers.
+    #[prost(string, tag = "1")]
+    pub description: String,
+    /// The URL of the link.
+    #[prost(string, tag = "2")]
+    pub url: String,
+}
+
+/// An error message that is safe to show to users.
+#[derive(Clone, PartialEq, Message)]
+pub struct LocalizedMessage {
+    /// The locale of the message, like `en-US`.
+    #[prost(string, tag = "1")]
+    pub locale: String,
+    /// The localized message.
+    #[prost(string, tag = "2")]
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let details = vec![
+            ErrorDetail::ErrorInfo(ErrorInfo {
+                reason: "QUOTA_EXCEEDED".to_string(),
+                domain: "example.com".to_string(),
+                metadata: vec![("limit".to_string(), "10".to_string())]
+                    .into_iter()
+                    .collect(),
+            }),
+            ErrorDetail::RetryInfo(RetryInfo {
+                retry_delay: Some(time::Duration::from_millis(1500).into()),
+            }),
+            ErrorDetail::Other {
+                type_url: "type.googleapis.com/my.Detail".to_string(),
+                value: vec![1, 2, 3],
+            },
+        ];
+
+        let status =
+            Status::with_error_details(Code::ResourceExhausted, "slow down", details.clone());
+        let header_map = status.to_header_map().unwrap();
+        let status = Status::from_header_map(&header_map).unwrap();
+
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(status.error_details().unwrap(), details);
+    }
+
+    #[test]
+    fn no_details() {
+        let status = Status::internal("oops");
+        assert!(status.error_details().unwrap().is_empty());
+    }
+
+    #[test]
+    fn retry_delay() {
+        let delay = time::Duration::from_millis(1500);
+        let status = Status::with_retry_delay(Code::Aborted, "conflict", delay);
+        assert_eq!(status.retry_delay(), Some(delay));
+        assert!(status.is_retryable());
+
+        // Other details are skipped.
+        let status = Status::with_error_details(
+            Code::Aborted,
+            "conflict",
+            vec![
+                ErrorDetail::Help(Help { links: vec![] }),
+                ErrorDetail::RetryInfo(RetryInfo {
+                    retry_delay: Some(delay.into()),
+                }),
+            ],
+        );
+        assert_eq!(status.retry_delay(), Some(delay));
+
+        let status = Status::aborted("conflict");
+        assert_eq!(status.retry_delay(), None);
+        assert!(!status.is_retryable());
+    }
+
+    #[test]
+    fn invalid_details() {
+        let status = Status::with_details(Code::Internal, "oops", Bytes::from_static(b"\xff"));
+        assert!(status.error_details().is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! Fault injection, for resilience tests.
+//!
+//! A [`FaultLayer`] wraps a channel, or the services added to a server,
+//! delaying or failing some of their calls, so clients can be tested
+//! against slow or failing servers without a proxy in between:
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tonic::fault::{Fault, FaultLayer};
+//! use tonic::Code;
+//!
+//! let faults = FaultLayer::new()
+//!     .fault(Fault::new().delay(Duration::from_millis(500)).sample(0.1))
+//!     .fault(
+//!         Fault::new()
+//!             .abort(Code::Unavailable, "injected fault")
+//!             .method("/helloworld.Greeter/SayHello")
+//!             .metadata("x-fault", "unavailable"),
+//!     );
+//!
+//! // Server::builder().add_service(faults.layer(GreeterServer::new(greeter)))
+//! ```
+//!
+//! [`FaultLayer`]: struct.FaultLayer.html
+
+use crate::{body::BoxBody, transport::NamedService, Code, Status};
+use bytes::Bytes;
+use http_body::Body as HttpBody;
+use std::{
+    future::Future,
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower::{layer::Layer, Service};
+
+/// Wraps services with [`FaultInjection`], injecting [`Fault`]s in their
+/// calls.
+///
+/// The faults are tried in the order they were added, the first one
+/// selecting a call being injected.
+///
+/// [`FaultInjection`]: struct.FaultInjection.html
+/// [`Fault`]: struct.Fault.html
+#[derive(Debug, Clone, Default)]
+pub struct FaultLayer {
+    faults: Vec<Fault>,
+}
+
+impl FaultLayer {
+    /// A layer injecting no fault.
+    pub fn new() -> Self {
+        FaultLayer::default()
+    }
+
+    /// Inject `fault`, if no fault added before it was.
+    pub fn fault(mut self, fault: Fault) -> Self {
+        self.faults.push(fault);
+        self
+    }
+
+    fn select<B>(&self, request: &http::Request<B>) -> Option<&Fault> {
+        self.faults.iter().find(|fault| fault.selects(request))
+    }
+}
+
+impl<S> Layer<S> for FaultLayer {
+    type Service = FaultInjection<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FaultInjection {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// A delay, a failure, or both, injected in the calls it selects.
+///
+/// A fault selects all the calls by default, and does nothing until given
+/// a [`delay`] or a status to [`abort`] with.
+///
+/// [`delay`]: #method.delay
+/// [`abort`]: #method.abort
+#[derive(Debug, Clone)]
+pub struct Fault {
+    delay: Option<Duration>,
+    abort: Option<(Code, String)>,
+    rate: f64,
+    methods: Vec<String>,
+    metadata: Vec<(String, String)>,
+}
+
+impl Fault {
+    /// A fault selecting all the calls, doing nothing.
+    pub fn new() -> Self {
+        Fault {
+            delay: None,
+            abort: None,
+            rate: 1.0,
+            methods: Vec::new(),
+            metadata: Vec::new(),
+        }
+    }
+
+    /// Delay the calls by `delay`, before sending or handling them, or
+    /// before failing them.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Fail the calls with `code` and `message`, without sending or
+    /// handling them.
+    pub fn abort(mut self, code: Code, message: impl Into<String>) -> Self {
+        self.abort = Some((code, message.into()));
+        self
+    }
+
+    /// Only select `rate`, between 0 and 1, of the calls at random.
+    pub fn sample(mut self, rate: f64) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Only select the calls of the method at `path`, like
+    /// `/helloworld.Greeter/SayHello`, and of the other methods added.
+    pub fn method(mut self, path: impl Into<String>) -> Self {
+        self.methods.push(path.into());
+        self
+    }
+
+    /// Only select the calls whose metadata entry `key` is `value`, like a
+    /// header set by the test asking for the fault.
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.push((key.into(), value.into()));
+        self
+    }
+
+    fn selects<B>(&self, request: &http::Request<B>) -> bool {
+        let path = request.uri().path();
+        if !self.methods.is_empty() && !self.methods.iter().any(|method| method == path) {
+            return false;
+        }
+
+        let headers = request.headers();
+        self.metadata.iter().all(|(key, value)| {
+            headers
+                .get(key.as_str())
+                .is_some_and(|v| v.as_bytes() == value.as_bytes())
+        }) && rand::random::<f64>() < self.rate
+    }
+}
+
+impl Default for Fault {
+    fn default() -> Self {
+        Fault::new()
+    }
+}
+
+/// A service injecting faults in its calls, created by a [`FaultLayer`].
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`FaultLayer`]: struct.FaultLayer.html
+/// [`Server`]: ../transport/struct.Server.html
+#[derive(Debug, Clone)]
+pub struct FaultInjection<S> {
+    inner: S,
+    layer: FaultLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for FaultInjection<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>> + Clone + Send + 'static,
+    S::Error: Into<crate::Error>,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+    ResBody: HttpBody<Data = Bytes> + Send + Sync + 'static,
+    ResBody::Error: Into<crate::Error>,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = crate::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = mem::replace(&mut self.inner, clone);
+
+        let (delay, abort) = match self.layer.select(&request) {
+            Some(fault) => (fault.delay, fault.abort.clone()),
+            None => (None, None),
+        };
+        Box::pin(async move {
+            if let Some(delay) = delay {
+                tokio::time::delay_for(delay).await;
+            }
+            if let Some((code, message)) = abort {
+                return Ok(Status::new(code, message).to_http());
+            }
+
+            let response = inner.call(request).await.map_err(Into::into)?;
+            Ok(response.map(BoxBody::map_from))
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for FaultInjection<S> {
+    const NAME: &'static str = S::NAME;
+}
@@ -0,0 +1,389 @@
+//! Latency and message size histograms of each method, kept in process.
+//!
+//! A [`Histograms`] is a [`MetricsRecorder`] keeping, for each method a
+//! client calls or a server serves, the histograms of the latencies of its
+//! calls and the sizes of their messages, so a process can expose their
+//! percentiles, like on a debug page, without a metrics pipeline:
+//!
+//! ```rust
+//! use tonic::histograms::Histograms;
+//! use tonic::metrics::MetricsLayer;
+//!
+//! let histograms = Histograms::new();
+//! let metrics = MetricsLayer::server(histograms.clone());
+//!
+//! // Server::builder().add_service(metrics.layer(GreeterServer::new(greeter)))
+//!
+//! for method in histograms.snapshot() {
+//!     println!(
+//!         "{}/{}: p50={}us p99={}us",
+//!         method.service(),
+//!         method.method(),
+//!         method.latency().percentile(0.5),
+//!         method.latency().percentile(0.99),
+//!     );
+//! }
+//! ```
+//!
+//! Recording only bumps atomic counters, the histograms of the methods
+//! being created on their first call. Their buckets grow exponentially, so
+//! the percentiles are within 12.5% of the values recorded.
+//!
+//! [`Histograms`]: struct.Histograms.html
+//! [`MetricsRecorder`]: ../metrics/trait.MetricsRecorder.html
+
+use crate::{
+    metrics::{CallInfo, MetricsRecorder, Side},
+    Code,
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+/// The values below are counted exactly, each in its own bucket.
+const EXACT: u64 = 16;
+
+/// The number of buckets each power of two above `EXACT` is split into.
+const SUB_BUCKETS: u64 = 8;
+
+const SUB_BUCKET_BITS: u32 = 3;
+
+const BUCKETS: usize = (EXACT + (64 - 4) * SUB_BUCKETS) as usize;
+
+fn bucket(value: u64) -> usize {
+    if value < EXACT {
+        return value as usize;
+    }
+    let exp = 63 - u64::from(value.leading_zeros());
+    let sub = (value >> (exp - u64::from(SUB_BUCKET_BITS))) & (SUB_BUCKETS - 1);
+    (EXACT + (exp - 4) * SUB_BUCKETS + sub) as usize
+}
+
+/// The largest value of the bucket `index`.
+fn upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < EXACT {
+        return index;
+    }
+    let exp = (index - EXACT) / SUB_BUCKETS + 4;
+    let sub = (index - EXACT) % SUB_BUCKETS;
+    let width = 1 << (exp - u64::from(SUB_BUCKET_BITS));
+    let lower = (SUB_BUCKETS + sub) * width;
+    lower + (width - 1)
+}
+
+/// A [`MetricsRecorder`] keeping the histograms of each method.
+///
+/// It is cheap to clone, its clones recording to the same histograms, so a
+/// clone is handed to the [`MetricsLayer`] and another one kept to take
+/// snapshots.
+///
+/// [`MetricsRecorder`]: ../metrics/trait.MetricsRecorder.html
+/// [`MetricsLayer`]: ../metrics/struct.MetricsLayer.html
+#[derive(Clone, Default)]
+pub struct Histograms {
+    methods: Arc<RwLock<HashMap<CallInfo, Arc<Method>>>>,
+}
+
+impl Histograms {
+    /// Create empty histograms.
+    pub fn new() -> Self {
+        Histograms::default()
+    }
+
+    fn method(&self, call: &CallInfo) -> Arc<Method> {
+        if let Some(method) = self.methods.read().unwrap().get(call) {
+            return method.clone();
+        }
+        self.methods
+            .write()
+            .unwrap()
+            .entry(call.clone())
+            .or_insert_with(|| Arc::new(Method::default()))
+            .clone()
+    }
+
+    /// A snapshot of the histograms of the methods called so far, sorted by
+    /// side, service and method.
+    pub fn snapshot(&self) -> Vec<MethodSnapshot> {
+        let methods = self.methods.read().unwrap();
+        let mut snapshots = methods
+            .iter()
+            .map(|(call, method)| MethodSnapshot {
+                call: call.clone(),
+                latency: method.latency.snapshot(),
+                request_size: method.request_size.snapshot(),
+                response_size: method.response_size.snapshot(),
+            })
+            .collect::<Vec<_>>();
+        fn key(snapshot: &MethodSnapshot) -> (bool, &str, &str) {
+            let server = snapshot.side() == Side::Server;
+            (server, snapshot.service(), snapshot.method())
+        }
+        snapshots.sort_by(|a, b| key(a).cmp(&key(b)));
+        snapshots
+    }
+
+    /// Clear the histograms, to start over.
+    pub fn reset(&self) {
+        self.methods.write().unwrap().clear();
+    }
+}
+
+impl MetricsRecorder for Histograms {
+    fn call_completed(&self, call: &CallInfo, _code: Code, latency: Duration) {
+        let micros = latency.as_micros();
+        let micros = if micros > u128::from(u64::MAX) {
+            u64::MAX
+        } else {
+            micros as u64
+        };
+        self.method(call).latency.record(micros);
+    }
+
+    fn message_sent(&self, call: &CallInfo, size: usize) {
+        let method = self.method(call);
+        match call.side() {
+            Side::Client => method.request_size.record(size as u64),
+            Side::Server => method.response_size.record(size as u64),
+        }
+    }
+
+    fn message_received(&self, call: &CallInfo, size: usize) {
+        let method = self.method(call);
+        match call.side() {
+            Side::Client => method.response_size.record(size as u64),
+            Side::Server => method.request_size.record(size as u64),
+        }
+    }
+}
+
+impl fmt::Debug for Histograms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.snapshot()).finish()
+    }
+}
+
+#[derive(Default)]
+struct Method {
+    latency: Histogram,
+    request_size: Histogram,
+    response_size: Histogram,
+}
+
+struct Histogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            max: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    fn record(&self, value: u64) {
+        self.buckets[bucket(value)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+        self.max.fetch_max(value, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|count| count.load(Ordering::Relaxed))
+                .collect(),
+            count: self.count.load(Ordering::Relaxed),
+            sum: self.sum.load(Ordering::Relaxed),
+            max: self.max.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The histograms of a method, at the time of a snapshot.
+#[derive(Debug, Clone)]
+pub struct MethodSnapshot {
+    call: CallInfo,
+    latency: HistogramSnapshot,
+    request_size: HistogramSnapshot,
+    response_size: HistogramSnapshot,
+}
+
+impl MethodSnapshot {
+    /// Whether the method is called by a client or served by a server.
+    pub fn side(&self) -> Side {
+        self.call.side()
+    }
+
+    /// The fully qualified name of the service of the method, like
+    /// `helloworld.Greeter`.
+    pub fn service(&self) -> &str {
+        self.call.service()
+    }
+
+    /// The name of the method, like `SayHello`.
+    pub fn method(&self) -> &str {
+        self.call.method()
+    }
+
+    /// The latencies of the calls completed, in microseconds.
+    pub fn latency(&self) -> &HistogramSnapshot {
+        &self.latency
+    }
+
+    /// The sizes of the request messages, in bytes, as sent on the wire.
+    pub fn request_size(&self) -> &HistogramSnapshot {
+        &self.request_size
+    }
+
+    /// The sizes of the response messages, in bytes, as sent on the wire.
+    pub fn response_size(&self) -> &HistogramSnapshot {
+        &self.response_size
+    }
+}
+
+impl fmt::Display for MethodSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}: latency(us) {}, request size {}, response size {}",
+            self.service(),
+            self.method(),
+            self.latency,
+            self.request_size,
+            self.response_size
+        )
+    }
+}
+
+/// A histogram, at the time of a snapshot.
+#[derive(Clone, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    buckets: Vec<u64>,
+    count: u64,
+    sum: u64,
+    max: u64,
+}
+
+impl HistogramSnapshot {
+    /// The number of values recorded.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The sum of the values recorded.
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+
+    /// The largest value recorded, or 0 if none was.
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// The mean of the values recorded, or 0 if none was.
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.sum as f64 / self.count as f64
+    }
+
+    /// The value below which the fraction `quantile` of the values recorded
+    /// fall, like `0.99` for the 99th percentile, or 0 if none was.
+    ///
+    /// It is the largest value of the bucket of the percentile, so above
+    /// the exact percentile by at most 12.5%, and never above `max`.
+    pub fn percentile(&self, quantile: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let quantile = quantile.clamp(0.0, 1.0);
+        let rank = ((quantile * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return upper_bound(index).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+impl fmt::Debug for HistogramSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HistogramSnapshot")
+            .field("count", &self.count)
+            .field("sum", &self.sum)
+            .field("p50", &self.percentile(0.5))
+            .field("p99", &self.percentile(0.99))
+            .field("max", &self.max)
+            .finish()
+    }
+}
+
+impl fmt::Display for HistogramSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "count={} p50={} p90={} p99={} max={}",
+            self.count,
+            self.percentile(0.5),
+            self.percentile(0.9),
+            self.percentile(0.99),
+            self.max
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets() {
+        for value in (0..100_000).chain(vec![u64::MAX / 3, u64::MAX]) {
+            let index = bucket(value);
+            assert!(index < BUCKETS);
+            assert!(value <= upper_bound(index), "{}", value);
+            assert!(upper_bound(index) - value <= value / 8, "{}", value);
+            if index > 0 {
+                assert!(upper_bound(index - 1) < value, "{}", value);
+            }
+        }
+    }
+
+    #[test]
+    fn percentiles() {
+        let histogram = Histogram::default();
+        for value in 1..=1000 {
+            histogram.record(value);
+        }
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 1000);
+        assert_eq!(snapshot.max(), 1000);
+        assert_eq!(snapshot.mean(), 500.5);
+        for &(quantile, exact) in &[(0.5, 500), (0.9, 900), (0.99, 990), (1.0, 1000)] {
+            let value = snapshot.percentile(quantile);
+            assert!(value >= exact && value - exact <= exact / 8, "{}", value);
+        }
+    }
+}
@@ -14,6 +14,17 @@ use std::{fmt, sync::Arc};
 /// These interceptors do not allow you to modify the `Message` of the request
 /// but allow you to check for metadata. If you would like to apply middleware like
 /// features to the body of the request, going through the `tower` abstraction is recommended.
+///
+/// On the server side the request passed to the interceptor also carries the
+/// connection level information of the `transport` server, so authorization
+/// decisions can be based on [`Request::remote_addr`], [`Request::peer_certs`]
+/// and [`Request::is_tls`]. Values inserted into [`Request::extensions_mut`]
+/// are forwarded to the service handler.
+///
+/// [`Request::remote_addr`]: struct.Request.html#method.remote_addr
+/// [`Request::peer_certs`]: struct.Request.html#method.peer_certs
+/// [`Request::is_tls`]: struct.Request.html#method.is_tls
+/// [`Request::extensions_mut`]: struct.Request.html#method.extensions_mut
 #[derive(Clone)]
 pub struct Interceptor {
     f: Arc<dyn Fn(Request<()>) -> Result<Request<()>, Status> + Send + Sync + 'static>,
@@ -27,6 +38,28 @@ impl Interceptor {
         Interceptor { f: Arc::new(f) }
     }
 
+    /// Run `next` on the requests this interceptor let through, composing
+    /// both into a single interceptor.
+    ///
+    /// ```rust
+    /// # use tonic::{interceptor_fn, Request, Status};
+    /// let interceptor = interceptor_fn(|req: Request<()>| {
+    ///     if req.metadata().contains_key("authorization") {
+    ///         Ok(req)
+    ///     } else {
+    ///         Err(Status::unauthenticated("missing credentials"))
+    ///     }
+    /// })
+    /// .and_then(|mut req: Request<()>| {
+    ///     req.metadata_mut().insert("x-tenant", "acme".parse().unwrap());
+    ///     Ok(req)
+    /// });
+    /// ```
+    pub fn and_then(self, next: impl Into<Interceptor>) -> Interceptor {
+        let next = next.into();
+        Interceptor::new(move |req| (next.f)((self.f)(req)?))
+    }
+
     pub(crate) fn call<T>(&self, req: Request<T>) -> Result<Request<T>, Status> {
         let (metadata, ext, message) = req.into_parts();
 
@@ -38,6 +71,58 @@ impl Interceptor {
     }
 }
 
+/// Create an [`Interceptor`] from `f`, to compose with others through
+/// [`Interceptor::and_then`].
+pub fn interceptor_fn(
+    f: impl Fn(Request<()>) -> Result<Request<()>, Status> + Send + Sync + 'static,
+) -> Interceptor {
+    Interceptor::new(f)
+}
+
+/// A sequence of interceptors, run in the order they were pushed as a single
+/// [`Interceptor`], the first failing one failing the request.
+///
+/// Useful to assemble the interceptors of a server or client from its
+/// configuration, like authentication, tracing or tenant injection:
+///
+/// ```rust,ignore
+/// let mut chain = InterceptorChain::new();
+/// if config.auth {
+///     chain = chain.push(check_auth);
+/// }
+/// chain = chain.push(inject_tenant);
+///
+/// let server = GreeterServer::with_interceptor(greeter, chain);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct InterceptorChain {
+    interceptors: Vec<Interceptor>,
+}
+
+impl InterceptorChain {
+    /// Create an empty chain, letting all the requests through.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `interceptor` after the interceptors already in the chain.
+    pub fn push(mut self, interceptor: impl Into<Interceptor>) -> Self {
+        self.interceptors.push(interceptor.into());
+        self
+    }
+}
+
+impl From<InterceptorChain> for Interceptor {
+    fn from(chain: InterceptorChain) -> Self {
+        let interceptors = chain.interceptors;
+        Interceptor::new(move |req| {
+            interceptors
+                .iter()
+                .try_fold(req, |req, interceptor| (interceptor.f)(req))
+        })
+    }
+}
+
 impl<F> From<F> for Interceptor
 where
     F: Fn(Request<()>) -> Result<Request<()>, Status> + Send + Sync + 'static,
@@ -52,3 +137,58 @@ impl fmt::Debug for Interceptor {
         f.debug_struct("Interceptor").finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+
+    fn tag(value: &'static str) -> Interceptor {
+        interceptor_fn(move |mut req: Request<()>| {
+            let tags = match req.metadata().get("tags") {
+                Some(tags) => format!("{},{}", tags.to_str().unwrap(), value),
+                None => value.to_string(),
+            };
+            req.metadata_mut().insert("tags", tags.parse().unwrap());
+            Ok(req)
+        })
+    }
+
+    fn tags(interceptor: &Interceptor) -> Result<String, Status> {
+        let req = interceptor.call(Request::new(1))?;
+        assert_eq!(*req.get_ref(), 1);
+        Ok(req
+            .metadata()
+            .get("tags")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string())
+    }
+
+    #[test]
+    fn runs_composed_interceptors_in_order() {
+        let interceptor = tag("auth").and_then(tag("tracing"));
+        assert_eq!(tags(&interceptor).unwrap(), "auth,tracing");
+
+        let chain = InterceptorChain::new()
+            .push(tag("auth"))
+            .push(tag("tracing"))
+            .push(tag("tenant"));
+        assert_eq!(tags(&chain.into()).unwrap(), "auth,tracing,tenant");
+    }
+
+    #[test]
+    fn stops_at_the_first_failure() {
+        let interceptor = InterceptorChain::new()
+            .push(tag("auth"))
+            .push(|_| Err(Status::permission_denied("denied")))
+            .push(|_: Request<()>| -> Result<Request<()>, Status> { unreachable!() })
+            .into();
+
+        assert_eq!(
+            tags(&interceptor).unwrap_err().code(),
+            Code::PermissionDenied
+        );
+    }
+}
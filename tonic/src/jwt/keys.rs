@@ -0,0 +1,353 @@
+use super::Algorithm;
+use crate::{body::BoxBody, transport::Channel, Status};
+use futures_util::lock::Mutex;
+use http::{header, Request, Uri};
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde_json::Value;
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tower::ServiceExt;
+
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+/// How long to wait before fetching the keys again for a token signed by an
+/// unknown key, so forged tokens can't make the server hammer the issuer.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The public keys the tokens are signed with, as a JSON Web Key Set.
+///
+/// The keys are either given, or fetched from the `jwks_uri` of the issuer,
+/// cached, and fetched again every [`refresh_interval`], or when a token is
+/// signed by a key that isn't in the set, like after the keys are rotated.
+/// While they can't be fetched, the tokens are validated with the keys
+/// fetched last, if any.
+///
+/// [`refresh_interval`]: #method.refresh_interval
+#[derive(Clone)]
+pub struct KeySet {
+    remote: Option<Remote>,
+    cached: Arc<Mutex<Cached>>,
+}
+
+#[derive(Clone)]
+struct Remote {
+    channel: Channel,
+    jwks_uri: Uri,
+    refresh_interval: Duration,
+}
+
+#[derive(Default)]
+struct Cached {
+    keys: Arc<Vec<Jwk>>,
+    fetched_at: Option<Instant>,
+}
+
+impl KeySet {
+    /// The keys of the JSON Web Key Set `json`, like
+    /// `{"keys": [{"kty": "RSA", "kid": "...", "n": "...", "e": "AQAB"}]}`.
+    ///
+    /// The keys of an unsupported type or curve are ignored.
+    pub fn from_json(json: &[u8]) -> Result<Self, InvalidKeySet> {
+        let keys = parse_jwks(json)?;
+        Ok(KeySet {
+            remote: None,
+            cached: Arc::new(Mutex::new(Cached {
+                keys: Arc::new(keys),
+                fetched_at: None,
+            })),
+        })
+    }
+
+    /// The keys fetched from `jwks_uri` over `channel`, connected to the
+    /// issuer.
+    pub fn remote(channel: Channel, jwks_uri: Uri) -> Self {
+        KeySet {
+            remote: Some(Remote {
+                channel,
+                jwks_uri,
+                refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            }),
+            cached: Arc::new(Mutex::new(Cached::default())),
+        }
+    }
+
+    /// Fetch the keys again after `refresh_interval`, by default one hour.
+    ///
+    /// Has no effect on the given keys.
+    pub fn refresh_interval(mut self, refresh_interval: Duration) -> Self {
+        if let Some(remote) = &mut self.remote {
+            remote.refresh_interval = refresh_interval;
+        }
+        self
+    }
+
+    /// The keys a token signed with `alg` by the key `kid` may be verified
+    /// with.
+    pub(crate) async fn keys(&self, kid: Option<&str>, alg: Algorithm) -> Result<Vec<Jwk>, Status> {
+        let remote = match &self.remote {
+            Some(remote) => remote,
+            None => return Ok(matching(&self.cached.lock().await.keys, kid, alg)),
+        };
+
+        let mut cached = self.cached.lock().await;
+        let age = cached.fetched_at.map(|at| at.elapsed());
+        let keys = matching(&cached.keys, kid, alg);
+        let refresh = match age {
+            None => true,
+            Some(age) if age >= remote.refresh_interval => true,
+            Some(age) => keys.is_empty() && age >= MIN_REFRESH_INTERVAL,
+        };
+        if !refresh {
+            return Ok(keys);
+        }
+
+        match remote.fetch().await {
+            Ok(fetched) => {
+                *cached = Cached {
+                    keys: Arc::new(fetched),
+                    fetched_at: Some(Instant::now()),
+                };
+                Ok(matching(&cached.keys, kid, alg))
+            }
+            Err(status) => {
+                tracing::warn!("failed to fetch the JSON Web Key Set: {}", status.message());
+                if cached.fetched_at.is_none() {
+                    return Err(status);
+                }
+                Ok(keys)
+            }
+        }
+    }
+}
+
+impl Remote {
+    async fn fetch(&self) -> Result<Vec<Jwk>, Status> {
+        let request = Request::get(self.jwks_uri.clone())
+            .header(header::ACCEPT, "application/json")
+            .body(BoxBody::empty())
+            .map_err(|_| Status::internal("invalid JSON Web Key Set request"))?;
+
+        let unavailable = |e: &dyn fmt::Display| {
+            Status::unavailable(format!("failed to fetch the JSON Web Key Set: {}", e))
+        };
+        let response = self
+            .channel
+            .clone()
+            .oneshot(request)
+            .await
+            .map_err(|e| unavailable(&e))?;
+        if !response.status().is_success() {
+            return Err(unavailable(&response.status()));
+        }
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|e| unavailable(&e))?;
+
+        parse_jwks(&body).map_err(|e| unavailable(&e))
+    }
+}
+
+impl fmt::Debug for KeySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("KeySet");
+        if let Some(remote) = &self.remote {
+            f.field("jwks_uri", &remote.jwks_uri)
+                .field("refresh_interval", &remote.refresh_interval);
+        }
+        f.finish()
+    }
+}
+
+/// The error of a JSON Web Key Set that couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidKeySet(&'static str);
+
+impl fmt::Display for InvalidKeySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid JSON Web Key Set: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidKeySet {}
+
+/// A public key of a key set.
+#[derive(Debug, Clone)]
+pub(crate) struct Jwk {
+    kid: Option<String>,
+    alg: Option<String>,
+    key: PublicKey,
+}
+
+#[derive(Debug, Clone)]
+enum PublicKey {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    P256(Vec<u8>),
+    P384(Vec<u8>),
+    Ed25519(Vec<u8>),
+}
+
+impl Jwk {
+    /// Whether the key can verify signatures made with `alg`.
+    fn supports(&self, alg: Algorithm) -> bool {
+        let supported = match self.key {
+            PublicKey::Rsa { .. } => matches!(
+                alg,
+                Algorithm::RS256
+                    | Algorithm::RS384
+                    | Algorithm::RS512
+                    | Algorithm::PS256
+                    | Algorithm::PS384
+                    | Algorithm::PS512
+            ),
+            PublicKey::P256(_) => alg == Algorithm::ES256,
+            PublicKey::P384(_) => alg == Algorithm::ES384,
+            PublicKey::Ed25519(_) => alg == Algorithm::EdDSA,
+        };
+        supported && self.alg.as_ref().is_none_or(|a| a == alg.name())
+    }
+
+    /// Whether `signature` is the signature of `message` made with `alg` by
+    /// this key.
+    pub(crate) fn verify(&self, alg: Algorithm, message: &[u8], signature: &[u8]) -> bool {
+        let rsa = |params| match &self.key {
+            PublicKey::Rsa { n, e } => RsaPublicKeyComponents { n, e }
+                .verify(params, message, signature)
+                .is_ok(),
+            _ => false,
+        };
+        let point = |algorithm, point: &[u8]| {
+            UnparsedPublicKey::new(algorithm, point)
+                .verify(message, signature)
+                .is_ok()
+        };
+
+        match (alg, &self.key) {
+            (Algorithm::RS256, _) => rsa(&signature::RSA_PKCS1_2048_8192_SHA256),
+            (Algorithm::RS384, _) => rsa(&signature::RSA_PKCS1_2048_8192_SHA384),
+            (Algorithm::RS512, _) => rsa(&signature::RSA_PKCS1_2048_8192_SHA512),
+            (Algorithm::PS256, _) => rsa(&signature::RSA_PSS_2048_8192_SHA256),
+            (Algorithm::PS384, _) => rsa(&signature::RSA_PSS_2048_8192_SHA384),
+            (Algorithm::PS512, _) => rsa(&signature::RSA_PSS_2048_8192_SHA512),
+            (Algorithm::ES256, PublicKey::P256(key)) => {
+                point(&signature::ECDSA_P256_SHA256_FIXED, key)
+            }
+            (Algorithm::ES384, PublicKey::P384(key)) => {
+                point(&signature::ECDSA_P384_SHA384_FIXED, key)
+            }
+            (Algorithm::EdDSA, PublicKey::Ed25519(key)) => point(&signature::ED25519, key),
+            _ => false,
+        }
+    }
+}
+
+/// The `keys` with the id `kid`, if any, supporting `alg`.
+fn matching(keys: &[Jwk], kid: Option<&str>, alg: Algorithm) -> Vec<Jwk> {
+    keys.iter()
+        .filter(|key| kid.is_none_or(|kid| key.kid.as_deref() == Some(kid)))
+        .filter(|key| key.supports(alg))
+        .cloned()
+        .collect()
+}
+
+fn parse_jwks(json: &[u8]) -> Result<Vec<Jwk>, InvalidKeySet> {
+    let jwks =
+        serde_json::from_slice::<Value>(json).map_err(|_| InvalidKeySet("not a JSON document"))?;
+    let keys = jwks
+        .get("keys")
+        .and_then(Value::as_array)
+        .ok_or(InvalidKeySet("missing keys"))?;
+
+    let mut parsed = Vec::new();
+    for key in keys {
+        let field = |name| key.get(name).and_then(Value::as_str);
+        // Keys only used to encrypt.
+        if field("use").is_some_and(|u| u != "sig") {
+            continue;
+        }
+        let bytes = |name| {
+            field(name)
+                .and_then(|value| base64::decode_config(value, base64::URL_SAFE_NO_PAD).ok())
+                .ok_or(InvalidKeySet("invalid key parameters"))
+        };
+        let point = |x: Vec<u8>, y: Vec<u8>| {
+            let mut point = vec![4];
+            point.extend(x);
+            point.extend(y);
+            point
+        };
+
+        let public_key = match (field("kty"), field("crv")) {
+            (Some("RSA"), _) => PublicKey::Rsa {
+                n: bytes("n")?,
+                e: bytes("e")?,
+            },
+            (Some("EC"), Some("P-256")) => PublicKey::P256(point(bytes("x")?, bytes("y")?)),
+            (Some("EC"), Some("P-384")) => PublicKey::P384(point(bytes("x")?, bytes("y")?)),
+            (Some("OKP"), Some("Ed25519")) => PublicKey::Ed25519(bytes("x")?),
+            _ => continue,
+        };
+        parsed.push(Jwk {
+            kid: field("kid").map(str::to_string),
+            alg: field("alg").map(str::to_string),
+            key: public_key,
+        });
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn matches_keys_by_id_and_algorithm() {
+        let keys = KeySet::from_json(
+            br#"{"keys": [
+                {"kty": "RSA", "kid": "a", "n": "AQAB", "e": "AQAB"},
+                {"kty": "RSA", "kid": "b", "alg": "PS256", "n": "AQAB", "e": "AQAB"},
+                {"kty": "EC", "kid": "c", "crv": "P-256", "x": "AQ", "y": "AQ"},
+                {"kty": "EC", "kid": "d", "crv": "P-521", "x": "AQ", "y": "AQ"},
+                {"kty": "RSA", "kid": "e", "use": "enc", "n": "AQAB", "e": "AQAB"}
+            ]}"#,
+        )
+        .unwrap();
+        let kids = |keys: Vec<Jwk>| keys.into_iter().map(|k| k.kid.unwrap()).collect::<Vec<_>>();
+
+        assert_eq!(
+            kids(keys.keys(None, Algorithm::RS256).await.unwrap()),
+            ["a"]
+        );
+        assert_eq!(
+            kids(keys.keys(None, Algorithm::PS256).await.unwrap()),
+            ["a", "b"]
+        );
+        assert_eq!(
+            kids(keys.keys(Some("c"), Algorithm::ES256).await.unwrap()),
+            ["c"]
+        );
+        assert!(keys
+            .keys(Some("c"), Algorithm::RS256)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(keys
+            .keys(Some("e"), Algorithm::RS256)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_key_sets() {
+        assert_eq!(
+            KeySet::from_json(b"{}").unwrap_err(),
+            InvalidKeySet("missing keys")
+        );
+        assert_eq!(
+            KeySet::from_json(br#"{"keys": [{"kty": "RSA", "n": "%"}]}"#).unwrap_err(),
+            InvalidKeySet("invalid key parameters")
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use super::Validator;
+use crate::{body::BoxBody, transport::NamedService, Status};
+use http::{header, Request, Response};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{layer::Layer, Service};
+
+/// Wraps services with [`JwtAuth`], validating the tokens of their calls.
+///
+/// [`JwtAuth`]: struct.JwtAuth.html
+#[derive(Debug, Clone)]
+pub struct JwtAuthLayer {
+    validator: Arc<Validator>,
+}
+
+impl JwtAuthLayer {
+    /// Validate the tokens with `validator`.
+    pub fn new(validator: Validator) -> Self {
+        JwtAuthLayer {
+            validator: Arc::new(validator),
+        }
+    }
+}
+
+impl<S> Layer<S> for JwtAuthLayer {
+    type Service = JwtAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        JwtAuth {
+            inner,
+            validator: self.validator.clone(),
+        }
+    }
+}
+
+/// A service only called with the requests carrying a valid token, along
+/// with its [`Claims`], failing the other ones with `UNAUTHENTICATED`.
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`Claims`]: struct.Claims.html
+/// [`Server`]: ../transport/struct.Server.html
+#[derive(Debug, Clone)]
+pub struct JwtAuth<S> {
+    inner: S,
+    validator: Arc<Validator>,
+}
+
+impl<S, B> Service<Request<B>> for JwtAuth<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        // Call the service that was polled ready, leaving a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let validator = self.validator.clone();
+
+        Box::pin(async move {
+            let token = request
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "));
+            let claims = match token {
+                Some(token) => validator.validate(token.trim()).await,
+                None => Err(Status::unauthenticated("missing bearer token")),
+            };
+
+            match claims {
+                Ok(claims) => {
+                    request.extensions_mut().insert(claims);
+                    inner.call(request).await
+                }
+                Err(status) => Ok(status.to_http()),
+            }
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for JwtAuth<S> {
+    const NAME: &'static str = S::NAME;
+}
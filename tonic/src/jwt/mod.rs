@@ -0,0 +1,400 @@
+//! Validation of the JSON Web Tokens authenticating the calls of a server.
+//!
+//! [`JwtAuthLayer`] wraps a service, like a generated server, validating
+//! the `Bearer` token in the `authorization` header of each call with a
+//! [`Validator`]: its signature, by one of the keys of a [`KeySet`], usually
+//! fetched from the issuer, its expiration and, if configured, its issuer
+//! and audience. The calls without a valid token fail with
+//! `UNAUTHENTICATED`, the other ones reach the service with the [`Claims`]
+//! of their token in the extensions of the request.
+//!
+//! ```rust,no_run
+//! use tonic::jwt::{JwtAuthLayer, KeySet, Validator};
+//! use tonic::transport::Channel;
+//! use tower::layer::Layer;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let issuer = Channel::from_static("http://auth.example.com").connect().await?;
+//! let keys = KeySet::remote(issuer, "http://auth.example.com/jwks.json".parse()?);
+//! let auth = JwtAuthLayer::new(
+//!     Validator::new(keys)
+//!         .issuer("https://auth.example.com")
+//!         .audience("orders"),
+//! );
+//!
+//! // Wrapping a generated server:
+//! // Server::builder().add_service(auth.layer(OrdersServer::new(orders)))
+//! # drop(auth);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The handlers get the claims of the call from its extensions:
+//!
+//! ```rust
+//! # use tonic::{jwt::Claims, Request, Status};
+//! fn subject(request: &Request<()>) -> Result<&str, Status> {
+//!     request
+//!         .extensions()
+//!         .get::<Claims>()
+//!         .and_then(Claims::subject)
+//!         .ok_or_else(|| Status::unauthenticated("no subject"))
+//! }
+//! ```
+//!
+//! [`JwtAuthLayer`]: struct.JwtAuthLayer.html
+//! [`Validator`]: struct.Validator.html
+//! [`KeySet`]: struct.KeySet.html
+//! [`Claims`]: struct.Claims.html
+
+mod keys;
+mod layer;
+
+pub use self::keys::{InvalidKeySet, KeySet};
+pub use self::layer::{JwtAuth, JwtAuthLayer};
+
+use crate::Status;
+use serde_json::{Map, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_LEEWAY: Duration = Duration::from_secs(60);
+
+/// The signature algorithms of the tokens, all asymmetric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub(crate) enum Algorithm {
+    RS256,
+    RS384,
+    RS512,
+    PS256,
+    PS384,
+    PS512,
+    ES256,
+    ES384,
+    EdDSA,
+}
+
+impl Algorithm {
+    fn from_name(name: &str) -> Option<Self> {
+        let alg = match name {
+            "RS256" => Algorithm::RS256,
+            "RS384" => Algorithm::RS384,
+            "RS512" => Algorithm::RS512,
+            "PS256" => Algorithm::PS256,
+            "PS384" => Algorithm::PS384,
+            "PS512" => Algorithm::PS512,
+            "ES256" => Algorithm::ES256,
+            "ES384" => Algorithm::ES384,
+            "EdDSA" => Algorithm::EdDSA,
+            _ => return None,
+        };
+        Some(alg)
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Algorithm::RS256 => "RS256",
+            Algorithm::RS384 => "RS384",
+            Algorithm::RS512 => "RS512",
+            Algorithm::PS256 => "PS256",
+            Algorithm::PS384 => "PS384",
+            Algorithm::PS512 => "PS512",
+            Algorithm::ES256 => "ES256",
+            Algorithm::ES384 => "ES384",
+            Algorithm::EdDSA => "EdDSA",
+        }
+    }
+}
+
+/// The claims of a validated token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Claims(Map<String, Value>);
+
+impl Claims {
+    /// The claim `name`, like `email` or `scope`.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    /// The `sub` claim, identifying the principal of the token.
+    pub fn subject(&self) -> Option<&str> {
+        self.get("sub").and_then(Value::as_str)
+    }
+
+    /// The `iss` claim, identifying the issuer of the token.
+    pub fn issuer(&self) -> Option<&str> {
+        self.get("iss").and_then(Value::as_str)
+    }
+
+    /// The `aud` claim, the recipients the token is intended for.
+    pub fn audience(&self) -> Vec<&str> {
+        match self.get("aud") {
+            Some(Value::String(aud)) => vec![aud.as_str()],
+            Some(Value::Array(aud)) => aud.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The `exp` claim, when the token expires.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.time("exp")
+    }
+
+    /// All the claims.
+    pub fn as_map(&self) -> &Map<String, Value> {
+        &self.0
+    }
+
+    fn time(&self, name: &str) -> Option<SystemTime> {
+        let secs = self.get(name).and_then(Value::as_u64)?;
+        Some(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+}
+
+/// Validates tokens against a [`KeySet`] and the expected claims.
+///
+/// [`KeySet`]: struct.KeySet.html
+#[derive(Debug, Clone)]
+pub struct Validator {
+    keys: KeySet,
+    issuers: Vec<String>,
+    audiences: Vec<String>,
+    leeway: Duration,
+}
+
+impl Validator {
+    /// Validate tokens signed by one of `keys`, not expired, accepting any
+    /// issuer and audience.
+    pub fn new(keys: KeySet) -> Self {
+        Validator {
+            keys,
+            issuers: Vec::new(),
+            audiences: Vec::new(),
+            leeway: DEFAULT_LEEWAY,
+        }
+    }
+
+    /// Accept the tokens of `issuer`, the only ones accepted along with the
+    /// other issuers added.
+    pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuers.push(issuer.into());
+        self
+    }
+
+    /// Accept the tokens intended for `audience`, the only ones accepted
+    /// along with the other audiences added.
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        self.audiences.push(audience.into());
+        self
+    }
+
+    /// Tolerate clocks `leeway` apart from the issuer's when checking the
+    /// `exp` and `nbf` claims, by default one minute.
+    pub fn leeway(self, leeway: Duration) -> Self {
+        Validator { leeway, ..self }
+    }
+
+    /// The claims of `token`, or the `UNAUTHENTICATED` status telling why it
+    /// isn't valid.
+    ///
+    /// Fails with `UNAVAILABLE` while the keys can't be fetched.
+    pub async fn validate(&self, token: &str) -> Result<Claims, Status> {
+        let invalid = |reason: &str| Status::unauthenticated(format!("invalid token: {}", reason));
+
+        let mut parts = token.splitn(3, '.');
+        let (header, payload, signature) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(header), Some(payload), Some(signature)) => (header, payload, signature),
+            _ => return Err(invalid("malformed")),
+        };
+        let message = &token[..header.len() + 1 + payload.len()];
+        let header = decode_json(header).ok_or_else(|| invalid("malformed header"))?;
+        let claims = decode_json(payload).ok_or_else(|| invalid("malformed claims"))?;
+        let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| invalid("malformed signature"))?;
+
+        let alg = header
+            .get("alg")
+            .and_then(Value::as_str)
+            .and_then(Algorithm::from_name)
+            .ok_or_else(|| invalid("unsupported algorithm"))?;
+        let kid = header.get("kid").and_then(Value::as_str);
+        let keys = self.keys.keys(kid, alg).await?;
+        if keys.is_empty() {
+            return Err(invalid("unknown key"));
+        }
+        if !keys
+            .iter()
+            .any(|key| key.verify(alg, message.as_bytes(), &signature))
+        {
+            return Err(invalid("bad signature"));
+        }
+
+        let claims = Claims(claims);
+        self.check(&claims, SystemTime::now()).map_err(invalid)?;
+        Ok(claims)
+    }
+
+    /// Check the claims of a token received at `now`.
+    fn check(&self, claims: &Claims, now: SystemTime) -> Result<(), &'static str> {
+        match claims.expires_at() {
+            Some(exp) if exp + self.leeway > now => {}
+            Some(_) => return Err("expired"),
+            None => return Err("missing exp claim"),
+        }
+        if let Some(nbf) = claims.time("nbf") {
+            if nbf > now + self.leeway {
+                return Err("not yet valid");
+            }
+        }
+
+        if !self.issuers.is_empty()
+            && !claims
+                .issuer()
+                .is_some_and(|iss| self.issuers.iter().any(|i| i == iss))
+        {
+            return Err("unexpected issuer");
+        }
+        if !self.audiences.is_empty()
+            && !claims
+                .audience()
+                .iter()
+                .any(|aud| self.audiences.iter().any(|a| a == aud))
+        {
+            return Err("unexpected audience");
+        }
+        Ok(())
+    }
+}
+
+fn decode_json(part: &str) -> Option<Map<String, Value>> {
+    let json = base64::decode_config(part, base64::URL_SAFE_NO_PAD).ok()?;
+    match serde_json::from_slice(&json).ok()? {
+        Value::Object(map) => Some(map),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+    use ring::{
+        rand::SystemRandom,
+        signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+    };
+    use serde_json::json;
+
+    fn encode(value: &Value) -> String {
+        base64::encode_config(&value.to_string(), base64::URL_SAFE_NO_PAD)
+    }
+
+    /// An ES256 key pair, along with its key set.
+    fn key_pair(kid: &str) -> (EcdsaKeyPair, KeySet) {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref()).unwrap();
+        let point = key_pair.public_key().as_ref();
+        let jwks = json!({"keys": [{
+            "kty": "EC",
+            "crv": "P-256",
+            "kid": kid,
+            "x": base64::encode_config(&point[1..33], base64::URL_SAFE_NO_PAD),
+            "y": base64::encode_config(&point[33..], base64::URL_SAFE_NO_PAD),
+        }]});
+        let keys = KeySet::from_json(jwks.to_string().as_bytes()).unwrap();
+        (key_pair, keys)
+    }
+
+    fn sign(key_pair: &EcdsaKeyPair, kid: &str, claims: Value) -> String {
+        let message = format!(
+            "{}.{}",
+            encode(&json!({"alg": "ES256", "typ": "JWT", "kid": kid})),
+            encode(&claims)
+        );
+        let signature = key_pair
+            .sign(&SystemRandom::new(), message.as_bytes())
+            .unwrap();
+        format!(
+            "{}.{}",
+            message,
+            base64::encode_config(signature.as_ref(), base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[tokio::test]
+    async fn validates_signatures() {
+        let (signer, keys) = key_pair("a");
+        let validator = Validator::new(keys);
+        let claims = json!({"sub": "alice", "exp": now() + 60});
+
+        let token = sign(&signer, "a", claims.clone());
+        let validated = validator.validate(&token).await.unwrap();
+        assert_eq!(validated.subject(), Some("alice"));
+
+        let message = |status: Status| {
+            assert_eq!(status.code(), Code::Unauthenticated);
+            status.message().to_string()
+        };
+        let (other, _) = key_pair("a");
+        let forged = sign(&other, "a", claims.clone());
+        assert_eq!(
+            message(validator.validate(&forged).await.unwrap_err()),
+            "invalid token: bad signature"
+        );
+        let unknown = sign(&signer, "b", claims);
+        assert_eq!(
+            message(validator.validate(&unknown).await.unwrap_err()),
+            "invalid token: unknown key"
+        );
+        assert_eq!(
+            message(validator.validate("not-a-token").await.unwrap_err()),
+            "invalid token: malformed"
+        );
+    }
+
+    #[test]
+    fn checks_claims() {
+        let (_, keys) = key_pair("a");
+        let validator = Validator::new(keys)
+            .issuer("https://auth.example.com")
+            .audience("orders")
+            .leeway(Duration::from_secs(10));
+        let at = UNIX_EPOCH + Duration::from_secs(1000);
+        let check = |claims: Value| match claims {
+            Value::Object(claims) => validator.check(&Claims(claims), at),
+            _ => unreachable!(),
+        };
+
+        let valid = json!({
+            "iss": "https://auth.example.com",
+            "aud": ["billing", "orders"],
+            "exp": 995,
+            "nbf": 1005,
+        });
+        assert_eq!(check(valid.clone()), Ok(()));
+
+        let mut claims = valid.clone();
+        claims["exp"] = json!(990);
+        assert_eq!(check(claims), Err("expired"));
+        let mut claims = valid.clone();
+        claims.as_object_mut().unwrap().remove("exp");
+        assert_eq!(check(claims), Err("missing exp claim"));
+        let mut claims = valid.clone();
+        claims["nbf"] = json!(1011);
+        assert_eq!(check(claims), Err("not yet valid"));
+        let mut claims = valid.clone();
+        claims["iss"] = json!("https://other.example.com");
+        assert_eq!(check(claims), Err("unexpected issuer"));
+        let mut claims = valid;
+        claims["aud"] = json!("billing");
+        assert_eq!(check(claims), Err("unexpected audience"));
+    }
+}
@@ -25,7 +25,19 @@
 //! - `tls-roots`: Adds system trust roots to `rustls`-based gRPC clients using the
 //! `rustls-native-certs` crate. Not enabled by default. `tls` must be enabled to use
 //! `tls-roots`.
-//! - `prost`: Enables the [`prost`] based gRPC [`Codec`] implementation.
+//! - `prost`: Enables the [`prost`] based gRPC [`Codec`] implementation. Enabled by default.
+//! Disable it when messages come from another crate, see [`codec::MessageCodec`]. Also enables [`error_details`] and the [`orca`] load reports.
+//! - `gzip`: Enables compressing messages with `gzip`, see [`CompressionEncoding`]. Not enabled by default.
+//! - `zstd`: Enables compressing messages with `zstd`. Not enabled by default.
+//! - `deflate`: Enables compressing messages with `deflate`. Not enabled by default.
+//! - `json`: Enables the [`serde`] based JSON [`Codec`] implementation, and the helpers used by messages generated with serde support. Not enabled by default.
+//! - `flatbuffers`: Enables the FlatBuffers [`Codec`] implementation. Not enabled by default.
+//! - `chrono`: Enables the [`ChronoTimestamp`] and [`ChronoDuration`] conversions of the `google.protobuf.Timestamp` and `google.protobuf.Duration` well-known types to [`chrono`] types, for [`ConvertCodec`]. Not enabled by default.
+//! - `oauth2`: Enables the OAuth2 access token credentials of [`oauth2`], enabling `tls`. Not enabled by default.
+//! - `jwt`: Enables the [`jwt`] server middleware validating the JSON Web Tokens of the calls. Not enabled by default.
+//! - `h2-trace`: Enables `DEBUG` [`tracing`] events for the HTTP/2 control frames (`SETTINGS`, `WINDOW_UPDATE`, `PING`, `GOAWAY` and `RST_STREAM`) sent and received on the connections of the [`transport`], with their settings, window increments and error codes. Not enabled by default.
+//! - `otel`: Enables the [`otel`] metrics recorder following the semantic conventions of OpenTelemetry. Not enabled by default.
+//! - `tracing`: Enables a `grpc` [`tracing`] span for each call of the clients and servers, with the `rpc.*` fields of OpenTelemetry and an event for each message sent or received. Not enabled by default.
 //!
 //! # Structure
 //!
@@ -50,16 +62,27 @@
 //! [`tonic`]: https://github.com/hyperium/tonic
 //! [`tokio`]: https://docs.rs/tokio
 //! [`prost`]: https://docs.rs/prost
+//! [`serde`]: https://docs.rs/serde
 //! [`hyper`]: https://docs.rs/hyper
 //! [`tower`]: https://docs.rs/tower
 //! [`tonic-build`]: https://docs.rs/tonic-build
 //! [`tonic-examples`]: https://github.com/hyperium/tonic/tree/master/examples
 //! [`Codec`]: codec/trait.Codec.html
+//! [`CompressionEncoding`]: codec/enum.CompressionEncoding.html
+//! [`ConvertCodec`]: codec/struct.ConvertCodec.html
+//! [`ChronoTimestamp`]: codec/enum.ChronoTimestamp.html
+//! [`ChronoDuration`]: codec/enum.ChronoDuration.html
+//! [`chrono`]: https://docs.rs/chrono
 //! [`Channel`]: transport/struct.Channel.html
 //! [`Server`]: transport/struct.Server.html
 //! [`rustls`]: https://docs.rs/rustls
 //! [`client`]: client/index.html
 //! [`transport`]: transport/index.html
+//! [`oauth2`]: oauth2/index.html
+//! [`jwt`]: jwt/index.html
+//! [`orca`]: orca/index.html
+//! [`otel`]: otel/index.html
+//! [`tracing`]: https://docs.rs/tracing
 
 #![recursion_limit = "256"]
 #![warn(
@@ -79,17 +102,45 @@
 pub mod body;
 pub mod client;
 pub mod codec;
+pub mod deadline;
+pub mod descriptor;
+#[cfg(feature = "prost")]
+#[cfg_attr(docsrs, doc(cfg(feature = "prost")))]
+pub mod error_details;
+#[cfg(feature = "transport")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport")))]
+pub mod fault;
+pub mod histograms;
+#[cfg(feature = "jwt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+pub mod jwt;
 pub mod metadata;
+pub mod metrics;
+#[cfg(feature = "oauth2")]
+#[cfg_attr(docsrs, doc(cfg(feature = "oauth2")))]
+pub mod oauth2;
+#[cfg(feature = "prost")]
+#[cfg_attr(docsrs, doc(cfg(feature = "prost")))]
+pub mod orca;
+#[cfg(feature = "otel")]
+#[cfg_attr(docsrs, doc(cfg(feature = "otel")))]
+pub mod otel;
+pub mod request_id;
 pub mod server;
+pub mod stats;
+pub mod trace_context;
 
 #[cfg(feature = "transport")]
 #[cfg_attr(docsrs, doc(cfg(feature = "transport")))]
 pub mod transport;
 
+mod context;
+mod credentials;
 mod interceptor;
 mod macros;
 mod request;
 mod response;
+mod spans;
 mod status;
 
 /// A re-export of [`async-trait`](https://docs.rs/async-trait) for use with codegen.
@@ -99,10 +150,12 @@ pub use async_trait::async_trait;
 
 #[doc(inline)]
 pub use codec::Streaming;
-pub use interceptor::Interceptor;
+pub use context::RequestContext;
+pub use credentials::CallCredentials;
+pub use interceptor::{interceptor_fn, Interceptor, InterceptorChain};
 pub use request::{IntoRequest, IntoStreamingRequest, Request};
 pub use response::Response;
-pub use status::{Code, Status};
+pub use status::{CancellationCause, Code, Status};
 
 pub(crate) type Error = Box<dyn std::error::Error + Send + Sync>;
 
@@ -33,3 +33,27 @@ macro_rules! include_proto {
         include!(concat!(env!("OUT_DIR"), concat!("/", $package, ".rs")));
     };
 }
+
+/// Include the module tree generated by `tonic-build` with its
+/// `include_file` option.
+///
+/// You must specify the name of the file, the modules of the packages are
+/// then nested in the module calling the macro.
+///
+/// ```rust,ignore
+/// mod pb {
+///     tonic::include_file!("protos.rs");
+/// }
+///
+/// use pb::helloworld::greeter_client::GreeterClient;
+/// ```
+///
+/// # Note:
+/// **This only works if the tonic-build output directory has been unmodified**,
+/// like [`include_proto!`].
+#[macro_export]
+macro_rules! include_file {
+    ($file: tt) => {
+        include!(concat!(env!("OUT_DIR"), concat!("/", $file)));
+    };
+}
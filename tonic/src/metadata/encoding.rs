@@ -8,7 +8,7 @@ use std::hash::Hash;
 /// slice.
 #[derive(Debug)]
 pub struct InvalidMetadataValue {
-    _priv: (),
+    invalid_byte: Option<(usize, u8)>,
 }
 
 mod value_encoding {
@@ -66,11 +66,13 @@ impl self::value_encoding::Sealed for Ascii {
     }
 
     fn from_bytes(value: &[u8]) -> Result<HeaderValue, InvalidMetadataValueBytes> {
-        HeaderValue::from_bytes(value).map_err(|_| InvalidMetadataValueBytes::new())
+        HeaderValue::from_bytes(value)
+            .map_err(|_| InvalidMetadataValueBytes::first_invalid(value, is_header_value_byte))
     }
 
     fn from_shared(value: Bytes) -> Result<HeaderValue, InvalidMetadataValueBytes> {
-        HeaderValue::from_maybe_shared(value).map_err(|_| InvalidMetadataValueBytes::new())
+        HeaderValue::from_maybe_shared(value.clone())
+            .map_err(|_| InvalidMetadataValueBytes::first_invalid(&value, is_header_value_byte))
     }
 
     fn from_static(value: &'static str) -> HeaderValue {
@@ -134,7 +136,15 @@ impl self::value_encoding::Sealed for Binary {
     fn decode(value: &[u8]) -> Result<Bytes, InvalidMetadataValueBytes> {
         base64::decode(value)
             .map(|bytes_vec| bytes_vec.into())
-            .map_err(|_| InvalidMetadataValueBytes::new())
+            .map_err(|error| match error {
+                base64::DecodeError::InvalidByte(index, byte)
+                | base64::DecodeError::InvalidLastSymbol(index, byte) => {
+                    InvalidMetadataValueBytes(InvalidMetadataValue {
+                        invalid_byte: Some((index, byte)),
+                    })
+                }
+                base64::DecodeError::InvalidLength => InvalidMetadataValueBytes::new(),
+            })
     }
 
     fn equals(a: &HeaderValue, b: &[u8]) -> bool {
@@ -170,17 +180,61 @@ impl ValueEncoding for Binary {
     }
 }
 
+/// Returns true if `byte` is allowed in an HTTP header value.
+pub(crate) fn is_header_value_byte(byte: u8) -> bool {
+    byte == b'\t' || (byte >= 32 && byte != 127)
+}
+
+/// Returns true if `byte` is allowed in a gRPC ascii metadata value, which
+/// only permits visible ASCII characters and spaces.
+pub(crate) fn is_strict_value_byte(byte: u8) -> bool {
+    (32..127).contains(&byte)
+}
+
 // ===== impl InvalidMetadataValue =====
 
 impl InvalidMetadataValue {
     pub(crate) fn new() -> Self {
-        InvalidMetadataValue { _priv: () }
+        InvalidMetadataValue { invalid_byte: None }
+    }
+
+    /// Creates an error reporting the first byte of `value` rejected by
+    /// `is_valid`.
+    pub(crate) fn first_invalid(value: &[u8], is_valid: impl Fn(u8) -> bool) -> Self {
+        InvalidMetadataValue {
+            invalid_byte: value
+                .iter()
+                .position(|&byte| !is_valid(byte))
+                .map(|index| (index, value[index])),
+        }
+    }
+
+    /// Returns the position and value of the byte that made the conversion
+    /// fail, if a single byte is to blame.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let err = AsciiMetadataValue::from_str("hello\nworld").unwrap_err();
+    /// assert_eq!(err.invalid_byte(), Some((5, b'\n')));
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "failed to parse metadata value: invalid byte 0x0a at position 5"
+    /// );
+    /// ```
+    pub fn invalid_byte(&self) -> Option<(usize, u8)> {
+        self.invalid_byte
     }
 }
 
 impl fmt::Display for InvalidMetadataValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.description().fmt(f)
+        f.write_str("failed to parse metadata value")?;
+        if let Some((index, byte)) = self.invalid_byte {
+            write!(f, ": invalid byte {:#04x} at position {}", byte, index)?;
+        }
+        Ok(())
     }
 }
 
@@ -201,6 +255,16 @@ impl InvalidMetadataValueBytes {
     pub(crate) fn new() -> Self {
         InvalidMetadataValueBytes(InvalidMetadataValue::new())
     }
+
+    pub(crate) fn first_invalid(value: &[u8], is_valid: impl Fn(u8) -> bool) -> Self {
+        InvalidMetadataValueBytes(InvalidMetadataValue::first_invalid(value, is_valid))
+    }
+
+    /// Returns the position and value of the byte that made the conversion
+    /// fail, if a single byte is to blame.
+    pub fn invalid_byte(&self) -> Option<(usize, u8)> {
+        self.0.invalid_byte()
+    }
 }
 
 impl fmt::Display for InvalidMetadataValueBytes {
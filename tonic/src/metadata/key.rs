@@ -26,7 +26,7 @@ pub struct MetadataKey<VE: ValueEncoding> {
 /// A possible error when converting a `MetadataKey` from another type.
 #[derive(Debug)]
 pub struct InvalidMetadataKey {
-    _priv: (),
+    key: Option<String>,
 }
 
 /// An ascii metadata key.
@@ -50,10 +50,91 @@ impl<VE: ValueEncoding> MetadataKey<VE> {
                     phantom: PhantomData,
                 })
             }
-            Err(_) => Err(InvalidMetadataKey::new()),
+            Err(_) => Err(InvalidMetadataKey::for_key(src)),
         }
     }
 
+    /// Converts a slice of bytes to a `MetadataKey`, only accepting the keys
+    /// allowed by the gRPC specification.
+    ///
+    /// Unlike `from_bytes`, this function doesn't normalize the input: only
+    /// lowercase letters, digits, `-`, `_` and `.` are permitted. A key with
+    /// the wrong "-bin" suffix for the encoding is rejected too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// assert!(AsciiMetadataKey::from_bytes_strict(b"x-request-id").is_ok());
+    /// assert!(AsciiMetadataKey::from_bytes_strict(b"X-Request-Id").is_err());
+    /// assert!(AsciiMetadataKey::from_bytes_strict(b"x-request-id-bin").is_err());
+    /// assert!(BinaryMetadataKey::from_bytes_strict(b"x-request-id").is_err());
+    /// ```
+    pub fn from_bytes_strict(src: &[u8]) -> Result<Self, InvalidMetadataKey> {
+        let valid = !src.is_empty()
+            && src.iter().all(|&byte| is_strict_key_byte(byte))
+            && VE::is_valid_key(std::str::from_utf8(src).expect("ascii key"));
+        if !valid {
+            return Err(InvalidMetadataKey::for_key(src));
+        }
+
+        let name = HeaderName::from_bytes(src).map_err(|_| InvalidMetadataKey::for_key(src))?;
+        Ok(MetadataKey::unchecked_from_header_name(name))
+    }
+
+    /// Converts a string to a `MetadataKey`, lowercasing it and replacing the
+    /// characters not allowed in gRPC metadata keys with `-`.
+    ///
+    /// The key is also fixed up for the encoding: binary keys get a "-bin"
+    /// suffix if they don't have one, and the "-bin" suffix of ascii keys is
+    /// turned into "_bin". This makes it possible to bridge arbitrary header
+    /// names into metadata without handling errors.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `src` is empty and the key is an ascii key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let key = AsciiMetadataKey::from_lowercase_str("X-Forwarded For");
+    /// assert_eq!(key, "x-forwarded-for");
+    ///
+    /// let key = AsciiMetadataKey::from_lowercase_str("Trace-Bin");
+    /// assert_eq!(key, "trace_bin");
+    ///
+    /// let key = BinaryMetadataKey::from_lowercase_str("Trace");
+    /// assert_eq!(key, "trace-bin");
+    /// ```
+    pub fn from_lowercase_str(src: &str) -> Self {
+        assert!(
+            !src.is_empty() || VE::is_valid_key("-bin"),
+            "empty metadata key"
+        );
+
+        let mut key: String = src
+            .chars()
+            .map(|c| match c {
+                'A'..='Z' => c.to_ascii_lowercase(),
+                c if c.is_ascii() && is_strict_key_byte(c as u8) => c,
+                _ => '-',
+            })
+            .collect();
+
+        if !VE::is_valid_key(&key) {
+            if Binary::is_valid_key(&key) {
+                let suffix = key.len() - "-bin".len();
+                key.replace_range(suffix..=suffix, "_");
+            } else {
+                key.push_str("-bin");
+            }
+        }
+
+        let name = HeaderName::from_bytes(key.as_bytes()).expect("sanitized metadata key");
+        MetadataKey::unchecked_from_header_name(name)
+    }
+
     /// Converts a static string to a `MetadataKey`.
     ///
     /// This function panics when the static string is a invalid metadata key.
@@ -143,7 +224,7 @@ impl<VE: ValueEncoding> FromStr for MetadataKey<VE> {
     type Err = InvalidMetadataKey;
 
     fn from_str(s: &str) -> Result<Self, InvalidMetadataKey> {
-        MetadataKey::from_bytes(s.as_bytes()).map_err(|_| InvalidMetadataKey::new())
+        MetadataKey::from_bytes(s.as_bytes())
     }
 }
 
@@ -180,10 +261,38 @@ impl<VE: ValueEncoding> fmt::Display for MetadataKey<VE> {
 impl InvalidMetadataKey {
     #[doc(hidden)]
     pub fn new() -> InvalidMetadataKey {
-        InvalidMetadataKey { _priv: () }
+        InvalidMetadataKey { key: None }
+    }
+
+    pub(crate) fn for_key(key: &[u8]) -> InvalidMetadataKey {
+        InvalidMetadataKey {
+            key: Some(String::from_utf8_lossy(key).into_owned()),
+        }
+    }
+
+    /// Returns the key that was rejected, if known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let err = "invalid key".parse::<AsciiMetadataKey>().unwrap_err();
+    /// assert_eq!(err.key(), Some("invalid key"));
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "invalid gRPC metadata key name: \"invalid key\""
+    /// );
+    /// ```
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
     }
 }
 
+/// Returns true if `byte` is allowed in a gRPC metadata key.
+fn is_strict_key_byte(byte: u8) -> bool {
+    matches!(byte, b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.')
+}
+
 impl<'a, VE: ValueEncoding> From<&'a MetadataKey<VE>> for MetadataKey<VE> {
     fn from(src: &'a MetadataKey<VE>) -> MetadataKey<VE> {
         src.clone()
@@ -271,7 +380,11 @@ impl<'a, VE: ValueEncoding> PartialEq<MetadataKey<VE>> for &'a str {
 
 impl fmt::Display for InvalidMetadataKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.description().fmt(f)
+        f.write_str("invalid gRPC metadata key name")?;
+        if let Some(key) = &self.key {
+            write!(f, ": {:?}", key)?;
+        }
+        Ok(())
     }
 }
 
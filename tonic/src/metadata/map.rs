@@ -4,8 +4,10 @@ pub(crate) use self::into_metadata_key::IntoMetadataKey;
 
 use super::encoding::{Ascii, Binary, ValueEncoding};
 use super::key::{InvalidMetadataKey, MetadataKey};
+use super::typed::TypedMetadata;
 use super::value::MetadataValue;
 
+use std::collections::HashMap;
 use std::marker::PhantomData;
 
 /// A set of gRPC custom metadata entries.
@@ -65,6 +67,26 @@ pub enum KeyAndMutValueRef<'a> {
     Binary(&'a MetadataKey<Binary>, &'a mut MetadataValue<Binary>),
 }
 
+/// `MetadataMap` entry iterator.
+///
+/// Yields `KeyAndValuesRef` values. Each header name is yielded only once,
+/// along with all of its associated values.
+#[derive(Debug)]
+pub struct Entries<'a> {
+    keys: http::header::Keys<'a, http::header::HeaderValue>,
+    headers: &'a http::HeaderMap,
+}
+
+/// Reference to a key and all its associated values in a `MetadataMap`. It
+/// can point to either an ascii or a binary ("*-bin") key.
+#[derive(Debug)]
+pub enum KeyAndValuesRef<'a> {
+    /// An ascii metadata key and its values.
+    Ascii(&'a MetadataKey<Ascii>, ValueIter<'a, Ascii>),
+    /// A binary metadata key and its values.
+    Binary(&'a MetadataKey<Binary>, ValueIter<'a, Binary>),
+}
+
 /// `MetadataMap` entry iterator.
 ///
 /// Yields `(&MetadataKey, &mut value)` tuples. The same header name may be yielded
@@ -334,6 +356,37 @@ impl MetadataMap {
         self.headers.keys_len()
     }
 
+    /// Returns the size of the map once sent, counted like HTTP/2 does for
+    /// the header list size limit peers set: the length of each key and
+    /// value, with binary values base64 encoded, plus 32 bytes per entry.
+    ///
+    /// This makes it possible to check that the metadata of a request or
+    /// response stays under the limit of the peer before sending it. The
+    /// reserved gRPC headers, which aren't sent from the map, are not
+    /// counted, and neither are the headers added by tonic itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let mut map = MetadataMap::new();
+    /// assert_eq!(map.encoded_len(), 0);
+    ///
+    /// map.insert("x-host", "example.com".parse().unwrap());
+    /// assert_eq!(map.encoded_len(), 6 + 11 + 32);
+    ///
+    /// // Sent as "AAEC".
+    /// map.insert_bin("x-data-bin", MetadataValue::from_bytes(&[0, 1, 2]));
+    /// assert_eq!(map.encoded_len(), 6 + 11 + 32 + 10 + 4 + 32);
+    /// ```
+    pub fn encoded_len(&self) -> usize {
+        super::header_list_size(
+            self.headers
+                .iter()
+                .filter(|(name, _)| !Self::GRPC_RESERVED_HEADERS.contains(&name.as_str())),
+        )
+    }
+
     /// Returns true if the map contains no elements.
     ///
     /// # Examples
@@ -747,6 +800,39 @@ impl MetadataMap {
         }
     }
 
+    /// An iterator visiting all keys, each along with all of its associated
+    /// values.
+    ///
+    /// The iteration order is arbitrary, but consistent across platforms for
+    /// the same crate version. Each key will be yielded only once even if it
+    /// has multiple associated values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let mut map = MetadataMap::new();
+    ///
+    /// map.insert("x-word", "hello".parse().unwrap());
+    /// map.append("x-word", "goodbye".parse().unwrap());
+    /// map.insert_bin("x-number-bin", MetadataValue::from_bytes(b"123"));
+    ///
+    /// for entry in map.entries() {
+    ///     match entry {
+    ///         KeyAndValuesRef::Ascii(key, values) =>
+    ///             println!("Ascii: {:?}: {:?}", key, values.collect::<Vec<_>>()),
+    ///         KeyAndValuesRef::Binary(key, values) =>
+    ///             println!("Binary: {:?}: {:?}", key, values.collect::<Vec<_>>()),
+    ///     }
+    /// }
+    /// ```
+    pub fn entries(&self) -> Entries<'_> {
+        Entries {
+            keys: self.headers.keys(),
+            headers: &self.headers,
+        }
+    }
+
     /// An iterator visiting all keys.
     ///
     /// The iteration order is arbitrary, but consistent across platforms for
@@ -1211,11 +1297,224 @@ impl MetadataMap {
         key.remove(self)
     }
 
-    pub(crate) fn merge(&mut self, other: MetadataMap) {
+    /// Returns the decoded value of the typed metadata entry `T`.
+    ///
+    /// `Ok(None)` is returned if the map has no value for `T::KEY`. If there
+    /// are multiple values associated with the key, the first one is decoded.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `T::KEY` isn't a valid metadata key for
+    /// `T::Encoding`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// # use tonic::Status;
+    /// struct RequestId(u64);
+    ///
+    /// impl TypedMetadata for RequestId {
+    ///     type Encoding = Ascii;
+    ///
+    ///     const KEY: &'static str = "x-request-id";
+    ///
+    ///     fn decode(value: &AsciiMetadataValue) -> Result<Self, Status> {
+    ///         value
+    ///             .to_str()
+    ///             .ok()
+    ///             .and_then(|value| value.parse().ok())
+    ///             .map(RequestId)
+    ///             .ok_or_else(|| Status::invalid_argument("Invalid request id"))
+    ///     }
+    ///
+    ///     fn encode(&self) -> AsciiMetadataValue {
+    ///         MetadataValue::from(self.0)
+    ///     }
+    /// }
+    ///
+    /// let mut map = MetadataMap::new();
+    /// assert!(map.get_typed::<RequestId>().unwrap().is_none());
+    ///
+    /// map.insert("x-request-id", "42".parse().unwrap());
+    /// assert_eq!(map.get_typed::<RequestId>().unwrap().unwrap().0, 42);
+    ///
+    /// map.insert("x-request-id", "forty-two".parse().unwrap());
+    /// assert!(map.get_typed::<RequestId>().is_err());
+    /// ```
+    pub fn get_typed<T>(&self) -> Result<Option<T>, crate::Status>
+    where
+        T: TypedMetadata,
+    {
+        let key = MetadataKey::<T::Encoding>::from_static(T::KEY);
+        self.headers
+            .get(key.inner)
+            .map(|value| T::decode(MetadataValue::unchecked_from_header_value_ref(value)))
+            .transpose()
+    }
+
+    /// Inserts the encoded `value` of the typed metadata entry `T` into the
+    /// map.
+    ///
+    /// Like [`insert`], any previous values associated with `T::KEY` are
+    /// removed and the first one is returned.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `T::KEY` isn't a valid metadata key for
+    /// `T::Encoding`.
+    ///
+    /// [`insert`]: #method.insert
+    pub fn insert_typed<T>(&mut self, value: &T) -> Option<MetadataValue<T::Encoding>>
+    where
+        T: TypedMetadata,
+    {
+        let key = MetadataKey::<T::Encoding>::from_static(T::KEY);
+        self.headers
+            .insert(key.inner, value.encode().inner)
+            .map(&MetadataValue::unchecked_from_header_value)
+    }
+
+    /// Appends all the entries of `other` to the map.
+    ///
+    /// Values already associated with a key are kept, and the values of
+    /// `other` are added after them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let mut map = MetadataMap::new();
+    /// map.insert("x-word", "hello".parse().unwrap());
+    ///
+    /// let mut other = MetadataMap::new();
+    /// other.insert("x-word", "goodbye".parse().unwrap());
+    /// other.insert_bin("trace-proto-bin", MetadataValue::from_bytes(b"[binary data]"));
+    ///
+    /// map.append_all(other);
+    ///
+    /// let words: Vec<_> = map.get_all("x-word").iter().collect();
+    /// assert_eq!(words, ["hello", "goodbye"]);
+    /// assert!(map.contains_key("trace-proto-bin"));
+    /// ```
+    pub fn append_all(&mut self, other: MetadataMap) {
+        let mut key = None;
+        for (name, value) in other.headers {
+            if name.is_some() {
+                key = name;
+            }
+            // Every value is yielded after the name of its key.
+            let key = key.clone().expect("value without a key");
+            self.headers.append(key, value);
+        }
+    }
+
+    /// Merges the entries of `other` into the map.
+    ///
+    /// For every key of `other`, the values previously associated with it
+    /// are replaced by the ones of `other`. Other keys are left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let mut map = MetadataMap::new();
+    /// map.insert("x-word", "hello".parse().unwrap());
+    /// map.insert("x-number", "123".parse().unwrap());
+    ///
+    /// let mut other = MetadataMap::new();
+    /// other.insert("x-word", "goodbye".parse().unwrap());
+    ///
+    /// map.merge(other);
+    ///
+    /// let words: Vec<_> = map.get_all("x-word").iter().collect();
+    /// assert_eq!(words, ["goodbye"]);
+    /// assert_eq!(map.get("x-number").unwrap(), "123");
+    /// ```
+    pub fn merge(&mut self, other: MetadataMap) {
         self.headers.extend(other.headers);
     }
 }
 
+impl From<HashMap<String, String>> for MetadataMap {
+    /// Creates a map with the entries of `map`.
+    ///
+    /// Values of binary ("*-bin") keys are stored as the bytes of the string.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if a key isn't a valid metadata key, or if the
+    /// value of an ascii key isn't a valid ascii metadata value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// # use std::collections::HashMap;
+    /// let mut entries = HashMap::new();
+    /// entries.insert("x-host".to_string(), "example.com".to_string());
+    /// entries.insert("trace-proto-bin".to_string(), "[binary data]".to_string());
+    ///
+    /// let map = MetadataMap::from(entries);
+    /// assert_eq!(map.get("x-host").unwrap(), "example.com");
+    /// assert_eq!(map.get_bin("trace-proto-bin").unwrap(), "[binary data]");
+    /// ```
+    fn from(map: HashMap<String, String>) -> Self {
+        let mut metadata = MetadataMap::with_capacity(map.len());
+        for (key, value) in map {
+            if Binary::is_valid_key(&key) {
+                let key = MetadataKey::<Binary>::from_bytes(key.as_bytes())
+                    .expect("invalid metadata key");
+                metadata.append_bin(key, MetadataValue::from_bytes(value.as_bytes()));
+            } else {
+                let key =
+                    MetadataKey::<Ascii>::from_bytes(key.as_bytes()).expect("invalid metadata key");
+                let value = value.parse().unwrap_or_else(|error| {
+                    panic!("invalid metadata value for key {:?}: {}", key, error)
+                });
+                metadata.append(key, value);
+            }
+        }
+        metadata
+    }
+}
+
+// ===== impl Entries =====
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = KeyAndValuesRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let headers = self.headers;
+        self.keys.next().map(|key| {
+            let values = Some(headers.get_all(key).iter());
+            if Ascii::is_valid_key(key.as_str()) {
+                KeyAndValuesRef::Ascii(
+                    MetadataKey::unchecked_from_header_name_ref(key),
+                    ValueIter {
+                        inner: values,
+                        phantom: PhantomData,
+                    },
+                )
+            } else {
+                KeyAndValuesRef::Binary(
+                    MetadataKey::unchecked_from_header_name_ref(key),
+                    ValueIter {
+                        inner: values,
+                        phantom: PhantomData,
+                    },
+                )
+            }
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for Entries<'a> {}
+
 // ===== impl Iter =====
 
 impl<'a> Iterator for Iter<'a> {
@@ -2272,11 +2571,11 @@ mod as_metadata_key {
             map: &mut MetadataMap,
         ) -> Result<Entry<'_, HeaderValue>, InvalidMetadataKey> {
             if !VE::is_valid_key(self) {
-                return Err(InvalidMetadataKey::new());
+                return Err(InvalidMetadataKey::for_key(self.as_bytes()));
             }
 
             let key = http::header::HeaderName::from_bytes(self.as_bytes())
-                .map_err(|_| InvalidMetadataKey::new())?;
+                .map_err(|_| InvalidMetadataKey::for_key(self.as_bytes()))?;
             let entry = map.headers.entry(key);
             Ok(entry)
         }
@@ -2334,11 +2633,11 @@ mod as_metadata_key {
             map: &mut MetadataMap,
         ) -> Result<Entry<'_, HeaderValue>, InvalidMetadataKey> {
             if !VE::is_valid_key(self.as_str()) {
-                return Err(InvalidMetadataKey::new());
+                return Err(InvalidMetadataKey::for_key(self.as_bytes()));
             }
 
             let key = http::header::HeaderName::from_bytes(self.as_bytes())
-                .map_err(|_| InvalidMetadataKey::new())?;
+                .map_err(|_| InvalidMetadataKey::for_key(self.as_bytes()))?;
             Ok(map.headers.entry(key))
         }
 
@@ -2395,11 +2694,11 @@ mod as_metadata_key {
             map: &mut MetadataMap,
         ) -> Result<Entry<'_, HeaderValue>, InvalidMetadataKey> {
             if !VE::is_valid_key(self) {
-                return Err(InvalidMetadataKey::new());
+                return Err(InvalidMetadataKey::for_key(self.as_bytes()));
             }
 
             let key = http::header::HeaderName::from_bytes(self.as_bytes())
-                .map_err(|_| InvalidMetadataKey::new())?;
+                .map_err(|_| InvalidMetadataKey::for_key(self.as_bytes()))?;
             Ok(map.headers.entry(key))
         }
 
@@ -2544,6 +2843,104 @@ mod tests {
         assert!(found_x_word);
     }
 
+    #[test]
+    fn test_entries_groups_values_by_key() {
+        let mut map = MetadataMap::new();
+
+        map.insert("x-word", "hello".parse().unwrap());
+        map.append("x-word", "goodbye".parse().unwrap());
+        map.insert_bin("x-number-bin", MetadataValue::from_bytes(b"123"));
+
+        let mut entries = map.entries();
+        assert_eq!(entries.len(), 2);
+        for entry in &mut entries {
+            match entry {
+                KeyAndValuesRef::Ascii(key, values) => {
+                    assert_eq!(key.as_str(), "x-word");
+                    assert_eq!(values.collect::<Vec<_>>(), ["hello", "goodbye"]);
+                }
+                KeyAndValuesRef::Binary(key, values) => {
+                    assert_eq!(key.as_str(), "x-number-bin");
+                    assert_eq!(values.collect::<Vec<_>>(), ["123"]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_append_all_keeps_existing_values() {
+        let mut map = MetadataMap::new();
+        map.insert("x-word", "hello".parse().unwrap());
+
+        let mut other = MetadataMap::new();
+        other.insert("x-word", "goodbye".parse().unwrap());
+        other.append("x-word", "farewell".parse().unwrap());
+        other.insert("x-number", "123".parse().unwrap());
+
+        map.append_all(other);
+
+        let words: Vec<_> = map.get_all("x-word").iter().collect();
+        assert_eq!(words, ["hello", "goodbye", "farewell"]);
+        assert_eq!(map.get("x-number").unwrap(), "123");
+        assert_eq!(map.len(), 4);
+    }
+
+    #[test]
+    fn test_typed_binary_entries() {
+        struct Digest(Vec<u8>);
+
+        impl TypedMetadata for Digest {
+            type Encoding = Binary;
+
+            const KEY: &'static str = "x-digest-bin";
+
+            fn decode(value: &MetadataValue<Binary>) -> Result<Self, crate::Status> {
+                value
+                    .to_bytes()
+                    .map(|bytes| Digest(bytes.to_vec()))
+                    .map_err(|_| crate::Status::invalid_argument("invalid digest"))
+            }
+
+            fn encode(&self) -> MetadataValue<Binary> {
+                MetadataValue::from_bytes(&self.0)
+            }
+        }
+
+        let mut map = MetadataMap::new();
+        map.append_bin("x-digest-bin", MetadataValue::from_bytes(b"stale"));
+        map.append_bin("x-digest-bin", MetadataValue::from_bytes(b"staler"));
+
+        let previous = map.insert_typed(&Digest(vec![1, 2, 3])).unwrap();
+        assert_eq!(previous, "stale");
+        assert_eq!(map.len(), 1);
+
+        let digest = map.get_typed::<Digest>().unwrap().unwrap();
+        assert_eq!(digest.0, [1, 2, 3]);
+
+        let invalid = http::HeaderValue::from_static("!!!");
+        map.insert_bin(
+            "x-digest-bin",
+            MetadataValue::unchecked_from_header_value(invalid),
+        );
+        let status = map.get_typed::<Digest>().err().unwrap();
+        assert_eq!(status.code(), crate::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_encoded_len_skips_reserved_headers() {
+        let mut map = MetadataMap::new();
+        map.insert("x-word", "hello".parse().unwrap());
+        let len = map.encoded_len();
+
+        map.insert("grpc-status", "0".parse().unwrap());
+        map.insert("content-type", "application/grpc".parse().unwrap());
+        assert_eq!(map.encoded_len(), len);
+
+        let headers = map.into_sanitized_headers();
+        assert!(crate::metadata::check_size(&headers, Some(len)).is_ok());
+        assert!(crate::metadata::check_size(&headers, Some(len - 1)).is_err());
+    }
+
     #[test]
     fn test_iter_categorizes_binary_entries() {
         let mut map = MetadataMap::new();
@@ -3,6 +3,7 @@
 mod encoding;
 mod key;
 mod map;
+mod typed;
 mod value;
 
 pub use self::encoding::Ascii;
@@ -10,11 +11,13 @@ pub use self::encoding::Binary;
 pub use self::key::AsciiMetadataKey;
 pub use self::key::BinaryMetadataKey;
 pub use self::key::MetadataKey;
+pub use self::map::Entries;
 pub use self::map::Entry;
 pub use self::map::GetAll;
 pub use self::map::Iter;
 pub use self::map::KeyAndMutValueRef;
 pub use self::map::KeyAndValueRef;
+pub use self::map::KeyAndValuesRef;
 pub use self::map::KeyRef;
 pub use self::map::Keys;
 pub use self::map::MetadataMap;
@@ -25,10 +28,45 @@ pub use self::map::ValueIter;
 pub use self::map::ValueRef;
 pub use self::map::ValueRefMut;
 pub use self::map::Values;
+pub use self::typed::TypedMetadata;
 pub use self::value::AsciiMetadataValue;
 pub use self::value::BinaryMetadataValue;
 pub use self::value::MetadataValue;
 
+/// The size of `headers` as counted by HTTP/2 for its
+/// `SETTINGS_MAX_HEADER_LIST_SIZE`: the length of each name and value, plus
+/// 32 bytes per entry.
+pub(crate) fn header_list_size<'a>(
+    headers: impl Iterator<Item = (&'a http::header::HeaderName, &'a http::HeaderValue)>,
+) -> usize {
+    headers
+        .map(|(name, value)| name.as_str().len() + value.len() + 32)
+        .sum()
+}
+
+/// Check that the size of `headers`, counted like HTTP/2 does for its
+/// `SETTINGS_MAX_HEADER_LIST_SIZE`, is at most `limit` bytes.
+pub(crate) fn check_size(
+    headers: &http::HeaderMap,
+    limit: Option<usize>,
+) -> Result<(), crate::Status> {
+    let limit = match limit {
+        Some(limit) => limit,
+        None => return Ok(()),
+    };
+
+    let size = header_list_size(headers.iter());
+
+    if size > limit {
+        Err(crate::Status::resource_exhausted(format!(
+            "Received metadata larger than max ({} vs. {})",
+            size, limit
+        )))
+    } else {
+        Ok(())
+    }
+}
+
 /// The metadata::errors module contains types for errors that can occur
 /// while handling gRPC custom metadata.
 pub mod errors {
@@ -0,0 +1,115 @@
+use super::encoding::ValueEncoding;
+use super::value::MetadataValue;
+use crate::Status;
+
+/// A metadata entry with a structured value.
+///
+/// Implementing this trait for a type lets it be read from and written to
+/// a [`MetadataMap`] with [`get_typed`] and [`insert_typed`], instead of
+/// parsing and formatting the raw value at every use.
+///
+/// # Examples
+///
+/// An ascii entry:
+///
+/// ```
+/// # use tonic::metadata::*;
+/// # use tonic::Status;
+/// struct Authorization {
+///     token: String,
+/// }
+///
+/// impl TypedMetadata for Authorization {
+///     type Encoding = Ascii;
+///
+///     const KEY: &'static str = "authorization";
+///
+///     fn decode(value: &AsciiMetadataValue) -> Result<Self, Status> {
+///         let value = value
+///             .to_str()
+///             .map_err(|_| Status::unauthenticated("Invalid authorization"))?;
+///         match value.strip_prefix("Bearer ") {
+///             Some(token) => Ok(Authorization { token: token.to_string() }),
+///             None => Err(Status::unauthenticated("Invalid authorization")),
+///         }
+///     }
+///
+///     fn encode(&self) -> AsciiMetadataValue {
+///         format!("Bearer {}", self.token).parse().unwrap()
+///     }
+/// }
+///
+/// let mut map = MetadataMap::new();
+/// map.insert_typed(&Authorization { token: "secret".to_string() });
+/// assert_eq!(map.get("authorization").unwrap(), "Bearer secret");
+///
+/// let auth = map.get_typed::<Authorization>().unwrap().unwrap();
+/// assert_eq!(auth.token, "secret");
+/// ```
+///
+/// A binary entry holding a protobuf message:
+///
+/// ```
+/// # use tonic::metadata::*;
+/// # use tonic::Status;
+/// # use prost::Message;
+/// #[derive(Clone, PartialEq, Message)]
+/// struct TraceContext {
+///     #[prost(uint64, tag = "1")]
+///     trace_id: u64,
+/// }
+///
+/// struct Trace(TraceContext);
+///
+/// impl TypedMetadata for Trace {
+///     type Encoding = Binary;
+///
+///     const KEY: &'static str = "trace-context-bin";
+///
+///     fn decode(value: &BinaryMetadataValue) -> Result<Self, Status> {
+///         value
+///             .to_bytes()
+///             .ok()
+///             .and_then(|bytes| TraceContext::decode(bytes).ok())
+///             .map(Trace)
+///             .ok_or_else(|| Status::invalid_argument("Invalid trace context"))
+///     }
+///
+///     fn encode(&self) -> BinaryMetadataValue {
+///         let mut buf = Vec::with_capacity(self.0.encoded_len());
+///         self.0.encode(&mut buf).unwrap();
+///         MetadataValue::from_bytes(&buf)
+///     }
+/// }
+///
+/// let mut map = MetadataMap::new();
+/// map.insert_typed(&Trace(TraceContext { trace_id: 42 }));
+///
+/// let trace = map.get_typed::<Trace>().unwrap().unwrap();
+/// assert_eq!(trace.0.trace_id, 42);
+/// ```
+///
+/// [`MetadataMap`]: struct.MetadataMap.html
+/// [`get_typed`]: struct.MetadataMap.html#method.get_typed
+/// [`insert_typed`]: struct.MetadataMap.html#method.insert_typed
+pub trait TypedMetadata: Sized {
+    /// The encoding of the value, [`Ascii`] or [`Binary`].
+    ///
+    /// [`Ascii`]: enum.Ascii.html
+    /// [`Binary`]: enum.Binary.html
+    type Encoding: ValueEncoding;
+
+    /// The key of the entry, which must end with "-bin" for binary entries.
+    const KEY: &'static str;
+
+    /// Decode a value from the metadata.
+    ///
+    /// The returned `Status` is handed to the caller of [`get_typed`], so it
+    /// can be propagated as is from interceptors and services.
+    ///
+    /// [`get_typed`]: struct.MetadataMap.html#method.get_typed
+    fn decode(value: &MetadataValue<Self::Encoding>) -> Result<Self, Status>;
+
+    /// Encode `self` into a metadata value.
+    fn encode(&self) -> MetadataValue<Self::Encoding>;
+}
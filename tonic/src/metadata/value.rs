@@ -1,5 +1,6 @@
 use super::encoding::{
-    Ascii, Binary, InvalidMetadataValue, InvalidMetadataValueBytes, ValueEncoding,
+    is_strict_value_byte, Ascii, Binary, InvalidMetadataValue, InvalidMetadataValueBytes,
+    ValueEncoding,
 };
 use super::key::MetadataKey;
 
@@ -310,7 +311,65 @@ impl MetadataValue<Ascii> {
                 inner: value,
                 phantom: PhantomData,
             })
-            .map_err(|_| InvalidMetadataValue::new())
+            .map_err(|_| {
+                InvalidMetadataValue::first_invalid(src.as_bytes(), |byte| {
+                    byte == b'\t' || (32..127).contains(&byte)
+                })
+            })
+    }
+
+    /// Attempt to convert a byte slice to a `MetadataValue<Ascii>`, only
+    /// accepting the values allowed by the gRPC specification.
+    ///
+    /// Unlike `try_from_bytes`, which accepts everything HTTP/2 does, only
+    /// visible ASCII characters and spaces (32-126) are permitted, so that
+    /// the value is accepted by every gRPC implementation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let val = AsciiMetadataValue::try_from_bytes_strict(b"hello world").unwrap();
+    /// assert_eq!(val, "hello world");
+    ///
+    /// let err = AsciiMetadataValue::try_from_bytes_strict(b"hello\xfa").unwrap_err();
+    /// assert_eq!(err.invalid_byte(), Some((5, 0xfa)));
+    /// ```
+    pub fn try_from_bytes_strict(src: &[u8]) -> Result<Self, InvalidMetadataValueBytes> {
+        if !src.iter().all(|&byte| is_strict_value_byte(byte)) {
+            return Err(InvalidMetadataValueBytes::first_invalid(
+                src,
+                is_strict_value_byte,
+            ));
+        }
+        Self::try_from_bytes(src)
+    }
+
+    /// Convert a byte slice to a `MetadataValue<Ascii>`, replacing the bytes
+    /// `try_from_bytes_strict` rejects with `?`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let val = AsciiMetadataValue::from_bytes_lossy(b"line 1\nline 2");
+    /// assert_eq!(val, "line 1?line 2");
+    ///
+    /// let val = AsciiMetadataValue::from_bytes_lossy("caf\u{e9}".as_bytes());
+    /// assert_eq!(val, "caf??");
+    /// ```
+    pub fn from_bytes_lossy(src: &[u8]) -> Self {
+        let sanitized: Vec<u8> = src
+            .iter()
+            .map(|&byte| {
+                if is_strict_value_byte(byte) {
+                    byte
+                } else {
+                    b'?'
+                }
+            })
+            .collect();
+        Self::try_from_bytes(&sanitized).expect("sanitized metadata value")
     }
 
     /// Converts a MetadataKey into a MetadataValue<Ascii>.
@@ -745,6 +804,25 @@ fn test_from_shared_base64_encodes() {
     assert_eq!(value.as_encoded_bytes(), b"SGVsbG8");
 }
 
+#[test]
+fn test_invalid_bytes_are_reported() {
+    let err = AsciiMetadataValue::try_from_bytes(b"abc\x7f").unwrap_err();
+    assert_eq!(err.invalid_byte(), Some((3, 0x7f)));
+
+    // Opaque octets are only rejected in strict mode.
+    assert!(AsciiMetadataValue::try_from_bytes(b"abc\xfa").is_ok());
+    let err = AsciiMetadataValue::try_from_bytes_strict(b"\tabc").unwrap_err();
+    assert_eq!(err.invalid_byte(), Some((0, b'\t')));
+
+    let value = BinaryMetadataValue::unchecked_from_header_value(HeaderValue::from_static("ab*d"));
+    let err = value.to_bytes().unwrap_err();
+    assert_eq!(err.invalid_byte(), Some((2, b'*')));
+    assert_eq!(
+        err.to_string(),
+        "failed to parse metadata value: invalid byte 0x2a at position 2"
+    );
+}
+
 #[test]
 fn test_value_eq_value() {
     type BMV = BinaryMetadataValue;
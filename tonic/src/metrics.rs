@@ -0,0 +1,607 @@
+//! Metrics of the calls of clients and servers.
+//!
+//! A [`MetricsLayer`] wraps a channel, or the services added to a server,
+//! recording to a [`MetricsRecorder`] the calls started and completed, with
+//! their code and latency, and the messages sent and received, with their
+//! size, each along with the service and the method of its call:
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tonic::metrics::{CallInfo, MetricsLayer, MetricsRecorder};
+//! use tonic::Code;
+//!
+//! struct Log;
+//!
+//! impl MetricsRecorder for Log {
+//!     fn call_completed(&self, call: &CallInfo, code: Code, latency: Duration) {
+//!         println!("{}/{}: {:?} in {:?}", call.service(), call.method(), code, latency);
+//!     }
+//! }
+//!
+//! let metrics = MetricsLayer::server(Log);
+//!
+//! // Server::builder().add_service(metrics.layer(GreeterServer::new(greeter)))
+//! ```
+//!
+//! [`MetricsLayer`]: struct.MetricsLayer.html
+//! [`MetricsRecorder`]: trait.MetricsRecorder.html
+
+use crate::{body::BoxBody, Code, Status};
+use bytes::Bytes;
+use futures_util::ready;
+use http::HeaderMap;
+use http_body::Body as HttpBody;
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// The size of the prefix of the messages, a compression flag followed by
+/// their length.
+const PREFIX_SIZE: usize = 5;
+
+/// Records the metrics of calls.
+///
+/// The methods do nothing by default, each recorder recording the metrics
+/// it needs. They are called while the calls are in progress, and must not
+/// block.
+pub trait MetricsRecorder: Send + Sync + 'static {
+    /// Record that `call` started.
+    fn call_started(&self, call: &CallInfo) {
+        let _ = call;
+    }
+
+    /// Record that `call` completed with `code`, `latency` after it
+    /// started. The calls dropped before completing, like the ones
+    /// cancelled by their client, complete with `CANCELLED`.
+    fn call_completed(&self, call: &CallInfo, code: Code, latency: Duration) {
+        let _ = (call, code, latency);
+    }
+
+    /// Record that a message of `size` bytes, as sent on the wire, was sent
+    /// on `call`.
+    fn message_sent(&self, call: &CallInfo, size: usize) {
+        let _ = (call, size);
+    }
+
+    /// Record that a message of `size` bytes, as received on the wire, was
+    /// received on `call`.
+    fn message_received(&self, call: &CallInfo, size: usize) {
+        let _ = (call, size);
+    }
+}
+
+impl<R: MetricsRecorder + ?Sized> MetricsRecorder for Arc<R> {
+    fn call_started(&self, call: &CallInfo) {
+        (**self).call_started(call)
+    }
+
+    fn call_completed(&self, call: &CallInfo, code: Code, latency: Duration) {
+        (**self).call_completed(call, code, latency)
+    }
+
+    fn message_sent(&self, call: &CallInfo, size: usize) {
+        (**self).message_sent(call, size)
+    }
+
+    fn message_received(&self, call: &CallInfo, size: usize) {
+        (**self).message_received(call, size)
+    }
+}
+
+/// Whether calls are recorded by a client or a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// The calls made by a client.
+    Client,
+    /// The calls served by a server.
+    Server,
+}
+
+/// The call metrics are recorded for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CallInfo {
+    side: Side,
+    service: String,
+    method: String,
+}
+
+impl CallInfo {
+    fn from_path(side: Side, path: &str) -> Self {
+        let path = path.trim_start_matches('/');
+        let (service, method) = match path.rfind('/') {
+            Some(slash) => (&path[..slash], &path[slash + 1..]),
+            None => (path, ""),
+        };
+        CallInfo {
+            side,
+            service: service.to_string(),
+            method: method.to_string(),
+        }
+    }
+
+    /// Whether the call was made by a client or served by a server.
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    /// The fully qualified name of the service of the call, like
+    /// `helloworld.Greeter`.
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// The name of the method of the call, like `SayHello`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+}
+
+/// Wraps services with [`Metrics`], recording the metrics of their calls.
+///
+/// [`Metrics`]: struct.Metrics.html
+#[derive(Clone)]
+pub struct MetricsLayer {
+    side: Side,
+    recorder: Arc<dyn MetricsRecorder>,
+}
+
+impl MetricsLayer {
+    /// Record the calls made by a client to `recorder`, wrapping its
+    /// channel.
+    pub fn client(recorder: impl MetricsRecorder) -> Self {
+        MetricsLayer {
+            side: Side::Client,
+            recorder: Arc::new(recorder),
+        }
+    }
+
+    /// Record the calls served by a server to `recorder`, wrapping the
+    /// services added to it.
+    pub fn server(recorder: impl MetricsRecorder) -> Self {
+        MetricsLayer {
+            side: Side::Server,
+            recorder: Arc::new(recorder),
+        }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = Metrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Metrics {
+            inner,
+            side: self.side,
+            recorder: self.recorder.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for MetricsLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetricsLayer")
+            .field("side", &self.side)
+            .finish()
+    }
+}
+
+/// A service recording the metrics of its calls, created by a
+/// [`MetricsLayer`].
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`MetricsLayer`]: struct.MetricsLayer.html
+/// [`Server`]: ../transport/struct.Server.html
+#[derive(Clone)]
+pub struct Metrics<S> {
+    inner: S,
+    side: Side,
+    recorder: Arc<dyn MetricsRecorder>,
+}
+
+impl<S> Metrics<S> {
+    fn start<B>(&self, request: &http::Request<B>) -> Arc<Tracker> {
+        let tracker = Arc::new(Tracker {
+            call: CallInfo::from_path(self.side, request.uri().path()),
+            recorder: self.recorder.clone(),
+            started_at: Instant::now(),
+            completed: AtomicBool::new(false),
+        });
+        tracker.recorder.call_started(&tracker.call);
+        tracker
+    }
+}
+
+impl<S, ResBody> Service<http::Request<BoxBody>> for Metrics<S>
+where
+    S: Service<http::Request<BoxBody>, Response = http::Response<ResBody>>,
+    S::Error: Into<crate::Error>,
+    ResBody: HttpBody<Data = Bytes> + Send + Sync + 'static,
+    ResBody::Error: Into<crate::Error>,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = crate::Error;
+    type Future = MetricsFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: http::Request<BoxBody>) -> Self::Future {
+        let tracker = self.start(&request);
+        let request =
+            request.map(|inner| BoxBody::map_from(MetricsBody::new(inner, tracker.clone(), false)));
+        MetricsFuture {
+            inner: self.inner.call(request),
+            tracker: Some(tracker),
+        }
+    }
+}
+
+#[cfg(feature = "transport")]
+impl<S, ResBody> Service<http::Request<hyper::Body>> for Metrics<S>
+where
+    S: Service<http::Request<hyper::Body>, Response = http::Response<ResBody>>,
+    S::Error: Into<crate::Error>,
+    ResBody: HttpBody<Data = Bytes> + Send + Sync + 'static,
+    ResBody::Error: Into<crate::Error>,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = crate::Error;
+    type Future = MetricsFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: http::Request<hyper::Body>) -> Self::Future {
+        // The generated servers take hyper bodies, which can only wrap the
+        // data of the requests, their trailers being unused by gRPC.
+        let tracker = self.start(&request);
+        let request = request
+            .map(|inner| hyper::Body::wrap_stream(MetricsBody::new(inner, tracker.clone(), false)));
+        MetricsFuture {
+            inner: self.inner.call(request),
+            tracker: Some(tracker),
+        }
+    }
+}
+
+#[cfg(feature = "transport")]
+impl<S: crate::transport::NamedService> crate::transport::NamedService for Metrics<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S: fmt::Debug> fmt::Debug for Metrics<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("inner", &self.inner)
+            .field("side", &self.side)
+            .finish()
+    }
+}
+
+/// The response future of [`Metrics`].
+///
+/// [`Metrics`]: struct.Metrics.html
+#[pin_project]
+pub struct MetricsFuture<F> {
+    #[pin]
+    inner: F,
+    tracker: Option<Arc<Tracker>>,
+}
+
+impl<F, B, E> Future for MetricsFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+    E: Into<crate::Error>,
+    B: HttpBody<Data = Bytes> + Send + Sync + 'static,
+    B::Error: Into<crate::Error>,
+{
+    type Output = Result<http::Response<BoxBody>, crate::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.inner.poll(cx));
+        let tracker = this.tracker.take().expect("polled after completion");
+
+        let response = match result {
+            Ok(response) => response,
+            Err(err) => {
+                let status = Status::from_error(err.into());
+                tracker.complete(status.code());
+                return Poll::Ready(Err(Box::new(status)));
+            }
+        };
+        // The status of trailers-only responses is known right away, the
+        // one of the others once their trailers are sent or received.
+        if let Some(status) = Status::from_header_map(response.headers()) {
+            tracker.complete(status.code());
+        }
+        Poll::Ready(Ok(response.map(|inner| {
+            BoxBody::map_from(MetricsBody::new(inner, tracker, true))
+        })))
+    }
+}
+
+impl<F> fmt::Debug for MetricsFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetricsFuture").finish()
+    }
+}
+
+/// A call in progress, completed with `CANCELLED` if dropped before its
+/// status was known.
+struct Tracker {
+    call: CallInfo,
+    recorder: Arc<dyn MetricsRecorder>,
+    started_at: Instant,
+    completed: AtomicBool,
+}
+
+impl Tracker {
+    fn complete(&self, code: Code) {
+        if !self.completed.swap(true, Ordering::SeqCst) {
+            let latency = self.started_at.elapsed();
+            self.recorder.call_completed(&self.call, code, latency);
+        }
+    }
+
+    fn message(&self, size: usize, response: bool) {
+        // Clients send requests and receive responses, servers the other way.
+        let sent = response == (self.call.side == Side::Server);
+        if sent {
+            self.recorder.message_sent(&self.call, size);
+        } else {
+            self.recorder.message_received(&self.call, size);
+        }
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        self.complete(Code::Cancelled);
+    }
+}
+
+/// The request or response body of a call, recording its messages, and the
+/// status of the call from the trailers of responses.
+#[pin_project]
+struct MetricsBody<B> {
+    #[pin]
+    inner: B,
+    tracker: Arc<Tracker>,
+    frames: Frames,
+    response: bool,
+}
+
+impl<B> MetricsBody<B> {
+    fn new(inner: B, tracker: Arc<Tracker>, response: bool) -> Self {
+        MetricsBody {
+            inner,
+            tracker,
+            frames: Frames::default(),
+            response,
+        }
+    }
+}
+
+impl<B> HttpBody for MetricsBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+    B::Error: Into<crate::Error>,
+{
+    type Data = Bytes;
+    type Error = crate::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        match ready!(this.inner.poll_data(cx)) {
+            Some(Ok(data)) => {
+                let tracker = &this.tracker;
+                let response = *this.response;
+                this.frames
+                    .read(&data, |size| tracker.message(size, response));
+                Poll::Ready(Some(Ok(data)))
+            }
+            Some(Err(err)) => {
+                let status = Status::from_error(err.into());
+                if *this.response {
+                    this.tracker.complete(status.code());
+                }
+                Poll::Ready(Some(Err(Box::new(status))))
+            }
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = match ready!(this.inner.poll_trailers(cx)) {
+            Ok(trailers) => trailers,
+            Err(err) => {
+                let status = Status::from_error(err.into());
+                if *this.response {
+                    this.tracker.complete(status.code());
+                }
+                return Poll::Ready(Err(Box::new(status)));
+            }
+        };
+        if *this.response {
+            let code = trailers
+                .as_ref()
+                .and_then(Status::from_header_map)
+                .map_or(Code::Unknown, |status| status.code());
+            this.tracker.complete(code);
+        }
+        Poll::Ready(Ok(trailers))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+}
+
+#[cfg(feature = "transport")]
+impl<B> futures_core::Stream for MetricsBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+    B::Error: Into<crate::Error>,
+{
+    type Item = Result<Bytes, crate::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_data(cx)
+    }
+}
+
+/// Splits the data of a body into the messages it carries.
+#[derive(Debug, Default)]
+pub(crate) struct Frames {
+    prefix: [u8; PREFIX_SIZE],
+    /// The bytes of the prefix of the current message read so far.
+    prefix_read: usize,
+    /// The bytes of the current message left to read.
+    remaining: usize,
+}
+
+impl Frames {
+    /// Read `data`, calling `message` with the size of each message read
+    /// to its end.
+    pub(crate) fn read(&mut self, mut data: &[u8], mut message: impl FnMut(usize)) {
+        while !data.is_empty() {
+            if self.prefix_read < PREFIX_SIZE {
+                let n = (PREFIX_SIZE - self.prefix_read).min(data.len());
+                self.prefix[self.prefix_read..self.prefix_read + n].copy_from_slice(&data[..n]);
+                self.prefix_read += n;
+                data = &data[n..];
+                if self.prefix_read == PREFIX_SIZE {
+                    let mut len = [0; 4];
+                    len.copy_from_slice(&self.prefix[1..]);
+                    self.remaining = u32::from_be_bytes(len) as usize;
+                }
+            } else {
+                let n = self.remaining.min(data.len());
+                self.remaining -= n;
+                data = &data[n..];
+            }
+
+            if self.prefix_read == PREFIX_SIZE && self.remaining == 0 {
+                let mut len = [0; 4];
+                len.copy_from_slice(&self.prefix[1..]);
+                message(PREFIX_SIZE + u32::from_be_bytes(len) as usize);
+                self.prefix_read = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Events(Mutex<Vec<String>>);
+
+    impl MetricsRecorder for Events {
+        fn call_started(&self, call: &CallInfo) {
+            let event = format!("started {}/{}", call.service(), call.method());
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn call_completed(&self, _: &CallInfo, code: Code, _: Duration) {
+            self.0.lock().unwrap().push(format!("completed {:?}", code));
+        }
+
+        fn message_sent(&self, _: &CallInfo, size: usize) {
+            self.0.lock().unwrap().push(format!("sent {}", size));
+        }
+
+        fn message_received(&self, _: &CallInfo, size: usize) {
+            self.0.lock().unwrap().push(format!("received {}", size));
+        }
+    }
+
+    fn message(len: usize) -> Vec<u8> {
+        let mut message = vec![0];
+        message.extend_from_slice(&(len as u32).to_be_bytes());
+        message.resize(PREFIX_SIZE + len, 42);
+        message
+    }
+
+    #[test]
+    fn splits_the_messages_of_the_data() {
+        let data = [message(3), message(0), message(10)].concat();
+
+        let mut sizes = Vec::new();
+        let mut frames = Frames::default();
+        for chunk in data.chunks(4) {
+            frames.read(chunk, |size| sizes.push(size));
+        }
+        assert_eq!(sizes, vec![8, 5, 15]);
+
+        let mut sizes = Vec::new();
+        Frames::default().read(&data, |size| sizes.push(size));
+        assert_eq!(sizes, vec![8, 5, 15]);
+    }
+
+    #[test]
+    fn parses_the_paths_of_the_calls() {
+        let call = CallInfo::from_path(Side::Client, "/helloworld.Greeter/SayHello");
+        assert_eq!(call.service(), "helloworld.Greeter");
+        assert_eq!(call.method(), "SayHello");
+    }
+
+    #[test]
+    fn completes_the_calls_once() {
+        let events = Arc::new(Events::default());
+        let tracker = Tracker {
+            call: CallInfo::from_path(Side::Server, "/test.Test/Echo"),
+            recorder: Arc::new(events.clone()),
+            started_at: Instant::now(),
+            completed: AtomicBool::new(false),
+        };
+        tracker.message(8, false);
+        tracker.message(15, true);
+        tracker.complete(Code::NotFound);
+        drop(tracker);
+
+        let tracker = Tracker {
+            call: CallInfo::from_path(Side::Client, "/test.Test/Echo"),
+            recorder: Arc::new(events.clone()),
+            started_at: Instant::now(),
+            completed: AtomicBool::new(false),
+        };
+        tracker.message(8, false);
+        drop(tracker);
+
+        assert_eq!(
+            *events.0.lock().unwrap(),
+            vec![
+                "received 8",
+                "sent 15",
+                "completed NotFound",
+                "sent 8",
+                "completed Cancelled",
+            ]
+        );
+    }
+}
@@ -0,0 +1,600 @@
+//! OAuth2 access tokens authenticating calls, like the ones of Google APIs.
+//!
+//! A [`TokenSource`] fetches access tokens from the token endpoint of an
+//! authorization server, over a [`Channel`] connected to it:
+//!
+//! - [`ServiceAccount`] with a JWT signed by the key of a Google service
+//!   account, from its JSON key file.
+//! - [`ClientCredentials`] with the id and secret of an OAuth2 client.
+//!
+//! [`TokenCredentials`] caches the token of a source, fetching a new one
+//! when it is about to expire, and sends it in the `authorization` header of
+//! each call, as [`CallCredentials`] attached to a client:
+//!
+//! ```rust,no_run
+//! use tonic::oauth2::{ServiceAccount, ServiceAccountKey, TokenCredentials};
+//! use tonic::transport::{Certificate, Channel, ClientTlsConfig};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let key = ServiceAccountKey::from_json(&std::fs::read("service-account.json")?)?;
+//! let roots = Certificate::from_pem(std::fs::read("roots.pem")?);
+//! let oauth2 = Channel::from_static("https://oauth2.googleapis.com")
+//!     .tls_config(
+//!         ClientTlsConfig::new()
+//!             .ca_certificate(roots)
+//!             .domain_name("oauth2.googleapis.com"),
+//!     )
+//!     .connect()
+//!     .await?;
+//! let credentials = TokenCredentials::new(
+//!     ServiceAccount::new(oauth2, key)
+//!         .scopes(vec!["https://www.googleapis.com/auth/cloud-platform"]),
+//! );
+//!
+//! // Attached to a generated client:
+//! // let client = PublisherClient::new(channel).call_credentials(credentials);
+//! # drop(credentials);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Channel`]: ../transport/struct.Channel.html
+//! [`CallCredentials`]: ../trait.CallCredentials.html
+//! [`TokenSource`]: trait.TokenSource.html
+//! [`ServiceAccount`]: struct.ServiceAccount.html
+//! [`ClientCredentials`]: struct.ClientCredentials.html
+//! [`TokenCredentials`]: struct.TokenCredentials.html
+
+use crate::{
+    body::BoxBody,
+    metadata::{AsciiMetadataValue, MetadataMap},
+    transport::Channel,
+    CallCredentials, Status,
+};
+use futures_util::lock::Mutex;
+use http::{header, Request, Uri};
+use percent_encoding::{percent_encode, EncodeSet};
+use ring::{
+    rand::SystemRandom,
+    signature::{RsaKeyPair, RSA_PKCS1_SHA256},
+};
+use serde_json::{json, Value};
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tower::ServiceExt;
+
+const JWT_BEARER_GRANT: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+const DEFAULT_REFRESH_BEFORE: Duration = Duration::from_secs(60);
+const DEFAULT_ASSERTION_LIFETIME: Duration = Duration::from_secs(3600);
+
+/// An access token, along with how long it is valid for.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Token {
+    access_token: String,
+    expires_in: Option<Duration>,
+}
+
+impl Token {
+    /// A token valid for `expires_in`, or until it is rejected if `None`.
+    pub fn new(access_token: impl Into<String>, expires_in: Option<Duration>) -> Self {
+        Token {
+            access_token: access_token.into(),
+            expires_in,
+        }
+    }
+
+    /// The access token, sent as `Bearer` token.
+    pub fn access_token(&self) -> &str {
+        &self.access_token
+    }
+
+    /// How long the token is valid for, from when it was issued.
+    pub fn expires_in(&self) -> Option<Duration> {
+        self.expires_in
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("expires_in", &self.expires_in)
+            .finish()
+    }
+}
+
+/// Fetches new access tokens.
+pub trait TokenSource: Send + Sync + 'static {
+    /// A new access token.
+    ///
+    /// An error fails the calls waiting for the token with the returned
+    /// status.
+    fn token(&self) -> Pin<Box<dyn Future<Output = Result<Token, Status>> + Send + '_>>;
+}
+
+/// Caches the token of a [`TokenSource`], sent as `Bearer` token in the
+/// `authorization` header of the calls.
+///
+/// The token is fetched by the first call, and again by the first call after
+/// it is about to expire, the other calls waiting for it. A failed fetch is
+/// retried by the next call.
+///
+/// [`TokenSource`]: trait.TokenSource.html
+pub struct TokenCredentials<S> {
+    source: S,
+    refresh_before: Duration,
+    cached: Mutex<Option<Cached>>,
+}
+
+struct Cached {
+    authorization: AsciiMetadataValue,
+    refresh_at: Option<Instant>,
+}
+
+impl<S: TokenSource> TokenCredentials<S> {
+    /// Credentials with the tokens of `source`.
+    pub fn new(source: S) -> Self {
+        TokenCredentials {
+            source,
+            refresh_before: DEFAULT_REFRESH_BEFORE,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Fetch a new token `refresh_before` it expires, by default one minute,
+    /// so it is still valid when it reaches the server.
+    pub fn refresh_before(self, refresh_before: Duration) -> Self {
+        TokenCredentials {
+            refresh_before,
+            ..self
+        }
+    }
+
+    async fn authorization(&self) -> Result<AsciiMetadataValue, Status> {
+        let mut cached = self.cached.lock().await;
+        if let Some(cached) = &*cached {
+            if cached.refresh_at.is_none_or(|at| Instant::now() < at) {
+                return Ok(cached.authorization.clone());
+            }
+        }
+
+        let issued = Instant::now();
+        let token = self.source.token().await?;
+        let authorization = format!("Bearer {}", token.access_token)
+            .parse::<AsciiMetadataValue>()
+            .map_err(|_| Status::unauthenticated("invalid access token"))?;
+        *cached = Some(Cached {
+            authorization: authorization.clone(),
+            refresh_at: token
+                .expires_in
+                .map(|expires_in| issued + expires_in.saturating_sub(self.refresh_before)),
+        });
+        Ok(authorization)
+    }
+}
+
+impl<S: TokenSource> CallCredentials for TokenCredentials<S> {
+    fn metadata<'a>(
+        &'a self,
+        _path: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<MetadataMap, Status>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut metadata = MetadataMap::new();
+            metadata.insert("authorization", self.authorization().await?);
+            Ok(metadata)
+        })
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for TokenCredentials<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenCredentials")
+            .field("source", &self.source)
+            .field("refresh_before", &self.refresh_before)
+            .finish()
+    }
+}
+
+/// The key of a Google service account, from its JSON key file.
+#[derive(Clone)]
+pub struct ServiceAccountKey {
+    client_email: String,
+    private_key_id: Option<String>,
+    token_uri: Uri,
+    key_pair: Arc<RsaKeyPair>,
+}
+
+impl ServiceAccountKey {
+    /// Parse the JSON key file of a service account, with its
+    /// `client_email`, `private_key` and `token_uri`.
+    pub fn from_json(json: &[u8]) -> Result<Self, InvalidKey> {
+        let key =
+            serde_json::from_slice::<Value>(json).map_err(|_| InvalidKey("not a JSON key file"))?;
+        let field = |name| key.get(name).and_then(Value::as_str);
+
+        let pem = field("private_key").ok_or(InvalidKey("missing private_key"))?;
+        let der = pem
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect::<String>();
+        let der = base64::decode(&der).map_err(|_| InvalidKey("invalid private_key"))?;
+        let key_pair =
+            RsaKeyPair::from_pkcs8(&der).map_err(|_| InvalidKey("invalid private_key"))?;
+
+        Ok(ServiceAccountKey {
+            client_email: field("client_email")
+                .ok_or(InvalidKey("missing client_email"))?
+                .to_string(),
+            private_key_id: field("private_key_id").map(str::to_string),
+            token_uri: field("token_uri")
+                .ok_or(InvalidKey("missing token_uri"))?
+                .parse()
+                .map_err(|_| InvalidKey("invalid token_uri"))?,
+            key_pair: Arc::new(key_pair),
+        })
+    }
+
+    /// The email address of the service account.
+    pub fn client_email(&self) -> &str {
+        &self.client_email
+    }
+
+    /// The token endpoint the account requests its tokens from.
+    pub fn token_uri(&self) -> &Uri {
+        &self.token_uri
+    }
+
+    /// The JWT asserting the identity of the account, or of the `subject` it
+    /// acts on behalf of, for `scopes`, issued at `now`.
+    fn assertion(
+        &self,
+        scopes: &[String],
+        subject: Option<&str>,
+        lifetime: Duration,
+        now: SystemTime,
+    ) -> Result<String, Status> {
+        let iat = now
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| Status::internal("system time before the UNIX epoch"))?
+            .as_secs();
+        let mut header = json!({ "alg": "RS256", "typ": "JWT" });
+        if let Some(kid) = &self.private_key_id {
+            header["kid"] = json!(kid);
+        }
+        let mut claims = json!({
+            "iss": self.client_email,
+            "scope": scopes.join(" "),
+            "aud": self.token_uri.to_string(),
+            "iat": iat,
+            "exp": iat + lifetime.as_secs(),
+        });
+        if let Some(subject) = subject {
+            claims["sub"] = json!(subject);
+        }
+
+        let message = format!(
+            "{}.{}",
+            base64::encode_config(&header.to_string(), base64::URL_SAFE_NO_PAD),
+            base64::encode_config(&claims.to_string(), base64::URL_SAFE_NO_PAD)
+        );
+        let mut signature = vec![0; self.key_pair.public_modulus_len()];
+        self.key_pair
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                message.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| Status::internal("failed to sign the JWT assertion"))?;
+        Ok(format!(
+            "{}.{}",
+            message,
+            base64::encode_config(&signature, base64::URL_SAFE_NO_PAD)
+        ))
+    }
+}
+
+impl fmt::Debug for ServiceAccountKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceAccountKey")
+            .field("client_email", &self.client_email)
+            .field("private_key_id", &self.private_key_id)
+            .field("token_uri", &self.token_uri)
+            .finish()
+    }
+}
+
+/// The error of a service account key that couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidKey(&'static str);
+
+impl fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid service account key: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidKey {}
+
+/// The tokens of a Google service account, exchanged for a JWT signed by
+/// its key.
+#[derive(Debug, Clone)]
+pub struct ServiceAccount {
+    channel: Channel,
+    key: ServiceAccountKey,
+    scopes: Vec<String>,
+    subject: Option<String>,
+}
+
+impl ServiceAccount {
+    /// The tokens of the account of `key`, requested from its `token_uri`
+    /// over `channel`, connected to it.
+    pub fn new(channel: Channel, key: ServiceAccountKey) -> Self {
+        ServiceAccount {
+            channel,
+            key,
+            scopes: Vec::new(),
+            subject: None,
+        }
+    }
+
+    /// The scopes the tokens are requested for.
+    pub fn scopes<I>(self, scopes: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        ServiceAccount {
+            scopes: scopes.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Request the tokens on behalf of the user `subject`, with the domain
+    /// wide delegation of the account.
+    pub fn subject(self, subject: impl Into<String>) -> Self {
+        ServiceAccount {
+            subject: Some(subject.into()),
+            ..self
+        }
+    }
+}
+
+impl TokenSource for ServiceAccount {
+    fn token(&self) -> Pin<Box<dyn Future<Output = Result<Token, Status>> + Send + '_>> {
+        Box::pin(async move {
+            let assertion = self.key.assertion(
+                &self.scopes,
+                self.subject.as_deref(),
+                DEFAULT_ASSERTION_LIFETIME,
+                SystemTime::now(),
+            )?;
+            let form = encode_form(&[("grant_type", JWT_BEARER_GRANT), ("assertion", &assertion)]);
+            request_token(&self.channel, &self.key.token_uri, form).await
+        })
+    }
+}
+
+/// The tokens of an OAuth2 client, requested with its id and secret.
+#[derive(Clone)]
+pub struct ClientCredentials {
+    channel: Channel,
+    token_uri: Uri,
+    client_id: String,
+    client_secret: String,
+    scopes: Vec<String>,
+}
+
+impl ClientCredentials {
+    /// The tokens of the client `client_id`, requested from `token_uri` over
+    /// `channel`, connected to it.
+    pub fn new(
+        channel: Channel,
+        token_uri: Uri,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        ClientCredentials {
+            channel,
+            token_uri,
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scopes: Vec::new(),
+        }
+    }
+
+    /// The scopes the tokens are requested for.
+    pub fn scopes<I>(self, scopes: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        ClientCredentials {
+            scopes: scopes.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+}
+
+impl TokenSource for ClientCredentials {
+    fn token(&self) -> Pin<Box<dyn Future<Output = Result<Token, Status>> + Send + '_>> {
+        Box::pin(async move {
+            let scope = self.scopes.join(" ");
+            let mut params = vec![
+                ("grant_type", "client_credentials"),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+            ];
+            if !scope.is_empty() {
+                params.push(("scope", &scope));
+            }
+            request_token(&self.channel, &self.token_uri, encode_form(&params)).await
+        })
+    }
+}
+
+impl fmt::Debug for ClientCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientCredentials")
+            .field("token_uri", &self.token_uri)
+            .field("client_id", &self.client_id)
+            .field("scopes", &self.scopes)
+            .finish()
+    }
+}
+
+/// POST the token request `form` to `token_uri`, returning the token of the
+/// response.
+async fn request_token(channel: &Channel, token_uri: &Uri, form: String) -> Result<Token, Status> {
+    let request = Request::post(token_uri.clone())
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .header(header::ACCEPT, "application/json")
+        .body(BoxBody::map_from(hyper::Body::from(form)))
+        .map_err(|_| Status::internal("invalid token request"))?;
+
+    let unavailable = |e: &dyn fmt::Display| {
+        Status::unavailable(format!("failed to request an access token: {}", e))
+    };
+    let response = channel
+        .clone()
+        .oneshot(request)
+        .await
+        .map_err(|e| unavailable(&e))?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .map_err(|e| unavailable(&e))?;
+
+    parse_token(status, &body)
+}
+
+/// The token of a token endpoint response, or the status of its error.
+fn parse_token(status: http::StatusCode, body: &[u8]) -> Result<Token, Status> {
+    let response = serde_json::from_slice::<Value>(body).unwrap_or(Value::Null);
+
+    if !status.is_success() {
+        let error = response
+            .get("error_description")
+            .or_else(|| response.get("error"))
+            .and_then(Value::as_str)
+            .unwrap_or_else(|| status.as_str());
+        let message = format!("access token request failed: {}", error);
+        return Err(if status.is_client_error() {
+            Status::unauthenticated(message)
+        } else {
+            Status::unavailable(message)
+        });
+    }
+
+    let access_token = response
+        .get("access_token")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Status::unauthenticated("no access token in the token response"))?;
+    let expires_in = response
+        .get("expires_in")
+        .and_then(Value::as_u64)
+        .map(Duration::from_secs);
+    Ok(Token::new(access_token, expires_in))
+}
+
+/// The bytes percent-encoded in `application/x-www-form-urlencoded` values:
+/// everything but the ASCII alphanumerics and `*-._`.
+#[derive(Clone, Copy)]
+struct FormEncodeSet;
+
+impl EncodeSet for FormEncodeSet {
+    fn contains(&self, byte: u8) -> bool {
+        !(byte.is_ascii_alphanumeric() || b"*-._".contains(&byte))
+    }
+}
+
+fn encode_form(params: &[(&str, &str)]) -> String {
+    params
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{}={}",
+                name,
+                percent_encode(value.as_bytes(), FormEncodeSet)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Issues `token-{n}`, valid for `expires_in`.
+    struct Counter(AtomicUsize, Option<Duration>);
+
+    impl TokenSource for Counter {
+        fn token(&self) -> Pin<Box<dyn Future<Output = Result<Token, Status>> + Send + '_>> {
+            Box::pin(async move {
+                let n = self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(Token::new(format!("token-{}", n), self.1))
+            })
+        }
+    }
+
+    async fn authorization<S: TokenSource>(credentials: &TokenCredentials<S>) -> String {
+        let metadata = credentials.metadata("/test.Test/Echo").await.unwrap();
+        metadata
+            .get("authorization")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn caches_tokens_until_they_are_about_to_expire() {
+        let credentials = TokenCredentials::new(Counter(AtomicUsize::new(0), None));
+        assert_eq!(authorization(&credentials).await, "Bearer token-0");
+        assert_eq!(authorization(&credentials).await, "Bearer token-0");
+
+        let expiring = Some(Duration::from_secs(30));
+        let credentials = TokenCredentials::new(Counter(AtomicUsize::new(0), expiring));
+        assert_eq!(authorization(&credentials).await, "Bearer token-0");
+        assert_eq!(authorization(&credentials).await, "Bearer token-1");
+
+        let credentials = credentials.refresh_before(Duration::from_secs(10));
+        assert_eq!(authorization(&credentials).await, "Bearer token-2");
+        assert_eq!(authorization(&credentials).await, "Bearer token-2");
+    }
+
+    #[test]
+    fn parses_token_responses() {
+        let token = parse_token(
+            http::StatusCode::OK,
+            br#"{"access_token":"abc","expires_in":3599,"token_type":"Bearer"}"#,
+        )
+        .unwrap();
+        assert_eq!(token, Token::new("abc", Some(Duration::from_secs(3599))));
+
+        let status = parse_token(
+            http::StatusCode::BAD_REQUEST,
+            br#"{"error":"invalid_grant","error_description":"Invalid JWT"}"#,
+        )
+        .unwrap_err();
+        assert_eq!(status.code(), crate::Code::Unauthenticated);
+        assert_eq!(status.message(), "access token request failed: Invalid JWT");
+
+        let status = parse_token(http::StatusCode::BAD_GATEWAY, b"").unwrap_err();
+        assert_eq!(status.code(), crate::Code::Unavailable);
+    }
+
+    #[test]
+    fn encodes_forms() {
+        assert_eq!(
+            encode_form(&[("grant_type", JWT_BEARER_GRANT), ("scope", "a b+c")]),
+            "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer&scope=a%20b%2Bc"
+        );
+    }
+}
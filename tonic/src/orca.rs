@@ -0,0 +1,693 @@
+//! Backend metrics of servers, following the [ORCA] load reports of gRPC.
+//!
+//! Servers report their load, like their CPU utilization or the cost of
+//! each call, so the load balancers of their clients can weight them by it.
+//! The reports are sent per call, as a serialized [`OrcaLoadReport`] in the
+//! `endpoint-load-metrics-bin` trailer, and out-of-band, streamed by the
+//! `xds.service.orca.v3.OpenRcaService` service.
+//!
+//! An [`OrcaLayer::server`] wrapping the services of a server hands an
+//! [`OrcaRecorder`] to each call, through the [`RequestContext`] of its
+//! request, and sends what the handler recorded in its trailers:
+//!
+//! ```rust
+//! use tonic::orca::{OrcaLayer, OrcaRecorder};
+//! use tonic::{Request, Response, Status};
+//!
+//! async fn say_hello(request: Request<String>) -> Result<Response<String>, Status> {
+//!     if let Some(recorder) = OrcaRecorder::from_request(&request) {
+//!         recorder.set_request_cost("db_queries", 3.0);
+//!     }
+//!     Ok(Response::new(format!("Hello {}!", request.into_inner())))
+//! }
+//!
+//! let server_metrics = OrcaRecorder::new();
+//! server_metrics.set_cpu_utilization(0.4);
+//!
+//! let orca = OrcaLayer::server().server_metrics(server_metrics);
+//!
+//! // Server::builder().add_service(orca.layer(GreeterServer::new(greeter)))
+//! ```
+//!
+//! An [`OrcaLayer::client`] wrapping a channel hands the reports received
+//! in the trailers to a listener, like a custom load balancer, which can
+//! also read the ones of [`Response::trailers`] with
+//! [`OrcaLoadReport::from_metadata`], or stream the out-of-band reports of
+//! a server with an [`OrcaClient`].
+//!
+//! [ORCA]: https://github.com/grpc/proposal/blob/master/A51-custom-backend-metrics.md
+//! [`OrcaLoadReport`]: struct.OrcaLoadReport.html
+//! [`OrcaLayer::server`]: struct.OrcaLayer.html#method.server
+//! [`OrcaLayer::client`]: struct.OrcaLayer.html#method.client
+//! [`OrcaRecorder`]: struct.OrcaRecorder.html
+//! [`RequestContext`]: ../struct.RequestContext.html
+//! [`Response::trailers`]: ../struct.Response.html#method.trailers
+//! [`OrcaLoadReport::from_metadata`]: struct.OrcaLoadReport.html#method.from_metadata
+//! [`OrcaClient`]: struct.OrcaClient.html
+
+use crate::{
+    body::{Body, BoxBody},
+    client::{Grpc, GrpcService},
+    codec::{ProstCodec, Streaming},
+    context::RequestContext,
+    error_details::Duration,
+    metadata::{Binary, MetadataMap, MetadataValue},
+    IntoRequest, Request, Response, Status,
+};
+use futures_util::ready;
+use http::{uri::PathAndQuery, HeaderMap};
+use http_body::Body as HttpBody;
+use pin_project::pin_project;
+use prost::Message;
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// The trailer carrying the load reports of calls.
+const TRAILER: &str = "endpoint-load-metrics-bin";
+
+const STREAM_CORE_METRICS: &str = "/xds.service.orca.v3.OpenRcaService/StreamCoreMetrics";
+
+/// The `xds.data.orca.v3.OrcaLoadReport` message, the load of a server.
+#[derive(Clone, PartialEq, Message)]
+pub struct OrcaLoadReport {
+    /// The CPU utilization of the server, from 0 to 1, or above when it
+    /// uses more than its share.
+    #[prost(double, tag = "1")]
+    pub cpu_utilization: f64,
+    /// The memory utilization of the server, from 0 to 1.
+    #[prost(double, tag = "2")]
+    pub mem_utilization: f64,
+    /// The costs of the call, by name, like the number of database queries
+    /// it made.
+    #[prost(map = "string, double", tag = "4")]
+    pub request_cost: HashMap<String, f64>,
+    /// The utilizations of the resources of the server, by name, from 0
+    /// to 1.
+    #[prost(map = "string, double", tag = "5")]
+    pub utilization: HashMap<String, f64>,
+    /// The queries per second served.
+    #[prost(double, tag = "6")]
+    pub rps_fractional: f64,
+    /// The errors per second.
+    #[prost(double, tag = "7")]
+    pub eps: f64,
+    /// Application specific metrics, by name.
+    #[prost(map = "string, double", tag = "8")]
+    pub named_metrics: HashMap<String, f64>,
+    /// The utilization of the server as defined by the application, from 0
+    /// to 1, or above when it uses more than its share.
+    #[prost(double, tag = "9")]
+    pub application_utilization: f64,
+}
+
+impl OrcaLoadReport {
+    /// The load report of the `endpoint-load-metrics-bin` trailer, if any
+    /// and valid.
+    pub fn from_metadata(metadata: &MetadataMap) -> Option<Self> {
+        let bytes = metadata.get_bin(TRAILER)?.to_bytes().ok()?;
+        OrcaLoadReport::decode(bytes).ok()
+    }
+
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let value = headers.get(TRAILER)?;
+        let bytes = MetadataValue::<Binary>::unchecked_from_header_value(value.clone())
+            .to_bytes()
+            .ok()?;
+        OrcaLoadReport::decode(bytes).ok()
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == OrcaLoadReport::default()
+    }
+
+    /// Overwrite the metrics of `self` with the ones set in `other`.
+    fn merge_from(&mut self, other: OrcaLoadReport) {
+        fn set(value: &mut f64, other: f64) {
+            if other != 0.0 {
+                *value = other;
+            }
+        }
+
+        set(&mut self.cpu_utilization, other.cpu_utilization);
+        set(&mut self.mem_utilization, other.mem_utilization);
+        set(&mut self.rps_fractional, other.rps_fractional);
+        set(&mut self.eps, other.eps);
+        set(
+            &mut self.application_utilization,
+            other.application_utilization,
+        );
+        self.request_cost.extend(other.request_cost);
+        self.utilization.extend(other.utilization);
+        self.named_metrics.extend(other.named_metrics);
+    }
+}
+
+/// The `xds.service.orca.v3.OrcaLoadReportRequest` message, asking a
+/// server for its out-of-band load reports.
+#[derive(Clone, PartialEq, Message)]
+pub struct OrcaLoadReportRequest {
+    /// The interval between the reports, raised to the minimum of the
+    /// server.
+    #[prost(message, optional, tag = "1")]
+    pub report_interval: Option<Duration>,
+    /// The names of the request costs to report, none by default.
+    #[prost(string, repeated, tag = "2")]
+    pub request_cost_names: Vec<String>,
+}
+
+/// Records the metrics of a load report.
+///
+/// A recorder is either the one of a call, handed to its handler by an
+/// [`OrcaLayer::server`], or the one of a server, whose metrics are sent in
+/// the reports of all its calls, and out-of-band by an [`OrcaService`]. The
+/// metrics of a call take precedence over the ones of its server.
+///
+/// It is cheap to clone, its clones recording to the same report.
+///
+/// [`OrcaLayer::server`]: struct.OrcaLayer.html#method.server
+/// [`OrcaService`]: struct.OrcaService.html
+#[derive(Clone, Default)]
+pub struct OrcaRecorder {
+    report: Arc<Mutex<OrcaLoadReport>>,
+}
+
+impl OrcaRecorder {
+    /// Create a recorder with an empty report.
+    pub fn new() -> Self {
+        OrcaRecorder::default()
+    }
+
+    /// The recorder of the call of `request`, if its server is wrapped by an
+    /// [`OrcaLayer::server`].
+    ///
+    /// [`OrcaLayer::server`]: struct.OrcaLayer.html#method.server
+    pub fn from_request<T>(request: &Request<T>) -> Option<Self> {
+        request.context()?.get::<OrcaRecorder>()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut OrcaLoadReport)) {
+        f(&mut self.report.lock().unwrap());
+    }
+
+    /// Record the CPU utilization, from 0 to 1, or above.
+    pub fn set_cpu_utilization(&self, value: f64) {
+        self.update(|report| report.cpu_utilization = value);
+    }
+
+    /// Record the memory utilization, from 0 to 1.
+    pub fn set_memory_utilization(&self, value: f64) {
+        self.update(|report| report.mem_utilization = value);
+    }
+
+    /// Record the utilization defined by the application, from 0 to 1, or
+    /// above.
+    pub fn set_application_utilization(&self, value: f64) {
+        self.update(|report| report.application_utilization = value);
+    }
+
+    /// Record the queries per second.
+    pub fn set_qps(&self, value: f64) {
+        self.update(|report| report.rps_fractional = value);
+    }
+
+    /// Record the errors per second.
+    pub fn set_eps(&self, value: f64) {
+        self.update(|report| report.eps = value);
+    }
+
+    /// Record the cost `name` of the call.
+    pub fn set_request_cost(&self, name: impl Into<String>, value: f64) {
+        self.update(|report| {
+            report.request_cost.insert(name.into(), value);
+        });
+    }
+
+    /// Record the utilization of the resource `name`, from 0 to 1.
+    pub fn set_utilization(&self, name: impl Into<String>, value: f64) {
+        self.update(|report| {
+            report.utilization.insert(name.into(), value);
+        });
+    }
+
+    /// Record the application specific metric `name`.
+    pub fn set_named_metric(&self, name: impl Into<String>, value: f64) {
+        self.update(|report| {
+            report.named_metrics.insert(name.into(), value);
+        });
+    }
+
+    /// The report recorded so far.
+    pub fn report(&self) -> OrcaLoadReport {
+        self.report.lock().unwrap().clone()
+    }
+}
+
+impl fmt::Debug for OrcaRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OrcaRecorder").field(&self.report()).finish()
+    }
+}
+
+type Listener = Arc<dyn Fn(&OrcaLoadReport) + Send + Sync + 'static>;
+
+#[derive(Clone)]
+enum Role {
+    Server(Option<OrcaRecorder>),
+    Client(Listener),
+}
+
+/// Wraps services with [`Orca`], sending or receiving the load reports of
+/// their calls.
+///
+/// [`Orca`]: struct.Orca.html
+#[derive(Clone)]
+pub struct OrcaLayer {
+    role: Role,
+}
+
+impl OrcaLayer {
+    /// Send the load reports of the calls served by a server in their
+    /// trailers, wrapping the services added to it.
+    ///
+    /// Each call gets its own [`OrcaRecorder`], found with
+    /// [`OrcaRecorder::from_request`], its report sent unless empty.
+    ///
+    /// [`OrcaRecorder`]: struct.OrcaRecorder.html
+    /// [`OrcaRecorder::from_request`]: struct.OrcaRecorder.html#method.from_request
+    pub fn server() -> Self {
+        OrcaLayer {
+            role: Role::Server(None),
+        }
+    }
+
+    /// Hand the load reports received in the trailers of the calls made by
+    /// a client to `listener`, wrapping its channel.
+    pub fn client(listener: impl Fn(&OrcaLoadReport) + Send + Sync + 'static) -> Self {
+        OrcaLayer {
+            role: Role::Client(Arc::new(listener)),
+        }
+    }
+
+    /// Also send the metrics of `recorder`, the ones of the server, in the
+    /// reports of the calls. Ignored by the client layers.
+    pub fn server_metrics(self, recorder: OrcaRecorder) -> Self {
+        match self.role {
+            Role::Server(_) => OrcaLayer {
+                role: Role::Server(Some(recorder)),
+            },
+            role => OrcaLayer { role },
+        }
+    }
+}
+
+impl<S> Layer<S> for OrcaLayer {
+    type Service = Orca<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Orca {
+            inner,
+            role: self.role.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for OrcaLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OrcaLayer").finish()
+    }
+}
+
+/// A service sending or receiving the load reports of its calls, created
+/// by an [`OrcaLayer`].
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`OrcaLayer`]: struct.OrcaLayer.html
+/// [`Server`]: ../transport/struct.Server.html
+#[derive(Clone)]
+pub struct Orca<S> {
+    inner: S,
+    role: Role,
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for Orca<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Error: Into<crate::Error>,
+    ResBody: Body + Send + Sync + 'static,
+    ResBody::Error: Into<crate::Error>,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = crate::Error;
+    type Future = OrcaFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut request: http::Request<ReqBody>) -> Self::Future {
+        let reports = match &self.role {
+            Role::Server(server) => {
+                let call = OrcaRecorder::new();
+                RequestContext::get_or_insert(request.extensions_mut()).insert(call.clone());
+                Reports::Send {
+                    call,
+                    server: server.clone(),
+                }
+            }
+            Role::Client(listener) => Reports::Receive(listener.clone()),
+        };
+        OrcaFuture {
+            inner: self.inner.call(request),
+            reports: Some(reports),
+        }
+    }
+}
+
+#[cfg(feature = "transport")]
+impl<S: crate::transport::NamedService> crate::transport::NamedService for Orca<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S: fmt::Debug> fmt::Debug for Orca<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Orca").field("inner", &self.inner).finish()
+    }
+}
+
+/// The response future of [`Orca`].
+///
+/// [`Orca`]: struct.Orca.html
+#[pin_project]
+pub struct OrcaFuture<F> {
+    #[pin]
+    inner: F,
+    reports: Option<Reports>,
+}
+
+impl<F, B, E> Future for OrcaFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+    E: Into<crate::Error>,
+    B: Body + Send + Sync + 'static,
+    B::Error: Into<crate::Error>,
+{
+    type Output = Result<http::Response<BoxBody>, crate::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let response = ready!(this.inner.poll(cx)).map_err(Into::into)?;
+        let reports = this.reports.take().expect("polled after completion");
+        Poll::Ready(Ok(
+            response.map(|inner| BoxBody::map_from(OrcaBody { inner, reports }))
+        ))
+    }
+}
+
+impl<F> fmt::Debug for OrcaFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OrcaFuture").finish()
+    }
+}
+
+enum Reports {
+    Send {
+        call: OrcaRecorder,
+        server: Option<OrcaRecorder>,
+    },
+    Receive(Listener),
+}
+
+impl Reports {
+    fn trailers(&self, trailers: &mut HeaderMap) {
+        match self {
+            Reports::Send { call, server } => {
+                let mut report = server
+                    .as_ref()
+                    .map(OrcaRecorder::report)
+                    .unwrap_or_default();
+                report.merge_from(call.report());
+                if report.is_empty() {
+                    return;
+                }
+                let mut buf = Vec::with_capacity(report.encoded_len());
+                if report.encode(&mut buf).is_ok() {
+                    let value = MetadataValue::<Binary>::from_bytes(&buf);
+                    trailers.insert(TRAILER, value.inner);
+                }
+            }
+            Reports::Receive(listener) => {
+                if let Some(report) = OrcaLoadReport::from_headers(trailers) {
+                    listener(&report);
+                }
+            }
+        }
+    }
+}
+
+/// The response body of a call, sending or receiving the load report of
+/// its trailers.
+#[pin_project]
+struct OrcaBody<B> {
+    #[pin]
+    inner: B,
+    reports: Reports,
+}
+
+impl<B> HttpBody for OrcaBody<B>
+where
+    B: Body,
+    B::Error: Into<crate::Error>,
+{
+    type Data = B::Data;
+    type Error = crate::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let data = ready!(Body::poll_data(self.project().inner, cx));
+        Poll::Ready(data.map(|data| data.map_err(Into::into)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let mut trailers = ready!(Body::poll_trailers(this.inner, cx)).map_err(Into::into)?;
+        if let Some(trailers) = &mut trailers {
+            this.reports.trailers(trailers);
+        }
+        Poll::Ready(Ok(trailers))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        Body::is_end_stream(&self.inner)
+    }
+}
+
+/// A client of the `xds.service.orca.v3.OpenRcaService` service, streaming
+/// the out-of-band load reports of a server.
+///
+/// ```rust,no_run
+/// # use tonic::transport::Channel;
+/// # use tonic::orca::{OrcaClient, OrcaLoadReportRequest};
+/// # use std::time::Duration;
+/// # async fn f(channel: Channel) -> Result<(), tonic::Status> {
+/// let mut client = OrcaClient::new(channel);
+/// let mut reports = client
+///     .stream_core_metrics(OrcaLoadReportRequest {
+///         report_interval: Some(Duration::from_secs(30).into()),
+///         request_cost_names: vec![],
+///     })
+///     .await?
+///     .into_inner();
+///
+/// while let Some(report) = reports.message().await? {
+///     println!("cpu: {}", report.cpu_utilization);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OrcaClient<T> {
+    inner: Grpc<T>,
+}
+
+impl<T> OrcaClient<T>
+where
+    T: GrpcService<BoxBody>,
+    T::ResponseBody: Body + HttpBody + Send + 'static,
+    T::Error: Into<crate::Error>,
+    <T::ResponseBody as HttpBody>::Error: Into<crate::Error> + Send,
+{
+    /// Create a client sending its requests to `inner`, like a channel.
+    pub fn new(inner: T) -> Self {
+        OrcaClient {
+            inner: Grpc::new(inner),
+        }
+    }
+
+    /// Stream the load reports of the server, at the interval of
+    /// `request`.
+    pub async fn stream_core_metrics(
+        &mut self,
+        request: impl IntoRequest<OrcaLoadReportRequest>,
+    ) -> Result<Response<Streaming<OrcaLoadReport>>, Status> {
+        self.inner.ready().await.map_err(|e| {
+            Status::new(
+                crate::Code::Unknown,
+                format!("Service was not ready: {}", e.into()),
+            )
+        })?;
+        let codec = ProstCodec::default();
+        let path = PathAndQuery::from_static(STREAM_CORE_METRICS);
+        self.inner
+            .server_streaming(request.into_request(), path, codec)
+            .await
+    }
+}
+
+#[cfg(feature = "transport")]
+pub use self::service::OrcaService;
+
+#[cfg(feature = "transport")]
+mod service {
+    use super::{OrcaLoadReport, OrcaLoadReportRequest, OrcaRecorder, STREAM_CORE_METRICS};
+    use crate::{
+        body::BoxBody, codec::ProstCodec, server::Grpc, transport::NamedService, Request, Response,
+        Status,
+    };
+    use futures_core::Stream;
+    use std::{
+        convert::Infallible,
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+        time::Duration,
+    };
+    use tokio::time::{interval, Interval};
+    use tower_service::Service;
+
+    const DEFAULT_MIN_REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// The `xds.service.orca.v3.OpenRcaService` service, streaming the load
+    /// reports of a server out-of-band, from the metrics of an
+    /// [`OrcaRecorder`].
+    ///
+    /// ```rust
+    /// use tonic::orca::{OrcaRecorder, OrcaService};
+    ///
+    /// let server_metrics = OrcaRecorder::new();
+    /// let service = OrcaService::new(server_metrics.clone());
+    ///
+    /// // Server::builder().add_service(service)
+    /// server_metrics.set_cpu_utilization(0.4);
+    /// ```
+    ///
+    /// [`OrcaRecorder`]: struct.OrcaRecorder.html
+    #[derive(Debug, Clone)]
+    pub struct OrcaService {
+        recorder: OrcaRecorder,
+        min_report_interval: Duration,
+    }
+
+    impl OrcaService {
+        /// Stream the reports of `recorder`.
+        pub fn new(recorder: OrcaRecorder) -> Self {
+            OrcaService {
+                recorder,
+                min_report_interval: DEFAULT_MIN_REPORT_INTERVAL,
+            }
+        }
+
+        /// The minimum interval between the reports, the one of the clients
+        /// asking for a shorter one. Defaults to 30 seconds.
+        pub fn min_report_interval(self, interval: Duration) -> Self {
+            OrcaService {
+                min_report_interval: interval,
+                ..self
+            }
+        }
+    }
+
+    impl Service<http::Request<hyper::Body>> for OrcaService {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<hyper::Body>) -> Self::Future {
+            if request.uri().path() != STREAM_CORE_METRICS {
+                return Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .body(BoxBody::empty())
+                        .unwrap())
+                });
+            }
+
+            let service = self.clone();
+            Box::pin(async move {
+                let method = tower::service_fn(move |request| {
+                    let reports = service.reports(request);
+                    async move { Ok::<_, Status>(Response::new(reports)) }
+                });
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc.server_streaming(method, request).await)
+            })
+        }
+    }
+
+    impl OrcaService {
+        fn reports(&self, request: Request<OrcaLoadReportRequest>) -> Reports {
+            let request = request.into_inner();
+            let period = request
+                .report_interval
+                .map(Duration::from)
+                .unwrap_or_default()
+                .max(self.min_report_interval);
+            Reports {
+                recorder: self.recorder.clone(),
+                request_cost_names: request.request_cost_names,
+                interval: interval(period),
+            }
+        }
+    }
+
+    impl NamedService for OrcaService {
+        const NAME: &'static str = "xds.service.orca.v3.OpenRcaService";
+    }
+
+    /// The reports of a server, one per interval, the first one right away.
+    struct Reports {
+        recorder: OrcaRecorder,
+        request_cost_names: Vec<String>,
+        interval: Interval,
+    }
+
+    impl Stream for Reports {
+        type Item = Result<OrcaLoadReport, Status>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            futures_util::ready!(self.interval.poll_tick(cx));
+            let mut report = self.recorder.report();
+            let names = &self.request_cost_names;
+            report.request_cost.retain(|name, _| names.contains(name));
+            Poll::Ready(Some(Ok(report)))
+        }
+    }
+}
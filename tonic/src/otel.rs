@@ -0,0 +1,218 @@
+//! Metrics of the calls of clients and servers following the semantic
+//! conventions of OpenTelemetry for RPCs.
+//!
+//! An [`OtelRecorder`] is the [`MetricsRecorder`] of a [`MetricsLayer`],
+//! recording the durations of the calls and the sizes of their messages to
+//! the histograms of the conventions, like `rpc.server.duration`, with the
+//! `rpc.system`, `rpc.service`, `rpc.method` and `rpc.grpc.status_code`
+//! attributes, so the dashboards built for the other gRPC implementations
+//! work for tonic services too.
+//!
+//! The values are recorded to a [`Meter`], usually bridging to the
+//! histograms of an OpenTelemetry meter:
+//!
+//! ```rust
+//! use tonic::metrics::MetricsLayer;
+//! use tonic::otel::{Instrument, KeyValue, Meter, OtelRecorder};
+//!
+//! struct Print;
+//!
+//! impl Meter for Print {
+//!     fn record(&self, instrument: Instrument, value: f64, attributes: &[KeyValue<'_>]) {
+//!         println!("{} {}{} {:?}", instrument.name(), value, instrument.unit(), attributes);
+//!     }
+//! }
+//!
+//! let metrics = MetricsLayer::server(OtelRecorder::new(Print));
+//!
+//! // Server::builder().add_service(metrics.layer(GreeterServer::new(greeter)))
+//! ```
+//!
+//! [`OtelRecorder`]: struct.OtelRecorder.html
+//! [`MetricsRecorder`]: ../metrics/trait.MetricsRecorder.html
+//! [`MetricsLayer`]: ../metrics/struct.MetricsLayer.html
+//! [`Meter`]: trait.Meter.html
+
+use crate::{
+    metrics::{CallInfo, MetricsRecorder, Side},
+    Code,
+};
+use std::{fmt, sync::Arc, time::Duration};
+
+/// The histograms of the semantic conventions for RPCs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Instrument {
+    /// `rpc.server.duration`, the durations of the calls served, in
+    /// milliseconds.
+    ServerDuration,
+    /// `rpc.server.request.size`, the sizes of the messages received by
+    /// servers, in bytes.
+    ServerRequestSize,
+    /// `rpc.server.response.size`, the sizes of the messages sent by
+    /// servers, in bytes.
+    ServerResponseSize,
+    /// `rpc.client.duration`, the durations of the calls made, in
+    /// milliseconds.
+    ClientDuration,
+    /// `rpc.client.request.size`, the sizes of the messages sent by
+    /// clients, in bytes.
+    ClientRequestSize,
+    /// `rpc.client.response.size`, the sizes of the messages received by
+    /// clients, in bytes.
+    ClientResponseSize,
+}
+
+impl Instrument {
+    /// All the instruments, to create them up front.
+    pub const ALL: [Instrument; 6] = [
+        Instrument::ServerDuration,
+        Instrument::ServerRequestSize,
+        Instrument::ServerResponseSize,
+        Instrument::ClientDuration,
+        Instrument::ClientRequestSize,
+        Instrument::ClientResponseSize,
+    ];
+
+    /// The name of the instrument, like `rpc.server.duration`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Instrument::ServerDuration => "rpc.server.duration",
+            Instrument::ServerRequestSize => "rpc.server.request.size",
+            Instrument::ServerResponseSize => "rpc.server.response.size",
+            Instrument::ClientDuration => "rpc.client.duration",
+            Instrument::ClientRequestSize => "rpc.client.request.size",
+            Instrument::ClientResponseSize => "rpc.client.response.size",
+        }
+    }
+
+    /// The UCUM unit of the values of the instrument, `ms` or `By`.
+    pub fn unit(self) -> &'static str {
+        match self {
+            Instrument::ServerDuration | Instrument::ClientDuration => "ms",
+            _ => "By",
+        }
+    }
+
+    /// The description of the instrument.
+    pub fn description(self) -> &'static str {
+        match self {
+            Instrument::ServerDuration => "Measures the duration of inbound RPC.",
+            Instrument::ServerRequestSize => "Measures the size of RPC request messages.",
+            Instrument::ServerResponseSize => "Measures the size of RPC response messages.",
+            Instrument::ClientDuration => "Measures the duration of outbound RPC.",
+            Instrument::ClientRequestSize => "Measures the size of RPC request messages.",
+            Instrument::ClientResponseSize => "Measures the size of RPC response messages.",
+        }
+    }
+}
+
+impl fmt::Display for Instrument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The value of an attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Value<'a> {
+    /// A string, like the name of a service.
+    String(&'a str),
+    /// An integer, like a status code.
+    I64(i64),
+}
+
+/// An attribute of a recorded value, like `rpc.method`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyValue<'a> {
+    /// The name of the attribute.
+    pub key: &'static str,
+    /// The value of the attribute.
+    pub value: Value<'a>,
+}
+
+impl<'a> KeyValue<'a> {
+    fn new(key: &'static str, value: Value<'a>) -> Self {
+        KeyValue { key, value }
+    }
+}
+
+/// Records the values of the instruments.
+///
+/// Called while the calls are in progress, it must not block.
+pub trait Meter: Send + Sync + 'static {
+    /// Record `value` to the histogram `instrument`, with `attributes`.
+    fn record(&self, instrument: Instrument, value: f64, attributes: &[KeyValue<'_>]);
+}
+
+impl<M: Meter + ?Sized> Meter for Arc<M> {
+    fn record(&self, instrument: Instrument, value: f64, attributes: &[KeyValue<'_>]) {
+        (**self).record(instrument, value, attributes)
+    }
+}
+
+/// A [`MetricsRecorder`] recording the calls to the instruments of the
+/// semantic conventions.
+///
+/// [`MetricsRecorder`]: ../metrics/trait.MetricsRecorder.html
+#[derive(Debug, Clone)]
+pub struct OtelRecorder<M> {
+    meter: M,
+}
+
+impl<M: Meter> OtelRecorder<M> {
+    /// Record to `meter`.
+    pub fn new(meter: M) -> Self {
+        OtelRecorder { meter }
+    }
+
+    /// The meter recorded to.
+    pub fn meter(&self) -> &M {
+        &self.meter
+    }
+
+    fn message(&self, call: &CallInfo, size: usize, request: bool) {
+        let instrument = match (call.side(), request) {
+            (Side::Server, true) => Instrument::ServerRequestSize,
+            (Side::Server, false) => Instrument::ServerResponseSize,
+            (Side::Client, true) => Instrument::ClientRequestSize,
+            (Side::Client, false) => Instrument::ClientResponseSize,
+        };
+        self.meter
+            .record(instrument, size as f64, &attributes(call, None));
+    }
+}
+
+impl<M: Meter> MetricsRecorder for OtelRecorder<M> {
+    fn call_completed(&self, call: &CallInfo, code: Code, latency: Duration) {
+        let instrument = match call.side() {
+            Side::Server => Instrument::ServerDuration,
+            Side::Client => Instrument::ClientDuration,
+        };
+        let millis = latency.as_secs_f64() * 1000.0;
+        self.meter
+            .record(instrument, millis, &attributes(call, Some(code)));
+    }
+
+    fn message_sent(&self, call: &CallInfo, size: usize) {
+        self.message(call, size, call.side() == Side::Client);
+    }
+
+    fn message_received(&self, call: &CallInfo, size: usize) {
+        self.message(call, size, call.side() == Side::Server);
+    }
+}
+
+fn attributes(call: &CallInfo, code: Option<Code>) -> Vec<KeyValue<'_>> {
+    let mut attributes = vec![
+        KeyValue::new("rpc.system", Value::String("grpc")),
+        KeyValue::new("rpc.service", Value::String(call.service())),
+        KeyValue::new("rpc.method", Value::String(call.method())),
+    ];
+    if let Some(code) = code {
+        attributes.push(KeyValue::new(
+            "rpc.grpc.status_code",
+            Value::I64(code as i64),
+        ));
+    }
+    attributes
+}
@@ -1,4 +1,11 @@
+use crate::codec::{CompressionEncoding, CompressionOverride};
+use crate::context::RequestContext;
+use crate::credentials::{CallCredentials, SharedCredentials};
+use crate::deadline::{self, Deadline};
+use crate::descriptor::MethodDescriptor;
 use crate::metadata::MetadataMap;
+#[cfg(all(unix, feature = "transport"))]
+use crate::transport::server::PeerCred;
 #[cfg(feature = "transport")]
 use crate::transport::Certificate;
 use futures_core::Stream;
@@ -6,6 +13,7 @@ use http::Extensions;
 use std::net::SocketAddr;
 #[cfg(feature = "transport")]
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// A gRPC request and metadata from an RPC call.
 #[derive(Debug)]
@@ -20,6 +28,9 @@ pub(crate) struct ConnectionInfo {
     pub(crate) remote_addr: Option<SocketAddr>,
     #[cfg(feature = "transport")]
     pub(crate) peer_certs: Option<Arc<Vec<Certificate>>>,
+    #[cfg(all(unix, feature = "transport"))]
+    pub(crate) peer_cred: Option<PeerCred>,
+    pub(crate) tls: bool,
 }
 
 /// Trait implemented by RPC request types.
@@ -145,6 +156,45 @@ impl<T> Request<T> {
         self.message
     }
 
+    /// Get a reference to the request extensions.
+    ///
+    /// On the server side these contain the extensions of the inbound HTTP
+    /// request, including any values inserted by `tower` middleware that ran
+    /// before the gRPC handler.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Get a mutable reference to the request extensions.
+    ///
+    /// Values inserted here by an [`Interceptor`] are carried through to the
+    /// service handler.
+    ///
+    /// [`Interceptor`]: struct.Interceptor.html
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Get the [`RequestContext`] shared with the response path, if any.
+    ///
+    /// Requests received by servers always carry one.
+    ///
+    /// [`RequestContext`]: struct.RequestContext.html
+    pub fn context(&self) -> Option<&RequestContext> {
+        self.extensions.get::<RequestContext>()
+    }
+
+    /// Get the descriptor of the method called, if any.
+    ///
+    /// Generated clients set it on the requests they send, so interceptors
+    /// can tell the methods apart without parsing paths. Layers find it in
+    /// the extensions of the HTTP requests, as a `&'static` [`MethodDescriptor`].
+    ///
+    /// [`MethodDescriptor`]: descriptor/struct.MethodDescriptor.html
+    pub fn method_descriptor(&self) -> Option<&'static MethodDescriptor> {
+        self.extensions.get::<&'static MethodDescriptor>().copied()
+    }
+
     pub(crate) fn into_parts(self) -> (MetadataMap, Extensions, T) {
         (self.metadata, self.extensions, self.message)
     }
@@ -158,10 +208,19 @@ impl<T> Request<T> {
     }
 
     pub(crate) fn from_http_parts(parts: http::request::Parts, message: T) -> Self {
+        let mut extensions = parts.extensions;
+        if let Some(timeout) = parts
+            .headers
+            .get(deadline::GRPC_TIMEOUT_HEADER)
+            .and_then(deadline::parse_timeout)
+        {
+            extensions.insert(Deadline(Instant::now() + timeout));
+        }
+
         Request {
             metadata: MetadataMap::from_headers(parts.headers),
             message,
-            extensions: parts.extensions,
+            extensions,
         }
     }
 
@@ -193,7 +252,7 @@ impl<T> Request<T> {
         Request {
             metadata: self.metadata,
             message,
-            extensions: Extensions::default(),
+            extensions: self.extensions,
         }
     }
 
@@ -218,6 +277,98 @@ impl<T> Request<T> {
         self.get::<ConnectionInfo>()?.peer_certs.clone()
     }
 
+    /// Get the OS credentials of the peer process.
+    ///
+    /// This is only `Some` on the server side of the `transport` server
+    /// when serving over a Unix domain socket, for example by passing a
+    /// `tokio::net::UnixListener`'s incoming stream to
+    /// `Router::serve_with_incoming`. Local services can use it to authorize
+    /// callers by uid, gid or pid.
+    #[cfg(all(unix, feature = "transport"))]
+    #[cfg_attr(docsrs, doc(cfg(all(unix, feature = "transport"))))]
+    pub fn peer_cred(&self) -> Option<PeerCred> {
+        self.get::<ConnectionInfo>()?.peer_cred
+    }
+
+    /// Returns `true` if the connection this request was received on was
+    /// secured with TLS.
+    ///
+    /// Like [`Request::remote_addr`], this is only populated on the server
+    /// side; client requests and custom `IO` types that were not accepted by
+    /// the `transport` server always return `false`.
+    pub fn is_tls(&self) -> bool {
+        self.get::<ConnectionInfo>()
+            .map(|info| info.tls)
+            .unwrap_or(false)
+    }
+
+    /// Set the time the call is given to complete.
+    ///
+    /// Clients send it as the `grpc-timeout` of the call. When made while
+    /// handling another call, the shorter of this timeout and the time left
+    /// until the [current deadline] is sent.
+    ///
+    /// ```rust
+    /// # use tonic::Request;
+    /// # use std::time::Duration;
+    /// let mut request = Request::new(());
+    /// request.set_timeout(Duration::from_secs(2));
+    /// assert!(request.time_remaining().unwrap() <= Duration::from_secs(2));
+    /// ```
+    ///
+    /// [current deadline]: deadline/fn.current.html
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.extensions.insert(Deadline(Instant::now() + timeout));
+    }
+
+    /// Get the instant the call must complete by.
+    ///
+    /// On servers, this is set from the `grpc-timeout` header sent by the
+    /// client. On clients, from [`Request::set_timeout`].
+    pub fn deadline(&self) -> Option<Instant> {
+        self.get::<Deadline>().map(|deadline| deadline.0)
+    }
+
+    /// Get the time left until the [deadline](#method.deadline) of the call,
+    /// which is zero once it passed.
+    pub fn time_remaining(&self) -> Option<Duration> {
+        self.deadline()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Override the compression of this request's messages.
+    ///
+    /// `Some(encoding)` compresses the messages with `encoding`, which the
+    /// server must accept, even if the client was not configured to compress
+    /// requests. `None` sends them uncompressed. Only used by clients.
+    ///
+    /// ```rust
+    /// # use tonic::Request;
+    /// let mut request = Request::new(vec![0u8; 16]);
+    /// // Not worth compressing.
+    /// request.set_compression(None);
+    /// ```
+    pub fn set_compression(&mut self, encoding: impl Into<Option<CompressionEncoding>>) {
+        self.extensions
+            .insert(CompressionOverride::from(encoding.into()));
+    }
+
+    /// Authenticate the call with `credentials`, on top of the credentials
+    /// of the client, if any.
+    ///
+    /// Only used by clients.
+    pub fn set_call_credentials(&mut self, credentials: impl CallCredentials) {
+        self.extensions.insert(SharedCredentials::new(credentials));
+    }
+
+    pub(crate) fn take_call_credentials(&mut self) -> Option<SharedCredentials> {
+        self.extensions.remove::<SharedCredentials>()
+    }
+
+    pub(crate) fn take_compression_override(&mut self) -> Option<CompressionOverride> {
+        self.extensions.remove::<CompressionOverride>()
+    }
+
     pub(crate) fn get<I: Send + Sync + 'static>(&self) -> Option<&I> {
         self.extensions.get::<I>()
     }
@@ -283,4 +434,37 @@ mod tests {
         let http_request = r.into_http(Uri::default());
         assert!(http_request.headers().is_empty());
     }
+
+    #[test]
+    fn extensions_survive_map() {
+        let mut r = Request::new(1);
+        r.extensions_mut().insert("tenant-a");
+
+        let r = r.map(|m| m + 1);
+        assert_eq!(r.extensions().get::<&'static str>(), Some(&"tenant-a"));
+    }
+
+    #[test]
+    fn connection_info_visible_to_interceptor() {
+        let addr: SocketAddr = "127.0.0.1:50051".parse().unwrap();
+
+        let mut r = Request::new(());
+        r.extensions_mut().insert(ConnectionInfo {
+            remote_addr: Some(addr),
+            #[cfg(feature = "transport")]
+            peer_certs: None,
+            #[cfg(all(unix, feature = "transport"))]
+            peer_cred: None,
+            tls: true,
+        });
+
+        let interceptor = crate::Interceptor::new(move |req: Request<()>| {
+            assert_eq!(req.remote_addr(), Some(addr));
+            assert!(req.is_tls());
+            Ok(req)
+        });
+
+        let r = interceptor.call(r).unwrap();
+        assert_eq!(r.remote_addr(), Some(addr));
+    }
 }
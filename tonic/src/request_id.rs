@@ -0,0 +1,337 @@
+//! Request IDs, correlating the calls handled across services.
+//!
+//! A [`RequestIdLayer`] wrapping the services of a server gives each call a
+//! [`RequestId`], the one of its `x-request-id` metadata or else a new one.
+//! The ID is stored in the extensions of the request, where it is the
+//! current ID while the handler runs, and echoed in the `x-request-id` of the
+//! response. The [`propagate`] interceptor of a client sends the current
+//! ID, or the one of the request extensions, so the calls made by handlers
+//! carry the ID of the call they serve:
+//!
+//! ```rust,ignore
+//! let server = RequestIdLayer::new().layer(GreeterServer::new(greeter));
+//! let client = BackendClient::with_interceptor(channel, tonic::request_id::propagate());
+//! ```
+//!
+//! Handlers read the ID of their call from its extensions:
+//!
+//! ```rust
+//! use tonic::{request_id::RequestId, Request};
+//!
+//! fn log(request: &Request<()>) {
+//!     if let Some(id) = request.extensions().get::<RequestId>() {
+//!         println!("handling {}", id);
+//!     }
+//! }
+//! ```
+//!
+//! Like the current trace context, the current ID is tied to the future of
+//! the handler, so the work moved to spawned tasks should be wrapped with
+//! [`scope`].
+//!
+//! [`RequestIdLayer`]: struct.RequestIdLayer.html
+//! [`RequestId`]: struct.RequestId.html
+//! [`propagate`]: fn.propagate.html
+//! [`scope`]: fn.scope.html
+
+use crate::{metadata::MetadataMap, Interceptor, Request};
+use futures_util::ready;
+use http::{HeaderMap, HeaderValue};
+use pin_project::pin_project;
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The longest request ID accepted from clients.
+const MAX_LEN: usize = 128;
+
+thread_local! {
+    static CURRENT: RefCell<Option<RequestId>> = RefCell::default();
+}
+
+/// The ID of a request, correlating it with the calls made to serve it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// A new random ID, of 32 hex digits.
+    pub fn new_random() -> Self {
+        RequestId(format!("{:032x}", rand::random::<u128>()))
+    }
+
+    /// Parse an ID of visible ASCII characters, `None` if it is empty, longer
+    /// than 128 characters or has other characters.
+    pub fn parse(id: &str) -> Option<Self> {
+        let valid =
+            !id.is_empty() && id.len() <= MAX_LEN && id.bytes().all(|b| b.is_ascii_graphic());
+        if valid {
+            Some(RequestId(id.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// Read the ID of a call from its `x-request-id` metadata, if any and
+    /// valid.
+    pub fn from_metadata(metadata: &MetadataMap) -> Option<Self> {
+        let id = metadata.get(REQUEST_ID_HEADER)?.to_str().ok()?;
+        RequestId::parse(id)
+    }
+
+    /// Read the ID of a call from its `x-request-id` header, if any and
+    /// valid.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let id = headers.get(REQUEST_ID_HEADER)?.to_str().ok()?;
+        RequestId::parse(id)
+    }
+
+    /// The ID.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Write the ID to the `x-request-id` metadata of a call.
+    pub fn inject(&self, metadata: &mut MetadataMap) {
+        metadata.insert(
+            REQUEST_ID_HEADER,
+            self.0.parse().expect("request ids are ascii"),
+        );
+    }
+
+    fn header_value(&self) -> HeaderValue {
+        HeaderValue::from_str(&self.0).expect("request ids are ascii")
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Get the request ID of the call being handled, if any.
+pub fn current() -> Option<RequestId> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Run `future` with `id` as the current request ID.
+pub fn scope<F>(id: Option<RequestId>, future: F) -> Scope<F>
+where
+    F: Future,
+{
+    Scope { id, inner: future }
+}
+
+/// A future running with a current request ID, created by [`scope`].
+///
+/// [`scope`]: fn.scope.html
+#[pin_project]
+#[derive(Debug)]
+pub struct Scope<F> {
+    id: Option<RequestId>,
+    #[pin]
+    inner: F,
+}
+
+impl<F: Future> Future for Scope<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _current = SetCurrent::new(this.id.clone());
+        this.inner.poll(cx)
+    }
+}
+
+/// Restores the previous current ID on drop, even when unwinding.
+struct SetCurrent(Option<RequestId>);
+
+impl SetCurrent {
+    fn new(id: Option<RequestId>) -> Self {
+        SetCurrent(CURRENT.with(|current| current.replace(id)))
+    }
+}
+
+impl Drop for SetCurrent {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// An interceptor sending the request ID of the calls of a client, the one
+/// in their extensions or else the current one, unless their metadata
+/// already has one. The calls without an ID are sent as they are.
+pub fn propagate() -> Interceptor {
+    Interceptor::new(|mut request: Request<()>| {
+        if request.metadata().contains_key(REQUEST_ID_HEADER) {
+            return Ok(request);
+        }
+        let id = request.extensions().get::<RequestId>().cloned();
+        if let Some(id) = id.or_else(current) {
+            id.inject(request.metadata_mut());
+        }
+        Ok(request)
+    })
+}
+
+/// Wraps services with [`RequestIds`], giving an ID to each of their calls.
+///
+/// [`RequestIds`]: struct.RequestIds.html
+#[derive(Debug, Clone)]
+pub struct RequestIdLayer {
+    trust_incoming: bool,
+}
+
+impl RequestIdLayer {
+    /// Keep the IDs sent by clients, giving a new one to the other calls.
+    pub fn new() -> Self {
+        RequestIdLayer {
+            trust_incoming: true,
+        }
+    }
+
+    /// Whether to keep the IDs sent by clients, `true` by default. The
+    /// servers facing untrusted clients give a new ID to every call
+    /// instead.
+    pub fn trust_incoming(self, trust_incoming: bool) -> Self {
+        RequestIdLayer { trust_incoming }
+    }
+}
+
+impl Default for RequestIdLayer {
+    fn default() -> Self {
+        RequestIdLayer::new()
+    }
+}
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIds<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIds {
+            inner,
+            trust_incoming: self.trust_incoming,
+        }
+    }
+}
+
+/// A service giving an ID to each of its calls, created by a
+/// [`RequestIdLayer`].
+///
+/// The ID is set as the `x-request-id` of the request, stored in its
+/// extensions, current while its handler runs, and echoed in the
+/// `x-request-id` of the response.
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`RequestIdLayer`]: struct.RequestIdLayer.html
+/// [`Server`]: ../transport/struct.Server.html
+#[derive(Debug, Clone)]
+pub struct RequestIds<S> {
+    inner: S,
+    trust_incoming: bool,
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for RequestIds<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RequestIdFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<ReqBody>) -> Self::Future {
+        let incoming = if self.trust_incoming {
+            RequestId::from_headers(request.headers())
+        } else {
+            None
+        };
+        let id = incoming.unwrap_or_else(RequestId::new_random);
+        request
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER, id.header_value());
+        request.extensions_mut().insert(id.clone());
+        RequestIdFuture {
+            inner: scope(Some(id.clone()), self.inner.call(request)),
+            id,
+        }
+    }
+}
+
+#[cfg(feature = "transport")]
+impl<S: crate::transport::NamedService> crate::transport::NamedService for RequestIds<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// The response future of [`RequestIds`].
+///
+/// [`RequestIds`]: struct.RequestIds.html
+#[pin_project]
+#[derive(Debug)]
+pub struct RequestIdFuture<F> {
+    #[pin]
+    inner: Scope<F>,
+    id: RequestId,
+}
+
+impl<F, B, E> Future for RequestIdFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = ready!(this.inner.poll(cx))?;
+        response
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER, this.id.header_value());
+        Poll::Ready(Ok(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_request_ids() {
+        assert_eq!(RequestId::parse("abc-123").unwrap().as_str(), "abc-123");
+        assert_eq!(RequestId::parse(""), None);
+        assert_eq!(RequestId::parse("a b"), None);
+        assert_eq!(RequestId::parse("é"), None);
+        assert_eq!(RequestId::parse(&"a".repeat(MAX_LEN + 1)), None);
+
+        let id = RequestId::new_random();
+        assert_eq!(id.as_str().len(), 32);
+        assert_eq!(RequestId::parse(id.as_str()), Some(id));
+    }
+
+    #[test]
+    fn propagates_the_current_request_id() {
+        let id = RequestId::parse("abc").unwrap();
+        let request =
+            futures_util::future::FutureExt::now_or_never(scope(Some(id.clone()), async {
+                propagate().call(Request::new(())).unwrap()
+            }))
+            .unwrap();
+        assert_eq!(current(), None);
+        assert_eq!(RequestId::from_metadata(request.metadata()), Some(id));
+
+        let request = propagate().call(Request::new(())).unwrap();
+        assert!(request.metadata().is_empty());
+    }
+}
@@ -1,10 +1,15 @@
+use crate::codec::{CompressionEncoding, CompressionOverride};
 use crate::metadata::MetadataMap;
+use http::Extensions;
 
 /// A gRPC response and metadata from an RPC call.
 #[derive(Debug)]
 pub struct Response<T> {
     metadata: MetadataMap,
     message: T,
+    trailers: MetadataMap,
+    extensions: Extensions,
+    compression: Option<CompressionOverride>,
 }
 
 impl<T> Response<T> {
@@ -24,6 +29,9 @@ impl<T> Response<T> {
         Response {
             metadata: MetadataMap::new(),
             message,
+            trailers: MetadataMap::new(),
+            extensions: Extensions::new(),
+            compression: None,
         }
     }
 
@@ -47,17 +55,65 @@ impl<T> Response<T> {
         &mut self.metadata
     }
 
+    /// Get a reference to the trailing metadata of the response.
+    ///
+    /// Only populated on clients, with the trailers received after the
+    /// message of unary and client streaming calls. They are also merged
+    /// into [`metadata`](#method.metadata).
+    pub fn trailers(&self) -> &MetadataMap {
+        &self.trailers
+    }
+
+    /// Get a reference to the response extensions.
+    ///
+    /// On the client side these contain the extensions of the HTTP response.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Get a mutable reference to the response extensions.
+    ///
+    /// On the server side the values inserted here are moved to the
+    /// extensions of the HTTP response, for the `tower` middleware wrapping
+    /// the service.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
     /// Consumes `self`, returning the message
     pub fn into_inner(self) -> T {
         self.message
     }
 
-    pub(crate) fn into_parts(self) -> (MetadataMap, T) {
-        (self.metadata, self.message)
+    /// Override the compression of this response's messages.
+    ///
+    /// `Some(encoding)` compresses the messages with `encoding` if the client
+    /// accepts it, even if the server was not configured to compress
+    /// responses. `None` sends them uncompressed. Only used by servers.
+    pub fn set_compression(&mut self, encoding: impl Into<Option<CompressionEncoding>>) {
+        self.compression = Some(encoding.into().into());
+    }
+
+    pub(crate) fn compression_override(&self) -> Option<CompressionOverride> {
+        self.compression
+    }
+
+    pub(crate) fn into_parts(self) -> (MetadataMap, Extensions, T) {
+        (self.metadata, self.extensions, self.message)
+    }
+
+    pub(crate) fn from_parts(metadata: MetadataMap, extensions: Extensions, message: T) -> Self {
+        Self {
+            metadata,
+            message,
+            trailers: MetadataMap::new(),
+            extensions,
+            compression: None,
+        }
     }
 
-    pub(crate) fn from_parts(metadata: MetadataMap, message: T) -> Self {
-        Self { metadata, message }
+    pub(crate) fn with_trailers(self, trailers: MetadataMap) -> Self {
+        Self { trailers, ..self }
     }
 
     pub(crate) fn from_http(res: http::Response<T>) -> Self {
@@ -65,6 +121,9 @@ impl<T> Response<T> {
         Response {
             metadata: MetadataMap::from_headers(head.headers),
             message,
+            trailers: MetadataMap::new(),
+            extensions: head.extensions,
+            compression: None,
         }
     }
 
@@ -73,6 +132,7 @@ impl<T> Response<T> {
 
         *res.version_mut() = http::Version::HTTP_2;
         *res.headers_mut() = self.metadata.into_sanitized_headers();
+        *res.extensions_mut() = self.extensions;
 
         res
     }
@@ -86,6 +146,9 @@ impl<T> Response<T> {
         Response {
             metadata: self.metadata,
             message,
+            trailers: self.trailers,
+            extensions: self.extensions,
+            compression: self.compression,
         }
     }
 }
@@ -0,0 +1,231 @@
+use crate::codec::{DecodeBuf, Decoder, EncodeBuf, Encoder};
+use std::{
+    any::Any,
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Observes the messages of calls, once decoded and before being encoded.
+///
+/// The messages are passed as [`Any`], to downcast to the message types of
+/// the audited methods. They may be modified, like to redact the fields
+/// handlers should not see or clients should not receive, but audit logs
+/// will usually redact a copy instead.
+///
+/// ```rust
+/// use std::any::Any;
+/// use tonic::server::MessageAudit;
+///
+/// # struct CreateUserRequest { email: String }
+/// struct AuditLog;
+///
+/// impl MessageAudit for AuditLog {
+///     fn request(&self, method: &str, message: &mut dyn Any) {
+///         if let Some(request) = message.downcast_ref::<CreateUserRequest>() {
+///             println!("{}: creating a user at {}", method, request.email);
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`Any`]: https://doc.rust-lang.org/std/any/trait.Any.html
+pub trait MessageAudit: Send + Sync + 'static {
+    /// Observe a request message of a call of the method at `method`, like
+    /// `/helloworld.Greeter/SayHello`, before its handler receives it.
+    fn request(&self, method: &str, message: &mut dyn Any) {
+        let _ = (method, message);
+    }
+
+    /// Observe a response message of a call of the method at `method`,
+    /// before it is sent.
+    fn response(&self, method: &str, message: &mut dyn Any) {
+        let _ = (method, message);
+    }
+}
+
+impl<A: MessageAudit + ?Sized> MessageAudit for Arc<A> {
+    fn request(&self, method: &str, message: &mut dyn Any) {
+        (**self).request(method, message)
+    }
+
+    fn response(&self, method: &str, message: &mut dyn Any) {
+        (**self).response(method, message)
+    }
+}
+
+/// Wraps services with [`Audit`], passing the messages of their calls to a
+/// [`MessageAudit`].
+///
+/// [`Audit`]: struct.Audit.html
+/// [`MessageAudit`]: trait.MessageAudit.html
+#[derive(Clone)]
+pub struct AuditLayer {
+    audit: Arc<dyn MessageAudit>,
+    methods: Vec<String>,
+}
+
+impl AuditLayer {
+    /// Pass the messages of the calls to `audit`.
+    pub fn new(audit: impl MessageAudit) -> Self {
+        AuditLayer {
+            audit: Arc::new(audit),
+            methods: Vec::new(),
+        }
+    }
+
+    /// Only pass the messages of the method at `path`, like
+    /// `/helloworld.Greeter/SayHello`, and of the other methods added, by
+    /// default the messages of all the methods.
+    pub fn method(mut self, path: impl Into<String>) -> Self {
+        self.methods.push(path.into());
+        self
+    }
+}
+
+impl<S> Layer<S> for AuditLayer {
+    type Service = Audit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Audit {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for AuditLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditLayer")
+            .field("methods", &self.methods)
+            .finish()
+    }
+}
+
+/// A service passing the decoded messages of its calls to a
+/// [`MessageAudit`], created by an [`AuditLayer`].
+///
+/// The messages are passed by the generated server it wraps, which finds
+/// the audit in the extensions of the requests.
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`MessageAudit`]: trait.MessageAudit.html
+/// [`AuditLayer`]: struct.AuditLayer.html
+/// [`Server`]: ../transport/struct.Server.html
+#[derive(Debug, Clone)]
+pub struct Audit<S> {
+    inner: S,
+    layer: AuditLayer,
+}
+
+impl<S, B> Service<http::Request<B>> for Audit<S>
+where
+    S: Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        let path = request.uri().path();
+        let methods = &self.layer.methods;
+        if methods.is_empty() || methods.iter().any(|method| method == path) {
+            let auditor = Auditor {
+                audit: self.layer.audit.clone(),
+                method: Arc::from(path),
+            };
+            request.extensions_mut().insert(auditor);
+        }
+        self.inner.call(request)
+    }
+}
+
+#[cfg(feature = "transport")]
+impl<S: crate::transport::NamedService> crate::transport::NamedService for Audit<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// The audit of a call, in the extensions of its request.
+#[derive(Clone)]
+pub(crate) struct Auditor {
+    audit: Arc<dyn MessageAudit>,
+    method: Arc<str>,
+}
+
+impl Auditor {
+    pub(crate) fn from_request<B>(request: &http::Request<B>) -> Option<Self> {
+        request.extensions().get::<Auditor>().cloned()
+    }
+}
+
+/// Passes the messages it decodes to the audit of their call, if any.
+pub(crate) struct AuditDecoder<D> {
+    inner: D,
+    auditor: Option<Auditor>,
+}
+
+impl<D> AuditDecoder<D> {
+    pub(crate) fn new(inner: D, auditor: Option<Auditor>) -> Self {
+        AuditDecoder { inner, auditor }
+    }
+}
+
+impl<D> Decoder for AuditDecoder<D>
+where
+    D: Decoder,
+    D::Item: 'static,
+{
+    type Item = D::Item;
+    type Error = D::Error;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let mut message = self.inner.decode(src)?;
+        if let (Some(auditor), Some(message)) = (&self.auditor, &mut message) {
+            auditor.audit.request(&auditor.method, message);
+        }
+        Ok(message)
+    }
+}
+
+/// Passes the messages to encode to the audit of their call, if any.
+pub(crate) struct AuditEncoder<E> {
+    inner: E,
+    auditor: Option<Auditor>,
+}
+
+impl<E> AuditEncoder<E> {
+    pub(crate) fn new(inner: E, auditor: Option<Auditor>) -> Self {
+        AuditEncoder { inner, auditor }
+    }
+}
+
+impl<E> Encoder for AuditEncoder<E>
+where
+    E: Encoder,
+    E::Item: 'static,
+{
+    type Item = E::Item;
+    type Error = E::Error;
+
+    fn encode(&mut self, mut item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        if let Some(auditor) = &self.auditor {
+            auditor.audit.response(&auditor.method, &mut item);
+        }
+        self.inner.encode(item, dst)
+    }
+
+    fn encoded_len(&self, item: &Self::Item) -> Option<usize> {
+        // The audit may change the message.
+        match self.auditor {
+            Some(_) => None,
+            None => self.inner.encoded_len(item),
+        }
+    }
+}
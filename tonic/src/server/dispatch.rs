@@ -0,0 +1,110 @@
+use crate::{body::BoxBody, Code, Status};
+use bytes::{Bytes, BytesMut};
+use futures_util::future::poll_fn;
+use http::{header, Method, Request, Response, Uri};
+use http_body::Body as HttpBody;
+use std::{
+    convert::Infallible,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower_service::Service;
+
+/// Call the gRPC `service` with a request to `path`, like
+/// `/helloworld.Greeter/SayHello`, carrying the gRPC framed `body`: the
+/// length-prefixed request messages.
+///
+/// Returns the length-prefixed response messages and the status the call
+/// ended with, `Ok` on success, for HTTP servers other than the one of the
+/// `transport` module to send back as the body and the trailers of the
+/// response. The whole request is passed at once and the whole response
+/// collected, streaming calls included, without any metadata.
+///
+/// `B` is the request body the service takes, `hyper::Body` for the servers
+/// generated with the `transport` feature, [`FullBody`] for the others.
+pub async fn dispatch<S, B>(mut service: S, path: &str, body: Bytes) -> (Bytes, Status)
+where
+    S: Service<Request<B>, Response = Response<BoxBody>>,
+    S::Error: Into<crate::Error>,
+    B: From<Bytes>,
+{
+    let uri = match path.parse::<Uri>() {
+        Ok(uri) => uri,
+        Err(_) => {
+            let status = Status::new(Code::Unimplemented, format!("invalid path: {}", path));
+            return (Bytes::new(), status);
+        }
+    };
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/grpc")
+        .header(header::TE, "trailers")
+        .body(B::from(body))
+        .expect("the request is valid");
+
+    let response = match poll_fn(|cx| service.poll_ready(cx)).await {
+        Ok(()) => service.call(request).await,
+        Err(error) => Err(error),
+    };
+    let (parts, mut body) = match response {
+        Ok(response) => response.into_parts(),
+        Err(error) => return (Bytes::new(), Status::from_error(error.into())),
+    };
+
+    // A trailers-only response.
+    if let Some(status) = Status::from_header_map(&parts.headers) {
+        return (Bytes::new(), status);
+    }
+
+    let mut messages = BytesMut::new();
+    while let Some(data) = body.data().await {
+        match data {
+            Ok(data) => messages.extend_from_slice(&data),
+            Err(status) => return (messages.freeze(), status),
+        }
+    }
+    let status = match body.trailers().await {
+        Ok(trailers) => trailers
+            .as_ref()
+            .and_then(Status::from_header_map)
+            .unwrap_or_else(|| Status::new(Code::Internal, "missing grpc-status")),
+        Err(status) => status,
+    };
+
+    (messages.freeze(), status)
+}
+
+/// A request body holding the whole body at once, passed by [`dispatch`] to
+/// the servers generated without the `transport` feature.
+#[derive(Debug, Default)]
+pub struct FullBody(Option<Bytes>);
+
+impl From<Bytes> for FullBody {
+    fn from(body: Bytes) -> Self {
+        FullBody(Some(body))
+    }
+}
+
+impl HttpBody for FullBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn is_end_stream(&self) -> bool {
+        self.0.is_none()
+    }
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Poll::Ready(self.get_mut().0.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+}
@@ -1,14 +1,29 @@
 use crate::{
     body::BoxBody,
-    codec::{encode_server, Codec, Streaming},
+    codec::{
+        encode_server, BufferPool, Codec, CompressionEncoding, CompressionLevel,
+        CompressionOverride, CompressionSettings, DecodeSettings, EnabledCompressionEncodings,
+        Streaming, ACCEPT_ENCODING_HEADER, DEFAULT_MAX_DECODING_MESSAGE_SIZE, ENCODING_HEADER,
+    },
+    context::RequestContext,
+    deadline::{self, Enforced},
     interceptor::Interceptor,
-    server::{ClientStreamingService, ServerStreamingService, StreamingService, UnaryService},
+    metadata,
+    server::{
+        audit::{AuditDecoder, AuditEncoder, Auditor},
+        transform::{TransformDecoder, Transformer},
+        ClientStreamingService, ServerStreamingService, StreamingService, UnaryService,
+    },
+    spans::{self, Direction, TracedBody},
+    trace_context::{self, TraceContext},
     Code, Request, Response, Status,
 };
 use futures_core::TryStream;
 use futures_util::{future, stream, TryStreamExt};
+use http::HeaderValue;
 use http_body::Body;
-use std::fmt;
+use std::{fmt, time::Instant};
+use tracing::Instrument;
 
 // A try! type macro for intercepting requests
 macro_rules! t {
@@ -32,6 +47,16 @@ macro_rules! t {
 pub struct Grpc<T> {
     codec: T,
     interceptor: Option<Interceptor>,
+    accept_compression_encodings: EnabledCompressionEncodings,
+    send_compression_encodings: EnabledCompressionEncodings,
+    compression_settings: CompressionSettings,
+    buffer_pool: Option<BufferPool>,
+    yield_after_messages: Option<usize>,
+    message_checksums: bool,
+    enforce_deadlines: bool,
+    max_metadata_size: Option<usize>,
+    max_decoding_message_size: Option<usize>,
+    max_encoding_message_size: Option<usize>,
 }
 
 impl<T> Grpc<T>
@@ -44,6 +69,16 @@ where
         Self {
             codec,
             interceptor: None,
+            accept_compression_encodings: EnabledCompressionEncodings::default(),
+            send_compression_encodings: EnabledCompressionEncodings::default(),
+            compression_settings: CompressionSettings::default(),
+            buffer_pool: None,
+            yield_after_messages: None,
+            message_checksums: false,
+            enforce_deadlines: true,
+            max_metadata_size: None,
+            max_decoding_message_size: Some(DEFAULT_MAX_DECODING_MESSAGE_SIZE),
+            max_encoding_message_size: None,
         }
     }
 
@@ -51,13 +86,188 @@ where
     /// interceptor on each inbound request.
     pub fn with_interceptor(codec: T, interceptor: impl Into<Interceptor>) -> Self {
         Self {
-            codec,
             interceptor: Some(interceptor.into()),
+            ..Self::new(codec)
+        }
+    }
+
+    /// Accept requests compressed with the provided encoding.
+    ///
+    /// Requests compressed with an encoding that was not accepted are
+    /// rejected with an `Unimplemented` status.
+    pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.accept_compression_encodings.enable(encoding);
+        self
+    }
+
+    /// Compress responses with the provided encoding, if the client accepts
+    /// it.
+    pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.send_compression_encodings.enable(encoding);
+        self
+    }
+
+    /// Set the level responses are compressed at.
+    pub fn compression_level(mut self, level: CompressionLevel) -> Self {
+        self.compression_settings.level = level;
+        self
+    }
+
+    /// Send response messages smaller than `threshold` bytes uncompressed,
+    /// since compressing them costs CPU and can grow them.
+    pub fn compression_threshold(mut self, threshold: usize) -> Self {
+        self.compression_settings.threshold = threshold;
+        self
+    }
+
+    /// Limit the size of the request messages this server decodes to `limit`
+    /// bytes.
+    ///
+    /// Larger messages fail the call with a `ResourceExhausted` status.
+    /// Defaults to 4MiB.
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.max_decoding_message_size = Some(limit);
+        self
+    }
+
+    /// Limit the size of the response messages this server encodes to
+    /// `limit` bytes.
+    ///
+    /// Larger messages fail the call with an `OutOfRange` status. Defaults
+    /// to no limit.
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.max_encoding_message_size = Some(limit);
+        self
+    }
+
+    #[doc(hidden)]
+    pub fn apply_compression_config(
+        self,
+        accept_encodings: EnabledCompressionEncodings,
+        send_encodings: EnabledCompressionEncodings,
+    ) -> Self {
+        Self {
+            accept_compression_encodings: accept_encodings,
+            send_compression_encodings: send_encodings,
+            ..self
+        }
+    }
+
+    /// Take the buffers messages are encoded into and decoded from from
+    /// `pool`, if any, instead of allocating them for every call.
+    #[doc(hidden)]
+    pub fn apply_buffer_pool(self, pool: Option<BufferPool>) -> Self {
+        Self {
+            buffer_pool: pool,
+            ..self
+        }
+    }
+
+    /// Yield to the runtime after handing `limit` already buffered request
+    /// messages in a row to the service, if any, so one busy stream does not
+    /// starve the other tasks.
+    #[doc(hidden)]
+    pub fn apply_yield_after_messages(self, limit: Option<usize>) -> Self {
+        Self {
+            yield_after_messages: limit,
+            ..self
+        }
+    }
+
+    /// Send a CRC32C checksum of the response messages in the response
+    /// trailers, and check the one the client sends in the request trailers,
+    /// if any.
+    #[doc(hidden)]
+    pub fn apply_message_checksums(self, enable: bool) -> Self {
+        Self {
+            message_checksums: enable,
+            ..self
+        }
+    }
+
+    /// Fail calls with `DeadlineExceeded` once the deadline set by the
+    /// `grpc-timeout` of their request passed, cancelling their handler and
+    /// ending their response stream.
+    ///
+    /// Enabled by default. Requires the `transport` feature.
+    #[doc(hidden)]
+    pub fn apply_deadline_enforcement(self, enable: bool) -> Self {
+        Self {
+            enforce_deadlines: enable,
+            ..self
+        }
+    }
+
+    /// Limit the size of the request metadata, headers and trailers alike,
+    /// to `limit` bytes, if any.
+    ///
+    /// The size of an entry is counted as the length of its key and value
+    /// plus 32 bytes, like HTTP/2 does. Larger metadata fails the call with a
+    /// `ResourceExhausted` status.
+    #[doc(hidden)]
+    pub fn apply_max_metadata_size(self, limit: Option<usize>) -> Self {
+        Self {
+            max_metadata_size: limit,
+            ..self
+        }
+    }
+
+    /// Limit the size of the messages decoded and encoded to the limits set,
+    /// keeping the defaults otherwise.
+    #[doc(hidden)]
+    pub fn apply_max_message_size_config(
+        self,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    ) -> Self {
+        Self {
+            max_decoding_message_size: max_decoding_message_size.or(self.max_decoding_message_size),
+            max_encoding_message_size: max_encoding_message_size.or(self.max_encoding_message_size),
+            ..self
+        }
+    }
+
+    /// The deadline to enforce on the handling of `request`, if any.
+    fn enforced_deadline<M>(&self, request: &Request<M>) -> Option<Instant> {
+        if self.enforce_deadlines {
+            request.deadline()
+        } else {
+            None
+        }
+    }
+
+    fn decode_settings(&self) -> DecodeSettings {
+        DecodeSettings {
+            max_message_size: self.max_decoding_message_size,
+            yield_after_messages: self.yield_after_messages,
+            buffer_pool: self.buffer_pool.clone(),
+            checksum: self.message_checksums,
+            max_metadata_size: self.max_metadata_size,
         }
     }
 
     /// Handle a single unary gRPC request.
     pub async fn unary<S, B>(
+        &mut self,
+        service: S,
+        mut req: http::Request<B>,
+    ) -> http::Response<BoxBody>
+    where
+        S: UnaryService<T::Decode, Response = T::Encode>,
+        B: Body + Send + Sync + 'static,
+        B::Error: Into<crate::Error> + Send,
+    {
+        let context = RequestContext::get_or_insert(req.extensions_mut());
+        let span = spans::server(&req);
+        let req = req.map(|body| TracedBody::new(body, span.clone(), Direction::Received));
+        let response = self
+            .handle_unary(service, req)
+            .instrument(span.clone())
+            .await;
+        with_context(spans::server_response(response, span), context)
+    }
+
+    async fn handle_unary<S, B>(
         &mut self,
         mut service: S,
         req: http::Request<B>,
@@ -67,28 +277,67 @@ where
         B: Body + Send + Sync + 'static,
         B::Error: Into<crate::Error> + Send,
     {
-        let request = match self.map_request_unary(req).await {
+        let accept_encoding = req.headers().get(ACCEPT_ENCODING_HEADER).cloned();
+        let auditor = Auditor::from_request(&req);
+        t!(metadata::check_size(req.headers(), self.max_metadata_size)
+            .map_err(|status| self.map_status(status)));
+        let request_encoding = t!(self
+            .request_encoding(&req)
+            .map_err(|status| self.map_status(status)));
+
+        let request = match self
+            .map_request_unary(req, request_encoding, auditor.clone())
+            .await
+        {
             Ok(r) => r,
             Err(status) => {
                 return self
-                    .map_response::<stream::Once<future::Ready<Result<T::Encode, Status>>>>(Err(
-                        status,
-                    ));
+                    .map_response::<stream::Once<future::Ready<Result<T::Encode, Status>>>>(
+                        Err(status),
+                        accept_encoding,
+                        auditor,
+                    );
             }
         };
 
         let request = t!(self.intercept_request(request));
 
-        let response = service
-            .call(request)
-            .await
-            .map(|r| r.map(|m| stream::once(future::ok(m))));
+        let enforced = self.enforced_deadline(&request);
+        let deadline = request.deadline();
+        let trace = request.extensions().get::<TraceContext>().cloned();
+        let response = trace_context::scope(
+            trace,
+            deadline::scope(deadline, Enforced::new(service.call(request), enforced)),
+        )
+        .await
+        .map(|r| r.map(|m| stream::once(future::ok(m))));
 
-        self.map_response(response)
+        self.map_response(response, accept_encoding, auditor)
     }
 
     /// Handle a server side streaming request.
     pub async fn server_streaming<S, B>(
+        &mut self,
+        service: S,
+        mut req: http::Request<B>,
+    ) -> http::Response<BoxBody>
+    where
+        S: ServerStreamingService<T::Decode, Response = T::Encode>,
+        S::ResponseStream: Send + Sync + 'static,
+        B: Body + Send + Sync + 'static,
+        B::Error: Into<crate::Error> + Send,
+    {
+        let context = RequestContext::get_or_insert(req.extensions_mut());
+        let span = spans::server(&req);
+        let req = req.map(|body| TracedBody::new(body, span.clone(), Direction::Received));
+        let response = self
+            .handle_server_streaming(service, req)
+            .instrument(span.clone())
+            .await;
+        with_context(spans::server_response(response, span), context)
+    }
+
+    async fn handle_server_streaming<S, B>(
         &mut self,
         mut service: S,
         req: http::Request<B>,
@@ -99,22 +348,65 @@ where
         B: Body + Send + Sync + 'static,
         B::Error: Into<crate::Error> + Send,
     {
-        let request = match self.map_request_unary(req).await {
+        let accept_encoding = req.headers().get(ACCEPT_ENCODING_HEADER).cloned();
+        let auditor = Auditor::from_request(&req);
+        t!(metadata::check_size(req.headers(), self.max_metadata_size)
+            .map_err(|status| self.map_status(status)));
+        let request_encoding = t!(self
+            .request_encoding(&req)
+            .map_err(|status| self.map_status(status)));
+
+        let request = match self
+            .map_request_unary(req, request_encoding, auditor.clone())
+            .await
+        {
             Ok(r) => r,
             Err(status) => {
-                return self.map_response::<S::ResponseStream>(Err(status));
+                return self.map_response::<S::ResponseStream>(
+                    Err(status),
+                    accept_encoding,
+                    auditor,
+                );
             }
         };
 
         let request = t!(self.intercept_request(request));
 
-        let response = service.call(request).await;
+        let enforced = self.enforced_deadline(&request);
+        let deadline = request.deadline();
+        let trace = request.extensions().get::<TraceContext>().cloned();
+        let response = trace_context::scope(
+            trace,
+            deadline::scope(deadline, Enforced::new(service.call(request), enforced)),
+        )
+        .await
+        .map(|r| r.map(|s| Enforced::new(s, enforced)));
 
-        self.map_response(response)
+        self.map_response(response, accept_encoding, auditor)
     }
 
     /// Handle a client side streaming gRPC request.
     pub async fn client_streaming<S, B>(
+        &mut self,
+        service: S,
+        mut req: http::Request<B>,
+    ) -> http::Response<BoxBody>
+    where
+        S: ClientStreamingService<T::Decode, Response = T::Encode>,
+        B: Body + Send + Sync + 'static,
+        B::Error: Into<crate::Error> + Send + 'static,
+    {
+        let context = RequestContext::get_or_insert(req.extensions_mut());
+        let span = spans::server(&req);
+        let req = req.map(|body| TracedBody::new(body, span.clone(), Direction::Received));
+        let response = self
+            .handle_client_streaming(service, req)
+            .instrument(span.clone())
+            .await;
+        with_context(spans::server_response(response, span), context)
+    }
+
+    async fn handle_client_streaming<S, B>(
         &mut self,
         mut service: S,
         req: http::Request<B>,
@@ -124,17 +416,51 @@ where
         B: Body + Send + Sync + 'static,
         B::Error: Into<crate::Error> + Send + 'static,
     {
-        let request = self.map_request_streaming(req);
+        let accept_encoding = req.headers().get(ACCEPT_ENCODING_HEADER).cloned();
+        let auditor = Auditor::from_request(&req);
+        t!(metadata::check_size(req.headers(), self.max_metadata_size)
+            .map_err(|status| self.map_status(status)));
+        let request_encoding = t!(self
+            .request_encoding(&req)
+            .map_err(|status| self.map_status(status)));
+
+        let request = self.map_request_streaming(req, request_encoding, auditor.clone());
         let request = t!(self.intercept_request(request));
-        let response = service
-            .call(request)
-            .await
-            .map(|r| r.map(|m| stream::once(future::ok(m))));
-        self.map_response(response)
+        let enforced = self.enforced_deadline(&request);
+        let deadline = request.deadline();
+        let trace = request.extensions().get::<TraceContext>().cloned();
+        let response = trace_context::scope(
+            trace,
+            deadline::scope(deadline, Enforced::new(service.call(request), enforced)),
+        )
+        .await
+        .map(|r| r.map(|m| stream::once(future::ok(m))));
+        self.map_response(response, accept_encoding, auditor)
     }
 
     /// Handle a bi-directional streaming gRPC request.
     pub async fn streaming<S, B>(
+        &mut self,
+        service: S,
+        mut req: http::Request<B>,
+    ) -> http::Response<BoxBody>
+    where
+        S: StreamingService<T::Decode, Response = T::Encode> + Send,
+        S::ResponseStream: Send + Sync + 'static,
+        B: Body + Send + Sync + 'static,
+        B::Error: Into<crate::Error> + Send,
+    {
+        let context = RequestContext::get_or_insert(req.extensions_mut());
+        let span = spans::server(&req);
+        let req = req.map(|body| TracedBody::new(body, span.clone(), Direction::Received));
+        let response = self
+            .handle_streaming(service, req)
+            .instrument(span.clone())
+            .await;
+        with_context(spans::server_response(response, span), context)
+    }
+
+    async fn handle_streaming<S, B>(
         &mut self,
         mut service: S,
         req: http::Request<B>,
@@ -145,22 +471,82 @@ where
         B: Body + Send + Sync + 'static,
         B::Error: Into<crate::Error> + Send,
     {
-        let request = self.map_request_streaming(req);
+        let accept_encoding = req.headers().get(ACCEPT_ENCODING_HEADER).cloned();
+        let auditor = Auditor::from_request(&req);
+        t!(metadata::check_size(req.headers(), self.max_metadata_size)
+            .map_err(|status| self.map_status(status)));
+        let request_encoding = t!(self
+            .request_encoding(&req)
+            .map_err(|status| self.map_status(status)));
+
+        let request = self.map_request_streaming(req, request_encoding, auditor.clone());
         let request = t!(self.intercept_request(request));
-        let response = service.call(request).await;
-        self.map_response(response)
+        let enforced = self.enforced_deadline(&request);
+        let deadline = request.deadline();
+        let trace = request.extensions().get::<TraceContext>().cloned();
+        let response = trace_context::scope(
+            trace,
+            deadline::scope(deadline, Enforced::new(service.call(request), enforced)),
+        )
+        .await
+        .map(|r| r.map(|s| Enforced::new(s, enforced)));
+        self.map_response(response, accept_encoding, auditor)
+    }
+
+    /// The encoding to compress the response with, given the
+    /// `grpc-accept-encoding` header of the request and the override set on
+    /// the response, if any.
+    fn response_encoding(
+        &self,
+        accept_encoding: Option<&HeaderValue>,
+        compression: Option<CompressionOverride>,
+    ) -> Option<CompressionEncoding> {
+        let enabled = match compression {
+            Some(CompressionOverride::Disable) => return None,
+            Some(CompressionOverride::Encoding(encoding)) => {
+                let mut enabled = EnabledCompressionEncodings::default();
+                enabled.enable(encoding);
+                enabled
+            }
+            None => self.send_compression_encodings,
+        };
+
+        CompressionEncoding::from_accept_encoding_header(accept_encoding, enabled)
+    }
+
+    /// The encoding the request is compressed with, or an `Unimplemented`
+    /// status if that encoding is not accepted.
+    fn request_encoding<B>(
+        &self,
+        request: &http::Request<B>,
+    ) -> Result<Option<CompressionEncoding>, Status> {
+        CompressionEncoding::from_encoding_header(
+            request.headers(),
+            self.accept_compression_encodings,
+        )
     }
 
     async fn map_request_unary<B>(
         &mut self,
         request: http::Request<B>,
+        encoding: Option<CompressionEncoding>,
+        auditor: Option<Auditor>,
     ) -> Result<Request<T::Decode>, Status>
     where
         B: Body + Send + Sync + 'static,
         B::Error: Into<crate::Error> + Send,
     {
+        let transformer = Transformer::from_request(&request);
         let (parts, body) = request.into_parts();
-        let stream = Streaming::new_request(self.codec.decoder(), body);
+        let stream = Streaming::new_request_with(
+            AuditDecoder::new(
+                TransformDecoder::new(self.codec.decoder(), transformer),
+                auditor,
+            ),
+            body,
+            encoding,
+            self.decode_settings(),
+        );
 
         futures_util::pin_mut!(stream);
 
@@ -181,58 +567,98 @@ where
     fn map_request_streaming<B>(
         &mut self,
         request: http::Request<B>,
+        encoding: Option<CompressionEncoding>,
+        auditor: Option<Auditor>,
     ) -> Request<Streaming<T::Decode>>
     where
         B: Body + Send + Sync + 'static,
         B::Error: Into<crate::Error> + Send,
     {
-        Request::from_http(request.map(|body| Streaming::new_request(self.codec.decoder(), body)))
+        let transformer = Transformer::from_request(&request);
+        let decoder = AuditDecoder::new(
+            TransformDecoder::new(self.codec.decoder(), transformer),
+            auditor,
+        );
+        let settings = self.decode_settings();
+        Request::from_http(
+            request.map(|body| Streaming::new_request_with(decoder, body, encoding, settings)),
+        )
     }
 
     fn map_response<B>(
         &mut self,
         response: Result<crate::Response<B>, Status>,
+        accept_encoding: Option<HeaderValue>,
+        auditor: Option<Auditor>,
     ) -> http::Response<BoxBody>
     where
         B: TryStream<Ok = T::Encode, Error = Status> + Send + Sync + 'static,
     {
         match response {
             Ok(r) => {
+                let encoding =
+                    self.response_encoding(accept_encoding.as_ref(), r.compression_override());
                 let (mut parts, body) = r.into_http().into_parts();
 
                 // Set the content type
                 parts.headers.insert(
                     http::header::CONTENT_TYPE,
-                    http::header::HeaderValue::from_static("application/grpc"),
+                    http::header::HeaderValue::from_static(self.codec.content_type()),
                 );
 
-                let body = encode_server(self.codec.encoder(), body.into_stream());
+                if let Some(encoding) = encoding {
+                    parts
+                        .headers
+                        .insert(ENCODING_HEADER, encoding.into_header_value());
+                }
+                self.add_accept_encoding_header(&mut parts.headers);
+
+                let compression = encoding.map(|encoding| (encoding, self.compression_settings));
+                let body = encode_server(
+                    AuditEncoder::new(self.codec.encoder(), auditor),
+                    body.into_stream(),
+                    compression,
+                    self.max_encoding_message_size,
+                    self.buffer_pool.clone(),
+                    self.message_checksums,
+                );
 
                 http::Response::from_parts(parts, BoxBody::new(body))
             }
-            Err(status) => Self::map_status(status),
+            Err(status) => self.map_status(status),
         }
     }
 
-    fn map_status(status: Status) -> http::Response<BoxBody> {
+    fn map_status(&self, status: Status) -> http::Response<BoxBody> {
         let (mut parts, _body) = Response::new(()).into_http().into_parts();
 
         parts.headers.insert(
             http::header::CONTENT_TYPE,
-            http::header::HeaderValue::from_static("application/grpc"),
+            http::header::HeaderValue::from_static(self.codec.content_type()),
         );
+        self.add_accept_encoding_header(&mut parts.headers);
 
         status.add_header(&mut parts.headers).unwrap();
 
         http::Response::from_parts(parts, BoxBody::empty())
     }
 
+    /// Advertise the encodings requests may be compressed with.
+    fn add_accept_encoding_header(&self, headers: &mut http::HeaderMap) {
+        if let Some(value) = self
+            .accept_compression_encodings
+            .into_accept_encoding_header_value()
+        {
+            headers.insert(ACCEPT_ENCODING_HEADER, value);
+        }
+    }
+
     fn intercept_request<A>(&self, req: Request<A>) -> Result<Request<A>, http::Response<BoxBody>> {
         if let Some(interceptor) = &self.interceptor {
             match interceptor.call(req) {
                 Ok(req) => Ok(req),
                 Err(status) => {
-                    let res = Self::map_status(status);
+                    let res = self.map_status(status);
                     return Err(res);
                 }
             }
@@ -242,6 +668,15 @@ where
     }
 }
 
+/// Attach the `RequestContext` of a call to its response.
+fn with_context(
+    mut response: http::Response<BoxBody>,
+    context: RequestContext,
+) -> http::Response<BoxBody> {
+    response.extensions_mut().insert(context);
+    response
+}
+
 impl<T: fmt::Debug> fmt::Debug for Grpc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Grpc").field("codec", &self.codec).finish()
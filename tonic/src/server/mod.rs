@@ -8,10 +8,16 @@
 //! will implement the proper gRPC service. Thusly, they are a bit hard to use
 //! by hand.
 
+mod audit;
+mod dispatch;
 mod grpc;
 mod service;
+mod transform;
 
+pub use self::audit::{Audit, AuditLayer, MessageAudit};
+pub use self::dispatch::{dispatch, FullBody};
 pub use self::grpc::Grpc;
 pub use self::service::{
     ClientStreamingService, ServerStreamingService, StreamingService, UnaryService,
 };
+pub use self::transform::{RequestTransform, RequestTransformLayer};
@@ -0,0 +1,198 @@
+use crate::{
+    codec::{DecodeBuf, Decoder},
+    metadata::MetadataMap,
+    Status,
+};
+use std::{
+    any::Any,
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+type TransformFn = dyn Fn(&mut dyn Any, &MetadataMap) -> Result<(), Status> + Send + Sync;
+
+/// Wraps services with [`RequestTransform`], passing the decoded request
+/// messages of some methods to transforms before their handlers receive
+/// them.
+///
+/// The transforms take the messages of their method, along with the
+/// metadata of their call, and may change them, like to fill the defaults
+/// of a tenant or normalize fields, or fail the call. They are applied to
+/// each request message of the streaming methods.
+///
+/// ```rust
+/// use tonic::{metadata::MetadataMap, server::RequestTransformLayer, Status};
+///
+/// # struct ListOrdersRequest { tenant: String, page_size: u32 }
+/// let transform = RequestTransformLayer::new().method(
+///     "/shop.Orders/ListOrders",
+///     |request: &mut ListOrdersRequest, metadata: &MetadataMap| {
+///         if request.tenant.is_empty() {
+///             let tenant = metadata
+///                 .get("x-tenant")
+///                 .and_then(|tenant| tenant.to_str().ok())
+///                 .ok_or_else(|| Status::invalid_argument("missing tenant"))?;
+///             request.tenant = tenant.to_string();
+///         }
+///         request.page_size = request.page_size.min(100);
+///         Ok(())
+///     },
+/// );
+/// ```
+///
+/// [`RequestTransform`]: struct.RequestTransform.html
+#[derive(Clone, Default)]
+pub struct RequestTransformLayer {
+    transforms: Vec<(String, Arc<TransformFn>)>,
+}
+
+impl RequestTransformLayer {
+    /// A layer transforming no message.
+    pub fn new() -> Self {
+        RequestTransformLayer::default()
+    }
+
+    /// Pass the request messages of the method at `path`, like
+    /// `/helloworld.Greeter/SayHello`, to `transform`, after the transforms
+    /// added before it.
+    ///
+    /// The calls fail with `INTERNAL` if the messages of the method are not
+    /// of type `M`.
+    pub fn method<M, F>(mut self, path: impl Into<String>, transform: F) -> Self
+    where
+        M: 'static,
+        F: Fn(&mut M, &MetadataMap) -> Result<(), Status> + Send + Sync + 'static,
+    {
+        let path = path.into();
+        let mismatch = format!("unexpected request message type for {}", path);
+        let transform = move |message: &mut dyn Any, metadata: &MetadataMap| {
+            let message = message
+                .downcast_mut::<M>()
+                .ok_or_else(|| Status::internal(mismatch.clone()))?;
+            transform(message, metadata)
+        };
+        self.transforms.push((path, Arc::new(transform)));
+        self
+    }
+}
+
+impl<S> Layer<S> for RequestTransformLayer {
+    type Service = RequestTransform<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestTransform {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for RequestTransformLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let methods = self.transforms.iter().map(|(path, _)| path);
+        f.debug_struct("RequestTransformLayer")
+            .field("methods", &methods.collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// A service passing the decoded request messages of its calls to
+/// transforms, created by a [`RequestTransformLayer`].
+///
+/// The messages are passed by the generated server it wraps, which finds
+/// the transforms in the extensions of the requests.
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`RequestTransformLayer`]: struct.RequestTransformLayer.html
+/// [`Server`]: ../transport/struct.Server.html
+#[derive(Debug, Clone)]
+pub struct RequestTransform<S> {
+    inner: S,
+    layer: RequestTransformLayer,
+}
+
+impl<S, B> Service<http::Request<B>> for RequestTransform<S>
+where
+    S: Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        let path = request.uri().path();
+        let transforms = self
+            .layer
+            .transforms
+            .iter()
+            .filter(|(method, _)| method == path)
+            .map(|(_, transform)| transform.clone())
+            .collect::<Vec<_>>();
+        if !transforms.is_empty() {
+            let transformer = Transformer {
+                transforms: transforms.into(),
+                metadata: Arc::new(MetadataMap::from_headers(request.headers().clone())),
+            };
+            request.extensions_mut().insert(transformer);
+        }
+        self.inner.call(request)
+    }
+}
+
+#[cfg(feature = "transport")]
+impl<S: crate::transport::NamedService> crate::transport::NamedService for RequestTransform<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// The transforms of the request messages of a call, in the extensions of
+/// its request.
+#[derive(Clone)]
+pub(crate) struct Transformer {
+    transforms: Arc<[Arc<TransformFn>]>,
+    metadata: Arc<MetadataMap>,
+}
+
+impl Transformer {
+    pub(crate) fn from_request<B>(request: &http::Request<B>) -> Option<Self> {
+        request.extensions().get::<Transformer>().cloned()
+    }
+}
+
+/// Passes the messages it decodes to the transforms of their call, if any.
+pub(crate) struct TransformDecoder<D> {
+    inner: D,
+    transformer: Option<Transformer>,
+}
+
+impl<D> TransformDecoder<D> {
+    pub(crate) fn new(inner: D, transformer: Option<Transformer>) -> Self {
+        TransformDecoder { inner, transformer }
+    }
+}
+
+impl<D> Decoder for TransformDecoder<D>
+where
+    D: Decoder<Error = Status>,
+    D::Item: 'static,
+{
+    type Item = D::Item;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let mut message = self.inner.decode(src)?;
+        if let (Some(transformer), Some(message)) = (&self.transformer, &mut message) {
+            for transform in transformer.transforms.iter() {
+                transform(message, &transformer.metadata)?;
+            }
+        }
+        Ok(message)
+    }
+}
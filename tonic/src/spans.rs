@@ -0,0 +1,181 @@
+//! The spans of the calls of clients and servers, created with the
+//! `tracing` feature.
+
+use crate::{
+    body::{Body, BoxBody},
+    metrics::Frames,
+    Status,
+};
+use bytes::Buf;
+use http::HeaderMap;
+use http_body::Body as HttpBody;
+use pin_project::pin_project;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tracing::Span;
+
+/// The span of a call made by a client to the method at `path`, whose
+/// server is at `authority`, if known.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn client(path: &str, authority: Option<&str>) -> Span {
+    #[cfg(feature = "tracing")]
+    {
+        let span = span("client", path);
+        if let Some(authority) = authority {
+            span.record("net.peer.name", authority);
+        }
+        span
+    }
+    #[cfg(not(feature = "tracing"))]
+    Span::none()
+}
+
+/// The span of a call served by a server.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn server<B>(request: &http::Request<B>) -> Span {
+    #[cfg(feature = "tracing")]
+    {
+        let span = span("server", request.uri().path());
+        #[cfg(feature = "transport")]
+        if let Some(addr) = request
+            .extensions()
+            .get::<crate::request::ConnectionInfo>()
+            .and_then(|info| info.remote_addr)
+        {
+            span.record("net.peer.ip", tracing::field::display(addr.ip()));
+            span.record("net.peer.port", addr.port());
+        }
+        span
+    }
+    #[cfg(not(feature = "tracing"))]
+    Span::none()
+}
+
+#[cfg(feature = "tracing")]
+fn span(kind: &'static str, path: &str) -> Span {
+    let path = path.trim_start_matches('/');
+    let (service, method) = match path.rfind('/') {
+        Some(slash) => (&path[..slash], &path[slash + 1..]),
+        None => (path, ""),
+    };
+    tracing::info_span!(
+        "grpc",
+        otel.name = path,
+        otel.kind = kind,
+        rpc.system = "grpc",
+        rpc.service = service,
+        rpc.method = method,
+        rpc.grpc.status_code = tracing::field::Empty,
+        net.peer.name = tracing::field::Empty,
+        net.peer.ip = tracing::field::Empty,
+        net.peer.port = tracing::field::Empty,
+    )
+}
+
+/// Record the status of the call of `span` found in `headers`, the
+/// trailers of a response or the headers of a trailers-only one, if any.
+pub(crate) fn record_status(span: &Span, headers: &HeaderMap) {
+    if let Some(status) = Status::from_header_map(headers) {
+        record_code(span, &status);
+    }
+}
+
+/// Record the code of `status` as the one of the call of `span`.
+pub(crate) fn record_code(span: &Span, status: &Status) {
+    if !span.is_disabled() {
+        span.record("rpc.grpc.status_code", status.code() as i32);
+    }
+}
+
+/// Whether a body carries the messages sent or received by its side of the
+/// call.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Direction {
+    Sent,
+    Received,
+}
+
+/// A body recording an event in the span of its call for each of its
+/// messages, along with the status of the call found in its trailers.
+#[pin_project]
+pub(crate) struct TracedBody<B> {
+    #[pin]
+    inner: B,
+    span: Span,
+    direction: Direction,
+    frames: Frames,
+    messages: u64,
+}
+
+impl<B> TracedBody<B> {
+    pub(crate) fn new(inner: B, span: Span, direction: Direction) -> Self {
+        TracedBody {
+            inner,
+            span,
+            direction,
+            frames: Frames::default(),
+            messages: 0,
+        }
+    }
+}
+
+impl<B: Body> HttpBody for TracedBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let data = futures_util::ready!(Body::poll_data(this.inner, cx));
+        if let (Some(Ok(data)), false) = (&data, this.span.is_disabled()) {
+            let (span, direction, messages) = (&*this.span, *this.direction, this.messages);
+            this.frames.read(data.bytes(), |size| {
+                *messages += 1;
+                let direction = match direction {
+                    Direction::Sent => "SENT",
+                    Direction::Received => "RECEIVED",
+                };
+                tracing::debug!(
+                    parent: span,
+                    message.type = direction,
+                    message.id = *messages,
+                    message.compressed_size = size as u64,
+                    "message",
+                );
+            });
+        }
+        Poll::Ready(data)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = futures_util::ready!(Body::poll_trailers(this.inner, cx));
+        if let Ok(Some(trailers)) = &trailers {
+            record_status(this.span, trailers);
+        }
+        Poll::Ready(trailers)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        Body::is_end_stream(&self.inner)
+    }
+}
+
+/// Trace the response of a server in `span`.
+pub(crate) fn server_response(
+    response: http::Response<BoxBody>,
+    span: Span,
+) -> http::Response<BoxBody> {
+    if span.is_disabled() {
+        return response;
+    }
+    record_status(&span, response.headers());
+    response.map(|body| BoxBody::new(TracedBody::new(body, span, Direction::Sent)))
+}
@@ -0,0 +1,401 @@
+//! Low level hooks into the calls and connections of clients and servers.
+//!
+//! A [`StatsHandler`] set on a [`Server`] or an [`Endpoint`] is told of
+//! each step of their calls, as [`RpcStats`]: their beginning, the headers,
+//! messages and trailers sent and received, and their end, along with the
+//! beginning and end of their connections, as [`ConnStats`]. It is the hook
+//! of the instrumentation libraries, which would otherwise need to wrap
+//! each layer of the clients and servers:
+//!
+//! ```rust
+//! use tonic::stats::{ConnInfo, ConnStats, RpcInfo, RpcStats, StatsHandler};
+//!
+//! struct Log;
+//!
+//! impl StatsHandler for Log {
+//!     fn handle_rpc(&self, rpc: &RpcInfo, stats: &RpcStats<'_>) {
+//!         if let RpcStats::End { code, .. } = stats {
+//!             println!("#{} {}: {:?}", rpc.id(), rpc.method(), code);
+//!         }
+//!     }
+//!
+//!     fn handle_conn(&self, conn: &ConnInfo, stats: &ConnStats) {
+//!         println!("{:?}: {:?}", conn.remote_addr(), stats);
+//!     }
+//! }
+//!
+//! // Server::builder().stats_handler(Log)
+//! // Endpoint::from_static("http://[::1]:50051").stats_handler(Log)
+//! ```
+//!
+//! [`StatsHandler`]: trait.StatsHandler.html
+//! [`Server`]: ../transport/struct.Server.html
+//! [`Endpoint`]: ../transport/struct.Endpoint.html
+//! [`RpcStats`]: enum.RpcStats.html
+//! [`ConnStats`]: enum.ConnStats.html
+
+pub use crate::metrics::Side;
+use crate::{body::Body, metrics::Frames, Code, Status};
+use bytes::Buf;
+use futures_util::ready;
+use http::HeaderMap;
+use http_body::Body as HttpBody;
+use pin_project::pin_project;
+#[cfg(feature = "transport")]
+use std::sync::atomic::AtomicU64;
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::SystemTime,
+};
+
+/// Handles the stats of the calls and connections of a client or a server.
+///
+/// The methods do nothing by default. They are called while the calls are
+/// in progress, and must not block.
+pub trait StatsHandler: Send + Sync + 'static {
+    /// Handle `stats` of the call `rpc`.
+    fn handle_rpc(&self, rpc: &RpcInfo, stats: &RpcStats<'_>) {
+        let _ = (rpc, stats);
+    }
+
+    /// Handle `stats` of the connection `conn`.
+    fn handle_conn(&self, conn: &ConnInfo, stats: &ConnStats) {
+        let _ = (conn, stats);
+    }
+}
+
+impl<H: StatsHandler + ?Sized> StatsHandler for Arc<H> {
+    fn handle_rpc(&self, rpc: &RpcInfo, stats: &RpcStats<'_>) {
+        (**self).handle_rpc(rpc, stats)
+    }
+
+    fn handle_conn(&self, conn: &ConnInfo, stats: &ConnStats) {
+        (**self).handle_conn(conn, stats)
+    }
+}
+
+/// The call stats are handled for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RpcInfo {
+    id: u64,
+    side: Side,
+    method: String,
+    remote_addr: Option<SocketAddr>,
+}
+
+impl RpcInfo {
+    /// The identifier of the call, unique within the process, to tell the
+    /// stats of concurrent calls apart.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Whether the call was made by a client or served by a server.
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    /// The path of the method of the call, like
+    /// `/helloworld.Greeter/SayHello`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// The address of the client of the calls served by a server, if known.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+}
+
+/// A step of a call.
+///
+/// Each call begins with `Begin`, and ends with `End` once its status is
+/// known, the calls dropped before, like the ones cancelled by their
+/// client, ending with `CANCELLED`.
+#[derive(Debug)]
+pub enum RpcStats<'a> {
+    /// The call began.
+    Begin {
+        /// When the call began.
+        at: SystemTime,
+    },
+    /// The headers of the request, for servers, or of the response, for
+    /// clients, were received.
+    InHeader {
+        /// The headers received.
+        headers: &'a HeaderMap,
+    },
+    /// The headers of the request, for clients, or of the response, for
+    /// servers, were sent.
+    OutHeader {
+        /// The headers sent.
+        headers: &'a HeaderMap,
+    },
+    /// A message was received.
+    InPayload {
+        /// The size of the message, as received on the wire.
+        size: usize,
+        /// When the message was received.
+        at: SystemTime,
+    },
+    /// A message was sent.
+    OutPayload {
+        /// The size of the message, as sent on the wire.
+        size: usize,
+        /// When the message was sent.
+        at: SystemTime,
+    },
+    /// The trailers of the response were received by a client.
+    InTrailer {
+        /// The trailers received.
+        trailers: &'a HeaderMap,
+    },
+    /// The trailers of the response were sent by a server.
+    OutTrailer {
+        /// The trailers sent.
+        trailers: &'a HeaderMap,
+    },
+    /// The call ended.
+    End {
+        /// The code of the status of the call.
+        code: Code,
+        /// When the call ended.
+        at: SystemTime,
+    },
+}
+
+/// The connection stats are handled for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConnInfo {
+    side: Side,
+    remote_addr: Option<SocketAddr>,
+}
+
+impl ConnInfo {
+    #[cfg(feature = "transport")]
+    pub(crate) fn new(side: Side, remote_addr: Option<SocketAddr>) -> Self {
+        ConnInfo { side, remote_addr }
+    }
+
+    /// Whether the connection was made by a client or accepted by a server.
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    /// The address of the client of the connections accepted by a server,
+    /// if known.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+}
+
+/// A step of a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnStats {
+    /// The connection was established, after its TLS handshake, if any.
+    Begin {
+        /// When the connection was established.
+        at: SystemTime,
+    },
+    /// The connection was closed.
+    End {
+        /// When the connection was closed.
+        at: SystemTime,
+    },
+}
+
+pub(crate) type SharedHandler = Arc<dyn StatsHandler>;
+
+#[cfg(feature = "transport")]
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A call in progress, ended with `CANCELLED` if dropped before its status
+/// was known.
+pub(crate) struct RpcTracker {
+    handler: SharedHandler,
+    info: RpcInfo,
+    ended: AtomicBool,
+}
+
+impl RpcTracker {
+    /// Begin the call of `request`, whose headers are sent by clients and
+    /// received by servers.
+    #[cfg(feature = "transport")]
+    pub(crate) fn begin<B>(
+        handler: &SharedHandler,
+        side: Side,
+        request: &http::Request<B>,
+        remote_addr: Option<SocketAddr>,
+    ) -> Arc<Self> {
+        let tracker = RpcTracker {
+            handler: handler.clone(),
+            info: RpcInfo {
+                id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+                side,
+                method: request.uri().path().to_string(),
+                remote_addr,
+            },
+            ended: AtomicBool::new(false),
+        };
+        tracker.handle(&RpcStats::Begin {
+            at: SystemTime::now(),
+        });
+        let headers = request.headers();
+        tracker.handle(&match side {
+            Side::Client => RpcStats::OutHeader { headers },
+            Side::Server => RpcStats::InHeader { headers },
+        });
+        Arc::new(tracker)
+    }
+
+    fn handle(&self, stats: &RpcStats<'_>) {
+        self.handler.handle_rpc(&self.info, stats);
+    }
+
+    /// Handle the headers of the response, ending the trailers-only ones.
+    #[cfg(feature = "transport")]
+    pub(crate) fn response_headers(&self, headers: &HeaderMap) {
+        self.handle(&match self.info.side {
+            Side::Client => RpcStats::InHeader { headers },
+            Side::Server => RpcStats::OutHeader { headers },
+        });
+        if let Some(status) = Status::from_header_map(headers) {
+            self.end(status.code());
+        }
+    }
+
+    fn message(&self, size: usize, response: bool) {
+        let at = SystemTime::now();
+        // Clients send requests and receive responses, servers the other way.
+        self.handle(&if response == (self.info.side == Side::Server) {
+            RpcStats::OutPayload { size, at }
+        } else {
+            RpcStats::InPayload { size, at }
+        });
+    }
+
+    fn response_trailers(&self, trailers: Option<&HeaderMap>) {
+        if let Some(trailers) = trailers {
+            self.handle(&match self.info.side {
+                Side::Client => RpcStats::InTrailer { trailers },
+                Side::Server => RpcStats::OutTrailer { trailers },
+            });
+        }
+        let code = trailers
+            .and_then(Status::from_header_map)
+            .map_or(Code::Unknown, |status| status.code());
+        self.end(code);
+    }
+
+    pub(crate) fn end(&self, code: Code) {
+        if !self.ended.swap(true, Ordering::SeqCst) {
+            self.handle(&RpcStats::End {
+                code,
+                at: SystemTime::now(),
+            });
+        }
+    }
+}
+
+impl Drop for RpcTracker {
+    fn drop(&mut self) {
+        self.end(Code::Cancelled);
+    }
+}
+
+/// The request or response body of a call, handling its messages, and the
+/// trailers of responses, if the call is tracked.
+#[pin_project]
+pub(crate) struct StatsBody<B> {
+    #[pin]
+    inner: B,
+    tracker: Option<Arc<RpcTracker>>,
+    frames: Frames,
+    response: bool,
+}
+
+impl<B> StatsBody<B> {
+    pub(crate) fn new(inner: B, tracker: Option<Arc<RpcTracker>>, response: bool) -> Self {
+        StatsBody {
+            inner,
+            tracker,
+            frames: Frames::default(),
+            response,
+        }
+    }
+}
+
+impl<B: Body> HttpBody for StatsBody<B> {
+    type Data = B::Data;
+    type Error = crate::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let data = ready!(Body::poll_data(this.inner, cx));
+        let tracker = match this.tracker {
+            Some(tracker) => tracker,
+            None => return Poll::Ready(data.map(|data| data.map_err(Into::into))),
+        };
+        match data {
+            Some(Ok(data)) => {
+                let response = *this.response;
+                this.frames
+                    .read(data.bytes(), |size| tracker.message(size, response));
+                Poll::Ready(Some(Ok(data)))
+            }
+            Some(Err(err)) => {
+                let status = Status::from_error(err.into());
+                if *this.response {
+                    tracker.end(status.code());
+                }
+                Poll::Ready(Some(Err(Box::new(status))))
+            }
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(Body::poll_trailers(this.inner, cx));
+        let tracker = match this.tracker {
+            Some(tracker) if *this.response => tracker,
+            _ => return Poll::Ready(trailers.map_err(Into::into)),
+        };
+        match trailers {
+            Ok(trailers) => {
+                tracker.response_trailers(trailers.as_ref());
+                Poll::Ready(Ok(trailers))
+            }
+            Err(err) => {
+                let status = Status::from_error(err.into());
+                tracker.end(status.code());
+                Poll::Ready(Err(Box::new(status)))
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        Body::is_end_stream(&self.inner)
+    }
+}
+
+#[cfg(feature = "transport")]
+impl<B: Body> futures_core::Stream for StatsBody<B> {
+    type Item = Result<B::Data, crate::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        HttpBody::poll_data(self, cx)
+    }
+}
@@ -1,13 +1,25 @@
 use bytes::Bytes;
 use http::header::{HeaderMap, HeaderValue};
-use percent_encoding::{percent_decode, percent_encode, EncodeSet, DEFAULT_ENCODE_SET};
-use std::{error::Error, fmt};
+use percent_encoding::{percent_decode, percent_encode, EncodeSet};
+use std::{error::Error, fmt, io, sync::Arc};
 use tracing::{debug, trace, warn};
 
 const GRPC_STATUS_HEADER_CODE: &str = "grpc-status";
 const GRPC_STATUS_MESSAGE_HEADER: &str = "grpc-message";
 const GRPC_STATUS_DETAILS_HEADER: &str = "grpc-status-details-bin";
 
+/// The bytes percent-encoded in the `grpc-message` header: per the gRPC
+/// specification, everything but the visible ASCII characters and spaces,
+/// plus `%` itself.
+#[derive(Clone, Copy)]
+struct MessageEncodeSet;
+
+impl EncodeSet for MessageEncodeSet {
+    fn contains(&self, byte: u8) -> bool {
+        !(0x20..=0x7e).contains(&byte) || byte == b'%'
+    }
+}
+
 /// A gRPC status describing the result of an RPC call.
 ///
 /// Values can be created using the `new` function or one of the specialized
@@ -20,14 +32,24 @@ const GRPC_STATUS_DETAILS_HEADER: &str = "grpc-status-details-bin";
 /// assert_eq!(status1.code(), Code::InvalidArgument);
 /// assert_eq!(status1.code(), status2.code());
 /// ```
+///
+/// Statuses created from transport, HTTP/2 or I/O errors keep the original
+/// error as their [`Error::source`], to tell apart the failures behind a
+/// same code.
 #[derive(Clone)]
 pub struct Status {
     /// The gRPC status code, found in the `grpc-status` header.
     code: Code,
     /// A relevant error message, found in the `grpc-message` header.
     message: String,
+    /// The `grpc-message` header as received, before percent-decoding.
+    raw_message: Option<Bytes>,
     /// Binary opaque details, found in the `grpc-status-details-bin` header.
     details: Bytes,
+    /// The error this status was created from, if any.
+    source: Option<Arc<dyn Error + Send + Sync + 'static>>,
+    /// Why the call was cancelled, if known.
+    cancellation: Option<CancellationCause>,
 }
 
 /// gRPC status codes used by [`Status`].
@@ -93,6 +115,29 @@ pub enum Code {
     __NonExhaustive,
 }
 
+/// Why a call was cancelled before completing, as told by
+/// [`Status::cancellation_cause`].
+///
+/// This is only known locally, it is not sent to the peer.
+///
+/// [`Status::cancellation_cause`]: struct.Status.html#method.cancellation_cause
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CancellationCause {
+    /// The client cancelled the call, by resetting its stream.
+    Client,
+
+    /// The deadline of the call expired.
+    Deadline,
+
+    /// The connection carrying the call was lost.
+    ConnectionLost,
+
+    /// The server went away, shutting down gracefully, before handling the
+    /// call.
+    ServerShutdown,
+}
+
 // ===== impl Status =====
 
 impl Status {
@@ -101,7 +146,23 @@ impl Status {
         Status {
             code,
             message: message.into(),
+            raw_message: None,
             details: Bytes::new(),
+            source: None,
+            cancellation: None,
+        }
+    }
+
+    /// Create a new `Status` with the associated code, message and binary
+    /// details, sent in the `grpc-status-details-bin` trailer.
+    pub fn with_details(code: Code, message: impl Into<String>, details: Bytes) -> Status {
+        Status {
+            code,
+            message: message.into(),
+            raw_message: None,
+            details,
+            source: None,
+            cancellation: None,
         }
     }
 
@@ -241,21 +302,31 @@ impl Status {
         Status::new(Code::Unauthenticated, message)
     }
 
+    /// Create a `Status` from an error, keeping the error as its source.
+    ///
+    /// A `Status` found in the error chain is returned as is, otherwise the
+    /// code is inferred from the errors of the chain.
     #[cfg_attr(not(feature = "h2"), allow(dead_code))]
-    pub(crate) fn from_error(err: &(dyn Error + 'static)) -> Status {
-        Status::try_from_error(err).unwrap_or_else(|| Status::new(Code::Unknown, err.to_string()))
+    pub(crate) fn from_error(err: crate::Error) -> Status {
+        let err = match err.downcast::<Status>() {
+            Ok(status) => return *status,
+            Err(err) => err,
+        };
+
+        let mut status = Status::find_in_error(&*err)
+            .unwrap_or_else(|| Status::new(Code::Unknown, err.to_string()));
+        if status.source.is_none() {
+            status.source = Some(Arc::from(err));
+        }
+        status
     }
 
-    fn try_from_error(err: &(dyn Error + 'static)) -> Option<Status> {
+    pub(crate) fn find_in_error(err: &(dyn Error + 'static)) -> Option<Status> {
         let mut cause = Some(err);
 
         while let Some(err) = cause {
             if let Some(status) = err.downcast_ref::<Status>() {
-                return Some(Status {
-                    code: status.code,
-                    message: status.message.clone(),
-                    details: status.details.clone(),
-                });
+                return Some(status.clone());
             }
 
             #[cfg(feature = "h2")]
@@ -265,6 +336,26 @@ impl Status {
                 }
             }
 
+            #[cfg(feature = "transport")]
+            {
+                if let Some(hyper) = err.downcast_ref::<hyper::Error>() {
+                    if let Some(status) = Status::from_hyper_error(hyper) {
+                        return Some(status);
+                    }
+                }
+
+                if err.is::<tower::timeout::error::Elapsed>() {
+                    return Some(
+                        Status::new(Code::DeadlineExceeded, err.to_string())
+                            .with_cancellation_cause(CancellationCause::Deadline),
+                    );
+                }
+            }
+
+            if let Some(io) = err.downcast_ref::<io::Error>() {
+                return Some(Status::from_io_error(io));
+            }
+
             cause = err.source();
         }
 
@@ -274,6 +365,10 @@ impl Status {
     // FIXME: bubble this into `transport` and expose generic http2 reasons.
     #[cfg(feature = "h2")]
     fn from_h2_error(err: &h2::Error) -> Status {
+        if let Some(io) = err.get_io() {
+            return Status::from_io_error(io);
+        }
+
         // See https://github.com/grpc/grpc/blob/3977c30/doc/PROTOCOL-HTTP2.md#errors
         let code = match err.reason() {
             Some(h2::Reason::NO_ERROR)
@@ -281,6 +376,7 @@ impl Status {
             | Some(h2::Reason::INTERNAL_ERROR)
             | Some(h2::Reason::FLOW_CONTROL_ERROR)
             | Some(h2::Reason::SETTINGS_TIMEOUT)
+            | Some(h2::Reason::FRAME_SIZE_ERROR)
             | Some(h2::Reason::COMPRESSION_ERROR)
             | Some(h2::Reason::CONNECT_ERROR) => Code::Internal,
             Some(h2::Reason::REFUSED_STREAM) => Code::Unavailable,
@@ -291,7 +387,69 @@ impl Status {
             _ => Code::Unknown,
         };
 
-        Status::new(code, format!("h2 protocol error: {}", err))
+        // Streams are only failed with `NO_ERROR` by the GOAWAY frame of a
+        // graceful shutdown.
+        let cancellation = match err.reason() {
+            Some(h2::Reason::CANCEL) => Some(CancellationCause::Client),
+            Some(h2::Reason::NO_ERROR) => Some(CancellationCause::ServerShutdown),
+            _ => None,
+        };
+
+        Status {
+            cancellation,
+            ..Status::new(code, format!("h2 protocol error: {}", err))
+        }
+    }
+
+    // Connection level failures are `Unavailable`, the errors of a single
+    // stream are found further down the chain.
+    #[cfg(feature = "transport")]
+    fn from_hyper_error(err: &hyper::Error) -> Option<Status> {
+        let (code, cancellation) = if err.is_canceled() {
+            (Code::Cancelled, Some(CancellationCause::ConnectionLost))
+        } else if err.is_timeout() {
+            (Code::DeadlineExceeded, Some(CancellationCause::Deadline))
+        } else if err.is_closed() || err.is_incomplete_message() {
+            (Code::Unavailable, Some(CancellationCause::ConnectionLost))
+        } else if err.is_connect() {
+            (Code::Unavailable, None)
+        } else {
+            return None;
+        };
+
+        Some(Status {
+            cancellation,
+            ..Status::new(code, format!("transport error: {}", err))
+        })
+    }
+
+    fn from_io_error(err: &io::Error) -> Status {
+        let code = match err.kind() {
+            io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::AddrNotAvailable
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::TimedOut => Code::Unavailable,
+            _ => Code::Unknown,
+        };
+
+        // Failures to connect in the first place lose no call.
+        let cancellation = match err.kind() {
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof => Some(CancellationCause::ConnectionLost),
+            _ => None,
+        };
+
+        Status {
+            cancellation,
+            ..Status::new(code, format!("io error: {}", err))
+        }
     }
 
     #[cfg(feature = "h2")]
@@ -309,42 +467,62 @@ impl Status {
     where
         E: Into<Box<dyn Error + Send + Sync>>,
     {
-        Status::from_error(&*err.into())
+        Status::from_error(err.into())
     }
 
     pub(crate) fn from_header_map(header_map: &HeaderMap) -> Option<Status> {
         header_map.get(GRPC_STATUS_HEADER_CODE).map(|code| {
             let code = Code::from_bytes(code.as_ref());
-            let error_message = header_map
+            let raw_message = header_map
                 .get(GRPC_STATUS_MESSAGE_HEADER)
-                .map(|header| {
-                    percent_decode(header.as_bytes())
-                        .decode_utf8()
-                        .map(|cow| cow.to_string())
-                })
-                .unwrap_or_else(|| Ok(String::new()));
+                .map(|header| Bytes::copy_from_slice(header.as_bytes()));
+            let message = raw_message
+                .as_ref()
+                .map(|raw| decode_message(raw))
+                .unwrap_or_default();
             let details = header_map
                 .get(GRPC_STATUS_DETAILS_HEADER)
-                .map(|h| Bytes::copy_from_slice(h.as_bytes()))
-                .unwrap_or_else(Bytes::new);
-            match error_message {
-                Ok(message) => Status {
-                    code,
-                    message,
-                    details,
-                },
-                Err(err) => {
-                    warn!("Error deserializing status message header: {}", err);
-                    Status {
-                        code: Code::Unknown,
-                        message: format!("Error deserializing status message header: {}", err),
-                        details,
+                .and_then(|h| match base64::decode(h.as_bytes()) {
+                    Ok(details) => Some(Bytes::from(details)),
+                    Err(err) => {
+                        warn!("Error decoding status details header: {}", err);
+                        None
                     }
-                }
+                })
+                .unwrap_or_else(Bytes::new);
+            Status {
+                code,
+                message,
+                raw_message,
+                details,
+                source: None,
+                cancellation: None,
             }
         })
     }
 
+    /// Whether the call that failed with this `Status` can be retried as is.
+    ///
+    /// Following the gRPC status code guidelines, `Unavailable` is the only
+    /// code denoting a transient condition. Other statuses are retryable
+    /// when the server pushed back with a [`retry_delay`], which requires
+    /// the `prost` feature.
+    ///
+    /// [`retry_delay`]: #method.retry_delay
+    pub fn is_retryable(&self) -> bool {
+        self.code == Code::Unavailable || self.has_retry_delay()
+    }
+
+    #[cfg(feature = "prost")]
+    fn has_retry_delay(&self) -> bool {
+        self.retry_delay().is_some()
+    }
+
+    #[cfg(not(feature = "prost"))]
+    fn has_retry_delay(&self) -> bool {
+        false
+    }
+
     /// Get the gRPC `Code` of this `Status`.
     pub fn code(&self) -> Code {
         self.code
@@ -355,7 +533,50 @@ impl Status {
         &self.message
     }
 
+    /// Get the `grpc-message` header this `Status` was decoded from, before
+    /// percent-decoding.
+    ///
+    /// This is `None` for statuses that weren't received from a peer, or
+    /// that were received without a message.
+    pub fn raw_message(&self) -> Option<&[u8]> {
+        self.raw_message.as_ref().map(|raw| &raw[..])
+    }
+
+    /// Get why the call that failed with this `Status` was cancelled, if it
+    /// was and the cause is known.
+    ///
+    /// Statuses created from transport, HTTP/2 or I/O errors, and the ones
+    /// of the calls whose deadline expired, carry a cause. This tells apart
+    /// the cancellations that share a code, like the ones by the client and
+    /// by a lost connection.
+    pub fn cancellation_cause(&self) -> Option<CancellationCause> {
+        self.cancellation
+    }
+
+    /// Set the cause of the cancellation of the call that failed with this
+    /// `Status`.
+    ///
+    /// ```rust
+    /// # use tonic::{CancellationCause, Status};
+    /// let status = Status::cancelled("Server is shutting down.")
+    ///     .with_cancellation_cause(CancellationCause::ServerShutdown);
+    /// assert_eq!(
+    ///     status.cancellation_cause(),
+    ///     Some(CancellationCause::ServerShutdown)
+    /// );
+    /// ```
+    pub fn with_cancellation_cause(self, cause: CancellationCause) -> Status {
+        Status {
+            cancellation: Some(cause),
+            ..self
+        }
+    }
+
     /// Get the opaque error details of this `Status`.
+    ///
+    /// On clients, these are decoded from the `grpc-status-details-bin`
+    /// header of the response, be it sent with the trailers or in a
+    /// trailers-only response.
     pub fn details(&self) -> &[u8] {
         &self.details
     }
@@ -366,6 +587,20 @@ impl Status {
         Ok(header_map)
     }
 
+    /// The trailers-only response of a call failed with this status, sent
+    /// by the middleware rejecting calls before they reach the service.
+    #[cfg(feature = "transport")]
+    pub(crate) fn to_http(&self) -> http::Response<crate::body::BoxBody> {
+        let mut response = http::Response::new(crate::body::BoxBody::empty());
+        response.headers_mut().insert(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/grpc"),
+        );
+        self.add_header(response.headers_mut())
+            .expect("status messages are percent-encoded");
+        response
+    }
+
     pub(crate) fn add_header(&self, header_map: &mut HeaderMap) -> Result<(), Self> {
         header_map.insert(GRPC_STATUS_HEADER_CODE, self.code.to_header_value());
 
@@ -374,13 +609,13 @@ impl Status {
                 .message
                 .as_bytes()
                 .iter()
-                .any(|&x| DEFAULT_ENCODE_SET.contains(x));
+                .any(|&x| MessageEncodeSet.contains(x));
             let to_write = if is_need_encode {
-                percent_encode(&self.message().as_bytes(), DEFAULT_ENCODE_SET)
+                percent_encode(self.message.as_bytes(), MessageEncodeSet)
                     .to_string()
                     .into()
             } else {
-                Bytes::copy_from_slice(self.message().as_bytes())
+                Bytes::copy_from_slice(self.message.as_bytes())
             };
 
             header_map.insert(
@@ -392,8 +627,11 @@ impl Status {
         if !self.details.is_empty() {
             header_map.insert(
                 GRPC_STATUS_DETAILS_HEADER,
-                HeaderValue::from_maybe_shared(self.details.clone())
-                    .map_err(invalid_header_value_byte)?,
+                HeaderValue::from_maybe_shared(Bytes::from(base64::encode_config(
+                    &self.details[..],
+                    base64::STANDARD_NO_PAD,
+                )))
+                .map_err(invalid_header_value_byte)?,
             );
         }
 
@@ -416,10 +654,33 @@ impl fmt::Debug for Status {
             builder.field("details", &self.details);
         }
 
+        if let Some(source) = &self.source {
+            builder.field("source", source);
+        }
+
+        if let Some(cancellation) = &self.cancellation {
+            builder.field("cancellation", cancellation);
+        }
+
         builder.finish()
     }
 }
 
+/// Percent-decode a `grpc-message` header.
+///
+/// As required by the gRPC specification, invalid values don't fail the
+/// decoding: malformed percent-encodings are kept as is, and messages that
+/// don't decode to UTF-8 are returned in their raw form.
+fn decode_message(raw: &[u8]) -> String {
+    match percent_decode(raw).decode_utf8() {
+        Ok(message) => message.into_owned(),
+        Err(err) => {
+            warn!("Error deserializing status message header: {}", err);
+            String::from_utf8_lossy(raw).into_owned()
+        }
+    }
+}
+
 fn invalid_header_value_byte<Error: fmt::Display>(err: Error) -> Status {
     debug!("Invalid header: {}", err);
     Status::new(
@@ -431,7 +692,7 @@ fn invalid_header_value_byte<Error: fmt::Display>(err: Error) -> Status {
 #[cfg(feature = "h2")]
 impl From<h2::Error> for Status {
     fn from(err: h2::Error) -> Self {
-        Status::from_h2_error(&err)
+        Status::from_error(Box::new(err))
     }
 }
 
@@ -443,8 +704,8 @@ impl From<Status> for h2::Error {
 }
 
 impl From<std::io::Error> for Status {
-    fn from(_io: std::io::Error) -> Self {
-        unimplemented!()
+    fn from(err: std::io::Error) -> Self {
+        Status::from_error(Box::new(err))
     }
 }
 
@@ -459,7 +720,13 @@ impl fmt::Display for Status {
     }
 }
 
-impl Error for Status {}
+impl Error for Status {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
 
 ///
 /// Take the `Status` value from `trailers` if it is available, else from `status_code`.
@@ -598,6 +865,7 @@ impl From<i32> for Code {
 mod tests {
     use super::*;
     use crate::Error;
+    use std::error::Error as _;
 
     #[derive(Debug)]
     struct Nested(Error);
@@ -617,7 +885,7 @@ mod tests {
     #[test]
     fn from_error_status() {
         let orig = Status::new(Code::OutOfRange, "weeaboo");
-        let found = Status::from_error(&orig);
+        let found = Status::from_error(Box::new(orig.clone()));
 
         assert_eq!(orig.code(), found.code());
         assert_eq!(orig.message(), found.message());
@@ -626,16 +894,37 @@ mod tests {
     #[test]
     fn from_error_unknown() {
         let orig: Error = "peek-a-boo".into();
-        let found = Status::from_error(&*orig);
+        let found = Status::from_error(orig);
 
         assert_eq!(found.code(), Code::Unknown);
-        assert_eq!(found.message(), orig.to_string());
+        assert_eq!(found.message(), "peek-a-boo");
+        assert_eq!(found.source().unwrap().to_string(), "peek-a-boo");
+    }
+
+    #[test]
+    fn from_error_keeps_source() {
+        let orig = Nested(Box::new(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "refused",
+        )));
+        let found = Status::from_error(Box::new(orig));
+
+        let source = found.source().unwrap();
+        assert!(source.is::<Nested>());
+        let io = source.source().unwrap();
+        assert_eq!(
+            io.downcast_ref::<std::io::Error>().unwrap().kind(),
+            std::io::ErrorKind::ConnectionRefused
+        );
+
+        // The source survives clones.
+        assert!(found.clone().source().is_some());
     }
 
     #[test]
     fn from_error_nested() {
         let orig = Nested(Box::new(Status::new(Code::OutOfRange, "weeaboo")));
-        let found = Status::from_error(&orig);
+        let found = Status::from_error(Box::new(orig));
 
         assert_eq!(found.code(), Code::OutOfRange);
         assert_eq!(found.message(), "weeaboo");
@@ -645,9 +934,78 @@ mod tests {
     #[cfg(feature = "h2")]
     fn from_error_h2() {
         let orig = h2::Error::from(h2::Reason::CANCEL);
-        let found = Status::from_error(&orig);
+        let found = Status::from_error(Box::new(orig));
 
         assert_eq!(found.code(), Code::Cancelled);
+        let source = found.source().unwrap().downcast_ref::<h2::Error>();
+        assert_eq!(source.unwrap().reason(), Some(h2::Reason::CANCEL));
+    }
+
+    #[test]
+    #[cfg(feature = "h2")]
+    fn from_error_h2_reasons() {
+        let cases = [
+            (h2::Reason::REFUSED_STREAM, Code::Unavailable),
+            (h2::Reason::ENHANCE_YOUR_CALM, Code::ResourceExhausted),
+            (h2::Reason::INADEQUATE_SECURITY, Code::PermissionDenied),
+            (h2::Reason::FRAME_SIZE_ERROR, Code::Internal),
+        ];
+
+        for &(reason, code) in &cases {
+            let found = Status::from_error(Box::new(h2::Error::from(reason)));
+            assert_eq!(found.code(), code, "{:?}", reason);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "h2")]
+    fn cancellation_causes() {
+        let cases = [
+            (h2::Reason::CANCEL, Some(CancellationCause::Client)),
+            (
+                h2::Reason::NO_ERROR,
+                Some(CancellationCause::ServerShutdown),
+            ),
+            (h2::Reason::REFUSED_STREAM, None),
+        ];
+
+        for &(reason, cause) in &cases {
+            let found = Status::from_error(Box::new(h2::Error::from(reason)));
+            assert_eq!(found.cancellation_cause(), cause, "{:?}", reason);
+        }
+
+        let reset = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+        assert_eq!(
+            Status::from(reset).cancellation_cause(),
+            Some(CancellationCause::ConnectionLost)
+        );
+        let refused = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused");
+        assert_eq!(Status::from(refused).cancellation_cause(), None);
+    }
+
+    #[test]
+    fn from_error_io() {
+        let orig = Nested(Box::new(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "refused",
+        )));
+        assert_eq!(Status::from_error(Box::new(orig)).code(), Code::Unavailable);
+
+        let orig = std::io::Error::new(std::io::ErrorKind::Other, "oops");
+        assert_eq!(Status::from(orig).code(), Code::Unknown);
+    }
+
+    #[test]
+    #[cfg(feature = "transport")]
+    fn from_error_timeout() {
+        let orig = tower::timeout::error::Elapsed::new();
+        let found = Status::from_error(Box::new(orig));
+
+        assert_eq!(found.code(), Code::DeadlineExceeded);
+        assert_eq!(
+            found.cancellation_cause(),
+            Some(CancellationCause::Deadline)
+        );
     }
 
     #[test]
@@ -659,6 +1017,82 @@ mod tests {
         assert_eq!(err.reason(), Some(h2::Reason::CANCEL));
     }
 
+    #[test]
+    fn binary_details_round_trip() {
+        let details = Bytes::from_static(&[0, 1, b'\n', 0xff]);
+        let orig = Status::with_details(Code::Internal, "oops", details.clone());
+
+        let header_map = orig.to_header_map().unwrap();
+        let found = Status::from_header_map(&header_map).unwrap();
+
+        assert_eq!(found.code(), Code::Internal);
+        assert_eq!(found.details(), &details[..]);
+    }
+
+    #[test]
+    fn message_percent_encoding() {
+        let message = "100% \"déjà vu\" 日本語\n";
+        let header_map = Status::new(Code::Internal, message)
+            .to_header_map()
+            .unwrap();
+
+        let raw = header_map[GRPC_STATUS_MESSAGE_HEADER].as_bytes();
+        assert_eq!(
+            raw,
+            &b"100%25 \"d%C3%A9j%C3%A0 vu\" %E6%97%A5%E6%9C%AC%E8%AA%9E%0A"[..]
+        );
+
+        let status = Status::from_header_map(&header_map).unwrap();
+        assert_eq!(status.message(), message);
+        assert_eq!(status.raw_message(), Some(raw));
+    }
+
+    #[test]
+    fn invalid_message_encodings_are_kept() {
+        let decode = |raw: &'static str| {
+            let mut header_map = HeaderMap::new();
+            header_map.insert(GRPC_STATUS_HEADER_CODE, HeaderValue::from_static("13"));
+            header_map.insert(GRPC_STATUS_MESSAGE_HEADER, HeaderValue::from_static(raw));
+            Status::from_header_map(&header_map).unwrap()
+        };
+
+        let status = decode("50%% off %zz");
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(status.message(), "50%% off %zz");
+
+        // Not UTF-8 once decoded.
+        let status = decode("caf%E9");
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(status.message(), "caf%E9");
+        assert_eq!(status.raw_message(), Some(&b"caf%E9"[..]));
+
+        assert_eq!(Status::internal("oops").raw_message(), None);
+    }
+
+    #[test]
+    fn retryable_codes() {
+        assert!(Status::unavailable("down").is_retryable());
+        assert!(!Status::deadline_exceeded("late").is_retryable());
+        assert!(!Status::internal("oops").is_retryable());
+    }
+
+    #[test]
+    fn padded_and_unpadded_details() {
+        // Peers may or may not pad the base64 encoded details.
+        for encoded in &["AAEK/w==", "AAEK/w"] {
+            let mut header_map = HeaderMap::new();
+            header_map.insert(GRPC_STATUS_HEADER_CODE, HeaderValue::from_static("13"));
+            header_map.insert(
+                GRPC_STATUS_DETAILS_HEADER,
+                HeaderValue::from_static(encoded),
+            );
+
+            let found = Status::from_header_map(&header_map).unwrap();
+            assert_eq!(found.code(), Code::Internal);
+            assert_eq!(found.details(), &[0, 1, b'\n', 0xff]);
+        }
+    }
+
     #[test]
     fn code_from_i32() {
         // This for loop should catch if we ever add a new variant and don't
@@ -0,0 +1,458 @@
+//! Propagation of distributed trace contexts.
+//!
+//! A [`TraceContext`] identifies the trace a call belongs to and the span
+//! that made it. Clients send it in the W3C `traceparent` and `tracestate`
+//! metadata, along with the binary `grpc-trace-bin` of the OpenCensus based
+//! gRPC implementations, and servers read it from either.
+//!
+//! The [`extract`] interceptor of a server stores the context of its calls
+//! in their extensions, where it is the current context while their handler
+//! runs, and the [`inject`] interceptor of a client sends the current
+//! context, or the one of the request extensions, so traces cross the calls
+//! from handlers to other servers without further work:
+//!
+//! ```rust,ignore
+//! let server = GreeterServer::with_interceptor(greeter, tonic::trace_context::extract());
+//! let client = BackendClient::with_interceptor(channel, tonic::trace_context::inject());
+//! ```
+//!
+//! The contexts of other tracing systems, like the span of an OpenTelemetry
+//! tracer, are bridged by converting them to and from [`TraceContext`], and
+//! running the client calls within their [`scope`]. The trace identifiers can
+//! be recorded by the [`tracing`] spans of a [`Server`] through
+//! [`TraceContext::from_headers`] in its `trace_fn`.
+//!
+//! Like the current deadline, the current context is tied to the future of
+//! the handler, so the work moved to spawned tasks should be wrapped with
+//! [`scope`].
+//!
+//! [`TraceContext`]: struct.TraceContext.html
+//! [`TraceContext::from_headers`]: struct.TraceContext.html#method.from_headers
+//! [`extract`]: fn.extract.html
+//! [`inject`]: fn.inject.html
+//! [`scope`]: fn.scope.html
+//! [`tracing`]: https://docs.rs/tracing
+//! [`Server`]: ../transport/struct.Server.html
+
+use crate::{
+    metadata::{BinaryMetadataValue, MetadataMap},
+    Interceptor, Request,
+};
+use http::HeaderMap;
+use pin_project::pin_project;
+use std::{
+    cell::RefCell,
+    fmt::{self, Write},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+const TRACEPARENT_HEADER: &str = "traceparent";
+const TRACESTATE_HEADER: &str = "tracestate";
+const GRPC_TRACE_BIN_HEADER: &str = "grpc-trace-bin";
+const SAMPLED: u8 = 1;
+
+thread_local! {
+    static CURRENT: RefCell<Option<TraceContext>> = RefCell::default();
+}
+
+/// The context of a distributed trace, as the span of a trace that made a
+/// call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    flags: u8,
+    trace_state: Option<String>,
+}
+
+impl TraceContext {
+    /// Create the context of the span `span_id` of the trace `trace_id`,
+    /// `None` if either is all zeros.
+    pub fn new(trace_id: [u8; 16], span_id: [u8; 8], sampled: bool) -> Option<Self> {
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return None;
+        }
+        Some(TraceContext {
+            trace_id,
+            span_id,
+            flags: if sampled { SAMPLED } else { 0 },
+            trace_state: None,
+        })
+    }
+
+    /// Start a new trace, with random identifiers.
+    pub fn new_root(sampled: bool) -> Self {
+        let mut context = TraceContext {
+            trace_id: [0; 16],
+            span_id: [0; 8],
+            flags: if sampled { SAMPLED } else { 0 },
+            trace_state: None,
+        };
+        while context.trace_id == [0; 16] {
+            context.trace_id = rand::random();
+        }
+        context.child()
+    }
+
+    /// The context of a new span of the same trace, with a random
+    /// identifier, like the one of a call made from this span.
+    pub fn child(&self) -> Self {
+        let mut span_id = [0; 8];
+        while span_id == [0; 8] || span_id == self.span_id {
+            span_id = rand::random();
+        }
+        TraceContext {
+            span_id,
+            ..self.clone()
+        }
+    }
+
+    /// Set the vendor specific `tracestate` sent along with the context.
+    pub fn with_trace_state(self, trace_state: impl Into<String>) -> Self {
+        TraceContext {
+            trace_state: Some(trace_state.into()),
+            ..self
+        }
+    }
+
+    /// The identifier of the trace.
+    pub fn trace_id(&self) -> [u8; 16] {
+        self.trace_id
+    }
+
+    /// The identifier of the span.
+    pub fn span_id(&self) -> [u8; 8] {
+        self.span_id
+    }
+
+    /// Whether the trace is being recorded.
+    pub fn is_sampled(&self) -> bool {
+        self.flags & SAMPLED != 0
+    }
+
+    /// The vendor specific `tracestate` of the trace.
+    pub fn trace_state(&self) -> Option<&str> {
+        self.trace_state.as_deref()
+    }
+
+    /// The identifier of the trace, in hex.
+    pub fn trace_id_hex(&self) -> String {
+        hex(&self.trace_id)
+    }
+
+    /// The identifier of the span, in hex.
+    pub fn span_id_hex(&self) -> String {
+        hex(&self.span_id)
+    }
+
+    /// Parse a W3C `traceparent`, along with its `tracestate`.
+    pub fn from_traceparent(traceparent: &str, trace_state: Option<&str>) -> Option<Self> {
+        let (mut version, mut trace_id, mut span_id, mut flags) = ([0], [0; 16], [0; 8], [0]);
+        let mut fields = traceparent.trim().split('-');
+        parse_hex(fields.next()?, &mut version)?;
+        parse_hex(fields.next()?, &mut trace_id)?;
+        parse_hex(fields.next()?, &mut span_id)?;
+        parse_hex(fields.next()?, &mut flags)?;
+        // Later versions may add fields, but not the first one.
+        if version[0] == 0xff || (version[0] == 0 && fields.next().is_some()) {
+            return None;
+        }
+
+        let mut context = TraceContext::new(trace_id, span_id, false)?;
+        context.flags = flags[0];
+        context.trace_state = trace_state
+            .map(str::trim)
+            .filter(|state| !state.is_empty())
+            .map(String::from);
+        Some(context)
+    }
+
+    /// Format the context as a W3C `traceparent`.
+    pub fn to_traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.trace_id_hex(),
+            self.span_id_hex(),
+            self.flags
+        )
+    }
+
+    /// Parse a binary `grpc-trace-bin`.
+    pub fn from_grpc_trace_bin(bytes: &[u8]) -> Option<Self> {
+        let (version, mut fields) = bytes.split_first()?;
+        if *version != 0 {
+            return None;
+        }
+
+        let mut trace_id = None;
+        let mut span_id = None;
+        let mut options = 0;
+        // Fields are read in order, until an unknown one.
+        while let Some((&field, rest)) = fields.split_first() {
+            match field {
+                0 if rest.len() >= 16 => {
+                    let mut id = [0; 16];
+                    id.copy_from_slice(&rest[..16]);
+                    trace_id = Some(id);
+                    fields = &rest[16..];
+                }
+                1 if rest.len() >= 8 => {
+                    let mut id = [0; 8];
+                    id.copy_from_slice(&rest[..8]);
+                    span_id = Some(id);
+                    fields = &rest[8..];
+                }
+                2 if !rest.is_empty() => {
+                    options = rest[0];
+                    fields = &rest[1..];
+                }
+                _ => break,
+            }
+        }
+        TraceContext::new(trace_id?, span_id?, options & SAMPLED != 0)
+    }
+
+    /// Format the context as a binary `grpc-trace-bin`.
+    pub fn to_grpc_trace_bin(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(29);
+        bytes.extend_from_slice(&[0, 0]);
+        bytes.extend_from_slice(&self.trace_id);
+        bytes.push(1);
+        bytes.extend_from_slice(&self.span_id);
+        bytes.extend_from_slice(&[2, self.flags & SAMPLED]);
+        bytes
+    }
+
+    /// Read the context of a call from its metadata, from its `traceparent`
+    /// or else its `grpc-trace-bin`.
+    pub fn from_metadata(metadata: &MetadataMap) -> Option<Self> {
+        let traceparent = metadata
+            .get(TRACEPARENT_HEADER)
+            .and_then(|value| value.to_str().ok());
+        if let Some(traceparent) = traceparent {
+            let trace_state = metadata
+                .get(TRACESTATE_HEADER)
+                .and_then(|value| value.to_str().ok());
+            if let Some(context) = TraceContext::from_traceparent(traceparent, trace_state) {
+                return Some(context);
+            }
+        }
+
+        let bytes = metadata.get_bin(GRPC_TRACE_BIN_HEADER)?.to_bytes().ok()?;
+        TraceContext::from_grpc_trace_bin(&bytes)
+    }
+
+    /// Read the context of a call from its headers, like the ones passed to
+    /// the `trace_fn` of a [`Server`].
+    ///
+    /// [`Server`]: ../transport/struct.Server.html
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        TraceContext::from_metadata(&MetadataMap::from_headers(headers.clone()))
+    }
+
+    /// Write the context to the metadata of a call, in its `traceparent`,
+    /// `tracestate` and `grpc-trace-bin`.
+    pub fn inject(&self, metadata: &mut MetadataMap) {
+        let traceparent = self.to_traceparent();
+        metadata.insert(
+            TRACEPARENT_HEADER,
+            traceparent.parse().expect("traceparent is ascii"),
+        );
+        match self.trace_state.as_ref().map(|state| state.parse()) {
+            Some(Ok(state)) => {
+                metadata.insert(TRACESTATE_HEADER, state);
+            }
+            _ => {
+                metadata.remove(TRACESTATE_HEADER);
+            }
+        }
+        metadata.insert_bin(
+            GRPC_TRACE_BIN_HEADER,
+            BinaryMetadataValue::from_bytes(&self.to_grpc_trace_bin()),
+        );
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_traceparent())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    hex
+}
+
+/// Parse the lowercase hex encoding of `bytes`.
+fn parse_hex(hex: &str, bytes: &mut [u8]) -> Option<()> {
+    if hex.len() != bytes.len() * 2 || hex.bytes().any(|b| b.is_ascii_uppercase()) {
+        return None;
+    }
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(())
+}
+
+/// Get the trace context of the call being handled, if any.
+pub fn current() -> Option<TraceContext> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Run `future` with `context` as the current trace context.
+pub fn scope<F>(context: Option<TraceContext>, future: F) -> Scope<F>
+where
+    F: Future,
+{
+    Scope {
+        context,
+        inner: future,
+    }
+}
+
+/// A future running with a current trace context, created by [`scope`].
+///
+/// [`scope`]: fn.scope.html
+#[pin_project]
+#[derive(Debug)]
+pub struct Scope<F> {
+    context: Option<TraceContext>,
+    #[pin]
+    inner: F,
+}
+
+impl<F: Future> Future for Scope<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _current = SetCurrent::new(this.context.clone());
+        this.inner.poll(cx)
+    }
+}
+
+/// Restores the previous current context on drop, even when unwinding.
+struct SetCurrent(Option<TraceContext>);
+
+impl SetCurrent {
+    fn new(context: Option<TraceContext>) -> Self {
+        SetCurrent(CURRENT.with(|current| current.replace(context)))
+    }
+}
+
+impl Drop for SetCurrent {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// An interceptor storing the trace context of the calls of a server in
+/// their extensions.
+pub fn extract() -> Interceptor {
+    Interceptor::new(|mut request: Request<()>| {
+        if let Some(context) = TraceContext::from_metadata(request.metadata()) {
+            request.extensions_mut().insert(context);
+        }
+        Ok(request)
+    })
+}
+
+/// An interceptor sending the trace context of the calls of a client, the
+/// one in their extensions or else the current one, as a new span for the
+/// call. The calls without a context are sent as they are.
+pub fn inject() -> Interceptor {
+    Interceptor::new(|mut request: Request<()>| {
+        let context = request.extensions().get::<TraceContext>().cloned();
+        if let Some(context) = context.or_else(current) {
+            context.child().inject(request.metadata_mut());
+        }
+        Ok(request)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn parses_traceparents() {
+        let context =
+            TraceContext::from_traceparent(TRACEPARENT, Some("congo=t61rcWkgMzE")).unwrap();
+        assert_eq!(context.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.span_id_hex(), "00f067aa0ba902b7");
+        assert!(context.is_sampled());
+        assert_eq!(context.trace_state(), Some("congo=t61rcWkgMzE"));
+        assert_eq!(context.to_traceparent(), TRACEPARENT);
+
+        let invalid = [
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-00",
+        ];
+        for traceparent in &invalid {
+            assert_eq!(TraceContext::from_traceparent(traceparent, None), None);
+        }
+        // Later versions are read as the first one.
+        let later = "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-future";
+        assert!(TraceContext::from_traceparent(later, None).is_some());
+    }
+
+    #[test]
+    fn converts_grpc_trace_bins() {
+        let context = TraceContext::from_traceparent(TRACEPARENT, None).unwrap();
+        let bytes = context.to_grpc_trace_bin();
+        assert_eq!(bytes.len(), 29);
+        assert_eq!(TraceContext::from_grpc_trace_bin(&bytes), Some(context));
+
+        assert_eq!(TraceContext::from_grpc_trace_bin(&bytes[..20]), None);
+        assert_eq!(TraceContext::from_grpc_trace_bin(&[1]), None);
+    }
+
+    #[test]
+    fn prefers_the_traceparent_of_the_metadata() {
+        let context = TraceContext::new_root(true).with_trace_state("a=b");
+        let mut metadata = MetadataMap::new();
+        context.inject(&mut metadata);
+        assert_eq!(
+            TraceContext::from_metadata(&metadata),
+            Some(context.clone())
+        );
+
+        // Only the traceparent carries the trace state.
+        metadata.remove(TRACEPARENT_HEADER);
+        let from_bin = TraceContext::from_metadata(&metadata).unwrap();
+        assert_eq!(from_bin.trace_id(), context.trace_id());
+        assert_eq!(from_bin.trace_state(), None);
+    }
+
+    #[test]
+    fn injects_children_of_the_current_context() {
+        let context = TraceContext::new_root(false);
+        let request =
+            futures_util::future::FutureExt::now_or_never(scope(Some(context.clone()), async {
+                inject().call(Request::new(())).unwrap()
+            }))
+            .unwrap();
+        assert_eq!(current(), None);
+
+        let sent = TraceContext::from_metadata(request.metadata()).unwrap();
+        assert_eq!(sent.trace_id(), context.trace_id());
+        assert_ne!(sent.span_id(), context.span_id());
+        assert!(!sent.is_sampled());
+
+        let request = inject().call(Request::new(())).unwrap();
+        assert!(request.metadata().is_empty());
+    }
+}
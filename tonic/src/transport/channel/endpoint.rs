@@ -1,15 +1,19 @@
 use super::super::service;
-use super::Channel;
 #[cfg(feature = "tls")]
 use super::ClientTlsConfig;
+use super::{Channel, ChannelEvents, Events};
 #[cfg(feature = "tls")]
 use crate::transport::service::TlsConnector;
-use crate::transport::Error;
+use crate::{
+    stats::{SharedHandler, StatsHandler},
+    transport::Error,
+};
 use bytes::Bytes;
 use http::uri::{InvalidUri, Uri};
 use std::{
     convert::{TryFrom, TryInto},
     fmt,
+    sync::Arc,
     time::Duration,
 };
 use tower_make::MakeConnection;
@@ -30,6 +34,8 @@ pub struct Endpoint {
     pub(crate) init_connection_window_size: Option<u32>,
     pub(crate) tcp_keepalive: Option<Duration>,
     pub(crate) tcp_nodelay: bool,
+    pub(crate) stats_handler: Option<SharedHandler>,
+    pub(crate) events: Events,
 }
 
 impl Endpoint {
@@ -167,6 +173,26 @@ impl Endpoint {
         }
     }
 
+    /// Report the stats of the calls made and the connections established
+    /// by the channels of this endpoint to `handler`, see [`StatsHandler`].
+    ///
+    /// [`StatsHandler`]: ../stats/trait.StatsHandler.html
+    pub fn stats_handler(self, handler: impl StatsHandler) -> Self {
+        Endpoint {
+            stats_handler: Some(Arc::new(handler)),
+            ..self
+        }
+    }
+
+    /// Subscribe to the events of the subchannels of the channels created
+    /// from this endpoint, and its clones, including the ones of their first
+    /// connection, see [`Channel::events`].
+    ///
+    /// [`Channel::events`]: struct.Channel.html#method.events
+    pub fn events(&self) -> ChannelEvents {
+        self.events.subscribe()
+    }
+
     /// Create a channel from this config.
     pub async fn connect(&self) -> Result<Channel, Error> {
         let mut http = hyper::client::connect::HttpConnector::new();
@@ -215,6 +241,8 @@ impl From<Uri> for Endpoint {
             init_connection_window_size: None,
             tcp_keepalive: None,
             tcp_nodelay: true,
+            stats_handler: None,
+            events: Events::new(),
         }
     }
 }
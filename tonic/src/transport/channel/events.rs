@@ -0,0 +1,203 @@
+use futures_channel::mpsc;
+use futures_core::Stream;
+use http::Uri;
+use std::{
+    fmt,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::SystemTime,
+};
+
+/// The connectivity state of a subchannel, following the states of the
+/// gRPC connectivity semantics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectivityState {
+    /// The subchannel is not connected, and connects on the next request.
+    Idle,
+    /// The subchannel is connecting, including its TLS handshake, if any.
+    Connecting,
+    /// The subchannel is connected, and ready to send requests.
+    Ready,
+    /// The last attempt to connect failed.
+    TransientFailure,
+}
+
+/// An event of a subchannel of a [`Channel`], the connection to one of its
+/// endpoints, reconnected whenever it is lost.
+///
+/// The events are received from the [`ChannelEvents`] of
+/// [`Channel::events`] and [`Endpoint::events`].
+///
+/// [`Channel`]: struct.Channel.html
+/// [`ChannelEvents`]: struct.ChannelEvents.html
+/// [`Channel::events`]: struct.Channel.html#method.events
+/// [`Endpoint::events`]: struct.Endpoint.html#method.events
+#[derive(Debug, Clone)]
+pub struct ChannelEvent {
+    subchannel: u64,
+    target: Uri,
+    at: SystemTime,
+    kind: ChannelEventKind,
+}
+
+impl ChannelEvent {
+    /// The identifier of the subchannel, unique within the process, to tell
+    /// the events of the subchannels of a balanced channel apart.
+    pub fn subchannel(&self) -> u64 {
+        self.subchannel
+    }
+
+    /// The uri of the endpoint of the subchannel.
+    pub fn target(&self) -> &Uri {
+        &self.target
+    }
+
+    /// When the event happened.
+    pub fn at(&self) -> SystemTime {
+        self.at
+    }
+
+    /// What happened.
+    pub fn kind(&self) -> &ChannelEventKind {
+        &self.kind
+    }
+}
+
+/// What happened to a subchannel.
+///
+/// Each `ConnectAttempt` is followed by either `ConnectFailed` or
+/// `Connected`, and every connection eventually reports `Disconnected`, the
+/// changes of the [`ConnectivityState`] they make reported as
+/// `StateChanged`.
+///
+/// [`ConnectivityState`]: enum.ConnectivityState.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelEventKind {
+    /// The subchannel began to connect.
+    ConnectAttempt,
+    /// The connection, or its TLS handshake, failed.
+    ConnectFailed {
+        /// The reason the connection failed.
+        error: String,
+    },
+    /// The subchannel connected, after completing the TLS handshake when TLS
+    /// is enabled.
+    Connected,
+    /// The connection was closed.
+    Disconnected {
+        /// The IO error that ended the connection, or `None` if it was
+        /// closed cleanly by either side.
+        error: Option<String>,
+    },
+    /// The connectivity state of the subchannel changed.
+    StateChanged {
+        /// The state before.
+        from: ConnectivityState,
+        /// The state after.
+        to: ConnectivityState,
+    },
+}
+
+/// A stream of the [`ChannelEvent`]s of the subchannels of a channel,
+/// beginning with the events happening once subscribed.
+///
+/// It ends once the channels and endpoints it subscribed to are dropped.
+///
+/// [`ChannelEvent`]: struct.ChannelEvent.html
+pub struct ChannelEvents(mpsc::UnboundedReceiver<ChannelEvent>);
+
+impl Stream for ChannelEvents {
+    type Item = ChannelEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0).poll_next(cx)
+    }
+}
+
+impl fmt::Debug for ChannelEvents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChannelEvents").finish()
+    }
+}
+
+type Subscribers = Arc<Mutex<Vec<mpsc::UnboundedSender<ChannelEvent>>>>;
+
+/// The subscribers to the events of an endpoint or a channel, along with
+/// the ones of the balanced channels the endpoint is part of.
+#[derive(Clone)]
+pub(crate) struct Events {
+    hubs: Vec<Subscribers>,
+}
+
+impl Events {
+    pub(crate) fn new() -> Self {
+        Events {
+            hubs: vec![Subscribers::default()],
+        }
+    }
+
+    /// Also send the events to the subscribers of `other`.
+    pub(crate) fn and(mut self, other: &Events) -> Self {
+        self.hubs.extend(other.hubs.iter().cloned());
+        self
+    }
+
+    pub(crate) fn subscribe(&self) -> ChannelEvents {
+        let (sender, receiver) = mpsc::unbounded();
+        if let Some(hub) = self.hubs.first() {
+            hub.lock().unwrap().push(sender);
+        }
+        ChannelEvents(receiver)
+    }
+
+    fn send(&self, event: ChannelEvent) {
+        for hub in &self.hubs {
+            hub.lock()
+                .unwrap()
+                .retain(|sender| sender.unbounded_send(event.clone()).is_ok());
+        }
+    }
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The connection to an endpoint, sending its events.
+pub(crate) struct Subchannel {
+    id: u64,
+    target: Uri,
+    events: Events,
+    state: Mutex<ConnectivityState>,
+}
+
+impl Subchannel {
+    pub(crate) fn new(target: Uri, events: Events) -> Self {
+        Subchannel {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            target,
+            events,
+            state: Mutex::new(ConnectivityState::Idle),
+        }
+    }
+
+    pub(crate) fn send(&self, kind: ChannelEventKind) {
+        self.events.send(ChannelEvent {
+            subchannel: self.id,
+            target: self.target.clone(),
+            at: SystemTime::now(),
+            kind,
+        });
+    }
+
+    /// Move to `to`, sending the change, if any.
+    pub(crate) fn transition(&self, to: ConnectivityState) {
+        let mut state = self.state.lock().unwrap();
+        if *state != to {
+            let from = std::mem::replace(&mut *state, to);
+            self.send(ChannelEventKind::StateChanged { from, to });
+        }
+    }
+}
@@ -1,11 +1,16 @@
 //! Client implementation and builder.
 
 mod endpoint;
+mod events;
+mod route;
 #[cfg(feature = "tls")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
 mod tls;
 
 pub use endpoint::Endpoint;
+pub use events::{ChannelEvent, ChannelEventKind, ChannelEvents, ConnectivityState};
+pub(crate) use events::{Events, Subchannel};
+pub use route::{Route, RouteLayer};
 #[cfg(feature = "tls")]
 pub use tls::ClientTlsConfig;
 
@@ -59,9 +64,16 @@ const DEFAULT_BUFFER_SIZE: usize = 1024;
 /// the channel is backed by a `tower_buffer::Buffer` which runs the connection
 /// in a background task and provides a `mpsc` channel interface. Due to this
 /// cloning the `Channel` type is cheap and encouraged.
+///
+/// # Middleware
+///
+/// `Channel` is a `tower` `Service` of `http::Request<BoxBody>`, so it can be
+/// wrapped with `tower` middleware, like retries or rate limits, and the
+/// result handed to the generated clients, which accept any such service.
 #[derive(Clone)]
 pub struct Channel {
     svc: Buffer<Svc, Request<BoxBody>>,
+    events: Events,
 }
 
 /// A future that resolves to an HTTP response.
@@ -112,9 +124,10 @@ impl Channel {
             .and_then(|e| e.buffer_size)
             .unwrap_or(DEFAULT_BUFFER_SIZE);
 
-        let discover = ServiceList::new(list);
+        let events = Events::new();
+        let discover = ServiceList::new(list, events.clone());
 
-        Self::balance(discover, buffer_size)
+        Self::balance(discover, buffer_size, events)
     }
 
     pub(crate) async fn connect<C>(connector: C, endpoint: Endpoint) -> Result<Self, super::Error>
@@ -125,6 +138,7 @@ impl Channel {
         C::Response: AsyncRead + AsyncWrite + HyperConnection + Unpin + Send + 'static,
     {
         let buffer_size = endpoint.buffer_size.clone().unwrap_or(DEFAULT_BUFFER_SIZE);
+        let events = endpoint.events.clone();
 
         let svc = Connection::new(connector, endpoint)
             .await
@@ -132,10 +146,10 @@ impl Channel {
 
         let svc = Buffer::new(Either::A(svc), buffer_size);
 
-        Ok(Channel { svc })
+        Ok(Channel { svc, events })
     }
 
-    pub(crate) fn balance<D>(discover: D, buffer_size: usize) -> Self
+    pub(crate) fn balance<D>(discover: D, buffer_size: usize, events: Events) -> Self
     where
         D: Discover<Service = Connection> + Unpin + Send + 'static,
         D::Error: Into<crate::Error>,
@@ -146,12 +160,34 @@ impl Channel {
         let svc = BoxService::new(svc);
         let svc = Buffer::new(Either::B(svc), buffer_size);
 
-        Channel { svc }
+        Channel { svc, events }
+    }
+
+    /// Subscribe to the [`ChannelEvent`]s of the subchannels of this
+    /// channel: their connection attempts, the results of their handshakes,
+    /// the changes of their [`ConnectivityState`] and the reasons they
+    /// disconnected.
+    ///
+    /// ```no_run
+    /// # use tonic::transport::Channel;
+    /// # use futures_util::StreamExt;
+    /// # async fn f(channel: Channel) {
+    /// let mut events = channel.events();
+    /// while let Some(event) = events.next().await {
+    ///     println!("{} #{}: {:?}", event.target(), event.subchannel(), event.kind());
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [`ChannelEvent`]: struct.ChannelEvent.html
+    /// [`ConnectivityState`]: enum.ConnectivityState.html
+    pub fn events(&self) -> ChannelEvents {
+        self.events.subscribe()
     }
 }
 
-impl GrpcService<BoxBody> for Channel {
-    type ResponseBody = hyper::Body;
+impl Service<Request<BoxBody>> for Channel {
+    type Response = Response<hyper::Body>;
     type Error = super::Error;
     type Future = ResponseFuture;
 
@@ -0,0 +1,251 @@
+use super::Channel;
+use crate::{body::BoxBody, Status};
+use bytes::Bytes;
+use futures_channel::mpsc;
+use futures_core::Stream;
+use futures_util::ready;
+use http::{HeaderMap, Request, Response};
+use http_body::Body as HttpBody;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{layer::Layer, Service, ServiceExt};
+
+/// Sends some of the calls of the channels it wraps to an alternate
+/// [`Channel`], for gradual migrations between backends, or copies of them
+/// for shadow traffic, with a [`Route`].
+///
+/// No call is selected until given a [`sample`] of them, or the
+/// [`metadata`] of the selected calls, like a header set by the clients
+/// trying a new backend out. The calls may also be restricted to some
+/// [`method`]s.
+///
+/// ```rust
+/// use tonic::transport::{channel::RouteLayer, Channel};
+///
+/// # async fn connect() -> Result<(), tonic::transport::Error> {
+/// let canary = Channel::from_static("http://[::1]:50052").connect().await?;
+/// let layer = RouteLayer::route(canary)
+///     .sample(0.05)
+///     .metadata("x-canary", "true");
+///
+/// // let client = GreeterClient::new(tower::layer::Layer::layer(&layer, channel));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Channel`]: ../struct.Channel.html
+/// [`Route`]: struct.Route.html
+/// [`sample`]: #method.sample
+/// [`metadata`]: #method.metadata
+/// [`method`]: #method.method
+#[derive(Debug, Clone)]
+pub struct RouteLayer {
+    alternate: Channel,
+    mirror: bool,
+    rate: f64,
+    metadata: Vec<(String, String)>,
+    methods: Vec<String>,
+}
+
+impl RouteLayer {
+    /// Send the selected calls to `alternate` instead.
+    pub fn route(alternate: Channel) -> Self {
+        RouteLayer::new(alternate, false)
+    }
+
+    /// Send copies of the selected calls to `alternate` too, their responses
+    /// being discarded.
+    ///
+    /// The copies are sent in the background, without waiting for or
+    /// holding back the original calls.
+    pub fn mirror(alternate: Channel) -> Self {
+        RouteLayer::new(alternate, true)
+    }
+
+    fn new(alternate: Channel, mirror: bool) -> Self {
+        RouteLayer {
+            alternate,
+            mirror,
+            rate: 0.0,
+            metadata: Vec::new(),
+            methods: Vec::new(),
+        }
+    }
+
+    /// Select `rate`, between 0 and 1, of the calls at random.
+    pub fn sample(mut self, rate: f64) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Select the calls whose metadata entry `key` is `value`, whatever the
+    /// sample.
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.push((key.into(), value.into()));
+        self
+    }
+
+    /// Only select the calls of the method at `path`, like
+    /// `/helloworld.Greeter/SayHello`, and of the other methods added.
+    pub fn method(mut self, path: impl Into<String>) -> Self {
+        self.methods.push(path.into());
+        self
+    }
+
+    fn selects<B>(&self, request: &Request<B>) -> bool {
+        let path = request.uri().path();
+        if !self.methods.is_empty() && !self.methods.iter().any(|method| method == path) {
+            return false;
+        }
+
+        let headers = request.headers();
+        self.metadata.iter().any(|(key, value)| {
+            headers
+                .get(key.as_str())
+                .is_some_and(|v| v.as_bytes() == value.as_bytes())
+        }) || rand::random::<f64>() < self.rate
+    }
+}
+
+impl<S> Layer<S> for RouteLayer {
+    type Service = Route<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Route {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// A service sending some of its calls, or copies of them, to an alternate
+/// [`Channel`], created by a [`RouteLayer`].
+///
+/// [`Channel`]: ../struct.Channel.html
+/// [`RouteLayer`]: struct.RouteLayer.html
+#[derive(Debug, Clone)]
+pub struct Route<S> {
+    inner: S,
+    layer: RouteLayer,
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = Result<T, crate::Error>> + Send + 'static>>;
+
+impl<S> Service<Request<BoxBody>> for Route<S>
+where
+    S: Service<Request<BoxBody>, Response = Response<hyper::Body>>,
+    S::Error: Into<crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<hyper::Body>;
+    type Error = crate::Error;
+    type Future = BoxFuture<Self::Response>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        if !self.layer.selects(&request) {
+            let response = self.inner.call(request);
+            return Box::pin(async move { response.await.map_err(Into::into) });
+        }
+
+        let alternate = self.layer.alternate.clone();
+        if !self.layer.mirror {
+            let response = alternate.oneshot(request);
+            return Box::pin(async move { response.await.map_err(Into::into) });
+        }
+
+        let (copy, request) = mirror(request);
+        tokio::spawn(async move {
+            if let Ok(response) = alternate.oneshot(copy).await {
+                let mut body = response.into_body();
+                while let Some(Ok(_)) = body.data().await {}
+            }
+        });
+        let response = self.inner.call(request);
+        Box::pin(async move { response.await.map_err(Into::into) })
+    }
+}
+
+/// Copy `request`, its body being copied as it is sent.
+fn mirror(request: Request<BoxBody>) -> (Request<BoxBody>, Request<BoxBody>) {
+    let (sender, receiver) = mpsc::unbounded();
+    let (parts, body) = request.into_parts();
+
+    let mut copy = Request::new(BoxBody::new(MirrorBody(receiver)));
+    *copy.method_mut() = parts.method.clone();
+    *copy.uri_mut() = parts.uri.clone();
+    *copy.version_mut() = parts.version;
+    *copy.headers_mut() = parts.headers.clone();
+
+    let body = TeeBody {
+        inner: body,
+        sender: Some(sender),
+    };
+    (copy, Request::from_parts(parts, BoxBody::new(body)))
+}
+
+/// A request body sending a copy of its data to a [`MirrorBody`].
+struct TeeBody {
+    inner: BoxBody,
+    sender: Option<mpsc::UnboundedSender<Result<Bytes, Status>>>,
+}
+
+impl HttpBody for TeeBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let data = ready!(Pin::new(&mut self.inner).poll_data(cx));
+        // The copy may have failed already.
+        match (&data, &self.sender) {
+            (Some(data), Some(sender)) if sender.unbounded_send(data.clone()).is_err() => {
+                self.sender = None
+            }
+            (None, _) => self.sender = None,
+            _ => {}
+        }
+        Poll::Ready(data)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+}
+
+/// The body of the copy of a request, received from its [`TeeBody`].
+struct MirrorBody(mpsc::UnboundedReceiver<Result<Bytes, Status>>);
+
+impl HttpBody for MirrorBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Pin::new(&mut self.0).poll_next(cx)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+}
@@ -0,0 +1,465 @@
+use super::NamedService;
+#[cfg(feature = "jwt")]
+use crate::jwt::Claims;
+use crate::{body::BoxBody, metadata::MetadataMap, request::ConnectionInfo, Status};
+use http::{Request, Response};
+use std::{
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{layer::Layer, Service};
+
+/// The DER encoded OID of the subject alternative name extension, 2.5.29.17.
+const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// Decides whether calls are allowed, like a [`Policy`] or an external
+/// engine like Cedar or OPA.
+///
+/// [`Policy`]: struct.Policy.html
+pub trait Authorizer: Send + Sync + 'static {
+    /// Decide whether `request` is allowed.
+    fn authorize<'a>(
+        &'a self,
+        request: &'a AuthzRequest,
+    ) -> Pin<Box<dyn Future<Output = Decision> + Send + 'a>>;
+}
+
+impl<A: Authorizer + ?Sized> Authorizer for Arc<A> {
+    fn authorize<'a>(
+        &'a self,
+        request: &'a AuthzRequest,
+    ) -> Pin<Box<dyn Future<Output = Decision> + Send + 'a>> {
+        (**self).authorize(request)
+    }
+}
+
+/// Whether a call is allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// The call is allowed.
+    Allow,
+    /// The call is denied, failing with `PERMISSION_DENIED` and the given
+    /// message.
+    Deny(String),
+}
+
+/// What a call is authorized on: its method, its metadata and the identity
+/// of its peer.
+#[derive(Debug)]
+pub struct AuthzRequest {
+    method: String,
+    metadata: MetadataMap,
+    remote_addr: Option<SocketAddr>,
+    peer_names: Vec<String>,
+    #[cfg(feature = "jwt")]
+    claims: Option<Claims>,
+}
+
+impl AuthzRequest {
+    fn new<B>(request: &Request<B>) -> Self {
+        let info = request.extensions().get::<ConnectionInfo>();
+        let peer_names = info
+            .and_then(|info| info.peer_certs.as_ref())
+            .and_then(|certs| certs.first())
+            .map(|cert| subject_alt_names(&cert.pem).unwrap_or_default())
+            .unwrap_or_default();
+
+        AuthzRequest {
+            method: request.uri().path().to_string(),
+            metadata: MetadataMap::from_headers(request.headers().clone()),
+            remote_addr: info.and_then(|info| info.remote_addr),
+            peer_names,
+            #[cfg(feature = "jwt")]
+            claims: request.extensions().get::<Claims>().cloned(),
+        }
+    }
+
+    /// The path of the method of the call, like
+    /// `/helloworld.Greeter/SayHello`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// The metadata of the call.
+    pub fn metadata(&self) -> &MetadataMap {
+        &self.metadata
+    }
+
+    /// The address of the peer of the call.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// The DNS names and URIs, like SPIFFE IDs, in the subject alternative
+    /// names of the certificate of the peer, over mutual TLS.
+    pub fn peer_names(&self) -> &[String] {
+        &self.peer_names
+    }
+
+    /// The claims of the token of the call, validated by a [`JwtAuth`]
+    /// wrapping the [`Authz`] of the service.
+    ///
+    /// [`JwtAuth`]: ../../jwt/struct.JwtAuth.html
+    /// [`Authz`]: struct.Authz.html
+    #[cfg(feature = "jwt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+    pub fn claims(&self) -> Option<&Claims> {
+        self.claims.as_ref()
+    }
+}
+
+/// Allow and deny rules on the methods of the calls, denying the calls no
+/// rule allows.
+///
+/// A call is denied if a deny rule of its method matches it, and otherwise
+/// allowed if an allow rule of its method does. Methods are given as paths,
+/// like `/helloworld.Greeter/SayHello`, all the methods of a service as
+/// `/helloworld.Greeter/*`, and all the methods as `*`.
+///
+/// ```rust
+/// use tonic::transport::server::{Policy, Rule};
+///
+/// let policy = Policy::new()
+///     .allow("/helloworld.Greeter/*", Rule::any())
+///     .allow("/admin.Admin/*", Rule::any().peer_name("spiffe://example.org/ops"))
+///     .deny("*", Rule::any().metadata("x-tenant", "suspended"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    allow: Vec<(String, Rule)>,
+    deny: Vec<(String, Rule)>,
+}
+
+impl Policy {
+    /// A policy denying all the calls.
+    pub fn new() -> Self {
+        Policy::default()
+    }
+
+    /// Allow the calls of `methods` matching `rule`.
+    pub fn allow(mut self, methods: impl Into<String>, rule: Rule) -> Self {
+        self.allow.push((methods.into(), rule));
+        self
+    }
+
+    /// Deny the calls of `methods` matching `rule`, even if allowed.
+    pub fn deny(mut self, methods: impl Into<String>, rule: Rule) -> Self {
+        self.deny.push((methods.into(), rule));
+        self
+    }
+
+    fn decide(&self, request: &AuthzRequest) -> Decision {
+        let matches = |(methods, rule): &(String, Rule)| {
+            matches_method(methods, request.method()) && rule.matches(request)
+        };
+        if self.deny.iter().any(matches) {
+            Decision::Deny("denied by policy".to_string())
+        } else if self.allow.iter().any(matches) {
+            Decision::Allow
+        } else {
+            Decision::Deny("not allowed by policy".to_string())
+        }
+    }
+}
+
+impl Authorizer for Policy {
+    fn authorize<'a>(
+        &'a self,
+        request: &'a AuthzRequest,
+    ) -> Pin<Box<dyn Future<Output = Decision> + Send + 'a>> {
+        Box::pin(futures_util::future::ready(self.decide(request)))
+    }
+}
+
+fn matches_method(methods: &str, path: &str) -> bool {
+    match methods.strip_suffix('*') {
+        Some(prefix) => path.starts_with(prefix),
+        None => methods == path,
+    }
+}
+
+/// The conditions calls match a rule of a [`Policy`] on, all of which must
+/// hold.
+///
+/// [`Policy`]: struct.Policy.html
+#[derive(Debug, Clone, Default)]
+pub struct Rule {
+    conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    PeerName(String),
+    Metadata(String, String),
+    #[cfg(feature = "jwt")]
+    Claim(String, String),
+}
+
+impl Rule {
+    /// A rule matching all the calls.
+    pub fn any() -> Self {
+        Rule::default()
+    }
+
+    /// Only match the calls whose peer certificate has `name` among its
+    /// subject alternative names.
+    pub fn peer_name(mut self, name: impl Into<String>) -> Self {
+        self.conditions.push(Condition::PeerName(name.into()));
+        self
+    }
+
+    /// Only match the calls whose metadata entry `key` is `value`.
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let condition = Condition::Metadata(key.into(), value.into());
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Only match the calls whose token has the claim `name` set to `value`,
+    /// or to an array containing it, like a role in `roles`.
+    #[cfg(feature = "jwt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+    pub fn claim(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.conditions
+            .push(Condition::Claim(name.into(), value.into()));
+        self
+    }
+
+    fn matches(&self, request: &AuthzRequest) -> bool {
+        self.conditions.iter().all(|condition| match condition {
+            Condition::PeerName(name) => request.peer_names.contains(name),
+            Condition::Metadata(key, value) => request
+                .metadata
+                .get(key.as_str())
+                .is_some_and(|v| v.as_bytes() == value.as_bytes()),
+            #[cfg(feature = "jwt")]
+            Condition::Claim(name, value) => {
+                use serde_json::Value;
+                match request.claims().and_then(|claims| claims.get(name)) {
+                    Some(Value::String(claim)) => claim == value,
+                    Some(Value::Array(claims)) => claims.iter().any(|claim| claim == value),
+                    _ => false,
+                }
+            }
+        })
+    }
+}
+
+/// Wraps services with [`Authz`], authorizing their calls.
+///
+/// [`Authz`]: struct.Authz.html
+#[derive(Clone)]
+pub struct AuthzLayer {
+    authorizer: Arc<dyn Authorizer>,
+}
+
+impl AuthzLayer {
+    /// Authorize the calls with `authorizer`.
+    pub fn new(authorizer: impl Authorizer) -> Self {
+        AuthzLayer {
+            authorizer: Arc::new(authorizer),
+        }
+    }
+}
+
+impl<S> Layer<S> for AuthzLayer {
+    type Service = Authz<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Authz {
+            inner,
+            authorizer: self.authorizer.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for AuthzLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthzLayer").finish()
+    }
+}
+
+/// A service only called with the requests its [`Authorizer`] allows,
+/// failing the other ones with `PERMISSION_DENIED`.
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`Authorizer`]: trait.Authorizer.html
+/// [`Server`]: ../struct.Server.html
+#[derive(Clone)]
+pub struct Authz<S> {
+    inner: S,
+    authorizer: Arc<dyn Authorizer>,
+}
+
+impl<S, B> Service<Request<B>> for Authz<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        // Call the service that was polled ready, leaving a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let authorizer = self.authorizer.clone();
+
+        Box::pin(async move {
+            let authz = AuthzRequest::new(&request);
+            match authorizer.authorize(&authz).await {
+                Decision::Allow => inner.call(request).await,
+                Decision::Deny(message) => Ok(Status::permission_denied(message).to_http()),
+            }
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for Authz<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S: fmt::Debug> fmt::Debug for Authz<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Authz").field("inner", &self.inner).finish()
+    }
+}
+
+/// Read a DER value, as its tag, its contents and the bytes following it.
+fn read_der(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&len, mut input) = input.split_first()?;
+    let len = if len < 0x80 {
+        len as usize
+    } else {
+        let octets = (len & 0x7f) as usize;
+        if octets == 0 || octets > 4 || input.len() < octets {
+            return None;
+        }
+        let len = input[..octets]
+            .iter()
+            .fold(0, |len, &octet| (len << 8) | octet as usize);
+        input = &input[octets..];
+        len
+    };
+    if input.len() < len {
+        return None;
+    }
+    Some((tag, &input[..len], &input[len..]))
+}
+
+/// The DNS names and URIs in the subject alternative names of a DER
+/// encoded certificate.
+fn subject_alt_names(cert: &[u8]) -> Option<Vec<String>> {
+    const SEQUENCE: u8 = 0x30;
+    const EXTENSIONS: u8 = 0xa3;
+    const BOOLEAN: u8 = 0x01;
+    const DNS_NAME: u8 = 0x82;
+    const URI: u8 = 0x86;
+
+    let (_, cert, _) = read_der(cert).filter(|(tag, ..)| *tag == SEQUENCE)?;
+    let (_, mut tbs, _) = read_der(cert).filter(|(tag, ..)| *tag == SEQUENCE)?;
+
+    let mut extensions = None;
+    while !tbs.is_empty() {
+        let (tag, value, rest) = read_der(tbs)?;
+        if tag == EXTENSIONS {
+            extensions = Some(read_der(value)?.1);
+        }
+        tbs = rest;
+    }
+
+    let mut names = Vec::new();
+    let mut extensions = extensions?;
+    while !extensions.is_empty() {
+        let (_, extension, rest) = read_der(extensions)?;
+        extensions = rest;
+        let (_, oid, mut extension) = read_der(extension)?;
+        if oid != SUBJECT_ALT_NAME {
+            continue;
+        }
+        let (tag, _, rest) = read_der(extension)?;
+        if tag == BOOLEAN {
+            extension = rest;
+        }
+        let (_, value, _) = read_der(extension)?;
+        let (_, mut general_names, _) = read_der(value)?;
+        while !general_names.is_empty() {
+            let (tag, name, rest) = read_der(general_names)?;
+            if tag == DNS_NAME || tag == URI {
+                if let Ok(name) = std::str::from_utf8(name) {
+                    names.push(name.to_string());
+                }
+            }
+            general_names = rest;
+        }
+    }
+    Some(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(path: &str, tenant: Option<&str>, peer_names: &[&str]) -> AuthzRequest {
+        let mut metadata = MetadataMap::new();
+        if let Some(tenant) = tenant {
+            metadata.insert("x-tenant", tenant.parse().unwrap());
+        }
+        AuthzRequest {
+            method: path.to_string(),
+            metadata,
+            remote_addr: None,
+            peer_names: peer_names.iter().map(|name| name.to_string()).collect(),
+            #[cfg(feature = "jwt")]
+            claims: None,
+        }
+    }
+
+    #[test]
+    fn denies_before_allowing() {
+        let policy = Policy::new()
+            .allow("/test.Test/*", Rule::any())
+            .allow(
+                "/admin.Admin/Reset",
+                Rule::any().peer_name("ops.example.com"),
+            )
+            .deny("*", Rule::any().metadata("x-tenant", "suspended"));
+
+        let allowed = |path, tenant, peer_names| {
+            policy.decide(&request(path, tenant, peer_names)) == Decision::Allow
+        };
+        assert!(allowed("/test.Test/Echo", None, &[]));
+        assert!(allowed("/test.Test/Echo", Some("acme"), &[]));
+        assert!(!allowed("/test.Test/Echo", Some("suspended"), &[]));
+        assert!(!allowed("/admin.Admin/Reset", None, &[]));
+        assert!(allowed("/admin.Admin/Reset", None, &["ops.example.com"]));
+        assert!(!allowed("/admin.Admin/Other", None, &["ops.example.com"]));
+        assert!(!allowed("/test.TestOther/Echo", None, &[]));
+    }
+
+    #[test]
+    fn reads_the_names_of_certificates() {
+        let pem = include_str!("../../../../examples/data/tls/server.pem")
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect::<String>();
+        let der = base64::decode(&pem).unwrap();
+        assert_eq!(
+            subject_alt_names(&der).unwrap(),
+            vec!["example.com", "*.example.com", "example.test", "localhost"]
+        );
+        assert_eq!(subject_alt_names(&der[..100]), None);
+    }
+}
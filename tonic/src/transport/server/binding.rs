@@ -0,0 +1,131 @@
+#[cfg(feature = "tls")]
+use super::ServerTlsConfig;
+use super::{incoming::TcpIncoming, Server};
+#[cfg(feature = "tls")]
+use crate::transport::service::TlsAcceptor;
+use std::{
+    fmt,
+    net::{SocketAddr, TcpListener as StdTcpListener},
+    time::Duration,
+};
+
+/// A socket for a [`Server`] to accept connections on, together with the
+/// TLS and timeout settings used for those connections.
+///
+/// A single [`Router`] can serve several bindings at once with
+/// [`Router::serve_bindings`], sharing its services and shutdown signal
+/// between them. This allows, for example, requiring mTLS on a public port
+/// while exposing a plaintext port on localhost for a sidecar.
+///
+/// Connections accepted on a binding are plaintext unless
+/// [`Binding::tls_config`] is set on it; the server wide
+/// [`Server::tls_config`] does not apply to bindings. Requests use the
+/// [`Server::timeout`] unless [`Binding::timeout`] is set.
+///
+/// # Example
+///
+/// ```no_run
+/// # use tonic::transport::server::Binding;
+/// let public = Binding::new("[::]:50051".parse().unwrap());
+/// let local = Binding::new("127.0.0.1:50052".parse().unwrap());
+/// ```
+///
+/// [`Server`]: struct.Server.html
+/// [`Router`]: struct.Router.html
+/// [`Router::serve_bindings`]: struct.Router.html#method.serve_bindings
+/// [`Binding::tls_config`]: struct.Binding.html#method.tls_config
+/// [`Server::tls_config`]: struct.Server.html#method.tls_config
+/// [`Server::timeout`]: struct.Server.html#method.timeout
+/// [`Binding::timeout`]: struct.Binding.html#method.timeout
+pub struct Binding {
+    socket: Socket,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsAcceptor>,
+    timeout: Option<Duration>,
+}
+
+enum Socket {
+    Addr(SocketAddr),
+    Listener(StdTcpListener),
+}
+
+impl Binding {
+    /// Create a plaintext binding that will listen on `addr`.
+    pub fn new(addr: SocketAddr) -> Self {
+        Binding {
+            socket: Socket::Addr(addr),
+            #[cfg(feature = "tls")]
+            tls: None,
+            timeout: None,
+        }
+    }
+
+    /// Create a plaintext binding from an already bound
+    /// [`std::net::TcpListener`].
+    ///
+    /// [`std::net::TcpListener`]: https://doc.rust-lang.org/std/net/struct.TcpListener.html
+    pub fn from_listener(listener: StdTcpListener) -> Self {
+        Binding {
+            socket: Socket::Listener(listener),
+            #[cfg(feature = "tls")]
+            tls: None,
+            timeout: None,
+        }
+    }
+
+    /// Configure TLS for connections accepted on this binding.
+    #[cfg(feature = "tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    pub fn tls_config(self, tls_config: ServerTlsConfig) -> Self {
+        Binding {
+            tls: Some(tls_config.tls_acceptor().unwrap()),
+            ..self
+        }
+    }
+
+    /// Set a timeout on the request handlers of connections accepted on this
+    /// binding, instead of the server wide one.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Binding {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Bind the socket, returning the incoming stream and a copy of `server`
+    /// carrying this binding's TLS and timeout settings.
+    pub(crate) fn into_incoming(
+        self,
+        server: &Server,
+    ) -> Result<(TcpIncoming, Server), crate::Error> {
+        let incoming = match self.socket {
+            Socket::Addr(addr) => TcpIncoming::new(addr, server)?,
+            Socket::Listener(listener) => TcpIncoming::from_std(listener, server)?,
+        };
+
+        let mut server = server.clone();
+        #[cfg(feature = "tls")]
+        {
+            server.tls = self.tls;
+        }
+        if let Some(timeout) = self.timeout {
+            server.timeout = Some(timeout);
+        }
+
+        Ok((incoming, server))
+    }
+}
+
+impl fmt::Debug for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("Binding");
+        match &self.socket {
+            Socket::Addr(addr) => f.field("addr", addr),
+            Socket::Listener(listener) => f.field("listener", listener),
+        };
+        #[cfg(feature = "tls")]
+        f.field("tls", &self.tls.is_some());
+        f.field("timeout", &self.timeout);
+        f.finish()
+    }
+}
@@ -0,0 +1,526 @@
+use super::{idempotency::Cached, NamedService};
+use crate::{body::BoxBody, codec::ACCEPT_ENCODING_HEADER, Code, Status};
+use bytes::{Bytes, BytesMut};
+use futures_util::{stream, StreamExt};
+use http::{HeaderMap, HeaderValue, Request, Response};
+use http_body::Body as HttpBody;
+use hyper::Body;
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{layer::Layer, Service};
+
+const DEFAULT_HEADER: &str = "cache-control";
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+const DEFAULT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// Wraps services with [`ResponseCache`], answering the calls of some of
+/// their methods with the responses of the previous calls with the same
+/// request.
+///
+/// No method is cached until added, the methods added being read-only
+/// unary ones.
+///
+/// The cached responses are shared by all the callers of a method, whoever
+/// they are: the cached methods must not answer depending on the caller,
+/// like on its `authorization` or tenant, unless the metadata entries they
+/// depend on are added to the cache key with [`vary`].
+///
+/// ```rust
+/// use std::time::Duration;
+/// use tonic::transport::server::ResponseCacheLayer;
+///
+/// let cache = ResponseCacheLayer::new(Duration::from_secs(60))
+///     .method("/catalog.Catalog/GetProduct")
+///     .vary("x-tenant-id")
+///     .max_bytes(16 * 1024 * 1024);
+/// ```
+///
+/// [`vary`]: #method.vary
+///
+/// [`ResponseCache`]: struct.ResponseCache.html
+#[derive(Debug, Clone)]
+pub struct ResponseCacheLayer {
+    ttl: Duration,
+    header: String,
+    methods: Vec<String>,
+    vary: Vec<String>,
+    max_entries: usize,
+    max_bytes: usize,
+}
+
+impl ResponseCacheLayer {
+    /// Keep the responses for `ttl`, unless they set a shorter `max-age`.
+    pub fn new(ttl: Duration) -> Self {
+        ResponseCacheLayer {
+            ttl,
+            header: DEFAULT_HEADER.to_string(),
+            methods: Vec::new(),
+            vary: Vec::new(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+
+    /// Cache the responses of the method at `path`, like
+    /// `/helloworld.Greeter/SayHello`.
+    pub fn method(mut self, path: impl Into<String>) -> Self {
+        self.methods.push(path.into());
+        self
+    }
+
+    /// Only share the responses between the calls with the same value of
+    /// the metadata entry `key`, like `authorization`, or without it, along
+    /// with the other keys added.
+    pub fn vary(mut self, key: impl Into<String>) -> Self {
+        self.vary.push(key.into());
+        self
+    }
+
+    /// Read the cache directives of the calls and of their responses from
+    /// the metadata entry `header`, by default `cache-control`.
+    pub fn header(self, header: impl Into<String>) -> Self {
+        ResponseCacheLayer {
+            header: header.into(),
+            ..self
+        }
+    }
+
+    /// Keep up to `max_entries` responses, by default 10000.
+    pub fn max_entries(self, max_entries: usize) -> Self {
+        ResponseCacheLayer {
+            max_entries,
+            ..self
+        }
+    }
+
+    /// Keep up to `max_bytes` of requests and responses, by default 64MiB.
+    ///
+    /// The larger requests are passed on as they are, without being read
+    /// first.
+    pub fn max_bytes(self, max_bytes: usize) -> Self {
+        ResponseCacheLayer { max_bytes, ..self }
+    }
+}
+
+impl<S> Layer<S> for ResponseCacheLayer {
+    type Service = ResponseCache<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ResponseCache {
+            inner,
+            store: Arc::new(Store::new(self.clone())),
+        }
+    }
+}
+
+/// A service answering the calls of some methods with the response of a
+/// previous call of the same method with the same request, without calling
+/// the service again.
+///
+/// Only the responses of the calls that succeeded are kept, until their
+/// time to live expires, or they are evicted to make room for others.
+/// Responses are only shared by the calls accepting the same compression
+/// encodings, and with the same values of the metadata entries the cache
+/// varies on.
+///
+/// The calls and their responses may carry cache directives, in a
+/// `cache-control` metadata entry by default:
+///
+/// - `no-cache` on a call skips the cached responses, the response of the
+///   call still being kept.
+/// - `no-store` on a call skips the cache entirely, and on a response
+///   keeps it from being cached.
+/// - `max-age=<seconds>` on a call only accepts the responses cached for
+///   at most that long, and on a response keeps it for at most that long.
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`Server`]: ../struct.Server.html
+#[derive(Debug, Clone)]
+pub struct ResponseCache<S> {
+    inner: S,
+    store: Arc<Store>,
+}
+
+impl<S> Service<Request<Body>> for ResponseCache<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let path = request.uri().path();
+        if !self.store.methods.iter().any(|method| method == path) {
+            return Box::pin(self.inner.call(request));
+        }
+        let directives = Directives::read(request.headers().get(self.store.header.as_str()));
+        if directives.no_store {
+            return Box::pin(self.inner.call(request));
+        }
+
+        // Call the service that was polled ready, leaving a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let store = self.store.clone();
+
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let message = match read(body, store.max_bytes).await {
+                Ok(Read::Whole(message)) => message,
+                Ok(Read::TooLarge(body)) => {
+                    return inner.call(Request::from_parts(parts, body)).await
+                }
+                Err(err) => return Ok(Status::from_error(Box::new(err)).to_http()),
+            };
+            let key = Key {
+                path: parts.uri.path().to_string(),
+                accept_encoding: parts.headers.get(ACCEPT_ENCODING_HEADER).cloned(),
+                vary: store.vary(&parts.headers),
+                message: message.clone(),
+            };
+            if !directives.no_cache {
+                if let Some(cached) = store.get(&key, directives.max_age, Instant::now()) {
+                    return Ok(cached.replay());
+                }
+            }
+
+            let request = Request::from_parts(parts, Body::from(message));
+            let response = inner.call(request).await?;
+            let directives = Directives::read(response.headers().get(store.header.as_str()));
+            if directives.no_store {
+                return Ok(response);
+            }
+            let cached = match Cached::read(response).await {
+                Ok(cached) => cached,
+                Err(status) => return Ok(status.to_http()),
+            };
+            let response = cached.replay();
+            if cached.code() == Code::Ok {
+                let ttl = directives
+                    .max_age
+                    .map_or(store.ttl, |age| age.min(store.ttl));
+                store.insert(key, cached, ttl, Instant::now());
+            }
+            Ok(response)
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for ResponseCache<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// A request body, read whole unless too large to be cached.
+enum Read {
+    Whole(Bytes),
+    /// The body, with the bytes read from it put back in front.
+    TooLarge(Body),
+}
+
+/// Read `body` whole if it has at most `limit` bytes.
+async fn read(mut body: Body, limit: usize) -> Result<Read, hyper::Error> {
+    if body.size_hint().lower() > limit as u64 {
+        return Ok(Read::TooLarge(body));
+    }
+
+    let mut chunks = Vec::new();
+    let mut len = 0;
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        len += chunk.len();
+        chunks.push(chunk);
+        if len > limit {
+            let read = stream::iter(chunks.into_iter().map(Ok));
+            return Ok(Read::TooLarge(Body::wrap_stream(read.chain(body))));
+        }
+    }
+
+    let mut message = BytesMut::with_capacity(len);
+    for chunk in chunks {
+        message.extend_from_slice(&chunk);
+    }
+    Ok(Read::Whole(message.freeze()))
+}
+
+/// The cache directives of a call or of its response.
+#[derive(Debug, Default, PartialEq)]
+struct Directives {
+    no_cache: bool,
+    no_store: bool,
+    max_age: Option<Duration>,
+}
+
+impl Directives {
+    fn read(value: Option<&HeaderValue>) -> Self {
+        let mut directives = Directives::default();
+        let value = match value.and_then(|value| value.to_str().ok()) {
+            Some(value) => value,
+            None => return directives,
+        };
+        for directive in value.split(',').map(str::trim) {
+            match directive.split_once('=') {
+                Some(("max-age", seconds)) => {
+                    directives.max_age = seconds.parse().ok().map(Duration::from_secs)
+                }
+                None if directive == "no-cache" => directives.no_cache = true,
+                None if directive == "no-store" => directives.no_store = true,
+                _ => {}
+            }
+        }
+        directives
+    }
+}
+
+/// The responses of the calls, by method and request.
+struct Store {
+    ttl: Duration,
+    header: String,
+    methods: Vec<String>,
+    vary: Vec<String>,
+    max_entries: usize,
+    max_bytes: usize,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    path: String,
+    accept_encoding: Option<HeaderValue>,
+    /// The values of the metadata entries the cache varies on.
+    vary: Vec<Option<HeaderValue>>,
+    message: Bytes,
+}
+
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<Key, Entry>,
+    bytes: usize,
+}
+
+struct Entry {
+    stored_at: Instant,
+    expires_at: Instant,
+    response: Arc<Cached>,
+}
+
+impl Entry {
+    fn size(&self, key: &Key) -> usize {
+        let vary = key
+            .vary
+            .iter()
+            .flatten()
+            .map(HeaderValue::len)
+            .sum::<usize>();
+        key.message.len() + vary + self.response.size()
+    }
+}
+
+impl Store {
+    fn new(layer: ResponseCacheLayer) -> Self {
+        Store {
+            ttl: layer.ttl,
+            header: layer.header,
+            methods: layer.methods,
+            vary: layer.vary,
+            max_entries: layer.max_entries,
+            max_bytes: layer.max_bytes,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// The values in `headers` of the metadata entries the cache varies on.
+    fn vary(&self, headers: &HeaderMap) -> Vec<Option<HeaderValue>> {
+        self.vary
+            .iter()
+            .map(|key| headers.get(key.as_str()).cloned())
+            .collect()
+    }
+
+    /// The response cached for `key`, if it is no older than `max_age`.
+    fn get(&self, key: &Key, max_age: Option<Duration>, now: Instant) -> Option<Arc<Cached>> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.entries.get(key)?;
+        let fresh = max_age.is_none_or(|age| now.saturating_duration_since(entry.stored_at) <= age);
+        if entry.expires_at > now && fresh {
+            Some(entry.response.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Keep `response` for `ttl`, evicting the responses expiring first
+    /// when full.
+    fn insert(&self, key: Key, response: Cached, ttl: Duration, now: Instant) {
+        let entry = Entry {
+            stored_at: now,
+            expires_at: now + ttl,
+            response: Arc::new(response),
+        };
+        let size = entry.size(&key);
+        if size > self.max_bytes || ttl == Duration::from_secs(0) {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(&key);
+        let full = |entries: &Entries| {
+            entries.entries.len() >= self.max_entries || entries.bytes + size > self.max_bytes
+        };
+        if full(&entries) {
+            let expired = entries
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.expires_at <= now)
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+            for key in &expired {
+                entries.remove(key);
+            }
+        }
+        while full(&entries) {
+            let first = entries
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone());
+            match first {
+                Some(key) => entries.remove(&key),
+                None => return,
+            }
+        }
+        entries.bytes += size;
+        entries.entries.insert(key, entry);
+    }
+}
+
+impl Entries {
+    fn remove(&mut self, key: &Key) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry.size(key);
+        }
+    }
+}
+
+impl fmt::Debug for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Store")
+            .field("ttl", &self.ttl)
+            .field("header", &self.header)
+            .field("methods", &self.methods)
+            .field("vary", &self.vary)
+            .field("max_entries", &self.max_entries)
+            .field("max_bytes", &self.max_bytes)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(message: &'static [u8]) -> Key {
+        Key {
+            path: "/test.Test/Echo".to_string(),
+            accept_encoding: None,
+            vary: Vec::new(),
+            message: Bytes::from_static(message),
+        }
+    }
+
+    async fn response() -> Cached {
+        Cached::read(Response::new(BoxBody::empty())).await.unwrap()
+    }
+
+    #[test]
+    fn reads_the_directives() {
+        let read = |value| Directives::read(Some(&HeaderValue::from_static(value)));
+
+        assert_eq!(Directives::read(None), Directives::default());
+        assert_eq!(
+            read("no-cache, max-age=30"),
+            Directives {
+                no_cache: true,
+                no_store: false,
+                max_age: Some(Duration::from_secs(30)),
+            }
+        );
+        assert!(read("private,no-store").no_store);
+        assert_eq!(read("max-age=soon").max_age, None);
+    }
+
+    #[tokio::test]
+    async fn reads_the_bodies_up_to_the_limit() {
+        let chunked = || {
+            let chunks = ["ab", "cd", "ef"]
+                .iter()
+                .map(|chunk| Ok::<_, hyper::Error>(*chunk));
+            Body::wrap_stream(stream::iter(chunks))
+        };
+        let whole = |read| match read {
+            Read::Whole(message) => message,
+            Read::TooLarge(_) => panic!("read a body too large"),
+        };
+        let too_large = |read| async move {
+            match read {
+                Read::Whole(message) => panic!("read {:?} whole", message),
+                Read::TooLarge(body) => hyper::body::to_bytes(body).await.unwrap(),
+            }
+        };
+
+        assert_eq!(whole(read(chunked(), 6).await.unwrap()), "abcdef");
+        assert_eq!(too_large(read(chunked(), 3).await.unwrap()).await, "abcdef");
+        assert_eq!(
+            too_large(read(Body::from("abcdef"), 3).await.unwrap()).await,
+            "abcdef"
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_the_responses_until_they_expire() {
+        let store = Store::new(ResponseCacheLayer::new(Duration::from_secs(60)));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        store.insert(key(b"a"), response().await, store.ttl, at(0));
+        assert!(store.get(&key(b"a"), None, at(59)).is_some());
+        assert!(store.get(&key(b"b"), None, at(59)).is_none());
+        assert!(store.get(&key(b"a"), None, at(60)).is_none());
+
+        let max_age = Some(Duration::from_secs(10));
+        assert!(store.get(&key(b"a"), max_age, at(10)).is_some());
+        assert!(store.get(&key(b"a"), max_age, at(11)).is_none());
+    }
+
+    #[tokio::test]
+    async fn evicts_the_responses_expiring_first_when_full() {
+        let store = Store::new(ResponseCacheLayer::new(Duration::from_secs(60)).max_bytes(4));
+        let now = Instant::now();
+
+        store.insert(key(b"ab"), response().await, Duration::from_secs(30), now);
+        store.insert(key(b"cd"), response().await, Duration::from_secs(10), now);
+        store.insert(key(b"ef"), response().await, Duration::from_secs(20), now);
+        assert!(store.get(&key(b"ab"), None, now).is_some());
+        assert!(store.get(&key(b"cd"), None, now).is_none());
+        assert!(store.get(&key(b"ef"), None, now).is_some());
+
+        // Too large to ever be kept.
+        store.insert(key(b"large"), response().await, store.ttl, now);
+        assert!(store.get(&key(b"large"), None, now).is_none());
+        assert_eq!(store.entries.lock().unwrap().bytes, 4);
+    }
+}
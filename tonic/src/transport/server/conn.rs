@@ -2,6 +2,8 @@ use crate::transport::Certificate;
 use hyper::server::conn::AddrStream;
 use std::net::SocketAddr;
 use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 #[cfg(feature = "tls")]
 use tokio_rustls::{rustls::Session, server::TlsStream};
 
@@ -20,6 +22,55 @@ pub trait Connected {
     fn peer_certs(&self) -> Option<Vec<Certificate>> {
         None
     }
+
+    /// Return the credentials of the process on the other end of a Unix
+    /// domain socket.
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    fn peer_cred(&self) -> Option<PeerCred> {
+        None
+    }
+}
+
+/// The OS identity of the peer process connected over a Unix domain socket.
+///
+/// This is read from the socket with `SO_PEERCRED` on Linux and the
+/// equivalent `LOCAL_PEERCRED`/`getpeereid` call on other Unix platforms.
+/// The values reflect the peer at the time it called `connect`.
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCred {
+    uid: u32,
+    gid: u32,
+    pid: Option<i32>,
+}
+
+#[cfg(unix)]
+impl PeerCred {
+    /// Create a new `PeerCred` from its raw parts.
+    ///
+    /// This is mostly useful for custom [`Connected`] implementations.
+    ///
+    /// [`Connected`]: trait.Connected.html
+    pub fn new(uid: u32, gid: u32, pid: Option<i32>) -> Self {
+        PeerCred { uid, gid, pid }
+    }
+
+    /// The effective user id of the peer process.
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// The effective group id of the peer process.
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// The process id of the peer, on platforms that report it.
+    pub fn pid(&self) -> Option<i32> {
+        self.pid
+    }
 }
 
 impl Connected for AddrStream {
@@ -34,6 +85,49 @@ impl Connected for TcpStream {
     }
 }
 
+#[cfg(unix)]
+impl Connected for UnixStream {
+    fn peer_cred(&self) -> Option<PeerCred> {
+        unix_peer_cred(self)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn unix_peer_cred(stream: &UnixStream) -> Option<PeerCred> {
+    use std::{mem, os::unix::io::AsRawFd};
+
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = mem::size_of::<libc::ucred>() as libc::socklen_t;
+
+    // SAFETY: `cred` and `len` are valid for writes and `len` holds the size
+    // of `cred`, as `getsockopt` requires.
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+
+    if ret == 0 && len as usize == mem::size_of::<libc::ucred>() {
+        Some(PeerCred::new(cred.uid, cred.gid, Some(cred.pid)))
+    } else {
+        None
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn unix_peer_cred(stream: &UnixStream) -> Option<PeerCred> {
+    let cred = stream.peer_cred().ok()?;
+    Some(PeerCred::new(cred.uid, cred.gid, None))
+}
+
 #[cfg(feature = "tls")]
 impl<T: Connected> Connected for TlsStream<T> {
     fn remote_addr(&self) -> Option<SocketAddr> {
@@ -41,6 +135,12 @@ impl<T: Connected> Connected for TlsStream<T> {
         inner.remote_addr()
     }
 
+    #[cfg(unix)]
+    fn peer_cred(&self) -> Option<PeerCred> {
+        let (inner, _) = self.get_ref();
+        inner.peer_cred()
+    }
+
     fn peer_certs(&self) -> Option<Vec<Certificate>> {
         let (_, session) = self.get_ref();
 
@@ -55,3 +155,19 @@ impl<T: Connected> Connected for TlsStream<T> {
         }
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unix_stream_peer_cred() {
+        let (a, _b) = UnixStream::pair().unwrap();
+        let cred = Connected::peer_cred(&a).expect("socketpair should report credentials");
+
+        assert_eq!(cred.uid(), unsafe { libc::geteuid() });
+        assert_eq!(cred.gid(), unsafe { libc::getegid() });
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        assert_eq!(cred.pid(), Some(std::process::id() as i32));
+    }
+}
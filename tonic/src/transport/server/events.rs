@@ -0,0 +1,47 @@
+use super::ConnectionStats;
+use std::{error::Error as StdError, io, net::SocketAddr, sync::Arc};
+
+pub(crate) type ConnectionHook = Arc<dyn Fn(&ConnectionEvent<'_>) + Send + Sync + 'static>;
+
+/// An event in the lifecycle of a connection accepted by a [`Server`].
+///
+/// Events are delivered to the callback registered with
+/// [`Server::on_connection_event`]. For every connection an `Accepted` event
+/// is followed by either `HandshakeFailed` or `Established`, and every
+/// established connection eventually reports `Closed`.
+///
+/// [`Server`]: struct.Server.html
+/// [`Server::on_connection_event`]: struct.Server.html#method.on_connection_event
+#[derive(Debug)]
+pub enum ConnectionEvent<'a> {
+    /// A new connection was accepted from the listener.
+    Accepted {
+        /// The address of the peer, if known.
+        remote_addr: Option<SocketAddr>,
+    },
+    /// The TLS handshake failed and the connection was dropped.
+    HandshakeFailed {
+        /// The address of the peer, if known.
+        remote_addr: Option<SocketAddr>,
+        /// The reason the handshake failed.
+        error: &'a (dyn StdError + Send + Sync + 'static),
+    },
+    /// The connection is ready to serve HTTP/2, after completing the TLS
+    /// handshake when TLS is enabled.
+    Established {
+        /// The address of the peer, if known.
+        remote_addr: Option<SocketAddr>,
+        /// Whether the connection is secured with TLS.
+        tls: bool,
+    },
+    /// The connection was closed.
+    Closed {
+        /// The address of the peer, if known.
+        remote_addr: Option<SocketAddr>,
+        /// The IO error that ended the connection, or `None` if it was closed
+        /// cleanly by either side.
+        error: Option<&'a io::Error>,
+        /// Statistics collected over the lifetime of the connection.
+        stats: &'a ConnectionStats,
+    },
+}
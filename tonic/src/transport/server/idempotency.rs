@@ -0,0 +1,485 @@
+use super::NamedService;
+use crate::{body::BoxBody, Code, Status};
+use bytes::{Bytes, BytesMut};
+use futures_util::future;
+use http::{HeaderMap, HeaderValue, Request, Response, StatusCode};
+use http_body::Body as HttpBody;
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{layer::Layer, Service};
+
+const DEFAULT_HEADER: &str = "idempotency-key";
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+const REPLAYED_HEADER: &str = "idempotency-replayed";
+/// The codes of the calls whose responses are cached, the others failing
+/// for reasons a retry may fix.
+const CACHED_CODES: &[Code] = &[
+    Code::Ok,
+    Code::InvalidArgument,
+    Code::NotFound,
+    Code::AlreadyExists,
+    Code::FailedPrecondition,
+    Code::OutOfRange,
+    Code::Unimplemented,
+];
+
+/// Wraps services with [`Idempotency`], answering the retries of their calls
+/// with the response of the first one.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use tonic::transport::server::IdempotencyLayer;
+///
+/// let idempotency = IdempotencyLayer::new(Duration::from_secs(24 * 60 * 60))
+///     .method("/payments.Payments/Charge")
+///     .method("/payments.Payments/Refund");
+/// ```
+///
+/// [`Idempotency`]: struct.Idempotency.html
+#[derive(Debug, Clone)]
+pub struct IdempotencyLayer {
+    ttl: Duration,
+    header: String,
+    methods: Vec<String>,
+    max_entries: usize,
+}
+
+impl IdempotencyLayer {
+    /// Keep the responses for `ttl` after their call completed.
+    pub fn new(ttl: Duration) -> Self {
+        IdempotencyLayer {
+            ttl,
+            header: DEFAULT_HEADER.to_string(),
+            methods: Vec::new(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+
+    /// Read the keys of the calls from the metadata entry `header`, by
+    /// default `idempotency-key`.
+    pub fn header(self, header: impl Into<String>) -> Self {
+        IdempotencyLayer {
+            header: header.into(),
+            ..self
+        }
+    }
+
+    /// Only deduplicate the calls of the method at `path`, like
+    /// `/helloworld.Greeter/SayHello`, and of the other methods added, by
+    /// default the calls of all the methods.
+    pub fn method(mut self, path: impl Into<String>) -> Self {
+        self.methods.push(path.into());
+        self
+    }
+
+    /// Keep up to `max_entries` responses, by default 10000, the calls made
+    /// while the cache is full of live ones not being deduplicated.
+    pub fn max_entries(self, max_entries: usize) -> Self {
+        IdempotencyLayer {
+            max_entries,
+            ..self
+        }
+    }
+}
+
+impl<S> Layer<S> for IdempotencyLayer {
+    type Service = Idempotency<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Idempotency {
+            inner,
+            cache: Arc::new(Cache {
+                ttl: self.ttl,
+                header: self.header.clone(),
+                methods: self.methods.clone(),
+                max_entries: self.max_entries,
+                entries: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+}
+
+/// A service answering the calls carrying the idempotency key of a previous
+/// call of the same method with its response, without calling the service
+/// again.
+///
+/// The responses are buffered and kept until their time to live expires,
+/// making it a fit for unary methods. Only the responses of the calls that
+/// succeeded, or failed with `INVALID_ARGUMENT`, `NOT_FOUND`,
+/// `ALREADY_EXISTS`, `FAILED_PRECONDITION`, `OUT_OF_RANGE` or
+/// `UNIMPLEMENTED`, are kept, as a retry may succeed where the others
+/// failed. The replayed responses carry an `idempotency-replayed` header,
+/// and the calls made while the first one is still in progress fail with
+/// `ABORTED`.
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`Server`]: ../struct.Server.html
+#[derive(Debug, Clone)]
+pub struct Idempotency<S> {
+    inner: S,
+    cache: Arc<Cache>,
+}
+
+impl<S, B> Service<Request<B>> for Idempotency<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let call = match self.cache.key(&request) {
+            Some(key) => self.cache.begin(key, Instant::now()),
+            None => return Box::pin(self.inner.call(request)),
+        };
+        let mut call = match call {
+            Ok(Some(call)) => call,
+            Ok(None) => return Box::pin(self.inner.call(request)),
+            Err(answer) => return Box::pin(future::ok(answer.into_response())),
+        };
+
+        // Call the service that was polled ready, leaving a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let response = inner.call(request).await?;
+            let cached = match Cached::read(response).await {
+                Ok(cached) => cached,
+                Err(status) => return Ok(status.to_http()),
+            };
+            let response = cached.replay();
+            if CACHED_CODES.contains(&cached.code()) {
+                call.complete(cached, Instant::now());
+            }
+            Ok(response)
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for Idempotency<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// The responses of the calls, by method and idempotency key.
+struct Cache {
+    ttl: Duration,
+    header: String,
+    methods: Vec<String>,
+    max_entries: usize,
+    entries: Mutex<HashMap<Key, Entry>>,
+}
+
+type Key = (String, HeaderValue);
+
+enum Entry {
+    InProgress,
+    Completed {
+        expires_at: Instant,
+        response: Arc<Cached>,
+    },
+}
+
+/// The response of a call already made.
+#[derive(Debug)]
+enum Answer {
+    InProgress,
+    Replay(Arc<Cached>),
+}
+
+impl Answer {
+    fn into_response(self) -> Response<BoxBody> {
+        match self {
+            Answer::InProgress => {
+                Status::aborted("a call with the same idempotency key is in progress").to_http()
+            }
+            Answer::Replay(cached) => {
+                let mut response = cached.replay();
+                response
+                    .headers_mut()
+                    .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+                response
+            }
+        }
+    }
+}
+
+impl Cache {
+    /// The key of `request`, if it is to be deduplicated.
+    fn key<B>(&self, request: &Request<B>) -> Option<Key> {
+        let path = request.uri().path();
+        if !self.methods.is_empty() && !self.methods.iter().any(|m| m == path) {
+            return None;
+        }
+        let key = request.headers().get(self.header.as_str())?;
+        Some((path.to_string(), key.clone()))
+    }
+
+    /// Begin the call of `key`, or the response of the call already made.
+    /// No call is begun if the cache is full.
+    fn begin(self: &Arc<Self>, key: Key, now: Instant) -> Result<Option<Call>, Answer> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&key) {
+            Some(Entry::InProgress) => return Err(Answer::InProgress),
+            Some(Entry::Completed {
+                expires_at,
+                response,
+            }) if *expires_at > now => return Err(Answer::Replay(response.clone())),
+            _ => {}
+        }
+
+        if entries.len() >= self.max_entries {
+            entries.retain(|_, entry| match entry {
+                Entry::InProgress => true,
+                Entry::Completed { expires_at, .. } => *expires_at > now,
+            });
+            if entries.len() >= self.max_entries {
+                return Ok(None);
+            }
+        }
+        entries.insert(key.clone(), Entry::InProgress);
+        Ok(Some(Call {
+            cache: self.clone(),
+            key: Some(key),
+        }))
+    }
+}
+
+impl fmt::Debug for Cache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cache")
+            .field("ttl", &self.ttl)
+            .field("header", &self.header)
+            .field("methods", &self.methods)
+            .field("max_entries", &self.max_entries)
+            .finish()
+    }
+}
+
+/// A call in progress, forgotten unless completed, so it can be retried
+/// when it failed or was cancelled.
+struct Call {
+    cache: Arc<Cache>,
+    key: Option<Key>,
+}
+
+impl Call {
+    fn complete(&mut self, response: Cached, now: Instant) {
+        let response = Arc::new(response);
+        if let Some(key) = self.key.take() {
+            let expires_at = now + self.cache.ttl;
+            let mut entries = self.cache.entries.lock().unwrap();
+            entries.insert(
+                key,
+                Entry::Completed {
+                    expires_at,
+                    response,
+                },
+            );
+        }
+    }
+}
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.cache.entries.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+/// A buffered response.
+#[derive(Debug)]
+pub(super) struct Cached {
+    status: StatusCode,
+    headers: HeaderMap,
+    data: Bytes,
+    trailers: Option<HeaderMap>,
+}
+
+impl Cached {
+    pub(super) async fn read(response: Response<BoxBody>) -> Result<Self, Status> {
+        let (parts, mut body) = response.into_parts();
+        let mut data = BytesMut::new();
+        while let Some(chunk) = future::poll_fn(|cx| Pin::new(&mut body).poll_data(cx)).await {
+            data.extend_from_slice(&chunk?);
+        }
+        let trailers = future::poll_fn(|cx| Pin::new(&mut body).poll_trailers(cx)).await?;
+
+        Ok(Cached {
+            status: parts.status,
+            headers: parts.headers,
+            data: data.freeze(),
+            trailers,
+        })
+    }
+
+    /// The code of the call, from the trailers or the headers of
+    /// trailers-only responses.
+    pub(super) fn code(&self) -> Code {
+        self.trailers
+            .as_ref()
+            .and_then(Status::from_header_map)
+            .or_else(|| Status::from_header_map(&self.headers))
+            .map_or(Code::Unknown, |status| status.code())
+    }
+
+    /// The size of the data of the response.
+    pub(super) fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    pub(super) fn replay(&self) -> Response<BoxBody> {
+        let mut response = Response::new(BoxBody::new(ReplayBody {
+            data: Some(self.data.clone()).filter(|data| !data.is_empty()),
+            trailers: self.trailers.clone(),
+        }));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+    }
+}
+
+struct ReplayBody {
+    data: Option<Bytes>,
+    trailers: Option<HeaderMap>,
+}
+
+impl HttpBody for ReplayBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn is_end_stream(&self) -> bool {
+        self.data.is_none() && self.trailers.is_none()
+    }
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Poll::Ready(self.data.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(self.trailers.take()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(layer: IdempotencyLayer) -> Arc<Cache> {
+        Arc::new(Cache {
+            ttl: layer.ttl,
+            header: layer.header,
+            methods: layer.methods,
+            max_entries: layer.max_entries,
+            entries: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn key(idempotency_key: &'static str) -> Key {
+        (
+            "/test.Test/Echo".to_string(),
+            HeaderValue::from_static(idempotency_key),
+        )
+    }
+
+    fn response(data: &'static [u8]) -> Cached {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", HeaderValue::from_static("0"));
+        Cached {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            data: Bytes::from_static(data),
+            trailers: Some(trailers),
+        }
+    }
+
+    fn code(response: Response<BoxBody>) -> Option<Code> {
+        Status::from_header_map(response.headers()).map(|status| status.code())
+    }
+
+    #[test]
+    fn reads_the_keys_of_the_calls_of_the_methods() {
+        let cache = cache(
+            IdempotencyLayer::new(Duration::from_secs(60))
+                .header("x-request-id")
+                .method("/test.Test/Echo"),
+        );
+        let request = |path: &str, key: Option<&'static str>| {
+            let mut request = Request::builder().uri(path);
+            if let Some(key) = key {
+                request = request.header("x-request-id", key);
+            }
+            request.body(()).unwrap()
+        };
+
+        assert_eq!(
+            cache.key(&request("/test.Test/Echo", Some("a"))),
+            Some(key("a"))
+        );
+        assert_eq!(cache.key(&request("/test.Test/Echo", None)), None);
+        assert_eq!(cache.key(&request("/test.Test/Other", Some("a"))), None);
+    }
+
+    #[test]
+    fn replays_the_responses_until_they_expire() {
+        let cache = cache(IdempotencyLayer::new(Duration::from_secs(60)));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let mut call = cache.begin(key("a"), at(0)).unwrap().unwrap();
+        let in_progress = cache.begin(key("a"), at(0)).err().unwrap();
+        assert_eq!(code(in_progress.into_response()), Some(Code::Aborted));
+        // Other keys are other calls.
+        assert!(cache.begin(key("b"), at(0)).unwrap().is_some());
+
+        call.complete(response(b"hello"), at(1));
+        let replayed = cache.begin(key("a"), at(60)).err().unwrap().into_response();
+        assert_eq!(replayed.headers()[REPLAYED_HEADER], "true");
+        assert!(cache.begin(key("a"), at(62)).unwrap().is_some());
+    }
+
+    #[test]
+    fn forgets_the_calls_not_completed() {
+        let cache = cache(IdempotencyLayer::new(Duration::from_secs(60)));
+        let now = Instant::now();
+
+        drop(cache.begin(key("a"), now).unwrap().unwrap());
+        assert!(cache.begin(key("a"), now).unwrap().is_some());
+    }
+
+    #[test]
+    fn does_not_deduplicate_calls_once_full() {
+        let cache = cache(IdempotencyLayer::new(Duration::from_secs(60)).max_entries(1));
+        let now = Instant::now();
+
+        let mut call = cache.begin(key("a"), now).unwrap().unwrap();
+        call.complete(response(b""), now);
+        assert!(cache.begin(key("b"), now).unwrap().is_none());
+        // Expired responses make room for new ones.
+        let later = now + Duration::from_secs(61);
+        assert!(cache.begin(key("b"), later).unwrap().is_some());
+    }
+}
@@ -1,20 +1,31 @@
-use super::{Connected, Server};
+#[cfg(feature = "tls")]
+use super::sniff::Sniffed;
+use super::{
+    events::{ConnectionEvent, ConnectionHook},
+    keepalive::{KeepalivePolicy, PingEnforcer},
+    stats::{ConnectionMonitor, ConnectionTracker},
+    Connected, Server,
+};
 use crate::transport::service::ServerIo;
+#[cfg(feature = "tls")]
+use crate::transport::service::TlsAcceptor;
 use futures_core::Stream;
-use futures_util::stream::TryStreamExt;
-use hyper::server::{
-    accept::Accept,
-    conn::{AddrIncoming, AddrStream},
-};
+use futures_util::{ready, stream::TryStreamExt};
 use std::{
-    net::SocketAddr,
+    future::Future,
+    io,
+    net::{SocketAddr, TcpListener as StdTcpListener},
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
     time::Duration,
 };
-use tokio::io::{AsyncRead, AsyncWrite};
-#[cfg(feature = "tls")]
-use tracing::error;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+    time::Delay,
+};
+use tracing::{debug, error, trace};
 
 #[cfg_attr(not(feature = "tls"), allow(unused_variables))]
 pub(crate) fn tcp_incoming<IO, IE>(
@@ -25,51 +36,284 @@ where
     IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
     IE: Into<crate::Error>,
 {
+    let keepalive = server.keepalive_policy();
+    let hook = server.connection_hook();
+    let tracker = server.connection_tracker.clone();
+    let timeout = server.timeout;
+
     async_stream::try_stream! {
         futures_util::pin_mut!(incoming);
 
         while let Some(stream) = incoming.try_next().await? {
+            let remote_addr = stream.remote_addr();
+            if let Some(hook) = &hook {
+                hook(&ConnectionEvent::Accepted { remote_addr });
+            }
+
             #[cfg(feature = "tls")]
             {
                 if let Some(tls) = &server.tls {
-                    let io = match tls.accept(stream).await {
-                        Ok(io) => io,
-                        Err(error) => {
-                            error!(message = "Unable to accept incoming connection.", %error);
-                            continue
-                        },
+                    let io = if server.tls_accept_plaintext {
+                        match Sniffed::new(stream).await {
+                            Ok(stream) if !stream.is_tls() => {
+                                Some(server_io(stream, false, &keepalive))
+                            }
+                            Ok(stream) => accept_tls(tls, stream, &hook, &keepalive).await,
+                            Err(error) => {
+                                debug!(message = "Unable to sniff incoming connection.", %error);
+                                handshake_failed(&hook, remote_addr, error.into());
+                                None
+                            }
+                        }
+                    } else {
+                        accept_tls(tls, stream, &hook, &keepalive).await
                     };
-                    yield ServerIo::new(io);
+
+                    if let Some(io) = io {
+                        yield established(io, remote_addr, &hook, &tracker).with_timeout(timeout);
+                    }
                     continue;
                 }
             }
 
-            yield ServerIo::new(stream);
+            let io = server_io(stream, false, &keepalive);
+            yield established(io, remote_addr, &hook, &tracker).with_timeout(timeout);
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+async fn accept_tls<IO>(
+    tls: &TlsAcceptor,
+    stream: IO,
+    hook: &Option<ConnectionHook>,
+    keepalive: &Option<KeepalivePolicy>,
+) -> Option<ServerIo>
+where
+    IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
+{
+    let remote_addr = stream.remote_addr();
+
+    match tls.accept(stream).await {
+        Ok(io) => Some(server_io(io, true, keepalive)),
+        Err(error) => {
+            error!(message = "Unable to accept incoming connection.", %error);
+            handshake_failed(hook, remote_addr, error);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+fn handshake_failed(
+    hook: &Option<ConnectionHook>,
+    remote_addr: Option<SocketAddr>,
+    error: crate::Error,
+) {
+    if let Some(hook) = hook {
+        hook(&ConnectionEvent::HandshakeFailed {
+            remote_addr,
+            error: &*error,
+        });
+    }
+}
+
+#[cfg_attr(not(feature = "tls"), allow(unused_variables))]
+fn server_io<IO>(io: IO, tls: bool, keepalive: &Option<KeepalivePolicy>) -> ServerIo
+where
+    IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
+{
+    #[cfg(feature = "h2-trace")]
+    let io = crate::transport::service::FrameTraceIo::server(io);
+
+    #[cfg(feature = "tls")]
+    {
+        if tls {
+            return match keepalive {
+                Some(policy) => ServerIo::new_tls(PingEnforcer::new(io, policy.clone())),
+                None => ServerIo::new_tls(io),
+            };
+        }
+    }
+
+    match keepalive {
+        Some(policy) => ServerIo::new(PingEnforcer::new(io, policy.clone())),
+        None => ServerIo::new(io),
+    }
+}
+
+fn established(
+    io: ServerIo,
+    remote_addr: Option<SocketAddr>,
+    hook: &Option<ConnectionHook>,
+    tracker: &Option<ConnectionTracker>,
+) -> ServerIo {
+    if let Some(hook) = hook {
+        hook(&ConnectionEvent::Established {
+            remote_addr,
+            tls: io.is_tls(),
+        });
+    }
+
+    if hook.is_some() || tracker.is_some() {
+        io.with_monitor(ConnectionMonitor::new(
+            remote_addr,
+            hook.clone(),
+            tracker.clone(),
+        ))
+    } else {
+        io
+    }
+}
+
+pub(crate) type AcceptErrorHook = Arc<dyn Fn(&io::Error) -> AcceptErrorAction + Send + Sync>;
+
+/// What a server does after it failed to accept a connection.
+///
+/// The action for each error is chosen by the callback registered with
+/// [`Server::on_accept_error`], which defaults to
+/// [`AcceptErrorAction::default_for`].
+///
+/// [`Server::on_accept_error`]: struct.Server.html#method.on_accept_error
+/// [`AcceptErrorAction::default_for`]: enum.AcceptErrorAction.html#method.default_for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcceptErrorAction {
+    /// Ignore the error and accept the next connection right away.
+    Continue,
+    /// Wait for the given duration before accepting the next connection.
+    Backoff(Duration),
+    /// Stop the server, returning the error from the serve future.
+    Abort,
+}
+
+impl AcceptErrorAction {
+    /// The action servers take when no callback was registered.
+    ///
+    /// Errors that only concern the connection being accepted, like a peer
+    /// that reset it before it was accepted, are ignored. Any other error,
+    /// like running out of file descriptors (`EMFILE`), is logged and
+    /// followed by a one second backoff, giving other connections time to
+    /// close instead of spinning on the listener.
+    pub fn default_for(e: &io::Error) -> Self {
+        if is_connection_error(e) {
+            debug!("accepted connection already errored: {}", e);
+            AcceptErrorAction::Continue
+        } else {
+            error!("accept error: {}", e);
+            AcceptErrorAction::Backoff(Duration::from_secs(1))
         }
     }
 }
 
 pub(crate) struct TcpIncoming {
-    inner: AddrIncoming,
+    inner: TcpListener,
+    nodelay: bool,
+    keepalive: Option<Duration>,
+    recv_buffer_size: Option<usize>,
+    on_error: Option<AcceptErrorHook>,
+    timeout: Option<Delay>,
 }
 
 impl TcpIncoming {
-    pub(crate) fn new(
-        addr: SocketAddr,
-        nodelay: bool,
-        keepalive: Option<Duration>,
+    pub(crate) fn new(addr: SocketAddr, server: &Server) -> Result<Self, crate::Error> {
+        let listener = StdTcpListener::bind(addr)?;
+        TcpIncoming::from_std(listener, server)
+    }
+
+    pub(crate) fn from_std(
+        listener: StdTcpListener,
+        server: &Server,
     ) -> Result<Self, crate::Error> {
-        let mut inner = AddrIncoming::bind(&addr)?;
-        inner.set_nodelay(nodelay);
-        inner.set_keepalive(keepalive);
-        Ok(TcpIncoming { inner })
+        let inner = TcpListener::from_std(listener)?;
+        Ok(TcpIncoming {
+            inner,
+            nodelay: server.tcp_nodelay,
+            keepalive: server.tcp_keepalive,
+            recv_buffer_size: server.tcp_recv_buffer_size,
+            on_error: server.accept_error_hook.clone(),
+            timeout: None,
+        })
     }
 }
 
 impl Stream for TcpIncoming {
-    type Item = Result<AddrStream, std::io::Error>;
+    type Item = Result<TcpStream, io::Error>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.inner).poll_accept(cx)
+        // Back off after an error that is not tied to a single connection,
+        // like running out of file descriptors.
+        if let Some(timeout) = &mut self.timeout {
+            ready!(Pin::new(timeout).poll(cx));
+        }
+        self.timeout = None;
+
+        loop {
+            match ready!(self.inner.poll_accept(cx)) {
+                Ok((socket, _)) => {
+                    if let Err(e) = socket.set_keepalive(self.keepalive) {
+                        trace!("error trying to set TCP keepalive: {}", e);
+                    }
+                    if let Err(e) = socket.set_nodelay(self.nodelay) {
+                        trace!("error trying to set TCP nodelay: {}", e);
+                    }
+                    if let Some(size) = self.recv_buffer_size {
+                        if let Err(e) = socket.set_recv_buffer_size(size) {
+                            trace!("error trying to set TCP receive buffer size: {}", e);
+                        }
+                    }
+                    return Poll::Ready(Some(Ok(socket)));
+                }
+                Err(e) => {
+                    let action = match &self.on_error {
+                        Some(on_error) => on_error(&e),
+                        None => AcceptErrorAction::default_for(&e),
+                    };
+
+                    match action {
+                        AcceptErrorAction::Continue => continue,
+                        AcceptErrorAction::Abort => return Poll::Ready(Some(Err(e))),
+                        AcceptErrorAction::Backoff(backoff) => {
+                            let mut timeout = tokio::time::delay_for(backoff);
+                            match Pin::new(&mut timeout).poll(cx) {
+                                Poll::Ready(()) => continue,
+                                Poll::Pending => {
+                                    self.timeout = Some(timeout);
+                                    return Poll::Pending;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_connection_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_accept_error_actions() {
+        let reset = io::Error::from(io::ErrorKind::ConnectionReset);
+        assert_eq!(
+            AcceptErrorAction::default_for(&reset),
+            AcceptErrorAction::Continue
+        );
+
+        let emfile = io::Error::from_raw_os_error(24);
+        assert_eq!(
+            AcceptErrorAction::default_for(&emfile),
+            AcceptErrorAction::Backoff(Duration::from_secs(1))
+        );
     }
 }
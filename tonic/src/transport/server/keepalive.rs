@@ -0,0 +1,466 @@
+//! Enforcement of the client keepalive policy.
+//!
+//! `h2` answers PING frames on its own and does not surface them, so the
+//! policy is enforced by following the frame boundaries of the raw HTTP/2
+//! byte stream on both halves of the connection. Frames are never buffered or
+//! modified; the only thing ever added to the stream is a final `GOAWAY`,
+//! which is written between two outbound frames.
+
+use super::Connected;
+#[cfg(unix)]
+use super::PeerCred;
+use crate::transport::Certificate;
+use std::{
+    collections::HashSet,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tracing::debug;
+
+/// The number of misbehaving pings tolerated before the connection is closed,
+/// matching the default of other gRPC implementations.
+const MAX_PING_STRIKES: u32 = 2;
+
+const PREFACE_LEN: usize = 24;
+const HEADER_LEN: usize = 9;
+
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+
+const ENHANCE_YOUR_CALM: u32 = 0xb;
+const GOAWAY_DEBUG_DATA: &[u8] = b"too_many_pings";
+
+#[derive(Debug, Clone)]
+pub(crate) struct KeepalivePolicy {
+    pub(crate) min_ping_interval: Duration,
+    pub(crate) permit_without_stream: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FrameHeader {
+    kind: u8,
+    flags: u8,
+    stream_id: u32,
+}
+
+/// Follows frame boundaries in one direction of an HTTP/2 connection.
+#[derive(Debug)]
+struct FrameTracker {
+    preface: usize,
+    header: [u8; HEADER_LEN],
+    header_len: usize,
+    payload: usize,
+}
+
+impl FrameTracker {
+    fn new(preface: usize) -> Self {
+        FrameTracker {
+            preface,
+            header: [0; HEADER_LEN],
+            header_len: 0,
+            payload: 0,
+        }
+    }
+
+    /// The number of bytes left before the next frame boundary, if known.
+    fn remaining_in_frame(&self) -> usize {
+        if self.header_len > 0 {
+            HEADER_LEN - self.header_len
+        } else {
+            self.payload
+        }
+    }
+
+    /// Whether the next byte starts a new frame.
+    fn at_boundary(&self) -> bool {
+        self.preface == 0 && self.header_len == 0 && self.payload == 0
+    }
+
+    fn feed(&mut self, mut buf: &[u8], mut on_frame: impl FnMut(FrameHeader)) {
+        while !buf.is_empty() {
+            if self.preface > 0 {
+                let n = self.preface.min(buf.len());
+                self.preface -= n;
+                buf = &buf[n..];
+            } else if self.payload > 0 {
+                let n = self.payload.min(buf.len());
+                self.payload -= n;
+                buf = &buf[n..];
+            } else {
+                let n = (HEADER_LEN - self.header_len).min(buf.len());
+                self.header[self.header_len..self.header_len + n].copy_from_slice(&buf[..n]);
+                self.header_len += n;
+                buf = &buf[n..];
+
+                if self.header_len == HEADER_LEN {
+                    let h = &self.header;
+                    self.header_len = 0;
+                    self.payload = (h[0] as usize) << 16 | (h[1] as usize) << 8 | h[2] as usize;
+
+                    on_frame(FrameHeader {
+                        kind: h[3],
+                        flags: h[4],
+                        stream_id: u32::from_be_bytes([h[5], h[6], h[7], h[8]]) & 0x7fff_ffff,
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum State {
+    Open,
+    /// The policy was violated; `usize` bytes of the `GOAWAY` were written.
+    GoingAway(Vec<u8>, usize),
+    Closed,
+}
+
+/// An IO wrapper that closes connections whose peer sends pings more often
+/// than its [`KeepalivePolicy`] allows.
+#[derive(Debug)]
+pub(crate) struct PingEnforcer<IO> {
+    inner: IO,
+    policy: KeepalivePolicy,
+    read: FrameTracker,
+    write: FrameTracker,
+    streams: HashSet<u32>,
+    last_stream_id: u32,
+    last_ping: Option<Instant>,
+    strikes: u32,
+    state: State,
+}
+
+impl<IO> PingEnforcer<IO> {
+    pub(crate) fn new(inner: IO, policy: KeepalivePolicy) -> Self {
+        PingEnforcer {
+            inner,
+            policy,
+            read: FrameTracker::new(PREFACE_LEN),
+            write: FrameTracker::new(0),
+            streams: HashSet::new(),
+            last_stream_id: 0,
+            last_ping: None,
+            strikes: 0,
+            state: State::Open,
+        }
+    }
+
+    fn on_inbound(&mut self, frame: FrameHeader) {
+        match frame.kind {
+            HEADERS => {
+                self.streams.insert(frame.stream_id);
+                self.last_stream_id = self.last_stream_id.max(frame.stream_id);
+            }
+            RST_STREAM => {
+                self.streams.remove(&frame.stream_id);
+            }
+            PING if frame.flags & ACK == 0 => self.on_ping(Instant::now()),
+            _ => {}
+        }
+    }
+
+    fn on_outbound(&mut self, frame: FrameHeader) {
+        match frame.kind {
+            DATA | HEADERS => {
+                if frame.flags & END_STREAM != 0 {
+                    self.streams.remove(&frame.stream_id);
+                }
+                self.last_ping = None;
+                self.strikes = 0;
+            }
+            RST_STREAM => {
+                self.streams.remove(&frame.stream_id);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_ping(&mut self, now: Instant) {
+        let too_soon = self
+            .last_ping
+            .map(|last| now.duration_since(last) < self.policy.min_ping_interval)
+            .unwrap_or(false);
+        let idle = self.streams.is_empty() && !self.policy.permit_without_stream;
+
+        self.last_ping = Some(now);
+
+        if !(too_soon || idle) {
+            return;
+        }
+
+        self.strikes += 1;
+
+        if self.strikes > MAX_PING_STRIKES {
+            if let State::Open = self.state {
+                debug!("closing connection after {} ping strikes", self.strikes);
+                self.state = State::GoingAway(goaway(self.last_stream_id), 0);
+            }
+        }
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> PingEnforcer<IO> {
+    /// Drive the pending `GOAWAY` to the peer once the outbound stream is
+    /// between frames.
+    ///
+    /// Resolves once the connection is closed, and is pending while the
+    /// `GOAWAY` is in flight. Returns `None` while the connection is open or
+    /// an outbound frame is only partially written.
+    fn poll_goaway(&mut self, cx: &mut Context<'_>) -> Option<Poll<io::Result<()>>> {
+        loop {
+            match &mut self.state {
+                State::Open => return None,
+                State::Closed => return Some(Poll::Ready(Ok(()))),
+                State::GoingAway(..) if !self.write.at_boundary() => return None,
+                State::GoingAway(frame, written) if *written < frame.len() => {
+                    match Pin::new(&mut self.inner).poll_write(cx, &frame[*written..]) {
+                        Poll::Ready(Ok(0)) => {
+                            self.state = State::Closed;
+                            return Some(Poll::Ready(Err(io::ErrorKind::WriteZero.into())));
+                        }
+                        Poll::Ready(Ok(n)) => *written += n,
+                        Poll::Ready(Err(e)) => {
+                            self.state = State::Closed;
+                            return Some(Poll::Ready(Err(e)));
+                        }
+                        Poll::Pending => return Some(Poll::Pending),
+                    }
+                }
+                State::GoingAway(..) => {
+                    let res = match Pin::new(&mut self.inner).poll_flush(cx) {
+                        Poll::Ready(res) => res,
+                        Poll::Pending => return Some(Poll::Pending),
+                    };
+                    self.state = State::Closed;
+                    return Some(Poll::Ready(res));
+                }
+            }
+        }
+    }
+}
+
+fn goaway(last_stream_id: u32) -> Vec<u8> {
+    let len = 8 + GOAWAY_DEBUG_DATA.len();
+    let mut frame = Vec::with_capacity(HEADER_LEN + len);
+
+    frame.extend_from_slice(&(len as u32).to_be_bytes()[1..]);
+    frame.push(GOAWAY);
+    frame.push(0);
+    frame.extend_from_slice(&0u32.to_be_bytes());
+    frame.extend_from_slice(&last_stream_id.to_be_bytes());
+    frame.extend_from_slice(&ENHANCE_YOUR_CALM.to_be_bytes());
+    frame.extend_from_slice(GOAWAY_DEBUG_DATA);
+
+    frame
+}
+
+fn too_many_pings() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        "connection closed for sending too many pings",
+    )
+}
+
+impl<IO: Connected> Connected for PingEnforcer<IO> {
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        self.inner.remote_addr()
+    }
+
+    fn peer_certs(&self) -> Option<Vec<Certificate>> {
+        self.inner.peer_certs()
+    }
+
+    #[cfg(unix)]
+    fn peer_cred(&self) -> Option<PeerCred> {
+        self.inner.peer_cred()
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin> AsyncRead for PingEnforcer<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if let Some(res) = this.poll_goaway(cx) {
+            futures_util::ready!(res)?;
+            return Poll::Ready(Err(too_many_pings()));
+        }
+
+        let n = futures_util::ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+
+        let mut frames = Vec::new();
+        this.read.feed(&buf[..n], |frame| frames.push(frame));
+        for frame in frames {
+            this.on_inbound(frame);
+        }
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for PingEnforcer<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if let Some(res) = this.poll_goaway(cx) {
+            futures_util::ready!(res)?;
+            return Poll::Ready(Err(too_many_pings()));
+        }
+
+        // Once a `GOAWAY` is pending, stop at the end of the current frame so
+        // it can be written next.
+        let buf = match this.state {
+            State::GoingAway(..) => &buf[..buf.len().min(this.write.remaining_in_frame())],
+            _ => buf,
+        };
+
+        let n = futures_util::ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+
+        let mut frames = Vec::new();
+        this.write.feed(&buf[..n], |frame| frames.push(frame));
+        for frame in frames {
+            this.on_outbound(frame);
+        }
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(kind: u8, flags: u8, stream_id: u32, payload: usize) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(payload as u32).to_be_bytes()[1..]);
+        buf.push(kind);
+        buf.push(flags);
+        buf.extend_from_slice(&stream_id.to_be_bytes());
+        buf.resize(buf.len() + payload, 0);
+        buf
+    }
+
+    fn enforcer(permit_without_stream: bool) -> PingEnforcer<()> {
+        PingEnforcer::new(
+            (),
+            KeepalivePolicy {
+                min_ping_interval: Duration::from_secs(60),
+                permit_without_stream,
+            },
+        )
+    }
+
+    fn feed_inbound(e: &mut PingEnforcer<()>, bytes: &[u8]) {
+        let mut frames = Vec::new();
+        e.read.feed(bytes, |f| frames.push(f));
+        for f in frames {
+            e.on_inbound(f);
+        }
+    }
+
+    #[test]
+    fn tracker_splits_frames_across_reads() {
+        let mut bytes = vec![0; PREFACE_LEN];
+        bytes.extend(frame(HEADERS, 0, 1, 10));
+        bytes.extend(frame(PING, 0, 0, 8));
+
+        let mut tracker = FrameTracker::new(PREFACE_LEN);
+        let mut kinds = Vec::new();
+        for chunk in bytes.chunks(5) {
+            tracker.feed(chunk, |f| kinds.push((f.kind, f.stream_id)));
+        }
+
+        assert_eq!(kinds, vec![(HEADERS, 1), (PING, 0)]);
+        assert!(tracker.at_boundary());
+    }
+
+    #[test]
+    fn ping_flood_triggers_goaway() {
+        let mut e = enforcer(true);
+        let mut bytes = vec![0; PREFACE_LEN];
+        bytes.extend(frame(HEADERS, 0, 1, 4));
+        for _ in 0..=MAX_PING_STRIKES + 1 {
+            bytes.extend(frame(PING, 0, 0, 8));
+        }
+        feed_inbound(&mut e, &bytes);
+
+        match &e.state {
+            State::GoingAway(goaway, 0) => {
+                assert_eq!(goaway[3], GOAWAY);
+                assert_eq!(&goaway[9..13], &1u32.to_be_bytes());
+                assert_eq!(&goaway[13..17], &ENHANCE_YOUR_CALM.to_be_bytes());
+            }
+            state => panic!("unexpected state {:?}", state),
+        }
+    }
+
+    #[test]
+    fn ping_acks_and_responses_do_not_strike() {
+        let mut e = enforcer(true);
+        let mut bytes = vec![0; PREFACE_LEN];
+        bytes.extend(frame(HEADERS, 0, 1, 4));
+        bytes.extend(frame(PING, 0, 0, 8));
+        for _ in 0..=MAX_PING_STRIKES + 1 {
+            bytes.extend(frame(PING, ACK, 0, 8));
+        }
+        feed_inbound(&mut e, &bytes);
+        assert!(matches!(e.state, State::Open));
+
+        e.on_outbound(FrameHeader {
+            kind: DATA,
+            flags: 0,
+            stream_id: 1,
+        });
+        for _ in 0..=MAX_PING_STRIKES {
+            feed_inbound(&mut e, &frame(PING, 0, 0, 8));
+            e.on_outbound(FrameHeader {
+                kind: DATA,
+                flags: 0,
+                stream_id: 1,
+            });
+        }
+        assert!(matches!(e.state, State::Open));
+    }
+
+    #[test]
+    fn idle_pings_need_permission() {
+        let mut e = enforcer(false);
+        let mut bytes = vec![0; PREFACE_LEN];
+        for _ in 0..=MAX_PING_STRIKES {
+            bytes.extend(frame(PING, 0, 0, 8));
+        }
+        feed_inbound(&mut e, &bytes);
+        assert!(matches!(e.state, State::GoingAway(..)));
+
+        let mut e = enforcer(true);
+        e.policy.min_ping_interval = Duration::from_secs(0);
+        feed_inbound(&mut e, &bytes);
+        assert!(matches!(e.state, State::Open));
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    convert::TryFrom,
+    env, io,
+    mem::{self, ManuallyDrop},
+    net::TcpListener,
+    os::unix::io::{FromRawFd, RawFd},
+    process,
+};
+
+/// The first file descriptor passed by the service manager.
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// Take the TCP listeners passed to this process via systemd socket activation.
+///
+/// This follows the [`sd_listen_fds`] protocol: if `LISTEN_PID` matches the
+/// current process, the `LISTEN_FDS` file descriptors starting at `3` are
+/// returned as already bound listeners in the order the service manager
+/// passed them. The activation variables are then removed from the
+/// environment so that child processes do not try to take them as well.
+///
+/// Every descriptor must be a TCP stream socket. Otherwise an error is
+/// returned before any of them is taken, leaving the descriptors open and
+/// the activation variables in place.
+///
+/// Returns an empty list if the process was not socket activated. The
+/// listeners can be served with [`Router::serve_with_listener`].
+///
+/// ```no_run
+/// # use std::net::TcpListener;
+/// # fn main() -> std::io::Result<()> {
+/// let listener = match tonic::transport::server::systemd_listeners()?.pop() {
+///     Some(listener) => listener,
+///     None => TcpListener::bind("[::1]:50051")?,
+/// };
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`sd_listen_fds`]: https://www.freedesktop.org/software/systemd/man/sd_listen_fds.html
+/// [`Router::serve_with_listener`]: struct.Router.html#method.serve_with_listener
+pub fn systemd_listeners() -> io::Result<Vec<TcpListener>> {
+    let pid = match env::var("LISTEN_PID") {
+        Ok(pid) => pid,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    if pid.parse::<u32>().ok() != Some(process::id()) {
+        return Ok(Vec::new());
+    }
+
+    let end = env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|fds| fds.parse::<u32>().ok())
+        .and_then(|fds| RawFd::try_from(fds).ok())
+        .and_then(|fds| SD_LISTEN_FDS_START.checked_add(fds))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid LISTEN_FDS"))?;
+
+    // Check all of them before taking any, so a bad descriptor leaves the
+    // activation untouched instead of closing the ones already taken.
+    for fd in SD_LISTEN_FDS_START..end {
+        check_tcp_listener(fd)?;
+    }
+
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    // Safety: the service manager hands these descriptors over to us and
+    // nothing else in this process owns them.
+    let listeners = (SD_LISTEN_FDS_START..end)
+        .map(|fd| unsafe { TcpListener::from_raw_fd(fd) })
+        .collect();
+
+    Ok(listeners)
+}
+
+/// Fails unless `fd` is a TCP stream socket, without taking it.
+fn check_tcp_listener(fd: RawFd) -> io::Result<()> {
+    let mut ty: libc::c_int = 0;
+    let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+
+    // SAFETY: `ty` and `len` are valid for writes and `len` holds the size
+    // of `ty`, as `getsockopt` requires.
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_TYPE,
+            &mut ty as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // UDP sockets are datagram sockets, and Unix sockets have no inet
+    // address, so both are rejected.
+    if ty != libc::SOCK_STREAM {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("file descriptor {} is not a stream socket", fd),
+        ));
+    }
+
+    // Safety: the listener is never dropped, so the descriptor is only
+    // borrowed.
+    let listener = ManuallyDrop::new(unsafe { TcpListener::from_raw_fd(fd) });
+    listener.local_addr().map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::UdpSocket, os::unix::io::AsRawFd};
+
+    #[test]
+    fn accepts_only_tcp_listeners() {
+        let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+        assert!(check_tcp_listener(tcp.as_raw_fd()).is_ok());
+        // The descriptor was only borrowed.
+        assert!(tcp.local_addr().is_ok());
+
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let err = check_tcp_listener(udp.as_raw_fd()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(udp.local_addr().is_ok());
+    }
+}
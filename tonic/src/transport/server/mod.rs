@@ -1,22 +1,59 @@
 //! Server implementation and builder.
 
+mod authz;
+mod binding;
+mod cache;
 mod conn;
+mod events;
+mod idempotency;
 mod incoming;
+mod keepalive;
+#[cfg(unix)]
+mod listenfd;
+mod overload;
+mod rate_limit;
+mod slow_call;
+#[cfg(feature = "tls")]
+mod sniff;
+mod stats;
+mod status_map;
 #[cfg(feature = "tls")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
 mod tls;
 
+pub use authz::{Authorizer, Authz, AuthzLayer, AuthzRequest, Decision, Policy, Rule};
+pub use binding::Binding;
+pub use cache::{ResponseCache, ResponseCacheLayer};
 pub use conn::Connected;
+#[cfg(unix)]
+pub use conn::PeerCred;
+pub use events::ConnectionEvent;
+pub use idempotency::{Idempotency, IdempotencyLayer};
+#[cfg(unix)]
+pub use listenfd::systemd_listeners;
+pub use rate_limit::{Quota, RateLimit, RateLimitKey, RateLimitLayer};
+pub use slow_call::{SlowCall, SlowCallFuture, SlowCallLayer, SlowCallWatch};
+pub use stats::{ConnectionStats, ConnectionTracker};
 #[cfg(feature = "tls")]
 pub use tls::ServerTlsConfig;
 
 #[cfg(feature = "tls")]
 use super::service::TlsAcceptor;
 
-use incoming::TcpIncoming;
+use events::ConnectionHook;
+pub use incoming::AcceptErrorAction;
+use incoming::{AcceptErrorHook, TcpIncoming};
+use overload::RefuseOverloadLayer;
+use stats::StreamGuard;
+pub(crate) use stats::{ConnectionCounters, ConnectionMonitor};
+use status_map::{MapStatus, StatusMapper};
 
 use super::service::{Or, Routes, ServerIo, ServiceBuilderExt};
-use crate::{body::BoxBody, request::ConnectionInfo};
+use crate::{
+    body::BoxBody,
+    request::ConnectionInfo,
+    stats::{ConnInfo, ConnStats, RpcTracker, SharedHandler, Side, StatsBody, StatsHandler},
+};
 use futures_core::Stream;
 use futures_util::{
     future::{self, MapErr},
@@ -24,14 +61,15 @@ use futures_util::{
 };
 use http::{HeaderMap, Request, Response};
 use hyper::{server::accept, Body};
+use pin_project::pin_project;
 use std::{
     fmt,
     future::Future,
-    net::SocketAddr,
+    net::{SocketAddr, TcpListener as StdTcpListener},
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 use tokio::io::{AsyncRead, AsyncWrite};
 use tower::{
@@ -41,6 +79,7 @@ use tracing_futures::{Instrument, Instrumented};
 
 type BoxService = tower::util::BoxService<Request<Body>, Response<BoxBody>, crate::Error>;
 type TraceInterceptor = Arc<dyn Fn(&HeaderMap) -> tracing::Span + Send + Sync + 'static>;
+type DrainHook = Arc<dyn Fn() + Send + Sync + 'static>;
 
 /// A default batteries included `transport` server.
 ///
@@ -53,22 +92,36 @@ type TraceInterceptor = Arc<dyn Fn(&HeaderMap) -> tracing::Span + Send + Sync +
 #[derive(Default, Clone)]
 pub struct Server {
     trace_interceptor: Option<TraceInterceptor>,
+    connection_hook: Option<ConnectionHook>,
+    connection_tracker: Option<ConnectionTracker>,
+    stats_handler: Option<SharedHandler>,
+    drain_hook: Option<DrainHook>,
+    lameduck: Option<Duration>,
     concurrency_limit: Option<usize>,
+    refuse_over_limit: bool,
     timeout: Option<Duration>,
     #[cfg(feature = "tls")]
     tls: Option<TlsAcceptor>,
+    #[cfg(feature = "tls")]
+    tls_accept_plaintext: bool,
     init_stream_window_size: Option<u32>,
     init_connection_window_size: Option<u32>,
+    http2_adaptive_window: Option<bool>,
+    max_frame_size: Option<u32>,
     max_concurrent_streams: Option<u32>,
+    http2_keepalive_min_ping_interval: Option<Duration>,
+    http2_keepalive_permit_without_stream: bool,
     tcp_keepalive: Option<Duration>,
     tcp_nodelay: bool,
+    tcp_recv_buffer_size: Option<usize>,
+    accept_error_hook: Option<AcceptErrorHook>,
 }
 
 /// A stack based `Service` router.
-#[derive(Debug)]
 pub struct Router<A, B> {
     server: Server,
     routes: Routes<A, B, Request<Body>>,
+    status_mapper: Option<StatusMapper>,
 }
 
 /// A trait to provide a static reference to the service's
@@ -101,6 +154,26 @@ impl Server {
         }
     }
 
+    /// Accept plaintext h2c connections on the same port as TLS ones.
+    ///
+    /// By default a server with a [`ServerTlsConfig`] only accepts TLS. When
+    /// this is enabled, the first byte of every connection is inspected and
+    /// connections that do not start a TLS handshake are served as plaintext
+    /// instead. This eases rolling TLS out incrementally inside a cluster;
+    /// [`Request::is_tls`] tells both kinds of requests apart. Without a TLS
+    /// configuration the server is plaintext only and this has no effect.
+    ///
+    /// [`ServerTlsConfig`]: struct.ServerTlsConfig.html
+    /// [`Request::is_tls`]: ../../struct.Request.html#method.is_tls
+    #[cfg(feature = "tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    pub fn tls_accept_plaintext(self, enabled: bool) -> Self {
+        Server {
+            tls_accept_plaintext: enabled,
+            ..self
+        }
+    }
+
     /// Set the concurrency limit applied to on requests inbound per connection.
     ///
     /// # Example
@@ -118,6 +191,23 @@ impl Server {
         }
     }
 
+    /// Refuse streams above the [`Server::concurrency_limit_per_connection`]
+    /// with an HTTP/2 `REFUSED_STREAM` instead of queueing them.
+    ///
+    /// By default requests over the limit wait until an earlier request on the
+    /// same connection completes. When this is enabled they are reset right
+    /// away instead. A refused stream is guaranteed to not have been processed,
+    /// so clients can safely retry it elsewhere, unlike a request that was
+    /// accepted and then failed with an application error.
+    ///
+    /// [`Server::concurrency_limit_per_connection`]: #method.concurrency_limit_per_connection
+    pub fn refuse_streams_over_limit(self, enabled: bool) -> Self {
+        Server {
+            refuse_over_limit: enabled,
+            ..self
+        }
+    }
+
     /// Set a timeout on for all request handlers.
     ///
     /// # Example
@@ -157,6 +247,36 @@ impl Server {
         }
     }
 
+    /// Sets whether to use an adaptive flow control. Defaults to false.
+    ///
+    /// When enabled, the stream and connection windows grow with the measured
+    /// bandwidth-delay product of each connection, which keeps large client
+    /// streaming uploads over high latency links from being throttled. This
+    /// overrides the limits set with [`Server::initial_stream_window_size`]
+    /// and [`Server::initial_connection_window_size`].
+    ///
+    /// [`Server::initial_stream_window_size`]: #method.initial_stream_window_size
+    /// [`Server::initial_connection_window_size`]: #method.initial_connection_window_size
+    pub fn http2_adaptive_window(self, enabled: impl Into<Option<bool>>) -> Self {
+        Server {
+            http2_adaptive_window: enabled.into(),
+            ..self
+        }
+    }
+
+    /// Sets the [`SETTINGS_MAX_FRAME_SIZE`][spec] option for HTTP2
+    /// connections.
+    ///
+    /// Default is 16,384
+    ///
+    /// [spec]: https://http2.github.io/http2-spec/#SETTINGS_MAX_FRAME_SIZE
+    pub fn max_frame_size(self, sz: impl Into<Option<u32>>) -> Self {
+        Server {
+            max_frame_size: sz.into(),
+            ..self
+        }
+    }
+
     /// Sets the [`SETTINGS_MAX_CONCURRENT_STREAMS`][spec] option for HTTP2
     /// connections.
     ///
@@ -170,6 +290,42 @@ impl Server {
         }
     }
 
+    /// Enforce a minimum interval between HTTP2 pings sent by clients.
+    ///
+    /// A ping received sooner than `interval` after the previous one counts
+    /// as a strike, and so does any ping received while the connection has no
+    /// active streams unless [`Server::http2_keepalive_permit_without_stream`]
+    /// is enabled. Strikes are cleared whenever the server sends headers or
+    /// data. After more than two strikes the connection is closed with a
+    /// `GOAWAY` carrying `ENHANCE_YOUR_CALM`, protecting the server from
+    /// clients that flood it with pings.
+    ///
+    /// Default is no enforcement (`None`).
+    ///
+    /// [`Server::http2_keepalive_permit_without_stream`]: #method.http2_keepalive_permit_without_stream
+    pub fn http2_keepalive_min_ping_interval(self, interval: impl Into<Option<Duration>>) -> Self {
+        Server {
+            http2_keepalive_min_ping_interval: interval.into(),
+            ..self
+        }
+    }
+
+    /// Set whether clients may send HTTP2 pings on connections without any
+    /// active streams.
+    ///
+    /// This only has an effect when
+    /// [`Server::http2_keepalive_min_ping_interval`] is set.
+    ///
+    /// Default is `false`.
+    ///
+    /// [`Server::http2_keepalive_min_ping_interval`]: #method.http2_keepalive_min_ping_interval
+    pub fn http2_keepalive_permit_without_stream(self, enabled: bool) -> Self {
+        Server {
+            http2_keepalive_permit_without_stream: enabled,
+            ..self
+        }
+    }
+
     /// Set whether TCP keepalive messages are enabled on accepted connections.
     ///
     /// If `None` is specified, keepalive is disabled, otherwise the duration
@@ -193,6 +349,49 @@ impl Server {
         }
     }
 
+    /// Set the size of the receive buffer (`SO_RCVBUF`) of accepted
+    /// connections.
+    ///
+    /// Together with larger HTTP2 windows, a larger buffer keeps client
+    /// streaming uploads over high latency links from being throttled.
+    ///
+    /// Default is the one of the operating system (`None`)
+    pub fn tcp_recv_buffer_size(self, sz: impl Into<Option<usize>>) -> Self {
+        Server {
+            tcp_recv_buffer_size: sz.into(),
+            ..self
+        }
+    }
+
+    /// Decide how the server reacts to errors accepting connections.
+    ///
+    /// The callback is invoked with every error returned while accepting a
+    /// connection on a listener bound by tonic, and can log it before
+    /// choosing an [`AcceptErrorAction`]. Without a callback the server uses
+    /// [`AcceptErrorAction::default_for`], which never stops the server.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::{server::AcceptErrorAction, Server};
+    /// Server::builder().on_accept_error(|e| {
+    ///     eprintln!("accept error: {}", e);
+    ///     AcceptErrorAction::default_for(e)
+    /// });
+    /// ```
+    ///
+    /// [`AcceptErrorAction`]: enum.AcceptErrorAction.html
+    /// [`AcceptErrorAction::default_for`]: enum.AcceptErrorAction.html#method.default_for
+    pub fn on_accept_error<F>(self, f: F) -> Self
+    where
+        F: Fn(&std::io::Error) -> AcceptErrorAction + Send + Sync + 'static,
+    {
+        Server {
+            accept_error_hook: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
     /// Intercept inbound headers and add a [`tracing::Span`] to each response future.
     pub fn trace_fn<F>(self, f: F) -> Self
     where
@@ -204,6 +403,96 @@ impl Server {
         }
     }
 
+    /// Register a callback that is invoked on every [`ConnectionEvent`].
+    ///
+    /// This allows per connection accounting and debugging, for example
+    /// counting open connections or logging how many bytes a connection
+    /// transferred once it closes. The callback runs on the task driving the
+    /// connection, so it should return quickly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::{server::ConnectionEvent, Server};
+    /// Server::builder().on_connection_event(|event| {
+    ///     if let ConnectionEvent::Closed { remote_addr, stats, .. } = event {
+    ///         println!("{:?} closed after {:?}", remote_addr, stats.duration());
+    ///     }
+    /// });
+    /// ```
+    ///
+    /// [`ConnectionEvent`]: enum.ConnectionEvent.html
+    pub fn on_connection_event<F>(self, f: F) -> Self
+    where
+        F: Fn(&ConnectionEvent<'_>) + Send + Sync + 'static,
+    {
+        Server {
+            connection_hook: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
+    /// Register every connection accepted by this server with `tracker`.
+    ///
+    /// The tracker can then be queried for the active and total streams and
+    /// the bytes transferred of each live connection, see
+    /// [`ConnectionTracker`].
+    ///
+    /// [`ConnectionTracker`]: struct.ConnectionTracker.html
+    pub fn track_connections(self, tracker: ConnectionTracker) -> Self {
+        Server {
+            connection_tracker: Some(tracker),
+            ..self
+        }
+    }
+
+    /// Report the stats of the calls served and the connections accepted by
+    /// this server to `handler`, see [`StatsHandler`].
+    ///
+    /// [`StatsHandler`]: ../stats/trait.StatsHandler.html
+    pub fn stats_handler(self, handler: impl StatsHandler) -> Self {
+        Server {
+            stats_handler: Some(Arc::new(handler)),
+            ..self
+        }
+    }
+
+    /// Register a callback that is invoked as soon as the shutdown signal
+    /// passed to one of the `*_shutdown` serve methods resolves.
+    ///
+    /// The callback runs before the [`Server::lameduck`] period starts, which
+    /// makes it the place to mark a health service as `NOT_SERVING` so load
+    /// balancers stop routing new traffic to this server while in flight
+    /// requests finish.
+    ///
+    /// [`Server::lameduck`]: #method.lameduck
+    pub fn on_drain<F>(self, f: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        Server {
+            drain_hook: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
+    /// Set how long the server keeps accepting connections and requests
+    /// after the shutdown signal resolved, before it sends `GOAWAY` and
+    /// starts its graceful shutdown.
+    ///
+    /// This gives load balancers that were notified through
+    /// [`Server::on_drain`] time to stop sending traffic to this server.
+    ///
+    /// Default is no lameduck period (`None`).
+    ///
+    /// [`Server::on_drain`]: #method.on_drain
+    pub fn lameduck(self, period: impl Into<Option<Duration>>) -> Self {
+        Server {
+            lameduck: period.into(),
+            ..self
+        }
+    }
+
     /// Create a router with the `S` typed service as the first service.
     ///
     /// This will clone the `Server` builder and create a router that will
@@ -221,6 +510,39 @@ impl Server {
         Router::new(self.clone(), svc)
     }
 
+    /// The callback of the connection events, reporting them to the stats
+    /// handler too.
+    fn connection_hook(&self) -> Option<ConnectionHook> {
+        let handler = match &self.stats_handler {
+            Some(handler) => handler.clone(),
+            None => return self.connection_hook.clone(),
+        };
+        let hook = self.connection_hook.clone();
+        Some(Arc::new(move |event: &ConnectionEvent<'_>| {
+            if let Some(hook) = &hook {
+                hook(event);
+            }
+            let at = SystemTime::now();
+            let (remote_addr, stats) = match event {
+                ConnectionEvent::Established { remote_addr, .. } => {
+                    (remote_addr, ConnStats::Begin { at })
+                }
+                ConnectionEvent::Closed { remote_addr, .. } => (remote_addr, ConnStats::End { at }),
+                _ => return,
+            };
+            let conn = ConnInfo::new(Side::Server, *remote_addr);
+            handler.handle_conn(&conn, &stats);
+        }))
+    }
+
+    fn keepalive_policy(&self) -> Option<keepalive::KeepalivePolicy> {
+        self.http2_keepalive_min_ping_interval
+            .map(|min_ping_interval| keepalive::KeepalivePolicy {
+                min_ping_interval,
+                permit_without_stream: self.http2_keepalive_permit_without_stream,
+            })
+    }
+
     pub(crate) async fn serve_with_shutdown<S, I, F, IO, IE>(
         self,
         svc: S,
@@ -235,31 +557,69 @@ impl Server {
         IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
         IE: Into<crate::Error>,
         F: Future<Output = ()>,
+    {
+        let tcp = incoming::tcp_incoming(incoming, self.clone());
+        self.serve_io(svc, tcp, signal).await
+    }
+
+    async fn serve_io<S, I, F>(
+        self,
+        svc: S,
+        incoming: I,
+        signal: Option<F>,
+    ) -> Result<(), super::Error>
+    where
+        S: Service<Request<Body>, Response = Response<BoxBody>> + Clone + Send + 'static,
+        S::Future: Send + 'static,
+        S::Error: Into<crate::Error> + Send,
+        I: Stream<Item = Result<ServerIo, crate::Error>>,
+        F: Future<Output = ()>,
     {
         let span = self.trace_interceptor.clone();
         let concurrency_limit = self.concurrency_limit;
+        let refuse_over_limit = self.refuse_over_limit;
         let init_connection_window_size = self.init_connection_window_size;
         let init_stream_window_size = self.init_stream_window_size;
+        let http2_adaptive_window = self.http2_adaptive_window;
+        let max_frame_size = self.max_frame_size;
         let max_concurrent_streams = self.max_concurrent_streams;
-        let timeout = self.timeout.clone();
+        let stats = self.stats_handler.clone();
 
-        let tcp = incoming::tcp_incoming(incoming, self);
-        let incoming = accept::from_stream::<_, _, crate::Error>(tcp);
+        let incoming = accept::from_stream::<_, _, crate::Error>(incoming);
 
         let svc = MakeSvc {
             inner: svc,
             concurrency_limit,
-            timeout,
+            refuse_over_limit,
             span,
+            stats,
         };
 
-        let server = hyper::Server::builder(incoming)
+        let mut server = hyper::Server::builder(incoming)
             .http2_only(true)
             .http2_initial_connection_window_size(init_connection_window_size)
             .http2_initial_stream_window_size(init_stream_window_size)
+            .http2_max_frame_size(max_frame_size)
             .http2_max_concurrent_streams(max_concurrent_streams);
 
+        if let Some(enabled) = http2_adaptive_window {
+            server = server.http2_adaptive_window(enabled);
+        }
+
         if let Some(signal) = signal {
+            let drain_hook = self.drain_hook.clone();
+            let lameduck = self.lameduck;
+            let signal = async move {
+                signal.await;
+
+                if let Some(drain_hook) = drain_hook {
+                    drain_hook();
+                }
+                if let Some(lameduck) = lameduck {
+                    tokio::time::delay_for(lameduck).await;
+                }
+            };
+
             server
                 .serve(svc)
                 .with_graceful_shutdown(signal)
@@ -273,6 +633,27 @@ impl Server {
     }
 }
 
+impl Server {
+    fn bind_all<I>(
+        &self,
+        bindings: I,
+    ) -> Result<impl Stream<Item = Result<ServerIo, crate::Error>>, super::Error>
+    where
+        I: IntoIterator<Item = Binding>,
+    {
+        let mut streams = Vec::new();
+
+        for binding in bindings {
+            let (incoming, server) = binding
+                .into_incoming(self)
+                .map_err(super::Error::from_source)?;
+            streams.push(Box::pin(incoming::tcp_incoming(incoming, server)));
+        }
+
+        Ok(futures_util::stream::select_all(streams))
+    }
+}
+
 impl<S> Router<S, Unimplemented> {
     pub(crate) fn new(server: Server, svc: S) -> Self
     where
@@ -294,6 +675,45 @@ impl<S> Router<S, Unimplemented> {
         Self {
             server,
             routes: Routes::new(pred, svc, Unimplemented::default()),
+            status_mapper: None,
+        }
+    }
+}
+
+impl<A, B> Router<A, B> {
+    /// Customize the responses of the calls failing with a status for the
+    /// clients that don't speak gRPC, like gRPC-Web or transcoding clients.
+    ///
+    /// `f` is called with the status of the failed calls made with a
+    /// `content-type` other than `application/grpc` and
+    /// `application/grpc+*`, and the response it returns, if any, replaces
+    /// the gRPC one. Only the calls failing before sending their first
+    /// message are mapped, since the other ones already sent their headers.
+    ///
+    /// ```rust
+    /// # use tonic::{body::BoxBody, Code};
+    /// # use tonic::transport::server::Router;
+    /// # fn routes<A, B>(router: Router<A, B>) -> Router<A, B> {
+    /// router.status_mapper(|status| match status.code() {
+    ///     Code::NotFound => http::Response::builder()
+    ///         .status(404)
+    ///         .header("content-type", "application/json")
+    ///         .body(BoxBody::map_from(hyper::Body::from(format!(
+    ///             "{{\"error\":{:?}}}",
+    ///             status.message()
+    ///         ))))
+    ///         .ok(),
+    ///     _ => None,
+    /// })
+    /// # }
+    /// ```
+    pub fn status_mapper<F>(self, f: F) -> Self
+    where
+        F: Fn(&crate::Status) -> Option<Response<BoxBody>> + Send + Sync + 'static,
+    {
+        Router {
+            status_mapper: Some(Arc::new(f)),
+            ..self
         }
     }
 }
@@ -318,7 +738,11 @@ where
         S::Future: Send + 'static,
         S::Error: Into<crate::Error> + Send,
     {
-        let Self { routes, server } = self;
+        let Self {
+            routes,
+            server,
+            status_mapper,
+        } = self;
 
         let svc_name = <S as NamedService>::NAME;
         let svc_route = format!("/{}", svc_name);
@@ -329,7 +753,11 @@ where
         };
         let routes = routes.push(pred, svc);
 
-        Router { server, routes }
+        Router {
+            server,
+            routes,
+            status_mapper,
+        }
     }
 
     /// Consume this [`Server`] creating a future that will execute the server
@@ -337,10 +765,13 @@ where
     ///
     /// [`Server`]: struct.Server.html
     pub async fn serve(self, addr: SocketAddr) -> Result<(), super::Error> {
-        let incoming = TcpIncoming::new(addr, self.server.tcp_nodelay, self.server.tcp_keepalive)
-            .map_err(super::Error::from_source)?;
+        let incoming = TcpIncoming::new(addr, &self.server).map_err(super::Error::from_source)?;
         self.server
-            .serve_with_shutdown::<_, _, future::Ready<()>, _, _>(self.routes, incoming, None)
+            .serve_with_shutdown::<_, _, future::Ready<()>, _, _>(
+                MapStatus::new(self.routes, self.status_mapper),
+                incoming,
+                None,
+            )
             .await
     }
 
@@ -354,10 +785,105 @@ where
         addr: SocketAddr,
         signal: F,
     ) -> Result<(), super::Error> {
-        let incoming = TcpIncoming::new(addr, self.server.tcp_nodelay, self.server.tcp_keepalive)
-            .map_err(super::Error::from_source)?;
+        let incoming = TcpIncoming::new(addr, &self.server).map_err(super::Error::from_source)?;
+        self.server
+            .serve_with_shutdown(
+                MapStatus::new(self.routes, self.status_mapper),
+                incoming,
+                Some(signal),
+            )
+            .await
+    }
+
+    /// Consume this [`Server`] creating a future that will execute the server
+    /// on an already bound [`std::net::TcpListener`].
+    ///
+    /// This allows the listening socket to be created outside of tonic, for
+    /// example by a supervisor that binds a privileged port or hands over its
+    /// socket during a zero downtime restart. On unix, listeners passed through
+    /// systemd socket activation can be obtained with [`systemd_listeners`].
+    ///
+    /// [`Server`]: struct.Server.html
+    /// [`std::net::TcpListener`]: https://doc.rust-lang.org/std/net/struct.TcpListener.html
+    /// [`systemd_listeners`]: fn.systemd_listeners.html
+    pub async fn serve_with_listener(self, listener: StdTcpListener) -> Result<(), super::Error> {
+        let incoming =
+            TcpIncoming::from_std(listener, &self.server).map_err(super::Error::from_source)?;
         self.server
-            .serve_with_shutdown(self.routes, incoming, Some(signal))
+            .serve_with_shutdown::<_, _, future::Ready<()>, _, _>(
+                MapStatus::new(self.routes, self.status_mapper),
+                incoming,
+                None,
+            )
+            .await
+    }
+
+    /// Consume this [`Server`] creating a future that will execute the server
+    /// on an already bound [`std::net::TcpListener`]. And shutdown when the
+    /// provided signal is received.
+    ///
+    /// [`Server`]: struct.Server.html
+    /// [`std::net::TcpListener`]: https://doc.rust-lang.org/std/net/struct.TcpListener.html
+    pub async fn serve_with_listener_shutdown<F: Future<Output = ()>>(
+        self,
+        listener: StdTcpListener,
+        signal: F,
+    ) -> Result<(), super::Error> {
+        let incoming =
+            TcpIncoming::from_std(listener, &self.server).map_err(super::Error::from_source)?;
+        self.server
+            .serve_with_shutdown(
+                MapStatus::new(self.routes, self.status_mapper),
+                incoming,
+                Some(signal),
+            )
+            .await
+    }
+
+    /// Consume this [`Server`] creating a future that will execute the server
+    /// on every provided [`Binding`] at once.
+    ///
+    /// All bindings share this router's services and the server's settings,
+    /// but each one applies its own TLS configuration.
+    ///
+    /// [`Server`]: struct.Server.html
+    /// [`Binding`]: struct.Binding.html
+    pub async fn serve_bindings<I>(self, bindings: I) -> Result<(), super::Error>
+    where
+        I: IntoIterator<Item = Binding>,
+    {
+        let incoming = self.server.bind_all(bindings)?;
+        self.server
+            .serve_io::<_, _, future::Ready<()>>(
+                MapStatus::new(self.routes, self.status_mapper),
+                incoming,
+                None,
+            )
+            .await
+    }
+
+    /// Consume this [`Server`] creating a future that will execute the server
+    /// on every provided [`Binding`] at once. And shutdown all of them when
+    /// the provided signal is received.
+    ///
+    /// [`Server`]: struct.Server.html
+    /// [`Binding`]: struct.Binding.html
+    pub async fn serve_bindings_shutdown<I, F>(
+        self,
+        bindings: I,
+        signal: F,
+    ) -> Result<(), super::Error>
+    where
+        I: IntoIterator<Item = Binding>,
+        F: Future<Output = ()>,
+    {
+        let incoming = self.server.bind_all(bindings)?;
+        self.server
+            .serve_io(
+                MapStatus::new(self.routes, self.status_mapper),
+                incoming,
+                Some(signal),
+            )
             .await
     }
 
@@ -372,7 +898,11 @@ where
         IE: Into<crate::Error>,
     {
         self.server
-            .serve_with_shutdown::<_, _, future::Ready<()>, _, _>(self.routes, incoming, None)
+            .serve_with_shutdown::<_, _, future::Ready<()>, _, _>(
+                MapStatus::new(self.routes, self.status_mapper),
+                incoming,
+                None,
+            )
             .await
     }
 
@@ -394,11 +924,27 @@ where
         F: Future<Output = ()>,
     {
         self.server
-            .serve_with_shutdown(self.routes, incoming, Some(signal))
+            .serve_with_shutdown(
+                MapStatus::new(self.routes, self.status_mapper),
+                incoming,
+                Some(signal),
+            )
             .await
     }
 }
 
+impl<A, B> fmt::Debug for Router<A, B>
+where
+    Routes<A, B, Request<Body>>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Router")
+            .field("server", &self.server)
+            .field("routes", &self.routes)
+            .finish()
+    }
+}
+
 impl fmt::Debug for Server {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Builder").finish()
@@ -409,6 +955,8 @@ struct Svc<S> {
     inner: S,
     span: Option<TraceInterceptor>,
     conn_info: ConnectionInfo,
+    counters: Option<Arc<ConnectionCounters>>,
+    stats: Option<SharedHandler>,
 }
 
 impl<S> Service<Request<Body>> for Svc<S>
@@ -418,7 +966,7 @@ where
 {
     type Response = Response<BoxBody>;
     type Error = crate::Error;
-    type Future = MapErr<Instrumented<S::Future>, fn(S::Error) -> crate::Error>;
+    type Future = SvcFuture<MapErr<Instrumented<S::Future>, fn(S::Error) -> crate::Error>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.inner.poll_ready(cx).map_err(Into::into)
@@ -433,7 +981,70 @@ where
 
         req.extensions_mut().insert(self.conn_info.clone());
 
-        self.inner.call(req).instrument(span).map_err(|e| e.into())
+        let remote_addr = self.conn_info.remote_addr;
+        let tracker = self
+            .stats
+            .as_ref()
+            .map(|handler| RpcTracker::begin(handler, Side::Server, &req, remote_addr));
+        if let Some(tracker) = &tracker {
+            let tracker = tracker.clone();
+            req = req.map(|body| Body::wrap_stream(StatsBody::new(body, Some(tracker), false)));
+        }
+
+        let guard = self.counters.as_ref().map(ConnectionCounters::start_stream);
+        let inner = self
+            .inner
+            .call(req)
+            .instrument(span)
+            .map_err(Into::into as fn(S::Error) -> crate::Error);
+
+        SvcFuture {
+            inner,
+            guard,
+            tracker,
+        }
+    }
+}
+
+#[pin_project]
+struct SvcFuture<F> {
+    #[pin]
+    inner: F,
+    guard: Option<StreamGuard>,
+    tracker: Option<Arc<RpcTracker>>,
+}
+
+impl<F> Future for SvcFuture<F>
+where
+    F: Future<Output = Result<Response<BoxBody>, crate::Error>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = match futures_util::ready!(this.inner.poll(cx)) {
+            Ok(res) => res,
+            Err(err) => {
+                if let Some(tracker) = this.tracker.take() {
+                    let code = crate::Status::find_in_error(&*err)
+                        .map_or(crate::Code::Unknown, |status| status.code());
+                    tracker.end(code);
+                }
+                return Poll::Ready(Err(err));
+            }
+        };
+
+        let res = match this.tracker.take() {
+            Some(tracker) => {
+                tracker.response_headers(res.headers());
+                res.map(|body| BoxBody::map_from(StatsBody::new(body, Some(tracker), true)))
+            }
+            None => res,
+        };
+        match this.guard.take() {
+            Some(guard) => Poll::Ready(Ok(res.map(|body| guard.attach(body)))),
+            None => Poll::Ready(Ok(res)),
+        }
     }
 }
 
@@ -445,9 +1056,10 @@ impl<S> fmt::Debug for Svc<S> {
 
 struct MakeSvc<S> {
     concurrency_limit: Option<usize>,
-    timeout: Option<Duration>,
+    refuse_over_limit: bool,
     inner: S,
     span: Option<TraceInterceptor>,
+    stats: Option<SharedHandler>,
 }
 
 impl<S> Service<&ServerIo> for MakeSvc<S>
@@ -469,16 +1081,30 @@ where
         let conn_info = crate::request::ConnectionInfo {
             remote_addr: io.remote_addr(),
             peer_certs: io.peer_certs().map(Arc::new),
+            #[cfg(unix)]
+            peer_cred: io.peer_cred(),
+            tls: io.is_tls(),
         };
 
+        let counters = io.counters();
+
         let svc = self.inner.clone();
         let concurrency_limit = self.concurrency_limit;
-        let timeout = self.timeout.clone();
+        let refuse_over_limit = self.refuse_over_limit;
+        let timeout = io.timeout();
         let span = self.span.clone();
+        let stats = self.stats.clone();
 
         Box::pin(async move {
+            let (queue_limit, refuse_limit) = if refuse_over_limit {
+                (None, concurrency_limit)
+            } else {
+                (concurrency_limit, None)
+            };
+
             let svc = ServiceBuilder::new()
-                .optional_layer(concurrency_limit.map(ConcurrencyLimitLayer::new))
+                .optional_layer(refuse_limit.map(RefuseOverloadLayer::new))
+                .optional_layer(queue_limit.map(ConcurrencyLimitLayer::new))
                 .optional_layer(timeout.map(TimeoutLayer::new))
                 .service(svc);
 
@@ -486,6 +1112,8 @@ where
                 inner: svc,
                 span,
                 conn_info,
+                counters,
+                stats,
             });
 
             Ok(svc)
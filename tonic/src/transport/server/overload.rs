@@ -0,0 +1,129 @@
+use futures_util::{future, TryFutureExt};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use tower::{layer::Layer, Service};
+
+/// Enforces a concurrency limit by refusing the streams above it with
+/// `REFUSED_STREAM`, instead of holding them until a slot frees up.
+///
+/// A refused stream was never processed by the server, which lets clients
+/// transparently retry it on another connection.
+#[derive(Debug, Clone)]
+pub(crate) struct RefuseOverloadLayer {
+    max: usize,
+}
+
+impl RefuseOverloadLayer {
+    pub(crate) fn new(max: usize) -> Self {
+        RefuseOverloadLayer { max }
+    }
+}
+
+impl<S> Layer<S> for RefuseOverloadLayer {
+    type Service = RefuseOverload<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RefuseOverload {
+            inner,
+            max: self.max,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RefuseOverload<S> {
+    inner: S,
+    max: usize,
+    in_flight: Arc<AtomicUsize>,
+}
+
+/// Releases a concurrency slot once the response future completes or is
+/// dropped.
+#[derive(Debug)]
+struct InFlight(Arc<AtomicUsize>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl<S, B> Service<B> for RefuseOverload<S>
+where
+    S: Service<B>,
+    S::Error: Into<crate::Error> + 'static,
+    S::Future: Send + 'static,
+    S::Response: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = crate::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: B) -> Self::Future {
+        if self.in_flight.fetch_add(1, Ordering::AcqRel) >= self.max {
+            self.in_flight.fetch_sub(1, Ordering::AcqRel);
+
+            let refused: crate::Error = Box::new(h2::Error::from(h2::Reason::REFUSED_STREAM));
+            return Box::pin(future::err(refused));
+        }
+
+        let guard = InFlight(self.in_flight.clone());
+        let fut = self.inner.call(req).map_err(Into::into);
+
+        Box::pin(async move {
+            let res = fut.await;
+            drop(guard);
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::poll_fn;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn refuses_streams_above_limit() {
+        let (tx, rx) = oneshot::channel::<()>();
+        let mut rx = Some(rx);
+        let svc = tower::service_fn(move |()| {
+            let rx = rx.take();
+            async move {
+                if let Some(rx) = rx {
+                    let _ = rx.await;
+                }
+                Ok::<_, crate::Error>(())
+            }
+        });
+        let mut svc = RefuseOverloadLayer::new(1).layer(svc);
+
+        poll_fn(|cx| svc.poll_ready(cx)).await.unwrap();
+        let first = svc.call(());
+
+        poll_fn(|cx| svc.poll_ready(cx)).await.unwrap();
+        let err = svc.call(()).await.unwrap_err();
+        let reason = err.downcast_ref::<h2::Error>().and_then(|e| e.reason());
+        assert_eq!(reason, Some(h2::Reason::REFUSED_STREAM));
+
+        tx.send(()).unwrap();
+        first.await.unwrap();
+
+        poll_fn(|cx| svc.poll_ready(cx)).await.unwrap();
+        svc.call(()).await.unwrap();
+    }
+}
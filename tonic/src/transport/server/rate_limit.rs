@@ -0,0 +1,359 @@
+use super::NamedService;
+use crate::{body::BoxBody, request::ConnectionInfo, Code, Status};
+use futures_util::future::{self, Either};
+use http::{HeaderValue, Request, Response};
+use std::{
+    collections::HashMap,
+    fmt,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{layer::Layer, Service};
+
+const GRPC_RETRY_PUSHBACK_HEADER: &str = "grpc-retry-pushback-ms";
+/// The number of buckets above which the full ones are dropped, as they
+/// are the same as new ones.
+const MAX_BUCKETS: usize = 4096;
+
+/// The number of calls allowed over a period of time.
+///
+/// Calls are allowed as long as a token bucket holding up to `burst` tokens,
+/// refilled at the rate of the quota, has a token left, each call taking
+/// one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quota {
+    calls: u32,
+    period: Duration,
+    burst: u32,
+}
+
+impl Quota {
+    /// `calls` per `period`, all of them allowed in a burst.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `calls` is zero or `period` is empty.
+    pub fn new(calls: u32, period: Duration) -> Self {
+        assert!(calls > 0, "a quota allows at least one call");
+        assert!(period > Duration::from_secs(0), "a quota has a period");
+        Quota {
+            calls,
+            period,
+            burst: calls,
+        }
+    }
+
+    /// `calls` per second.
+    pub fn per_second(calls: u32) -> Self {
+        Quota::new(calls, Duration::from_secs(1))
+    }
+
+    /// `calls` per minute.
+    pub fn per_minute(calls: u32) -> Self {
+        Quota::new(calls, Duration::from_secs(60))
+    }
+
+    /// Allow at most `burst` calls at once, by default the calls of a whole
+    /// period, the other ones spread over the period.
+    pub fn burst(self, burst: u32) -> Self {
+        Quota {
+            burst: burst.max(1),
+            ..self
+        }
+    }
+
+    /// How long it takes to get a token back.
+    fn interval(&self) -> Duration {
+        self.period / self.calls
+    }
+}
+
+/// What the calls are limited by, each value of the key with its own quota.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// All the calls, sharing a single quota.
+    Global,
+    /// The IP address of the client, as in [`Request::remote_addr`].
+    ///
+    /// [`Request::remote_addr`]: ../../struct.Request.html#method.remote_addr
+    Peer,
+    /// The value of the metadata entry of the given key, like a tenant or an
+    /// API key, the calls without it sharing a quota.
+    Metadata(String),
+}
+
+/// Wraps services with [`RateLimit`], limiting the rate of their calls.
+///
+/// ```rust
+/// use tonic::transport::server::{Quota, RateLimitKey, RateLimitLayer};
+///
+/// let limit = RateLimitLayer::new(Quota::per_second(100))
+///     .key(RateLimitKey::Metadata("x-api-key".to_string()))
+///     .method("/orders.Orders/Export", Quota::per_minute(2));
+/// ```
+///
+/// [`RateLimit`]: struct.RateLimit.html
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    quota: Quota,
+    methods: HashMap<String, Quota>,
+    key: RateLimitKey,
+}
+
+impl RateLimitLayer {
+    /// Limit the calls of each method to `quota`, shared by all clients.
+    pub fn new(quota: Quota) -> Self {
+        RateLimitLayer {
+            quota,
+            methods: HashMap::new(),
+            key: RateLimitKey::Global,
+        }
+    }
+
+    /// Limit the calls of the method at `path`, like
+    /// `/helloworld.Greeter/SayHello`, to `quota` instead.
+    pub fn method(mut self, path: impl Into<String>, quota: Quota) -> Self {
+        self.methods.insert(path.into(), quota);
+        self
+    }
+
+    /// Give a quota to each value of `key`, by default a single one to all
+    /// the calls.
+    pub fn key(self, key: RateLimitKey) -> Self {
+        RateLimitLayer { key, ..self }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: Arc::new(Limiter {
+                quota: self.quota,
+                methods: self.methods.clone(),
+                key: self.key.clone(),
+                buckets: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+}
+
+/// A service rejecting the calls over their quota with `RESOURCE_EXHAUSTED`.
+///
+/// The rejected calls are sent the time until the quota allows a new call,
+/// as the `grpc-retry-pushback-ms` the gRPC clients retrying calls wait
+/// for, and with the `prost` feature as the [`Status::retry_delay`] of the
+/// status.
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`Status::retry_delay`]: ../../struct.Status.html#method.retry_delay
+/// [`Server`]: ../struct.Server.html
+#[derive(Debug, Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: Arc<Limiter>,
+}
+
+impl<S, B> Service<Request<B>> for RateLimit<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>>,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Either<S::Future, future::Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        match self.limiter.check(&request, Instant::now()) {
+            Ok(()) => Either::Left(self.inner.call(request)),
+            Err(retry_after) => Either::Right(future::ok(exhausted(retry_after))),
+        }
+    }
+}
+
+impl<S: NamedService> NamedService for RateLimit<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// The quotas of the calls, with the buckets of their keys.
+struct Limiter {
+    quota: Quota,
+    methods: HashMap<String, Quota>,
+    key: RateLimitKey,
+    buckets: Mutex<HashMap<(String, Key), Bucket>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Global,
+    Peer(Option<IpAddr>),
+    Metadata(Option<HeaderValue>),
+}
+
+/// A token bucket, as the time it is full at: each call takes a token by
+/// pushing it back by the interval of the quota.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    full_at: Instant,
+}
+
+impl Limiter {
+    /// Take a token for `request`, or the time until there is one.
+    fn check<B>(&self, request: &Request<B>, now: Instant) -> Result<(), Duration> {
+        let path = request.uri().path();
+        let quota = self.methods.get(path).unwrap_or(&self.quota);
+        let key = match &self.key {
+            RateLimitKey::Global => Key::Global,
+            RateLimitKey::Peer => Key::Peer(
+                request
+                    .extensions()
+                    .get::<ConnectionInfo>()
+                    .and_then(|info| info.remote_addr)
+                    .map(|addr| addr.ip()),
+            ),
+            RateLimitKey::Metadata(name) => Key::Metadata(request.headers().get(name).cloned()),
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_BUCKETS {
+            buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+        let bucket = buckets
+            .entry((path.to_string(), key))
+            .or_insert(Bucket { full_at: now });
+
+        // The bucket is empty once it takes the whole burst to be full.
+        let interval = quota.interval();
+        let full_at = bucket.full_at.max(now) + interval;
+        let empty_at = full_at.checked_sub(interval * quota.burst).unwrap_or(now);
+        if empty_at > now {
+            return Err(empty_at - now);
+        }
+        bucket.full_at = full_at;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Limiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Limiter")
+            .field("quota", &self.quota)
+            .field("methods", &self.methods)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+fn exhausted(retry_after: Duration) -> Response<BoxBody> {
+    let message = "rate limit exceeded";
+    #[cfg(feature = "prost")]
+    let status = Status::with_retry_delay(Code::ResourceExhausted, message, retry_after);
+    #[cfg(not(feature = "prost"))]
+    let status = Status::new(Code::ResourceExhausted, message);
+
+    let mut response = status.to_http();
+    // Rounded up, so the call is allowed once retried.
+    let millis = (retry_after.as_micros() as u64).div_ceil(1000);
+    response
+        .headers_mut()
+        .insert(GRPC_RETRY_PUSHBACK_HEADER, HeaderValue::from(millis));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    fn limiter(layer: RateLimitLayer) -> Limiter {
+        Limiter {
+            quota: layer.quota,
+            methods: layer.methods,
+            key: layer.key,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn request(path: &str, peer: &str, api_key: Option<&str>) -> Request<()> {
+        let mut request = Request::builder().uri(path);
+        if let Some(api_key) = api_key {
+            request = request.header("x-api-key", api_key);
+        }
+        let mut request = request.body(()).unwrap();
+        request.extensions_mut().insert(ConnectionInfo {
+            remote_addr: Some(peer.parse::<SocketAddr>().unwrap()),
+            peer_certs: None,
+            #[cfg(unix)]
+            peer_cred: None,
+            tls: false,
+        });
+        request
+    }
+
+    #[test]
+    fn refills_buckets_at_the_rate_of_the_quota() {
+        let limiter = limiter(RateLimitLayer::new(Quota::per_second(4).burst(2)));
+        let request = request("/test.Test/Echo", "10.0.0.1:1000", None);
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        assert_eq!(limiter.check(&request, at(0)), Ok(()));
+        assert_eq!(limiter.check(&request, at(0)), Ok(()));
+        assert_eq!(
+            limiter.check(&request, at(100)),
+            Err(Duration::from_millis(150))
+        );
+        assert_eq!(limiter.check(&request, at(250)), Ok(()));
+        assert_eq!(
+            limiter.check(&request, at(250)),
+            Err(Duration::from_millis(250))
+        );
+        // Refilled up to the burst.
+        assert_eq!(limiter.check(&request, at(5000)), Ok(()));
+        assert_eq!(limiter.check(&request, at(5000)), Ok(()));
+        assert!(limiter.check(&request, at(5000)).is_err());
+    }
+
+    #[test]
+    fn gives_a_quota_to_each_method_and_key() {
+        let limiter = limiter(
+            RateLimitLayer::new(Quota::per_minute(1))
+                .method("/test.Test/Export", Quota::per_minute(2))
+                .key(RateLimitKey::Metadata("x-api-key".to_string())),
+        );
+        let now = Instant::now();
+        let check = |path, peer, api_key| limiter.check(&request(path, peer, api_key), now);
+
+        assert!(check("/test.Test/Echo", "10.0.0.1:1000", Some("a")).is_ok());
+        assert!(check("/test.Test/Echo", "10.0.0.2:1000", Some("a")).is_err());
+        assert!(check("/test.Test/Echo", "10.0.0.1:1000", Some("b")).is_ok());
+        assert!(check("/test.Test/Echo", "10.0.0.1:1000", None).is_ok());
+        assert!(check("/test.Test/Echo", "10.0.0.1:1000", None).is_err());
+
+        assert!(check("/test.Test/Export", "10.0.0.1:1000", Some("a")).is_ok());
+        assert!(check("/test.Test/Export", "10.0.0.1:1000", Some("a")).is_ok());
+        assert!(check("/test.Test/Export", "10.0.0.1:1000", Some("a")).is_err());
+
+        let limiter =
+            self::limiter(RateLimitLayer::new(Quota::per_minute(1)).key(RateLimitKey::Peer));
+        let check = |peer| limiter.check(&request("/test.Test/Echo", peer, None), now);
+        assert!(check("10.0.0.1:1000").is_ok());
+        assert!(check("10.0.0.1:2000").is_err());
+        assert!(check("10.0.0.2:1000").is_ok());
+    }
+
+    #[test]
+    fn pushes_back_rejected_calls() {
+        let response = exhausted(Duration::from_micros(1500));
+        assert_eq!(response.headers()["grpc-status"], "8");
+        assert_eq!(response.headers()[GRPC_RETRY_PUSHBACK_HEADER], "2");
+    }
+}
@@ -0,0 +1,271 @@
+use super::NamedService;
+use crate::{metadata::MetadataMap, request::ConnectionInfo};
+use http::Request;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::time::{delay_for, Delay};
+use tower::{layer::Layer, Service};
+
+type SlowCallHook = Arc<dyn Fn(&SlowCall<'_>) + Send + Sync + 'static>;
+
+/// A call still in progress after the threshold of its method, reported by
+/// a [`SlowCallWatch`].
+///
+/// [`SlowCallWatch`]: struct.SlowCallWatch.html
+#[derive(Debug)]
+pub struct SlowCall<'a> {
+    method: &'a str,
+    metadata: &'a MetadataMap,
+    remote_addr: Option<SocketAddr>,
+    elapsed: Duration,
+}
+
+impl<'a> SlowCall<'a> {
+    /// The path of the method of the call, like
+    /// `/helloworld.Greeter/SayHello`.
+    pub fn method(&self) -> &'a str {
+        self.method
+    }
+
+    /// The metadata of the request.
+    pub fn metadata(&self) -> &'a MetadataMap {
+        self.metadata
+    }
+
+    /// The address of the client, if known.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// How long the call has been in progress.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// Wraps services with [`SlowCallWatch`], reporting their calls still in
+/// progress after a threshold.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use tonic::transport::server::SlowCallLayer;
+///
+/// let slow_calls = SlowCallLayer::new(Duration::from_secs(1))
+///     .method("/orders.Orders/Export", Duration::from_secs(30))
+///     .on_slow_call(|call| {
+///         eprintln!("{} still running after {:?}", call.method(), call.elapsed());
+///     });
+/// ```
+///
+/// [`SlowCallWatch`]: struct.SlowCallWatch.html
+#[derive(Clone)]
+pub struct SlowCallLayer {
+    threshold: Option<Duration>,
+    methods: HashMap<String, Option<Duration>>,
+    hook: Option<SlowCallHook>,
+}
+
+impl SlowCallLayer {
+    /// Report the calls of each method still in progress after `threshold`.
+    pub fn new(threshold: Duration) -> Self {
+        SlowCallLayer {
+            threshold: Some(threshold),
+            methods: HashMap::new(),
+            hook: None,
+        }
+    }
+
+    /// Report only the calls of the methods given a threshold with
+    /// [`method`].
+    ///
+    /// [`method`]: #method.method
+    pub fn methods() -> Self {
+        SlowCallLayer {
+            threshold: None,
+            methods: HashMap::new(),
+            hook: None,
+        }
+    }
+
+    /// Report the calls of the method at `path`, like
+    /// `/helloworld.Greeter/SayHello`, after `threshold` instead.
+    pub fn method(mut self, path: impl Into<String>, threshold: Duration) -> Self {
+        self.methods.insert(path.into(), Some(threshold));
+        self
+    }
+
+    /// Never report the calls of the method at `path`, like the long lived
+    /// streams of a watch method.
+    pub fn ignore_method(mut self, path: impl Into<String>) -> Self {
+        self.methods.insert(path.into(), None);
+        self
+    }
+
+    /// Hand the slow calls to `hook` instead of emitting a `WARN` [`tracing`]
+    /// event for each.
+    ///
+    /// It is called while the calls are in progress, and must not block.
+    ///
+    /// [`tracing`]: https://docs.rs/tracing
+    pub fn on_slow_call<F>(self, hook: F) -> Self
+    where
+        F: Fn(&SlowCall<'_>) + Send + Sync + 'static,
+    {
+        SlowCallLayer {
+            hook: Some(Arc::new(hook)),
+            ..self
+        }
+    }
+
+    fn threshold(&self, path: &str) -> Option<Duration> {
+        match self.methods.get(path) {
+            Some(threshold) => *threshold,
+            None => self.threshold,
+        }
+    }
+}
+
+impl<S> Layer<S> for SlowCallLayer {
+    type Service = SlowCallWatch<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SlowCallWatch {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for SlowCallLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlowCallLayer")
+            .field("threshold", &self.threshold)
+            .field("methods", &self.methods)
+            .finish()
+    }
+}
+
+/// A service reporting its calls still in progress after the threshold of
+/// their method, before they complete, so the handlers that hang are
+/// caught even if they never return.
+///
+/// A call is in progress until its handler returns its response, or the
+/// stream of its messages. Each call is reported at most once.
+///
+/// Added to a [`Server`] under the name of the service it wraps.
+///
+/// [`Server`]: ../struct.Server.html
+#[derive(Debug, Clone)]
+pub struct SlowCallWatch<S> {
+    inner: S,
+    layer: SlowCallLayer,
+}
+
+impl<S, B> Service<Request<B>> for SlowCallWatch<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SlowCallFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let watch = self.layer.threshold(request.uri().path()).map(|threshold| {
+            let watch = Watch {
+                method: request.uri().path().to_string(),
+                metadata: MetadataMap::from_headers(request.headers().clone()),
+                remote_addr: request
+                    .extensions()
+                    .get::<ConnectionInfo>()
+                    .and_then(|info| info.remote_addr),
+                started: Instant::now(),
+                hook: self.layer.hook.clone(),
+            };
+            (delay_for(threshold), watch)
+        });
+        SlowCallFuture {
+            inner: self.inner.call(request),
+            watch,
+        }
+    }
+}
+
+impl<S: NamedService> NamedService for SlowCallWatch<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// The response future of [`SlowCallWatch`].
+///
+/// [`SlowCallWatch`]: struct.SlowCallWatch.html
+#[pin_project]
+pub struct SlowCallFuture<F> {
+    #[pin]
+    inner: F,
+    watch: Option<(Delay, Watch)>,
+}
+
+impl<F: Future> Future for SlowCallFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(output) = this.inner.poll(cx) {
+            return Poll::Ready(output);
+        }
+        if let Some((delay, watch)) = this.watch {
+            if Pin::new(delay).poll(cx).is_ready() {
+                watch.report();
+                *this.watch = None;
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<F> fmt::Debug for SlowCallFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlowCallFuture").finish()
+    }
+}
+
+/// A call to report once past its threshold.
+struct Watch {
+    method: String,
+    metadata: MetadataMap,
+    remote_addr: Option<SocketAddr>,
+    started: Instant,
+    hook: Option<SlowCallHook>,
+}
+
+impl Watch {
+    fn report(&self) {
+        let call = SlowCall {
+            method: &self.method,
+            metadata: &self.metadata,
+            remote_addr: self.remote_addr,
+            elapsed: self.started.elapsed(),
+        };
+        match &self.hook {
+            Some(hook) => hook(&call),
+            None => tracing::warn!(
+                method = call.method,
+                elapsed_ms = call.elapsed.as_millis() as u64,
+                remote_addr = ?call.remote_addr,
+                "slow call still in progress"
+            ),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use super::Connected;
+#[cfg(unix)]
+use super::PeerCred;
+use crate::transport::Certificate;
+use futures_util::future::poll_fn;
+use std::{
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// The content type of a TLS handshake record, which every `ClientHello`
+/// starts with. An h2c connection starts with the `PRI` preface instead.
+const TLS_HANDSHAKE: u8 = 0x16;
+
+/// An IO resource whose first byte was read ahead to tell TLS from
+/// plaintext connections, and is replayed on the first read.
+#[derive(Debug)]
+pub(crate) struct Sniffed<IO> {
+    first: Option<u8>,
+    is_tls: bool,
+    inner: IO,
+}
+
+impl<IO: AsyncRead + Unpin> Sniffed<IO> {
+    pub(crate) async fn new(mut inner: IO) -> io::Result<Self> {
+        let mut buf = [0; 1];
+        let n = poll_fn(|cx| Pin::new(&mut inner).poll_read(cx, &mut buf)).await?;
+
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(Sniffed {
+            first: Some(buf[0]),
+            is_tls: buf[0] == TLS_HANDSHAKE,
+            inner,
+        })
+    }
+}
+
+impl<IO> Sniffed<IO> {
+    /// Whether the peer started a TLS handshake.
+    pub(crate) fn is_tls(&self) -> bool {
+        self.is_tls
+    }
+}
+
+impl<IO: Connected> Connected for Sniffed<IO> {
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        self.inner.remote_addr()
+    }
+
+    fn peer_certs(&self) -> Option<Vec<Certificate>> {
+        self.inner.peer_certs()
+    }
+
+    #[cfg(unix)]
+    fn peer_cred(&self) -> Option<PeerCred> {
+        self.inner.peer_cred()
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for Sniffed<IO> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !buf.is_empty() {
+            if let Some(first) = self.first.take() {
+                buf[0] = first;
+                return Poll::Ready(Ok(1));
+            }
+        }
+
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for Sniffed<IO> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    async fn read_all(mut io: impl AsyncRead + Unpin) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut buf = [0; 4];
+        loop {
+            let n = poll_fn(|cx| Pin::new(&mut io).poll_read(cx, &mut buf))
+                .await
+                .unwrap();
+            if n == 0 {
+                return out;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_first_byte() {
+        let preface = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n".to_vec();
+        let sniffed = Sniffed::new(Cursor::new(preface.clone())).await.unwrap();
+
+        assert!(!sniffed.is_tls());
+        assert_eq!(read_all(sniffed).await, preface);
+    }
+
+    #[tokio::test]
+    async fn detects_client_hello() {
+        let hello = vec![TLS_HANDSHAKE, 0x03, 0x01];
+        let sniffed = Sniffed::new(Cursor::new(hello)).await.unwrap();
+
+        assert!(sniffed.is_tls());
+    }
+
+    #[tokio::test]
+    async fn empty_connection_is_an_error() {
+        let err = Sniffed::new(Cursor::new(Vec::new())).await.unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,331 @@
+use super::events::{ConnectionEvent, ConnectionHook};
+use crate::body::BoxBody;
+use bytes::Bytes;
+use http::HeaderMap;
+use http_body::Body;
+use std::{
+    collections::HashMap,
+    fmt, io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// A snapshot of the statistics of a connection.
+///
+/// Snapshots of live connections are returned by
+/// [`ConnectionTracker::connections`], and a final one is attached to
+/// [`ConnectionEvent::Closed`].
+///
+/// [`ConnectionTracker::connections`]: struct.ConnectionTracker.html#method.connections
+/// [`ConnectionEvent::Closed`]: enum.ConnectionEvent.html#variant.Closed
+#[derive(Debug, Clone)]
+pub struct ConnectionStats {
+    remote_addr: Option<SocketAddr>,
+    active_streams: usize,
+    total_streams: u64,
+    bytes_read: u64,
+    bytes_written: u64,
+    duration: Duration,
+}
+
+impl ConnectionStats {
+    /// The address of the peer, if known.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// The number of requests currently being handled on the connection,
+    /// counted until their response body has been fully sent.
+    pub fn active_streams(&self) -> usize {
+        self.active_streams
+    }
+
+    /// The number of requests received on the connection so far.
+    pub fn total_streams(&self) -> u64 {
+        self.total_streams
+    }
+
+    /// The number of bytes read from the connection after the TLS handshake.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// The number of bytes written to the connection after the TLS handshake.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// How long the connection has been established.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// A handle to query the live connections of one or more servers.
+///
+/// Register a tracker with [`Server::track_connections`] and keep a clone of
+/// it to inspect the connections while the server runs, for example to feed
+/// autoscaling or draining decisions.
+///
+/// # Example
+///
+/// ```
+/// # use tonic::transport::{server::ConnectionTracker, Server};
+/// let tracker = ConnectionTracker::new();
+/// let builder = Server::builder().track_connections(tracker.clone());
+///
+/// let active: usize = tracker
+///     .connections()
+///     .iter()
+///     .map(|conn| conn.active_streams())
+///     .sum();
+/// assert_eq!(active, 0);
+/// ```
+///
+/// [`Server::track_connections`]: struct.Server.html#method.track_connections
+#[derive(Clone, Default)]
+pub struct ConnectionTracker {
+    inner: Arc<Mutex<Tracked>>,
+}
+
+#[derive(Default)]
+struct Tracked {
+    next_id: u64,
+    connections: HashMap<u64, Arc<ConnectionCounters>>,
+}
+
+impl ConnectionTracker {
+    /// Create a new tracker with no connections.
+    pub fn new() -> Self {
+        ConnectionTracker::default()
+    }
+
+    /// Return a snapshot of every live connection.
+    pub fn connections(&self) -> Vec<ConnectionStats> {
+        let tracked = self.inner.lock().unwrap();
+        tracked.connections.values().map(|c| c.stats()).collect()
+    }
+
+    fn insert(&self, counters: Arc<ConnectionCounters>) -> u64 {
+        let mut tracked = self.inner.lock().unwrap();
+        let id = tracked.next_id;
+        tracked.next_id += 1;
+        tracked.connections.insert(id, counters);
+        id
+    }
+
+    fn remove(&self, id: u64) {
+        self.inner.lock().unwrap().connections.remove(&id);
+    }
+}
+
+impl fmt::Debug for ConnectionTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionTracker").finish()
+    }
+}
+
+/// Counters shared between a connection's IO and its service.
+#[derive(Debug)]
+pub(crate) struct ConnectionCounters {
+    remote_addr: Option<SocketAddr>,
+    established: Instant,
+    active_streams: AtomicUsize,
+    total_streams: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl ConnectionCounters {
+    fn stats(&self) -> ConnectionStats {
+        ConnectionStats {
+            remote_addr: self.remote_addr,
+            active_streams: self.active_streams.load(Ordering::Relaxed),
+            total_streams: self.total_streams.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            duration: self.established.elapsed(),
+        }
+    }
+
+    /// Count a new stream as active until the returned guard is dropped.
+    pub(crate) fn start_stream(self: &Arc<Self>) -> StreamGuard {
+        self.total_streams.fetch_add(1, Ordering::Relaxed);
+        self.active_streams.fetch_add(1, Ordering::Relaxed);
+        StreamGuard(self.clone())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct StreamGuard(Arc<ConnectionCounters>);
+
+impl StreamGuard {
+    /// Keep the stream active until `body` has been sent or dropped.
+    pub(crate) fn attach(self, body: BoxBody) -> BoxBody {
+        BoxBody::new(GuardedBody {
+            inner: body,
+            _guard: self,
+        })
+    }
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        self.0.active_streams.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+struct GuardedBody {
+    inner: BoxBody,
+    _guard: StreamGuard,
+}
+
+impl Body for GuardedBody {
+    type Data = Bytes;
+    type Error = crate::Status;
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_data(cx)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+}
+
+/// Tracks an established connection, and reports it as closed once dropped.
+pub(crate) struct ConnectionMonitor {
+    counters: Arc<ConnectionCounters>,
+    hook: Option<ConnectionHook>,
+    tracker: Option<(ConnectionTracker, u64)>,
+    error: Option<io::Error>,
+}
+
+impl ConnectionMonitor {
+    pub(crate) fn new(
+        remote_addr: Option<SocketAddr>,
+        hook: Option<ConnectionHook>,
+        tracker: Option<ConnectionTracker>,
+    ) -> Self {
+        let counters = Arc::new(ConnectionCounters {
+            remote_addr,
+            established: Instant::now(),
+            active_streams: AtomicUsize::new(0),
+            total_streams: AtomicU64::new(0),
+            bytes_read: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+        });
+        let tracker = tracker.map(|tracker| {
+            let id = tracker.insert(counters.clone());
+            (tracker, id)
+        });
+
+        ConnectionMonitor {
+            counters,
+            hook,
+            tracker,
+            error: None,
+        }
+    }
+
+    pub(crate) fn counters(&self) -> Arc<ConnectionCounters> {
+        self.counters.clone()
+    }
+
+    pub(crate) fn on_read(&mut self, res: &io::Result<usize>) {
+        match res {
+            Ok(n) => {
+                self.counters
+                    .bytes_read
+                    .fetch_add(*n as u64, Ordering::Relaxed);
+            }
+            Err(e) => self.on_error(e),
+        }
+    }
+
+    pub(crate) fn on_write(&mut self, res: &io::Result<usize>) {
+        match res {
+            Ok(n) => {
+                self.counters
+                    .bytes_written
+                    .fetch_add(*n as u64, Ordering::Relaxed);
+            }
+            Err(e) => self.on_error(e),
+        }
+    }
+
+    pub(crate) fn on_error(&mut self, e: &io::Error) {
+        if self.error.is_none() {
+            self.error = Some(io::Error::new(e.kind(), e.to_string()));
+        }
+    }
+}
+
+impl Drop for ConnectionMonitor {
+    fn drop(&mut self) {
+        if let Some((tracker, id)) = &self.tracker {
+            tracker.remove(*id);
+        }
+
+        if let Some(hook) = &self.hook {
+            hook(&ConnectionEvent::Closed {
+                remote_addr: self.counters.remote_addr,
+                error: self.error.as_ref(),
+                stats: &self.counters.stats(),
+            });
+        }
+    }
+}
+
+impl fmt::Debug for ConnectionMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionMonitor")
+            .field("counters", &self.counters)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_live_connections() {
+        let tracker = ConnectionTracker::new();
+        let mut monitor = ConnectionMonitor::new(None, None, Some(tracker.clone()));
+
+        monitor.on_read(&Ok(10));
+        monitor.on_write(&Ok(4));
+        let guard = monitor.counters().start_stream();
+
+        let conns = tracker.connections();
+        assert_eq!(conns.len(), 1);
+        assert_eq!(conns[0].bytes_read(), 10);
+        assert_eq!(conns[0].bytes_written(), 4);
+        assert_eq!(conns[0].active_streams(), 1);
+        assert_eq!(conns[0].total_streams(), 1);
+
+        drop(guard);
+        assert_eq!(tracker.connections()[0].active_streams(), 0);
+        assert_eq!(tracker.connections()[0].total_streams(), 1);
+
+        drop(monitor);
+        assert!(tracker.connections().is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{body::BoxBody, Status};
+use futures_core::ready;
+use http::{header::CONTENT_TYPE, Request, Response};
+use hyper::Body;
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::Service;
+
+pub(crate) type StatusMapper =
+    Arc<dyn Fn(&Status) -> Option<Response<BoxBody>> + Send + Sync + 'static>;
+
+/// Replaces the failed responses sent to non-gRPC clients with the ones
+/// built by a [`StatusMapper`].
+///
+/// Only failures reported in the response headers, as done when a call fails
+/// before its first message, can be replaced.
+#[derive(Clone)]
+pub(crate) struct MapStatus<S> {
+    inner: S,
+    mapper: Option<StatusMapper>,
+}
+
+impl<S> MapStatus<S> {
+    pub(crate) fn new(inner: S, mapper: Option<StatusMapper>) -> Self {
+        MapStatus { inner, mapper }
+    }
+}
+
+/// Whether `request` was sent by a gRPC client, as told by its content type.
+///
+/// gRPC-Web requests don't count, their clients expect the status code and
+/// body of plain HTTP errors.
+fn is_grpc<B>(request: &Request<B>) -> bool {
+    let content_type = match request.headers().get(CONTENT_TYPE) {
+        Some(content_type) => content_type.as_bytes(),
+        None => return false,
+    };
+
+    content_type == b"application/grpc" || content_type.starts_with(b"application/grpc+")
+}
+
+impl<S> Service<Request<Body>> for MapStatus<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>>,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = MapStatusFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let mapper = if is_grpc(&request) {
+            None
+        } else {
+            self.mapper.clone()
+        };
+
+        MapStatusFuture {
+            inner: self.inner.call(request),
+            mapper,
+        }
+    }
+}
+
+impl<S> fmt::Debug for MapStatus<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MapStatus").finish()
+    }
+}
+
+#[pin_project]
+pub(crate) struct MapStatusFuture<F> {
+    #[pin]
+    inner: F,
+    mapper: Option<StatusMapper>,
+}
+
+impl<F, E> Future for MapStatusFuture<F>
+where
+    F: Future<Output = Result<Response<BoxBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let response = ready!(this.inner.poll(cx))?;
+
+        let mapped = match (
+            this.mapper.take(),
+            Status::from_header_map(response.headers()),
+        ) {
+            (Some(mapper), Some(status)) if status.code() != crate::Code::Ok => mapper(&status),
+            _ => None,
+        };
+
+        Poll::Ready(Ok(mapped.unwrap_or(response)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::{self, FutureExt};
+
+    fn unavailable(_: Request<Body>) -> future::Ready<Result<Response<BoxBody>, crate::Error>> {
+        let mut response = Response::new(BoxBody::empty());
+        Status::unavailable("down")
+            .add_header(response.headers_mut())
+            .unwrap();
+        future::ok(response)
+    }
+
+    fn request(content_type: &'static str) -> Request<Body> {
+        Request::builder()
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn maps_statuses_for_non_grpc_clients() {
+        let mapper: StatusMapper = Arc::new(|status: &Status| {
+            let mut response = Response::new(BoxBody::empty());
+            *response.status_mut() = http::StatusCode::SERVICE_UNAVAILABLE;
+            response.headers_mut().insert(
+                "x-message",
+                http::HeaderValue::from_str(status.message()).unwrap(),
+            );
+            Some(response)
+        });
+        let mut svc = MapStatus::new(tower::service_fn(unavailable), Some(mapper));
+
+        let response = svc
+            .call(request("application/json"))
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["x-message"], "down");
+
+        for content_type in &["application/grpc", "application/grpc+proto"] {
+            let response = svc
+                .call(request(content_type))
+                .now_or_never()
+                .unwrap()
+                .unwrap();
+            assert_eq!(response.status(), http::StatusCode::OK);
+            assert_eq!(response.headers()["grpc-status"], "14");
+        }
+    }
+}
@@ -1,5 +1,14 @@
-use super::{layer::ServiceBuilderExt, reconnect::Reconnect, AddOrigin};
-use crate::{body::BoxBody, transport::Endpoint};
+use super::{
+    events::EventsConnector,
+    layer::ServiceBuilderExt,
+    reconnect::Reconnect,
+    stats::{Stats, StatsConnector},
+    AddOrigin,
+};
+use crate::{
+    body::BoxBody,
+    transport::{channel::Subchannel, Endpoint},
+};
 use http::Uri;
 use hyper::client::conn::Builder;
 use hyper::client::connect::Connection as HyperConnection;
@@ -42,13 +51,21 @@ impl Connection {
             .http2_only(true)
             .clone();
 
+        let stats = endpoint.stats_handler.clone();
         let stack = ServiceBuilder::new()
+            .optional_layer_fn(
+                stats
+                    .clone()
+                    .map(|handler| move |s| Stats::new(s, handler.clone())),
+            )
             .layer_fn(|s| AddOrigin::new(s, endpoint.uri.clone()))
             .optional_layer(endpoint.timeout.map(TimeoutLayer::new))
             .optional_layer(endpoint.concurrency_limit.map(ConcurrencyLimitLayer::new))
             .optional_layer(endpoint.rate_limit.map(|(l, d)| RateLimitLayer::new(l, d)))
             .into_inner();
 
+        let subchannel = Subchannel::new(endpoint.uri.clone(), endpoint.events.clone());
+        let connector = EventsConnector::new(StatsConnector::new(connector, stats), subchannel);
         let mut connector = HyperConnect::new(connector, settings);
         let initial_conn = connector.call(endpoint.uri.clone()).await?;
         let conn = Reconnect::new(initial_conn, connector, endpoint.uri.clone());
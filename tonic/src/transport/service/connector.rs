@@ -57,6 +57,8 @@ where
     }
 
     fn call(&mut self, uri: Uri) -> Self::Future {
+        #[cfg(feature = "h2-trace")]
+        let peer = uri.authority().map(|authority| authority.to_string());
         let connect = self.inner.make_connection(uri);
 
         #[cfg(feature = "tls")]
@@ -69,10 +71,14 @@ where
             {
                 if let Some(tls) = tls {
                     let conn = tls.connect(io).await?;
-                    return Ok(BoxedIo::new(conn));
+                    #[cfg(feature = "h2-trace")]
+                    let conn = BoxedIo::new(super::FrameTraceIo::client(conn, peer));
+                    return Ok(conn);
                 }
             }
 
+            #[cfg(feature = "h2-trace")]
+            let io = super::FrameTraceIo::client(io, peer);
             Ok(BoxedIo::new(io))
         })
     }
@@ -1,5 +1,5 @@
 use super::connection::Connection;
-use crate::transport::Endpoint;
+use crate::transport::{channel::Events, Endpoint};
 use std::{
     collections::VecDeque,
     fmt,
@@ -14,14 +14,16 @@ pub(crate) struct ServiceList {
     connecting:
         Option<Pin<Box<dyn Future<Output = Result<Connection, crate::Error>> + Send + 'static>>>,
     i: usize,
+    events: Events,
 }
 
 impl ServiceList {
-    pub(crate) fn new(list: Vec<Endpoint>) -> Self {
+    pub(crate) fn new(list: Vec<Endpoint>, events: Events) -> Self {
         Self {
             list: list.into(),
             connecting: None,
             i: 0,
+            events,
         }
     }
 }
@@ -48,7 +50,9 @@ impl Discover for ServiceList {
                 return Poll::Ready(change);
             }
 
-            if let Some(endpoint) = self.list.pop_front() {
+            if let Some(mut endpoint) = self.list.pop_front() {
+                endpoint.events = endpoint.events.and(&self.events);
+
                 let mut http = hyper::client::connect::HttpConnector::new();
                 http.set_nodelay(endpoint.tcp_nodelay);
                 http.set_keepalive(endpoint.tcp_keepalive);
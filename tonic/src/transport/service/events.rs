@@ -0,0 +1,154 @@
+use crate::transport::channel::{
+    ChannelEventKind::{ConnectAttempt, ConnectFailed, Connected, Disconnected},
+    ConnectivityState, Subchannel,
+};
+use http::Uri;
+use hyper::client::connect::{Connected as HyperConnected, Connection as HyperConnection};
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tower_service::Service;
+
+/// Sends the events of the connections made to the endpoint of a
+/// subchannel.
+pub(crate) struct EventsConnector<C> {
+    inner: C,
+    subchannel: Arc<Subchannel>,
+}
+
+impl<C> EventsConnector<C> {
+    pub(crate) fn new(inner: C, subchannel: Subchannel) -> Self {
+        EventsConnector {
+            inner,
+            subchannel: Arc::new(subchannel),
+        }
+    }
+}
+
+impl<C> Service<Uri> for EventsConnector<C>
+where
+    C: Service<Uri>,
+    C::Error: Into<crate::Error>,
+    C::Future: Unpin + Send + 'static,
+{
+    type Response = EventsIo<C::Response>;
+    type Error = crate::Error;
+    type Future = EventsConnect<C::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        self.subchannel.send(ConnectAttempt);
+        self.subchannel.transition(ConnectivityState::Connecting);
+        EventsConnect {
+            inner: self.inner.call(uri),
+            subchannel: self.subchannel.clone(),
+        }
+    }
+}
+
+pub(crate) struct EventsConnect<F> {
+    inner: F,
+    subchannel: Arc<Subchannel>,
+}
+
+impl<F, IO, E> Future for EventsConnect<F>
+where
+    F: Future<Output = Result<IO, E>> + Unpin,
+    E: Into<crate::Error>,
+{
+    type Output = Result<EventsIo<IO>, crate::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let subchannel = self.subchannel.clone();
+        match futures_util::ready!(Pin::new(&mut self.inner).poll(cx)) {
+            Ok(io) => {
+                subchannel.send(Connected);
+                subchannel.transition(ConnectivityState::Ready);
+                Poll::Ready(Ok(EventsIo {
+                    inner: io,
+                    subchannel,
+                    error: None,
+                }))
+            }
+            Err(err) => {
+                let err = err.into();
+                subchannel.send(ConnectFailed {
+                    error: err.to_string(),
+                });
+                subchannel.transition(ConnectivityState::TransientFailure);
+                Poll::Ready(Err(err))
+            }
+        }
+    }
+}
+
+/// A connection of a subchannel, its end sent once dropped, along with the
+/// IO error that ended it, if any.
+pub(crate) struct EventsIo<IO> {
+    inner: IO,
+    subchannel: Arc<Subchannel>,
+    error: Option<String>,
+}
+
+impl<IO> EventsIo<IO> {
+    fn record<T>(&mut self, result: Poll<io::Result<T>>) -> Poll<io::Result<T>> {
+        if let Poll::Ready(Err(err)) = &result {
+            self.error = Some(err.to_string());
+        }
+        result
+    }
+}
+
+impl<IO> Drop for EventsIo<IO> {
+    fn drop(&mut self) {
+        let error = self.error.take();
+        self.subchannel.send(Disconnected { error });
+        self.subchannel.transition(ConnectivityState::Idle);
+    }
+}
+
+impl<IO: HyperConnection> HyperConnection for EventsIo<IO> {
+    fn connected(&self) -> HyperConnected {
+        self.inner.connected()
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for EventsIo<IO> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        self.record(result)
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for EventsIo<IO> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+        self.record(result)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let result = Pin::new(&mut self.inner).poll_flush(cx);
+        self.record(result)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let result = Pin::new(&mut self.inner).poll_shutdown(cx);
+        self.record(result)
+    }
+}
@@ -0,0 +1,378 @@
+//! Debug tracing of the HTTP/2 control frames of connections, enabled by
+//! the `h2-trace` feature.
+//!
+//! `h2` does not surface the frames it sends and receives, so they are read
+//! from the raw HTTP/2 byte stream on both halves of the connection, after
+//! TLS, and reported as `DEBUG` events of this module. Only the frames
+//! useful to diagnose flow control stalls and broken connections are
+//! reported, the `DATA` and `HEADERS` ones being left out.
+
+use crate::transport::server::Connected;
+#[cfg(unix)]
+use crate::transport::server::PeerCred;
+use crate::transport::Certificate;
+use std::{
+    fmt::Write,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+const PREFACE_LEN: usize = 24;
+const HEADER_LEN: usize = 9;
+/// The bytes kept of the payload of a frame, enough for the settings sent
+/// in practice and the start of the debug data of a `GOAWAY`.
+const MAX_PAYLOAD: usize = 128;
+
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const WINDOW_UPDATE: u8 = 0x8;
+
+const ACK: u8 = 0x1;
+
+/// A frame read in full, its payload truncated to `MAX_PAYLOAD` bytes.
+#[derive(Debug)]
+struct Frame<'a> {
+    kind: u8,
+    flags: u8,
+    stream_id: u32,
+    payload: &'a [u8],
+}
+
+/// Reads the frames of one direction of an HTTP/2 connection.
+#[derive(Debug)]
+struct Frames {
+    preface: usize,
+    header: [u8; HEADER_LEN],
+    header_len: usize,
+    /// The bytes of the payload of the current frame left to read.
+    remaining: usize,
+    payload: Vec<u8>,
+}
+
+impl Frames {
+    fn new(preface: usize) -> Self {
+        Frames {
+            preface,
+            header: [0; HEADER_LEN],
+            header_len: 0,
+            remaining: 0,
+            payload: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, mut buf: &[u8], mut on_frame: impl FnMut(Frame<'_>)) {
+        while !buf.is_empty() {
+            if self.preface > 0 {
+                let n = self.preface.min(buf.len());
+                self.preface -= n;
+                buf = &buf[n..];
+                continue;
+            }
+
+            if self.header_len < HEADER_LEN {
+                let n = (HEADER_LEN - self.header_len).min(buf.len());
+                self.header[self.header_len..self.header_len + n].copy_from_slice(&buf[..n]);
+                self.header_len += n;
+                buf = &buf[n..];
+                if self.header_len < HEADER_LEN {
+                    continue;
+                }
+                let h = &self.header;
+                self.remaining = (h[0] as usize) << 16 | (h[1] as usize) << 8 | h[2] as usize;
+                self.payload.clear();
+            } else {
+                let n = self.remaining.min(buf.len());
+                if traced(self.header[3]) {
+                    let kept = (MAX_PAYLOAD - self.payload.len().min(MAX_PAYLOAD)).min(n);
+                    self.payload.extend_from_slice(&buf[..kept]);
+                }
+                self.remaining -= n;
+                buf = &buf[n..];
+            }
+
+            if self.remaining == 0 {
+                let h = &self.header;
+                if traced(h[3]) {
+                    on_frame(Frame {
+                        kind: h[3],
+                        flags: h[4],
+                        stream_id: u32::from_be_bytes([h[5], h[6], h[7], h[8]]) & 0x7fff_ffff,
+                        payload: &self.payload,
+                    });
+                }
+                self.header_len = 0;
+            }
+        }
+    }
+}
+
+fn traced(kind: u8) -> bool {
+    matches!(kind, RST_STREAM | SETTINGS | PING | GOAWAY | WINDOW_UPDATE)
+}
+
+fn u32_at(payload: &[u8], at: usize) -> Option<u32> {
+    let bytes = payload.get(at..at + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn error_code(code: u32) -> String {
+    let name = match code {
+        0x0 => "NO_ERROR",
+        0x1 => "PROTOCOL_ERROR",
+        0x2 => "INTERNAL_ERROR",
+        0x3 => "FLOW_CONTROL_ERROR",
+        0x4 => "SETTINGS_TIMEOUT",
+        0x5 => "STREAM_CLOSED",
+        0x6 => "FRAME_SIZE_ERROR",
+        0x7 => "REFUSED_STREAM",
+        0x8 => "CANCEL",
+        0x9 => "COMPRESSION_ERROR",
+        0xa => "CONNECT_ERROR",
+        0xb => "ENHANCE_YOUR_CALM",
+        0xc => "INADEQUATE_SECURITY",
+        0xd => "HTTP_1_1_REQUIRED",
+        code => return format!("{:#x}", code),
+    };
+    name.to_string()
+}
+
+fn setting(id: u16) -> String {
+    let name = match id {
+        0x1 => "HEADER_TABLE_SIZE",
+        0x2 => "ENABLE_PUSH",
+        0x3 => "MAX_CONCURRENT_STREAMS",
+        0x4 => "INITIAL_WINDOW_SIZE",
+        0x5 => "MAX_FRAME_SIZE",
+        0x6 => "MAX_HEADER_LIST_SIZE",
+        0x8 => "ENABLE_CONNECT_PROTOCOL",
+        id => return format!("{:#x}", id),
+    };
+    name.to_string()
+}
+
+/// Describe `frame`, like `RST_STREAM error=CANCEL`.
+fn describe(frame: &Frame<'_>) -> String {
+    let payload = frame.payload;
+    let mut description = String::new();
+    match frame.kind {
+        SETTINGS if frame.flags & ACK != 0 => description.push_str("SETTINGS ack"),
+        SETTINGS => {
+            description.push_str("SETTINGS");
+            for entry in payload.chunks_exact(6) {
+                let id = u16::from_be_bytes([entry[0], entry[1]]);
+                let value = u32_at(entry, 2).unwrap_or_default();
+                let _ = write!(description, " {}={}", setting(id), value);
+            }
+        }
+        WINDOW_UPDATE => {
+            let increment = u32_at(payload, 0).unwrap_or_default() & 0x7fff_ffff;
+            let _ = write!(description, "WINDOW_UPDATE increment={}", increment);
+        }
+        GOAWAY => {
+            let last_stream_id = u32_at(payload, 0).unwrap_or_default() & 0x7fff_ffff;
+            let code = u32_at(payload, 4).unwrap_or_default();
+            let _ = write!(
+                description,
+                "GOAWAY last_stream_id={} error={}",
+                last_stream_id,
+                error_code(code)
+            );
+            if payload.len() > 8 {
+                let data = String::from_utf8_lossy(&payload[8..]);
+                let _ = write!(description, " debug_data={:?}", data);
+            }
+        }
+        RST_STREAM => {
+            let code = u32_at(payload, 0).unwrap_or_default();
+            let _ = write!(description, "RST_STREAM error={}", error_code(code));
+        }
+        PING if frame.flags & ACK != 0 => description.push_str("PING ack"),
+        _ => description.push_str("PING"),
+    }
+    description
+}
+
+/// An IO wrapper reporting the HTTP/2 control frames sent and received on
+/// a connection.
+#[derive(Debug)]
+pub(crate) struct FrameTraceIo<IO> {
+    inner: IO,
+    peer: Option<String>,
+    read: Frames,
+    write: Frames,
+}
+
+impl<IO> FrameTraceIo<IO> {
+    /// Trace a connection of a client to `peer`, which sends the preface.
+    pub(crate) fn client(inner: IO, peer: Option<String>) -> Self {
+        FrameTraceIo {
+            inner,
+            peer,
+            read: Frames::new(0),
+            write: Frames::new(PREFACE_LEN),
+        }
+    }
+
+    /// Trace a connection accepted by a server, which receives the preface.
+    pub(crate) fn server(inner: IO) -> Self
+    where
+        IO: Connected,
+    {
+        FrameTraceIo {
+            peer: inner.remote_addr().map(|addr| addr.to_string()),
+            inner,
+            read: Frames::new(PREFACE_LEN),
+            write: Frames::new(0),
+        }
+    }
+}
+
+fn trace(peer: &Option<String>, direction: &'static str, frame: Frame<'_>) {
+    tracing::debug!(
+        peer = peer.as_deref().unwrap_or("unknown"),
+        direction,
+        stream_id = frame.stream_id,
+        "{}",
+        describe(&frame),
+    );
+}
+
+impl<IO: Connected> Connected for FrameTraceIo<IO> {
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        self.inner.remote_addr()
+    }
+
+    fn peer_certs(&self) -> Option<Vec<Certificate>> {
+        self.inner.peer_certs()
+    }
+
+    #[cfg(unix)]
+    fn peer_cred(&self) -> Option<PeerCred> {
+        self.inner.peer_cred()
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for FrameTraceIo<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let n = futures_util::ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        let peer = &this.peer;
+        this.read
+            .feed(&buf[..n], |frame| trace(peer, "received", frame));
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for FrameTraceIo<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let n = futures_util::ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        let peer = &this.peer;
+        this.write
+            .feed(&buf[..n], |frame| trace(peer, "sent", frame));
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(kind: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+        buf.push(kind);
+        buf.push(flags);
+        buf.extend_from_slice(&stream_id.to_be_bytes());
+        buf.extend_from_slice(payload);
+        buf
+    }
+
+    fn describe_all(bytes: &[u8], preface: usize, chunk: usize) -> Vec<(u32, String)> {
+        let mut frames = Frames::new(preface);
+        let mut described = Vec::new();
+        for chunk in bytes.chunks(chunk) {
+            frames.feed(chunk, |frame| {
+                described.push((frame.stream_id, describe(&frame)))
+            });
+        }
+        described
+    }
+
+    #[test]
+    fn describes_the_control_frames() {
+        let mut bytes = vec![0; PREFACE_LEN];
+        let settings = [[0, 3, 0, 0, 0, 100], [0, 4, 0, 0, 0xff, 0xff]].concat();
+        bytes.extend(frame(SETTINGS, 0, 0, &settings));
+        bytes.extend(frame(SETTINGS, ACK, 0, &[]));
+        bytes.extend(frame(0x1, 0x4, 1, &[0; 20]));
+        bytes.extend(frame(0x0, 0x1, 1, &[0; 300]));
+        bytes.extend(frame(WINDOW_UPDATE, 0, 1, &1024u32.to_be_bytes()));
+        bytes.extend(frame(RST_STREAM, 0, 3, &8u32.to_be_bytes()));
+        bytes.extend(frame(PING, ACK, 0, &[0; 8]));
+        let goaway = [
+            &5u32.to_be_bytes()[..],
+            &0xbu32.to_be_bytes(),
+            b"too_many_pings",
+        ]
+        .concat();
+        bytes.extend(frame(GOAWAY, 0, 0, &goaway));
+
+        let expected = vec![
+            (
+                0,
+                "SETTINGS MAX_CONCURRENT_STREAMS=100 INITIAL_WINDOW_SIZE=65535".to_string(),
+            ),
+            (0, "SETTINGS ack".to_string()),
+            (1, "WINDOW_UPDATE increment=1024".to_string()),
+            (3, "RST_STREAM error=CANCEL".to_string()),
+            (0, "PING ack".to_string()),
+            (
+                0,
+                "GOAWAY last_stream_id=5 error=ENHANCE_YOUR_CALM debug_data=\"too_many_pings\""
+                    .to_string(),
+            ),
+        ];
+        assert_eq!(describe_all(&bytes, PREFACE_LEN, bytes.len()), expected);
+        assert_eq!(describe_all(&bytes, PREFACE_LEN, 7), expected);
+    }
+
+    #[test]
+    fn truncates_the_payloads() {
+        let goaway = [&[0; 8][..], &[b'x'; 1000]].concat();
+        let bytes = frame(GOAWAY, 0, 0, &goaway);
+        let described = describe_all(&bytes, 0, 100);
+        let data = "x".repeat(MAX_PAYLOAD - 8);
+        assert_eq!(
+            described,
+            vec![(
+                0,
+                format!(
+                    "GOAWAY last_stream_id=0 error=NO_ERROR debug_data={:?}",
+                    data
+                )
+            )]
+        );
+    }
+}
@@ -1,9 +1,14 @@
+#[cfg(unix)]
+use crate::transport::server::PeerCred;
+use crate::transport::server::{ConnectionCounters, ConnectionMonitor};
 use crate::transport::{server::Connected, Certificate};
 use hyper::client::connect::{Connected as HyperConnected, Connection};
 use std::io;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite};
 
 pub(in crate::transport) trait Io:
@@ -61,21 +66,74 @@ pub(in crate::transport) trait ConnectedIo: Io + Connected {}
 
 impl<T> ConnectedIo for T where T: Io + Connected {}
 
-pub(crate) struct ServerIo(Pin<Box<dyn ConnectedIo>>);
+pub(crate) struct ServerIo {
+    io: Pin<Box<dyn ConnectedIo>>,
+    tls: bool,
+    monitor: Option<ConnectionMonitor>,
+    timeout: Option<Duration>,
+}
 
 impl ServerIo {
     pub(in crate::transport) fn new<I: ConnectedIo>(io: I) -> Self {
-        ServerIo(Box::pin(io))
+        ServerIo {
+            io: Box::pin(io),
+            tls: false,
+            monitor: None,
+            timeout: None,
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    pub(in crate::transport) fn new_tls<I: ConnectedIo>(io: I) -> Self {
+        ServerIo {
+            io: Box::pin(io),
+            tls: true,
+            monitor: None,
+            timeout: None,
+        }
+    }
+
+    /// Report the lifecycle of this connection to `monitor`.
+    pub(in crate::transport) fn with_monitor(self, monitor: ConnectionMonitor) -> Self {
+        ServerIo {
+            monitor: Some(monitor),
+            ..self
+        }
+    }
+
+    /// Apply `timeout` to the requests handled on this connection.
+    pub(in crate::transport) fn with_timeout(self, timeout: Option<Duration>) -> Self {
+        ServerIo { timeout, ..self }
+    }
+
+    /// The timeout of the requests handled on this connection, if any.
+    pub(in crate::transport) fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The counters of this connection, if it is monitored.
+    pub(in crate::transport) fn counters(&self) -> Option<Arc<ConnectionCounters>> {
+        self.monitor.as_ref().map(ConnectionMonitor::counters)
+    }
+
+    /// Whether this connection went through the server's TLS handshake.
+    pub(in crate::transport) fn is_tls(&self) -> bool {
+        self.tls
     }
 }
 
 impl Connected for ServerIo {
     fn remote_addr(&self) -> Option<SocketAddr> {
-        (&*self.0).remote_addr()
+        (&*self.io).remote_addr()
     }
 
     fn peer_certs(&self) -> Option<Vec<Certificate>> {
-        (&self.0).peer_certs()
+        (&self.io).peer_certs()
+    }
+
+    #[cfg(unix)]
+    fn peer_cred(&self) -> Option<PeerCred> {
+        self.io.peer_cred()
     }
 }
 
@@ -85,7 +143,11 @@ impl AsyncRead for ServerIo {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.0).poll_read(cx, buf)
+        let res = futures_util::ready!(Pin::new(&mut self.io).poll_read(cx, buf));
+        if let Some(monitor) = &mut self.monitor {
+            monitor.on_read(&res);
+        }
+        Poll::Ready(res)
     }
 }
 
@@ -95,14 +157,26 @@ impl AsyncWrite for ServerIo {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.0).poll_write(cx, buf)
+        let res = futures_util::ready!(Pin::new(&mut self.io).poll_write(cx, buf));
+        if let Some(monitor) = &mut self.monitor {
+            monitor.on_write(&res);
+        }
+        Poll::Ready(res)
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.0).poll_flush(cx)
+        let res = futures_util::ready!(Pin::new(&mut self.io).poll_flush(cx));
+        if let (Err(e), Some(monitor)) = (&res, &mut self.monitor) {
+            monitor.on_error(e);
+        }
+        Poll::Ready(res)
     }
 
     fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.0).poll_shutdown(cx)
+        let res = futures_util::ready!(Pin::new(&mut self.io).poll_shutdown(cx));
+        if let (Err(e), Some(monitor)) = (&res, &mut self.monitor) {
+            monitor.on_error(e);
+        }
+        Poll::Ready(res)
     }
 }
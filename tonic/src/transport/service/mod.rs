@@ -2,10 +2,14 @@ mod add_origin;
 mod connection;
 mod connector;
 mod discover;
+mod events;
+#[cfg(feature = "h2-trace")]
+mod frames;
 mod io;
 mod layer;
 mod reconnect;
 mod router;
+mod stats;
 #[cfg(feature = "tls")]
 mod tls;
 
@@ -13,6 +17,8 @@ pub(crate) use self::add_origin::AddOrigin;
 pub(crate) use self::connection::Connection;
 pub(crate) use self::connector::connector;
 pub(crate) use self::discover::ServiceList;
+#[cfg(feature = "h2-trace")]
+pub(crate) use self::frames::FrameTraceIo;
 pub(crate) use self::io::ServerIo;
 pub(crate) use self::layer::ServiceBuilderExt;
 pub(crate) use self::router::{Or, Routes};
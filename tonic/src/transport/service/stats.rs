@@ -0,0 +1,175 @@
+use super::connection::{Request, Response};
+use crate::{
+    body::BoxBody,
+    stats::{ConnInfo, ConnStats, RpcTracker, SharedHandler, Side, StatsBody},
+    Code, Status,
+};
+use http::Uri;
+use hyper::client::connect::{Connected, Connection as HyperConnection};
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::SystemTime,
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tower_service::Service;
+
+/// Tracks the calls of a client, handing their tracker to the generated
+/// clients through the extensions of the responses, which handle their
+/// messages and trailers as they decode them.
+pub(crate) struct Stats<S> {
+    inner: S,
+    handler: SharedHandler,
+}
+
+impl<S> Stats<S> {
+    pub(crate) fn new(inner: S, handler: SharedHandler) -> Self {
+        Stats { inner, handler }
+    }
+}
+
+impl<S> Service<Request> for Stats<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Error: Into<crate::Error>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = crate::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let tracker = RpcTracker::begin(&self.handler, Side::Client, &request, None);
+        let body_tracker = Some(tracker.clone());
+        let request =
+            request.map(|body| BoxBody::map_from(StatsBody::new(body, body_tracker, false)));
+        let response = self.inner.call(request);
+
+        Box::pin(async move {
+            match response.await {
+                Ok(mut response) => {
+                    tracker.response_headers(response.headers());
+                    response.extensions_mut().insert(tracker);
+                    Ok(response)
+                }
+                Err(err) => {
+                    let err = err.into();
+                    let code = Status::find_in_error(&*err).map_or(Code::Unknown, |s| s.code());
+                    tracker.end(code);
+                    Err(err)
+                }
+            }
+        })
+    }
+}
+
+/// Handles the beginning and end of the connections made by a client.
+pub(crate) struct StatsConnector<C> {
+    inner: C,
+    handler: Option<SharedHandler>,
+}
+
+impl<C> StatsConnector<C> {
+    pub(crate) fn new(inner: C, handler: Option<SharedHandler>) -> Self {
+        StatsConnector { inner, handler }
+    }
+}
+
+impl<C> Service<Uri> for StatsConnector<C>
+where
+    C: Service<Uri>,
+    C::Future: Unpin + Send + 'static,
+{
+    type Response = StatsIo<C::Response>;
+    type Error = C::Error;
+    type Future = StatsConnect<C::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        StatsConnect {
+            inner: self.inner.call(uri),
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+pub(crate) struct StatsConnect<F> {
+    inner: F,
+    handler: Option<SharedHandler>,
+}
+
+impl<F, IO, E> Future for StatsConnect<F>
+where
+    F: Future<Output = Result<IO, E>> + Unpin,
+{
+    type Output = Result<StatsIo<IO>, E>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let io = futures_util::ready!(Pin::new(&mut self.inner).poll(cx))?;
+        let handler = self.handler.take();
+        if let Some(handler) = &handler {
+            let at = SystemTime::now();
+            handler.handle_conn(&ConnInfo::new(Side::Client, None), &ConnStats::Begin { at });
+        }
+        Poll::Ready(Ok(StatsIo { inner: io, handler }))
+    }
+}
+
+/// A connection of a client, its end handled once dropped.
+pub(crate) struct StatsIo<IO> {
+    inner: IO,
+    handler: Option<SharedHandler>,
+}
+
+impl<IO> Drop for StatsIo<IO> {
+    fn drop(&mut self) {
+        if let Some(handler) = &self.handler {
+            let at = SystemTime::now();
+            handler.handle_conn(&ConnInfo::new(Side::Client, None), &ConnStats::End { at });
+        }
+    }
+}
+
+impl<IO: HyperConnection> HyperConnection for StatsIo<IO> {
+    fn connected(&self) -> Connected {
+        self.inner.connected()
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for StatsIo<IO> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for StatsIo<IO> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}